@@ -1,18 +1,72 @@
 //! Hook daemon — persistent TCP relay that reuses HTTP connections.
 //!
-//! Listens on `127.0.0.1:{port+1}` (e.g. 15925).
+//! Listens on the server's host at `port+1` (e.g. `127.0.0.1:15925`).
 //! Protocol: client sends one JSON line, daemon forwards to agent-desk
 //! server using a persistent ureq Agent, then writes response line back.
+//! A `{"type":"ping"}` line is answered directly with relay counters
+//! (requests relayed, failures, in-flight, mean latency).
+//!
+//! Versioning: lines carry `"protocol": PROTOCOL`. A `{"type":"hello",
+//! "protocol":N}` line is answered with our protocol and capabilities, so
+//! the main app can tell an incompatible daemon (or one too old to answer
+//! `hello`) from a usable one and replace it. A hook line of another
+//! protocol is refused with `{"ok":false,"error":"protocol"}` instead of
+//! being relayed; lines without `protocol` come from hooks that predate
+//! versioning and share protocol 1's line format, so they are relayed.
+//!
+//! Failed relays go to the hook's error spool (see `report_error`).
 //!
 //! This avoids per-hook HTTP connection setup overhead.
 
 use std::io::{BufRead, BufReader, Write};
-use std::net::TcpListener;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Line protocol version. 1 was the unversioned original; bump on any
+/// incompatible change to request or response lines.
+pub const PROTOCOL: u64 = 2;
+
+/// What this daemon understands, reported by `hello`.
+const CAPABILITIES: &[&str] = &["ping", "hello", "relay", "fire_and_forget"];
+
+/// Relay counters, shared by all connection threads and reported by `ping`.
+#[derive(Default)]
+struct Stats {
+    relayed: AtomicU64,
+    failures: AtomicU64,
+    in_flight: AtomicU64,
+    /// Sum of relay latencies (µs) for computing the mean.
+    total_latency_us: AtomicU64,
+}
+
+impl Stats {
+    fn snapshot(&self, started: Instant) -> serde_json::Value {
+        let relayed = self.relayed.load(Ordering::Relaxed);
+        let total_us = self.total_latency_us.load(Ordering::Relaxed);
+        let mean_ms = if relayed > 0 { total_us as f64 / relayed as f64 / 1000.0 } else { 0.0 };
+        serde_json::json!({
+            "ok": true,
+            "pong": true,
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocol": PROTOCOL,
+            "pid": std::process::id(),
+            "uptime_secs": started.elapsed().as_secs(),
+            "relayed": relayed,
+            "failures": self.failures.load(Ordering::Relaxed),
+            "in_flight": self.in_flight.load(Ordering::Relaxed),
+            "mean_latency_ms": (mean_ms * 100.0).round() / 100.0,
+        })
+    }
+}
 
 /// Run the daemon. Blocks forever (until process killed).
-pub fn run(port: u16) {
-    let daemon_port = port + 1;
-    let addr = format!("127.0.0.1:{}", daemon_port);
+///
+/// Each connection is served on its own thread so a long-polling
+/// `pre_tool`/`permission_request` never stalls other hooks.
+pub fn run(server: &crate::Server) {
+    let addr = server.addr(server.port + 1);
 
     let listener = match TcpListener::bind(&addr) {
         Ok(l) => l,
@@ -24,111 +78,168 @@ pub fn run(port: u16) {
 
     eprintln!("agent-desk-hook daemon listening on {}", addr);
 
-    // Persistent HTTP agent — reuses TCP connections to the main server
+    // Persistent HTTP agent — reuses TCP connections to the main server.
+    // Clones share the same connection pool.
     let agent = ureq::Agent::config_builder()
         .timeout_global(Some(std::time::Duration::from_secs(660)))
         .build()
         .new_agent();
+    let stats = Arc::new(Stats::default());
+    let started = Instant::now();
 
     for stream in listener.incoming() {
-        let mut stream = match stream {
+        let stream = match stream {
             Ok(s) => s,
             Err(_) => continue,
         };
+        let agent = agent.clone();
+        let stats = stats.clone();
+        let server = server.clone();
+        std::thread::spawn(move || handle_connection(stream, &server, &agent, &stats, started));
+    }
+}
 
-        // Read one JSON line from client
-        let mut reader = BufReader::new(stream.try_clone().unwrap_or_else(|_| {
-            // If clone fails, just skip this connection
-            return stream.try_clone().unwrap();
-        }));
-        let mut line = String::new();
-        if reader.read_line(&mut line).is_err() || line.trim().is_empty() {
-            let _ = stream.write_all(b"{\"ok\":false,\"error\":\"empty\"}\n");
-            continue;
+fn handle_connection(mut stream: TcpStream, server: &crate::Server, agent: &ureq::Agent, stats: &Stats, started: Instant) {
+    // Read one JSON line from client
+    let mut reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(_) => return,
+    };
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() || line.trim().is_empty() {
+        let _ = stream.write_all(b"{\"ok\":false,\"error\":\"empty\"}\n");
+        return;
+    }
+
+    let mut data: serde_json::Value = match serde_json::from_str(line.trim()) {
+        Ok(v) => v,
+        Err(_) => {
+            let _ = stream.write_all(b"{\"ok\":false,\"error\":\"parse\"}\n");
+            return;
         }
+    };
 
-        let data: serde_json::Value = match serde_json::from_str(line.trim()) {
-            Ok(v) => v,
-            Err(_) => {
-                let _ = stream.write_all(b"{\"ok\":false,\"error\":\"parse\"}\n");
-                continue;
-            }
-        };
+    // Control messages are answered locally, never relayed
+    match data.get("type").and_then(|v| v.as_str()) {
+        Some("ping") => {
+            let _ = writeln!(stream, "{}", stats.snapshot(started));
+            return;
+        }
+        Some("hello") => {
+            let theirs = data.get("protocol").and_then(|v| v.as_u64());
+            let _ = writeln!(stream, "{}", serde_json::json!({
+                "ok": true,
+                "hello": true,
+                "protocol": PROTOCOL,
+                "compatible": theirs == Some(PROTOCOL),
+                "version": env!("CARGO_PKG_VERSION"),
+                "pid": std::process::id(),
+                "capabilities": CAPABILITIES,
+            }));
+            return;
+        }
+        _ => {}
+    }
+
+    // Not ours to relay: the payload may have changed shape
+    let protocol = data.as_object_mut().and_then(|o| o.remove("protocol"));
+    if let Some(p) = protocol.filter(|p| p.as_u64() != Some(PROTOCOL)) {
+        let _ = writeln!(stream, "{}", serde_json::json!({ "ok": false, "error": "protocol", "protocol": PROTOCOL, "got": p }));
+        return;
+    }
+
+    stats.in_flight.fetch_add(1, Ordering::Relaxed);
+    let t0 = Instant::now();
+    let (response, ok) = relay(&data, server, agent);
+    stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+    stats.relayed.fetch_add(1, Ordering::Relaxed);
+    stats.total_latency_us.fetch_add(t0.elapsed().as_micros() as u64, Ordering::Relaxed);
+    if !ok {
+        stats.failures.fetch_add(1, Ordering::Relaxed);
+    }
 
-        let event = data.get("event").and_then(|v| v.as_str()).unwrap_or("");
+    let _ = writeln!(stream, "{}", response);
+}
+
+/// Route and forward one hook payload. Returns (response line, relay succeeded).
+fn relay(data: &serde_json::Value, server: &crate::Server, agent: &ureq::Agent) -> (String, bool) {
+    let event = data.get("event").and_then(|v| v.as_str()).unwrap_or("");
 
-        // Route and forward
-        let response = match event {
-            "user_prompt" => {
-                let url = format!("http://127.0.0.1:{}/api/hook?event={}", port, event);
-                match agent.post(&url).header("Content-Type", "application/json").send_json(&data) {
-                    Ok(mut r) => r.body_mut().read_to_string().unwrap_or_default(),
-                    Err(_) => "{\"ok\":false}".to_string(),
+    match event {
+        "user_prompt" | "pre_tool_observe" => {
+            let url = server.url(&format!("/api/hook?event={}", crate::hook_event(event)));
+            match crate::post_json(agent, &url, data) {
+                Ok(mut r) => (r.body_mut().read_to_string().unwrap_or_default(), true),
+                Err(e) => {
+                    crate::report_error("daemon", &url, &e, data);
+                    ("{\"ok\":false}".to_string(), false)
                 }
             }
-            "pre_tool" => {
-                // PreToolUse: blocking long-poll to /api/pre-tool-check.
-                // Build structured payload from hook data.
-                let tool_name = data.get("tool_name")
-                    .or_else(|| data.get("toolName"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                let tool_input = data.get("tool_input")
-                    .or_else(|| data.get("input"))
-                    .cloned()
-                    .unwrap_or(serde_json::json!({}));
-                let session_id = data.get("session_id")
-                    .or_else(|| data.get("sessionId"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                let cwd = data.get("cwd")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-
-                let payload = serde_json::json!({
-                    "session_id": session_id,
-                    "cwd": cwd,
-                    "tool_name": tool_name,
-                    "tool_input": tool_input,
-                    "raw": &data,
-                });
-
-                let url = format!("http://127.0.0.1:{}/api/pre-tool-check", port);
-                match agent.post(&url).header("Content-Type", "application/json").send_json(&payload) {
-                    Ok(mut r) => r.body_mut().read_to_string().unwrap_or_default(),
-                    Err(_) => String::new(), // empty = no output, Claude Code proceeds normally
+        }
+        "pre_tool" => {
+            // PreToolUse: blocking long-poll to /api/pre-tool-check.
+            // Build structured payload from hook data.
+            let tool_name = data.get("tool_name")
+                .or_else(|| data.get("toolName"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let tool_input = data.get("tool_input")
+                .or_else(|| data.get("input"))
+                .cloned()
+                .unwrap_or(serde_json::json!({}));
+            let session_id = data.get("session_id")
+                .or_else(|| data.get("sessionId"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let cwd = data.get("cwd")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+
+            let payload = serde_json::json!({
+                "session_id": session_id,
+                "cwd": cwd,
+                "tool_name": tool_name,
+                "tool_input": tool_input,
+                "correlation_id": data.get("correlation_id").and_then(|v| v.as_str()).unwrap_or(""),
+                "raw": data,
+            });
+
+            let url = server.url("/api/pre-tool-check");
+            match crate::post_json(agent, &url, &payload) {
+                Ok(mut r) => (r.body_mut().read_to_string().unwrap_or_default(), true),
+                Err(e) => {
+                    crate::report_error("daemon", &url, &e, data);
+                    (String::new(), false) // empty = no output, Claude Code proceeds normally
                 }
             }
-            "permission_request" => {
-                let url = format!("http://127.0.0.1:{}/api/permission-request", port);
-                match agent.post(&url).header("Content-Type", "application/json").send_json(&data) {
-                    Ok(mut r) => r.body_mut().read_to_string().unwrap_or_default(),
-                    Err(_) => String::new(), // empty = Claude Code falls back
+        }
+        "permission_request" => {
+            let url = server.url("/api/permission-request");
+            match crate::post_json(agent, &url, data) {
+                Ok(mut r) => (r.body_mut().read_to_string().unwrap_or_default(), true),
+                Err(e) => {
+                    crate::report_error("daemon", &url, &e, data);
+                    (String::new(), false) // empty = Claude Code falls back
                 }
             }
-            _ => {
-                let url = format!("http://127.0.0.1:{}/api/signal", port);
-                match agent.post(&url).header("Content-Type", "application/json").send_json(&data) {
-                    Ok(mut r) => r.body_mut().read_to_string().unwrap_or_default(),
-                    Err(_) => "{\"ok\":false}".to_string(),
+        }
+        _ => {
+            let url = server.url("/api/signal");
+            match crate::post_json(agent, &url, data) {
+                Ok(mut r) => (r.body_mut().read_to_string().unwrap_or_default(), true),
+                Err(e) => {
+                    crate::report_error("daemon", &url, &e, data);
+                    ("{\"ok\":false}".to_string(), false)
                 }
             }
-        };
-
-        let _ = writeln!(stream, "{}", response);
+        }
     }
 }
 
 /// Try to send a hook payload via the daemon. Returns Some(response) on success.
-pub fn try_send(port: u16, data: &serde_json::Value) -> Option<String> {
-    let daemon_port = port + 1;
-    let addr = format!("127.0.0.1:{}", daemon_port);
-
+pub fn try_send(server: &crate::Server, data: &serde_json::Value) -> Option<String> {
     // Quick connect with short timeout
-    let stream = std::net::TcpStream::connect_timeout(
-        &addr.parse().ok()?,
-        std::time::Duration::from_millis(50),
-    ).ok()?;
+    let stream = server.connect(server.port + 1, std::time::Duration::from_millis(50))?;
 
     // Set read timeout (permission_request and pre_tool can take up to 660s)
     let event = data.get("event").and_then(|v| v.as_str()).unwrap_or("");
@@ -141,13 +252,32 @@ pub fn try_send(port: u16, data: &serde_json::Value) -> Option<String> {
     let _ = stream.set_write_timeout(Some(std::time::Duration::from_secs(2)));
 
     let mut stream_w = stream.try_clone().ok()?;
-    let json_line = serde_json::to_string(data).ok()?;
+    let mut data = data.clone();
+    if let Some(obj) = data.as_object_mut() {
+        obj.insert("protocol".into(), serde_json::json!(PROTOCOL));
+    }
+    let json_line = serde_json::to_string(&data).ok()?;
     writeln!(stream_w, "{}", json_line).ok()?;
 
+    // Fire-and-forget: the daemon relays it, nothing to print
+    if event == "pre_tool_observe" {
+        return Some(String::new());
+    }
+
     // Read response line
     let mut reader = BufReader::new(stream);
     let mut response = String::new();
     reader.read_line(&mut response).ok()?;
 
+    // A daemon of another protocol refused the line: go direct instead
+    let refused = serde_json::from_str::<serde_json::Value>(response.trim())
+        .is_ok_and(|r| r.get("error").and_then(|e| e.as_str()) == Some("protocol"));
+    if refused {
+        if std::env::var("AGENT_DESK_DEBUG").is_ok() {
+            eprintln!("agent-desk-hook: daemon speaks another protocol, sending directly");
+        }
+        return None;
+    }
+
     Some(response.trim().to_string())
 }
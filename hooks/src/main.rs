@@ -4,20 +4,67 @@
 //! adds the event type, and POSTs to the Agent Desk server.
 //!
 //! Usage:
-//!   agent-desk-hook --event stop [--port 15924]
-//!   agent-desk-hook --daemon [--port 15924]
+//!   agent-desk-hook --event stop [--host 127.0.0.1] [--port 15924] [--no-daemon]
+//!   agent-desk-hook --daemon [--host 127.0.0.1] [--port 15924]
 //!
 //! Handles all hook types:
-//!   Light (→ /api/hook):  user_prompt, pre_tool
+//!   Light (→ /api/hook):  user_prompt, pre_tool_observe (as pre_tool; fire-and-forget)
 //!   Heavy (→ /api/signal): stop, notification, session_start, session_end
 //!   Permission (→ /api/permission-request): permission_request (long-poll, stdout response)
 //!
 //! Daemon mode: listens on port+1, reuses HTTP connections for lower latency.
+//! `--no-daemon` (written by the app when `manager.use_hook_daemon` is off)
+//! posts straight to the server without probing for the daemon first.
+//!
+//! Failed requests (app not running, timeouts) are appended to
+//! `agent-desk-hook-errors.jsonl` in the temp dir, which the app picks up
+//! and shows as `hook_error` events. `AGENT_DESK_DEBUG` also prints them.
+//!
+//! `AGENT_DESK__MANAGER__PORT` sets the default port (`--port` still wins).
+//! The server is reached on `127.0.0.1` unless `--host`, `AGENT_DESK_HOST`
+//! or `AGENT_DESK__MANAGER__HOST` names another address: `::1` (or `[::1]`)
+//! where the server listens on IPv6, or a name such as `localhost`, whose
+//! addresses are tried in turn.
 
 mod daemon;
 
-use std::io::Read;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
 use std::process;
+use std::time::Duration;
+
+/// Where the Agent Desk server listens; the daemon listens on the same
+/// host, one port up.
+#[derive(Clone)]
+pub(crate) struct Server {
+    host: String,
+    pub(crate) port: u16,
+}
+
+impl Server {
+    /// `host:port` on the server's host, IPv6 literals in brackets.
+    pub(crate) fn addr(&self, port: u16) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, port)
+        } else {
+            format!("{}:{}", self.host, port)
+        }
+    }
+
+    /// URL of `path` on the server.
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr(self.port), path)
+    }
+
+    /// Connect to `port` on the server's host, trying each address the
+    /// host resolves to (`localhost` may be `::1` first, then `127.0.0.1`).
+    pub(crate) fn connect(&self, port: u16, timeout: Duration) -> Option<TcpStream> {
+        self.addr(port)
+            .to_socket_addrs()
+            .ok()?
+            .find_map(|a| TcpStream::connect_timeout(&a, timeout).ok())
+    }
+}
 
 /// Walk up the process tree from our PID to find the ancestor `claude.exe`.
 /// Process tree: claude.exe → bash/cmd → agent-desk-hook.exe
@@ -112,12 +159,28 @@ fn find_ancestor_claude_pid() -> Option<u32> {
 }
 
 fn main() {
+    // Taken first thing: hook processes can race each other to the server,
+    // so the app orders events by when they fired, not when they arrived.
+    let seq = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
     let args: Vec<String> = std::env::args().collect();
 
-    // Parse --event, --port, --daemon
+    // Parse --event, --host, --port, --daemon, --no-daemon. Host and port default to the
+    // same env overrides the main app honours, so both agree without flags.
     let mut event = String::new();
-    let mut port: u16 = 15924;
+    let mut host = std::env::var("AGENT_DESK_HOST")
+        .or_else(|_| std::env::var("AGENT_DESK__MANAGER__HOST"))
+        .ok()
+        .filter(|h| !h.trim().is_empty())
+        .unwrap_or_else(|| "127.0.0.1".to_string());
+    let mut port: u16 = std::env::var("AGENT_DESK__MANAGER__PORT")
+        .ok()
+        .and_then(|p| p.trim().parse().ok())
+        .unwrap_or(15924);
     let mut daemon_mode = false;
+    let mut use_daemon = true;
     let mut i = 1;
     while i < args.len() {
         match args[i].as_str() {
@@ -127,6 +190,12 @@ fn main() {
                     event = args[i].clone();
                 }
             }
+            "--host" => {
+                i += 1;
+                if i < args.len() {
+                    host = args[i].clone();
+                }
+            }
             "--port" | "-p" => {
                 i += 1;
                 if i < args.len() {
@@ -136,20 +205,27 @@ fn main() {
             "--daemon" => {
                 daemon_mode = true;
             }
+            "--no-daemon" => {
+                use_daemon = false;
+            }
             _ => {}
         }
         i += 1;
     }
 
+    // `[::1]` as written in a URL
+    let host = host.trim().trim_start_matches('[').trim_end_matches(']').to_string();
+    let server = Server { host, port };
+
     // Daemon mode: run persistent TCP relay
     if daemon_mode {
-        daemon::run(port);
+        daemon::run(&server);
         return;
     }
 
     if event.is_empty() {
-        eprintln!("Usage: agent-desk-hook --event <event_type> [--port <port>]");
-        eprintln!("       agent-desk-hook --daemon [--port <port>]");
+        eprintln!("Usage: agent-desk-hook --event <event_type> [--host <host>] [--port <port>] [--no-daemon]");
+        eprintln!("       agent-desk-hook --daemon [--host <host>] [--port <port>]");
         process::exit(1);
     }
 
@@ -163,18 +239,32 @@ fn main() {
         serde_json::from_str(&stdin_buf).unwrap_or_else(|_| serde_json::json!({}))
     };
 
+    // Names this invocation in the app's logs, event log, SSE messages and
+    // notifications, so a toast can be traced back to the hook that caused it
+    let correlation_id = format!("{:x}-{:x}", std::process::id(), seq);
+    if std::env::var("AGENT_DESK_DEBUG").is_ok() {
+        eprintln!("agent-desk-hook: {} [{}]", event, correlation_id);
+    }
+
     // Inject event type, our PID, and the ancestor claude.exe PID into payload.
     if let Some(obj) = data.as_object_mut() {
         obj.insert("event".into(), serde_json::json!(event));
         obj.insert("hook_pid".into(), serde_json::json!(std::process::id()));
+        obj.insert("hook_version".into(), serde_json::json!(env!("CARGO_PKG_VERSION")));
+        obj.insert("hook_seq".into(), serde_json::json!(seq));
+        obj.insert("correlation_id".into(), serde_json::json!(correlation_id));
         if let Some(ancestor_pid) = find_ancestor_claude_pid() {
             obj.insert("agent_pid".into(), serde_json::json!(ancestor_pid));
         }
+        // Observing only needs the tool name; inputs can be whole files
+        if event == "pre_tool_observe" {
+            obj.remove("tool_input");
+        }
     }
 
     // Validate event type
     match event.as_str() {
-        "user_prompt" | "pre_tool" | "permission_request"
+        "user_prompt" | "pre_tool" | "pre_tool_observe" | "permission_request"
         | "stop" | "notification" | "session_start" | "session_end" => {}
         other => {
             if std::env::var("AGENT_DESK_DEBUG").is_ok() {
@@ -184,7 +274,7 @@ fn main() {
     }
 
     // Try daemon relay first (fast path — reuses HTTP connections)
-    if let Some(response) = daemon::try_send(port, &data) {
+    if let Some(response) = use_daemon.then(|| daemon::try_send(&server, &data)).flatten() {
         if !response.is_empty() && (event == "permission_request" || event == "pre_tool") {
             println!("{}", response);
         }
@@ -192,33 +282,100 @@ fn main() {
     }
 
     // Fallback: direct HTTP (cold path — new connection per request)
-    send_direct(port, &event, &data);
+    send_direct(&server, &event, &data);
+}
+
+/// `/api/hook` event for a light hook: the observe-mode PreToolUse hook
+/// reports as `pre_tool`.
+pub(crate) fn hook_event(event: &str) -> &str {
+    match event {
+        "pre_tool_observe" => "pre_tool",
+        other => other,
+    }
+}
+
+/// Spool of failed requests in the temp dir, read by the app.
+const ERROR_SPOOL: &str = "agent-desk-hook-errors.jsonl";
+
+/// Past this size the spool takes no more: the app isn't reading it.
+const ERROR_SPOOL_MAX_BYTES: u64 = 1024 * 1024;
+
+/// A request to `url` for hook payload `data` failed: append it to the
+/// error spool. `source` is `hook` or `daemon`.
+pub(crate) fn report_error(source: &str, url: &str, error: &dyn std::fmt::Display, data: &serde_json::Value) {
+    if std::env::var("AGENT_DESK_DEBUG").is_ok() {
+        eprintln!("agent-desk-hook: {} -> {}", url, error);
+    }
+    let path = std::env::temp_dir().join(ERROR_SPOOL);
+    if std::fs::metadata(&path).is_ok_and(|m| m.len() >= ERROR_SPOOL_MAX_BYTES) {
+        return;
+    }
+    let field = |key: &str| data.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+    let line = serde_json::json!({
+        "ts": ts,
+        "source": source,
+        "event": field("event"),
+        "url": url,
+        "error": error.to_string(),
+        "session_id": field("session_id"),
+        "cwd": field("cwd"),
+        "correlation_id": field("correlation_id"),
+        "version": env!("CARGO_PKG_VERSION"),
+    });
+    if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+/// Bodies this big go out gzipped: a Stop payload carries the whole last
+/// assistant message, which can run to tens of KB.
+const GZIP_MIN_BYTES: usize = 8 * 1024;
+
+/// POST `data` as JSON, gzipped (`Content-Encoding: gzip`) once it reaches
+/// `GZIP_MIN_BYTES`. Shared by the direct path and the daemon relay.
+pub(crate) fn post_json(
+    agent: &ureq::Agent,
+    url: &str,
+    data: &serde_json::Value,
+) -> Result<ureq::http::Response<ureq::Body>, ureq::Error> {
+    let body = serde_json::to_vec(data).unwrap_or_default();
+    let req = agent.post(url).header("Content-Type", "application/json");
+    if body.len() < GZIP_MIN_BYTES {
+        return req.send(&body[..]);
+    }
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    match gz.write_all(&body).and_then(|_| gz.finish()) {
+        Ok(packed) => req.header("Content-Encoding", "gzip").send(&packed[..]),
+        Err(_) => req.send(&body[..]),
+    }
 }
 
 /// Direct HTTP send (fallback when daemon is not running).
-fn send_direct(port: u16, event: &str, data: &serde_json::Value) {
+fn send_direct(server: &Server, event: &str, data: &serde_json::Value) {
     match event {
-        "user_prompt" => {
-            let url = format!("http://127.0.0.1:{}/api/hook?event={}", port, event);
+        "user_prompt" | "pre_tool_observe" => {
+            let url = server.url(&format!("/api/hook?event={}", hook_event(event)));
+            // Observing runs before every tool: never hold it up for long
+            let timeout = if event == "pre_tool_observe" { 1 } else { 3 };
             let agent = ureq::Agent::config_builder()
-                .timeout_global(Some(std::time::Duration::from_secs(3)))
+                .timeout_global(Some(std::time::Duration::from_secs(timeout)))
                 .build()
                 .new_agent();
 
-            let result = agent.post(&url)
-                .header("Content-Type", "application/json")
-                .send_json(data);
+            let result = post_json(&agent, &url, data);
 
             if let Err(e) = result {
-                if std::env::var("AGENT_DESK_DEBUG").is_ok() {
-                    eprintln!("agent-desk-hook: {} -> {}", url, e);
-                }
+                report_error("hook", &url, &e, data);
             }
         }
         "pre_tool" => {
             // PreToolUse: blocking long-poll to /api/pre-tool-check.
             // Response is printed to stdout for Claude Code to read.
-            let url = format!("http://127.0.0.1:{}/api/pre-tool-check", port);
+            let url = server.url("/api/pre-tool-check");
             let agent = ureq::Agent::config_builder()
                 .timeout_global(Some(std::time::Duration::from_secs(660)))
                 .build()
@@ -246,12 +403,11 @@ fn send_direct(port: u16, event: &str, data: &serde_json::Value) {
                 "cwd": cwd,
                 "tool_name": tool_name,
                 "tool_input": tool_input,
+                "correlation_id": data.get("correlation_id").and_then(|v| v.as_str()).unwrap_or(""),
                 "raw": data,
             });
 
-            let result = agent.post(&url)
-                .header("Content-Type", "application/json")
-                .send_json(&payload);
+            let result = post_json(&agent, &url, &payload);
 
             match result {
                 Ok(mut resp) => {
@@ -259,23 +415,17 @@ fn send_direct(port: u16, event: &str, data: &serde_json::Value) {
                         println!("{}", body);
                     }
                 }
-                Err(e) => {
-                    if std::env::var("AGENT_DESK_DEBUG").is_ok() {
-                        eprintln!("agent-desk-hook: {} -> {}", url, e);
-                    }
-                }
+                Err(e) => report_error("hook", &url, &e, data),
             }
         }
         "permission_request" => {
-            let url = format!("http://127.0.0.1:{}/api/permission-request", port);
+            let url = server.url("/api/permission-request");
             let agent = ureq::Agent::config_builder()
                 .timeout_global(Some(std::time::Duration::from_secs(660)))
                 .build()
                 .new_agent();
 
-            let result = agent.post(&url)
-                .header("Content-Type", "application/json")
-                .send_json(data);
+            let result = post_json(&agent, &url, data);
 
             match result {
                 Ok(mut resp) => {
@@ -283,28 +433,20 @@ fn send_direct(port: u16, event: &str, data: &serde_json::Value) {
                         println!("{}", body);
                     }
                 }
-                Err(e) => {
-                    if std::env::var("AGENT_DESK_DEBUG").is_ok() {
-                        eprintln!("agent-desk-hook: {} -> {}", url, e);
-                    }
-                }
+                Err(e) => report_error("hook", &url, &e, data),
             }
         }
         _ => {
-            let url = format!("http://127.0.0.1:{}/api/signal", port);
+            let url = server.url("/api/signal");
             let agent = ureq::Agent::config_builder()
                 .timeout_global(Some(std::time::Duration::from_secs(3)))
                 .build()
                 .new_agent();
 
-            let result = agent.post(&url)
-                .header("Content-Type", "application/json")
-                .send_json(data);
+            let result = post_json(&agent, &url, data);
 
             if let Err(e) = result {
-                if std::env::var("AGENT_DESK_DEBUG").is_ok() {
-                    eprintln!("agent-desk-hook: {} -> {}", url, e);
-                }
+                report_error("hook", &url, &e, data);
             }
         }
     }
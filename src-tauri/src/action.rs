@@ -0,0 +1,260 @@
+//! Actions on sessions and notifications, one code path for every
+//! frontend.
+//!
+//! `POST /api/action` takes an `Action` as JSON, tagged by `action`:
+//! `{"action": "focus_session", "session_id": "…", "source": "cli"}`. The
+//! older endpoints (`/api/focus`, `/api/session/{id}/reply`), deep links,
+//! the tray's quick replies and the decision links in remote messages
+//! build the same enum and go through `run`, so each action is checked,
+//! logged and announced the same way: an `info` log line naming the
+//! source, and an `action` SSE message with the outcome. Access control is
+//! the API's own (`lan_auth`); decision links are signed per request.
+
+use std::sync::atomic::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::events::ClearFilter;
+use crate::island_state::IslandView;
+use crate::protocol::{PermissionDecisionKind, PermissionRespondPayload};
+use crate::server::AppState;
+use crate::status::scan_and_merge;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// Bring the session's terminal to the front: by session id (full or
+    /// short), else by `cwd` and/or agent `pid`. `peek` raises and flashes
+    /// it without taking keyboard focus.
+    FocusSession {
+        #[serde(default)]
+        session_id: String,
+        #[serde(default)]
+        cwd: String,
+        #[serde(default)]
+        pid: Option<u32>,
+        #[serde(default)]
+        peek: bool,
+    },
+    /// Allow a pending permission request, for the rest of the session
+    /// with `session`.
+    Approve {
+        id: String,
+        #[serde(default)]
+        session: bool,
+    },
+    /// Deny a pending permission request, with an optional explanation for
+    /// the agent.
+    Deny {
+        id: String,
+        #[serde(default)]
+        message: Option<String>,
+    },
+    /// Send a configured quick reply (`general.quick_replies`).
+    SendReply { session_id: String, reply_id: String },
+    /// Pause toasts, sounds and island pop-ups for `minutes`.
+    Snooze { minutes: u64 },
+    /// Clear a session's events from the list.
+    ClearSession { session_id: String },
+    /// Show the island expanded on a session's chat view.
+    OpenChat { session_id: String },
+    /// Write a session's transcript as markdown (see chat_export.rs); the
+    /// reply carries its `path`.
+    ExportChat { session_id: String },
+    /// Pause or resume toasts, sounds and island pop-ups; without `paused`
+    /// flip the current state.
+    ToggleDnd {
+        #[serde(default)]
+        paused: Option<bool>,
+    },
+    ShowIsland,
+    OpenDashboard,
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FocusSession { .. } => "focus_session",
+            Self::Approve { .. } => "approve",
+            Self::Deny { .. } => "deny",
+            Self::SendReply { .. } => "send_reply",
+            Self::Snooze { .. } => "snooze",
+            Self::ClearSession { .. } => "clear_session",
+            Self::OpenChat { .. } => "open_chat",
+            Self::ExportChat { .. } => "export_chat",
+            Self::ToggleDnd { .. } => "toggle_dnd",
+            Self::ShowIsland => "show_island",
+            Self::OpenDashboard => "open_dashboard",
+        }
+    }
+}
+
+/// Carry out `action` on behalf of `source` (`island`, `tray`,
+/// `deep_link`, `remote`, …). Blocking: focusing a terminal and sending a
+/// reply wait on other processes. `Ok` and `Err` are both API reply
+/// bodies, `{"ok": true, …}` or `{"ok": false, "error": …}`.
+pub fn run(state: &AppState, action: &Action, source: &str) -> Result<Value, Value> {
+    let result = dispatch(state, action);
+    let source = if source.is_empty() { "api" } else { source };
+    let error = result.as_ref().err().and_then(|e| e.get("error")).and_then(|v| v.as_str());
+    match error {
+        None => tracing::info!("Action {} from {}: {:?}", action.name(), source, action),
+        Some(e) => tracing::info!("Action {} from {} failed: {} ({:?})", action.name(), source, e, action),
+    }
+    state.sse.broadcast("action", json!({
+        "action": action.name(),
+        "source": source,
+        "ok": result.is_ok(),
+        "error": error,
+    }));
+    result
+}
+
+fn dispatch(state: &AppState, action: &Action) -> Result<Value, Value> {
+    match action {
+        Action::FocusSession { session_id, cwd, pid, peek } => focus_session(state, session_id, cwd, *pid, *peek),
+        Action::Approve { id, session } => {
+            let decision = if *session { PermissionDecisionKind::AllowSession } else { PermissionDecisionKind::Allow };
+            answer_permission(state, id, decision, None)
+        }
+        Action::Deny { id, message } => answer_permission(state, id, PermissionDecisionKind::Deny, message.clone()),
+        Action::SendReply { session_id, reply_id } => {
+            crate::server::send_quick_reply(state, session_id, reply_id).map(|()| json!({ "ok": true }))
+        }
+        Action::Snooze { minutes } => {
+            if *minutes == 0 {
+                return Err(json!({ "ok": false, "error": "minutes must be at least 1" }));
+            }
+            let until = now_secs() + *minutes as f64 * 60.0;
+            *mutex_lock!(state.snoozed_until) = Some(until);
+            set_paused(state, true);
+            Ok(json!({ "ok": true, "paused": true, "until": until }))
+        }
+        Action::ClearSession { session_id } => {
+            let sid = state.session_tracker.resolve_short_id(session_id).unwrap_or_else(|| session_id.clone());
+            let filter = ClearFilter { session_id: Some(sid), ..Default::default() };
+            let cleared = state.event_store.clear_matching(&filter);
+            state.sse.broadcast("clear", json!({ "session_id": filter.session_id }));
+            let _ = state.notify_tray.send(());
+            Ok(json!({ "ok": true, "cleared": cleared }))
+        }
+        Action::OpenChat { session_id } => {
+            let sid = state.session_tracker.resolve_short_id(session_id).unwrap_or_else(|| session_id.clone());
+            if state.session_tracker.get(&sid).is_none() {
+                return Err(json!({ "ok": false, "error": format!("no session {}", session_id) }));
+            }
+            let _ = state.island_state.set_view(IslandView::Chat, Some(sid));
+            show_island(state)
+        }
+        Action::ExportChat { session_id } => crate::server::export_chat(state, session_id)
+            .map(|path| json!({ "ok": true, "path": path.to_string_lossy() }))
+            .map_err(|e| json!({ "ok": false, "error": e })),
+        Action::ToggleDnd { paused } => {
+            let paused = paused.unwrap_or_else(|| !state.notifications_paused.load(Ordering::Relaxed));
+            *mutex_lock!(state.snoozed_until) = None;
+            set_paused(state, paused);
+            Ok(json!({ "ok": true, "paused": paused }))
+        }
+        Action::ShowIsland => show_island(state),
+        Action::OpenDashboard => {
+            let Some(handle) = state.app_handle.get() else {
+                return Err(json!({ "ok": false, "error": "no desktop to open the dashboard on" }));
+            };
+            crate::dashboard::open(handle, state.config.manager.port);
+            Ok(json!({ "ok": true }))
+        }
+    }
+}
+
+/// Resume notifications once a snooze is over; called from the presence
+/// tick.
+pub fn end_snooze(state: &AppState) {
+    {
+        let mut until = mutex_lock!(state.snoozed_until);
+        if until.is_none_or(|t| t > now_secs()) {
+            return;
+        }
+        *until = None;
+    }
+    set_paused(state, false);
+    state.sse.broadcast("action", json!({ "action": "toggle_dnd", "source": "snooze", "ok": true, "error": null }));
+}
+
+fn focus_session(state: &AppState, session_id: &str, cwd: &str, pid: Option<u32>, peek: bool) -> Result<Value, Value> {
+    let (mut cwd, mut pid, mut sid) = (cwd.to_string(), pid, String::new());
+    if !session_id.is_empty() {
+        sid = state.session_tracker.resolve_short_id(session_id).unwrap_or_else(|| session_id.to_string());
+        let merged = scan_and_merge(state);
+        let Some(proc) = merged.iter().find(|p| p.get("session_id").and_then(|v| v.as_str()) == Some(sid.as_str())) else {
+            return Err(json!({ "ok": false, "error": format!("no running session {}", session_id) }));
+        };
+        cwd = proc.get("cwd").and_then(|v| v.as_str()).unwrap_or("").to_string();
+        pid = proc.get("pid").and_then(|v| v.as_u64()).map(|p| p as u32);
+    } else if cwd.is_empty() && pid.is_none() {
+        return Err(json!({ "ok": false, "error": "no session_id, cwd or pid" }));
+    } else if pid.is_none() {
+        // The agent running in that directory
+        let cwd_norm = cwd.replace('/', "\\").to_lowercase();
+        pid = scan_and_merge(state).iter().find_map(|proc| {
+            let pcwd = proc.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+            if pcwd.replace('/', "\\").to_lowercase() == cwd_norm {
+                proc.get("pid").and_then(|v| v.as_u64()).map(|p| p as u32)
+            } else {
+                None
+            }
+        });
+    }
+    let cached = state.registry.get_cached();
+    if peek {
+        return if crate::focus::find_and_peek_terminal_with_pid(&sid, &cwd, &cached, pid) {
+            Ok(json!({ "ok": true }))
+        } else {
+            Err(json!({ "ok": false, "error": "no terminal found" }))
+        };
+    }
+    let focus = crate::focus::find_and_focus_terminal_with_pid(&sid, &cwd, &cached, pid);
+    if !focus.focused {
+        Err(json!({ "ok": false, "error": "no terminal found" }))
+    } else if focus.tab_verified == Some(false) {
+        Err(json!({ "ok": false, "error": "terminal window focused, but another tab is showing" }))
+    } else {
+        Ok(json!({ "ok": true, "tab_verified": focus.tab_verified }))
+    }
+}
+
+fn answer_permission(state: &AppState, id: &str, decision: PermissionDecisionKind, message: Option<String>) -> Result<Value, Value> {
+    crate::server::respond_permission(state, PermissionRespondPayload {
+        id: id.to_string(),
+        decision,
+        selected_suggestions: None,
+        allowed_paths: None,
+        message,
+    })
+    .map(|()| json!({ "ok": true }))
+    .map_err(crate::server::late_answer)
+}
+
+fn show_island(state: &AppState) -> Result<Value, Value> {
+    use tauri::Manager;
+    let Some(window) = state.app_handle.get().and_then(|h| h.get_webview_window("island")) else {
+        return Err(json!({ "ok": false, "error": "no island window" }));
+    };
+    let _ = window.show();
+    crate::island::expand(state.config.island.panel_width, state.config.island.panel_height);
+    state.island_state.expand();
+    crate::server::broadcast_island_state(state);
+    Ok(json!({ "ok": true }))
+}
+
+fn set_paused(state: &AppState, paused: bool) {
+    state.notifications_paused.store(paused, Ordering::Relaxed);
+    tracing::info!("Notifications {}", if paused { "paused" } else { "resumed" });
+    // The tray refresh rebuilds the jump list with the other task
+    let _ = state.notify_tray.send(());
+}
+
+fn now_secs() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()
+}
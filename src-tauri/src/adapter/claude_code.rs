@@ -1,51 +1,33 @@
-// Claude Code adapter — hook event parsing.
+// Claude Code adapter — hook payload normalization.
 // Process scanning is handled by ProcessScanner in process/scanner.rs.
 
 use serde_json::Value;
 
-/// Map Claude Code hook events to unified event names.
-pub fn map_hook_event(event_name: &str, data: &Value) -> (String, String) {
-    let sid = data.get("session_id").and_then(|v| v.as_str()).unwrap_or("unknown");
-    let short_sid = &sid[..sid.len().min(8)];
-    let cwd = data.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+use super::{parse_event, str_field, u32_field, HookNormalizer, NormalizedSignal};
 
-    let (unified_event, message) = match event_name {
-        "user_prompt" | "pre_tool" => {
-            ("active".to_string(), format!("[{}] {}", event_name, short_sid))
-        }
-        "stop" => {
-            let last_msg = data.get("last_assistant_message")
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-            let truncated = if last_msg.len() > 300 { &last_msg[..300] } else { last_msg };
-            ("done".to_string(), format!("[Done] {}\n{}\n{}", short_sid, cwd, truncated))
-        }
-        "notification" => {
-            let ntype = data.get("notification_type").and_then(|v| v.as_str()).unwrap_or("");
-            let msg = data.get("message").and_then(|v| v.as_str()).unwrap_or("");
-            match ntype {
-                "permission_prompt" => {
-                    ("waiting".to_string(), format!("[Confirm] {}\n{}", short_sid, msg))
-                }
-                "idle_prompt" => {
-                    ("done".to_string(), format!("[Idle] {} waiting for input", short_sid))
-                }
-                _ => {
-                    ("done".to_string(), format!("[Notice] {}\n{}", short_sid, msg))
-                }
-            }
-        }
-        "session_start" => {
-            let model = data.get("model").and_then(|v| v.as_str()).unwrap_or("unknown");
-            ("session_start".to_string(), format!("[Start] {} | {} | {}", short_sid, model, cwd))
-        }
-        "session_end" => {
-            ("session_end".to_string(), format!("[End] {}", short_sid))
-        }
-        _ => {
-            (event_name.to_string(), format!("[{}] {}", event_name, short_sid))
-        }
-    };
+/// The hook binary already sends Claude Code hooks in the unified shape;
+/// this only reads the fields leniently.
+pub struct ClaudeCode;
 
-    (unified_event, message)
+impl HookNormalizer for ClaudeCode {
+    fn normalize(&self, event: &str, raw: &Value) -> NormalizedSignal {
+        NormalizedSignal {
+            event: parse_event(event),
+            session_id: str_field(raw, &["session_id"]),
+            cwd: str_field(raw, &["cwd"]),
+            notification_type: str_field(raw, &["notification_type"]),
+            message: str_field(raw, &["message"]),
+            last_assistant_message: str_field(raw, &["last_assistant_message"]),
+            model: str_field(raw, &["model"]),
+            hook_pid: u32_field(raw, "hook_pid"),
+            hook_version: raw.get("hook_version").and_then(|v| v.as_str()).map(str::to_string),
+            hook_seq: raw.get("hook_seq").and_then(|v| v.as_u64()),
+            agent_pid: u32_field(raw, "agent_pid"),
+            parent_session_id: raw.get("parent_session_id").and_then(|v| v.as_str()).map(str::to_string),
+            source: str_field(raw, &["source"]),
+            transcript_path: str_field(raw, &["transcript_path"]),
+            agent: "claude_code".to_string(),
+            correlation_id: str_field(raw, &["correlation_id"]),
+        }
+    }
 }
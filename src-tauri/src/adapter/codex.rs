@@ -1,28 +1,43 @@
-// Codex CLI adapter — hook event parsing.
+// Codex CLI adapter — hook payload normalization.
 // Process scanning is handled by ProcessScanner in process/scanner.rs.
 
 use serde_json::Value;
 
-/// Map Codex hook events to unified event names.
-pub fn map_hook_event(event_name: &str, data: &Value) -> (String, String) {
-    let sid = data.get("session_id").and_then(|v| v.as_str()).unwrap_or("unknown");
-    let short_sid = &sid[..sid.len().min(8)];
-    let cwd = data.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+use crate::protocol::HookEvent;
+use super::{parse_event, str_field, u32_field, HookNormalizer, NormalizedSignal};
 
-    let (unified_event, message) = match event_name {
-        "after_agent" => {
-            let output = data.get("output").and_then(|v| v.as_str()).unwrap_or("");
-            let truncated = if output.len() > 300 { &output[..300] } else { output };
-            ("done".to_string(), format!("[Codex Done] {}\n{}\n{}", short_sid, cwd, truncated))
-        }
-        "after_tool_use" => {
-            let tool = data.get("tool_name").and_then(|v| v.as_str()).unwrap_or("tool");
-            ("active".to_string(), format!("[Codex Tool] {} | {}", short_sid, tool))
-        }
-        _ => {
-            (event_name.to_string(), format!("[Codex {}] {}", event_name, short_sid))
-        }
-    };
+/// Codex's `notify` program gets kebab-case JSON (`agent-turn-complete`,
+/// `thread-id`, `last-assistant-message`); older wrappers sent
+/// `after_agent` / `after_tool_use` with `output`.
+pub struct Codex;
 
-    (unified_event, message)
+impl HookNormalizer for Codex {
+    fn normalize(&self, event: &str, raw: &Value) -> NormalizedSignal {
+        let event = match event {
+            "agent-turn-complete" | "after_agent" => HookEvent::Stop,
+            "after_tool_use" => HookEvent::PreTool,
+            other => parse_event(other),
+        };
+        NormalizedSignal {
+            event,
+            session_id: str_field(raw, &["session_id", "thread-id", "thread_id"]),
+            cwd: str_field(raw, &["cwd"]),
+            notification_type: str_field(raw, &["notification_type"]),
+            message: str_field(raw, &["message"]),
+            last_assistant_message: str_field(raw, &["last-assistant-message", "last_assistant_message", "output"]),
+            model: match str_field(raw, &["model"]) {
+                m if m.is_empty() => "codex".to_string(),
+                m => m,
+            },
+            hook_pid: u32_field(raw, "hook_pid"),
+            hook_version: raw.get("hook_version").and_then(|v| v.as_str()).map(str::to_string),
+            hook_seq: raw.get("hook_seq").and_then(|v| v.as_u64()),
+            agent_pid: u32_field(raw, "agent_pid"),
+            parent_session_id: None,
+            source: String::new(),
+            transcript_path: String::new(),
+            agent: "codex".to_string(),
+            correlation_id: str_field(raw, &["correlation_id"]),
+        }
+    }
 }
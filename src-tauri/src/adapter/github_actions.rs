@@ -0,0 +1,151 @@
+// GitHub Actions adapter — polls workflow runs of cloud agents.
+// Unlike the CLI adapters there is no process to scan: runs are fetched
+// from the REST API and turned into session updates by the server.
+
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::config::GithubConfig;
+
+const API: &str = "https://api.github.com";
+
+#[derive(Deserialize)]
+struct RunsPage {
+    #[serde(default)]
+    workflow_runs: Vec<Run>,
+}
+
+#[derive(Deserialize)]
+struct Actor {
+    login: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Run {
+    pub id: u64,
+    /// Workflow name.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub display_title: String,
+    /// queued | in_progress | completed | ...
+    pub status: String,
+    #[serde(default)]
+    pub conclusion: Option<String>,
+    #[serde(default)]
+    pub html_url: String,
+    #[serde(default, deserialize_with = "actor_login")]
+    pub actor: String,
+}
+
+fn actor_login<'de, D: serde::Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    Ok(Option::<Actor>::deserialize(d)?.map(|a| a.login).unwrap_or_default())
+}
+
+impl Run {
+    fn is_agent(&self, patterns: &[String]) -> bool {
+        let haystack = format!("{} {} {}", self.name.as_deref().unwrap_or(""), self.display_title, self.actor)
+            .to_lowercase();
+        patterns.iter().any(|p| !p.is_empty() && haystack.contains(&p.to_lowercase()))
+    }
+}
+
+/// A change the server applies to the session tracker.
+pub enum RunUpdate {
+    /// Queued or in progress (sent on every poll while it lasts;
+    /// `new` on the first one).
+    Running { repo: String, run: Run, new: bool },
+    /// Left the in-progress list and is now completed.
+    Finished { repo: String, run: Run },
+}
+
+/// Session id for a run, e.g. `gha-octo-app-123456`.
+pub fn session_id(repo: &str, run_id: u64) -> String {
+    format!("gha-{}-{}", repo.replace('/', "-"), run_id)
+}
+
+/// Remembers which runs were in progress so completions can be reported.
+pub struct GithubPoller {
+    /// run id → repo
+    tracked: HashMap<u64, String>,
+}
+
+impl GithubPoller {
+    pub fn new() -> Self {
+        Self { tracked: HashMap::new() }
+    }
+
+    /// Fetch active agent runs of every configured repo. A repo that fails
+    /// to load keeps its tracked runs (missing ≠ finished).
+    pub async fn poll(&mut self, client: &reqwest::Client, cfg: &GithubConfig) -> Vec<RunUpdate> {
+        let mut updates = Vec::new();
+        let mut seen = HashSet::new();
+        let mut failed = HashSet::new();
+
+        for repo in cfg.repos.iter().map(|r| r.trim()).filter(|r| r.contains('/')) {
+            let mut runs = Vec::new();
+            for status in ["queued", "in_progress"] {
+                let url = format!("{}/repos/{}/actions/runs?status={}&per_page=50", API, repo, status);
+                match get_json::<RunsPage>(client, &cfg.token, &url).await {
+                    Ok(page) => runs.extend(page.workflow_runs),
+                    Err(e) => {
+                        tracing::warn!("GitHub runs for {}: {}", repo, e);
+                        failed.insert(repo.to_string());
+                        break;
+                    }
+                }
+            }
+            for run in runs.into_iter().filter(|r| r.is_agent(&cfg.agent_match)) {
+                seen.insert(run.id);
+                let new = self.tracked.insert(run.id, repo.to_string()).is_none();
+                updates.push(RunUpdate::Running { repo: repo.to_string(), run, new });
+            }
+        }
+
+        let gone: Vec<(u64, String)> = self
+            .tracked
+            .iter()
+            .filter(|(id, repo)| !seen.contains(*id) && !failed.contains(*repo))
+            .map(|(id, repo)| (*id, repo.clone()))
+            .collect();
+        for (id, repo) in gone {
+            let url = format!("{}/repos/{}/actions/runs/{}", API, repo, id);
+            match get_json::<Run>(client, &cfg.token, &url).await {
+                Ok(run) if run.status == "completed" => {
+                    self.tracked.remove(&id);
+                    updates.push(RunUpdate::Finished { repo, run });
+                }
+                Ok(_) => {} // e.g. waiting for approval — check again next poll
+                Err(e) => {
+                    tracing::warn!("GitHub run {}/{}: {}", repo, id, e);
+                    // Deleted runs 404 forever; stop tracking them
+                    if e.contains("404") {
+                        self.tracked.remove(&id);
+                    }
+                }
+            }
+        }
+        updates
+    }
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    token: &str,
+    url: &str,
+) -> Result<T, String> {
+    let mut req = client
+        .get(url)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", concat!("agent-desk/", env!("CARGO_PKG_VERSION")))
+        .timeout(std::time::Duration::from_secs(15));
+    if !token.is_empty() {
+        req = req.bearer_auth(token);
+    }
+    let resp = req.send().await.map_err(|e| e.to_string())?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status.as_u16()));
+    }
+    resp.json::<T>().await.map_err(|e| e.to_string())
+}
@@ -1,18 +1,69 @@
 mod claude_code;
 mod codex;
+pub mod github_actions;
 
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex, RwLock};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
 use crate::process::{ProcessInfo, ProcessScanner};
+use crate::protocol::{HookEvent, SignalPayload};
+
+/// A hook payload mapped onto the fields `/api/signal` works with.
+pub type NormalizedSignal = SignalPayload;
+
+/// Maps one agent's raw hook payloads onto `NormalizedSignal`, so agents
+/// with other payload shapes need no field fallbacks in server.rs.
+pub trait HookNormalizer: Send + Sync {
+    /// `event`: the payload's `event` field, in the agent's own naming.
+    fn normalize(&self, event: &str, raw: &Value) -> NormalizedSignal;
+}
+
+/// Unified event for a snake_case name; unrecognized names become `Unknown`.
+pub(crate) fn parse_event(event: &str) -> HookEvent {
+    serde_json::from_value(Value::String(event.to_string())).unwrap_or(HookEvent::Unknown)
+}
+
+/// First non-empty string among `keys` (empty if none).
+pub(crate) fn str_field(raw: &Value, keys: &[&str]) -> String {
+    keys.iter()
+        .find_map(|k| raw.get(*k).and_then(|v| v.as_str()).filter(|s| !s.is_empty()))
+        .unwrap_or_default()
+        .to_string()
+}
+
+pub(crate) fn u32_field(raw: &Value, key: &str) -> Option<u32> {
+    raw.get(key).and_then(|v| v.as_u64()).and_then(|v| u32::try_from(v).ok())
+}
+
+/// What an agent supports, so the UI can disable actions up front instead
+/// of failing at runtime.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Capabilities {
+    /// Conversation history can be read (`/api/chat`).
+    pub supports_chat: bool,
+    /// Tool calls can be approved/denied through hooks.
+    pub supports_permissions: bool,
+    /// Messages can be typed into its terminal (`/api/chat/send`).
+    pub supports_send: bool,
+    /// Transcript format the chat reader parses, if any.
+    pub transcript_format: Option<&'static str>,
+}
 
 pub struct AdapterEntry {
     pub name: String,
     pub scanner: ProcessScanner,
+    pub capabilities: Capabilities,
 }
 
 pub struct AdapterRegistry {
     adapters: Mutex<Vec<AdapterEntry>>,
     /// Cached process list — wrapped in Arc for cheap sharing (no deep clone).
     cache: RwLock<Arc<Vec<ProcessInfo>>>,
+    /// Hook payload normalizers by agent name (`agent` in `/api/signal`).
+    normalizers: HashMap<&'static str, Box<dyn HookNormalizer>>,
 }
 
 impl AdapterRegistry {
@@ -27,6 +78,12 @@ impl AdapterRegistry {
                 &["claude.exe", "claude"],
                 &["chrome-native-host.exe", "chrome-native-host"],
             ),
+            capabilities: Capabilities {
+                supports_chat: true,
+                supports_permissions: true,
+                supports_send: true,
+                transcript_format: Some("claude_jsonl"),
+            },
         });
 
         // Codex CLI adapter
@@ -37,14 +94,32 @@ impl AdapterRegistry {
                 &["codex.exe", "codex"],
                 &[],
             ),
+            // Only turn-complete notifications; no transcript reader or
+            // permission hook yet
+            capabilities: Capabilities {
+                supports_chat: false,
+                supports_permissions: false,
+                supports_send: true,
+                transcript_format: None,
+            },
         });
 
+        let mut normalizers: HashMap<&'static str, Box<dyn HookNormalizer>> = HashMap::new();
+        normalizers.insert("claude_code", Box::new(claude_code::ClaudeCode));
+        normalizers.insert("codex", Box::new(codex::Codex));
+
         Self {
             adapters: Mutex::new(adapters),
             cache: RwLock::new(Arc::new(Vec::new())),
+            normalizers,
         }
     }
 
+    /// Normalize a raw hook payload from `agent`; `None` for an unknown agent.
+    pub fn normalize(&self, agent: &str, event: &str, raw: &Value) -> Option<NormalizedSignal> {
+        self.normalizers.get(agent).map(|n| n.normalize(event, raw))
+    }
+
     /// Trigger a fresh scan from all adapters.
     pub fn scan_all(&self) {
         let mut results = Vec::new();
@@ -57,6 +132,23 @@ impl AdapterRegistry {
         *cache = Arc::new(results);
     }
 
+    /// Name and capabilities of every adapter, for `GET /api/adapters`.
+    pub fn list(&self) -> Vec<Value> {
+        self.adapters.lock().unwrap()
+            .iter()
+            .map(|a| {
+                let mut v = json!(a.capabilities);
+                v["name"] = json!(a.name);
+                v
+            })
+            .collect()
+    }
+
+    /// Capabilities of the adapter named `agent`, if registered.
+    pub fn capabilities(&self, agent: &str) -> Option<Capabilities> {
+        self.adapters.lock().unwrap().iter().find(|a| a.name == agent).map(|a| a.capabilities)
+    }
+
     /// Get cached process list — cheap Arc clone, no deep copy.
     pub fn get_cached(&self) -> Arc<Vec<ProcessInfo>> {
         let cache = self.cache.read().unwrap();
@@ -0,0 +1,121 @@
+//! OS autostart entry, registered with `--minimized` so a start at login goes
+//! straight to the tray.
+//!
+//! Windows (Run key) and macOS (LaunchAgent) go through
+//! tauri-plugin-autostart. On Linux we write the XDG autostart entry
+//! ourselves: the plugin leaves the `Exec` path unquoted, which breaks on
+//! paths with spaces, and misses a desktop environment having switched the
+//! entry off.
+
+use tauri::AppHandle;
+
+/// Passed by the autostart entry: start with the island hidden.
+pub const MINIMIZED_ARG: &str = "--minimized";
+
+/// Whether the app starts at login.
+#[cfg(not(target_os = "linux"))]
+pub fn is_enabled(app: &AppHandle) -> bool {
+    use tauri_plugin_autostart::ManagerExt;
+    app.autolaunch().is_enabled().unwrap_or(false)
+}
+
+/// Whether the app starts at login.
+#[cfg(target_os = "linux")]
+pub fn is_enabled(_app: &AppHandle) -> bool {
+    xdg::is_enabled()
+}
+
+/// Add or remove the autostart entry. Enabling an existing entry rewrites
+/// it, picking up the current executable path.
+#[cfg(not(target_os = "linux"))]
+pub fn set_enabled(app: &AppHandle, on: bool) -> Result<(), String> {
+    use tauri_plugin_autostart::ManagerExt;
+    let al = app.autolaunch();
+    let res = if on { al.enable() } else { al.disable() };
+    res.map_err(|e| e.to_string())
+}
+
+/// Add or remove the autostart entry. Enabling an existing entry rewrites
+/// it, picking up the current executable path.
+#[cfg(target_os = "linux")]
+pub fn set_enabled(_app: &AppHandle, on: bool) -> Result<(), String> {
+    xdg::set_enabled(on)
+}
+
+#[cfg(target_os = "linux")]
+mod xdg {
+    use std::path::PathBuf;
+
+    const FILE_NAME: &str = "agent-desk.desktop";
+    /// Written by the autostart plugin in earlier versions (named after the
+    /// product); removed so the app doesn't start twice.
+    const LEGACY_FILE_NAME: &str = "Agent Desk.desktop";
+
+    /// `$XDG_CONFIG_HOME/autostart`, else `~/.config/autostart`.
+    fn autostart_dir() -> Option<PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+            .map(|d| d.join("autostart"))
+    }
+
+    /// The entry exists and the desktop environment hasn't switched it off.
+    pub fn is_enabled() -> bool {
+        let Some(dir) = autostart_dir() else { return false };
+        match std::fs::read_to_string(dir.join(FILE_NAME)) {
+            Ok(text) => !text.lines().map(str::trim).any(|l| l == "Hidden=true" || l == "X-GNOME-Autostart-enabled=false"),
+            Err(_) => false,
+        }
+    }
+
+    pub fn set_enabled(on: bool) -> Result<(), String> {
+        let dir = autostart_dir().ok_or("neither XDG_CONFIG_HOME nor HOME is set")?;
+        let _ = std::fs::remove_file(dir.join(LEGACY_FILE_NAME));
+        let path = dir.join(FILE_NAME);
+        if !on {
+            return match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("{}: {}", path.display(), e)),
+                _ => Ok(()),
+            };
+        }
+        // An AppImage runs from a temporary mount; start the image itself
+        let exe = std::env::var_os("APPIMAGE")
+            .map(PathBuf::from)
+            .or_else(|| std::env::current_exe().ok())
+            .ok_or("cannot find the executable")?;
+        std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        std::fs::write(&path, entry(&exe.to_string_lossy())).map_err(|e| format!("{}: {}", path.display(), e))?;
+        tracing::info!("Autostart entry written to {}", path.display());
+        Ok(())
+    }
+
+    fn entry(exe: &str) -> String {
+        format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=Agent Desk\n\
+             Comment=Monitor AI coding agents from the tray\n\
+             Exec={} {}\n\
+             Terminal=false\n\
+             StartupNotify=false\n\
+             X-GNOME-Autostart-enabled=true\n",
+            quote_exec(exe),
+            super::MINIMIZED_ARG,
+        )
+    }
+
+    /// Quote an `Exec` argument per the Desktop Entry spec: backslash-escape
+    /// `"`, `` ` ``, `$` and `\` inside double quotes, then apply the string
+    /// escaping (every `\` doubled) and `%%` for a literal `%`.
+    fn quote_exec(arg: &str) -> String {
+        let mut quoted = String::with_capacity(arg.len() + 2);
+        for c in arg.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        format!("\"{}\"", quoted.replace('\\', "\\\\").replace('%', "%%"))
+    }
+}
@@ -0,0 +1,77 @@
+//! agent-desk-sim: replay mock agent sessions against a running Agent Desk.
+//!
+//! ```text
+//! agent-desk-sim [--url URL] [--speed X] <builtin|scenario.json|recording.jsonl>...
+//! agent-desk-sim --list
+//! ```
+//!
+//! Scenarios given together run side by side, each as its own session.
+
+use agent_desk_lib::simulator::{self, Scenario};
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut url = None;
+    let mut speed = 1.0;
+    let mut names = Vec::new();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--url" => url = it.next().cloned(),
+            "--speed" => speed = it.next().and_then(|s| s.parse().ok()).unwrap_or(1.0),
+            "--list" => {
+                println!("{}", simulator::BUILTIN.join("\n"));
+                return;
+            }
+            _ => names.push(arg.clone()),
+        }
+    }
+    if names.is_empty() {
+        eprintln!("usage: agent-desk-sim [--url URL] [--speed X] <{}|scenario.json>...", simulator::BUILTIN.join("|"));
+        std::process::exit(2);
+    }
+    let url = url.unwrap_or_else(simulator::local_base_url);
+
+    let mut scenarios = Vec::new();
+    for name in &names {
+        match load(name) {
+            Ok(s) => scenarios.push(s.prepared()),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let client = reqwest::Client::new();
+    let runs = scenarios.into_iter().map(|s| {
+        let (client, url) = (client.clone(), url.clone());
+        tokio::spawn(async move {
+            let result = simulator::run(&client, &url, &s, speed).await;
+            (s, result)
+        })
+    });
+    let mut failed = false;
+    for run in futures::future::join_all(runs).await {
+        let Ok((s, result)) = run else { continue };
+        match result {
+            Ok(n) => println!("{} ({}): {} steps", s.name, s.session_id, n),
+            Err(e) => {
+                eprintln!("{} ({}): {}", s.name, s.session_id, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// A builtin scenario by name, else a scenario or recording file.
+fn load(name: &str) -> Result<Scenario, String> {
+    match simulator::builtin(name) {
+        Some(s) => Ok(s),
+        None => simulator::load_file(std::path::Path::new(name)),
+    }
+}
@@ -0,0 +1,236 @@
+//! Token and cost budgets.
+//!
+//! `budget.session` caps every session on its own; `budget.projects` caps
+//! the sum over a project's tracked sessions. A background check compares
+//! transcript usage with the limits and reports each threshold once: the
+//! `warn_percent` warning, then the limit itself. With `pause_at_limit`, a
+//! session over its own or its project's limit gets further permission
+//! requests denied until the alert is acknowledged (`POST /api/budget/ack`).
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::config::{BudgetConfig, BudgetLimit, ModelPrice};
+use crate::process::cwd_key;
+
+/// Transcript usage of one tracked session.
+pub struct SessionUsage {
+    pub session_id: String,
+    pub cwd: String,
+    pub model: Option<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Usage against one budget.
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    /// `session:<id>` or `project:<cwd>`.
+    pub key: String,
+    pub scope: &'static str,
+    /// Short session id or project name.
+    pub name: String,
+    pub cwd: String,
+    pub tokens: u64,
+    /// USD; 0 when no price matches the model.
+    pub cost: f64,
+    pub token_limit: u64,
+    pub cost_limit: f64,
+    /// Share of the tighter limit used.
+    pub percent: f64,
+    pub paused: bool,
+}
+
+/// A threshold crossed for the first time.
+pub struct Crossing {
+    pub status: BudgetStatus,
+    pub at_limit: bool,
+}
+
+pub struct BudgetTracker {
+    /// Budget key → highest threshold reported (1 = warning, 2 = limit).
+    alerted: RwLock<HashMap<String, u8>>,
+    /// Budget key → reason given when denying its sessions' permissions.
+    paused: RwLock<HashMap<String, String>>,
+    latest: RwLock<Vec<BudgetStatus>>,
+}
+
+impl BudgetTracker {
+    pub fn new() -> Self {
+        Self {
+            alerted: RwLock::new(HashMap::new()),
+            paused: RwLock::new(HashMap::new()),
+            latest: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Re-evaluate every budget against fresh usage. Returns the thresholds
+    /// crossed since the last call; budgets that reached their limit are
+    /// paused when `pause_at_limit` is set.
+    pub fn update(&self, cfg: &BudgetConfig, sessions: &[SessionUsage]) -> Vec<Crossing> {
+        let mut statuses: Vec<BudgetStatus> = Vec::new();
+        let mut projects: HashMap<String, BudgetStatus> = HashMap::new();
+        for s in sessions {
+            let cost = cost(&cfg.prices, s.model.as_deref(), s.input_tokens, s.output_tokens);
+            let tokens = s.input_tokens + s.output_tokens;
+            if has_limit(&cfg.session) {
+                let short = if s.session_id.len() > 8 { &s.session_id[..8] } else { &s.session_id };
+                statuses.push(status(format!("session:{}", s.session_id), "session", short, &s.cwd, tokens, cost, &cfg.session));
+            }
+            let Some(limit) = project_limit(cfg, &s.cwd) else { continue };
+            let key = format!("project:{}", cwd_key(&s.cwd));
+            let p = projects
+                .entry(key.clone())
+                .or_insert_with(|| status(key, "project", crate::tray::project_name(&s.cwd), &s.cwd, 0, 0.0, limit));
+            p.tokens += tokens;
+            p.cost += cost;
+        }
+        statuses.extend(projects.into_values().map(|mut p| {
+            p.percent = percent(p.tokens, p.cost, p.token_limit, p.cost_limit);
+            p
+        }));
+
+        let mut alerted = write_lock!(self.alerted);
+        let mut paused = write_lock!(self.paused);
+        alerted.retain(|k, _| statuses.iter().any(|s| &s.key == k));
+        paused.retain(|k, _| statuses.iter().any(|s| &s.key == k));
+        let mut crossings = Vec::new();
+        for st in statuses.iter_mut() {
+            let level = if st.percent >= 100.0 {
+                2
+            } else if st.percent >= cfg.warn_percent as f64 {
+                1
+            } else {
+                0
+            };
+            let prev = alerted.get(&st.key).copied().unwrap_or(0);
+            if level > prev {
+                alerted.insert(st.key.clone(), level);
+                if level == 2 && cfg.pause_at_limit {
+                    paused.insert(st.key.clone(), format!(
+                        "Agent Desk paused this session: {} budget for {} reached ({}). Acknowledge the alert in Agent Desk to continue.",
+                        st.scope, st.name, describe(st),
+                    ));
+                }
+            }
+            st.paused = paused.contains_key(&st.key);
+            if level > prev {
+                crossings.push(Crossing { status: st.clone(), at_limit: level == 2 });
+            }
+        }
+        statuses.sort_by(|a, b| b.percent.total_cmp(&a.percent));
+        *write_lock!(self.latest) = statuses;
+        crossings
+    }
+
+    /// Why the session's permissions are being denied, while it or its
+    /// project is paused.
+    pub fn paused(&self, session_id: &str, cwd: &str) -> Option<String> {
+        let paused = read_lock!(self.paused);
+        if paused.is_empty() {
+            return None;
+        }
+        paused
+            .get(&format!("session:{}", session_id))
+            .or_else(|| paused.get(&format!("project:{}", cwd_key(cwd))))
+            .cloned()
+    }
+
+    /// Lift the pause on `keys` (all budgets when empty). Returns how many
+    /// pauses were lifted. Alerts stay reported, so the budget does not
+    /// pause again until the app restarts.
+    pub fn acknowledge(&self, keys: &[String]) -> usize {
+        let mut paused = write_lock!(self.paused);
+        let before = paused.len();
+        if keys.is_empty() {
+            paused.clear();
+        } else {
+            paused.retain(|k, _| !keys.contains(k));
+        }
+        let mut latest = write_lock!(self.latest);
+        for st in latest.iter_mut() {
+            st.paused = paused.contains_key(&st.key);
+        }
+        before - paused.len()
+    }
+
+    /// Budgets as of the last check, closest to their limit first.
+    pub fn snapshot(&self) -> Vec<BudgetStatus> {
+        read_lock!(self.latest).clone()
+    }
+}
+
+/// Budget keys covering a session: its own and its project's.
+pub fn keys_for(session_id: &str, cwd: &str) -> Vec<String> {
+    vec![format!("session:{}", session_id), format!("project:{}", cwd_key(cwd))]
+}
+
+/// "1.2M / 1M tokens, $3.10 / $5.00" — only the limits that are set.
+pub fn describe(st: &BudgetStatus) -> String {
+    let mut parts = Vec::new();
+    if st.token_limit > 0 {
+        parts.push(format!("{} / {} tokens", short_count(st.tokens), short_count(st.token_limit)));
+    }
+    if st.cost_limit > 0.0 {
+        parts.push(format!("${:.2} / ${:.2}", st.cost, st.cost_limit));
+    }
+    parts.join(", ")
+}
+
+fn short_count(n: u64) -> String {
+    match n {
+        0..=9_999 => n.to_string(),
+        10_000..=999_999 => format!("{}k", n / 1000),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
+
+fn has_limit(limit: &BudgetLimit) -> bool {
+    limit.tokens > 0 || limit.cost > 0.0
+}
+
+/// The project limit for `cwd`: keyed by folder name or full path.
+fn project_limit<'a>(cfg: &'a BudgetConfig, cwd: &str) -> Option<&'a BudgetLimit> {
+    if cwd.is_empty() {
+        return None;
+    }
+    let name = crate::tray::project_name(cwd);
+    let key = cwd_key(cwd);
+    cfg.projects
+        .iter()
+        .find(|(k, l)| has_limit(l) && (k.eq_ignore_ascii_case(name) || cwd_key(k) == key))
+        .map(|(_, l)| l)
+}
+
+/// USD for the tokens at the price of the longest matching model prefix.
+fn cost(prices: &HashMap<String, ModelPrice>, model: Option<&str>, input: u64, output: u64) -> f64 {
+    let Some(model) = model else { return 0.0 };
+    prices
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, p)| (input as f64 * p.input + output as f64 * p.output) / 1_000_000.0)
+        .unwrap_or(0.0)
+}
+
+fn percent(tokens: u64, cost: f64, token_limit: u64, cost_limit: f64) -> f64 {
+    let by_tokens = if token_limit > 0 { tokens as f64 / token_limit as f64 } else { 0.0 };
+    let by_cost = if cost_limit > 0.0 { cost / cost_limit } else { 0.0 };
+    by_tokens.max(by_cost) * 100.0
+}
+
+fn status(key: String, scope: &'static str, name: &str, cwd: &str, tokens: u64, cost: f64, limit: &BudgetLimit) -> BudgetStatus {
+    BudgetStatus {
+        key,
+        scope,
+        name: name.to_string(),
+        cwd: cwd.to_string(),
+        tokens,
+        cost,
+        token_limit: limit.tokens,
+        cost_limit: limit.cost,
+        percent: percent(tokens, cost, limit.tokens, limit.cost),
+        paused: false,
+    }
+}
@@ -1,7 +1,8 @@
 //! Claude Code JSONL chat reader.
 //!
-//! Reads `~/.claude/projects/<project-dir>/<session-uuid>.jsonl`
-//! incrementally, deduplicates streaming assistant messages by UUID,
+//! Reads `<root>/<project-dir>/<session-uuid>.jsonl` incrementally, where
+//! the roots are `general.transcript_roots.claude_code`, then
+//! `$CLAUDE_CONFIG_DIR/projects`, then `~/.claude/projects`. It deduplicates streaming assistant messages by UUID,
 //! and returns parsed chat messages.
 //!
 //! Two output formats:
@@ -12,8 +13,8 @@ use serde::Serialize;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
@@ -42,8 +43,22 @@ pub struct ChatToolUse {
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ChatEvent {
     Text { role: String, content: String },
-    ToolCall { name: String, input: Value },
-    ToolResult { tool_use_id: String, content: String, is_error: bool },
+    ToolCall {
+        name: String,
+        input: Value,
+        /// Matches the `tool_use_id` of its result.
+        #[serde(skip)]
+        id: String,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        is_error: bool,
+        /// Size of the captured full output (Bash/Grep), fetched on demand
+        /// from `/api/chat/tool-result/{uuid}`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        full_bytes: Option<usize>,
+    },
     Thinking { summary: String },
 }
 
@@ -67,142 +82,633 @@ pub struct TokenUsage {
 
 // ─── Session cache ──────────────────────────────────────
 
+/// A window of a session's messages. Indices are absolute positions in the
+/// transcript, stable across trimming.
+#[derive(Debug, Serialize)]
+pub struct ChatPage<T> {
+    pub messages: Vec<T>,
+    /// Index of `messages[0]` (older history exists when > 0).
+    pub first_index: usize,
+    /// Index after the last message (`after` for the next poll).
+    pub next_index: usize,
+    /// Messages in the session so far, trimmed ones included.
+    pub total: usize,
+}
+
+impl<T> ChatPage<T> {
+    pub fn empty(at: usize) -> Self {
+        Self { messages: Vec::new(), first_index: at, next_index: at, total: at }
+    }
+}
+
 struct SessionCache {
     offset: u64,
+    /// Absolute index of `messages[0]`; earlier ones were trimmed.
+    base: usize,
     messages: Vec<ChatMessage>,
+    /// Absolute index of `enriched[0]`.
+    enriched_base: usize,
     enriched: Vec<EnrichedMessage>,
-    /// UUID → index in messages vec (for dedup of streaming updates)
+    /// Per enriched message (absolute index): file offset of its row and
+    /// its position among the row's events, to page trimmed history back in.
+    bookmarks: Vec<(u64, u32)>,
+    /// Token usage of trimmed enriched messages: (unix ts, usage).
+    trimmed_usage: Vec<(f64, TokenUsage)>,
+    /// API message id whose usage was last counted. A streamed reply is
+    /// written as one row per content block, each repeating the usage.
+    last_usage_id: String,
+    /// UUID → absolute index in messages (for dedup of streaming updates)
     uuid_index: HashMap<String, usize>,
-    /// UUID → index in enriched vec (for dedup)
+    /// UUID → absolute index in enriched (for dedup)
     enriched_uuid_index: HashMap<String, usize>,
+    /// tool_use id → tool name, for calls whose result hasn't arrived yet.
+    tool_names: HashMap<String, String>,
+    /// Enriched message uuid → full output of a Bash/Grep result.
+    tool_outputs: HashMap<String, ToolOutput>,
+    /// Identity and first bytes of the file parsed so far — a mismatch means
+    /// it was rewritten (e.g. compacted) and `offset` is meaningless.
+    file_id: Option<FileId>,
+    head: Vec<u8>,
     last_accessed: Instant,
 }
 
+impl SessionCache {
+    fn new() -> Self {
+        Self {
+            offset: 0,
+            base: 0,
+            messages: Vec::new(),
+            enriched_base: 0,
+            enriched: Vec::new(),
+            bookmarks: Vec::new(),
+            trimmed_usage: Vec::new(),
+            last_usage_id: String::new(),
+            uuid_index: HashMap::new(),
+            enriched_uuid_index: HashMap::new(),
+            tool_names: HashMap::new(),
+            tool_outputs: HashMap::new(),
+            file_id: None,
+            head: Vec::new(),
+            last_accessed: Instant::now(),
+        }
+    }
+
+    /// Rough heap size: text lengths plus a fixed cost per entry (see
+    /// memory.rs).
+    fn approx_bytes(&self) -> usize {
+        use crate::memory::ENTRY_OVERHEAD;
+        let messages: usize = self
+            .messages
+            .iter()
+            .map(|m| ENTRY_OVERHEAD + m.role.len() + m.content.len() + m.timestamp.len() + m.uuid.len() + m.tool_uses.len() * ENTRY_OVERHEAD)
+            .sum();
+        let enriched: usize = self
+            .enriched
+            .iter()
+            .map(|em| {
+                let event = match &em.event {
+                    ChatEvent::Text { role, content } => role.len() + content.len(),
+                    ChatEvent::ToolCall { name, input, id } => name.len() + id.len() + crate::memory::value_bytes(input),
+                    ChatEvent::ToolResult { tool_use_id, content, .. } => tool_use_id.len() + content.len(),
+                    ChatEvent::Thinking { summary } => summary.len(),
+                };
+                ENTRY_OVERHEAD + event + em.uuid.len() + em.timestamp.len() + em.model.as_ref().map_or(0, String::len)
+            })
+            .sum();
+        let indexes: usize = self
+            .uuid_index
+            .keys()
+            .chain(self.enriched_uuid_index.keys())
+            .map(|k| ENTRY_OVERHEAD + k.len())
+            .sum();
+        let tools: usize = self.tool_names.iter().map(|(k, v)| ENTRY_OVERHEAD + k.len() + v.len()).sum::<usize>()
+            + self.tool_outputs.iter().map(|(k, v)| ENTRY_OVERHEAD + k.len() + v.compressed.len()).sum::<usize>();
+        messages
+            + enriched
+            + indexes
+            + tools
+            + self.bookmarks.len() * std::mem::size_of::<(u64, u32)>()
+            + self.trimmed_usage.len() * std::mem::size_of::<(f64, TokenUsage)>()
+            + self.head.len()
+    }
+
+    /// Keep the last `max` messages of each list (0 = unlimited). Trims in
+    /// batches so the index cleanup doesn't run on every new line.
+    fn trim(&mut self, max: usize) {
+        if max == 0 {
+            return;
+        }
+        if self.messages.len() > max + max / 4 {
+            let drop = self.messages.len() - max;
+            self.messages.drain(..drop);
+            self.base += drop;
+            let base = self.base;
+            self.uuid_index.retain(|_, i| *i >= base);
+        }
+        if self.enriched.len() > max + max / 4 {
+            let drop = self.enriched.len() - max;
+            for em in self.enriched.drain(..drop) {
+                self.tool_outputs.remove(&em.uuid);
+                if let Some(u) = em.usage {
+                    self.trimmed_usage.push((message_ts(&em.timestamp), u));
+                }
+            }
+            self.enriched_base += drop;
+            let base = self.enriched_base;
+            self.enriched_uuid_index.retain(|_, i| *i >= base);
+        }
+    }
+}
+
+/// Bytes compared at the start of a transcript to notice a rewrite.
+const HEAD_LEN: usize = 256;
+
+/// What identifies a transcript file across reads: the inode on Unix,
+/// the creation time elsewhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileId {
+    #[cfg(unix)]
+    ino: u64,
+    #[cfg(not(unix))]
+    created: Option<std::time::SystemTime>,
+}
+
+impl FileId {
+    fn of(meta: &std::fs::Metadata) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Self { ino: meta.ino() }
+        }
+        #[cfg(not(unix))]
+        {
+            Self { created: meta.created().ok() }
+        }
+    }
+}
+
+/// Shortest time between two full scans of the transcript roots.
+const INDEX_RESCAN: Duration = Duration::from_secs(10);
+
 pub struct ChatReader {
     cache: Mutex<HashMap<String, SessionCache>>,
+    /// Transcript search roots, in priority order.
+    roots: Vec<PathBuf>,
+    /// session_id → transcript, as reported by hooks or found by a search.
+    known_paths: Mutex<HashMap<String, PathBuf>>,
+    /// When the roots were last indexed in full (see `index_roots`).
+    indexed_at: Mutex<Option<Instant>>,
+    /// Sessions whose transcript was rewritten and reparsed since the last
+    /// `take_resets` (the server tells clients to re-render them).
+    resets: Mutex<Vec<String>>,
+    /// Messages kept in memory per session (0 = unlimited).
+    max_messages: usize,
+    /// Bytes of full Bash/Grep output kept per result (0 = none).
+    tool_output_bytes: usize,
 }
 
 impl ChatReader {
-    pub fn new() -> Self {
+    pub fn new(max_messages: usize, tool_output_bytes: usize, roots: Vec<PathBuf>) -> Self {
         Self {
             cache: Mutex::new(HashMap::new()),
+            roots,
+            known_paths: Mutex::new(HashMap::new()),
+            indexed_at: Mutex::new(None),
+            resets: Mutex::new(Vec::new()),
+            max_messages,
+            tool_output_bytes,
         }
     }
 
-    /// Read messages for a session, returning (messages, next_index).
-    /// `after` is the index to start from (for incremental reads).
-    pub fn read_messages(
+    /// Full output captured for the tool result with this uuid, and
+    /// whether it was cut at `tool_output_bytes`.
+    pub fn tool_output(&self, uuid: &str) -> Option<(String, bool)> {
+        let cache_map = mutex_lock!(self.cache);
+        let out = cache_map.values().find_map(|e| e.tool_outputs.get(uuid))?;
+        Some((out.text()?, out.truncated))
+    }
+
+    /// Read messages for a session from absolute index `after` on (for
+    /// incremental reads). Trimmed messages are skipped.
+    pub fn read_messages(&self, session_id: &str, cwd: &str, after: usize) -> ChatPage<ChatMessage> {
+        self.ensure_parsed(session_id, cwd);
+        let cache_key = format!("{}:{}", session_id, cwd);
+        let cache_map = mutex_lock!(self.cache);
+        let Some(entry) = cache_map.get(&cache_key) else {
+            return ChatPage::empty(0);
+        };
+        let total = entry.base + entry.messages.len();
+        let start = after.max(entry.base);
+        if start >= total {
+            return ChatPage::empty(total);
+        }
+        ChatPage {
+            messages: entry.messages[start - entry.base..].to_vec(),
+            first_index: start,
+            next_index: total,
+            total,
+        }
+    }
+
+    /// Read enriched (v2) messages for a session from absolute index `after`
+    /// on, at most `limit` of them (`next_index` continues where it stopped).
+    pub fn read_enriched(
         &self,
         session_id: &str,
         cwd: &str,
         after: usize,
-    ) -> (Vec<ChatMessage>, usize) {
+        limit: Option<usize>,
+    ) -> ChatPage<EnrichedMessage> {
         self.ensure_parsed(session_id, cwd);
         let cache_key = format!("{}:{}", session_id, cwd);
         let cache_map = mutex_lock!(self.cache);
-        if let Some(entry) = cache_map.get(&cache_key) {
-            let total = entry.messages.len();
-            if after >= total {
-                return (vec![], total);
-            }
-            let slice = entry.messages[after..].to_vec();
-            (slice, total)
-        } else {
-            (vec![], 0)
+        let Some(entry) = cache_map.get(&cache_key) else {
+            return ChatPage::empty(0);
+        };
+        let total = entry.enriched_base + entry.enriched.len();
+        let start = after.max(entry.enriched_base);
+        if start >= total {
+            return ChatPage::empty(total);
+        }
+        let end = limit.map_or(total, |n| total.min(start + n));
+        ChatPage {
+            messages: entry.enriched[start - entry.enriched_base..end - entry.enriched_base].to_vec(),
+            first_index: start,
+            next_index: end,
+            total,
         }
     }
 
-    /// Read enriched (v2) messages for a session.
-    pub fn read_enriched(
+    /// Up to `limit` enriched messages before absolute index `before`
+    /// (`usize::MAX` for the latest ones). Trimmed ones are re-read from the
+    /// transcript via their bookmarks.
+    pub fn read_enriched_before(
         &self,
         session_id: &str,
         cwd: &str,
-        after: usize,
-    ) -> (Vec<EnrichedMessage>, usize) {
+        before: usize,
+        limit: usize,
+    ) -> ChatPage<EnrichedMessage> {
+        self.ensure_parsed(session_id, cwd);
+        let cache_key = format!("{}:{}", session_id, cwd);
+        let (start, end, total, marks, in_memory) = {
+            let cache_map = mutex_lock!(self.cache);
+            let Some(entry) = cache_map.get(&cache_key) else {
+                return ChatPage::empty(0);
+            };
+            let base = entry.enriched_base;
+            let total = base + entry.enriched.len();
+            let end = before.min(total);
+            let start = end.saturating_sub(limit);
+            let split = base.clamp(start, end);
+            let marks = entry.bookmarks[start..split].to_vec();
+            let in_memory = entry.enriched[split - base..end - base].to_vec();
+            (start, end, total, marks, in_memory)
+        };
+
+        let mut messages = if marks.is_empty() {
+            Vec::new()
+        } else {
+            self.transcript_path(session_id, cwd)
+                .map(|path| reread_bookmarks(&path, &marks))
+                .unwrap_or_default()
+        };
+        messages.extend(in_memory);
+        ChatPage { messages, first_index: start, next_index: end, total }
+    }
+
+    /// Model of the session's most recent assistant message.
+    pub fn latest_model(&self, session_id: &str, cwd: &str) -> Option<String> {
         self.ensure_parsed(session_id, cwd);
         let cache_key = format!("{}:{}", session_id, cwd);
         let cache_map = mutex_lock!(self.cache);
+        cache_map
+            .get(&cache_key)?
+            .enriched
+            .iter()
+            .rev()
+            .filter_map(|em| em.model.as_deref())
+            // Claude Code tags locally generated errors with "<synthetic>"
+            .find(|m| !m.is_empty() && !m.starts_with('<'))
+            .map(str::to_string)
+    }
+
+    /// Sum token usage of a session's messages timestamped at or after
+    /// `since` (unix seconds).
+    pub fn token_usage_since(&self, session_id: &str, cwd: &str, since: f64) -> TokenUsage {
+        self.ensure_parsed(session_id, cwd);
+        let cache_key = format!("{}:{}", session_id, cwd);
+        let cache_map = mutex_lock!(self.cache);
+        let mut total = TokenUsage { input_tokens: 0, output_tokens: 0 };
         if let Some(entry) = cache_map.get(&cache_key) {
-            let total = entry.enriched.len();
-            if after >= total {
-                return (vec![], total);
+            let live = entry
+                .enriched
+                .iter()
+                .filter_map(|em| Some((message_ts(&em.timestamp), em.usage.as_ref()?)));
+            let trimmed = entry.trimmed_usage.iter().map(|(ts, u)| (*ts, u));
+            for (ts, u) in trimmed.chain(live) {
+                if ts >= since {
+                    total.input_tokens += u.input_tokens;
+                    total.output_tokens += u.output_tokens;
+                }
+            }
+        }
+        total
+    }
+
+    /// Token usage in each window `[bounds[i], bounds[i + 1])` (unix
+    /// seconds, ascending), trimmed messages included.
+    pub fn usage_between(&self, session_id: &str, cwd: &str, bounds: &[f64]) -> Vec<TokenUsage> {
+        self.ensure_parsed(session_id, cwd);
+        let cache_key = format!("{}:{}", session_id, cwd);
+        let cache_map = mutex_lock!(self.cache);
+        let mut buckets = vec![TokenUsage { input_tokens: 0, output_tokens: 0 }; bounds.len().saturating_sub(1)];
+        if let Some(entry) = cache_map.get(&cache_key) {
+            let live = entry
+                .enriched
+                .iter()
+                .filter_map(|em| Some((message_ts(&em.timestamp), em.usage.as_ref()?)));
+            let trimmed = entry.trimmed_usage.iter().map(|(ts, u)| (*ts, u));
+            for (ts, u) in trimmed.chain(live) {
+                // Index of the last bound at or before ts
+                let i = bounds.partition_point(|b| *b <= ts);
+                if let Some(b) = i.checked_sub(1).and_then(|i| buckets.get_mut(i)) {
+                    b.input_tokens += u.input_tokens;
+                    b.output_tokens += u.output_tokens;
+                }
+            }
+        }
+        buckets
+    }
+
+    /// Per tool name: (results, errors) of the tool calls answered at or
+    /// after `since` (unix seconds), among the messages still cached.
+    pub fn tool_outcomes(&self, session_id: &str, cwd: &str, since: f64) -> HashMap<String, (u64, u64)> {
+        self.ensure_parsed(session_id, cwd);
+        let cache_key = format!("{}:{}", session_id, cwd);
+        let cache_map = mutex_lock!(self.cache);
+        let mut outcomes: HashMap<String, (u64, u64)> = HashMap::new();
+        let Some(entry) = cache_map.get(&cache_key) else { return outcomes };
+        let mut names: HashMap<&str, &str> = HashMap::new();
+        for em in &entry.enriched {
+            match &em.event {
+                ChatEvent::ToolCall { name, id, .. } => {
+                    names.insert(id, name);
+                }
+                ChatEvent::ToolResult { tool_use_id, is_error, .. } => {
+                    let Some(name) = names.get(tool_use_id.as_str()) else { continue };
+                    if message_ts(&em.timestamp) < since {
+                        continue;
+                    }
+                    let o = outcomes.entry(name.to_string()).or_default();
+                    o.0 += 1;
+                    o.1 += *is_error as u64;
+                }
+                _ => {}
+            }
+        }
+        outcomes
+    }
+
+    /// Prompts the user typed and files the agent edited (first edit
+    /// first), among the messages still cached.
+    pub fn prompts_and_files(&self, session_id: &str, cwd: &str) -> (usize, Vec<String>) {
+        self.ensure_parsed(session_id, cwd);
+        let cache_key = format!("{}:{}", session_id, cwd);
+        let cache_map = mutex_lock!(self.cache);
+        let Some(entry) = cache_map.get(&cache_key) else { return (0, Vec::new()) };
+        let mut prompts = 0;
+        let mut files: Vec<String> = Vec::new();
+        for em in &entry.enriched {
+            match &em.event {
+                ChatEvent::Text { role, .. } if role == "user" => prompts += 1,
+                ChatEvent::ToolCall { name, input, .. } => {
+                    let Some(file) = crate::permission::edit_target(name, input) else { continue };
+                    if !files.iter().any(|f| f == file) {
+                        files.push(file.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        (prompts, files)
+    }
+
+    /// Output tokens per minute over the `minutes` whole minutes before
+    /// `now`, oldest first.
+    pub fn output_per_minute(&self, session_id: &str, cwd: &str, now: f64, minutes: usize) -> Vec<u64> {
+        self.ensure_parsed(session_id, cwd);
+        let cache_key = format!("{}:{}", session_id, cwd);
+        let cache_map = mutex_lock!(self.cache);
+        let mut buckets = vec![0u64; minutes];
+        let start = now - 60.0 * minutes as f64;
+        if let Some(entry) = cache_map.get(&cache_key) {
+            // Newest first: stop at the first message before the window
+            let recent = entry
+                .enriched
+                .iter()
+                .rev()
+                .filter_map(|em| Some((message_ts(&em.timestamp), em.usage.as_ref()?)))
+                .take_while(|(ts, _)| *ts >= start);
+            for (ts, u) in recent {
+                let idx = ((ts - start) / 60.0) as usize;
+                buckets[idx.min(minutes - 1)] += u.output_tokens;
             }
-            let slice = entry.enriched[after..].to_vec();
-            (slice, total)
-        } else {
-            (vec![], 0)
         }
+        buckets
     }
 
     /// Parse new lines from the JSONL file into both v1 and v2 caches.
     fn ensure_parsed(&self, session_id: &str, cwd: &str) {
-        let path = session_file_path(session_id, cwd);
-        if !path.exists() {
+        let Some(path) = self.transcript_path(session_id, cwd) else {
             return;
-        }
+        };
 
         let cache_key = format!("{}:{}", session_id, cwd);
         let mut cache_map = mutex_lock!(self.cache);
-        let entry = cache_map.entry(cache_key).or_insert_with(|| SessionCache {
-            offset: 0,
-            messages: Vec::new(),
-            enriched: Vec::new(),
-            uuid_index: HashMap::new(),
-            enriched_uuid_index: HashMap::new(),
-            last_accessed: Instant::now(),
-        });
+        let entry = cache_map.entry(cache_key).or_insert_with(SessionCache::new);
         entry.last_accessed = Instant::now();
 
+        let Ok(mut file) = File::open(&path) else { return };
+        let Ok(meta) = file.metadata() else { return };
+        let file_len = meta.len();
+        let file_id = FileId::of(&meta);
+        let mut head = Vec::with_capacity(HEAD_LEN);
+        let _ = (&mut file).take(HEAD_LEN as u64).read_to_end(&mut head);
+
+        // Compaction rewrites the transcript: start over from the top
+        let rewritten = entry.offset > file_len
+            || entry.file_id.as_ref().is_some_and(|id| *id != file_id)
+            || !head.starts_with(&entry.head);
+        if entry.offset > 0 && rewritten {
+            tracing::info!("Transcript of {} was rewritten, reparsing", session_id);
+            *entry = SessionCache::new();
+            mutex_lock!(self.resets).push(session_id.to_string());
+        }
+        entry.file_id = Some(file_id);
+        entry.head = head;
+
         // Read new lines from file
-        if let Ok(mut file) = File::open(&path) {
-            if let Ok(meta) = file.metadata() {
-                let file_len = meta.len();
-                if file_len > entry.offset {
-                    let _ = file.seek(SeekFrom::Start(entry.offset));
-                    let reader = BufReader::new(&file);
-                    for line in reader.lines() {
-                        let line = match line {
-                            Ok(l) => l,
-                            Err(_) => break,
-                        };
-                        if line.trim().is_empty() { continue; }
-                        if let Ok(row) = serde_json::from_str::<Value>(&line) {
-                            // v1 parsing
-                            if let Some(msg) = parse_jsonl_row(&row) {
-                                let uuid = msg.uuid.clone();
-                                if !uuid.is_empty() {
-                                    if let Some(&idx) = entry.uuid_index.get(&uuid) {
-                                        entry.messages[idx] = msg;
-                                    } else {
-                                        let idx = entry.messages.len();
-                                        entry.uuid_index.insert(uuid, idx);
-                                        entry.messages.push(msg);
-                                    }
-                                } else {
-                                    entry.messages.push(msg);
-                                }
+        if file_len > entry.offset {
+            let _ = file.seek(SeekFrom::Start(entry.offset));
+            let mut reader = BufReader::new(&file);
+            let mut line = String::new();
+            let mut pos = entry.offset;
+            loop {
+                line.clear();
+                let row_offset = pos;
+                match reader.read_line(&mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => pos += n as u64,
+                }
+                if line.trim().is_empty() { continue; }
+                if let Ok(row) = serde_json::from_str::<Value>(&line) {
+                    // v1 parsing
+                    if let Some(msg) = parse_jsonl_row(&row) {
+                        let uuid = msg.uuid.clone();
+                        if !uuid.is_empty() {
+                            if let Some(&idx) = entry.uuid_index.get(&uuid) {
+                                entry.messages[idx - entry.base] = msg;
+                            } else {
+                                let idx = entry.base + entry.messages.len();
+                                entry.uuid_index.insert(uuid, idx);
+                                entry.messages.push(msg);
                             }
-                            // v2 parsing — produces multiple events per row
-                            for em in parse_enriched_row(&row) {
-                                let uuid = em.uuid.clone();
-                                if !uuid.is_empty() {
-                                    if let Some(&idx) = entry.enriched_uuid_index.get(&uuid) {
-                                        entry.enriched[idx] = em;
-                                    } else {
-                                        let idx = entry.enriched.len();
-                                        entry.enriched_uuid_index.insert(uuid, idx);
-                                        entry.enriched.push(em);
-                                    }
-                                } else {
-                                    entry.enriched.push(em);
-                                }
+                        } else {
+                            entry.messages.push(msg);
+                        }
+                    }
+                    // v2 parsing — produces multiple events per row
+                    note_tool_uses(&row, &mut entry.tool_names);
+                    let msg_id = row.pointer("/message/id").and_then(|v| v.as_str()).unwrap_or("");
+                    let repeated_usage = !msg_id.is_empty() && msg_id == entry.last_usage_id;
+                    if !msg_id.is_empty() {
+                        entry.last_usage_id = msg_id.to_string();
+                    }
+                    for (seq, mut em) in parse_enriched_row(&row).into_iter().enumerate() {
+                        capture_tool_output(entry, &row, &mut em, self.tool_output_bytes);
+                        if repeated_usage {
+                            em.usage = None;
+                        }
+                        let uuid = em.uuid.clone();
+                        if !uuid.is_empty() {
+                            if let Some(&idx) = entry.enriched_uuid_index.get(&uuid) {
+                                entry.enriched[idx - entry.enriched_base] = em;
+                                continue;
                             }
+                            let idx = entry.enriched_base + entry.enriched.len();
+                            entry.enriched_uuid_index.insert(uuid, idx);
                         }
+                        entry.enriched.push(em);
+                        entry.bookmarks.push((row_offset, seq as u32));
                     }
-                    entry.offset = file_len;
                 }
             }
+            entry.offset = file_len;
+            entry.trim(self.max_messages);
+        }
+    }
+
+    /// Record the transcript path a hook reported for a session.
+    pub fn remember_path(&self, session_id: &str, transcript_path: &str) {
+        if session_id.is_empty() || transcript_path.is_empty() {
+            return;
+        }
+        mutex_lock!(self.known_paths).insert(session_id.to_string(), PathBuf::from(transcript_path));
+    }
+
+    /// Locate a session's transcript: the reported or indexed path, then
+    /// `<root>/<project-dir>/<id>.jsonl` in each root, then the index of
+    /// every project dir (for an empty cwd, or one that doesn't map to the
+    /// expected name).
+    fn transcript_path(&self, session_id: &str, cwd: &str) -> Option<PathBuf> {
+        let known = mutex_lock!(self.known_paths).get(session_id).cloned();
+        if let Some(p) = known.filter(|p| p.is_file()) {
+            return Some(p);
+        }
+        let file_name = format!("{}.jsonl", session_id);
+        let project_dir = cwd_to_project_dir(cwd);
+        let expected = (!cwd.is_empty())
+            .then(|| {
+                self.roots
+                    .iter()
+                    .map(|root| root.join(&project_dir).join(&file_name))
+                    .find(|p| p.is_file())
+            })
+            .flatten();
+        if let Some(p) = expected {
+            mutex_lock!(self.known_paths).insert(session_id.to_string(), p.clone());
+            return Some(p);
         }
+        self.index_roots();
+        mutex_lock!(self.known_paths).get(session_id).cloned().filter(|p| p.is_file())
+    }
+
+    /// Record every `<root>/<project-dir>/<id>.jsonl` in `known_paths`, at
+    /// most once per `INDEX_RESCAN` so lookups for a missing transcript
+    /// don't walk the roots each time. Paths already known and still
+    /// there are kept; roots earlier in the list win.
+    fn index_roots(&self) {
+        {
+            let mut at = mutex_lock!(self.indexed_at);
+            if at.is_some_and(|t| t.elapsed() < INDEX_RESCAN) {
+                return;
+            }
+            *at = Some(Instant::now());
+        }
+        let mut found: HashMap<String, PathBuf> = HashMap::new();
+        for root in &self.roots {
+            let Ok(dirs) = std::fs::read_dir(root) else { continue };
+            for dir in dirs.filter_map(Result::ok).map(|d| d.path()) {
+                let Ok(files) = std::fs::read_dir(&dir) else { continue };
+                for path in files.filter_map(Result::ok).map(|f| f.path()) {
+                    if path.extension().is_none_or(|e| e != "jsonl") {
+                        continue;
+                    }
+                    if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
+                        found.entry(id.to_string()).or_insert(path);
+                    }
+                }
+            }
+        }
+        let mut known = mutex_lock!(self.known_paths);
+        for (id, path) in found {
+            known
+                .entry(id)
+                .and_modify(|p| if !p.is_file() { *p = path.clone() })
+                .or_insert(path);
+        }
+    }
+
+    /// Other session ids tagged on the first rows of a transcript. A resumed
+    /// session's transcript starts with the history it continues, still
+    /// tagged with the earlier session's id. `transcript_path` may be empty.
+    pub fn prior_session_ids(&self, session_id: &str, cwd: &str, transcript_path: &str) -> Vec<String> {
+        let path = if transcript_path.is_empty() {
+            match self.transcript_path(session_id, cwd) {
+                Some(p) => p,
+                None => return Vec::new(),
+            }
+        } else {
+            PathBuf::from(transcript_path)
+        };
+        let Ok(file) = File::open(&path) else {
+            return Vec::new();
+        };
+        let mut ids: Vec<String> = Vec::new();
+        for line in BufReader::new(file).lines().take(50).map_while(Result::ok) {
+            let Ok(row) = serde_json::from_str::<Value>(&line) else { continue };
+            let id = row.get("sessionId").and_then(|v| v.as_str()).unwrap_or(session_id);
+            if id != session_id && !ids.iter().any(|i| i == id) {
+                ids.push(id.to_string());
+            }
+        }
+        ids
+    }
+
+    /// Session ids whose transcript was rewritten since the last call.
+    pub fn take_resets(&self) -> Vec<String> {
+        std::mem::take(&mut *mutex_lock!(self.resets))
     }
 
     /// Evict session caches not accessed within `max_age`.
@@ -211,6 +717,70 @@ impl ChatReader {
         let cutoff = Instant::now() - max_age;
         cache_map.retain(|_, entry| entry.last_accessed >= cutoff);
     }
+
+    /// Sessions cached, and their approximate size in bytes.
+    pub fn memory(&self) -> (usize, usize) {
+        let cache_map = mutex_lock!(self.cache);
+        (cache_map.len(), cache_map.values().map(SessionCache::approx_bytes).sum())
+    }
+
+    /// Drop the least recently read sessions until the rest fit in
+    /// `max_bytes`; they are parsed again when next read. Returns how many
+    /// were dropped.
+    pub fn shrink_to(&self, max_bytes: usize) -> usize {
+        let mut cache_map = mutex_lock!(self.cache);
+        let mut sizes: Vec<(Instant, String, usize)> = cache_map
+            .iter()
+            .map(|(key, entry)| (entry.last_accessed, key.clone(), entry.approx_bytes()))
+            .collect();
+        let mut total: usize = sizes.iter().map(|(_, _, n)| n).sum();
+        sizes.sort_by_key(|(accessed, _, _)| *accessed);
+        let mut dropped = 0;
+        for (_, key, bytes) in sizes {
+            if total <= max_bytes {
+                break;
+            }
+            cache_map.remove(&key);
+            total -= bytes;
+            dropped += 1;
+        }
+        dropped
+    }
+}
+
+/// Re-parse the rows behind `marks` (file offset, event position). Rows the
+/// file no longer has are skipped.
+fn reread_bookmarks(path: &Path, marks: &[(u64, u32)]) -> Vec<EnrichedMessage> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    let mut reader = BufReader::new(file);
+    let mut out = Vec::with_capacity(marks.len());
+    let mut row: Option<(u64, Vec<EnrichedMessage>)> = None;
+    for &(offset, seq) in marks {
+        if row.as_ref().is_none_or(|(o, _)| *o != offset) {
+            let mut line = String::new();
+            let parsed = reader
+                .seek(SeekFrom::Start(offset))
+                .ok()
+                .and_then(|_| reader.read_line(&mut line).ok())
+                .and_then(|_| serde_json::from_str::<Value>(&line).ok())
+                .map(|v| parse_enriched_row(&v))
+                .unwrap_or_default();
+            row = Some((offset, parsed));
+        }
+        if let Some(em) = row.as_ref().and_then(|(_, events)| events.get(seq as usize)) {
+            out.push(em.clone());
+        }
+    }
+    out
+}
+
+/// RFC 3339 message timestamp as unix seconds (0 if unparseable).
+fn message_ts(timestamp: &str) -> f64 {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.timestamp_millis() as f64 / 1000.0)
+        .unwrap_or(0.0)
 }
 
 /// Map CWD to the Claude Code project directory name.
@@ -222,18 +792,28 @@ fn cwd_to_project_dir(cwd: &str) -> String {
         .replace('.', "-")
 }
 
-/// Build the path to a session's JSONL file.
-fn session_file_path(session_id: &str, cwd: &str) -> PathBuf {
-    let home = std::env::var("USERPROFILE")
+/// Claude Code transcript roots in search order: configured roots, then
+/// `<config dir>/projects` (honours `CLAUDE_CONFIG_DIR`), then the default
+/// `~/.claude/projects`.
+pub fn claude_transcript_roots(configured: &[String]) -> Vec<PathBuf> {
+    let default = std::env::var("USERPROFILE")
         .or_else(|_| std::env::var("HOME"))
-        .unwrap_or_else(|_| ".".to_string());
-    let project_dir = cwd_to_project_dir(cwd);
-
-    PathBuf::from(&home)
-        .join(".claude")
-        .join("projects")
-        .join(&project_dir)
-        .join(format!("{}.jsonl", session_id))
+        .ok()
+        .map(|home| PathBuf::from(home).join(".claude").join("projects"));
+    let mut roots: Vec<PathBuf> = Vec::new();
+    let candidates = configured
+        .iter()
+        .map(|r| r.trim())
+        .filter(|r| !r.is_empty())
+        .map(PathBuf::from)
+        .chain(crate::setup::claude_projects_dir())
+        .chain(default);
+    for root in candidates {
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+    roots
 }
 
 // ─── v1 parsing (unchanged) ─────────────────────────────
@@ -313,6 +893,80 @@ fn parse_message_content(message: &Value) -> (String, Vec<ChatToolUse>) {
     (texts.join("\n"), tools)
 }
 
+// ─── Full tool output ───────────────────────────────────
+
+/// Tools whose full result is kept beyond what the transcript message shows.
+const CAPTURED_TOOLS: &[&str] = &["Bash", "Grep"];
+
+/// A tool's full output, deflated.
+struct ToolOutput {
+    compressed: Vec<u8>,
+    /// Cut at `tool_output_bytes`.
+    truncated: bool,
+}
+
+impl ToolOutput {
+    fn new(text: &str, max: usize) -> Option<(Self, usize)> {
+        use flate2::{write::DeflateEncoder, Compression};
+        use std::io::Write;
+        let mut end = text.len().min(max);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut enc = DeflateEncoder::new(Vec::new(), Compression::fast());
+        enc.write_all(&text.as_bytes()[..end]).ok()?;
+        let out = Self { compressed: enc.finish().ok()?, truncated: end < text.len() };
+        Some((out, end))
+    }
+
+    fn text(&self) -> Option<String> {
+        let mut text = String::new();
+        flate2::read::DeflateDecoder::new(self.compressed.as_slice()).read_to_string(&mut text).ok()?;
+        Some(text)
+    }
+}
+
+/// Remember the names of the tools called in an assistant row, so their
+/// results can be told apart.
+fn note_tool_uses(row: &Value, names: &mut HashMap<String, String>) {
+    let Some(Value::Array(blocks)) = row.get("message").and_then(|m| m.get("content")) else { return };
+    for block in blocks.iter().filter(|b| b.get("type").and_then(|v| v.as_str()) == Some("tool_use")) {
+        let id = block.get("id").and_then(|v| v.as_str()).unwrap_or("");
+        let name = block.get("name").and_then(|v| v.as_str()).unwrap_or("");
+        if !id.is_empty() && CAPTURED_TOOLS.contains(&name) {
+            names.insert(id.to_string(), name.to_string());
+        }
+    }
+}
+
+/// Keep the full output of a Bash/Grep result: the row's `toolUseResult`
+/// has it whole (stdout/stderr, grep content or file list) where the
+/// message content may be shortened.
+fn capture_tool_output(entry: &mut SessionCache, row: &Value, em: &mut EnrichedMessage, max: usize) {
+    let ChatEvent::ToolResult { tool_use_id, content, full_bytes, .. } = &mut em.event else { return };
+    if max == 0 || entry.tool_names.remove(tool_use_id.as_str()).is_none() {
+        return;
+    }
+    let result = row.get("toolUseResult");
+    let field = |k: &str| result.and_then(|r| r.get(k)).and_then(|v| v.as_str()).unwrap_or("");
+    let text = if !field("stdout").is_empty() || !field("stderr").is_empty() {
+        [field("stdout"), field("stderr")].iter().filter(|s| !s.is_empty()).copied().collect::<Vec<_>>().join("\n")
+    } else if !field("content").is_empty() {
+        field("content").to_string()
+    } else if let Some(Value::Array(files)) = result.and_then(|r| r.get("filenames")) {
+        files.iter().filter_map(|f| f.as_str()).collect::<Vec<_>>().join("\n")
+    } else {
+        content.clone()
+    };
+    if text.is_empty() {
+        return;
+    }
+    if let Some((out, bytes)) = ToolOutput::new(&text, max) {
+        *full_bytes = Some(bytes);
+        entry.tool_outputs.insert(em.uuid.clone(), out);
+    }
+}
+
 // ─── v2 parsing (enriched) ──────────────────────────────
 
 /// Parse a single JSONL row into zero or more EnrichedMessages.
@@ -335,7 +989,8 @@ fn parse_enriched_row(row: &Value) -> Vec<EnrichedMessage> {
 
     let model = message.get("model").and_then(|v| v.as_str()).map(|s| s.to_string());
 
-    let usage = row.get("usage").and_then(|u| {
+    // Claude Code keeps it on the API message; older rows had it top-level
+    let usage = message.get("usage").or_else(|| row.get("usage")).and_then(|u| {
         let input = u.get("input_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
         let output = u.get("output_tokens").and_then(|v| v.as_u64()).unwrap_or(0);
         if input > 0 || output > 0 {
@@ -388,10 +1043,11 @@ fn parse_enriched_row(row: &Value) -> Vec<EnrichedMessage> {
                     "tool_use" => {
                         let name = block.get("name").and_then(|v| v.as_str()).unwrap_or("tool").to_string();
                         let input = block.get("input").cloned().unwrap_or(Value::Object(serde_json::Map::new()));
+                        let id = block.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
                         events.push(EnrichedMessage {
                             uuid: make_uuid(&uuid, seq),
                             timestamp: timestamp.clone(),
-                            event: ChatEvent::ToolCall { name, input },
+                            event: ChatEvent::ToolCall { name, input, id },
                             model: model.clone(),
                             usage: if seq == 0 { usage.clone() } else { None },
                         });
@@ -415,7 +1071,7 @@ fn parse_enriched_row(row: &Value) -> Vec<EnrichedMessage> {
                             events.push(EnrichedMessage {
                                 uuid: make_uuid(&uuid, seq),
                                 timestamp: timestamp.clone(),
-                                event: ChatEvent::ToolResult { tool_use_id, content: result_content, is_error },
+                                event: ChatEvent::ToolResult { tool_use_id, content: result_content, is_error, full_bytes: None },
                                 model: None,
                                 usage: None,
                             });
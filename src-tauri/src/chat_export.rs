@@ -0,0 +1,117 @@
+//! Chat export: a session's transcript rendered as a markdown document —
+//! prompts, the agent's replies, and each tool call with its (shortened)
+//! result — written to `<project>/.agent-desk/chats/`, or to Downloads
+//! when the project folder can't take it. Secrets go through `redact`
+//! like any other text meant to be shared.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::chat::{ChatEvent, EnrichedMessage};
+
+/// Characters of a tool result kept in the export.
+const RESULT_MAX_CHARS: usize = 2000;
+
+/// Characters of a tool call's input shown next to its name.
+const INPUT_MAX_CHARS: usize = 200;
+
+/// The session's transcript as markdown.
+pub fn render(session_id: &str, cwd: &str, model: &str, messages: &[EnrichedMessage]) -> String {
+    let short_sid = session_id.get(..8).unwrap_or(session_id);
+    let mut out = format!("# Chat \u{2014} {} \u{00b7} {}\n\n", crate::tray::project_name(cwd), short_sid);
+    out.push_str(&format!("- Exported: {}\n", chrono::Local::now().format("%Y-%m-%d %H:%M")));
+    if !cwd.is_empty() {
+        out.push_str(&format!("- Project: `{}`\n", cwd));
+    }
+    if !model.is_empty() {
+        out.push_str(&format!("- Model: {}\n", model));
+    }
+    out.push_str(&format!("- Messages: {}\n", messages.len()));
+
+    // A heading each time the speaker changes; tool calls are the agent's
+    let mut speaker = "";
+    for msg in messages {
+        let role = match &msg.event {
+            ChatEvent::Text { role, .. } if role == "user" => "You",
+            ChatEvent::Thinking { .. } => continue,
+            _ => "Agent",
+        };
+        if role != speaker {
+            out.push_str(&format!("\n## {}{}\n", role, time_suffix(&msg.timestamp)));
+            speaker = role;
+        }
+        match &msg.event {
+            ChatEvent::Text { content, .. } => {
+                out.push('\n');
+                out.push_str(content.trim());
+                out.push('\n');
+            }
+            ChatEvent::ToolCall { name, input, .. } => {
+                let summary = crate::messages::truncate(&input_summary(input), INPUT_MAX_CHARS);
+                if summary.is_empty() {
+                    out.push_str(&format!("\n**{}**\n", name));
+                } else {
+                    out.push_str(&format!("\n**{}** `{}`\n", name, summary.replace('`', "'")));
+                }
+            }
+            ChatEvent::ToolResult { content, is_error, .. } => {
+                let label = if *is_error { "Error" } else { "Result" };
+                let content = crate::messages::truncate(content.trim_end(), RESULT_MAX_CHARS);
+                out.push_str(&format!(
+                    "\n<details><summary>{}</summary>\n\n````\n{}\n````\n\n</details>\n",
+                    label, content
+                ));
+            }
+            ChatEvent::Thinking { .. } => {}
+        }
+    }
+    crate::redact::text(&out).into_owned()
+}
+
+/// ` · HH:MM` for an RFC 3339 transcript timestamp, in local time.
+fn time_suffix(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| format!(" \u{00b7} {}", t.with_timezone(&chrono::Local).format("%H:%M")))
+        .unwrap_or_default()
+}
+
+/// One line saying what a tool call did: its command, path or pattern,
+/// else the input as compact JSON.
+fn input_summary(input: &Value) -> String {
+    for key in ["command", "file_path", "path", "pattern", "url", "query", "description"] {
+        if let Some(s) = input.get(key).and_then(|v| v.as_str()) {
+            return s.lines().next().unwrap_or("").to_string();
+        }
+    }
+    match input {
+        Value::Object(map) if map.is_empty() => String::new(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Write an export of `session_id` and return its path: under the
+/// project's `.agent-desk/chats/`, else the user's Downloads folder.
+pub fn write(session_id: &str, cwd: &str, content: &str) -> std::io::Result<PathBuf> {
+    let short_sid = session_id.get(..8).unwrap_or(session_id);
+    let name = format!("chat-{}-{}.md", short_sid, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let project = (!cwd.is_empty() && Path::new(cwd).is_dir()).then(|| Path::new(cwd).join(".agent-desk").join("chats"));
+    if let Some(dir) = project {
+        match write_in(&dir, &name, content) {
+            Ok(path) => return Ok(path),
+            Err(e) => tracing::debug!("Chat export not written to {}: {}", dir.display(), e),
+        }
+    }
+    let home = std::env::var("USERPROFILE")
+        .or_else(|_| std::env::var("HOME"))
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::NotFound, "no project folder or home directory"))?;
+    write_in(&Path::new(&home).join("Downloads"), &name, content)
+}
+
+fn write_in(dir: &Path, name: &str, content: &str) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(name);
+    std::fs::write(&path, content)?;
+    Ok(path)
+}
@@ -1,7 +1,9 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 pub struct Config {
     #[serde(default)]
     pub telegram: TelegramConfig,
@@ -10,6 +12,10 @@ pub struct Config {
     #[serde(default)]
     pub wechat: WeChatConfig,
     #[serde(default)]
+    pub pushover: PushoverConfig,
+    #[serde(default)]
+    pub bark: BarkConfig,
+    #[serde(default)]
     pub manager: ManagerConfig,
     #[serde(default)]
     pub widget: WidgetConfig,
@@ -17,9 +23,29 @@ pub struct Config {
     pub general: GeneralConfig,
     #[serde(default)]
     pub island: IslandConfig,
+    #[serde(default)]
+    pub presence: PresenceConfig,
+    #[serde(default)]
+    pub messages: MessagesConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub github: GithubConfig,
+    #[serde(default)]
+    pub team: TeamConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub redact: RedactConfig,
+    #[serde(default)]
+    pub sensitive: SensitiveConfig,
+    #[serde(default)]
+    pub journal: JournalConfig,
+    #[serde(default)]
+    pub memory: MemoryConfig,
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
 pub struct TelegramConfig {
     #[serde(default)]
     pub enabled: bool,
@@ -29,9 +55,16 @@ pub struct TelegramConfig {
     pub chat_id: String,
     #[serde(default)]
     pub allowed_user_ids: Vec<i64>,
+    /// Language of event messages sent here (`en`, `zh`); empty =
+    /// `messages.language`.
+    #[serde(default)]
+    pub language: String,
+    /// Accept any TLS certificate (e.g. an intercepting corporate proxy).
+    #[serde(default)]
+    pub skip_tls_verify: bool,
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
 pub struct DingTalkConfig {
     #[serde(default)]
     pub enabled: bool,
@@ -41,9 +74,30 @@ pub struct DingTalkConfig {
     pub access_token: String,
     #[serde(default)]
     pub secret: String,
+    /// Message type per event — `stop`, `notification`, `limit_reached`,
+    /// `permission_request`, `away`, `external`, or `default` for the rest:
+    /// `text`, `markdown` or `actionCard`.
+    #[serde(default)]
+    pub msgtypes: HashMap<String, String>,
+    /// Language of event messages sent here (`en`, `zh`); empty =
+    /// `messages.language`.
+    #[serde(default)]
+    pub language: String,
+    #[serde(default)]
+    pub skip_tls_verify: bool,
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
+impl DingTalkConfig {
+    pub fn msgtype(&self, event: &str) -> &str {
+        self.msgtypes
+            .get(event)
+            .or_else(|| self.msgtypes.get("default"))
+            .map(String::as_str)
+            .unwrap_or("text")
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
 pub struct WeChatConfig {
     #[serde(default)]
     pub enabled: bool,
@@ -53,35 +107,335 @@ pub struct WeChatConfig {
     pub pushplus_token: String,
     #[serde(default)]
     pub serverchan_sendkey: String,
+    /// WeCom group robot webhook key (the `key=` part of its URL).
+    #[serde(default)]
+    pub wecom_key: String,
+    /// Send WeCom messages as plain text instead of markdown.
+    #[serde(default)]
+    pub wecom_text: bool,
+    /// Language of event messages sent here (`en`, `zh`); empty =
+    /// `messages.language`.
+    #[serde(default)]
+    pub language: String,
+    #[serde(default)]
+    pub skip_tls_verify: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+pub struct PushoverConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Application API token.
+    #[serde(default)]
+    pub token: String,
+    /// User or group key.
+    #[serde(default)]
+    pub user: String,
+    /// Priority (-2..2) per event, or `default` for the rest. Unset:
+    /// 1 (high) for permission requests and usage limits, else 0.
+    #[serde(default)]
+    pub priorities: HashMap<String, i8>,
+    /// Language of event messages sent here (`en`, `zh`); empty =
+    /// `messages.language`.
+    #[serde(default)]
+    pub language: String,
+    #[serde(default)]
+    pub skip_tls_verify: bool,
+}
+
+impl PushoverConfig {
+    pub fn priority(&self, event: &str) -> i8 {
+        self.priorities
+            .get(event)
+            .or_else(|| self.priorities.get("default"))
+            .copied()
+            .unwrap_or(match event {
+                "permission_request" | "limit_reached" => 1,
+                _ => 0,
+            })
+            .clamp(-2, 2)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+pub struct BarkConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Bark server (empty = the public `https://api.day.app`).
+    #[serde(default)]
+    pub server_url: String,
+    #[serde(default)]
+    pub device_key: String,
+    /// Interruption level per event, or `default` for the rest: `critical`,
+    /// `timeSensitive`, `active` or `passive`. Unset: `critical` for
+    /// permission requests, else `active`.
+    #[serde(default)]
+    pub levels: HashMap<String, String>,
+    /// Language of event messages sent here (`en`, `zh`); empty =
+    /// `messages.language`.
+    #[serde(default)]
+    pub language: String,
+    #[serde(default)]
+    pub skip_tls_verify: bool,
+}
+
+impl BarkConfig {
+    pub fn level(&self, event: &str) -> &str {
+        self.levels
+            .get(event)
+            .or_else(|| self.levels.get("default"))
+            .map(String::as_str)
+            .unwrap_or(if event == "permission_request" { "critical" } else { "active" })
+    }
+}
+
+/// Secrets replaced in remote messages, recordings, team reports and the
+/// status page; see redact.rs.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct RedactConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Built-in patterns: API keys and tokens, private keys, emails.
+    #[serde(default = "default_true")]
+    pub builtin: bool,
+    /// Extra regular expressions, e.g. internal host names. With a group
+    /// named `secret`, only that group is replaced.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    #[serde(default = "default_redact_replacement")]
+    pub replacement: String,
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            builtin: true,
+            patterns: Vec::new(),
+            replacement: default_redact_replacement(),
+        }
+    }
+}
+
+fn default_redact_replacement() -> String { "[REDACTED]".to_string() }
+
+/// Tool calls to watch for whatever the agent's own permissions say; see
+/// sensitive.rs. Patterns are regular expressions matched against a Bash
+/// command, or the tool name and its input as JSON.
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+pub struct SensitiveConfig {
+    /// A matching call raises a `sensitive_command` event.
+    #[serde(default)]
+    pub alert: Vec<String>,
+    /// Like `alert`, and the call must be confirmed in Agent Desk (through
+    /// the pre-tool-check hook), whatever session rules or auto-approve
+    /// windows say.
+    #[serde(default)]
+    pub confirm: Vec<String>,
+}
+
+/// Per-project markdown journal of ended sessions; see journal.rs.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct JournalConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Journal file, relative to the project directory unless absolute;
+    /// `{project}` is the project folder's name.
+    #[serde(default = "default_journal_path")]
+    pub path: String,
+    /// The agent's last message is cut to this many characters (0 = no
+    /// limit).
+    #[serde(default = "default_journal_summary_max_chars")]
+    pub summary_max_chars: usize,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_journal_path(),
+            summary_max_chars: default_journal_summary_max_chars(),
+        }
+    }
+}
+
+fn default_journal_path() -> String { ".agent-desk/journal.md".to_string() }
+fn default_journal_summary_max_chars() -> usize { 600 }
+
+/// Ceilings for the in-memory caches; see memory.rs.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct MemoryConfig {
+    /// Parsed transcripts of all sessions together, in MB (0 = no
+    /// ceiling).
+    #[serde(default = "default_chat_cache_mb")]
+    pub chat_cache_mb: u64,
+    /// Hook dedup entries (0 = no ceiling).
+    #[serde(default = "default_dedup_max_entries")]
+    pub dedup_max_entries: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            chat_cache_mb: default_chat_cache_mb(),
+            dedup_max_entries: default_dedup_max_entries(),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+fn default_chat_cache_mb() -> u64 { 128 }
+fn default_dedup_max_entries() -> usize { 10000 }
+
+/// Outbound HTTP settings for remote channels, GitHub polling and team mode.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct NetworkConfig {
+    /// `http://host:port` (empty = system/env proxy).
+    #[serde(default)]
+    pub proxy: String,
+    /// PEM file with an extra root certificate to trust (e.g. a corporate CA).
+    #[serde(default)]
+    pub ca_cert: String,
+    /// Seconds to wait for a connection, for every outbound request.
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Seconds a remote channel send may take in all.
+    #[serde(default = "default_remote_timeout_secs")]
+    pub remote_timeout_secs: u64,
+    /// Extra attempts after a failed remote channel send.
+    #[serde(default = "default_remote_retries")]
+    pub remote_retries: u32,
+    /// Remote dispatches sending at once; more wait their turn.
+    #[serde(default = "default_remote_max_concurrent")]
+    pub remote_max_concurrent: usize,
+    /// Remote dispatches waiting or sending before new ones are dropped.
+    #[serde(default = "default_remote_max_queued")]
+    pub remote_max_queued: usize,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: String::new(),
+            ca_cert: String::new(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            remote_timeout_secs: default_remote_timeout_secs(),
+            remote_retries: default_remote_retries(),
+            remote_max_concurrent: default_remote_max_concurrent(),
+            remote_max_queued: default_remote_max_queued(),
+        }
+    }
+}
+
+fn default_connect_timeout_secs() -> u64 { 5 }
+fn default_remote_timeout_secs() -> u64 { 10 }
+fn default_remote_retries() -> u32 { 1 }
+fn default_remote_max_concurrent() -> usize { 4 }
+fn default_remote_max_queued() -> usize { 100 }
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 pub struct ManagerConfig {
     #[serde(default = "default_port")]
     pub port: u16,
-    #[serde(default = "default_events_file")]
+    /// Address the server listens on and hooks connect to: `127.0.0.1`,
+    /// `::1` where loopback is IPv6-only, or a name such as `localhost`.
+    /// `lan` and `status_page` listen on every interface instead.
+    #[serde(default = "default_host")]
+    pub host: String,
+    /// Relay hooks through the persistent hook daemon on `port + 1`. Off,
+    /// no daemon runs and hook commands carry `--no-daemon`, so each hook
+    /// posts straight to the server.
+    #[serde(default = "default_true")]
+    pub use_hook_daemon: bool,
+    /// Empty = `events.jsonl` in the per-user data dir (see `data_dir`).
+    #[serde(default)]
     pub events_file: String,
     #[serde(default = "default_max_events_age")]
     pub max_events_age: u64,
+    /// Maximum events kept after compaction (0 = unlimited).
+    #[serde(default = "default_max_events_count")]
+    pub max_events_count: usize,
+    /// Maximum events.jsonl size in bytes after compaction (0 = unlimited).
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
     #[serde(default = "default_true")]
     pub open_browser: bool,
+    /// Browser origins allowed to call the API. An entry without a port
+    /// (e.g. `http://localhost`) also matches that host on any port.
+    #[serde(default = "default_allowed_origins")]
+    pub allowed_origins: Vec<String>,
+    /// Dev escape hatch: accept any origin (the pre-allow-list behaviour).
+    #[serde(default)]
+    pub cors_permissive: bool,
+    /// Requests slower than this (ms) are logged at warn level.
+    #[serde(default = "default_slow_request_ms")]
+    pub slow_request_ms: u64,
+    /// Directory with a custom frontend served at `/ui/` (empty = built-in island page).
+    /// Files missing from it fall back to the built-in page.
+    #[serde(default)]
+    pub ui_dir: String,
+    /// Listen on all interfaces so other machines can open `/ui/`.
+    /// Requires `lan_token`; non-loopback requests must present it.
+    #[serde(default)]
+    pub lan: bool,
+    #[serde(default)]
+    pub lan_token: String,
+    /// Hook requests per second allowed per session (0 = no limit).
+    #[serde(default = "default_rate_limit_per_sec")]
+    pub rate_limit_per_sec: f64,
+    /// Requests a session may send back to back before the rate applies.
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
+    /// SSE messages buffered per client; a client further behind misses
+    /// messages and is told to resync.
+    #[serde(default = "default_sse_capacity")]
+    pub sse_capacity: usize,
+    /// Serve a read-only status page at `/status`, reachable from other
+    /// machines without `lan_token`.
+    #[serde(default)]
+    pub status_page: bool,
+    /// List project names (CWD basenames) on the status page.
+    #[serde(default)]
+    pub status_page_projects: bool,
+    /// Base URL other devices reach this app at (e.g.
+    /// `http://192.168.1.20:15924`), used for permission buttons in remote
+    /// messages. Needs `lan`.
+    #[serde(default)]
+    pub public_url: String,
 }
 
 impl Default for ManagerConfig {
     fn default() -> Self {
         Self {
             port: 15924,
-            events_file: default_events_file(),
+            host: default_host(),
+            use_hook_daemon: true,
+            events_file: String::new(),
             max_events_age: 86400,
+            max_events_count: default_max_events_count(),
+            max_file_size: default_max_file_size(),
             open_browser: true,
+            allowed_origins: default_allowed_origins(),
+            cors_permissive: false,
+            slow_request_ms: default_slow_request_ms(),
+            ui_dir: String::new(),
+            lan: false,
+            lan_token: String::new(),
+            rate_limit_per_sec: default_rate_limit_per_sec(),
+            rate_limit_burst: default_rate_limit_burst(),
+            sse_capacity: default_sse_capacity(),
+            status_page: false,
+            status_page_projects: false,
+            public_url: String::new(),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// Desktop widget: an always-visible mini session list; see widget.rs.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 pub struct WidgetConfig {
-    #[serde(default = "default_true")]
+    #[serde(default)]
     pub enabled: bool,
+    /// Keep the widget above other windows.
     #[serde(default = "default_true")]
     pub on_top: bool,
 }
@@ -89,36 +443,445 @@ pub struct WidgetConfig {
 impl Default for WidgetConfig {
     fn default() -> Self {
         Self {
-            enabled: true,
+            enabled: false,
             on_top: true,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 pub struct GeneralConfig {
-    #[serde(default = "default_sessions_file")]
+    /// Empty = `sessions.json` in the per-user data dir (see `data_dir`).
+    #[serde(default)]
     pub sessions_file: String,
+    /// Override for the per-user state directory. Empty = platform default.
+    #[serde(default)]
+    pub data_dir: String,
     #[serde(default = "default_claude_cli")]
     pub claude_cli: String,
     #[serde(default)]
     pub git_bash_path: String,
     #[serde(default = "default_session_ttl")]
     pub session_ttl: u64,
+    /// An active session with no hook activity for this long (seconds)
+    /// shows up in the inbox as stalled.
+    #[serde(default = "default_stall_secs")]
+    pub stall_secs: u64,
+    /// Chat messages kept in memory per session (0 = all); older ones are
+    /// re-read from the transcript on demand.
+    #[serde(default = "default_chat_cache_messages")]
+    pub chat_cache_messages: usize,
+    /// Bytes of full Bash/Grep output kept per tool result for
+    /// `/api/chat/tool-result` (0 = off).
+    #[serde(default = "default_tool_output_bytes")]
+    pub tool_output_bytes: usize,
+    /// Extra transcript directories per adapter (e.g. `claude_code`),
+    /// searched before the adapter's default location.
+    #[serde(default)]
+    pub transcript_roots: HashMap<String, Vec<String>>,
+    /// Send usage-limit notices to the remote channels too.
+    #[serde(default = "default_true")]
+    pub limit_notify_remote: bool,
+    /// Send permission requests to the remote channels too.
+    #[serde(default)]
+    pub permission_notify_remote: bool,
+    /// Warn when an agent runs this long without any hook event (0 = off).
+    #[serde(default = "default_hook_silence_secs")]
+    pub hook_silence_secs: u64,
+    /// Seconds between process scans while no agent is running and no
+    /// session is active; a hook event scans at once (minimum 5).
+    #[serde(default = "default_idle_scan_secs")]
+    pub idle_scan_secs: u64,
+    /// Also install a PreToolUse hook in observe mode: it reports the tool
+    /// about to run and returns at once, never holding up the terminal.
+    #[serde(default)]
+    pub observe_tools: bool,
+    /// Don't let startup rewrite the hooks in `~/.claude/settings.json`:
+    /// log the changes instead, for review at `GET /api/setup/plan` and
+    /// `POST /api/setup/apply`.
+    #[serde(default)]
+    pub hooks_dry_run: bool,
+    /// Backups of each Claude Code settings file kept in the data dir,
+    /// taken before the app rewrites it (0 = none).
+    #[serde(default = "default_settings_backups")]
+    pub settings_backups: usize,
+    /// When Windows refuses to bring a terminal to the front, fall back to
+    /// the synthetic Alt key press the app used before.
+    #[serde(default)]
+    pub focus_alt_key_fallback: bool,
+    /// Link added to remote messages about a session, in LAN mode: `web`
+    /// (the session in the web UI at `manager.public_url`), `scheme`
+    /// (`agentdesk://focus/…`, focuses its terminal) or `off`.
+    #[serde(default = "default_remote_link")]
+    pub remote_link: String,
+    /// Order of `/api/sessions` (and so the island and tray), by these keys
+    /// in turn: `pinned`, `status` (waiting, working, capped, stopped),
+    /// `project`, `started`. Empty = scan order.
+    #[serde(default = "default_session_sort")]
+    pub session_sort: Vec<String>,
+    /// Log filter in `RUST_LOG` syntax, written by `POST /api/logs/level`.
+    /// Empty = `info`; `RUST_LOG` overrides it.
+    #[serde(default)]
+    pub log_filter: String,
+    /// One-click replies the island and tray offer to waiting sessions.
+    #[serde(default = "default_quick_replies")]
+    pub quick_replies: Vec<QuickReply>,
+    /// Named session filters offered as quick filters in the island.
+    #[serde(default = "default_views")]
+    pub views: Vec<SessionView>,
+    /// How long sessions stay in sessions.json, by status.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Globs of project folders never monitored, e.g. `D:/scratch/**` or
+    /// `~/mnt/*`; see ignore.rs.
+    #[serde(default)]
+    pub ignore_paths: Vec<String>,
+}
+
+/// Seconds a session is kept after its last update, per status (0 = forever).
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct RetentionConfig {
+    #[serde(default = "default_retention_ended")]
+    pub ended: u64,
+    #[serde(default = "default_retention_idle")]
+    pub idle: u64,
+    #[serde(default = "default_retention_idle")]
+    pub stopped: u64,
+    #[serde(default)]
+    pub waiting: u64,
+    #[serde(default)]
+    pub active: u64,
+    #[serde(default = "default_retention_idle")]
+    pub unknown: u64,
+}
+
+impl RetentionConfig {
+    pub fn for_status(&self, status: &crate::protocol::SessionStatus) -> u64 {
+        use crate::protocol::SessionStatus;
+        match status {
+            SessionStatus::Ended => self.ended,
+            SessionStatus::Idle => self.idle,
+            SessionStatus::Stopped => self.stopped,
+            SessionStatus::Waiting => self.waiting,
+            SessionStatus::Active => self.active,
+            SessionStatus::Unknown => self.unknown,
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            ended: default_retention_ended(),
+            idle: default_retention_idle(),
+            stopped: default_retention_idle(),
+            waiting: 0,
+            active: 0,
+            unknown: default_retention_idle(),
+        }
+    }
+}
+
+fn default_retention_ended() -> u64 { 86400 }
+fn default_retention_idle() -> u64 { 7 * 86400 }
+
+/// A canned response sent to a session like a typed chat message.
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct QuickReply {
+    pub id: String,
+    /// Button / menu text; defaults to `text`.
+    #[serde(default)]
+    pub label: String,
+    pub text: String,
+}
+
+/// Which sessions `/api/sessions` returns. Each field is a comma-separated
+/// list of alternatives; empty fields match everything.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, JsonSchema)]
+pub struct SessionFilter {
+    /// `active`, `waiting`, `stopped`, or `limited` (usage limit hit).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub status: String,
+    /// Case-insensitive substring of the CWD.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub project: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub agent_type: String,
+    /// Case-insensitive substring of the project name or last message.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+}
+
+impl SessionFilter {
+    /// Whether a merged session entry (see `scan_and_merge`) passes.
+    pub fn matches(&self, proc: &serde_json::Value) -> bool {
+        let field = |k: &str| proc.get(k).and_then(|v| v.as_str()).unwrap_or("");
+        let any = |list: &str, f: &dyn Fn(&str) -> bool| {
+            list.split(',').map(str::trim).filter(|s| !s.is_empty()).any(f)
+        };
+        let limited = field("notification_type") == "limit_reached";
+        let cwd = field("cwd").to_lowercase();
+        let label = format!(
+            "{}\n{}",
+            cwd.rsplit(['/', '\\']).next().unwrap_or(""),
+            field("last_message").to_lowercase(),
+        );
+        (self.status.is_empty() || any(&self.status, &|s| s == field("status") || (s == "limited" && limited)))
+            && (self.project.is_empty() || any(&self.project, &|p| cwd.contains(&p.to_lowercase())))
+            && (self.agent_type.is_empty() || any(&self.agent_type, &|a| a.eq_ignore_ascii_case(field("agent_type"))))
+            && (self.label.is_empty() || any(&self.label, &|l| label.contains(&l.to_lowercase())))
+    }
+}
+
+/// A saved, named `SessionFilter` (`GET /api/views`).
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct SessionView {
+    pub name: String,
+    #[serde(flatten)]
+    pub filter: SessionFilter,
 }
 
 impl Default for GeneralConfig {
     fn default() -> Self {
         Self {
-            sessions_file: default_sessions_file(),
+            sessions_file: String::new(),
+            data_dir: String::new(),
             claude_cli: "claude".into(),
             git_bash_path: String::new(),
             session_ttl: 86400,
+            stall_secs: default_stall_secs(),
+            chat_cache_messages: default_chat_cache_messages(),
+            tool_output_bytes: default_tool_output_bytes(),
+            transcript_roots: HashMap::new(),
+            limit_notify_remote: true,
+            permission_notify_remote: false,
+            hook_silence_secs: default_hook_silence_secs(),
+            idle_scan_secs: 30,
+            observe_tools: false,
+            hooks_dry_run: false,
+            settings_backups: 10,
+            focus_alt_key_fallback: false,
+            log_filter: String::new(),
+            remote_link: default_remote_link(),
+            session_sort: default_session_sort(),
+            quick_replies: default_quick_replies(),
+            views: default_views(),
+            retention: RetentionConfig::default(),
+            ignore_paths: Vec::new(),
+        }
+    }
+}
+
+/// Away detection (keyboard/mouse idle time) and deferred notifications.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct PresenceConfig {
+    /// Idle seconds after which the user counts as away: toasts and sounds
+    /// are held and summarized on return. 0 = never away.
+    #[serde(default = "default_away_after_secs")]
+    pub away_after_secs: u64,
+    /// When > 0, remote channels only receive messages once the user has
+    /// been away this long (backlog is sent at that point). 0 = remote
+    /// channels receive every event, as before.
+    #[serde(default)]
+    pub escalate_after_secs: u64,
+    /// Attention batches: when > 0, toasts other than permission requests
+    /// are held and shown together every this many minutes, the island
+    /// showing how many are waiting. 0 = toast at once.
+    #[serde(default)]
+    pub batch_every_mins: u64,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            away_after_secs: default_away_after_secs(),
+            escalate_after_secs: 0,
+            batch_every_mins: 0,
+        }
+    }
+}
+
+fn default_away_after_secs() -> u64 { 300 }
+
+/// Text of event messages (event log, tray, remote channels) and toasts;
+/// see messages.rs.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct MessagesConfig {
+    /// Built-in templates: `en`, `zh`, or empty for English messages with
+    /// Chinese toasts.
+    #[serde(default)]
+    pub language: String,
+    /// Overrides keyed by event kind (`stop`, `confirm`, `idle`, `notice`,
+    /// `start`, `end`, `limit`, `permission`, `other`), with `.title` or
+    /// `.body` for the toast, e.g. `stop.title: "Done: {project}"`.
+    #[serde(default)]
+    pub templates: HashMap<String, String>,
+    /// `{message}` is cut to this many characters in event messages.
+    #[serde(default = "default_message_max_chars")]
+    pub message_max_chars: usize,
+    /// `{message}` is cut to this many characters in toast bodies.
+    #[serde(default = "default_toast_max_chars")]
+    pub toast_max_chars: usize,
+    /// Which part of a long `{message}` a toast keeps: `head`, `tail`, or
+    /// `smart` (the last whole sentences).
+    #[serde(default = "default_toast_truncate")]
+    pub toast_truncate: String,
+}
+
+impl Default for MessagesConfig {
+    fn default() -> Self {
+        Self {
+            language: String::new(),
+            templates: HashMap::new(),
+            message_max_chars: default_message_max_chars(),
+            toast_max_chars: default_toast_max_chars(),
+            toast_truncate: default_toast_truncate(),
+        }
+    }
+}
+
+fn default_message_max_chars() -> usize { 300 }
+fn default_toast_max_chars() -> usize { 200 }
+fn default_toast_truncate() -> String { "smart".to_string() }
+
+/// Token/cost budgets per session and per project.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct BudgetConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Limit applied to every session on its own.
+    #[serde(default)]
+    pub session: BudgetLimit,
+    /// Limits per project, keyed by project folder name or full path; a
+    /// project's usage is the sum over its tracked sessions.
+    #[serde(default)]
+    pub projects: HashMap<String, BudgetLimit>,
+    /// Percentage of a limit that raises the early warning.
+    #[serde(default = "default_budget_warn_percent")]
+    pub warn_percent: u32,
+    /// Deny further permission requests of a session over its limit until
+    /// the alert is acknowledged.
+    #[serde(default)]
+    pub pause_at_limit: bool,
+    /// USD per million tokens by model-name prefix, for cost limits.
+    #[serde(default)]
+    pub prices: HashMap<String, ModelPrice>,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            session: BudgetLimit::default(),
+            projects: HashMap::new(),
+            warn_percent: default_budget_warn_percent(),
+            pause_at_limit: false,
+            prices: HashMap::new(),
+        }
+    }
+}
+
+fn default_budget_warn_percent() -> u32 { 80 }
+
+/// A token and/or cost ceiling (0 = no limit).
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+pub struct BudgetLimit {
+    #[serde(default)]
+    pub tokens: u64,
+    /// USD, priced with `budget.prices`.
+    #[serde(default)]
+    pub cost: f64,
+}
+
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+pub struct ModelPrice {
+    #[serde(default)]
+    pub input: f64,
+    #[serde(default)]
+    pub output: f64,
+}
+
+/// GitHub Actions adapter: in-progress workflow runs of agents show up as sessions.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct GithubConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Personal access token with `actions:read` on the repos.
+    #[serde(default)]
+    pub token: String,
+    /// `owner/repo` entries to poll.
+    #[serde(default)]
+    pub repos: Vec<String>,
+    #[serde(default = "default_github_poll_secs")]
+    pub poll_secs: u64,
+    /// A run counts as an agent run when its workflow name, title or actor
+    /// contains one of these (case-insensitive).
+    #[serde(default = "default_github_agent_match")]
+    pub agent_match: Vec<String>,
+}
+
+impl Default for GithubConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token: String::new(),
+            repos: Vec::new(),
+            poll_secs: default_github_poll_secs(),
+            agent_match: default_github_agent_match(),
+        }
+    }
+}
+
+fn default_github_poll_secs() -> u64 { 60 }
+
+/// Team mode: share anonymized agent states with teammates (see `team.rs`).
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct TeamConfig {
+    /// Accept teammates' reports and show the island's team tab.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Name teammates see (empty = host name).
+    #[serde(default)]
+    pub name: String,
+    /// Where to post this instance's reports, e.g. a teammate's
+    /// `http://host:15924/api/team/report` (empty = receive only).
+    #[serde(default)]
+    pub sink_url: String,
+    /// Sent as a bearer token; the sink's `manager.lan_token` for agent-desk.
+    #[serde(default)]
+    pub token: String,
+    /// Include project names (last CWD component) in reports.
+    #[serde(default)]
+    pub share_projects: bool,
+    /// Resend an unchanged report this often (seconds).
+    #[serde(default = "default_team_heartbeat_secs")]
+    pub heartbeat_secs: u64,
+    /// Drop a teammate not heard from for this long (seconds).
+    #[serde(default = "default_team_stale_secs")]
+    pub stale_secs: u64,
+}
+
+impl Default for TeamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            name: String::new(),
+            sink_url: String::new(),
+            token: String::new(),
+            share_projects: false,
+            heartbeat_secs: default_team_heartbeat_secs(),
+            stale_secs: default_team_stale_secs(),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+fn default_team_heartbeat_secs() -> u64 { 60 }
+fn default_team_stale_secs() -> u64 { 180 }
+fn default_github_agent_match() -> Vec<String> {
+    ["claude", "codex", "copilot", "agent"].iter().map(|s| s.to_string()).collect()
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct IslandConfig {
     // Sizes (pixels)
     #[serde(default = "default_pill_width")]
@@ -137,6 +900,10 @@ pub struct IslandConfig {
     pub hover_expand_ms: u64,
     #[serde(default = "default_hover_collapse_ms")]
     pub hover_collapse_ms: u64,
+    /// Hovering a session row this long raises and flashes its terminal
+    /// without taking focus; 0 turns it off.
+    #[serde(default = "default_hover_peek_ms")]
+    pub hover_peek_ms: u64,
 
     // Colors (CSS format)
     #[serde(default = "default_background")]
@@ -147,12 +914,20 @@ pub struct IslandConfig {
     pub color_ready: String,
     #[serde(default = "default_color_permission")]
     pub color_permission: String,
+    /// Marks permission requests from pinned sessions and
+    /// `priority_paths` projects.
+    #[serde(default = "default_color_permission_priority")]
+    pub color_permission_priority: String,
     #[serde(default = "default_color_notification")]
     pub color_notification: String,
 
     // Hotkey
     #[serde(default = "default_hotkey")]
     pub hotkey: String,
+    /// Global shortcuts that drive the island panel, by action:
+    /// `next_item`, `prev_item`, `select`, `approve`, `deny`.
+    #[serde(default)]
+    pub nav_hotkeys: HashMap<String, String>,
 
     // Transparency
     #[serde(default = "default_transparency")]
@@ -169,6 +944,60 @@ pub struct IslandConfig {
     pub sound_notification: String,
     #[serde(default = "default_sound_permission")]
     pub sound_permission: String,
+    /// Sound for permission requests from pinned sessions and
+    /// `priority_paths` projects.
+    #[serde(default = "default_sound_permission_priority")]
+    pub sound_permission_priority: String,
+    /// Projects (globs, as `general.ignore_paths`) whose permission
+    /// requests go ahead of the others, like those of pinned sessions.
+    #[serde(default)]
+    pub priority_paths: Vec<String>,
+    /// Skip the toast and sound when the session's terminal is already the
+    /// foreground window.
+    #[serde(default = "default_true")]
+    pub suppress_when_focused: bool,
+
+    /// Pulse the tray icon while agents are working.
+    #[serde(default = "default_true")]
+    pub tray_animation: bool,
+
+    /// Start with the island hidden (tray + notifications only). Otherwise
+    /// the island comes back the way it was at the last exit.
+    #[serde(default)]
+    pub start_hidden: bool,
+
+    /// Folder with custom tray icons (`<state>[-light|-dark].png|.ico`);
+    /// empty = `tray-icons/` next to config.yaml.
+    #[serde(default)]
+    pub tray_icon_dir: String,
+
+    /// Give the island a taskbar button badged with pending permission
+    /// requests + sessions waiting on input.
+    #[serde(default)]
+    pub taskbar_badge: bool,
+
+    /// Flash the taskbar button when a permission request arrives while the
+    /// island is hidden (needs `taskbar_badge`).
+    #[serde(default = "default_true")]
+    pub taskbar_flash: bool,
+
+    /// Fill the Windows jump list (right-click on the taskbar button) with
+    /// recent sessions and tasks: show island, open dashboard, pause
+    /// notifications.
+    #[serde(default = "default_true")]
+    pub jump_list: bool,
+
+    /// Events that show the island when it is hidden: `permission`,
+    /// `stop`, `notification`, `session_start`.
+    #[serde(default = "default_auto_show")]
+    pub auto_show: Vec<String>,
+    /// Events that expand the island to its panel (same names).
+    #[serde(default = "default_auto_expand")]
+    pub auto_expand: Vec<String>,
+    /// Skip auto-show/expand while Windows holds notifications back
+    /// (full-screen app, presentation mode, quiet hours).
+    #[serde(default = "default_true")]
+    pub respect_dnd: bool,
 
     // Autostart
     #[serde(default)]
@@ -177,6 +1006,16 @@ pub struct IslandConfig {
     // Permission timeout (seconds)
     #[serde(default = "default_permission_timeout")]
     pub permission_timeout_secs: u64,
+
+    /// A request identical to one answered this recently (same session,
+    /// tool and input) gets the same answer without asking (0 = off).
+    #[serde(default = "default_permission_repeat_secs")]
+    pub permission_repeat_secs: u64,
+
+    /// "Allow for session" / "always allow" on Edit/Write requests covers
+    /// only the file (or the paths picked), never the whole tool.
+    #[serde(default)]
+    pub per_file_edit_approval: bool,
 }
 
 impl Default for IslandConfig {
@@ -189,25 +1028,43 @@ impl Default for IslandConfig {
             auto_collapse_ms: 3000,
             hover_expand_ms: 400,
             hover_collapse_ms: 300,
+            hover_peek_ms: 600,
             background: "#000000".into(),
             color_active: "#D97857".into(),
             color_ready: "#66BF73".into(),
             color_permission: "#6699FF".into(),
+            color_permission_priority: "#FF5C5C".into(),
             color_notification: "#FFB300".into(),
             hotkey: "Alt+D".into(),
+            nav_hotkeys: HashMap::new(),
             transparency: "off".into(),
             opacity: 0.75,
             sound_enabled: true,
             sound_stop: "asterisk".into(),
             sound_notification: "exclamation".into(),
             sound_permission: "question".into(),
+            sound_permission_priority: "hand".into(),
+            priority_paths: Vec::new(),
+            suppress_when_focused: true,
+            tray_animation: true,
+            start_hidden: false,
+            tray_icon_dir: String::new(),
+            taskbar_badge: false,
+            taskbar_flash: true,
+            jump_list: true,
+            auto_show: default_auto_show(),
+            auto_expand: default_auto_expand(),
+            respect_dnd: true,
             autostart: false,
             permission_timeout_secs: 600,
+            permission_repeat_secs: default_permission_repeat_secs(),
+            per_file_edit_approval: false,
         }
     }
 }
 
 fn default_permission_timeout() -> u64 { 600 }
+fn default_permission_repeat_secs() -> u64 { 120 }
 fn default_hotkey() -> String { "Alt+D".into() }
 fn default_transparency() -> String { "off".into() }
 fn default_opacity() -> f64 { 0.75 }
@@ -218,20 +1075,87 @@ fn default_panel_height() -> u32 { 320 }
 fn default_auto_collapse_ms() -> u64 { 3000 }
 fn default_hover_expand_ms() -> u64 { 400 }
 fn default_hover_collapse_ms() -> u64 { 300 }
+fn default_hover_peek_ms() -> u64 { 600 }
 fn default_background() -> String { "#000000".into() }
 fn default_color_active() -> String { "#D97857".into() }
 fn default_color_ready() -> String { "#66BF73".into() }
 fn default_color_permission() -> String { "#6699FF".into() }
+fn default_color_permission_priority() -> String { "#FF5C5C".into() }
 fn default_color_notification() -> String { "#FFB300".into() }
 fn default_sound_stop() -> String { "asterisk".into() }
 fn default_sound_notification() -> String { "exclamation".into() }
 fn default_sound_permission() -> String { "question".into() }
+fn default_sound_permission_priority() -> String { "hand".into() }
+fn default_auto_show() -> Vec<String> { vec!["permission".into()] }
+fn default_auto_expand() -> Vec<String> { vec!["permission".into(), "stop".into(), "session_start".into()] }
 
 fn default_port() -> u16 { 15924 }
+fn default_host() -> String { "127.0.0.1".into() }
 fn default_true() -> bool { true }
 fn default_max_events_age() -> u64 { 86400 }
+fn default_max_events_count() -> usize { 5000 }
+fn default_max_file_size() -> u64 { 10 * 1024 * 1024 }
+fn default_slow_request_ms() -> u64 { 500 }
+fn default_rate_limit_per_sec() -> f64 { 20.0 }
+fn default_rate_limit_burst() -> u32 { 100 }
+fn default_sse_capacity() -> usize { 1024 }
+fn default_allowed_origins() -> Vec<String> {
+    vec![
+        "tauri://localhost".into(),
+        "http://tauri.localhost".into(),
+        "https://tauri.localhost".into(),
+        "http://localhost".into(),
+        "http://127.0.0.1".into(),
+    ]
+}
 fn default_session_ttl() -> u64 { 86400 }
+fn default_stall_secs() -> u64 { 600 }
+fn default_chat_cache_messages() -> usize { 2000 }
+fn default_quick_replies() -> Vec<QuickReply> {
+    [
+        ("continue", "Continue", "continue"),
+        ("proceed", "Looks good", "looks good, proceed"),
+        ("summarize", "Summarize", "stop and summarize what you've done so far"),
+    ]
+    .iter()
+    .map(|(id, label, text)| QuickReply { id: id.to_string(), label: label.to_string(), text: text.to_string() })
+    .collect()
+}
+fn default_hook_silence_secs() -> u64 { 300 }
+fn default_idle_scan_secs() -> u64 { 30 }
+fn default_tool_output_bytes() -> usize { 256 * 1024 }
+fn default_remote_link() -> String { "web".to_string() }
+fn default_session_sort() -> Vec<String> {
+    ["pinned", "status", "project", "started"].map(String::from).to_vec()
+}
+fn default_views() -> Vec<SessionView> {
+    vec![SessionView {
+        name: "Waiting only".into(),
+        filter: SessionFilter { status: "waiting".into(), ..Default::default() },
+    }]
+}
 fn default_claude_cli() -> String { "claude".into() }
+fn default_settings_backups() -> usize { 10 }
+
+/// Portable mode: `--portable` on the command line or a `portable` marker
+/// file next to the exe. Keeps config, state and logs in the exe directory
+/// and leaves OS autostart untouched.
+pub fn is_portable() -> bool {
+    static PORTABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *PORTABLE.get_or_init(|| {
+        std::env::args().any(|a| a == "--portable") || app_dir().join("portable").exists()
+    })
+}
+
+/// Log directory: `<exe dir>/logs` when portable, else `%APPDATA%/agent-desk/logs`.
+pub fn log_dir() -> PathBuf {
+    if is_portable() {
+        return app_dir().join("logs");
+    }
+    std::env::var("APPDATA")
+        .map(|a| PathBuf::from(a).join("agent-desk").join("logs"))
+        .unwrap_or_else(|_| PathBuf::from("logs"))
+}
 
 fn app_dir() -> PathBuf {
     std::env::current_exe()
@@ -240,12 +1164,63 @@ fn app_dir() -> PathBuf {
         .unwrap_or_else(|| PathBuf::from("."))
 }
 
-fn default_events_file() -> String {
-    app_dir().join("events.jsonl").to_string_lossy().into_owned()
+/// Per-user state directory: `%APPDATA%/agent-desk` on Windows,
+/// `$XDG_DATA_HOME/agent-desk` (or `~/.local/share/agent-desk`) elsewhere,
+/// or the exe directory in portable mode.
+fn default_data_dir() -> PathBuf {
+    if is_portable() {
+        return app_dir();
+    }
+    if let Ok(appdata) = std::env::var("APPDATA") {
+        return PathBuf::from(appdata).join("agent-desk");
+    }
+    if let Some(xdg) = std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        return PathBuf::from(xdg).join("agent-desk");
+    }
+    if let Ok(home) = std::env::var("HOME") {
+        return PathBuf::from(home).join(".local").join("share").join("agent-desk");
+    }
+    app_dir()
+}
+
+/// Fill in empty state file paths from the data dir, migrating files left
+/// next to the exe by older versions on first run.
+fn resolve_state_paths(config: &mut Config) {
+    let data_dir = if config.general.data_dir.is_empty() {
+        default_data_dir()
+    } else {
+        PathBuf::from(&config.general.data_dir)
+    };
+
+    for (field, name) in [
+        (&mut config.manager.events_file, "events.jsonl"),
+        (&mut config.general.sessions_file, "sessions.json"),
+    ] {
+        if !field.is_empty() {
+            continue;
+        }
+        let target = data_dir.join(name);
+        migrate_legacy_file(&app_dir().join(name), &target);
+        *field = target.to_string_lossy().into_owned();
+    }
 }
 
-fn default_sessions_file() -> String {
-    app_dir().join("sessions.json").to_string_lossy().into_owned()
+/// Move `legacy` to `target` if only the legacy file exists.
+fn migrate_legacy_file(legacy: &Path, target: &Path) {
+    if legacy == target || target.exists() || !legacy.exists() {
+        return;
+    }
+    if let Some(parent) = target.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    // rename fails across volumes — fall back to copy + remove
+    let moved = std::fs::rename(legacy, target).is_ok()
+        || (std::fs::copy(legacy, target).is_ok() && std::fs::remove_file(legacy).is_ok());
+    if moved {
+        tracing::info!("Migrated {} -> {}", legacy.display(), target.display());
+    } else {
+        tracing::warn!("Failed to migrate {} -> {}", legacy.display(), target.display());
+    }
 }
 
 pub fn load_config() -> Config {
@@ -265,17 +1240,98 @@ pub fn load_config() -> Config {
         }
     }
 
-    match std::fs::read_to_string(&config_path) {
+    let mut tree = match std::fs::read_to_string(&config_path) {
         Ok(contents) => {
             serde_yaml::from_str(&contents).unwrap_or_else(|e| {
                 tracing::warn!("Failed to parse config {}: {}", config_path.display(), e);
-                Config::default()
+                serde_yaml::Value::Null
             })
         }
         Err(_) => {
             tracing::info!("No config file found at {}, using defaults", config_path.display());
+            serde_yaml::Value::Null
+        }
+    };
+    apply_env_overrides(&mut tree, std::env::vars());
+
+    let mut config = if tree.is_null() {
+        Config::default()
+    } else {
+        config_from_tree(&tree).unwrap_or_else(|e| {
+            tracing::warn!("Invalid config (after env overrides): {}", e);
             Config::default()
+        })
+    };
+    resolve_state_paths(&mut config);
+    config
+}
+
+/// Deserialize the merged YAML tree. It goes back through text because
+/// `from_value` is strict about scalar types: `chat_id: 123456789` (or an
+/// env override `=123`) would not load into a `String` field.
+fn config_from_tree(tree: &serde_yaml::Value) -> Result<Config, serde_yaml::Error> {
+    serde_yaml::from_str(&serde_yaml::to_string(tree)?)
+}
+
+/// Prefix for env overrides: `AGENT_DESK__MANAGER__PORT=16000` sets
+/// `manager.port`. Each `__` descends one level; keys are lowercased.
+const ENV_PREFIX: &str = "AGENT_DESK__";
+
+/// Merge `AGENT_DESK__SECTION__KEY=value` variables over the parsed YAML.
+/// Values are parsed as YAML scalars/flow collections, so `true`, `8080`
+/// and `[a, b]` get their natural types; anything unparsable stays a string.
+/// Overrides go in one at a time: one that would make a loadable config
+/// invalid (`AGENT_DESK__MANAGER__PORT=abc`) is dropped with a warning
+/// rather than sending the whole file back to defaults.
+fn apply_env_overrides(tree: &mut serde_yaml::Value, vars: impl Iterator<Item = (String, String)>) {
+    use serde_yaml::Value;
+
+    let mut valid = tree.is_null() || config_from_tree(tree).is_ok();
+    for (key, raw) in vars {
+        let Some(path) = key.strip_prefix(ENV_PREFIX) else { continue };
+        let parts: Vec<String> = path.split("__").map(|p| p.to_ascii_lowercase()).collect();
+        if parts.iter().any(|p| p.is_empty()) {
+            continue;
+        }
+        // Null results (empty value, "#ffffff" parsed as a comment) stay strings
+        let value: Value = serde_yaml::from_str(&raw)
+            .ok()
+            .filter(|v: &Value| !v.is_null())
+            .unwrap_or_else(|| Value::String(raw.clone()));
+
+        let mut candidate = tree.clone();
+        set_path(&mut candidate, &parts, value);
+        if valid {
+            if let Err(e) = config_from_tree(&candidate) {
+                tracing::warn!("Ignoring config override {}: {}", key, e);
+                continue;
+            }
+        } else {
+            valid = config_from_tree(&candidate).is_ok();
         }
+        *tree = candidate;
+        tracing::info!("Config override from env: {}", parts.join("."));
+    }
+}
+
+/// Set `parts` (a key path) in `tree` to `value`, turning anything in the
+/// way into a mapping.
+fn set_path(tree: &mut serde_yaml::Value, parts: &[String], value: serde_yaml::Value) {
+    use serde_yaml::{Mapping, Value};
+
+    let mut node = tree;
+    for part in &parts[..parts.len() - 1] {
+        if !node.is_mapping() {
+            *node = Value::Mapping(Mapping::new());
+        }
+        let map = node.as_mapping_mut().expect("mapping ensured above");
+        node = map.entry(Value::String(part.clone())).or_insert(Value::Null);
+    }
+    if !node.is_mapping() {
+        *node = Value::Mapping(Mapping::new());
+    }
+    if let Some(map) = node.as_mapping_mut() {
+        map.insert(Value::String(parts[parts.len() - 1].clone()), value);
     }
 }
 
@@ -285,10 +1341,22 @@ impl Default for Config {
             telegram: TelegramConfig::default(),
             dingtalk: DingTalkConfig::default(),
             wechat: WeChatConfig::default(),
+            pushover: PushoverConfig::default(),
+            bark: BarkConfig::default(),
             manager: ManagerConfig::default(),
             widget: WidgetConfig::default(),
             general: GeneralConfig::default(),
             island: IslandConfig::default(),
+            presence: PresenceConfig::default(),
+            messages: MessagesConfig::default(),
+            budget: BudgetConfig::default(),
+            github: GithubConfig::default(),
+            team: TeamConfig::default(),
+            network: NetworkConfig::default(),
+            redact: RedactConfig::default(),
+            sensitive: SensitiveConfig::default(),
+            journal: JournalConfig::default(),
+            memory: MemoryConfig::default(),
         }
     }
 }
@@ -311,6 +1379,11 @@ fn config_search_dirs() -> Vec<PathBuf> {
     let exe_dir = app_dir();
     dirs.push(exe_dir.join("config"));
 
+    // Portable: never look (or create files) outside the exe directory
+    if is_portable() {
+        return dirs;
+    }
+
     // 2. Walk up from exe directory looking for config/ (handles dev builds
     //    and autostart where CWD != project root). E.g. exe at
     //    project/src-tauri/target/release/ → walks up to project/config/.
@@ -380,23 +1453,239 @@ pub fn atomic_write_config(path: &std::path::Path, content: &str) {
 ///
 /// Example: `save_island_settings(&[("hotkey", "\"Alt+D\""), ("sound_enabled", "true")])`
 pub fn save_island_settings(settings: &[(&str, &str)]) {
+    save_section_settings("island", settings);
+}
+
+/// Write keys under a top-level `section:` of config.yaml using line-based
+/// replacement (same value format as `save_island_settings`). Keys missing
+/// from the section are added after its last entry, and a missing section
+/// at the end of the file. Returns false if config.yaml can't be read.
+pub fn save_section_settings(section: &str, settings: &[(&str, &str)]) -> bool {
     let path = find_config_path();
     let content = match std::fs::read_to_string(&path) {
         Ok(c) => c,
-        Err(_) => return,
+        Err(_) => return false,
+    };
+    atomic_write_config(&path, &set_section_keys(&content, section, settings));
+    true
+}
+
+/// `content` with `settings` written under `section:`, as
+/// `save_section_settings` saves it.
+fn set_section_keys(content: &str, section: &str, settings: &[(&str, &str)]) -> String {
+    let header = format!("{}:", section);
+    let mut in_section = false;
+    let mut written: Vec<&str> = Vec::new();
+    // Output index after the section's last entry, once the section is seen
+    let mut section_end: Option<usize> = None;
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        // Top-level keys start at column 0; they open or close a section
+        if !line.starts_with([' ', '\t', '#']) && !line.trim().is_empty() {
+            in_section = section_end.is_none() && line.trim_end().starts_with(&header);
+            lines.push(line.to_string());
+            if in_section {
+                section_end = Some(lines.len());
+            }
+            continue;
+        }
+        if !in_section {
+            lines.push(line.to_string());
+            continue;
+        }
+        let trimmed = line.trim_start();
+        let setting = settings.iter().find(|(key, _)| trimmed.starts_with(&format!("{}:", key)));
+        match setting {
+            Some(&(key, value)) => {
+                lines.push(format!("  {}: {}", key, value));
+                written.push(key);
+            }
+            None => lines.push(line.to_string()),
+        }
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            section_end = Some(lines.len());
+        }
+    }
+    let missing = settings
+        .iter()
+        .filter(|(key, _)| !written.contains(key))
+        .map(|(key, value)| format!("  {}: {}", key, value));
+    match section_end {
+        Some(end) => {
+            lines.splice(end..end, missing);
+        }
+        None => {
+            let missing: Vec<String> = missing.collect();
+            if !missing.is_empty() {
+                lines.push(String::new());
+                lines.push(header);
+                lines.extend(missing);
+            }
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// A problem in a candidate config.yaml.
+#[derive(Debug, Serialize)]
+pub struct ConfigIssue {
+    /// `error`: the file would not load (all defaults instead);
+    /// `warning`: it loads, but something in it is ignored.
+    pub severity: &'static str,
+    /// Dotted key path when known, e.g. `manager.port`.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub path: String,
+    pub message: String,
+    /// 1-based position in the YAML text, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl ConfigIssue {
+    fn from_yaml_error(e: &serde_yaml::Error) -> Self {
+        let loc = e.location();
+        let message = e.to_string();
+        // Type errors start with the key path, e.g. "manager.port: invalid type…"
+        let path = message
+            .split_once(": ")
+            .map(|(p, _)| p)
+            .filter(|p| p.chars().all(|c| c.is_ascii_alphanumeric() || "_.[]-".contains(c)))
+            .unwrap_or("")
+            .to_string();
+        Self {
+            severity: "error",
+            path,
+            message,
+            line: loc.as_ref().map(|l| l.line()),
+            column: loc.as_ref().map(|l| l.column()),
+        }
+    }
+}
+
+/// JSON Schema of config.yaml, generated from the config structs.
+pub fn config_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Config)).unwrap_or_default()
+}
+
+/// Check candidate config.yaml text the way `load_config` would read it:
+/// syntax and type errors, plus keys the app doesn't know (typos that
+/// would otherwise be ignored without a word).
+pub fn validate_config(yaml: &str) -> Vec<ConfigIssue> {
+    let tree: serde_yaml::Value = match serde_yaml::from_str(yaml) {
+        Ok(t) => t,
+        Err(e) => return vec![ConfigIssue::from_yaml_error(&e)],
     };
-    let new_content: String = content
-        .lines()
-        .map(|line| {
-            let trimmed = line.trim_start();
-            for &(key, value) in settings {
-                if trimmed.starts_with(&format!("{}:", key)) {
-                    return format!("  {}: {}", key, value);
+    let mut issues = Vec::new();
+    // An empty file loads as all defaults
+    match serde_yaml::from_str::<Config>(yaml) {
+        Err(e) if !tree.is_null() => issues.push(ConfigIssue::from_yaml_error(&e)),
+        _ => {}
+    }
+    let schema = config_schema();
+    let lines: Vec<&str> = yaml.lines().collect();
+    unknown_keys(&tree, &schema, &schema, "", &lines, 0, &mut issues);
+    issues
+}
+
+/// Follow a local `$ref` (`#/$defs/Name`) to its definition.
+fn resolve_ref<'a>(node: &'a serde_json::Value, root: &'a serde_json::Value) -> &'a serde_json::Value {
+    node.get("$ref")
+        .and_then(|r| r.as_str())
+        .and_then(|r| r.strip_prefix("#/"))
+        .and_then(|path| path.split('/').try_fold(root, |v, part| v.get(part)))
+        .unwrap_or(node)
+}
+
+/// Warn about mapping keys that `schema` has no property for. Maps with
+/// free-form keys (`additionalProperties`) are not checked.
+fn unknown_keys(
+    value: &serde_yaml::Value,
+    schema: &serde_json::Value,
+    root: &serde_json::Value,
+    path: &str,
+    lines: &[&str],
+    from_line: usize,
+    issues: &mut Vec<ConfigIssue>,
+) {
+    let schema = resolve_ref(schema, root);
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            let Some(props) = schema.get("properties").and_then(|p| p.as_object()) else {
+                return;
+            };
+            for (k, v) in map {
+                let Some(key) = k.as_str() else { continue };
+                let key_path = if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+                let line = key_line(lines, from_line, key);
+                match props.get(key) {
+                    Some(sub) => unknown_keys(v, sub, root, &key_path, lines, line.unwrap_or(from_line), issues),
+                    None => issues.push(ConfigIssue {
+                        severity: "warning",
+                        message: format!("unknown key `{}` (ignored)", key_path),
+                        path: key_path,
+                        line: line.map(|l| l + 1),
+                        column: None,
+                    }),
                 }
             }
-            line.to_string()
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
-    atomic_write_config(&path, &new_content);
+        }
+        serde_yaml::Value::Sequence(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    unknown_keys(item, item_schema, root, &format!("{}[{}]", path, i), lines, from_line, issues);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 0-based index of the first line at or after `from` that defines `key`.
+fn key_line(lines: &[&str], from: usize, key: &str) -> Option<usize> {
+    lines.iter().enumerate().skip(from).find_map(|(i, line)| {
+        let t = line.trim_start().trim_start_matches("- ");
+        let t = t.trim_start_matches(['"', '\'']);
+        let rest = t.strip_prefix(key)?;
+        rest.trim_start_matches(['"', '\'']).trim_start().starts_with(':').then_some(i)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(yaml: &str, env: &[(&str, &str)]) -> Config {
+        let mut tree: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        apply_env_overrides(&mut tree, env.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        config_from_tree(&tree).unwrap()
+    }
+
+    #[test]
+    fn numeric_scalars_load_into_string_fields() {
+        let config = load("telegram:\n  chat_id: 123456789\nmanager:\n  port: 16000\n", &[]);
+        assert_eq!(config.telegram.chat_id, "123456789");
+        assert_eq!(config.manager.port, 16000);
+    }
+
+    #[test]
+    fn env_overrides_keep_numeric_strings() {
+        let config = load(
+            "telegram:\n  chat_id: \"1\"\n",
+            &[("AGENT_DESK__TELEGRAM__CHAT_ID", "123"), ("AGENT_DESK__MANAGER__PORT", "16001")],
+        );
+        assert_eq!(config.telegram.chat_id, "123");
+        assert_eq!(config.manager.port, 16001);
+    }
+
+    #[test]
+    fn invalid_env_override_is_dropped() {
+        let config = load(
+            "telegram:\n  chat_id: \"1\"\nmanager:\n  port: 16000\n",
+            &[("AGENT_DESK__MANAGER__PORT", "abc"), ("AGENT_DESK__TELEGRAM__CHAT_ID", "5")],
+        );
+        assert_eq!(config.manager.port, 16000);
+        assert_eq!(config.telegram.chat_id, "5");
+    }
 }
@@ -0,0 +1,43 @@
+//! Dashboard window: a normal, resizable window next to the island for
+//! browsing full session history, searching events, usage charts and
+//! settings. It loads `dashboard.html` and talks to the same HTTP API as the
+//! island; it is created on first open and hidden rather than destroyed.
+
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+
+const LABEL: &str = "dashboard";
+
+/// Show the dashboard (creating it if needed), or hide it when it is
+/// already showing.
+pub fn toggle(app: &AppHandle, port: u16) {
+    let showing = app
+        .get_webview_window(LABEL)
+        .filter(|w| w.is_visible().unwrap_or(false) && !w.is_minimized().unwrap_or(false));
+    match showing {
+        Some(w) => {
+            let _ = w.hide();
+        }
+        None => open(app, port),
+    }
+}
+
+/// Show and focus the dashboard, creating it if needed.
+pub fn open(app: &AppHandle, port: u16) {
+    if let Some(w) = app.get_webview_window(LABEL) {
+        let _ = w.show();
+        let _ = w.unminimize();
+        let _ = w.set_focus();
+        return;
+    }
+    let built = WebviewWindowBuilder::new(app, LABEL, WebviewUrl::App("dashboard.html".into()))
+        .title("Agent Desk")
+        .inner_size(1100.0, 720.0)
+        .min_inner_size(640.0, 420.0)
+        .resizable(true)
+        .focused(true)
+        .initialization_script(&crate::setup::page_globals(port))
+        .build();
+    if let Err(e) = built {
+        tracing::warn!("Failed to open dashboard window: {}", e);
+    }
+}
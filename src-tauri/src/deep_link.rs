@@ -0,0 +1,160 @@
+//! Deep links: `agentdesk://` URLs and session links in remote messages.
+//!
+//! The scheme is registered with the OS at startup (Windows: HKCU
+//! `Software\Classes`, Linux: an `x-scheme-handler` desktop entry), so the
+//! OS launches the exe with the URL as its argument. A launch that finds
+//! agent-desk already running forwards the URL to `/api/deep-link` and
+//! exits; otherwise the new instance handles it once the server is up.
+//!
+//! Actions (carried out through action.rs):
+//! - `focus/{session}` — focus the session's terminal (full or short id)
+//! - `chat/{session}` — show the island expanded on the session's chat
+//! - `approve/{request}` / `deny/{request}` — answer a pending permission
+//!   request; needs its full id, so a link can't guess its way to approval
+//! - `show-island` — show and expand the island
+//! - `dashboard` — open the dashboard
+//! - `pause` / `resume` — pause or resume toasts, sounds and island
+//!   pop-ups (the jump list's task)
+
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use crate::action::Action;
+use crate::config::Config;
+use crate::server::AppState;
+
+pub const SCHEME: &str = "agentdesk";
+
+/// The `agentdesk://…` URL among the command-line arguments, if any.
+pub fn from_args(args: &[String]) -> Option<&str> {
+    args.iter()
+        .map(String::as_str)
+        .find(|a| a.starts_with(SCHEME) && a[SCHEME.len()..].starts_with("://"))
+}
+
+/// Link appended to remote messages about `session_id`, per
+/// `general.remote_link`. Only in LAN mode: the web link needs the UI to
+/// be reachable from the phone, and the scheme link only matters once
+/// you're back at this machine.
+pub fn session_link(cfg: &Config, session_id: &str) -> Option<String> {
+    if !cfg.manager.lan || session_id.is_empty() {
+        return None;
+    }
+    let sid = urlencoding::encode(session_id);
+    match cfg.general.remote_link.as_str() {
+        "web" if !cfg.manager.public_url.is_empty() => Some(format!(
+            "{}/ui/session/{}",
+            cfg.manager.public_url.trim_end_matches('/'),
+            sid
+        )),
+        "scheme" => Some(format!("{}://focus/{}", SCHEME, sid)),
+        _ => None,
+    }
+}
+
+/// Carry out a deep link.
+pub fn handle(state: &AppState, url: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix(SCHEME)
+        .and_then(|r| r.strip_prefix("://"))
+        .ok_or_else(|| format!("not an {} URL: {}", SCHEME, url))?;
+    let rest = rest.trim_end_matches('/');
+    let (action, arg) = rest.split_once('/').unwrap_or((rest, ""));
+    let arg = urlencoding::decode(arg).map(|s| s.into_owned()).unwrap_or_default();
+    let action = match action {
+        "focus" => Action::FocusSession { session_id: arg, cwd: String::new(), pid: None, peek: false },
+        "chat" => Action::OpenChat { session_id: arg },
+        "approve" => Action::Approve { id: arg, session: false },
+        "deny" => Action::Deny { id: arg, message: None },
+        "show-island" => Action::ShowIsland,
+        "dashboard" => Action::OpenDashboard,
+        "pause" => Action::ToggleDnd { paused: Some(true) },
+        "resume" => Action::ToggleDnd { paused: Some(false) },
+        _ => return Err(format!("unknown action: {}", action)),
+    };
+    crate::action::run(state, &action, "deep_link").map(|_| ()).map_err(|e| {
+        e.get("error").and_then(|v| v.as_str()).unwrap_or("failed").to_string()
+    })
+}
+
+/// Hand `url` to the instance already listening on `port`.
+pub fn forward(port: u16, url: &str) -> Result<(), String> {
+    let mut stream = crate::setup::connect_local(port, Duration::from_millis(500)).map_err(|e| e.to_string())?;
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let body = serde_json::json!({ "url": url }).to_string();
+    let req = format!(
+        "POST /api/deep-link HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        crate::setup::local_addr(port),
+        body.len(),
+        body
+    );
+    stream.write_all(req.as_bytes()).map_err(|e| e.to_string())?;
+    let mut resp = String::new();
+    let _ = stream.read_to_string(&mut resp);
+    let reply = resp
+        .split_once("\r\n\r\n")
+        .and_then(|(_, b)| serde_json::from_str::<serde_json::Value>(b).ok())
+        .unwrap_or_default();
+    if reply.get("ok").and_then(|v| v.as_bool()) == Some(true) {
+        Ok(())
+    } else {
+        Err(reply.get("error").and_then(|v| v.as_str()).unwrap_or("no reply").to_string())
+    }
+}
+
+/// Register `agentdesk://` with the OS for the current user, pointing at
+/// this exe. Idempotent; run on every desktop start so a moved exe is
+/// picked up.
+#[cfg(windows)]
+pub fn register() -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let key = format!("HKCU\\Software\\Classes\\{}", SCHEME);
+    let command_key = format!("{}\\shell\\open\\command", key);
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    let entries: [(&str, Option<&str>, &str); 3] = [
+        (&key, None, "URL:Agent Desk"),
+        (&key, Some("URL Protocol"), ""),
+        (&command_key, None, &command),
+    ];
+    for (path, name, data) in entries {
+        let mut cmd = std::process::Command::new("reg");
+        cmd.args(["add", path]);
+        match name {
+            Some(n) => cmd.args(["/v", n]),
+            None => cmd.arg("/ve"),
+        };
+        cmd.args(["/d", data, "/f"]).creation_flags(0x08000000); // CREATE_NO_WINDOW
+        let out = cmd.output().map_err(|e| e.to_string())?;
+        if !out.status.success() {
+            return Err(String::from_utf8_lossy(&out.stderr).trim().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn register() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let home = std::env::var("HOME").map_err(|_| "HOME not set".to_string())?;
+    let dir = std::path::Path::new(&home).join(".local/share/applications");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let name = "agent-desk-url.desktop";
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Agent Desk\nExec=\"{}\" %u\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+        exe.display(),
+        SCHEME
+    );
+    std::fs::write(dir.join(name), entry).map_err(|e| e.to_string())?;
+    let status = std::process::Command::new("xdg-mime")
+        .args(["default", name, &format!("x-scheme-handler/{}", SCHEME)])
+        .status()
+        .map_err(|e| format!("xdg-mime: {}", e))?;
+    if status.success() { Ok(()) } else { Err(format!("xdg-mime exited with {}", status)) }
+}
+
+/// macOS only hands URL schemes to apps that declare them in the bundle.
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn register() -> Result<(), String> {
+    Err("not supported on this platform".to_string())
+}
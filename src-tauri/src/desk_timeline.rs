@@ -0,0 +1,99 @@
+//! Desk state timeline: each change of the overall state (`sleeping`,
+//! `thinking`, `attention`, `limited`, `done`; see `status::summarize`) is
+//! kept in the event store as a `state_changed` event, and
+//! `GET /api/stats/timeline?day=` turns a day of them into a strip of
+//! segments, showing when agents were busy and when they were blocked on
+//! you.
+//!
+//! The state is sampled after each process scan, from the same merge as the
+//! tray. Time the app wasn't running counts as the state it last recorded.
+
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::events::Event;
+use crate::protocol::HookEvent;
+use crate::server::AppState;
+
+/// A stretch of one state, in unix seconds.
+#[derive(Debug, Serialize)]
+pub struct Segment {
+    pub state: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Record the desk's state if it differs from `last` (the last one
+/// recorded; looked up in the store when `None`). Blocking: merges the
+/// session list.
+pub fn observe(state: &AppState, last: &mut Option<String>) {
+    if last.is_none() {
+        let now = now_secs();
+        *last = state.event_store.state_changes(now, now).pop().map(|(_, s)| s);
+    }
+    let processes = crate::status::scan_and_merge(state);
+    let current = crate::status::compute_state(&processes)["state"].as_str().unwrap_or("sleeping").to_string();
+    if last.as_deref() == Some(current.as_str()) {
+        return;
+    }
+    tracing::debug!("Desk state {} -> {}", last.as_deref().unwrap_or("-"), current);
+    state.event_store.append_event(Event {
+        acked: true,
+        ..Event::new(HookEvent::StateChanged, "", "", &current, 1)
+    });
+    *last = Some(current);
+}
+
+/// The timeline of local day `day` (`YYYY-MM-DD`, today if `None`) up to
+/// now: its segments and the seconds spent in each state.
+pub fn day(state: &AppState, day: Option<&str>) -> Result<Value, String> {
+    use chrono::TimeZone;
+    let date = match day {
+        Some(d) => chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| format!("day {:?} is not YYYY-MM-DD", d))?,
+        None => chrono::Local::now().date_naive(),
+    };
+    let midnight = |d: chrono::NaiveDate| {
+        d.and_hms_opt(0, 0, 0)
+            .and_then(|t| chrono::Local.from_local_datetime(&t).earliest())
+            .map(|t| t.timestamp() as f64)
+    };
+    let (Some(from), Some(to)) = (midnight(date), date.succ_opt().and_then(midnight)) else {
+        return Err("local time conversion failed".into());
+    };
+
+    let until = to.min(now_secs());
+    let segments = if until > from { segments(&state.event_store.state_changes(from, until), from, until) } else { Vec::new() };
+    let mut totals: BTreeMap<&str, f64> = BTreeMap::new();
+    for s in &segments {
+        *totals.entry(s.state.as_str()).or_default() += s.end - s.start;
+    }
+    Ok(json!({
+        "ok": true,
+        "day": date.format("%Y-%m-%d").to_string(),
+        "from": from,
+        "to": to,
+        "segments": segments,
+        "totals": totals,
+    }))
+}
+
+/// Segments between `from` and `to` given the changes from
+/// `EventStore::state_changes`, each lasting until the next change.
+fn segments(changes: &[(f64, String)], from: f64, to: f64) -> Vec<Segment> {
+    changes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (ts, state))| {
+            let start = ts.max(from);
+            let end = changes.get(i + 1).map_or(to, |(next, _)| *next).min(to);
+            (end > start).then(|| Segment { state: state.clone(), start, end })
+        })
+        .collect()
+}
+
+fn now_secs() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()
+}
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::protocol::HookEvent;
@@ -23,51 +24,347 @@ pub struct Event {
     pub level: u8,
     #[serde(default)]
     pub cleared: bool,
+    /// Seen/handled by the user; still listed, no longer counted as unread.
+    #[serde(default)]
+    pub acked: bool,
+    /// Removed from the list individually (persisted, unlike a UI filter).
+    #[serde(default)]
+    pub dismissed: bool,
+    /// Reporting tool of an `external` event.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    /// Hook invocation that caused it (see `SignalPayload::correlation_id`).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub correlation_id: String,
 }
 
 fn default_level() -> u8 { 1 }
 
+impl Event {
+    /// A new event stamped now, with the one id scheme events use:
+    /// `evt_<ms>_<6 random hex>`, millisecond resolution so events can be
+    /// acked and dismissed by id. The other fields start empty.
+    pub fn new(event: HookEvent, session_id: &str, cwd: &str, message: &str, level: u8) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        Self {
+            id: format!("evt_{}_{}", (now * 1000.0) as u64, &uuid::Uuid::new_v4().to_string()[..6]),
+            ts: now,
+            event,
+            session_id: session_id.to_string(),
+            cwd: cwd.to_string(),
+            message: message.to_string(),
+            notification_type: String::new(),
+            last_assistant_message: String::new(),
+            level,
+            cleared: false,
+            acked: false,
+            dismissed: false,
+            source: String::new(),
+            correlation_id: String::new(),
+        }
+    }
+
+    /// Rough heap size: text lengths plus a fixed cost (see memory.rs).
+    fn approx_bytes(&self) -> usize {
+        crate::memory::ENTRY_OVERHEAD
+            + self.id.len()
+            + self.session_id.len()
+            + self.cwd.len()
+            + self.message.len()
+            + self.notification_type.len()
+            + self.last_assistant_message.len()
+            + self.source.len()
+            + self.correlation_id.len()
+    }
+
+    /// Cleared or dismissed, or a desk state record: kept on disk, left
+    /// out of lists and counts.
+    fn hidden(&self) -> bool {
+        self.cleared || self.dismissed || self.event == HookEvent::StateChanged
+    }
+}
+
+/// Which events `EventStore::clear_matching` clears. Unset fields match
+/// every event; set ones must all match.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClearFilter {
+    pub session_id: Option<String>,
+    /// Project directory (all sessions in it).
+    pub cwd: Option<String>,
+    /// Only events older than this Unix time.
+    pub before_ts: Option<f64>,
+    /// Only events at or below this level (1 = info … 3 = urgent).
+    pub level: Option<u8>,
+}
+
+impl ClearFilter {
+    /// No criteria set — the same as clearing everything.
+    pub fn is_empty(&self) -> bool {
+        self.session_id.is_none() && self.cwd.is_none() && self.before_ts.is_none() && self.level.is_none()
+    }
+
+    fn matches(&self, e: &Event) -> bool {
+        self.session_id.as_ref().is_none_or(|s| e.session_id == *s)
+            && self.cwd.as_ref().is_none_or(|c| e.cwd == *c)
+            && self.before_ts.is_none_or(|ts| e.ts < ts)
+            && self.level.is_none_or(|l| e.level <= l)
+    }
+}
+
 struct EventCache {
-    events: Vec<Event>,
+    /// Sorted by `ts`. Shared with readers as a snapshot; writers go through
+    /// `Arc::make_mut`, which only copies while a snapshot is still held.
+    events: Arc<Vec<Event>>,
+    /// Length of the leading run of cleared events. `clear_all` clears
+    /// everything present, so most cleared events sit in this prefix and
+    /// are skipped without a filter pass.
+    first_uncleared: usize,
+    /// Whether any event past the prefix is hidden (dismissed, cleared by a
+    /// filtered clear, or a state record) — lets counts skip the filter
+    /// pass.
+    any_hidden: bool,
+    /// Whether any event past the prefix is acked — lets unread counts
+    /// skip the filter pass too.
+    any_acked: bool,
     last_mtime: Option<SystemTime>,
     last_size: u64,
 }
 
+impl EventCache {
+    fn set_events(&mut self, events: Vec<Event>) {
+        self.events = Arc::new(events);
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        self.first_uncleared = self.events.iter().position(|e| !e.cleared).unwrap_or(self.events.len());
+        self.any_hidden = self.events[self.first_uncleared..].iter().any(Event::hidden);
+        self.any_acked = self.events[self.first_uncleared..].iter().any(|e| e.acked);
+    }
+
+    /// Index of the first visible (uncleared) event with `ts > after_ts` — O(log n).
+    fn start_after(&self, after_ts: f64) -> usize {
+        self.events.partition_point(|e| e.ts <= after_ts).max(self.first_uncleared)
+    }
+}
+
+/// Zero-copy view of visible events: a shared snapshot plus a start offset.
+/// Hidden events are skipped. Serializes as a plain JSON array, oldest first.
+pub struct EventsView {
+    events: Arc<Vec<Event>>,
+    start: usize,
+}
+
+impl EventsView {
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Event> {
+        self.events[self.start..].iter().filter(|e| !e.hidden())
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+impl Serialize for EventsView {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// One agent turn in the `folded` event list: the event standing for it
+/// plus the ones folded into it.
+#[derive(Debug, Clone, Serialize)]
+pub struct FoldedEvent {
+    /// The stop (or usage limit) ending the turn, else its latest event;
+    /// `level` is the turn's highest and `acked` holds only if every event
+    /// in it is acked.
+    #[serde(flatten)]
+    pub event: Event,
+    /// When the turn's first event came in.
+    pub first_ts: f64,
+    /// The other events of the turn, oldest first.
+    pub folded: Vec<FoldedPart>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FoldedPart {
+    pub id: String,
+    pub ts: f64,
+    pub event: HookEvent,
+    pub message: String,
+}
+
+/// Part of an agent turn: its notifications, the permission traffic and
+/// the stop that ends it.
+fn turn_event(e: &HookEvent) -> bool {
+    matches!(
+        e,
+        HookEvent::Notification
+            | HookEvent::PermissionRequest
+            | HookEvent::AutoApproved
+            | HookEvent::PermissionRepeated
+            | HookEvent::PermissionExpired
+            | HookEvent::SensitiveCommand
+            | HookEvent::Stop
+            | HookEvent::LimitReached
+    )
+}
+
+fn ends_turn(e: &HookEvent) -> bool {
+    matches!(e, HookEvent::Stop | HookEvent::LimitReached)
+}
+
+/// Fold `events` (newest first, as listed) into one item per agent turn:
+/// a session's turn events up to and including the stop that ends it, and
+/// any event sharing a correlation id with one already folded (the same
+/// hook call). Other events stay items of their own. Newest first.
+pub fn fold(events: Vec<Event>) -> Vec<FoldedEvent> {
+    let mut turns: Vec<Vec<Event>> = Vec::new();
+    // Session id → its open turn; correlation id → the turn holding it
+    let mut open: HashMap<String, usize> = HashMap::new();
+    let mut by_cid: HashMap<String, usize> = HashMap::new();
+    for e in events.into_iter().rev() {
+        let joined = match by_cid.get(&e.correlation_id) {
+            Some(&i) => Some(i),
+            _ if turn_event(&e.event) => open.get(&e.session_id).copied(),
+            _ => None,
+        };
+        let i = joined.unwrap_or_else(|| {
+            turns.push(Vec::new());
+            turns.len() - 1
+        });
+        if !e.correlation_id.is_empty() {
+            by_cid.insert(e.correlation_id.clone(), i);
+        }
+        if !e.session_id.is_empty() && turn_event(&e.event) {
+            if ends_turn(&e.event) {
+                open.remove(&e.session_id);
+            } else {
+                open.insert(e.session_id.clone(), i);
+            }
+        }
+        turns[i].push(e);
+    }
+
+    let mut folded: Vec<FoldedEvent> = turns
+        .into_iter()
+        .map(|mut turn| {
+            let head = turn.iter().rposition(|e| ends_turn(&e.event)).unwrap_or(turn.len() - 1);
+            let mut event = turn.remove(head);
+            let first_ts = turn.first().map_or(event.ts, |e| e.ts.min(event.ts));
+            event.level = turn.iter().map(|e| e.level).fold(event.level, u8::max);
+            event.acked = event.acked && turn.iter().all(|e| e.acked);
+            let folded = turn
+                .into_iter()
+                .map(|e| FoldedPart { id: e.id, ts: e.ts, event: e.event, message: e.message })
+                .collect();
+            FoldedEvent { event, first_ts, folded }
+        })
+        .collect();
+    folded.sort_by(|a, b| {
+        let last = |f: &FoldedEvent| f.folded.last().map_or(f.event.ts, |p| p.ts.max(f.event.ts));
+        last(b).total_cmp(&last(a))
+    });
+    folded
+}
+
 pub struct EventStore {
     path: PathBuf,
     max_age: u64,
+    /// Keep at most this many events after compaction (0 = unlimited).
+    max_count: usize,
+    /// Keep events.jsonl under this many bytes after compaction (0 = unlimited).
+    max_file_size: u64,
     cache: RwLock<EventCache>,
 }
 
 impl EventStore {
-    pub fn new(path: String, max_age: u64) -> Self {
+    pub fn new(path: String, max_age: u64, max_count: usize, max_file_size: u64) -> Self {
         Self {
             path: PathBuf::from(&path),
             max_age,
+            max_count,
+            max_file_size,
             cache: RwLock::new(EventCache {
-                events: Vec::new(),
+                events: Arc::new(Vec::new()),
+                first_uncleared: 0,
+                any_hidden: false,
+                any_acked: false,
                 last_mtime: None,
                 last_size: 0,
             }),
         }
     }
 
-    /// Read events, using mtime cache to avoid re-reading unchanged files.
-    pub fn get_events(&self, after_ts: f64) -> Vec<Event> {
+    /// Visible events after `after_ts` (oldest first), without cloning them.
+    /// Uses the mtime cache to avoid re-reading unchanged files.
+    pub fn get_events(&self, after_ts: f64) -> EventsView {
+        self.refresh_cache();
+
+        let cache = read_lock!(self.cache);
+        EventsView {
+            events: cache.events.clone(),
+            start: cache.start_after(after_ts),
+        }
+    }
+
+    /// Number of visible events after `after_ts` — O(log n) unless some
+    /// events are hidden, no snapshot.
+    pub fn count_after(&self, after_ts: f64) -> usize {
         self.refresh_cache();
 
         let cache = read_lock!(self.cache);
-        if after_ts > 0.0 {
-            cache.events.iter()
-                .filter(|e| !e.cleared && e.ts > after_ts)
-                .cloned()
-                .collect()
+        let start = cache.start_after(after_ts);
+        if cache.any_hidden {
+            cache.events[start..].iter().filter(|e| !e.hidden()).count()
         } else {
-            cache.events.iter()
-                .filter(|e| !e.cleared)
-                .cloned()
-                .collect()
+            cache.events.len() - start
+        }
+    }
+
+    /// Number of visible, unacknowledged events after `after_ts` — O(log n)
+    /// like `count_after` unless some events are hidden or acked.
+    pub fn count_unread_after(&self, after_ts: f64) -> usize {
+        self.refresh_cache();
+
+        let cache = read_lock!(self.cache);
+        let start = cache.start_after(after_ts);
+        if cache.any_hidden || cache.any_acked {
+            cache.events[start..].iter().filter(|e| !e.hidden() && !e.acked).count()
+        } else {
+            cache.events.len() - start
+        }
+    }
+
+    /// Desk state changes (`state_changed`: time, new state) from `from` to
+    /// `to`, oldest first, led by the last one before `from` if any. Cleared
+    /// ones count: clearing the list doesn't rewrite the timeline.
+    pub fn state_changes(&self, from: f64, to: f64) -> Vec<(f64, String)> {
+        self.refresh_cache();
+
+        let cache = read_lock!(self.cache);
+        let changes = cache.events.iter().filter(|e| e.event == HookEvent::StateChanged && e.ts <= to);
+        let mut out: Vec<(f64, String)> = Vec::new();
+        for e in changes {
+            if e.ts < from {
+                out.clear();
+            }
+            out.push((e.ts, e.message.clone()));
         }
+        out
+    }
+
+    /// Newest-first events after `after_ts`, cloning at most `limit` of them.
+    pub fn get_recent(&self, after_ts: f64, limit: usize) -> Vec<Event> {
+        self.get_events(after_ts).iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
     }
 
     /// Refresh cache if file has changed (mtime or size differ).
@@ -87,7 +384,7 @@ impl EventStore {
         // Re-read file
         let events = self.read_file();
         let mut cache = write_lock!(self.cache);
-        cache.events = events;
+        cache.set_events(events);
         cache.last_mtime = current_mtime;
         cache.last_size = current_size;
     }
@@ -111,6 +408,8 @@ impl EventStore {
                 Err(_) => continue,
             }
         }
+        // Appends are normally in order; a stable sort fixes up clock skew
+        events.sort_by(|a, b| a.ts.total_cmp(&b.ts));
         events
     }
 
@@ -130,9 +429,18 @@ impl EventStore {
             }
         }
 
-        // Update in-memory cache
+        // Update in-memory cache, keeping it sorted by ts
         let mut cache = write_lock!(self.cache);
-        cache.events.push(event);
+        cache.any_hidden |= event.hidden();
+        cache.any_acked |= event.acked;
+        let events = Arc::make_mut(&mut cache.events);
+        let pos = events.partition_point(|e| e.ts <= event.ts);
+        events.insert(pos, event);
+        // An older ts (a handler racing a clear) can land inside the
+        // cleared prefix, which then no longer is one
+        if pos < cache.first_uncleared {
+            cache.reindex();
+        }
         // Update metadata so next refresh_cache() doesn't re-read
         if let Ok(meta) = fs::metadata(&self.path) {
             cache.last_mtime = meta.modified().ok();
@@ -143,13 +451,84 @@ impl EventStore {
     /// Mark all events as cleared.
     pub fn clear_all(&self) {
         let mut cache = write_lock!(self.cache);
-        for evt in &mut cache.events {
+        for evt in Arc::make_mut(&mut cache.events) {
             evt.cleared = true;
         }
+        cache.first_uncleared = cache.events.len();
+        cache.any_hidden = false;
+        cache.any_acked = false;
+        self.rewrite_file(&mut cache);
+    }
+
+    /// Mark the visible events matching `filter` as cleared, leaving the
+    /// rest (other sessions' history) listed. Returns how many were cleared.
+    pub fn clear_matching(&self, filter: &ClearFilter) -> usize {
+        self.refresh_cache();
+
+        let mut cache = write_lock!(self.cache);
+        let start = cache.first_uncleared;
+        if !cache.events[start..].iter().any(|e| !e.hidden() && filter.matches(e)) {
+            return 0;
+        }
+        let mut cleared = 0;
+        for evt in Arc::make_mut(&mut cache.events)[start..].iter_mut().filter(|e| !e.hidden() && filter.matches(e)) {
+            evt.cleared = true;
+            cleared += 1;
+        }
+        cache.reindex();
+        self.rewrite_file(&mut cache);
+        cleared
+    }
+
+    /// Mark one event as acknowledged. Returns false if no such event.
+    pub fn ack(&self, id: &str) -> bool {
+        self.update_event(id, |e| e.acked = true)
+    }
+
+    /// Hide one event from the list. Returns false if no such event.
+    pub fn dismiss(&self, id: &str) -> bool {
+        self.update_event(id, |e| {
+            e.dismissed = true;
+            e.acked = true;
+        })
+    }
+
+    /// Move every event of session `old` to `new` (after a resume merge).
+    /// Returns how many were moved.
+    pub fn reassign_session(&self, old: &str, new: &str) -> usize {
+        self.refresh_cache();
+
+        let mut cache = write_lock!(self.cache);
+        if !cache.events.iter().any(|e| e.session_id == old) {
+            return 0;
+        }
+        let mut moved = 0;
+        for evt in Arc::make_mut(&mut cache.events).iter_mut().filter(|e| e.session_id == old) {
+            evt.session_id = new.to_string();
+            moved += 1;
+        }
+        self.rewrite_file(&mut cache);
+        moved
+    }
+
+    /// Apply `f` to the event with `id` and persist the change.
+    fn update_event(&self, id: &str, f: impl FnOnce(&mut Event)) -> bool {
+        self.refresh_cache();
+
+        let mut cache = write_lock!(self.cache);
+        let Some(idx) = cache.events.iter().position(|e| e.id == id) else {
+            return false;
+        };
+        f(&mut Arc::make_mut(&mut cache.events)[idx]);
+        cache.reindex();
+        self.rewrite_file(&mut cache);
+        true
+    }
 
-        // Rewrite file
+    /// Rewrite events.jsonl from the cache and refresh its metadata.
+    fn rewrite_file(&self, cache: &mut EventCache) {
         if let Ok(mut file) = fs::File::create(&self.path) {
-            for evt in &cache.events {
+            for evt in cache.events.iter() {
                 if let Ok(json) = serde_json::to_string(evt) {
                     let _ = writeln!(file, "{}", json);
                 }
@@ -163,7 +542,8 @@ impl EventStore {
         }
     }
 
-    /// Remove events older than max_age.
+    /// Remove events older than max_age, then trim the oldest events until
+    /// both the count and file size limits hold.
     pub fn compact(&self) {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -172,13 +552,39 @@ impl EventStore {
         let cutoff = now - self.max_age as f64;
 
         let mut cache = write_lock!(self.cache);
-        cache.events.retain(|e| e.ts >= cutoff);
+        let events = Arc::make_mut(&mut cache.events);
+        events.retain(|e| e.ts >= cutoff);
+
+        if self.max_count > 0 && events.len() > self.max_count {
+            let excess = events.len() - self.max_count;
+            events.drain(..excess);
+        }
+
+        let mut lines: Vec<String> = events.iter()
+            .map(|e| serde_json::to_string(e).unwrap_or_default())
+            .collect();
+
+        if self.max_file_size > 0 {
+            // Walk back from the newest event, keeping as many as fit
+            let mut total = 0u64;
+            let mut keep = 0;
+            for line in lines.iter().rev() {
+                total += line.len() as u64 + 1;
+                if total > self.max_file_size { break; }
+                keep += 1;
+            }
+            let excess = lines.len() - keep;
+            if excess > 0 {
+                events.drain(..excess);
+                lines.drain(..excess);
+            }
+        }
+        cache.reindex();
 
+        let before = cache.last_size;
         if let Ok(mut file) = fs::File::create(&self.path) {
-            for evt in &cache.events {
-                if let Ok(json) = serde_json::to_string(evt) {
-                    let _ = writeln!(file, "{}", json);
-                }
+            for line in &lines {
+                let _ = writeln!(file, "{}", line);
             }
         }
 
@@ -186,5 +592,14 @@ impl EventStore {
             cache.last_mtime = meta.modified().ok();
             cache.last_size = meta.len();
         }
+        tracing::debug!("Events compacted: {} kept, {} -> {} bytes", cache.events.len(), before, cache.last_size);
+    }
+
+    /// Events held in memory, hidden ones included, and their approximate
+    /// size in bytes.
+    pub fn memory(&self) -> (usize, usize) {
+        self.refresh_cache();
+        let cache = read_lock!(self.cache);
+        (cache.events.len(), cache.events.iter().map(Event::approx_bytes).sum())
     }
 }
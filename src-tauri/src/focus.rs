@@ -1,5 +1,22 @@
-/// Win32: find and focus terminal windows via process-tree tracing.
+/// Win32: find and focus terminal windows via process-tree tracing, or just
+/// "peek" at them (raise + flash without taking keyboard focus).
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(windows)]
+use std::sync::Mutex;
+
 use crate::process::ProcessInfo;
+#[cfg(windows)]
+use crate::process::snapshot::{self, ProcessTable};
+
+/// `general.focus_alt_key_fallback`: when Windows refuses to hand over the
+/// foreground, press and release Alt to get it (the old way; it can leave
+/// Alt stuck or land in what the user is typing).
+static ALT_KEY_FALLBACK: AtomicBool = AtomicBool::new(false);
+
+/// Apply the focus settings from config.
+pub fn configure(alt_key_fallback: bool) {
+    ALT_KEY_FALLBACK.store(alt_key_fallback, Ordering::Relaxed);
+}
 
 /// Known terminal process names (lowercase).
 const TERMINAL_PROCESSES: &[&str] = &[
@@ -12,11 +29,31 @@ const TERMINAL_PROCESSES: &[&str] = &[
 ];
 
 
+/// Title token naming a session, `[AD:<first 8 of its id>]`. A terminal
+/// whose title carries it (e.g. set by the script that launched the agent
+/// with `claude --session-id`) is taken as that session's, before any
+/// title is matched against the CWD.
+#[cfg(windows)]
+pub fn title_token(session_id: &str) -> Option<String> {
+    let short = session_id.get(..8).unwrap_or(session_id);
+    (!short.is_empty()).then(|| format!("[AD:{}]", short))
+}
+
+/// Lowercase strings a terminal title is matched against for `cwd`: the
+/// path with either separator, and its last component.
+#[cfg(windows)]
+fn cwd_variants(cwd: &str) -> Vec<String> {
+    let cwd_lower = cwd.replace('/', "\\").to_lowercase();
+    let cwd_fwd = cwd.replace('\\', "/").to_lowercase();
+    let basename = cwd.rsplit(&['/', '\\']).next().unwrap_or("").to_lowercase();
+    vec![cwd_lower, cwd_fwd, basename]
+}
+
 /// Find the terminal window for a session without focusing it.
 /// Returns `TerminalMatch` (hwnd + optional WT tab info) or `None`.
 #[cfg(windows)]
-pub fn find_terminal(cwd: &str, cached_processes: &[ProcessInfo], pid: Option<u32>) -> Option<TerminalMatch> {
-    let snapshot = ProcessSnapshot::capture();
+pub fn find_terminal(session_id: &str, cwd: &str, cached_processes: &[ProcessInfo], pid: Option<u32>) -> Option<TerminalMatch> {
+    let snapshot = snapshot::processes();
 
     // Strategy 1 (best): walk from the specific agent PID up to its terminal.
     if let Some(p) = pid {
@@ -26,16 +63,24 @@ pub fn find_terminal(cwd: &str, cached_processes: &[ProcessInfo], pid: Option<u3
         }
     }
 
+    // Titles are matched against the session's token first, then the CWD
+    let mut passes = Vec::new();
+    if let Some(token) = title_token(session_id) {
+        passes.push(("token", vec![token.to_lowercase()]));
+    }
     if !cwd.is_empty() {
-        // Strategy 2: walk from each cached agent process, check terminal title vs CWD
-        if let Some(m) = find_terminal_for_cwd(cwd, cached_processes, &snapshot) {
-            tracing::debug!("find_terminal: Strategy 2 (CWD process walk) matched: hwnd {}", m.hwnd);
+        passes.push(("CWD", cwd_variants(cwd)));
+    }
+    for (what, variants) in &passes {
+        // Strategy 2: walk from each cached agent process, check terminal title
+        if let Some(m) = find_terminal_for_title(variants, cached_processes, &snapshot) {
+            tracing::debug!("find_terminal: Strategy 2 ({} process walk) matched: hwnd {}", what, m.hwnd);
             return Some(m);
         }
 
-        // Strategy 3: scan all visible windows, match title vs CWD (only known terminals)
-        if let Some(hwnd) = find_terminal_by_title(cwd) {
-            tracing::debug!("find_terminal: Strategy 3 (title scan) matched: hwnd {}", hwnd);
+        // Strategy 3: scan all visible windows, match title (only known terminals)
+        if let Some(hwnd) = find_terminal_by_title(variants) {
+            tracing::debug!("find_terminal: Strategy 3 ({} title scan) matched: hwnd {}", what, hwnd);
             return Some(TerminalMatch { hwnd, wt_tab: None });
         }
     }
@@ -44,111 +89,120 @@ pub fn find_terminal(cwd: &str, cached_processes: &[ProcessInfo], pid: Option<u3
 }
 
 #[cfg(not(windows))]
-pub fn find_terminal(_cwd: &str, _cached_processes: &[ProcessInfo], _pid: Option<u32>) -> Option<()> {
+pub fn find_terminal(_session_id: &str, _cwd: &str, _cached_processes: &[ProcessInfo], _pid: Option<u32>) -> Option<()> {
     None
 }
 
+/// How focusing a session's terminal went.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FocusResult {
+    /// The terminal window came to the foreground.
+    pub focused: bool,
+    /// For a Windows Terminal tab: whether the tab showing afterwards was
+    /// checked to be the session's. `None` when there was no tab to switch
+    /// or it couldn't be told apart (see `switch_wt_tab`).
+    pub tab_verified: Option<bool>,
+}
+
 /// Focus a terminal match: set foreground + switch WT tab if applicable.
 #[cfg(windows)]
-pub fn focus_terminal(m: &TerminalMatch) -> bool {
-    let ok = focus_hwnd(m.hwnd);
-    if ok {
-        if let Some((wt_pid, shell_pid)) = m.wt_tab {
-            switch_wt_tab(wt_pid, shell_pid);
+pub fn focus_terminal(m: &TerminalMatch) -> FocusResult {
+    let focused = focus_hwnd(m.hwnd);
+    let tab_verified = match m.wt_tab {
+        Some((wt_pid, shell_pid)) if focused => switch_wt_tab(m.hwnd, wt_pid, shell_pid),
+        _ => None,
+    };
+    FocusResult { focused, tab_verified }
+}
+
+#[cfg(not(windows))]
+pub fn focus_terminal(_m: &()) -> FocusResult {
+    FocusResult::default()
+}
+
+pub fn find_and_focus_terminal_with_pid(session_id: &str, cwd: &str, cached_processes: &[ProcessInfo], pid: Option<u32>) -> FocusResult {
+    #[cfg(windows)]
+    {
+        if let Some(m) = find_terminal(session_id, cwd, cached_processes, pid) {
+            return focus_terminal(&m);
         }
     }
-    ok
+
+    let _ = (session_id, cwd, cached_processes, pid);
+    FocusResult::default()
 }
 
-pub fn find_and_focus_terminal_with_pid(cwd: &str, cached_processes: &[ProcessInfo], pid: Option<u32>) -> bool {
+/// Peek at a session's terminal: raise it above other windows and flash it,
+/// but leave keyboard focus (and the WT tab) where it is. Used while the
+/// user hovers a session in the island; a click still does the full focus.
+pub fn find_and_peek_terminal_with_pid(session_id: &str, cwd: &str, cached_processes: &[ProcessInfo], pid: Option<u32>) -> bool {
     #[cfg(windows)]
     {
-        if let Some(m) = find_terminal(cwd, cached_processes, pid) {
-            return focus_terminal(&m);
+        if let Some(m) = find_terminal(session_id, cwd, cached_processes, pid) {
+            return peek_hwnd(m.hwnd);
         }
     }
 
-    let _ = (cwd, cached_processes, pid);
+    let _ = (session_id, cwd, cached_processes, pid);
     false
 }
 
-/// Result from walk_to_terminal: the terminal window + optional WT tab info.
+/// Whether the foreground window is the terminal window of agent process
+/// `pid`, found the way focusing finds it (`walk_to_terminal`) — i.e. the
+/// user is already looking at this session's terminal. Other windows of
+/// its ancestors (Explorer, an unrelated VS Code window) don't count.
+///
+/// Windows Terminal hosts every tab, and every window, in one process, so
+/// any of its windows counts as foreground.
 #[cfg(windows)]
-pub struct TerminalMatch {
-    pub hwnd: isize,
-    /// If the terminal is Windows Terminal: (wt_pid, target_shell_pid) for tab switching.
-    pub wt_tab: Option<(u32, u32)>,
+pub fn is_session_foreground(pid: u32) -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    let fg = unsafe { GetForegroundWindow() };
+    if fg.0.is_null() {
+        return false;
+    }
+    let Some(terminal) = walk_to_terminal(&snapshot::processes(), pid) else {
+        return false;
+    };
+    if fg.0 as isize == terminal.hwnd {
+        return true;
+    }
+    // One Windows Terminal process can own several windows; the window list
+    // keeps only one of them per PID
+    let Some((wt_pid, _)) = terminal.wt_tab else {
+        return false;
+    };
+    let mut fg_pid: u32 = 0;
+    unsafe { GetWindowThreadProcessId(fg, Some(&mut fg_pid)) };
+    fg_pid == wt_pid
 }
 
-/// Cached Toolhelp32 process snapshot — avoids creating one per walk level.
-#[cfg(windows)]
-struct ProcessSnapshot {
-    /// (pid, parent_pid, exe_name)
-    entries: Vec<(u32, u32, String)>,
+#[cfg(not(windows))]
+pub fn is_session_foreground(_pid: u32) -> bool {
+    false
 }
 
+/// Result from walk_to_terminal: the terminal window + optional WT tab info.
 #[cfg(windows)]
-impl ProcessSnapshot {
-    fn capture() -> Self {
-        use windows::Win32::System::Diagnostics::ToolHelp::*;
-        use windows::Win32::Foundation::CloseHandle;
-
-        let mut entries = Vec::new();
-        unsafe {
-            let snapshot = match CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) {
-                Ok(h) => h,
-                Err(_) => return Self { entries },
-            };
-            let mut entry = PROCESSENTRY32W::default();
-            entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
-
-            if Process32FirstW(snapshot, &mut entry).is_ok() {
-                loop {
-                    let name = String::from_utf16_lossy(
-                        &entry.szExeFile[..entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len())]
-                    );
-                    entries.push((entry.th32ProcessID, entry.th32ParentProcessID, name));
-                    if Process32NextW(snapshot, &mut entry).is_err() {
-                        break;
-                    }
-                }
-            }
-            let _ = CloseHandle(snapshot);
-        }
-        Self { entries }
-    }
-
-    /// Get parent PID and parent's exe name for a given PID.
-    fn get_parent_info(&self, pid: u32) -> Option<(u32, &str)> {
-        let parent_pid = self.entries.iter()
-            .find(|(p, _, _)| *p == pid)
-            .map(|(_, pp, _)| *pp)
-            .filter(|&pp| pp != 0 && pp != pid)?;
-        let parent_name = self.entries.iter()
-            .find(|(p, _, _)| *p == parent_pid)
-            .map(|(_, _, n)| n.as_str())
-            .unwrap_or("");
-        Some((parent_pid, parent_name))
-    }
+pub struct TerminalMatch {
+    pub hwnd: isize,
+    /// If the terminal is Windows Terminal: (wt_pid, target_shell_pid) for tab switching.
+    pub wt_tab: Option<(u32, u32)>,
 }
 
 /// For each agent process, walk up to find its terminal window,
-/// then check if the terminal's title contains the target CWD.
+/// then check if the terminal's title contains one of `variants`.
 #[cfg(windows)]
-fn find_terminal_for_cwd(cwd: &str, cached: &[ProcessInfo], snapshot: &ProcessSnapshot) -> Option<TerminalMatch> {
-    let cwd_lower = cwd.replace('/', "\\").to_lowercase();
-    let cwd_fwd = cwd.replace('\\', "/").to_lowercase();
-    let basename = cwd.rsplit(&['/', '\\']).next().unwrap_or("").to_lowercase();
-    let variants = vec![cwd_lower.clone(), cwd_fwd, basename.clone()];
-
+fn find_terminal_for_title(variants: &[String], cached: &[ProcessInfo], snapshot: &ProcessTable) -> Option<TerminalMatch> {
     for proc in cached {
         if let Some(m) = walk_to_terminal(snapshot, proc.pid) {
-            // Got the terminal window — check if its title contains the CWD
+            // Got the terminal window — check its title
             let title = get_window_title(m.hwnd);
             let title_lower = title.to_lowercase();
             if variants.iter().any(|v| !v.is_empty() && title_lower.contains(v.as_str())) {
-                tracing::debug!("find_terminal_for_cwd: PID {} → terminal '{}' matches cwd '{}'",
-                    proc.pid, title, cwd);
+                tracing::debug!("find_terminal_for_title: PID {} → terminal '{}' matches {:?}",
+                    proc.pid, title, variants);
                 return Some(m);
             }
         }
@@ -171,17 +225,18 @@ fn get_window_title(hwnd: isize) -> String {
 }
 
 #[cfg(windows)]
-fn walk_to_terminal(snapshot: &ProcessSnapshot, pid: u32) -> Option<TerminalMatch> {
+fn walk_to_terminal(snapshot: &ProcessTable, pid: u32) -> Option<TerminalMatch> {
     let mut current_pid = pid;
+    let windows = snapshot::windows();
     tracing::debug!("walk_to_terminal: starting from PID {}", pid);
 
     for level in 0..6 {
-        let (parent_pid, parent_name) = snapshot.get_parent_info(current_pid)?;
+        let (parent_pid, parent_name) = snapshot.parent(current_pid)?;
         let parent_lower = parent_name.to_lowercase();
         tracing::debug!("  level {}: PID {} → parent PID {} ({})", level, current_pid, parent_pid, parent_name);
 
         if TERMINAL_PROCESSES.contains(&parent_lower.as_str()) {
-            if let Some(hwnd) = find_window_for_pid(parent_pid) {
+            if let Some(hwnd) = windows.for_pid(parent_pid) {
                 tracing::debug!("  → found terminal window hwnd={} for {} (PID {})", hwnd, parent_name, parent_pid);
 
                 // Record WT tab info — caller switches tab AFTER focus_hwnd
@@ -202,49 +257,36 @@ fn walk_to_terminal(snapshot: &ProcessSnapshot, pid: u32) -> Option<TerminalMatc
     None
 }
 
-/// Switch Windows Terminal to the tab containing `target_shell_pid`.
+/// Tabs tried after the guessed one fails the check.
+#[cfg(windows)]
+const MAX_TAB_RETRIES: usize = 10;
+
+/// Switch Windows Terminal (window `hwnd`, already in front) to the tab
+/// containing `target_shell_pid`, and check that it did.
 ///
 /// Strategy: enumerate WT's direct child processes (the per-tab shells),
 /// sort by creation time (approximates tab order), find the index of
-/// `target_shell_pid`, and run `wt.exe -w 0 focus-tab -t <index>`.
+/// `target_shell_pid`, and run `wt.exe -w 0 focus-tab -t <index>`. Tabs
+/// that were moved or closed throw the order off, so the result is
+/// checked: WT titles its window after the tab showing, which should then
+/// be the title of the target shell's console. If not, the other tabs are
+/// tried in turn. Returns whether the target tab ended up showing, or
+/// `None` when that can't be told (the shell wasn't found, its console
+/// title is unreadable, or another tab has the same title).
 #[cfg(windows)]
-pub fn switch_wt_tab(wt_pid: u32, target_shell_pid: u32) {
-    use windows::Win32::System::Diagnostics::ToolHelp::*;
-    use windows::Win32::Foundation::CloseHandle;
-
+pub fn switch_wt_tab(hwnd: isize, wt_pid: u32, target_shell_pid: u32) -> Option<bool> {
     // 1. Find all direct children of WT that are known shell processes
     let mut children: Vec<(u32, u64)> = Vec::new(); // (pid, create_time)
-    unsafe {
-        let snapshot = match CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) {
-            Ok(h) => h,
-            Err(_) => return,
-        };
-        let mut entry = PROCESSENTRY32W::default();
-        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
-
-        if Process32FirstW(snapshot, &mut entry).is_ok() {
-            loop {
-                if entry.th32ParentProcessID == wt_pid {
-                    let name = String::from_utf16_lossy(
-                        &entry.szExeFile[..entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len())]
-                    ).to_lowercase();
-                    // Only count shell processes (each WT tab has one)
-                    let is_shell = matches!(name.as_str(),
-                        "powershell.exe" | "pwsh.exe" | "cmd.exe" | "bash.exe"
-                        | "wsl.exe" | "ubuntu.exe" | "git-bash.exe" | "nu.exe"
-                        | "fish.exe" | "zsh.exe"
-                    );
-                    if is_shell {
-                        let ctime = get_process_create_time(entry.th32ProcessID);
-                        children.push((entry.th32ProcessID, ctime));
-                    }
-                }
-                if Process32NextW(snapshot, &mut entry).is_err() {
-                    break;
-                }
-            }
+    for (pid, name) in snapshot::processes().children(wt_pid) {
+        // Only count shell processes (each WT tab has one)
+        let is_shell = matches!(name.to_lowercase().as_str(),
+            "powershell.exe" | "pwsh.exe" | "cmd.exe" | "bash.exe"
+            | "wsl.exe" | "ubuntu.exe" | "git-bash.exe" | "nu.exe"
+            | "fish.exe" | "zsh.exe"
+        );
+        if is_shell {
+            children.push((pid, get_process_create_time(pid)));
         }
-        let _ = CloseHandle(snapshot);
     }
 
     // 2. Sort by creation time (tab order)
@@ -255,17 +297,86 @@ pub fn switch_wt_tab(wt_pid: u32, target_shell_pid: u32) {
     // 3. Find the index of target_shell_pid
     let tab_index = children.iter().position(|(pid, _)| *pid == target_shell_pid);
 
-    if let Some(idx) = tab_index {
-        tracing::debug!("  → switching to tab index {} via wt.exe", idx);
-        {
-            use std::os::windows::process::CommandExt;
-            let _ = std::process::Command::new("wt.exe")
-                .args(["-w", "0", "focus-tab", "-t", &idx.to_string()])
-                .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                .spawn();
-        }
-    } else {
+    let Some(idx) = tab_index else {
         tracing::debug!("  → target shell PID {} not found in WT children", target_shell_pid);
+        return None;
+    };
+    tracing::debug!("  → switching to tab index {} via wt.exe", idx);
+    focus_wt_tab(idx);
+
+    // 4. Check the tab showing by its title
+    let titles: Vec<Option<String>> = children.iter().map(|(pid, _)| console_title(*pid)).collect();
+    let target = titles[idx].clone().filter(|t| !t.is_empty())?;
+    if titles.iter().filter(|t| t.as_deref() == Some(target.as_str())).count() > 1 {
+        tracing::debug!("  → tab title {:?} is shared with another tab, not checked", target);
+        return None;
+    }
+    if wait_for_title(hwnd, &target) {
+        return Some(true);
+    }
+    for i in (0..children.len()).filter(|&i| i != idx).take(MAX_TAB_RETRIES) {
+        focus_wt_tab(i);
+        if wait_for_title(hwnd, &target) {
+            tracing::info!("switch_wt_tab: shell PID {} is in tab {}, not {} as its start order suggested", target_shell_pid, i, idx);
+            return Some(true);
+        }
+    }
+    let shown = get_window_title(hwnd);
+    focus_wt_tab(idx);
+    tracing::warn!(
+        wt_pid,
+        shell_pid = target_shell_pid,
+        tabs = children.len(),
+        guessed_tab = idx,
+        expected_title = %target,
+        window_title = %shown,
+        "Focused Windows Terminal but could not bring up the session's tab"
+    );
+    Some(false)
+}
+
+#[cfg(windows)]
+fn focus_wt_tab(index: usize) {
+    use std::os::windows::process::CommandExt;
+    let _ = std::process::Command::new("wt.exe")
+        .args(["-w", "0", "focus-tab", "-t", &index.to_string()])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .spawn();
+}
+
+/// Wait up to 500 ms for window `hwnd` to take the title `title` (a tab
+/// switch is asynchronous).
+#[cfg(windows)]
+fn wait_for_title(hwnd: isize, title: &str) -> bool {
+    for _ in 0..10 {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        if get_window_title(hwnd).trim() == title.trim() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Title of the console `pid` runs in, which WT shows on its tab, read by
+/// attaching to that console for a moment. `None` if that fails, and always
+/// when this process has a console of its own (a debug build), which
+/// attaching would give up.
+#[cfg(windows)]
+fn console_title(pid: u32) -> Option<String> {
+    use windows::Win32::System::Console::{AttachConsole, FreeConsole, GetConsoleTitleW, GetConsoleWindow};
+
+    // Attaching is per process: one lookup at a time
+    static CONSOLE: Mutex<()> = Mutex::new(());
+    let _guard = mutex_lock!(CONSOLE);
+    unsafe {
+        if !GetConsoleWindow().0.is_null() {
+            return None;
+        }
+        AttachConsole(pid).ok()?;
+        let mut buf = [0u16; 1024];
+        let len = GetConsoleTitleW(&mut buf) as usize;
+        let _ = FreeConsole();
+        Some(String::from_utf16_lossy(&buf[..len.min(buf.len())]))
     }
 }
 
@@ -295,75 +406,24 @@ fn get_process_create_time(pid: u32) -> u64 {
 }
 
 #[cfg(windows)]
-fn find_window_for_pid(target_pid: u32) -> Option<isize> {
-    use windows::Win32::UI::WindowsAndMessaging::*;
-    unsafe {
-        let mut hwnd = match GetTopWindow(None) {
-            Ok(h) => h,
-            Err(_) => return None,
-        };
-
-        loop {
-            if IsWindowVisible(hwnd).as_bool() {
-                let mut wnd_pid: u32 = 0;
-                GetWindowThreadProcessId(hwnd, Some(&mut wnd_pid));
-                if wnd_pid == target_pid {
-                    let text_len = GetWindowTextLengthW(hwnd);
-                    if text_len > 0 {
-                        return Some(hwnd.0 as isize);
-                    }
-                }
-            }
-            hwnd = match GetWindow(hwnd, GW_HWNDNEXT) {
-                Ok(h) => h,
-                Err(_) => break,
-            };
-        }
-    }
-
-    None
-}
-
-#[cfg(windows)]
-fn find_terminal_by_title(cwd: &str) -> Option<isize> {
-    use windows::Win32::UI::WindowsAndMessaging::*;
-
-    let cwd_lower = cwd.replace('/', "\\").to_lowercase();
-    let cwd_fwd = cwd.replace('\\', "/").to_lowercase();
-    let basename = cwd.rsplit(&['/', '\\']).next().unwrap_or("").to_lowercase();
+fn find_terminal_by_title(variants: &[String]) -> Option<isize> {
+    use windows::Win32::Foundation::HWND;
 
-    let variants = vec![cwd_lower, cwd_fwd, basename];
+    let processes = snapshot::processes();
 
-    unsafe {
-        let mut hwnd = match GetTopWindow(None) {
-            Ok(h) => h,
-            Err(_) => return None,
+    for w in &snapshot::windows().windows {
+        let title = w.title.to_lowercase();
+        if !variants.iter().any(|v| !v.is_empty() && title.contains(v.as_str())) {
+            continue;
+        }
+        // Started after the cached snapshot: ask the process itself
+        let proc_name = match processes.name(w.pid) {
+            Some(name) => name.to_string(),
+            None => get_window_process_name(HWND(w.hwnd as *mut _)),
         };
-
-        loop {
-            if IsWindowVisible(hwnd).as_bool() {
-                let text_len = GetWindowTextLengthW(hwnd);
-                if text_len > 0 {
-                    let mut buf = vec![0u16; text_len as usize + 1];
-                    GetWindowTextW(hwnd, &mut buf);
-                    let title = String::from_utf16_lossy(&buf).to_lowercase();
-
-                    if variants.iter().any(|v| !v.is_empty() && title.contains(v.as_str())) {
-                        let proc_name = get_window_process_name(hwnd);
-                        if proc_name.is_empty() { continue; }
-                        let proc_lower = proc_name.to_lowercase();
-
-                        // Only match known terminal processes — never focus random windows
-                        if TERMINAL_PROCESSES.contains(&proc_lower.as_str()) {
-                            return Some(hwnd.0 as isize);
-                        }
-                    }
-                }
-            }
-            hwnd = match GetWindow(hwnd, GW_HWNDNEXT) {
-                Ok(h) => h,
-                Err(_) => break,
-            };
+        // Only match known terminal processes — never focus random windows
+        if TERMINAL_PROCESSES.contains(&proc_name.to_lowercase().as_str()) {
+            return Some(w.hwnd);
         }
     }
 
@@ -410,19 +470,69 @@ fn get_window_process_name(hwnd: windows::Win32::Foundation::HWND) -> String {
     }
 }
 
+/// Raise `hwnd` to the top of the z-order (restoring it if minimized) and
+/// flash its caption and taskbar button a few times, without activating it.
+#[cfg(windows)]
+pub fn peek_hwnd(hwnd: isize) -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::*;
+    use windows::Win32::Foundation::HWND;
+
+    unsafe {
+        let h = HWND(hwnd as *mut _);
+        if IsIconic(h).as_bool() {
+            let _ = ShowWindow(h, SW_SHOWNOACTIVATE);
+        }
+        let raised = SetWindowPos(h, Some(HWND_TOP), 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE).is_ok();
+        let info = FLASHWINFO {
+            cbSize: std::mem::size_of::<FLASHWINFO>() as u32,
+            hwnd: h,
+            dwFlags: FLASHW_CAPTION | FLASHW_TRAY,
+            uCount: 3,
+            dwTimeout: 0,
+        };
+        let _ = FlashWindowEx(&info);
+        raised
+    }
+}
+
+/// Bring `hwnd` to the foreground. Windows only lets the thread that owns
+/// the foreground hand it over, so our thread briefly shares input state
+/// with that thread (`AttachThreadInput`) while raising the window.
 #[cfg(windows)]
 pub fn focus_hwnd(hwnd: isize) -> bool {
     use windows::Win32::UI::WindowsAndMessaging::*;
     use windows::Win32::UI::Input::KeyboardAndMouse::*;
+    use windows::Win32::System::Threading::{AttachThreadInput, GetCurrentThreadId};
     use windows::Win32::Foundation::HWND;
 
     unsafe {
         let h = HWND(hwnd as *mut _);
+        if IsIconic(h).as_bool() {
+            let _ = ShowWindow(h, SW_RESTORE);
+        }
+
+        let this_thread = GetCurrentThreadId();
+        let fg_thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+        let attached = fg_thread != 0
+            && fg_thread != this_thread
+            && AttachThreadInput(this_thread, fg_thread, true).as_bool();
+        let _ = BringWindowToTop(h);
+        let _ = SetForegroundWindow(h);
+        if attached {
+            let _ = AttachThreadInput(this_thread, fg_thread, false);
+        }
+        if GetForegroundWindow() == h {
+            return true;
+        }
+
+        if !ALT_KEY_FALLBACK.load(Ordering::Relaxed) {
+            tracing::debug!("focus_hwnd: Windows kept the foreground from hwnd {}", hwnd);
+            return false;
+        }
         // Alt-key trick to allow SetForegroundWindow
         keybd_event(0x12, 0, KEYBD_EVENT_FLAGS(0), 0);       // VK_MENU down
-        let _ = ShowWindow(h, SW_RESTORE);
         let _ = SetForegroundWindow(h);
         keybd_event(0x12, 0, KEYBD_EVENT_FLAGS(2), 0);       // VK_MENU up
-        true
+        GetForegroundWindow() == h
     }
 }
@@ -0,0 +1,117 @@
+//! Heartbeats of the background loops in `run_server`.
+//!
+//! Each periodic task registers here with its interval and reports every
+//! round: done, or failed with an error (a panicked `spawn_blocking`
+//! included). A loop that has died stops reporting, and once it is three
+//! intervals overdue `/api/diagnostics` lists it under `tasks_stale`, so a
+//! dead scanner or flusher shows up by name rather than as data that
+//! quietly stops changing.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Intervals a task may miss before it counts as stale.
+const STALE_AFTER_INTERVALS: f64 = 3.0;
+
+/// A registered task, as `/api/diagnostics` shows it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Task {
+    pub name: &'static str,
+    /// Seconds between runs.
+    pub interval: u64,
+    /// Unix time the task was registered.
+    pub since: f64,
+    /// Unix time of its latest run, failed or not.
+    pub last_run: Option<f64>,
+    /// The latest failure and when it happened; kept after later
+    /// successful runs.
+    pub last_error: Option<String>,
+    pub last_error_at: Option<f64>,
+    pub runs: u64,
+    pub failures: u64,
+    /// No run for `STALE_AFTER_INTERVALS` intervals.
+    pub stale: bool,
+}
+
+pub struct Heartbeats {
+    tasks: RwLock<BTreeMap<&'static str, Task>>,
+}
+
+impl Heartbeats {
+    pub fn new() -> Self {
+        Self { tasks: RwLock::new(BTreeMap::new()) }
+    }
+
+    /// Start tracking `name`, expected to run every `interval`.
+    pub fn register(&self, name: &'static str, interval: Duration) {
+        write_lock!(self.tasks).insert(name, Task {
+            name,
+            interval: interval.as_secs(),
+            since: now_secs(),
+            last_run: None,
+            last_error: None,
+            last_error_at: None,
+            runs: 0,
+            failures: 0,
+            stale: false,
+        });
+    }
+
+    /// `name` finished a run.
+    pub fn beat(&self, name: &'static str) {
+        if let Some(task) = write_lock!(self.tasks).get_mut(name) {
+            task.last_run = Some(now_secs());
+            task.runs += 1;
+        }
+    }
+
+    /// `name` ran and failed.
+    pub fn fail(&self, name: &'static str, error: impl Display) {
+        tracing::warn!("Background task {} failed: {}", name, error);
+        if let Some(task) = write_lock!(self.tasks).get_mut(name) {
+            let now = now_secs();
+            task.last_run = Some(now);
+            task.last_error = Some(error.to_string());
+            task.last_error_at = Some(now);
+            task.runs += 1;
+            task.failures += 1;
+        }
+    }
+
+    /// Report a run from its result (e.g. a `spawn_blocking` join), and
+    /// pass on the value of a successful one.
+    pub fn report<T, E: Display>(&self, name: &'static str, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(v) => {
+                self.beat(name);
+                Some(v)
+            }
+            Err(e) => {
+                self.fail(name, e);
+                None
+            }
+        }
+    }
+
+    /// Every task, by name, with `stale` worked out as of now.
+    pub fn tasks(&self) -> Vec<Task> {
+        let now = now_secs();
+        read_lock!(self.tasks)
+            .values()
+            .map(|t| {
+                let mut t = t.clone();
+                let since = t.last_run.unwrap_or(t.since);
+                t.stale = now - since > t.interval as f64 * STALE_AFTER_INTERVALS;
+                t
+            })
+            .collect()
+    }
+}
+
+fn now_secs() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()
+}
@@ -0,0 +1,140 @@
+//! Silent-hook detection.
+//!
+//! An agent the scanner sees that has sent no hook event since it started
+//! almost always means its hooks are missing or broken (settings.json
+//! edited, hook binary moved). Each such process is reported once, and the
+//! current list shows in the tray tooltip and `/api/diagnostics`.
+//!
+//! Requests the hook binary failed to deliver go to an error spool in the
+//! temp dir; `take_spooled` drains it so they can be shown here too.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::RwLock;
+
+use crate::process::{cwd_key, ProcessInfo};
+
+/// An agent process whose CWD has sent no hook event since it started.
+#[derive(Debug, Clone, Serialize)]
+pub struct SilentAgent {
+    pub pid: u32,
+    pub agent_type: String,
+    pub cwd: String,
+    /// Seconds since the process started.
+    pub uptime: u64,
+}
+
+/// Spool the hook binary appends failed requests to (see hooks/src/main.rs).
+const ERROR_SPOOL: &str = "agent-desk-hook-errors.jsonl";
+
+/// Hook errors kept for `/api/diagnostics`.
+const MAX_ERRORS: usize = 20;
+
+/// A request the hook binary or its daemon could not deliver.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookError {
+    pub ts: f64,
+    /// `hook` or `daemon`.
+    pub source: String,
+    pub event: String,
+    pub url: String,
+    pub error: String,
+    pub session_id: String,
+    pub cwd: String,
+    pub correlation_id: String,
+    pub version: String,
+}
+
+/// Drain the error spool. The file is renamed before reading so lines the
+/// hook appends meanwhile land in a fresh spool.
+pub fn take_spooled() -> Vec<HookError> {
+    let spool = std::env::temp_dir().join(ERROR_SPOOL);
+    if !spool.exists() {
+        return Vec::new();
+    }
+    let taken = spool.with_extension("jsonl.taken");
+    if let Err(e) = std::fs::rename(&spool, &taken) {
+        tracing::debug!("Hook error spool not taken: {}", e);
+        return Vec::new();
+    }
+    let content = std::fs::read_to_string(&taken).unwrap_or_default();
+    let _ = std::fs::remove_file(&taken);
+    content
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect()
+}
+
+pub struct HookHealth {
+    /// Normalized CWD → unix time of its latest hook event.
+    last_hook: RwLock<HashMap<String, f64>>,
+    /// PIDs already reported, so each gets one warning.
+    reported: RwLock<HashSet<u32>>,
+    silent: RwLock<Vec<SilentAgent>>,
+    /// Latest hook errors, oldest first.
+    errors: RwLock<VecDeque<HookError>>,
+}
+
+impl HookHealth {
+    pub fn new() -> Self {
+        Self {
+            last_hook: RwLock::new(HashMap::new()),
+            reported: RwLock::new(HashSet::new()),
+            silent: RwLock::new(Vec::new()),
+            errors: RwLock::new(VecDeque::new()),
+        }
+    }
+
+    /// A hook event arrived from `cwd`.
+    pub fn record(&self, cwd: &str, now: f64) {
+        if cwd.is_empty() {
+            return;
+        }
+        let key = cwd_key(cwd);
+        write_lock!(self.silent).retain(|a| cwd_key(&a.cwd) != key);
+        write_lock!(self.last_hook).insert(key, now);
+    }
+
+    /// Re-evaluate against a fresh scan. `hooked` says whether an agent
+    /// type reports through hooks at all; processes younger than
+    /// `after_secs` get the benefit of the doubt. Returns the agents that
+    /// just turned silent.
+    pub fn check(&self, procs: &[ProcessInfo], hooked: impl Fn(&str) -> bool, after_secs: u64) -> Vec<SilentAgent> {
+        let last_hook = read_lock!(self.last_hook);
+        let silent: Vec<SilentAgent> = procs
+            .iter()
+            .filter(|p| hooked(&p.agent_type) && !p.cwd.is_empty() && p.uptime >= after_secs)
+            .filter(|p| {
+                // Without a start time, any hook from the CWD will do
+                last_hook.get(&cwd_key(&p.cwd)).is_none_or(|ts| *ts < p.create_time)
+            })
+            .map(|p| SilentAgent { pid: p.pid, agent_type: p.agent_type.clone(), cwd: p.cwd.clone(), uptime: p.uptime })
+            .collect();
+        drop(last_hook);
+
+        let mut reported = write_lock!(self.reported);
+        let live: HashSet<u32> = procs.iter().map(|p| p.pid).collect();
+        reported.retain(|pid| live.contains(pid));
+        let fresh = silent.iter().filter(|a| reported.insert(a.pid)).cloned().collect();
+        *write_lock!(self.silent) = silent;
+        fresh
+    }
+
+    pub fn silent(&self) -> Vec<SilentAgent> {
+        read_lock!(self.silent).clone()
+    }
+
+    pub fn record_errors(&self, errors: &[HookError]) {
+        let mut kept = write_lock!(self.errors);
+        kept.extend(errors.iter().cloned());
+        while kept.len() > MAX_ERRORS {
+            kept.pop_front();
+        }
+    }
+
+    /// Latest hook errors, newest first.
+    pub fn errors(&self) -> Vec<HookError> {
+        read_lock!(self.errors).iter().rev().cloned().collect()
+    }
+}
@@ -0,0 +1,82 @@
+//! Projects never monitored (`general.ignore_paths`): hooks from sessions
+//! whose CWD matches are answered without being recorded, and scanned
+//! agent processes there are left out of the session list, so scratch
+//! folders or someone else's mounted repos never show up or notify. The
+//! same patterns pick out priority projects (`island.priority_paths`),
+//! whose permission requests go ahead of the others.
+//!
+//! Patterns are globs over `/`-separated paths: `*` and `?` stay within one
+//! folder name, `**` spans folders, and a leading `~/` is the home folder.
+//! A pattern covers the folders it matches and everything below them, so
+//! `D:/scratch` ignores `D:/scratch/foo` too. Matching ignores case on
+//! Windows.
+
+use regex::Regex;
+
+pub struct PathPatterns {
+    patterns: Vec<Regex>,
+}
+
+impl PathPatterns {
+    /// Compile the patterns of `setting`; invalid ones are logged and
+    /// skipped.
+    pub fn new(setting: &str, globs: &[String]) -> Self {
+        let patterns = globs
+            .iter()
+            .filter(|g| !g.trim().is_empty())
+            .filter_map(|g| match Regex::new(&to_regex(g)) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    tracing::warn!("{}: invalid pattern {:?} skipped: {}", setting, g, e);
+                    None
+                }
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    /// Whether `cwd` is one of the folders, or below one.
+    pub fn matches(&self, cwd: &str) -> bool {
+        if self.patterns.is_empty() || cwd.is_empty() {
+            return false;
+        }
+        let path = normalize(cwd);
+        self.patterns.iter().any(|re| re.is_match(&path))
+    }
+}
+
+/// `/` separators, no trailing `/`, lowercase on Windows.
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_end_matches('/');
+    if cfg!(windows) { path.to_lowercase() } else { path.to_string() }
+}
+
+/// Anchored regex for `glob`, matching the path or anything below it.
+fn to_regex(glob: &str) -> String {
+    let glob = glob.trim();
+    let expanded = match glob.strip_prefix("~/").zip(home()) {
+        Some((rest, home)) => format!("{}/{}", home, rest),
+        None => glob.to_string(),
+    };
+    let glob = normalize(&expanded);
+    let mut out = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                out.push_str(".*");
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    out.push_str("(/.*)?$");
+    out
+}
+
+fn home() -> Option<String> {
+    std::env::var("USERPROFILE").or_else(|_| std::env::var("HOME")).ok()
+}
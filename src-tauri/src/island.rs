@@ -4,48 +4,86 @@
 //! into a pill / rounded-rect shape. Tauri `transparent: false` avoids the
 //! WebView2 hit-test bug on Windows while still giving us custom shapes.
 //!
+//! All resizing runs on a dedicated animation thread that owns the window.
+//! Callers enqueue `AnimCommand`s and return immediately; the thread drains
+//! the queue, coalesces bursts (last expand/collapse wins, last pill width
+//! wins), and plays each transition with frame timing derived from elapsed
+//! time rather than fixed sleeps, so a slow frame shortens the next wait
+//! instead of stretching the whole animation. A command arriving mid-
+//! transition cancels it, and the next transition starts from the frame on
+//! screen, so quick pill ↔ panel toggles reverse smoothly instead of
+//! snapping to the end state first.
+//!
 //! Transitions:
-//! - **Pill width** (idle ↔ active): 6-frame spring with overshoot (~150ms)
-//! - **Expand** (pill → panel): 10-frame spring ease-out (~200ms)
-//! - **Collapse** (panel → pill): 8-frame ease-out (~160ms)
-
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::time::Duration;
+//! - **Pill width** (idle ↔ active): spring with overshoot (~150ms)
+//! - **Expand** (pill → panel): spring ease-out (~200ms)
+//! - **Collapse** (panel → pill): ease-out (~160ms)
+//!
+//! Geometry is computed in physical pixels from the primary monitor's own
+//! scale factor, so size, position and clip region always agree even when
+//! monitors have different DPI. A DPI change (`WM_DPICHANGED`), a change to
+//! the monitor layout (docking, resolution, monitors added or removed) or
+//! finding the window off every monitor re-applies the resting shape and
+//! position, clamped to the monitor.
+
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tauri::WebviewWindow;
 
+use crate::status::PillState;
+
 // Fixed dimensions (not configurable)
 const PILL_H: u32 = 36;
 const PILL_RADIUS: i32 = 18;
 const PANEL_RADIUS: i32 = 16;
 
-// Default pill width — only used for AtomicU32 initialization
-const DEFAULT_PILL_W: u32 = 300;
-
-// Pill animation state
-static PILL_TARGET_W: AtomicU32 = AtomicU32::new(DEFAULT_PILL_W);
-static PILL_ANIMATING: AtomicBool = AtomicBool::new(false);
-static ISLAND_EXPANDED: AtomicBool = AtomicBool::new(false);
-
-// Morph animation guard + stored expanded dimensions (for collapse)
-static MORPH_ANIMATING: AtomicBool = AtomicBool::new(false);
-static EXPANDED_W: AtomicU32 = AtomicU32::new(480);
-static EXPANDED_H: AtomicU32 = AtomicU32::new(320);
-
 /// Pill width spring keyframes (normalized 0→1 with overshoot).
-const SPRING_FRAMES: [f64; 6] = [0.30, 0.65, 1.00, 1.15, 1.05, 1.00];
-const FRAME_MS: u64 = 25;
+const SPRING_CURVE: [f64; 6] = [0.30, 0.65, 1.00, 1.15, 1.05, 1.00];
+const SPRING_DURATION: Duration = Duration::from_millis(150);
 
-/// Expand: spring ease-out with subtle overshoot (~200ms, 10 frames × 20ms).
+/// Expand: spring ease-out with subtle overshoot.
 const EXPAND_CURVE: [f64; 10] = [
     0.12, 0.33, 0.54, 0.72, 0.86, 0.95, 1.01, 1.03, 1.01, 1.00,
 ];
+const EXPAND_DURATION: Duration = Duration::from_millis(200);
 
-/// Collapse: smooth ease-out (~160ms, 8 frames × 20ms).
+/// Collapse: smooth ease-out.
 const COLLAPSE_CURVE: [f64; 8] = [
     0.15, 0.38, 0.60, 0.78, 0.90, 0.97, 0.99, 1.00,
 ];
+const COLLAPSE_DURATION: Duration = Duration::from_millis(160);
+
+/// Target frame interval (~60 fps). Actual progress is always computed from
+/// elapsed time, so this only bounds how often we touch the window.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How often the idle animation thread checks the monitor layout
+/// (resolution, scaling or monitors changed) and that the island is still
+/// on screen.
+const MONITOR_POLL: Duration = Duration::from_secs(2);
+
+/// Commands accepted by the animation thread.
+#[derive(Debug, Clone, Copy)]
+enum AnimCommand {
+    Expand { w: u32, h: u32 },
+    Collapse,
+    PillWidth(u32),
+    /// Re-apply the current resting shape and position (DPI or monitor
+    /// change, or a manual reset).
+    Refresh,
+}
+
+/// Queue into the animation thread (set once by `setup`).
+static ANIM_TX: OnceLock<Mutex<Sender<AnimCommand>>> = OnceLock::new();
 
-const MORPH_FRAME_MS: u64 = 20;
+fn send(cmd: AnimCommand) {
+    if let Some(tx) = ANIM_TX.get() {
+        let _ = mutex_lock!(tx).send(cmd);
+    }
+}
 
 // ---------------------------------------------------------------------------
 // Helpers
@@ -59,6 +97,21 @@ fn lerp_i32(a: i32, b: i32, t: f64) -> i32 {
     (a as f64 + (b as f64 - a as f64) * t).round() as i32
 }
 
+/// Sample a keyframe curve at `progress` (0→1), interpolating linearly
+/// between keyframes. Keyframe `i` sits at progress `(i + 1) / len`, with an
+/// implicit 0.0 at progress 0.
+fn sample_curve(curve: &[f64], progress: f64) -> f64 {
+    if curve.is_empty() || progress >= 1.0 {
+        return 1.0;
+    }
+    let pos = progress.max(0.0) * curve.len() as f64;
+    let idx = pos.floor() as usize;
+    let frac = pos - idx as f64;
+    let from = if idx == 0 { 0.0 } else { curve[idx - 1] };
+    let to = curve[idx.min(curve.len() - 1)];
+    from + (to - from) * frac
+}
+
 /// Apply a rounded-rect region to an HWND.
 /// All coordinates are in **physical pixels** (pre-scaled).
 #[cfg(windows)]
@@ -78,172 +131,427 @@ fn get_hwnd(window: &WebviewWindow) -> Option<windows::Win32::Foundation::HWND>
     Some(windows::Win32::Foundation::HWND(raw.0))
 }
 
-/// Position the window at top-center of the primary monitor.
-pub fn position_top_center(window: &WebviewWindow, w: u32, h: u32) {
-    if let Ok(Some(monitor)) = window.primary_monitor() {
-        let scale = monitor.scale_factor();
-        let screen_w = monitor.size().width as f64 / scale;
-        let x = (screen_w - w as f64) / 2.0;
-        let y = 8.0; // small gap from top edge
-        let _ = window.set_size(tauri::Size::Logical(tauri::LogicalSize::new(w as f64, h as f64)));
-        let _ = window.set_position(tauri::Position::Logical(tauri::LogicalPosition::new(x, y)));
+/// A monitor's bounds in physical pixels, with its scale factor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MonitorFrame {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    scale: f64,
+}
+
+impl MonitorFrame {
+    fn of(m: &tauri::Monitor) -> Self {
+        Self {
+            x: m.position().x,
+            y: m.position().y,
+            width: m.size().width,
+            height: m.size().height,
+            scale: m.scale_factor(),
+        }
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width as i32 && y >= self.y && y < self.y + self.height as i32
     }
 }
 
-/// Apply the rounded region, accounting for DPI scale factor.
-pub fn apply_shape(window: &WebviewWindow, w: u32, h: u32, radius: i32) {
+/// The monitor the island lives on: the primary, else any monitor at all
+/// (the primary can briefly be unknown while displays are changing).
+fn monitor_frame(window: &WebviewWindow) -> Option<MonitorFrame> {
+    if let Some(m) = window.primary_monitor().ok().flatten() {
+        return Some(MonitorFrame::of(&m));
+    }
+    window.available_monitors().ok()?.first().map(MonitorFrame::of)
+}
+
+/// Every monitor, to notice layout changes.
+fn monitor_layout(window: &WebviewWindow) -> Vec<MonitorFrame> {
+    window.available_monitors().map(|ms| ms.iter().map(MonitorFrame::of).collect()).unwrap_or_default()
+}
+
+/// Whether the middle of the window's top edge is on one of `layout`'s
+/// monitors.
+fn on_screen(window: &WebviewWindow, layout: &[MonitorFrame]) -> bool {
+    let (Ok(pos), Ok(size)) = (window.outer_position(), window.outer_size()) else { return true };
+    let (x, y) = (pos.x + size.width as i32 / 2, pos.y + 1);
+    layout.iter().any(|m| m.contains(x, y))
+}
+
+/// Logical → physical pixels at `scale`; size, position and region all
+/// round the same way so they line up exactly.
+fn to_physical(v: u32, scale: f64) -> u32 {
+    (v as f64 * scale).round() as u32
+}
+
+/// Position the window at top-center of the primary monitor. Works in that
+/// monitor's physical pixels: logical coordinates would be converted with
+/// the scale of whichever monitor the window is on right now.
+/// Returns the scale factor used, or `None` if there is no monitor.
+pub fn position_top_center(window: &WebviewWindow, w: u32, h: u32) -> Option<f64> {
+    let m = monitor_frame(window)?;
+    let pw = to_physical(w, m.scale);
+    // Centered, but never past the monitor's left or right edge
+    let x = m.x + ((m.width as i32 - pw as i32) / 2).max(0);
+    let y = m.y + to_physical(8, m.scale) as i32; // small gap from top edge
+    // Move first: landing on another monitor may rescale the window, and
+    // the size set afterwards wins
+    let _ = window.set_position(tauri::Position::Physical(tauri::PhysicalPosition::new(x, y)));
+    let _ = window.set_size(tauri::Size::Physical(tauri::PhysicalSize::new(pw, to_physical(h, m.scale))));
+    Some(m.scale)
+}
+
+/// Apply the rounded region for a `w`×`h` logical window sized at `scale`.
+pub fn apply_shape(window: &WebviewWindow, w: u32, h: u32, radius: i32, scale: f64) {
     #[cfg(windows)]
     {
         if let Some(hwnd) = get_hwnd(window) {
-            let scale = window.scale_factor().unwrap_or(1.0);
-            let pw = (w as f64 * scale) as i32;
-            let ph = (h as f64 * scale) as i32;
-            let pr = (radius as f64 * scale) as i32;
+            let pw = to_physical(w, scale) as i32;
+            let ph = to_physical(h, scale) as i32;
+            let pr = to_physical(radius.max(0) as u32, scale) as i32;
             apply_region(hwnd, pw, ph, pr);
         }
     }
     #[cfg(not(windows))]
     {
-        let _ = (w, h, radius);
+        let _ = (window, w, h, radius, scale);
     }
 }
 
 // ---------------------------------------------------------------------------
-// Pill width animation (idle ↔ active)
+// Animation thread
 // ---------------------------------------------------------------------------
 
-/// Animate pill width with spring overshoot (idle ↔ active).
-///
-/// Called from a blocking context. Updates `PILL_TARGET_W`, then plays a
-/// 6-frame spring animation (~150 ms) that overshoots the target by ~15%
-/// before settling. Skips if panel is expanded or another animation is
-/// already running.
-pub fn set_pill_active(window: &WebviewWindow, active: bool, pill_w: u32, pill_w_active: u32) {
-    let target = if active { pill_w_active } else { pill_w };
-    let prev = PILL_TARGET_W.swap(target, Ordering::SeqCst);
-    if prev == target {
-        return;
+/// State owned by the animation thread — no other thread touches geometry.
+struct Animator {
+    window: WebviewWindow,
+    /// Pill width to rest at when collapsed.
+    pill_w: u32,
+    expanded: bool,
+    /// Last expanded panel size (collapse animates from here).
+    expanded_w: u32,
+    expanded_h: u32,
+    /// Monitor layout the current shape was applied for.
+    layout: Vec<MonitorFrame>,
+    rx: Receiver<AnimCommand>,
+    /// Command that cancelled the last transition, handled next.
+    pending: Option<AnimCommand>,
+    /// Width, height and corner radius on screen, i.e. the last frame
+    /// drawn; transitions start from here.
+    current: Cell<(u32, u32, i32)>,
+}
+
+impl Animator {
+    fn run(mut self) {
+        loop {
+            let received = match self.pending.take() {
+                Some(cmd) => Ok(cmd),
+                None => self.rx.recv_timeout(MONITOR_POLL),
+            };
+            let first = match received {
+                Ok(cmd) => cmd,
+                Err(RecvTimeoutError::Timeout) => {
+                    let now = monitor_layout(&self.window);
+                    if now.is_empty() {
+                        continue;
+                    }
+                    if now != self.layout {
+                        tracing::info!("Monitor layout changed ({} -> {} monitors), repositioning island", self.layout.len(), now.len());
+                        self.refresh();
+                    } else if !on_screen(&self.window, &now) {
+                        tracing::info!("Island is off screen, repositioning");
+                        self.refresh();
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            // Coalesce everything queued behind the first command.
+            let mut morph = None;
+            let mut pill = None;
+            let mut refresh = false;
+            for cmd in std::iter::once(first).chain(self.rx.try_iter()) {
+                match cmd {
+                    AnimCommand::PillWidth(w) => pill = Some(w),
+                    AnimCommand::Refresh => refresh = true,
+                    m => morph = Some(m),
+                }
+            }
+
+            if let Some(w) = pill {
+                self.pill_w = w;
+            }
+
+            match morph {
+                Some(AnimCommand::Expand { w, h }) => self.expand(w, h),
+                Some(AnimCommand::Collapse) => self.collapse(),
+                _ => {
+                    // Pill width only matters while collapsed — when expanded
+                    // the new width is picked up by the next collapse. A
+                    // transition cut short by this command is finished first.
+                    let (current_w, current_h, _) = self.current.get();
+                    if self.expanded && (current_w, current_h) != (self.expanded_w, self.expanded_h) {
+                        self.expand(self.expanded_w, self.expanded_h);
+                    } else if !self.expanded && current_h != PILL_H {
+                        self.collapse();
+                    } else if !self.expanded && pill.is_some() && current_w != self.pill_w {
+                        self.animate_pill(current_w, self.pill_w);
+                    } else if refresh {
+                        self.refresh();
+                    }
+                }
+            }
+            self.layout = monitor_layout(&self.window);
+        }
     }
 
-    // Don't animate if panel is showing — just store the target for next collapse
-    if ISLAND_EXPANDED.load(Ordering::SeqCst) {
-        return;
+    /// Re-apply the resting shape and position for the current monitor
+    /// layout.
+    fn refresh(&mut self) {
+        if self.expanded {
+            self.set_geometry(self.expanded_w, self.expanded_h, PANEL_RADIUS);
+        } else {
+            self.set_geometry(self.pill_w, PILL_H, PILL_RADIUS);
+        }
+        self.layout = monitor_layout(&self.window);
     }
 
-    // Skip if already animating (target is stored, next cycle will catch up)
-    if PILL_ANIMATING
-        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-        .is_err()
-    {
-        return;
+    /// Play a transition: `frame(t)` is called with the curve value for the
+    /// elapsed fraction of `duration`, ending exactly at t = 1.0. A command
+    /// arriving in between stops it where it is and is kept in `pending`.
+    fn play(&mut self, curve: &[f64], duration: Duration, frame: impl Fn(&Self, f64)) {
+        let start = Instant::now();
+        let mut next_frame = start;
+        loop {
+            let progress = start.elapsed().as_secs_f64() / duration.as_secs_f64();
+            frame(self, sample_curve(curve, progress));
+            if progress >= 1.0 {
+                break;
+            }
+            if let Ok(cmd) = self.rx.try_recv() {
+                self.pending = Some(cmd);
+                break;
+            }
+            // Sleep until the next frame boundary; time spent in
+            // set_size/SetWindowRgn comes out of this wait, not on top of it.
+            next_frame += FRAME_INTERVAL;
+            let now = Instant::now();
+            if next_frame > now {
+                std::thread::sleep(next_frame - now);
+            } else {
+                next_frame = now;
+            }
+        }
+    }
+
+    fn set_geometry(&self, w: u32, h: u32, r: i32) {
+        if let Some(scale) = position_top_center(&self.window, w, h) {
+            apply_shape(&self.window, w, h, r, scale);
+            self.current.set((w, h, r));
+        }
+    }
+
+    fn animate_pill(&mut self, from: u32, to: u32) {
+        let diff = to as f64 - from as f64;
+        self.play(&SPRING_CURVE, SPRING_DURATION, move |a, t| {
+            let w = (from as f64 + diff * t).round() as u32;
+            a.set_geometry(w, PILL_H, PILL_RADIUS);
+        });
     }
 
-    let diff = target as f64 - prev as f64;
-    for &f in &SPRING_FRAMES {
-        let w = (prev as f64 + diff * f).round() as u32;
-        position_top_center(window, w, PILL_H);
-        apply_shape(window, w, PILL_H, PILL_RADIUS);
-        std::thread::sleep(Duration::from_millis(FRAME_MS));
+    fn expand(&mut self, panel_w: u32, panel_h: u32) {
+        let (start_w, start_h, start_r) = self.current.get();
+        self.expanded = true;
+        self.expanded_w = panel_w;
+        self.expanded_h = panel_h;
+
+        if start_w == panel_w && start_h == panel_h {
+            self.set_geometry(panel_w, panel_h, PANEL_RADIUS);
+            return;
+        }
+        self.play(&EXPAND_CURVE, EXPAND_DURATION, move |a, t| {
+            let w = lerp_u32(start_w, panel_w, t);
+            let h = lerp_u32(start_h, panel_h, t);
+            let r = lerp_i32(start_r, PANEL_RADIUS, t);
+            a.set_geometry(w, h, r);
+        });
     }
 
-    PILL_ANIMATING.store(false, Ordering::SeqCst);
+    fn collapse(&mut self) {
+        let target_w = self.pill_w;
+        let was_expanded = std::mem::replace(&mut self.expanded, false);
+        let (start_w, start_h, start_r) = self.current.get();
+        if !was_expanded && start_h == PILL_H {
+            // Already a pill — just set the shape directly (e.g. initial setup)
+            self.set_geometry(target_w, PILL_H, PILL_RADIUS);
+            return;
+        }
+        self.play(&COLLAPSE_CURVE, COLLAPSE_DURATION, move |a, t| {
+            let w = lerp_u32(start_w, target_w, t);
+            let h = lerp_u32(start_h, PILL_H, t);
+            let r = lerp_i32(start_r, PILL_RADIUS, t);
+            a.set_geometry(w, h, r);
+        });
+    }
 }
 
 // ---------------------------------------------------------------------------
-// Expand / Collapse with morph animation
+// Public API (non-blocking — enqueue and return)
 // ---------------------------------------------------------------------------
 
+/// Rest the pill at the width for `state`: `pill_w_active` from
+/// `Working` up (see `PillState::wide`), else `pill_w`.
+///
+/// If the panel is expanded the width is only stored and applied on the
+/// next collapse.
+pub fn set_pill_state(state: PillState, pill_w: u32, pill_w_active: u32) {
+    send(AnimCommand::PillWidth(if state.wide() { pill_w_active } else { pill_w }));
+}
+
 /// Expand from pill to full panel with spring animation.
+pub fn expand(panel_w: u32, panel_h: u32) {
+    send(AnimCommand::Expand { w: panel_w, h: panel_h });
+}
+
+/// Collapse from panel to pill with ease-out animation.
 ///
-/// Animates width, height, and corner radius from pill → panel over ~200ms.
-/// If another morph animation is running, jumps directly to the final state.
-pub fn expand(window: &WebviewWindow, panel_w: u32, panel_h: u32) {
-    ISLAND_EXPANDED.store(true, Ordering::SeqCst);
-    EXPANDED_W.store(panel_w, Ordering::SeqCst);
-    EXPANDED_H.store(panel_h, Ordering::SeqCst);
-
-    // If another morph is running, skip animation — just jump to final state
-    if MORPH_ANIMATING
-        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-        .is_err()
-    {
-        position_top_center(window, panel_w, panel_h);
-        apply_shape(window, panel_w, panel_h, PANEL_RADIUS);
-        return;
-    }
+/// If not currently expanded (e.g. initial setup), sets the pill shape
+/// directly without animation.
+pub fn collapse() {
+    send(AnimCommand::Collapse);
+}
 
-    let start_w = PILL_TARGET_W.load(Ordering::SeqCst);
-    let start_h = PILL_H;
-    let start_r = PILL_RADIUS;
+/// Put the island back at the top center of the primary monitor, for when
+/// it ended up somewhere it can't be reached.
+pub fn reset_position() {
+    send(AnimCommand::Refresh);
+}
 
-    for &t in &EXPAND_CURVE {
-        let w = lerp_u32(start_w, panel_w, t);
-        let h = lerp_u32(start_h, panel_h, t);
-        let r = lerp_i32(start_r, PANEL_RADIUS, t);
-        position_top_center(window, w, h);
-        apply_shape(window, w, h, r);
-        std::thread::sleep(Duration::from_millis(MORPH_FRAME_MS));
+/// Toggle island visibility (used by global hotkey and API).
+pub fn toggle_visibility(window: &WebviewWindow) {
+    let visible = window.is_visible().unwrap_or(true);
+    if visible {
+        use tauri::Manager;
+        crate::island_push::send(window.app_handle(), &crate::island_push::IslandPush::IslandHide);
+        let _ = window.hide();
+    } else {
+        let _ = window.show();
+        collapse();
     }
+}
 
-    MORPH_ANIMATING.store(false, Ordering::SeqCst);
+/// Window operations invoked by the island page when this process is a
+/// client of a headless service (which has no window to drive).
+#[tauri::command]
+pub fn island_window(app: tauri::AppHandle, action: String, width: Option<u32>, height: Option<u32>) {
+    use tauri::Manager;
+    match action.as_str() {
+        "expand" => expand(width.unwrap_or(480), height.unwrap_or(320)),
+        "collapse" => collapse(),
+        "pill" => send(AnimCommand::PillWidth(width.unwrap_or(300))),
+        "hide" => {
+            if let Some(w) = app.get_webview_window("island") {
+                let _ = w.hide();
+            }
+        }
+        _ => tracing::debug!("Unknown island_window action: {}", action),
+    }
 }
 
-/// Collapse from panel to pill with ease-out animation.
-///
-/// Animates from stored expanded dimensions back to pill. If not currently
-/// expanded (e.g. initial setup), sets pill shape directly without animation.
-pub fn collapse(window: &WebviewWindow) {
-    let was_expanded = ISLAND_EXPANDED.swap(false, Ordering::SeqCst);
-    let target_w = PILL_TARGET_W.load(Ordering::SeqCst);
-
-    if !was_expanded {
-        // Not expanded — just set pill shape directly (e.g. initial setup)
-        position_top_center(window, target_w, PILL_H);
-        apply_shape(window, target_w, PILL_H, PILL_RADIUS);
+/// Keyboard-navigation actions accepted by `run_command`.
+pub const COMMANDS: &[&str] = &["next_item", "prev_item", "select", "approve", "deny"];
+
+/// Drive the island page from the keyboard (`island.nav_hotkeys` or
+/// `POST /api/island/command`). Moving the selection shows a hidden island.
+pub fn run_command(window: &WebviewWindow, action: &str) {
+    if !COMMANDS.contains(&action) {
         return;
     }
+    if matches!(action, "next_item" | "prev_item" | "select") && !window.is_visible().unwrap_or(true) {
+        let _ = window.show();
+    }
+    use tauri::Manager;
+    let command = crate::island_push::IslandPush::IslandCommand { action: action.to_string() };
+    crate::island_push::send(window.app_handle(), &command);
+}
 
-    // If another morph is running, skip animation — just jump to final state
-    if MORPH_ANIMATING
-        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-        .is_err()
-    {
-        position_top_center(window, target_w, PILL_H);
-        apply_shape(window, target_w, PILL_H, PILL_RADIUS);
-        return;
+/// Show a toast relayed from a headless service's `toast` SSE message.
+#[tauri::command]
+pub fn island_toast(
+    app: tauri::AppHandle,
+    title: String,
+    body: String,
+    sound: Option<String>,
+    group: Option<String>,
+    tag: Option<String>,
+    links: Option<crate::toast::ToastLinks>,
+) {
+    let key = group.as_deref().zip(tag.as_deref()).map(|(group, tag)| crate::toast::ToastKey { group, tag });
+    crate::toast::show(&app, &title, &body, key, links.as_ref());
+    if let Some(st) = sound {
+        crate::tray::play_notification_sound(&st);
     }
+}
 
-    let start_w = EXPANDED_W.load(Ordering::SeqCst);
-    let start_h = EXPANDED_H.load(Ordering::SeqCst);
-    let start_r = PANEL_RADIUS;
+// ─── Visibility persistence ─────────────────────────────
 
-    for &t in &COLLAPSE_CURVE {
-        let w = lerp_u32(start_w, target_w, t);
-        let h = lerp_u32(start_h, PILL_H, t);
-        let r = lerp_i32(start_r, PILL_RADIUS, t);
-        position_top_center(window, w, h);
-        apply_shape(window, w, h, r);
-        std::thread::sleep(Duration::from_millis(MORPH_FRAME_MS));
-    }
+/// `island.json` next to the sessions file: whether the island was hidden
+/// when the app last exited.
+pub fn visibility_path(sessions_file: &str) -> PathBuf {
+    Path::new(sessions_file).with_file_name("island.json")
+}
 
-    MORPH_ANIMATING.store(false, Ordering::SeqCst);
+/// Whether the island was hidden at the last exit (false if unknown).
+pub fn load_hidden(path: &Path) -> bool {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        .and_then(|v| v.get("hidden").and_then(|h| h.as_bool()))
+        .unwrap_or(false)
 }
 
-/// Toggle island visibility (used by global hotkey and API).
-pub fn toggle_visibility(window: &WebviewWindow) {
-    let visible = window.is_visible().unwrap_or(true);
-    if visible {
-        let _ = window.eval("if(typeof hideIsland==='function')hideIsland()");
-        let _ = window.hide();
-    } else {
-        let _ = window.show();
-        collapse(window);
+/// Record the island window's current visibility for the next start.
+pub fn save_visibility(window: &WebviewWindow, path: &Path) {
+    let hidden = !window.is_visible().unwrap_or(true);
+    let body = serde_json::json!({ "hidden": hidden }).to_string();
+    if let Err(e) = std::fs::write(path, body) {
+        tracing::warn!("Failed to save island visibility to {}: {}", path.display(), e);
     }
 }
 
-/// Initial setup: store configured pill width + set pill shape (no animation).
+/// Initial setup: start the animation thread and set the pill shape.
 pub fn setup(window: &WebviewWindow, pill_w: u32) {
-    PILL_TARGET_W.store(pill_w, Ordering::SeqCst);
-    // Direct shape set — collapse() would skip animation anyway since !was_expanded
-    collapse(window);
+    if ANIM_TX.get().is_none() {
+        let (tx, rx) = mpsc::channel();
+        let animator = Animator {
+            window: window.clone(),
+            pill_w,
+            expanded: false,
+            expanded_w: 0,
+            expanded_h: 0,
+            layout: Vec::new(),
+            rx,
+            pending: None,
+            current: Cell::new((pill_w, PILL_H, PILL_RADIUS)),
+        };
+        let spawned = std::thread::Builder::new()
+            .name("island-anim".into())
+            .spawn(move || animator.run());
+        match spawned {
+            Ok(_) => {
+                let _ = ANIM_TX.set(Mutex::new(tx));
+            }
+            Err(e) => tracing::warn!("Failed to spawn island animation thread: {}", e),
+        }
+        // Dragged to (or rescaled on) a monitor with another DPI: Windows
+        // resizes the window with it, which breaks the clip region
+        window.on_window_event(|event| {
+            if matches!(event, tauri::WindowEvent::ScaleFactorChanged { .. }) {
+                send(AnimCommand::Refresh);
+            }
+        });
+    }
+    // Direct shape set — collapse() skips animation since the island starts collapsed
+    collapse();
 }
@@ -0,0 +1,61 @@
+//! Push channel to this process's island webview.
+//!
+//! The island page gets its updates as `island://push` Tauri events instead
+//! of polling the API: every SSE message is relayed as is, and after each
+//! one the server pushes the session list (`state`, the body of `/api/all`
+//! without events) and the pending requests (`permissions`) if they
+//! changed. HTTP and SSE stay for external clients — browsers, and the
+//! island of a desktop client attached to a headless service.
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{Emitter, Manager};
+
+use crate::permission::{AutoApproveWindow, PermissionRequest};
+use crate::presence::BatchStatus;
+
+/// Tauri event the island page listens to.
+pub const EVENT: &str = "island://push";
+
+/// Messages only the island gets. Each carries a `type`, like the SSE
+/// messages relayed alongside them.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IslandPush {
+    /// Sessions, overall status, missed activity, auto-approve windows and
+    /// held toasts (with attention batches on).
+    State {
+        status: Value,
+        processes: Vec<Value>,
+        missed: Option<Value>,
+        auto_approve: Vec<AutoApproveWindow>,
+        batch: Option<BatchStatus>,
+    },
+    /// Pending permission requests, as `GET /api/permissions`.
+    Permissions { requests: Vec<PermissionRequest> },
+    /// Tray state and session count after a tray refresh.
+    TrayState { state: String, sessions: usize },
+    /// Keyboard navigation (see `island::run_command`).
+    IslandCommand { action: String },
+    /// The island is about to be hidden.
+    IslandHide,
+}
+
+/// Send `msg` to the island page, if the island window exists.
+pub fn send(handle: &tauri::AppHandle, msg: &IslandPush) {
+    if handle.get_webview_window("island").is_some() {
+        let _ = handle.emit_to("island", EVENT, msg);
+    }
+}
+
+/// Relay an SSE message (its JSON text) to the island page. The periodic
+/// `refresh` is left out: the island gets `state` instead.
+pub fn relay(handle: &tauri::AppHandle, sse_message: &str) {
+    match serde_json::from_str::<Value>(sse_message) {
+        Ok(v) if v["type"] == "refresh" => {}
+        Ok(v) => {
+            let _ = handle.emit_to("island", EVENT, v);
+        }
+        Err(e) => tracing::debug!("SSE message not relayed to the island: {}", e),
+    }
+}
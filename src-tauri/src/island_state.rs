@@ -0,0 +1,133 @@
+//! Server-side island panel state: expanded or not, which view it shows,
+//! and whether pending permissions pin it open.
+//!
+//! The webview reports view changes (`POST /api/island/state`) and listens
+//! for `island_state` SSE messages; the server drives permission pinning.
+//! Window geometry stays in `island.rs` — this module only decides.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+/// What the expanded panel is showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IslandView {
+    Sessions,
+    Permissions,
+    Chat,
+    Settings,
+}
+
+#[derive(Debug, Clone)]
+struct Inner {
+    expanded: bool,
+    view: IslandView,
+    /// Session shown in the chat view (only meaningful for `Chat`).
+    chat_session: Option<String>,
+    pending_permissions: usize,
+}
+
+impl Inner {
+    /// Pending permissions keep the panel open until they are answered.
+    fn pinned(&self) -> bool {
+        self.pending_permissions > 0
+    }
+}
+
+/// Why a state change was refused.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IslandError {
+    /// Collapse refused: permissions are waiting for a decision.
+    Pinned,
+    /// `Chat` view requested without a session.
+    MissingSession,
+}
+
+impl std::fmt::Display for IslandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Pinned => write!(f, "pinned: pending permissions"),
+            Self::MissingSession => write!(f, "chat view needs a session_id"),
+        }
+    }
+}
+
+pub struct IslandState {
+    inner: Mutex<Inner>,
+}
+
+impl IslandState {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                expanded: false,
+                view: IslandView::Sessions,
+                chat_session: None,
+                pending_permissions: 0,
+            }),
+        }
+    }
+
+    /// Mark the panel expanded. Returns true if that changed anything.
+    pub fn expand(&self) -> bool {
+        let mut s = mutex_lock!(self.inner);
+        !std::mem::replace(&mut s.expanded, true)
+    }
+
+    /// Mark the panel collapsed. Refused while pinned unless `force`.
+    /// Returns whether the state changed.
+    pub fn collapse(&self, force: bool) -> Result<bool, IslandError> {
+        let mut s = mutex_lock!(self.inner);
+        if s.pinned() && !force {
+            return Err(IslandError::Pinned);
+        }
+        Ok(std::mem::replace(&mut s.expanded, false))
+    }
+
+    /// Switch the panel view. `session_id` is required for `Chat`.
+    pub fn set_view(&self, view: IslandView, session_id: Option<String>) -> Result<bool, IslandError> {
+        let mut s = mutex_lock!(self.inner);
+        let chat_session = match view {
+            IslandView::Chat => Some(session_id.ok_or(IslandError::MissingSession)?),
+            _ => None,
+        };
+        let changed = s.view != view || s.chat_session != chat_session;
+        s.view = view;
+        s.chat_session = chat_session;
+        Ok(changed)
+    }
+
+    /// Track the number of pending permissions. The first one expands the
+    /// panel and switches to the permissions view (an open chat keeps its
+    /// own permission banner); the last one answered returns to sessions.
+    /// Returns whether the state changed.
+    pub fn set_pending_permissions(&self, count: usize) -> bool {
+        let mut s = mutex_lock!(self.inner);
+        let before = s.clone();
+        s.pending_permissions = count;
+        if count > 0 && before.pending_permissions == 0 {
+            s.expanded = true;
+            if s.view != IslandView::Chat {
+                s.view = IslandView::Permissions;
+            }
+        } else if count == 0 && s.view == IslandView::Permissions {
+            s.view = IslandView::Sessions;
+        }
+        s.expanded != before.expanded
+            || s.view != before.view
+            || s.pending_permissions != before.pending_permissions
+    }
+
+    /// JSON snapshot for `GET /api/island/state` and the `island_state` SSE message.
+    pub fn snapshot(&self) -> Value {
+        let s = mutex_lock!(self.inner);
+        json!({
+            "expanded": s.expanded,
+            "view": s.view,
+            "chat_session": s.chat_session,
+            "pinned": s.pinned(),
+            "pending_permissions": s.pending_permissions,
+        })
+    }
+}
@@ -0,0 +1,98 @@
+//! Project journals: with `journal.enabled`, each session that ends gets a
+//! short markdown entry appended to its project's journal
+//! (`<project>/.agent-desk/journal.md` by default) — when and how long it
+//! ran, the model, how many prompts it took, the files it edited and the
+//! agent's last message — so what agents did in a repo stays greppable
+//! next to the code.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::JournalConfig;
+
+/// Files listed per entry; the rest are counted.
+const MAX_FILES: usize = 20;
+
+/// What an entry says about a session.
+pub struct Entry<'a> {
+    pub session_id: &'a str,
+    pub cwd: &'a str,
+    pub started_at: f64,
+    pub ended_at: f64,
+    pub model: &'a str,
+    pub prompts: usize,
+    /// Edited files, as the agent named them.
+    pub files: &'a [String],
+    /// The agent's last message.
+    pub summary: &'a str,
+}
+
+/// Journal file for the project in `cwd`: `journal.path` with `{project}`
+/// replaced, relative to `cwd` unless absolute.
+pub fn path(cfg: &JournalConfig, cwd: &str) -> PathBuf {
+    let p = cfg.path.replace("{project}", crate::tray::project_name(cwd));
+    let p = Path::new(&p);
+    if p.is_absolute() { p.to_path_buf() } else { Path::new(cwd).join(p) }
+}
+
+/// Append `entry` to the project's journal, creating it (and its folder)
+/// if needed. Returns the journal's path.
+pub fn append(cfg: &JournalConfig, entry: &Entry) -> std::io::Result<PathBuf> {
+    let path = path(cfg, entry.cwd);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let new = !path.exists();
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    if new {
+        writeln!(file, "# Agent journal \u{2014} {}", crate::tray::project_name(entry.cwd))?;
+    }
+    file.write_all(render(entry, cfg.summary_max_chars).as_bytes())?;
+    Ok(path)
+}
+
+fn render(entry: &Entry, summary_max_chars: usize) -> String {
+    let started = chrono::DateTime::from_timestamp(entry.started_at as i64, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+    let short_sid = entry.session_id.get(..8).unwrap_or(entry.session_id);
+    let mut out = format!(
+        "\n## {} \u{00b7} {} \u{00b7} {}\n\n",
+        started,
+        crate::messages::duration(entry.ended_at - entry.started_at),
+        short_sid
+    );
+    if !entry.model.is_empty() {
+        out.push_str(&format!("- Model: {}\n", entry.model));
+    }
+    out.push_str(&format!("- Prompts: {}\n", entry.prompts));
+    if !entry.files.is_empty() {
+        let mut files: Vec<String> = entry.files.iter().take(MAX_FILES).map(|f| format!("`{}`", relative(f, entry.cwd))).collect();
+        if entry.files.len() > MAX_FILES {
+            files.push(format!("and {} more", entry.files.len() - MAX_FILES));
+        }
+        out.push_str(&format!("- Files: {}\n", files.join(", ")));
+    }
+    let summary = crate::messages::truncate(entry.summary.trim(), summary_max_chars);
+    if !summary.is_empty() {
+        out.push('\n');
+        for line in summary.lines() {
+            match line {
+                "" => out.push_str(">\n"),
+                line => out.push_str(&format!("> {}\n", line)),
+            }
+        }
+    }
+    out
+}
+
+/// `file` relative to `cwd` when inside it, with `/` separators.
+fn relative(file: &str, cwd: &str) -> String {
+    let file = file.replace('\\', "/");
+    let cwd = cwd.replace('\\', "/");
+    let cwd = cwd.trim_end_matches('/');
+    match file.strip_prefix(cwd).and_then(|r| r.strip_prefix('/')) {
+        Some(rest) if !cwd.is_empty() => rest.to_string(),
+        _ => file,
+    }
+}
@@ -0,0 +1,157 @@
+//! Windows jump list: right-clicking the app's taskbar button offers the
+//! recent sessions ("Focus: repo-x") and a few tasks (show the island, open
+//! the dashboard, pause or resume notifications).
+//!
+//! Every item launches the exe with an `agentdesk://` URL, which the new
+//! process forwards to the running instance (see deep_link.rs). The list
+//! is rebuilt from the tray refresh, only when its items change.
+
+use std::sync::Mutex;
+
+use serde_json::Value;
+
+/// Sessions listed under "Recent projects".
+const MAX_RECENT: usize = 8;
+
+/// One jump list entry: its label and the deep link it launches.
+#[derive(Clone, PartialEq)]
+struct Item {
+    title: String,
+    url: String,
+}
+
+/// Items last committed, so an unchanged list is not rewritten.
+static SHOWN: Mutex<Option<(Vec<Item>, Vec<Item>)>> = Mutex::new(None);
+
+/// Rebuild the jump list from the merged session list, if it changed.
+pub fn update(processes: &[Value], paused: bool) {
+    use crate::deep_link::SCHEME;
+
+    let mut recent: Vec<&Value> = processes.iter().filter(|p| p.get("session_id").is_some()).collect();
+    recent.sort_by(|a, b| {
+        let ts = |p: &Value| p.get("updated_at").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        ts(b).total_cmp(&ts(a))
+    });
+    let sessions: Vec<Item> = recent
+        .into_iter()
+        .take(MAX_RECENT)
+        .map(|p| {
+            let sid = p.get("session_id").and_then(|v| v.as_str()).unwrap_or("");
+            let cwd = p.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+            Item {
+                title: format!("Focus: {}", crate::tray::project_name(cwd)),
+                url: format!("{}://focus/{}", SCHEME, urlencoding::encode(sid)),
+            }
+        })
+        .collect();
+    let (pause_title, pause_action) = if paused {
+        ("Resume notifications", "resume")
+    } else {
+        ("Pause notifications", "pause")
+    };
+    let tasks = vec![
+        Item { title: "Show island".into(), url: format!("{}://show-island", SCHEME) },
+        Item { title: "Open dashboard".into(), url: format!("{}://dashboard", SCHEME) },
+        Item { title: pause_title.into(), url: format!("{}://{}", SCHEME, pause_action) },
+    ];
+
+    {
+        let mut shown = mutex_lock!(SHOWN);
+        if shown.as_ref().is_some_and(|(t, s)| *t == tasks && *s == sessions) {
+            return;
+        }
+        *shown = Some((tasks.clone(), sessions.clone()));
+    }
+    // COM wants its own apartment; a short-lived thread keeps it off the
+    // tray updater
+    std::thread::spawn(move || {
+        if let Err(e) = commit(&tasks, &sessions) {
+            tracing::debug!("Jump list not updated: {}", e);
+        }
+    });
+}
+
+#[cfg(windows)]
+fn commit(tasks: &[Item], sessions: &[Item]) -> windows::core::Result<()> {
+    use windows::core::{Interface, HSTRING};
+    use windows::Win32::Foundation::E_FAIL;
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED};
+    use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+    use windows::Win32::UI::Shell::{DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW};
+
+    unsafe {
+        CoInitializeEx(None, COINIT_APARTMENTTHREADED).ok()?;
+        let result = (|| {
+            let exe = std::env::current_exe().map_err(|e| windows::core::Error::new(E_FAIL, e.to_string()))?;
+            let list: ICustomDestinationList = CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+            let mut slots = 0u32;
+            let removed: IObjectArray = list.BeginList(&mut slots)?;
+            // Re-adding an item the user removed from the list fails the
+            // whole category, so those stay out
+            let mut removed_urls = Vec::new();
+            for i in 0..removed.GetCount()? {
+                if let Ok(link) = removed.GetAt::<IShellLinkW>(i) {
+                    let mut buf = [0u16; 1024];
+                    if link.GetArguments(&mut buf).is_ok() {
+                        let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+                        removed_urls.push(String::from_utf16_lossy(&buf[..len]));
+                    }
+                }
+            }
+
+            let recent: IObjectCollection = CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+            for item in sessions.iter().filter(|i| !removed_urls.contains(&i.url)).take(slots as usize) {
+                recent.AddObject(&shell_link(&exe, item)?)?;
+            }
+            if !sessions.is_empty() {
+                list.AppendCategory(&HSTRING::from("Recent projects"), &recent.cast::<IObjectArray>()?)?;
+            }
+
+            let task_items: IObjectCollection = CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+            for item in tasks {
+                task_items.AddObject(&shell_link(&exe, item)?)?;
+            }
+            list.AddUserTasks(&task_items.cast::<IObjectArray>()?)?;
+            list.CommitList()
+        })();
+        CoUninitialize();
+        result
+    }
+}
+
+/// A shell link that launches this exe with the item's deep link, titled
+/// through its property store (jump lists show `PKEY_Title`).
+#[cfg(windows)]
+fn shell_link(exe: &std::path::Path, item: &Item) -> windows::core::Result<windows::Win32::UI::Shell::IShellLinkW> {
+    use windows::core::{Interface, HSTRING, PWSTR};
+    use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+    use windows::Win32::System::Com::StructuredStorage::PROPVARIANT;
+    use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+    use windows::Win32::System::Variant::VT_LPWSTR;
+    use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+    use windows::Win32::UI::Shell::{IShellLinkW, ShellLink};
+
+    unsafe {
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+        let exe = HSTRING::from(exe.as_os_str());
+        link.SetPath(&exe)?;
+        link.SetArguments(&HSTRING::from(item.url.as_str()))?;
+        link.SetIconLocation(&exe, 0)?;
+        link.SetDescription(&HSTRING::from(item.title.as_str()))?;
+
+        // The PROPVARIANT borrows `title`; SetValue copies it
+        let mut title: Vec<u16> = item.title.encode_utf16().chain(Some(0)).collect();
+        let mut value = PROPVARIANT::default();
+        (*value.Anonymous.Anonymous).vt = VT_LPWSTR;
+        (*value.Anonymous.Anonymous).Anonymous.pwszVal = PWSTR(title.as_mut_ptr());
+        let store: IPropertyStore = link.cast()?;
+        store.SetValue(&PKEY_Title, &value)?;
+        store.Commit()?;
+        Ok(link)
+    }
+}
+
+#[cfg(not(windows))]
+fn commit(_tasks: &[Item], _sessions: &[Item]) -> Result<(), String> {
+    Ok(())
+}
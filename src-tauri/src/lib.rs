@@ -2,8 +2,12 @@
 mod utils;
 mod config;
 mod events;
+mod messages;
 mod session;
+mod status;
 mod sse;
+mod metrics;
+mod ratelimit;
 pub mod server;
 mod process;
 mod adapter;
@@ -12,46 +16,192 @@ mod send_input;
 pub mod tray;
 mod remote;
 pub mod island;
+mod island_state;
+mod island_push;
+mod ui;
+mod status_page;
+mod permit_link;
+mod deep_link;
+mod hook_health;
+mod heartbeat;
+mod budget;
+mod tool_stats;
+mod desk_timeline;
+mod autostart;
+mod action;
+mod dashboard;
+mod widget;
+mod toast;
+mod taskbar;
+mod jumplist;
+mod journal;
+#[cfg(any(feature = "simulator", debug_assertions))]
+pub mod simulator;
+mod recorder;
+mod team;
 mod permission;
 mod chat;
+mod chat_export;
 mod setup;
+mod presence;
+mod redact;
+mod sensitive;
+mod ignore;
+mod migrate;
+mod settings_backup;
+mod state_backup;
+mod limits;
+mod memory;
+mod service;
 pub mod protocol;
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
 use tauri::Manager;
+use tracing_subscriber::{reload, EnvFilter, Registry};
 
 pub fn run() {
     // Structured logging: console + rolling JSON file in %APPDATA%/agent-desk/logs/
     init_logging();
 
+    // --headless: HTTP API, scanner, sessions and remote channels only — no
+    // tray, island or toasts (servers, WSL, machines without a display)
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let headless = args.iter().any(|a| a == "--headless");
+    // --minimized: started by the autostart entry — tray only, island hidden
+    let minimized = args.iter().any(|a| a == autostart::MINIMIZED_ARG);
+
+    // --install-service / --uninstall-service: manage the background service
+    let service_cmd = if args.iter().any(|a| a == "--install-service") {
+        Some(service::install())
+    } else if args.iter().any(|a| a == "--uninstall-service") {
+        Some(service::uninstall())
+    } else {
+        None
+    };
+    if let Some(result) = service_cmd {
+        match result {
+            Ok(msg) => {
+                tracing::info!("{}", msg);
+                println!("{}", msg);
+            }
+            Err(e) => {
+                tracing::error!("Service command failed: {}", e);
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let cfg = config::load_config();
-    setup::ensure_hooks_configured();
+    // RUST_LOG wins over the filter saved by /api/logs/level
+    let saved_filter = Some(&cfg.general.log_filter).filter(|f| !f.is_empty() && std::env::var_os("RUST_LOG").is_none());
+    if let Some(Err(e)) = saved_filter.map(|f| set_log_filter(f)) {
+        tracing::warn!("Ignoring general.log_filter: {}", e);
+    }
+    if config::is_portable() {
+        tracing::info!("Portable mode: config, state and logs stay next to the exe");
+    }
+    setup::configure(&cfg.manager.host, cfg.manager.use_hook_daemon);
+    let data_dir = std::path::Path::new(&cfg.general.sessions_file).parent().unwrap_or(std::path::Path::new("."));
+    settings_backup::configure(data_dir, cfg.general.settings_backups);
+    setup::refresh_installed_hooks(cfg.manager.port, cfg.general.observe_tools, cfg.general.hooks_dry_run);
+    focus::configure(cfg.general.focus_alt_key_fallback);
     let port = cfg.manager.port;
 
-    // Prevent duplicate instances: if port is already in use, exit quietly
-    if std::net::TcpStream::connect(format!("127.0.0.1:{}", port)).is_ok() {
+    // Launched by the OS for an agentdesk:// URL: hand it to the running
+    // instance, or handle it ourselves once the server is up
+    let deep_link = deep_link::from_args(&args).map(str::to_string);
+    if let Some(url) = &deep_link {
+        match deep_link::forward(port, url) {
+            Ok(()) => return,
+            Err(e) => tracing::info!("Deep link {} not forwarded ({}), starting", url, e),
+        }
+    }
+
+    // Prevent duplicate instances: if port is already in use, exit quietly —
+    // unless it is the headless service, which the desktop UI attaches to
+    if setup::connect_local(port, std::time::Duration::from_millis(500)).is_ok() {
+        if !headless && service::is_headless_service(port) {
+            tracing::info!("Headless service on port {} — starting the desktop UI as its client", port);
+            run_client(&cfg, minimized);
+            return;
+        }
         eprintln!("Agent Desk is already running on port {}. Exiting.", port);
         return;
     }
 
+    // A restore staged by POST /api/backup/restore, before the stores load
+    let cfg = if state_backup::apply_staged(&cfg) { config::load_config() } else { cfg };
     let (app_state, tray_rx) = server::AppState::new(cfg);
     let state = Arc::new(app_state);
+    state.headless.store(headless, std::sync::atomic::Ordering::Relaxed);
 
     // Start the HTTP+SSE server on a background tokio runtime
     let server_state = state.clone();
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
     std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
         rt.block_on(async {
-            server::run_server(server_state).await;
+            server::run_server(server_state, ready_tx).await;
         });
     });
 
-    // Give the HTTP server a moment to bind
-    std::thread::sleep(std::time::Duration::from_millis(500));
+    // Wait until the listener is bound — the daemon and the island both talk
+    // to the API immediately, so they must not start before it is up.
+    if ready_rx.blocking_recv().is_err() {
+        tracing::error!("HTTP server failed to start on port {}. Exiting.", port);
+        return;
+    }
 
-    // Kill orphaned daemon from previous crash, then spawn fresh
-    setup::kill_orphaned_daemon(port);
-    let daemon_pid = setup::spawn_hook_daemon(port);
+    if let Some(url) = deep_link {
+        let s = state.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = deep_link::handle(&s, &url) {
+                tracing::warn!("Deep link {}: {}", url, e);
+            }
+        });
+    }
+    // --replay <file> [--replay-speed X]: feed a recording from
+    // /api/record/start back in, for reproducing odd island behaviour
+    if let Some(file) = arg_value(&args, "--replay") {
+        #[cfg(any(feature = "simulator", debug_assertions))]
+        {
+            let speed = arg_value(&args, "--replay-speed").and_then(|s| s.parse().ok()).unwrap_or(1.0);
+            recorder::replay(std::path::PathBuf::from(file), port, speed);
+        }
+        #[cfg(not(any(feature = "simulator", debug_assertions)))]
+        tracing::warn!("--replay {}: needs a debug build or the simulator feature", file);
+    }
+    if !headless {
+        // Toasts need the AppUserModelID, even portable
+        toast::init();
+    }
+    if !headless && !config::is_portable() {
+        std::thread::spawn(|| {
+            if let Err(e) = deep_link::register() {
+                tracing::debug!("{}:// not registered: {}", deep_link::SCHEME, e);
+            }
+        });
+    }
+
+    // Reuse a compatible daemon left by a previous crash, else spawn fresh
+    if !state.config.manager.use_hook_daemon {
+        tracing::info!("Hook daemon disabled (manager.use_hook_daemon), hooks post directly");
+    } else if let Some(pid) = setup::start_hook_daemon(port) {
+        state.daemon_pid.store(pid, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    if headless {
+        // Nothing consumes tray refreshes; dropping the receiver turns the
+        // senders' notifications into no-ops instead of a growing queue.
+        drop(tray_rx);
+        run_headless(port);
+        shutdown(&state);
+    }
+    let exit_state = state.clone();
+    let visibility_path = island::visibility_path(&state.config.general.sessions_file);
+    let start_hidden = minimized || state.config.island.start_hidden || island::load_hidden(&visibility_path);
 
     // Build Tauri app
     tauri::Builder::default()
@@ -59,7 +209,7 @@ pub fn run() {
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_autostart::init(
             tauri_plugin_autostart::MacosLauncher::LaunchAgent,
-            None,
+            Some(vec![autostart::MINIMIZED_ARG]),
         ))
         .setup(move |app| {
             // Store AppHandle for notifications from api_signal
@@ -68,60 +218,70 @@ pub fn run() {
             // Sync OS autostart state to config — only enable, never
             // auto-disable.  On autostart the config dir may not be found
             // (CWD != project root), so the default `autostart: false`
-            // would incorrectly remove the registry entry.
-            {
-                use tauri_plugin_autostart::ManagerExt;
-                let al = app.autolaunch();
-                if state.config.island.autostart && !al.is_enabled().unwrap_or(false) {
-                    let _ = al.enable();
-                }
+            // would incorrectly remove the registry entry. Enabling always
+            // rewrites the entry, so one from an older version (or a moved
+            // exe) gets the current path and `--minimized`.
+            // Portable installs never touch the OS autostart entry.
+            if !config::is_portable() && state.config.island.autostart {
+                let _ = autostart::set_enabled(app.handle(), true)
+                    .inspect_err(|e| tracing::warn!("Failed to register autostart: {}", e));
             }
 
             // Setup system tray
             tray::setup_tray(app, state.clone())?;
 
-            // Setup Dynamic Island window
+            // One toast for whatever went unread while the app was closed
+            server::announce_missed_activity(&state);
+
+            // Setup Dynamic Island window (server readiness was awaited above,
+            // so the page can start fetching as soon as API_PORT is set)
             if let Some(w) = app.get_webview_window("island") {
-                let _ = w.eval(&format!("window.API_PORT={}", port));
-                let _ = w.set_skip_taskbar(true);
+                // This server pushes to its own island (see island_push.rs)
+                let _ = w.eval(&format!("{};window.ISLAND_PUSH=true", setup::page_globals(port)));
+                let _ = w.set_skip_taskbar(!state.config.island.taskbar_badge);
 
                 island::setup(&w, state.config.island.pill_width);
+                if start_hidden {
+                    let _ = w.hide();
+                }
+            }
+            if state.config.widget.enabled {
+                widget::open(app.handle(), port, &state.config.widget);
             }
 
             // Register global hotkey to toggle island visibility
-            {
-                use tauri_plugin_global_shortcut::GlobalShortcutExt;
-                let hotkey_str = state.config.island.hotkey.clone();
-                match hotkey_str.parse::<tauri_plugin_global_shortcut::Shortcut>() {
-                    Ok(shortcut) => {
-                        let reg = app.global_shortcut().on_shortcut(shortcut, |app, _shortcut, event| {
-                            if event.state == tauri_plugin_global_shortcut::ShortcutState::Pressed {
-                                if let Some(w) = app.get_webview_window("island") {
-                                    island::toggle_visibility(&w);
-                                }
-                            }
-                        });
-                        match reg {
-                            Ok(_) => tracing::info!("Global hotkey registered: {}", hotkey_str),
-                            Err(e) => tracing::warn!("Failed to register hotkey '{}': {}", hotkey_str, e),
-                        }
-                    }
-                    Err(e) => tracing::warn!("Invalid hotkey '{}': {}", hotkey_str, e),
-                }
-            }
+            register_hotkey(app, &state.config.island.hotkey);
+            register_nav_hotkeys(app, &state.config.island.nav_hotkeys);
 
-            // Tray updater thread: refreshes icon, tooltip, and menu
+            // Tray updater thread: refreshes icon, tooltip, and menu; while
+            // agents are working it also steps the pulse animation
             let tray_state = state.clone();
             let tray_handle = app.handle().clone();
             std::thread::spawn(move || {
+                let refresh = std::time::Duration::from_secs(3);
+                let animate = tray_state.config.island.tray_animation;
+                let mut thinking = false;
+                let mut frame = 0usize;
+                let mut last_refresh: Option<std::time::Instant> = None;
                 loop {
-                    // Block up to 3s, or wake immediately on signal from api_signal
-                    let _ = tray_rx.recv_timeout(std::time::Duration::from_secs(3));
+                    // Block up to 3s (one frame while animating), or wake
+                    // immediately on signal from api_signal
+                    let wait = if thinking { tray::PULSE_INTERVAL } else { refresh };
+                    let signaled = tray_rx.recv_timeout(wait).is_ok();
 
-                    if tray_state.app_handle.get().is_some() {
-                        let processes = server::scan_and_merge(&tray_state);
-                        let status = server::compute_state(&processes);
+                    if tray_state.app_handle.get().is_none() {
+                        continue;
+                    }
+                    if signaled || last_refresh.is_none_or(|t| t.elapsed() >= refresh) {
+                        let processes = status::scan_and_merge(&tray_state);
+                        let status = status::compute_state(&processes);
                         tray::update_tray(&tray_handle, &tray_state, &status, &processes);
+                        thinking = animate && status.get("state").and_then(|s| s.as_str()) == Some("thinking");
+                        last_refresh = Some(std::time::Instant::now());
+                    }
+                    if thinking {
+                        frame = frame.wrapping_add(1);
+                        tray::animate_thinking(&tray_handle, frame);
                     }
                 }
             });
@@ -129,25 +289,204 @@ pub fn run() {
             tracing::info!("Agent Desk running — http://localhost:{}", port);
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(move |app, event| persist_island_visibility(app, &event, &visibility_path));
+
+    // Tauri event loop exited
+    shutdown(&exit_state);
+}
 
-    // Tauri event loop exited — kill hook daemon and force-terminate all threads
-    if let Some(pid) = daemon_pid {
+/// Desktop UI attached to a headless service on `port`: island, tray and
+/// hotkey only. The service owns sessions, hooks and notifications; the
+/// island drives its own window through Tauri commands and shows the
+/// service's `toast` messages.
+fn run_client(cfg: &config::Config, minimized: bool) {
+    let port = cfg.manager.port;
+    let island_cfg = cfg.island.clone();
+    let widget_cfg = cfg.widget.clone();
+    let visibility_path = island::visibility_path(&cfg.general.sessions_file);
+    let start_hidden = minimized || island_cfg.start_hidden || island::load_hidden(&visibility_path);
+    toast::init();
+    tauri::Builder::default()
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .invoke_handler(tauri::generate_handler![island::island_window, island::island_toast])
+        .setup(move |app| {
+            tray::setup_client_tray(app, port, island_cfg.panel_width, island_cfg.panel_height, widget_cfg.clone())?;
+            if let Some(w) = app.get_webview_window("island") {
+                let _ = w.eval(&format!("{};window.ISLAND_CLIENT=true", setup::page_globals(port)));
+                let _ = w.set_skip_taskbar(true);
+                island::setup(&w, island_cfg.pill_width);
+                if start_hidden {
+                    let _ = w.hide();
+                }
+            }
+            if widget_cfg.enabled {
+                widget::open(app.handle(), port, &widget_cfg);
+            }
+            register_hotkey(app, &island_cfg.hotkey);
+            register_nav_hotkeys(app, &island_cfg.nav_hotkeys);
+            tracing::info!("Agent Desk client running — service at http://localhost:{}", port);
+            Ok(())
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(move |app, event| persist_island_visibility(app, &event, &visibility_path));
+    std::process::exit(0);
+}
+
+/// On exit, remember whether the island was hidden so the next start
+/// restores it. The window still exists while the exit is being requested.
+fn persist_island_visibility(app: &tauri::AppHandle, event: &tauri::RunEvent, path: &std::path::Path) {
+    if !matches!(event, tauri::RunEvent::ExitRequested { .. }) {
+        return;
+    }
+    if let Some(w) = app.get_webview_window("island") {
+        island::save_visibility(&w, path);
+    }
+}
+
+/// Register the global hotkey that toggles island visibility.
+fn register_hotkey(app: &tauri::App, hotkey: &str) {
+    use tauri_plugin_global_shortcut::GlobalShortcutExt;
+    match hotkey.parse::<tauri_plugin_global_shortcut::Shortcut>() {
+        Ok(shortcut) => {
+            let reg = app.global_shortcut().on_shortcut(shortcut, |app, _shortcut, event| {
+                if event.state == tauri_plugin_global_shortcut::ShortcutState::Pressed {
+                    if let Some(w) = app.get_webview_window("island") {
+                        island::toggle_visibility(&w);
+                    }
+                }
+            });
+            match reg {
+                Ok(_) => tracing::info!("Global hotkey registered: {}", hotkey),
+                Err(e) => tracing::warn!("Failed to register hotkey '{}': {}", hotkey, e),
+            }
+        }
+        Err(e) => tracing::warn!("Invalid hotkey '{}': {}", hotkey, e),
+    }
+}
+
+/// Register `island.nav_hotkeys`; each drives the island panel like
+/// `POST /api/island/command`.
+fn register_nav_hotkeys(app: &tauri::App, hotkeys: &std::collections::HashMap<String, String>) {
+    use tauri_plugin_global_shortcut::GlobalShortcutExt;
+    for (action, hotkey) in hotkeys {
+        if !island::COMMANDS.contains(&action.as_str()) {
+            tracing::warn!("Unknown island action '{}' in nav_hotkeys", action);
+            continue;
+        }
+        let shortcut = match hotkey.parse::<tauri_plugin_global_shortcut::Shortcut>() {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!("Invalid hotkey '{}' for {}: {}", hotkey, action, e);
+                continue;
+            }
+        };
+        let act = action.clone();
+        let reg = app.global_shortcut().on_shortcut(shortcut, move |app, _shortcut, event| {
+            if event.state != tauri_plugin_global_shortcut::ShortcutState::Pressed {
+                return;
+            }
+            if let Some(w) = app.get_webview_window("island") {
+                island::run_command(&w, &act);
+            }
+        });
+        match reg {
+            Ok(_) => tracing::info!("Island hotkey registered: {} = {}", hotkey, action),
+            Err(e) => tracing::warn!("Failed to register hotkey '{}': {}", hotkey, e),
+        }
+    }
+}
+
+/// Value following `name` on the command line.
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).map(String::as_str)
+}
+
+/// Block until Ctrl+C (or SIGTERM on Unix) while the server thread runs.
+fn run_headless(port: u16) {
+    tracing::info!("Agent Desk running headless — http://localhost:{}", port);
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+    rt.block_on(async {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut term) => {
+                    tokio::select! {
+                        _ = tokio::signal::ctrl_c() => {}
+                        _ = term.recv() => {}
+                    }
+                }
+                Err(_) => {
+                    let _ = tokio::signal::ctrl_c().await;
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    });
+    tracing::info!("Shutting down");
+}
+
+/// Persist sessions, kill the hook daemon (possibly respawned by the
+/// supervisor) and force-terminate all threads.
+fn shutdown(state: &server::AppState) -> ! {
+    state.session_tracker.flush_if_dirty();
+    let pid = state.daemon_pid.load(std::sync::atomic::Ordering::Relaxed);
+    if pid != 0 {
         setup::kill_hook_daemon(pid);
     }
     std::process::exit(0);
 }
 
 /// Initialize tracing with console output + rolling JSON file.
+/// Reload handle for the active EnvFilter, and the directives it was built from.
+static LOG_FILTER: OnceLock<(reload::Handle<EnvFilter, Registry>, Mutex<String>)> = OnceLock::new();
+
+/// Directives the log filter was last set to.
+pub(crate) fn log_filter() -> String {
+    LOG_FILTER.get().map(|(_, f)| mutex_lock!(f).clone()).unwrap_or_default()
+}
+
+/// Swap the log filter at runtime (`RUST_LOG` syntax). A bare module
+/// target like `focus=debug` also matches `agent_desk_lib::focus`.
+pub(crate) fn set_log_filter(filter: &str) -> Result<(), String> {
+    let (handle, current) = LOG_FILTER.get().ok_or("logging not initialized")?;
+    let directives: Vec<String> = filter
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .flat_map(|d| {
+            let local = match d.split_once('=') {
+                Some((target, _)) if !target.contains("::") && !target.contains('[') => {
+                    Some(format!("agent_desk_lib::{}", d))
+                }
+                _ => None,
+            };
+            std::iter::once(d.to_string()).chain(local)
+        })
+        .collect();
+    let new = EnvFilter::builder().parse(directives.join(",")).map_err(|e| e.to_string())?;
+    handle.reload(new).map_err(|e| e.to_string())?;
+    *mutex_lock!(current) = filter.to_string();
+    tracing::info!("Log filter set to {}", filter);
+    Ok(())
+}
+
 fn init_logging() {
     use tracing_subscriber::layer::SubscriberExt;
     use tracing_subscriber::util::SubscriberInitExt;
 
-    // Log directory: %APPDATA%/agent-desk/logs/
-    let log_dir = std::env::var("APPDATA")
-        .map(|a| std::path::PathBuf::from(a).join("agent-desk").join("logs"))
-        .unwrap_or_else(|_| std::path::PathBuf::from("logs"));
+    // Log directory: %APPDATA%/agent-desk/logs/ (exe dir in portable mode)
+    let log_dir = config::log_dir();
     let _ = std::fs::create_dir_all(&log_dir);
 
     // Rolling daily file appender (JSON format)
@@ -163,11 +502,13 @@ fn init_logging() {
         .with_target(false)
         .compact();
 
+    let initial = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
+    let filter = EnvFilter::try_new(&initial).unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
+    let _ = LOG_FILTER.set((handle, Mutex::new(initial)));
+
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
+        .with(filter)
         .with(console_layer)
         .with(file_layer)
         .init();
@@ -0,0 +1,95 @@
+//! Subscription / usage limit detection.
+//!
+//! Claude Code reports a hit cap as plain text, either through the
+//! Notification hook or as the final assistant message of the turn, e.g.
+//! `Claude AI usage limit reached|1717084800` (older builds, unix reset
+//! time) or `5-hour limit reached ∙ resets 3pm`. Without recognising it, a
+//! capped session just looks stalled.
+
+use chrono::{Local, NaiveTime, TimeZone};
+
+/// A recognised limit message.
+#[derive(Debug, Clone)]
+pub struct UsageLimit {
+    /// Human-readable message (the `|<epoch>` suffix stripped).
+    pub message: String,
+    /// When the limit resets (unix seconds), if the message says.
+    pub resets_at: Option<f64>,
+}
+
+const MARKERS: &[&str] = &[
+    "usage limit reached",
+    "limit reached",
+    "reached your usage limit",
+    "hit your limit",
+    "limit will reset",
+];
+
+/// Recognise a usage/rate limit message.
+pub fn detect(text: &str) -> Option<UsageLimit> {
+    let text = text.trim();
+    // Limit notices are short; don't match a long answer that mentions one
+    if text.is_empty() || text.len() > 500 {
+        return None;
+    }
+    let lower = text.to_lowercase();
+    if !MARKERS.iter().any(|m| lower.contains(m)) {
+        return None;
+    }
+
+    // `...|1717084800`
+    let epoch = text
+        .rsplit_once('|')
+        .and_then(|(msg, ts)| Some((msg, ts.trim().parse::<i64>().ok()?)));
+    if let Some((msg, ts)) = epoch {
+        let at = Local.timestamp_opt(ts, 0).single().map(|t| t.format(" (resets %H:%M)").to_string());
+        let message = format!("{}{}", msg.trim(), at.unwrap_or_default());
+        return Some(UsageLimit { message, resets_at: Some(ts as f64) });
+    }
+
+    let resets_at = ["resets at ", "reset at ", "resets "]
+        .iter()
+        .find_map(|k| lower.find(k).map(|i| &lower[i + k.len()..]))
+        .and_then(parse_clock)
+        .map(next_occurrence);
+    Some(UsageLimit { message: text.to_string(), resets_at })
+}
+
+/// Parse the clock time at the start of `s`: `3pm`, `3:30 pm`, `15:00`.
+fn parse_clock(s: &str) -> Option<NaiveTime> {
+    let mut words = s
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == ':'))
+        .filter(|w| !w.is_empty());
+    let first = words.next()?;
+    let meridiem = words.next().filter(|w| *w == "am" || *w == "pm").unwrap_or("");
+    let word = format!("{}{}", first, meridiem);
+    let (clock, pm) = if let Some(t) = word.strip_suffix("pm") {
+        (t, Some(true))
+    } else if let Some(t) = word.strip_suffix("am") {
+        (t, Some(false))
+    } else {
+        (word.as_str(), None)
+    };
+    let (h, m) = match clock.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.get(..2)?.parse::<u32>().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    let h = match pm {
+        Some(true) if h < 12 => h + 12,
+        Some(false) if h == 12 => 0,
+        _ => h,
+    };
+    NaiveTime::from_hms_opt(h, m, 0)
+}
+
+/// Unix time of the next local occurrence of `t` (today or tomorrow).
+fn next_occurrence(t: NaiveTime) -> f64 {
+    let now = Local::now();
+    let today = now.date_naive().and_time(t);
+    let at = Local
+        .from_local_datetime(&today)
+        .earliest()
+        .filter(|at| *at > now)
+        .or_else(|| Local.from_local_datetime(&(today + chrono::Duration::days(1))).earliest());
+    at.map(|at| at.timestamp() as f64).unwrap_or(0.0)
+}
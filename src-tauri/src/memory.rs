@@ -0,0 +1,134 @@
+//! Memory budget for the caches that grow with use: parsed transcripts
+//! (chat views, token counts), the event list and hook dedup entries.
+//!
+//! A pass every minute sizes each cache as entries × approximate size
+//! (text lengths plus `ENTRY_OVERHEAD` per entry — an estimate, not an
+//! allocator count) and, past its `memory` ceiling, evicts from it: the
+//! least recently read transcripts (parsed again when next opened) or the
+//! oldest dedup entries. The event list mirrors events.jsonl, so its
+//! ceiling is compaction's (`manager.max_events_count`/`max_file_size`)
+//! and it is only sized here. `/api/diagnostics` lists the sizes under
+//! `memory`.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::config::MemoryConfig;
+use crate::server::AppState;
+
+/// Bytes counted per cache entry on top of its text: struct, map slot and
+/// allocation headers.
+pub const ENTRY_OVERHEAD: usize = 64;
+
+/// Rough heap size of a JSON value (tool call inputs).
+pub fn value_bytes(v: &Value) -> usize {
+    match v {
+        Value::String(s) => ENTRY_OVERHEAD + s.len(),
+        Value::Array(items) => ENTRY_OVERHEAD + items.iter().map(value_bytes).sum::<usize>(),
+        Value::Object(map) => ENTRY_OVERHEAD + map.iter().map(|(k, v)| k.len() + value_bytes(v)).sum::<usize>(),
+        _ => ENTRY_OVERHEAD,
+    }
+}
+
+/// One cache, as `/api/diagnostics` shows it.
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheUsage {
+    pub entries: usize,
+    /// Approximate size.
+    pub bytes: usize,
+    /// Ceiling, in bytes or entries; absent when there is none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_entries: Option<usize>,
+    /// Entries evicted for the ceiling since startup (not counted for the
+    /// event list: compaction trims it).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evicted: Option<u64>,
+}
+
+/// Eviction counters, per cache.
+pub struct MemoryBudget {
+    chat_evicted: AtomicU64,
+    dedup_evicted: AtomicU64,
+}
+
+const MB: usize = 1024 * 1024;
+
+/// Approximate size of a dedup entry: its key and time.
+fn dedup_bytes(key: &str) -> usize {
+    ENTRY_OVERHEAD + key.len() + std::mem::size_of::<f64>()
+}
+
+impl MemoryBudget {
+    pub fn new() -> Self {
+        Self {
+            chat_evicted: AtomicU64::new(0),
+            dedup_evicted: AtomicU64::new(0),
+        }
+    }
+
+    /// Evict from every cache over its ceiling.
+    pub fn enforce(&self, state: &AppState, cfg: &MemoryConfig) {
+        if cfg.chat_cache_mb > 0 {
+            let dropped = state.chat_reader.shrink_to(cfg.chat_cache_mb as usize * MB);
+            if dropped > 0 {
+                tracing::info!("Chat cache over {} MB: dropped {} transcript(s)", cfg.chat_cache_mb, dropped);
+                self.chat_evicted.fetch_add(dropped as u64, Ordering::Relaxed);
+            }
+        }
+        if cfg.dedup_max_entries > 0 {
+            let mut cache = write_lock!(state.dedup_cache);
+            if cache.len() > cfg.dedup_max_entries {
+                let mut times: Vec<f64> = cache.values().copied().collect();
+                times.sort_by(|a, b| b.total_cmp(a));
+                let cutoff = times[cfg.dedup_max_entries - 1];
+                let before = cache.len();
+                cache.retain(|_, ts| *ts >= cutoff);
+                let dropped = before - cache.len();
+                tracing::debug!("Dedup cache over {} entries: dropped {}", cfg.dedup_max_entries, dropped);
+                self.dedup_evicted.fetch_add(dropped as u64, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Size of each cache. Blocking: reads events.jsonl if it changed.
+    pub fn usage(&self, state: &AppState) -> Value {
+        let cfg = &state.config.memory;
+        let manager = &state.config.manager;
+        let (chats, chat_bytes) = state.chat_reader.memory();
+        let (events, event_bytes) = state.event_store.memory();
+        let (dedup, dedup_bytes) = {
+            let cache = read_lock!(state.dedup_cache);
+            (cache.len(), cache.keys().map(|k| dedup_bytes(k)).sum::<usize>())
+        };
+        let chat = CacheUsage {
+            entries: chats,
+            bytes: chat_bytes,
+            limit_bytes: (cfg.chat_cache_mb > 0).then_some(cfg.chat_cache_mb as usize * MB),
+            limit_entries: None,
+            evicted: Some(self.chat_evicted.load(Ordering::Relaxed)),
+        };
+        let events = CacheUsage {
+            entries: events,
+            bytes: event_bytes,
+            limit_bytes: (manager.max_file_size > 0).then_some(manager.max_file_size as usize),
+            limit_entries: (manager.max_events_count > 0).then_some(manager.max_events_count),
+            evicted: None,
+        };
+        let dedup = CacheUsage {
+            entries: dedup,
+            bytes: dedup_bytes,
+            limit_bytes: None,
+            limit_entries: (cfg.dedup_max_entries > 0).then_some(cfg.dedup_max_entries),
+            evicted: Some(self.dedup_evicted.load(Ordering::Relaxed)),
+        };
+        serde_json::json!({
+            "total_bytes": chat.bytes + events.bytes + dedup.bytes,
+            "chat": chat,
+            "events": events,
+            "dedup": dedup,
+        })
+    }
+}
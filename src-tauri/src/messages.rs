@@ -0,0 +1,304 @@
+//! Text of event notifications.
+//!
+//! Every surface takes its wording from here so they read the same: the
+//! event message (event log, tray menu, remote channels) and the toast
+//! title and body. Each event kind has a built-in template per language
+//! (`messages.language`, or a remote channel's own `language`), and any of
+//! them can be replaced in `messages.templates` under `<kind>`,
+//! `<kind>.title` or `<kind>.body`.
+//!
+//! Placeholders: `{sid}`, `{cwd}`, `{project}`, `{message}`, `{model}`,
+//! `{event}`, `{tool}`, `{duration}`. `{message}` is cut to
+//! `message_max_chars` in event messages, keeping its start, and to
+//! `toast_max_chars` in toasts, keeping the part `toast_truncate` picks:
+//! the start (`head`), the end (`tail`) or the last whole sentences
+//! (`smart`) — a reply usually ends with its summary.
+
+use crate::config::MessagesConfig;
+use crate::protocol::HookEvent;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Surface {
+    Message,
+    Title,
+    Body,
+}
+
+/// What an event's text is made from.
+#[derive(Clone, Copy)]
+pub struct EventText<'a> {
+    pub event: &'a HookEvent,
+    /// Notification type (`permission_prompt`, `idle_prompt`, …).
+    pub ntype: &'a str,
+    /// Short session id.
+    pub sid: &'a str,
+    pub cwd: &'a str,
+    /// Project label as shown in toasts.
+    pub project: &'a str,
+    /// Last assistant message or notification text.
+    pub message: &'a str,
+    pub model: &'a str,
+    pub tool: &'a str,
+    /// How long the turn took, e.g. `3m 12s`; empty if unknown.
+    pub duration: &'a str,
+}
+
+impl<'a> EventText<'a> {
+    pub fn new(event: &'a HookEvent, ntype: &'a str) -> Self {
+        Self { event, ntype, sid: "", cwd: "", project: "", message: "", model: "", tool: "", duration: "" }
+    }
+
+    /// Template key of the event.
+    fn kind(&self) -> &'static str {
+        match self.event {
+            HookEvent::Stop => "stop",
+            HookEvent::Notification => match self.ntype {
+                "permission_prompt" => "confirm",
+                "idle_prompt" => "idle",
+                _ => "notice",
+            },
+            HookEvent::SessionStart => "start",
+            HookEvent::SessionEnd => "end",
+            HookEvent::LimitReached => "limit",
+            HookEvent::PermissionRequest => "permission",
+            _ => "other",
+        }
+    }
+}
+
+/// Event message for the event log, tray and remote channels.
+pub fn message(cfg: &MessagesConfig, t: &EventText) -> String {
+    message_in(cfg, t, &cfg.language)
+}
+
+/// Event message in `language` instead of `messages.language`, for remote
+/// channels set to their own.
+pub fn message_in(cfg: &MessagesConfig, t: &EventText, language: &str) -> String {
+    render(cfg, t, Surface::Message, language, cfg.message_max_chars, "head")
+}
+
+/// Toast title; empty for events that don't toast.
+pub fn toast_title(cfg: &MessagesConfig, t: &EventText) -> String {
+    render(cfg, t, Surface::Title, &cfg.language, cfg.toast_max_chars, &cfg.toast_truncate)
+}
+
+pub fn toast_body(cfg: &MessagesConfig, t: &EventText) -> String {
+    render(cfg, t, Surface::Body, &cfg.language, cfg.toast_max_chars, &cfg.toast_truncate)
+}
+
+/// `s` cut to `max` characters, ending in "..." when cut. 0 = no limit.
+pub fn truncate(s: &str, max: usize) -> String {
+    if max == 0 || s.chars().count() <= max {
+        return s.to_string();
+    }
+    format!("{}...", s.chars().take(max.saturating_sub(3)).collect::<String>())
+}
+
+/// `s` cut to `max` characters the way `mode` says: `head` keeps the
+/// start, `tail` the end (starting with "..."), and `smart` as many whole
+/// sentences from the end as fit, falling back to the start of the last
+/// sentence when even that one is too long.
+pub fn truncate_by(s: &str, max: usize, mode: &str) -> String {
+    if max == 0 || s.chars().count() <= max {
+        return s.to_string();
+    }
+    match mode {
+        "tail" => {
+            let keep = max.saturating_sub(3);
+            let skip = s.chars().count() - keep;
+            format!("...{}", s.chars().skip(skip).collect::<String>().trim_start())
+        }
+        "smart" => {
+            let parts = sentences(s);
+            let mut picked = Vec::new();
+            let mut len = 3;
+            for part in parts.iter().rev() {
+                let n = part.chars().count() + usize::from(!picked.is_empty());
+                if len + n > max {
+                    break;
+                }
+                len += n;
+                picked.push(*part);
+            }
+            match picked.len() {
+                0 => truncate(parts.last().copied().unwrap_or(s), max),
+                n => {
+                    picked.reverse();
+                    let joined = picked.join(" ");
+                    if n == parts.len() { joined } else { format!("...{}", joined) }
+                }
+            }
+        }
+        _ => truncate(s, max),
+    }
+}
+
+/// Sentences of `s`, trimmed. A sentence ends at a line break, at a CJK
+/// full stop, exclamation or question mark, or at `.` `!` `?` followed by
+/// whitespace (so `v1.2` and `main.rs` stay whole).
+fn sentences(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let end = match c {
+            '\n' | '\u{3002}' | '\u{ff01}' | '\u{ff1f}' => true, // 。！？
+            '.' | '!' | '?' => chars.peek().is_none_or(|&(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if end {
+            let next = i + c.len_utf8();
+            let part = s[start..next].trim();
+            if !part.is_empty() {
+                out.push(part);
+            }
+            start = next;
+        }
+    }
+    let part = s[start..].trim();
+    if !part.is_empty() {
+        out.push(part);
+    }
+    out
+}
+
+/// `secs` as `42s`, `3m 12s` or `1h 05m`.
+pub fn duration(secs: f64) -> String {
+    let secs = secs.max(0.0) as u64;
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// `duration` in `language`: `zh` gives `3分12秒`, anything else
+/// `3m 12s`.
+pub fn duration_in(secs: f64, language: &str) -> String {
+    if !language.eq_ignore_ascii_case("zh") {
+        return duration(secs);
+    }
+    let secs = secs.max(0.0) as u64;
+    match secs {
+        0..=59 => format!("{}\u{79d2}", secs), // 秒
+        60..=3599 => format!("{}\u{5206}{:02}\u{79d2}", secs / 60, secs % 60), // 分 秒
+        _ => format!("{}\u{5c0f}\u{65f6}{:02}\u{5206}", secs / 3600, secs % 3600 / 60), // 小时 分
+    }
+}
+
+/// How long ago something `secs` old happened, to the largest whole unit,
+/// in `language`: `just now`, `5m ago`, `2h ago`, `3d ago` (`zh`: 刚刚,
+/// 5分钟前, …). Future times count as now.
+pub fn ago(secs: f64, language: &str) -> String {
+    let secs = secs.max(0.0) as u64;
+    let zh = language.eq_ignore_ascii_case("zh");
+    let (n, en, cn) = match secs {
+        0..=59 => return if zh { "\u{521a}\u{521a}".to_string() } else { "just now".to_string() }, // 刚刚
+        60..=3599 => (secs / 60, "m", "\u{5206}\u{949f}"), // 分钟
+        3600..=86399 => (secs / 3600, "h", "\u{5c0f}\u{65f6}"), // 小时
+        _ => (secs / 86400, "d", "\u{5929}"), // 天
+    };
+    if zh { format!("{}{}\u{524d}", n, cn) } else { format!("{}{} ago", n, en) } // 前
+}
+
+fn render(cfg: &MessagesConfig, t: &EventText, surface: Surface, language: &str, max_chars: usize, mode: &str) -> String {
+    let kind = t.kind();
+    let key = match surface {
+        Surface::Message => kind.to_string(),
+        Surface::Title => format!("{}.title", kind),
+        Surface::Body => format!("{}.body", kind),
+    };
+    let template = match cfg.templates.get(&key) {
+        Some(custom) => custom.as_str(),
+        // Built-in bodies end with the duration only when it is known
+        None => match builtin(language, kind, surface) {
+            b if t.duration.is_empty() => b.strip_suffix(" \u{00b7} {duration}").unwrap_or(b),
+            b => b,
+        },
+    };
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    // One pass, so a placeholder inside a substituted value stays as it is
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('}') else {
+            rest = &rest[open..];
+            break;
+        };
+        match &after[..close] {
+            "sid" => out.push_str(t.sid),
+            "cwd" => out.push_str(t.cwd),
+            "project" => out.push_str(t.project),
+            "message" => out.push_str(&truncate_by(t.message, max_chars, mode)),
+            "model" => out.push_str(if t.model.is_empty() { "unknown" } else { t.model }),
+            "event" => out.push_str(&t.event.to_string()),
+            "tool" => out.push_str(t.tool),
+            "duration" => out.push_str(t.duration),
+            other => {
+                out.push('{');
+                out.push_str(other);
+                out.push('}');
+            }
+        }
+        rest = &after[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Built-in template. With no language set, event messages are English
+/// and toasts Chinese.
+fn builtin(language: &str, kind: &str, surface: Surface) -> &'static str {
+    let zh = match surface {
+        Surface::Message => language.eq_ignore_ascii_case("zh"),
+        Surface::Title | Surface::Body => !language.eq_ignore_ascii_case("en"),
+    };
+    match (surface, zh) {
+        (Surface::Message, false) => match kind {
+            "stop" => "[Done] {sid}\n{cwd}\n{message}",
+            "confirm" => "[Confirm] {sid}\n{message}",
+            "idle" => "[Idle] {sid} waiting for input",
+            "notice" => "[Notice] {sid}\n{message}",
+            "start" => "[Start] {sid} | {model} | {cwd}",
+            "end" => "[End] {sid}",
+            "limit" => "[Limit] {sid}\n{cwd}\n{message}",
+            _ => "[{event}] {sid}",
+        },
+        (Surface::Message, true) => match kind {
+            "stop" => "[\u{5b8c}\u{6210}] {sid}\n{cwd}\n{message}", // [完成] {sid} / {cwd} / {message}
+            "confirm" => "[\u{786e}\u{8ba4}] {sid}\n{message}", // [确认] {sid} / {message}
+            "idle" => "[\u{7a7a}\u{95f2}] {sid} \u{7b49}\u{5f85}\u{8f93}\u{5165}", // [空闲] {sid} 等待输入
+            "notice" => "[\u{901a}\u{77e5}] {sid}\n{message}", // [通知] {sid} / {message}
+            "start" => "[\u{542f}\u{52a8}] {sid} | {model} | {cwd}", // [启动] {sid} | {model} | {cwd}
+            "end" => "[\u{7ed3}\u{675f}] {sid}", // [结束] {sid}
+            "limit" => "[\u{9650}\u{989d}] {sid}\n{cwd}\n{message}", // [限额] {sid} / {cwd} / {message}
+            _ => "[{event}] {sid}",
+        },
+        (Surface::Title, false) => match kind {
+            "stop" => "\u{2705} Done \u{2014} {project}",
+            "confirm" => "\u{1f514} Action needed \u{2014} {project}",
+            "idle" => "\u{1f4a4} Waiting for input \u{2014} {project}",
+            "notice" => "\u{1f4e2} Notice \u{2014} {project}",
+            "limit" => "\u{23f3} Usage limit reached \u{2014} {project}",
+            "permission" => "\u{1f510} Permission request \u{2014} {tool} \u{00b7} {project}",
+            _ => "",
+        },
+        (Surface::Title, true) => match kind {
+            "stop" => "\u{2705} \u{4efb}\u{52a1}\u{5b8c}\u{6210} \u{2014} {project}", // ✅ 任务完成 — {project}
+            "confirm" => "\u{1f514} \u{9700}\u{8981}\u{64cd}\u{4f5c} \u{2014} {project}", // 🔔 需要操作 — {project}
+            "idle" => "\u{1f4a4} \u{7b49}\u{5f85}\u{8f93}\u{5165} \u{2014} {project}", // 💤 等待输入 — {project}
+            "notice" => "\u{1f4e2} \u{901a}\u{77e5} \u{2014} {project}", // 📢 通知 — {project}
+            "limit" => "\u{23f3} \u{7528}\u{91cf}\u{5df2}\u{8fbe}\u{4e0a}\u{9650} \u{2014} {project}", // ⏳ 用量已达上限 — {project}
+            "permission" => "\u{1f510} \u{6743}\u{9650}\u{8bf7}\u{6c42} \u{2014} {tool} \u{00b7} {project}", // 🔐 权限请求 — {tool} · {project}
+            _ => "",
+        },
+        (Surface::Body, zh) => match kind {
+            "idle" if zh => "\u{7b49}\u{5f85}\u{8f93}\u{5165}\u{4e2d}...", // 等待输入中...
+            "idle" => "Waiting for input...",
+            "stop" => "{message}\n{cwd} \u{00b7} {duration}",
+            "limit" => "{message}\n{cwd}",
+            _ => "{message}",
+        },
+    }
+}
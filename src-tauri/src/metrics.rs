@@ -0,0 +1,80 @@
+//! Per-endpoint request latency histograms.
+//!
+//! Recorded by the request logging middleware in `server.rs` and exposed
+//! through `/api/metrics`. Routes are keyed by their matched pattern
+//! (`/api/session/{id}`), so cardinality stays bounded.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Histogram bucket upper bounds in milliseconds (last bucket is +Inf).
+const BUCKETS_MS: [u64; 10] = [1, 5, 10, 25, 50, 100, 250, 500, 1000, 2500];
+
+#[derive(Default)]
+struct RouteStats {
+    count: u64,
+    errors: u64,
+    total_us: u64,
+    max_us: u64,
+    /// One slot per `BUCKETS_MS` entry plus a trailing +Inf slot.
+    buckets: [u64; BUCKETS_MS.len() + 1],
+}
+
+pub struct RequestMetrics {
+    routes: Mutex<HashMap<String, RouteStats>>,
+}
+
+impl RequestMetrics {
+    pub fn new() -> Self {
+        Self {
+            routes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record one completed request.
+    pub fn record(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let us = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let ms = us / 1000;
+        let slot = BUCKETS_MS.iter().position(|&b| ms < b).unwrap_or(BUCKETS_MS.len());
+
+        let mut routes = mutex_lock!(self.routes);
+        let stats = routes.entry(format!("{} {}", method, route)).or_default();
+        stats.count += 1;
+        if status >= 500 {
+            stats.errors += 1;
+        }
+        stats.total_us += us;
+        stats.max_us = stats.max_us.max(us);
+        stats.buckets[slot] += 1;
+    }
+
+    /// JSON snapshot: `{ "GET /api/all": { count, errors, avg_ms, max_ms, buckets } }`.
+    pub fn snapshot(&self) -> Value {
+        let routes = mutex_lock!(self.routes);
+        let mut out = serde_json::Map::new();
+        for (key, s) in routes.iter() {
+            let buckets: Vec<Value> = BUCKETS_MS
+                .iter()
+                .map(|b| json!(format!("<{}ms", b)))
+                .chain(std::iter::once(json!("+Inf")))
+                .zip(s.buckets.iter())
+                .map(|(le, n)| json!({ "le": le, "count": n }))
+                .collect();
+            let avg_ms = if s.count > 0 {
+                s.total_us as f64 / s.count as f64 / 1000.0
+            } else {
+                0.0
+            };
+            out.insert(key.clone(), json!({
+                "count": s.count,
+                "errors": s.errors,
+                "avg_ms": (avg_ms * 100.0).round() / 100.0,
+                "max_ms": (s.max_us as f64 / 10.0).round() / 100.0,
+                "buckets": buckets,
+            }));
+        }
+        Value::Object(out)
+    }
+}
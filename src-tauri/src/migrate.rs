@@ -0,0 +1,159 @@
+//! Versioned state files. The schema version of each file is recorded in
+//! `schema.json` next to `sessions.json`; at startup, before anything reads
+//! them, files behind the current version are brought up to it by running
+//! their migrations in order. A change to a stored shape (a field renamed,
+//! a value reinterpreted) adds a `Migration` to the file's list instead of
+//! throwing old state away; a field that is simply new needs none, since it
+//! deserializes with `#[serde(default)]`. Further state files join `run`'s
+//! list with their own migrations.
+//!
+//! Versions are kept beside the files rather than in them so the files keep
+//! their shape, readable by older builds and scripts. A file is backed up
+//! (`<name>.v<N>.bak`) before it is rewritten, and one recorded at a version
+//! newer than this build knows is left alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+/// One step, rewriting a document (or a record) from version `to - 1`.
+pub struct Migration {
+    pub to: u32,
+    /// What changed, for the log.
+    pub what: &'static str,
+    pub apply: fn(&mut Value),
+}
+
+/// sessions.json: `{session_id: SessionInfo}`.
+const SESSIONS: &[Migration] = &[];
+
+/// events.jsonl: one `Event` per line.
+const EVENTS: &[Migration] = &[];
+
+/// Version of files written before versioning.
+const UNVERSIONED: u32 = 1;
+
+#[derive(Clone, Copy)]
+enum Format {
+    /// One JSON document, migrated whole.
+    Json,
+    /// One JSON record per line, each migrated on its own.
+    JsonLines,
+}
+
+struct StateFile<'a> {
+    /// Key in `schema.json`.
+    key: &'static str,
+    path: &'a Path,
+    format: Format,
+    migrations: &'static [Migration],
+}
+
+impl StateFile<'_> {
+    fn current_version(&self) -> u32 {
+        self.migrations.last().map_or(UNVERSIONED, |m| m.to)
+    }
+}
+
+/// Bring the state files up to date. Call once at startup, before the
+/// stores load them.
+pub fn run(sessions_file: &str, events_file: &str) {
+    let files = [
+        StateFile { key: "sessions", path: Path::new(sessions_file), format: Format::Json, migrations: SESSIONS },
+        StateFile { key: "events", path: Path::new(events_file), format: Format::JsonLines, migrations: EVENTS },
+    ];
+    let versions_path = Path::new(sessions_file).with_file_name("schema.json");
+    let mut versions: Map<String, Value> = fs::read_to_string(&versions_path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    let mut changed = false;
+    for file in &files {
+        let target = file.current_version();
+        let recorded = versions.get(file.key).and_then(|v| v.as_u64()).map(|v| v as u32);
+        let from = recorded.unwrap_or(UNVERSIONED);
+        if from > target {
+            tracing::warn!(
+                "{} is at schema version {}, newer than this build's {} — left as is",
+                file.path.display(),
+                from,
+                target
+            );
+            continue;
+        }
+        let migrated = if from < target { migrate(file, from) } else { Ok(()) };
+        if let Err(e) = migrated {
+            tracing::warn!("Migrating {} from schema version {} failed: {}", file.path.display(), from, e);
+            continue;
+        }
+        if recorded != Some(target) {
+            versions.insert(file.key.to_string(), Value::from(target));
+            changed = true;
+        }
+    }
+
+    if changed {
+        let json = serde_json::to_string_pretty(&versions).unwrap_or_default();
+        if let Err(e) = fs::write(&versions_path, json) {
+            tracing::warn!("Failed to write {}: {}", versions_path.display(), e);
+        }
+    }
+}
+
+/// Run `file`'s migrations past version `from`, backing it up first.
+fn migrate(file: &StateFile, from: u32) -> std::io::Result<()> {
+    let steps: Vec<&Migration> = file.migrations.iter().filter(|m| m.to > from).collect();
+    let contents = match fs::read_to_string(file.path) {
+        Ok(c) => c,
+        // Nothing written yet: it starts out current
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let migrated = match file.format {
+        Format::Json => {
+            let mut doc: Value = serde_json::from_str(&contents)?;
+            for m in &steps {
+                (m.apply)(&mut doc);
+            }
+            serde_json::to_string_pretty(&doc)?
+        }
+        Format::JsonLines => {
+            let mut out = String::with_capacity(contents.len());
+            for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+                match serde_json::from_str::<Value>(line) {
+                    Ok(mut record) => {
+                        for m in &steps {
+                            (m.apply)(&mut record);
+                        }
+                        out.push_str(&record.to_string());
+                    }
+                    // Readers skip it as they did before
+                    Err(_) => out.push_str(line),
+                }
+                out.push('\n');
+            }
+            out
+        }
+    };
+
+    let backup = sibling(file.path, &format!("v{}.bak", from));
+    fs::copy(file.path, &backup)?;
+    let tmp = sibling(file.path, "tmp");
+    fs::write(&tmp, migrated)?;
+    fs::rename(&tmp, file.path)?;
+    for m in &steps {
+        tracing::info!("Migrated {} to schema version {}: {}", file.path.display(), m.to, m.what);
+    }
+    tracing::info!("Previous {} kept as {}", file.path.display(), backup.display());
+    Ok(())
+}
+
+/// `path` with `.suffix` appended to its file name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
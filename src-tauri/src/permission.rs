@@ -10,7 +10,9 @@
 use serde::Serialize;
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::oneshot;
 
 use crate::protocol::PermissionDecisionKind;
@@ -22,18 +24,305 @@ pub struct PermissionRequest {
     pub cwd: String,
     pub tool_name: String,
     pub tool_input: Value,
+    /// Display-ready digest of `tool_input` (None for unknown tools).
+    pub summary: Option<ToolSummary>,
+    /// Markdown plan of an ExitPlanMode request, reviewed as a whole
+    /// rather than shown as a tool call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
     pub permission_suggestions: Value,
     pub timestamp: f64,
     pub timeout_secs: u64,
+    /// Absolute unix time (seconds) at which the request auto-resolves.
+    pub deadline: f64,
+    /// From a pinned session or an `island.priority_paths` project: listed
+    /// ahead of the others, with its own sound and colour.
+    pub priority: bool,
+}
+
+/// `tool_input` of a common tool boiled down to what a UI shows.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolSummary {
+    /// command | file | url | search | plan
+    pub kind: &'static str,
+    /// Command string, file path, URL, query or plan title.
+    pub target: String,
+    /// Description, prompt or content excerpt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Whether `target` or `detail` was cut short.
+    pub truncated: bool,
+}
+
+const TARGET_MAX: usize = 1000;
+const DETAIL_MAX: usize = 300;
+
+impl ToolSummary {
+    /// Digest `tool_input` for Bash, Edit/MultiEdit, Write, Read,
+    /// NotebookEdit, WebFetch, WebSearch and ExitPlanMode. None for other
+    /// tools.
+    pub fn from_tool(tool_name: &str, input: &Value) -> Option<Self> {
+        let field = |key: &str| input.get(key).and_then(Value::as_str);
+        let (kind, target, detail) = match tool_name {
+            "Bash" => ("command", field("command")?, field("description")),
+            "Edit" => ("file", field("file_path")?, field("new_string")),
+            "MultiEdit" => (
+                "file",
+                field("file_path")?,
+                input.get("edits").and_then(|e| e.get(0)).and_then(|e| e.get("new_string")).and_then(Value::as_str),
+            ),
+            "Write" => ("file", field("file_path")?, field("content")),
+            "Read" => ("file", field("file_path")?, None),
+            "NotebookEdit" => ("file", field("notebook_path")?, field("new_source")),
+            "WebFetch" => ("url", field("url")?, field("prompt")),
+            "WebSearch" => ("search", field("query")?, None),
+            "ExitPlanMode" => {
+                let plan = field("plan")?;
+                let title = plan.lines().map(|l| l.trim_start_matches('#').trim()).find(|l| !l.is_empty())?;
+                ("plan", title, Some(plan))
+            }
+            _ => return None,
+        };
+        let (target, cut_target) = clip(target, TARGET_MAX);
+        let (detail, cut_detail) = match detail.filter(|d| !d.trim().is_empty()) {
+            Some(d) => {
+                let (d, cut) = clip(d, DETAIL_MAX);
+                (Some(d), cut)
+            }
+            None => (None, false),
+        };
+        Some(Self { kind, target, detail, truncated: cut_target || cut_detail })
+    }
+}
+
+/// The plan of an ExitPlanMode request.
+pub fn plan_text(tool_name: &str, input: &Value) -> Option<String> {
+    if tool_name != "ExitPlanMode" {
+        return None;
+    }
+    input.get("plan").and_then(Value::as_str).filter(|p| !p.trim().is_empty()).map(str::to_string)
+}
+
+/// First `max` chars of `s` (char-boundary safe) and whether it was cut.
+fn clip(s: &str, max: usize) -> (String, bool) {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => (format!("{}\u{2026}", &s[..i]), true),
+        None => (s.to_string(), false),
+    }
+}
+
+/// The user's answer to a permission request, handed to the waiting hook.
+#[derive(Debug, Clone)]
+pub struct PermissionReply {
+    pub decision: PermissionDecisionKind,
+    /// Indices into `permission_suggestions` chosen by the user.
+    pub selected_suggestions: Option<Vec<usize>>,
+    /// Reason passed to the agent with a deny (empty = none).
+    pub message: String,
+    /// File-edit approvals scoped to these paths (see `path_rules`).
+    pub allowed_paths: Option<Vec<String>>,
+}
+
+impl PermissionReply {
+    /// `updatedPermissions` for the hook output: the selected suggestions
+    /// (when allowing), or every suggestion for an unqualified `always_allow`.
+    pub fn updated_permissions(&self, suggestions: &Value) -> Value {
+        if self.decision.to_behavior() != "approve" {
+            return Value::Array(Vec::new());
+        }
+        match &self.selected_suggestions {
+            Some(picked) => {
+                let all = suggestions.as_array().map(Vec::as_slice).unwrap_or_default();
+                let mut seen = HashSet::new();
+                Value::Array(
+                    picked
+                        .iter()
+                        .filter(|i| seen.insert(**i))
+                        .filter_map(|&i| all.get(i).cloned())
+                        .collect(),
+                )
+            }
+            None if self.decision == PermissionDecisionKind::AlwaysAllow => suggestions.clone(),
+            None => Value::Array(Vec::new()),
+        }
+    }
+
+    /// `updatedPermissions` adding one `Edit(<path>)` rule per allowed path,
+    /// relative to `cwd` where possible: for the session with
+    /// `allow_session`, in the project's local settings with `always_allow`.
+    /// None unless the reply carries paths and remembers the approval.
+    pub fn path_rules(&self, cwd: &str) -> Option<Value> {
+        let paths = self.allowed_paths.as_ref().filter(|p| !p.is_empty())?;
+        let destination = match self.decision {
+            PermissionDecisionKind::AllowSession => "session",
+            PermissionDecisionKind::AlwaysAllow => "localSettings",
+            _ => return None,
+        };
+        let rules: Vec<Value> = paths
+            .iter()
+            .map(|p| serde_json::json!({ "toolName": "Edit", "ruleContent": rule_content(p, cwd) }))
+            .collect();
+        Some(serde_json::json!([{
+            "type": "addRules",
+            "rules": rules,
+            "behavior": "allow",
+            "destination": destination,
+        }]))
+    }
+}
+
+/// Tools whose permission concerns a single file.
+pub const EDIT_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// File an edit tool call writes to; None for other tools.
+pub fn edit_target<'a>(tool_name: &str, input: &'a Value) -> Option<&'a str> {
+    if !EDIT_TOOLS.contains(&tool_name) {
+        return None;
+    }
+    input.get("file_path").or_else(|| input.get("notebook_path")).and_then(Value::as_str)
+}
+
+fn is_absolute(p: &str) -> bool {
+    p.starts_with('/') || p.as_bytes().get(1) == Some(&b':')
+}
+
+/// Absolute, `/`-separated form of `path` (relative ones resolved against
+/// `cwd`, `.` and `..` resolved), lowercased on Windows. A trailing `/`
+/// marks a directory.
+fn normalize_path(path: &str, cwd: &str) -> String {
+    let path = path.replace('\\', "/");
+    let full = if is_absolute(&path) {
+        path
+    } else {
+        format!("{}/{}", cwd.replace('\\', "/").trim_end_matches('/'), path)
+    };
+    let full = resolve_dots(&full);
+    if cfg!(windows) { full.to_lowercase() } else { full }
+}
+
+/// `path` with `.` and `..` segments (and doubled `/`) resolved lexically,
+/// so `src/../../etc/x` can't pass a prefix check for `src/`. `..` stops
+/// at the root (`/` or a drive).
+fn resolve_dots(path: &str) -> String {
+    let mut segments = path.split('/');
+    let root = segments.next().unwrap_or("");
+    let mut parts: Vec<&str> = Vec::new();
+    for segment in segments {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            s => parts.push(s),
+        }
+    }
+    let mut out = format!("{}/{}", root, parts.join("/"));
+    if path.ends_with('/') && !parts.is_empty() {
+        out.push('/');
+    }
+    out
+}
+
+/// `ruleContent` of an Edit rule: relative to `cwd` when inside it, `//abs`
+/// otherwise; a directory covers everything below it.
+fn rule_content(path: &str, cwd: &str) -> String {
+    let full = normalize_path(path, cwd);
+    let base = format!("{}/", normalize_path(cwd, cwd).trim_end_matches('/'));
+    let mut rule = match full.strip_prefix(&base) {
+        Some(rel) => rel.to_string(),
+        None => format!("//{}", full.trim_start_matches('/')),
+    };
+    if rule.ends_with('/') {
+        rule.push_str("**");
+    }
+    rule
+}
+
+/// A temporary blanket approval ("auto-approve for 30 minutes") for
+/// periods when the user is supervising anyway.
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoApproveWindow {
+    pub id: String,
+    /// Only this session; None = every session.
+    pub session_id: Option<String>,
+    /// Only these tools; empty = every tool.
+    pub tools: Vec<String>,
+    pub started_at: f64,
+    pub expires_at: f64,
+    /// Requests granted through this window so far.
+    pub approved: u64,
+}
+
+impl AutoApproveWindow {
+    fn covers(&self, session_id: &str, tool_name: &str) -> bool {
+        self.session_id.as_deref().is_none_or(|s| s == session_id)
+            && (self.tools.is_empty() || self.tools.iter().any(|t| t == tool_name))
+    }
+}
+
+/// The answer to a request, reused for an identical one shortly after.
+struct RecentDecision {
+    decision: PermissionDecisionKind,
+    message: String,
+    at: f64,
+}
+
+/// Identity of a request for repeat detection: the tool plus its input with
+/// keys sorted, strings trimmed and `description` dropped (agents reword it
+/// when they retry).
+fn request_hash(tool_name: &str, input: &Value) -> u64 {
+    fn normalize(v: &Value) -> Value {
+        match v {
+            Value::Object(m) => Value::Object(
+                m.iter().filter(|(k, _)| *k != "description").map(|(k, v)| (k.clone(), normalize(v))).collect(),
+            ),
+            Value::Array(a) => Value::Array(a.iter().map(normalize).collect()),
+            Value::String(s) => Value::String(s.trim().to_string()),
+            other => other.clone(),
+        }
+    }
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    tool_name.hash(&mut h);
+    normalize(input).to_string().hash(&mut h);
+    h.finish()
+}
+
+/// How long a closed request is remembered for `PermissionStore::closed`.
+const CLOSED_KEEP_SECS: f64 = 3600.0;
+
+/// How a request that is no longer pending ended.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Closed {
+    /// Answered from some UI, a link or an auto-approve window.
+    Answered,
+    /// Timed out: the agent got the fallback answer.
+    Expired,
 }
 
 pub struct PermissionStore {
     /// Pending requests (keyed by id).
     requests: Mutex<HashMap<String, PermissionRequest>>,
     /// Oneshot senders waiting for decisions (keyed by request id).
-    senders: Mutex<HashMap<String, oneshot::Sender<PermissionDecisionKind>>>,
+    senders: Mutex<HashMap<String, oneshot::Sender<PermissionReply>>>,
     /// Session-scoped auto-approvals: (session_id, tool_name) → auto-approve.
     session_rules: Mutex<HashSet<(String, String)>>,
+    /// Session-scoped file-edit approvals: session_id → normalized paths
+    /// (directories end in `/`).
+    session_paths: Mutex<HashMap<String, Vec<String>>>,
+    /// Auto-approve windows, expired ones pruned lazily.
+    windows: Mutex<Vec<AutoApproveWindow>>,
+    /// Recent allow/deny answers: (session_id, request hash) → decision.
+    recent: Mutex<HashMap<(String, u64), RecentDecision>>,
+    /// Requests no longer pending, for `CLOSED_KEEP_SECS`: id → how and
+    /// when they ended, so a late answer can be told why it came too late.
+    closed: Mutex<HashMap<String, (Closed, f64)>>,
+    /// Lifetime counters (since app start) for the dashboard.
+    requested: AtomicU64,
+    allowed: AtomicU64,
+    denied: AtomicU64,
+    deferred: AtomicU64,
+    expired: AtomicU64,
 }
 
 impl PermissionStore {
@@ -42,6 +331,15 @@ impl PermissionStore {
             requests: Mutex::new(HashMap::new()),
             senders: Mutex::new(HashMap::new()),
             session_rules: Mutex::new(HashSet::new()),
+            session_paths: Mutex::new(HashMap::new()),
+            windows: Mutex::new(Vec::new()),
+            recent: Mutex::new(HashMap::new()),
+            closed: Mutex::new(HashMap::new()),
+            requested: AtomicU64::new(0),
+            allowed: AtomicU64::new(0),
+            denied: AtomicU64::new(0),
+            deferred: AtomicU64::new(0),
+            expired: AtomicU64::new(0),
         }
     }
 
@@ -50,33 +348,81 @@ impl PermissionStore {
     pub fn register(
         &self,
         req: PermissionRequest,
-    ) -> oneshot::Receiver<PermissionDecisionKind> {
+    ) -> oneshot::Receiver<PermissionReply> {
         let (tx, rx) = oneshot::channel();
         let id = req.id.clone();
         mutex_lock!(self.requests).insert(id.clone(), req);
         mutex_lock!(self.senders).insert(id, tx);
+        self.requested.fetch_add(1, Ordering::Relaxed);
         rx
     }
 
     /// Send a decision for a pending request. Returns true if sent.
-    pub fn respond(&self, id: &str, decision: PermissionDecisionKind) -> bool {
-        mutex_lock!(self.requests).remove(id);
+    pub fn respond(&self, id: &str, reply: PermissionReply, now: f64) -> bool {
+        if mutex_lock!(self.requests).remove(id).is_some() {
+            self.close(id, Closed::Answered, now);
+        }
+        let counter = match reply.decision {
+            PermissionDecisionKind::Allow
+            | PermissionDecisionKind::AllowSession
+            | PermissionDecisionKind::AlwaysAllow
+            | PermissionDecisionKind::ApprovePlan => &self.allowed,
+            PermissionDecisionKind::Deny | PermissionDecisionKind::RejectPlan => &self.denied,
+            PermissionDecisionKind::AskTerminal => &self.deferred,
+        };
         if let Some(tx) = mutex_lock!(self.senders).remove(id) {
-            tx.send(decision).is_ok()
+            let sent = tx.send(reply).is_ok();
+            if sent {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+            sent
         } else {
             false
         }
     }
 
-    /// Get all pending requests (for UI display).
+    /// Get all pending requests (for UI display): priority ones first,
+    /// then oldest first.
     pub fn get_pending(&self) -> Vec<PermissionRequest> {
-        mutex_lock!(self.requests).values().cloned().collect()
+        let mut pending: Vec<PermissionRequest> = mutex_lock!(self.requests).values().cloned().collect();
+        pending.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.timestamp.total_cmp(&b.timestamp)));
+        pending
     }
 
-    /// Clean up a request (e.g. on timeout).
-    pub fn remove(&self, id: &str) {
-        mutex_lock!(self.requests).remove(id);
+    /// Clean up a request on timeout. Returns it if it was still pending,
+    /// which counts it as expired.
+    pub fn expire(&self, id: &str, now: f64) -> Option<PermissionRequest> {
+        let req = mutex_lock!(self.requests).remove(id);
+        if req.is_some() {
+            self.expired.fetch_add(1, Ordering::Relaxed);
+            self.close(id, Closed::Expired, now);
+        }
         mutex_lock!(self.senders).remove(id);
+        req
+    }
+
+    /// How and when a request that is no longer pending ended, if that was
+    /// within the last `CLOSED_KEEP_SECS`.
+    pub fn closed(&self, id: &str) -> Option<(Closed, f64)> {
+        mutex_lock!(self.closed).get(id).copied()
+    }
+
+    fn close(&self, id: &str, how: Closed, now: f64) {
+        let mut closed = mutex_lock!(self.closed);
+        closed.retain(|_, (_, at)| now - *at < CLOSED_KEEP_SECS);
+        closed.insert(id.to_string(), (how, now));
+    }
+
+    /// Lifetime counters since app start.
+    pub fn stats(&self) -> serde_json::Value {
+        serde_json::json!({
+            "pending": mutex_lock!(self.requests).len(),
+            "requested": self.requested.load(Ordering::Relaxed),
+            "allowed": self.allowed.load(Ordering::Relaxed),
+            "denied": self.denied.load(Ordering::Relaxed),
+            "deferred": self.deferred.load(Ordering::Relaxed),
+            "expired": self.expired.load(Ordering::Relaxed),
+        })
     }
 
     /// Add a session-scoped auto-approve rule.
@@ -89,8 +435,141 @@ impl PermissionStore {
         mutex_lock!(self.session_rules).contains(&(session_id.to_string(), tool_name.to_string()))
     }
 
+    /// Add a session-scoped approval for edits to `paths` (relative to `cwd`).
+    pub fn add_session_paths(&self, session_id: &str, cwd: &str, paths: &[String]) {
+        mutex_lock!(self.session_paths)
+            .entry(session_id.to_string())
+            .or_default()
+            .extend(paths.iter().map(|p| normalize_path(p, cwd)));
+    }
+
+    /// Check if an edit tool call targets a path approved for this session.
+    pub fn check_session_paths(&self, session_id: &str, cwd: &str, tool_name: &str, input: &Value) -> bool {
+        let Some(target) = edit_target(tool_name, input) else {
+            return false;
+        };
+        let target = normalize_path(target, cwd);
+        mutex_lock!(self.session_paths)
+            .get(session_id)
+            .is_some_and(|paths| paths.iter().any(|p| if p.ends_with('/') { target.starts_with(p.as_str()) } else { target == *p }))
+    }
+
     /// Clear all session rules for a session (on session end).
     pub fn clear_session_rules(&self, session_id: &str) {
         mutex_lock!(self.session_rules).retain(|(sid, _)| sid != session_id);
+        mutex_lock!(self.session_paths).remove(session_id);
+        mutex_lock!(self.recent).retain(|(sid, _), _| sid != session_id);
+    }
+
+    /// Remember how `req` was answered, for `repeat_decision`. Only allow
+    /// and deny count; a request handed to the terminal is asked again.
+    pub fn remember_decision(&self, req: &PermissionRequest, decision: &PermissionDecisionKind, message: &str, now: f64) {
+        let decision = match decision {
+            PermissionDecisionKind::AskTerminal => return,
+            PermissionDecisionKind::Deny | PermissionDecisionKind::RejectPlan => PermissionDecisionKind::Deny,
+            _ => PermissionDecisionKind::Allow,
+        };
+        mutex_lock!(self.recent).insert(
+            (req.session_id.clone(), request_hash(&req.tool_name, &req.tool_input)),
+            RecentDecision { decision, message: message.to_string(), at: now },
+        );
+    }
+
+    /// The answer given within the last `window_secs` to a request identical
+    /// to this one: the decision and the deny message.
+    pub fn repeat_decision(
+        &self,
+        session_id: &str,
+        tool_name: &str,
+        input: &Value,
+        now: f64,
+        window_secs: f64,
+    ) -> Option<(PermissionDecisionKind, String)> {
+        let mut recent = mutex_lock!(self.recent);
+        recent.retain(|_, d| now - d.at < window_secs);
+        recent
+            .get(&(session_id.to_string(), request_hash(tool_name, input)))
+            .map(|d| (d.decision.clone(), d.message.clone()))
+    }
+
+    /// Open an auto-approve window lasting `secs` from `now`.
+    pub fn add_window(&self, session_id: Option<String>, tools: Vec<String>, now: f64, secs: f64) -> AutoApproveWindow {
+        let window = AutoApproveWindow {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id,
+            tools,
+            started_at: now,
+            expires_at: now + secs,
+            approved: 0,
+        };
+        let mut windows = mutex_lock!(self.windows);
+        windows.retain(|w| w.expires_at > now);
+        windows.push(window.clone());
+        window
+    }
+
+    /// If an open window covers the request, count it as approved through
+    /// that window and return the window.
+    pub fn take_window(&self, session_id: &str, tool_name: &str, now: f64) -> Option<AutoApproveWindow> {
+        let mut windows = mutex_lock!(self.windows);
+        windows.retain(|w| w.expires_at > now);
+        let w = windows.iter_mut().find(|w| w.covers(session_id, tool_name))?;
+        w.approved += 1;
+        Some(w.clone())
+    }
+
+    /// Windows still open at `now`.
+    pub fn windows(&self, now: f64) -> Vec<AutoApproveWindow> {
+        let mut windows = mutex_lock!(self.windows);
+        windows.retain(|w| w.expires_at > now);
+        windows.clone()
+    }
+
+    /// Close window `id`, or every window for `None`. Returns how many closed.
+    pub fn close_windows(&self, id: Option<&str>) -> usize {
+        let mut windows = mutex_lock!(self.windows);
+        let before = windows.len();
+        windows.retain(|w| id.is_some_and(|id| w.id != id));
+        before - windows.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CWD: &str = "/home/me/app";
+
+    fn approved(paths: &[&str], target: &str) -> bool {
+        let store = PermissionStore::new();
+        let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+        store.add_session_paths("s1", CWD, &paths);
+        store.check_session_paths("s1", CWD, "Edit", &json!({ "file_path": target }))
+    }
+
+    #[test]
+    fn dots_are_resolved() {
+        assert_eq!(normalize_path("./src/./a.rs", CWD), "/home/me/app/src/a.rs");
+        assert_eq!(normalize_path("src/../lib/", CWD), "/home/me/app/lib/");
+        assert_eq!(normalize_path("../../../../../etc/x", CWD), "/etc/x");
+        assert_eq!(normalize_path("/a//b/../c", CWD), "/a/c");
+        assert_eq!(normalize_path("C:\\code\\app\\..\\x", CWD).to_lowercase(), "c:/code/x");
+    }
+
+    #[test]
+    fn approved_directory_covers_its_files() {
+        assert!(approved(&["src/"], "src/main.rs"));
+        assert!(approved(&["src/"], "/home/me/app/src/deep/mod.rs"));
+        assert!(approved(&["src/"], "src/../src/main.rs"));
+        assert!(approved(&["src/main.rs"], "./src/main.rs"));
+    }
+
+    #[test]
+    fn dot_dot_cannot_escape_an_approved_directory() {
+        assert!(!approved(&["src/"], "src/../../etc/x"));
+        assert!(!approved(&["src/"], "src/../Cargo.toml"));
+        assert!(!approved(&["src/"], "/home/me/app/src/../../../etc/passwd"));
+        assert!(!approved(&["src/main.rs"], "src/main.rs/../../x"));
     }
 }
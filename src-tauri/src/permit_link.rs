@@ -0,0 +1,139 @@
+//! One-tap permission links for remote channels (DingTalk action cards).
+//!
+//! A link is `{manager.public_url}/permit/{id}/{decision}?sig=…`, signed
+//! with a secret that lives only as long as the process, so it needs no
+//! `lan_token` and grants exactly one decision on one request. Opening it
+//! shows a confirm page; the decision is applied by the page's POST, which
+//! keeps link previews and URL scanners from answering for the user.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::sync::{Arc, LazyLock};
+
+use crate::action::Action;
+use crate::protocol::PermissionDecisionKind;
+use crate::server::AppState;
+
+/// Per-run signing key; links from a previous run stop working, as do the
+/// requests they point to.
+static SECRET: LazyLock<String> = LazyLock::new(|| uuid::Uuid::new_v4().to_string());
+
+/// Decisions a link can carry, with their button labels.
+const DECISIONS: &[(&str, &str)] = &[
+    ("allow", "\u{2705} Allow"),
+    ("allow_session", "\u{1f501} Allow for session"),
+    ("deny", "\u{26d4} Deny"),
+];
+
+#[derive(Deserialize)]
+pub struct PermitQuery {
+    #[serde(default)]
+    sig: String,
+}
+
+/// Buttons (label, URL) for a pending request; none without `public_url`.
+pub fn buttons(public_url: &str, id: &str) -> Vec<(String, String)> {
+    let base = public_url.trim_end_matches('/');
+    if base.is_empty() {
+        return Vec::new();
+    }
+    DECISIONS
+        .iter()
+        .map(|(decision, label)| {
+            let url = format!("{}/permit/{}/{}?sig={}", base, id, decision, sign(id, decision));
+            (label.to_string(), url)
+        })
+        .collect()
+}
+
+fn sign(id: &str, decision: &str) -> String {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).expect("HMAC key");
+    mac.update(format!("{}:{}", id, decision).as_bytes());
+    mac.finalize().into_bytes().iter().take(16).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Check the signature and parse the decision.
+fn verify(id: &str, decision: &str, sig: &str) -> Option<PermissionDecisionKind> {
+    if !crate::ui::token_eq(sig, &sign(id, decision)) {
+        return None;
+    }
+    serde_json::from_value(serde_json::Value::String(decision.to_string())).ok()
+}
+
+/// GET /permit/{id}/{decision} — confirm page.
+pub async fn permit_page(
+    State(state): State<Arc<AppState>>,
+    Path((id, decision)): Path<(String, String)>,
+    Query(q): Query<PermitQuery>,
+) -> Response {
+    if verify(&id, &decision, &q.sig).is_none() {
+        return page(StatusCode::FORBIDDEN, "Invalid link", "");
+    }
+    let Some(req) = state.permissions.get_pending().into_iter().find(|r| r.id == id) else {
+        return page(StatusCode::GONE, "Already answered or expired", "");
+    };
+    let label = DECISIONS.iter().find(|(d, _)| *d == decision).map(|(_, l)| *l).unwrap_or("");
+    let target = req.summary.as_ref().map(|s| s.target.as_str()).unwrap_or("");
+    let body = format!(
+        r#"<p><b>{tool}</b> {target}</p><p>{project}</p>
+<form method="post"><button type="submit">{label}</button></form>"#,
+        tool = escape(&req.tool_name),
+        target = escape(target),
+        project = escape(crate::tray::project_name(&req.cwd)),
+        label = label,
+    );
+    page(StatusCode::OK, "Permission request", &body)
+}
+
+/// POST /permit/{id}/{decision} — apply the decision.
+pub async fn permit_apply(
+    State(state): State<Arc<AppState>>,
+    Path((id, decision)): Path<(String, String)>,
+    Query(q): Query<PermitQuery>,
+) -> Response {
+    let Some(kind) = verify(&id, &decision, &q.sig) else {
+        return page(StatusCode::FORBIDDEN, "Invalid link", "");
+    };
+    let action = match kind {
+        PermissionDecisionKind::Deny => Action::Deny { id, message: None },
+        kind => Action::Approve { id, session: kind == PermissionDecisionKind::AllowSession },
+    };
+    let result = crate::action::run(&state, &action, "remote");
+    match result.as_ref().map_err(|e| e.get("reason").and_then(|v| v.as_str())) {
+        Ok(_) => page(StatusCode::OK, "Done", "<p>You can close this page.</p>"),
+        Err(Some("expired")) => page(
+            StatusCode::GONE,
+            "Expired",
+            "<p>The request timed out before this answer; the agent already got the fallback answer.</p>",
+        ),
+        Err(Some("answered")) => page(StatusCode::GONE, "Already answered", ""),
+        Err(_) => page(StatusCode::GONE, "Already answered or expired", ""),
+    }
+}
+
+fn page(status: StatusCode, title: &str, body: &str) -> Response {
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Agent Desk — {title}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; background: #111; color: #eee; margin: 0; padding: 2em; }}
+button {{ font-size: 1.2em; padding: .6em 1.4em; border-radius: 8px; border: 0; background: #D97857; color: #fff; }}
+</style></head>
+<body><h1>{title}</h1>{body}</body></html>"#,
+        title = title,
+        body = body,
+    );
+    (status, [(header::CACHE_CONTROL, "no-store")], Html(html)).into_response()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
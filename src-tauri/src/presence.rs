@@ -0,0 +1,193 @@
+//! Away detection and deferred notifications.
+//!
+//! While the user has not touched keyboard or mouse for `away_after_secs`,
+//! toasts and sounds are held back and collected here. When input resumes
+//! they are delivered as one summary toast. Remote channels can optionally
+//! be reserved for long absences (`escalate_after_secs`).
+//!
+//! With attention batches (`batch_every_mins`) non-urgent toasts are also
+//! held while the user is present, and shown together once per interval;
+//! permission requests still toast at once.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Seconds since the last keyboard/mouse input, or `None` if unknown
+/// (non-Windows, or the call failed) — callers then treat the user as present.
+#[cfg(windows)]
+pub fn idle_secs() -> Option<u64> {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetTickCount() -> u32;
+    }
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    unsafe {
+        if !GetLastInputInfo(&mut info).as_bool() {
+            return None;
+        }
+        // Both are 32-bit tick counts; wrapping_sub handles the 49-day rollover
+        Some(GetTickCount().wrapping_sub(info.dwTime) as u64 / 1000)
+    }
+}
+
+#[cfg(not(windows))]
+pub fn idle_secs() -> Option<u64> {
+    None
+}
+
+/// Whether Windows is holding notifications back: a full-screen app or
+/// game, presentation mode, or quiet hours. `false` when unknown.
+#[cfg(windows)]
+pub fn notifications_suppressed() -> bool {
+    #[link(name = "shell32")]
+    unsafe extern "system" {
+        fn SHQueryUserNotificationState(state: *mut i32) -> i32;
+    }
+    // QUERY_USER_NOTIFICATION_STATE: 2 busy (full-screen), 3 Direct3D
+    // full-screen, 4 presentation mode, 6 quiet time, 7 full-screen store app
+    let mut state = 0i32;
+    let hr = unsafe { SHQueryUserNotificationState(&mut state) };
+    hr >= 0 && matches!(state, 2 | 3 | 4 | 6 | 7)
+}
+
+#[cfg(not(windows))]
+pub fn notifications_suppressed() -> bool {
+    false
+}
+
+/// A notification held back while the user was away.
+#[derive(Debug, Clone)]
+pub struct Deferred {
+    pub title: String,
+    /// Text for remote channels (empty = toast only).
+    pub message: String,
+}
+
+/// What changed on a presence tick, for the caller to act on.
+pub enum Transition {
+    None,
+    /// User just went away.
+    Away,
+    /// User came back; these are the notifications to summarize.
+    Returned(Vec<Deferred>),
+    /// Away long enough to escalate; these messages go to remote channels.
+    Escalate(Vec<Deferred>),
+}
+
+/// Toasts held for the next attention batch.
+#[derive(Default)]
+struct Batch {
+    held: Vec<Deferred>,
+    /// When the current interval ends (unix seconds, 0 = not started).
+    due_at: f64,
+}
+
+/// Held toast count and when they will be shown, for the island.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchStatus {
+    pub held: usize,
+    pub due_at: f64,
+}
+
+pub struct Presence {
+    away: AtomicBool,
+    /// Set once escalation fired for the current absence.
+    escalated: AtomicBool,
+    deferred: Mutex<Vec<Deferred>>,
+    /// Index into `deferred` of the first entry not yet sent remotely.
+    escalated_upto: Mutex<usize>,
+    batch: Mutex<Batch>,
+}
+
+impl Presence {
+    pub fn new() -> Self {
+        Self {
+            away: AtomicBool::new(false),
+            escalated: AtomicBool::new(false),
+            deferred: Mutex::new(Vec::new()),
+            escalated_upto: Mutex::new(0),
+            batch: Mutex::new(Batch::default()),
+        }
+    }
+
+    pub fn is_away(&self) -> bool {
+        self.away.load(Ordering::Relaxed)
+    }
+
+    /// Whether remote channels should receive messages right now.
+    pub fn is_escalated(&self) -> bool {
+        self.escalated.load(Ordering::Relaxed)
+    }
+
+    /// Queue a notification for the summary shown on return.
+    pub fn defer(&self, title: String, message: String) {
+        mutex_lock!(self.deferred).push(Deferred { title, message });
+    }
+
+    /// Hold a toast for the next attention batch; `every_secs` is the
+    /// interval, started by the first toast held.
+    pub fn hold(&self, title: String, now: f64, every_secs: u64) -> BatchStatus {
+        let mut batch = mutex_lock!(self.batch);
+        if batch.due_at == 0.0 {
+            batch.due_at = now + every_secs as f64;
+        }
+        batch.held.push(Deferred { title, message: String::new() });
+        BatchStatus { held: batch.held.len(), due_at: batch.due_at }
+    }
+
+    pub fn batch_status(&self) -> BatchStatus {
+        let batch = mutex_lock!(self.batch);
+        BatchStatus { held: batch.held.len(), due_at: batch.due_at }
+    }
+
+    /// The held toasts once the interval is over (possibly none), starting
+    /// the next one; `None` before that.
+    pub fn batch_due(&self, now: f64, every_secs: u64) -> Option<Vec<Deferred>> {
+        let mut batch = mutex_lock!(self.batch);
+        if batch.due_at == 0.0 {
+            batch.due_at = now + every_secs as f64;
+        }
+        if now < batch.due_at {
+            return None;
+        }
+        batch.due_at = now + every_secs as f64;
+        Some(std::mem::take(&mut batch.held))
+    }
+
+    /// Advance the state machine with the current idle time.
+    /// `away_after` = 0 disables away detection; `escalate_after` = 0 never escalates.
+    pub fn tick(&self, idle: Option<u64>, away_after: u64, escalate_after: u64) -> Transition {
+        let idle = idle.unwrap_or(0);
+        let away_now = away_after > 0 && idle >= away_after;
+        let was_away = self.away.swap(away_now, Ordering::Relaxed);
+
+        match (was_away, away_now) {
+            (false, true) => Transition::Away,
+            (true, false) => {
+                self.escalated.store(false, Ordering::Relaxed);
+                *mutex_lock!(self.escalated_upto) = 0;
+                Transition::Returned(std::mem::take(&mut *mutex_lock!(self.deferred)))
+            }
+            (true, true) if escalate_after > 0 && idle >= escalate_after => {
+                self.escalated.store(true, Ordering::Relaxed);
+                let deferred = mutex_lock!(self.deferred);
+                let mut upto = mutex_lock!(self.escalated_upto);
+                let pending: Vec<Deferred> = deferred[*upto..]
+                    .iter()
+                    .filter(|d| !d.message.is_empty())
+                    .cloned()
+                    .collect();
+                *upto = deferred.len();
+                if pending.is_empty() { Transition::None } else { Transition::Escalate(pending) }
+            }
+            _ => Transition::None,
+        }
+    }
+}
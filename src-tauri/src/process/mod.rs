@@ -1,3 +1,12 @@
+mod restart;
 mod scanner;
+#[cfg(windows)]
+pub mod snapshot;
 
-pub use scanner::{ProcessInfo, ProcessScanner};
+pub use restart::{Restart, RestartDetector};
+pub use scanner::{process_create_time, ProcessInfo, ProcessScanner, CAN_QUERY_CREATE_TIME};
+
+/// CWD compared case- and separator-insensitively.
+pub(crate) fn cwd_key(cwd: &str) -> String {
+    cwd.replace('/', "\\").to_lowercase().trim_end_matches('\\').to_string()
+}
@@ -0,0 +1,105 @@
+//! Crash/restart detection.
+//!
+//! An agent process that disappears and is replaced within a few seconds by
+//! a new one of the same agent in the same CWD is the same agent restarted
+//! (crash, `/exit` + relaunch, update). Reporting the pair lets its session
+//! move to the new PID instead of the new process showing up unmatched.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::{cwd_key, ProcessInfo};
+
+/// A vanished agent process and the one that replaced it.
+#[derive(Debug, Clone)]
+pub struct Restart {
+    pub old_pid: u32,
+    pub new_pid: u32,
+    pub agent_type: String,
+    pub cwd: String,
+}
+
+/// A process that appeared or disappeared, kept for `window`.
+struct Change {
+    pid: u32,
+    agent_type: String,
+    cwd: String,
+    cwd_key: String,
+    at: Instant,
+}
+
+pub struct RestartDetector {
+    window: Duration,
+    /// PID → (agent type, CWD) from the previous scan; None before the first.
+    known: Option<HashMap<u32, (String, String)>>,
+    vanished: Vec<Change>,
+    appeared: Vec<Change>,
+}
+
+impl RestartDetector {
+    /// A disappearance and an appearance count as a restart when they are
+    /// seen at most `window` apart.
+    pub fn new(window: Duration) -> Self {
+        Self { window, known: None, vanished: Vec::new(), appeared: Vec::new() }
+    }
+
+    /// Compare a new scan with the previous one and return the restarts.
+    pub fn observe(&mut self, procs: &[ProcessInfo]) -> Vec<Restart> {
+        let now = Instant::now();
+        let current: HashMap<u32, (String, String)> = procs
+            .iter()
+            .map(|p| (p.pid, (p.agent_type.clone(), p.cwd.clone())))
+            .collect();
+        let Some(known) = self.known.replace(current.clone()) else {
+            return Vec::new(); // first scan: nothing to compare with
+        };
+
+        let change = |pid: u32, (agent_type, cwd): (String, String)| Change {
+            pid,
+            cwd_key: cwd_key(&cwd),
+            agent_type,
+            cwd,
+            at: now,
+        };
+        for (pid, info) in &current {
+            if !known.contains_key(pid) {
+                self.appeared.push(change(*pid, info.clone()));
+            }
+        }
+        for (pid, info) in known {
+            if !current.contains_key(&pid) {
+                self.vanished.push(change(pid, info));
+            }
+        }
+        let window = self.window;
+        self.vanished.retain(|c| now.duration_since(c.at) <= window);
+        self.appeared.retain(|c| now.duration_since(c.at) <= window);
+
+        // Pair each new process with the latest vanished one of the same
+        // agent in the same CWD
+        let mut restarts = Vec::new();
+        let mut i = 0;
+        while i < self.appeared.len() {
+            let new = &self.appeared[i];
+            let old = self.vanished
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| !new.cwd_key.is_empty() && v.agent_type == new.agent_type && v.cwd_key == new.cwd_key)
+                .max_by_key(|(_, v)| v.at)
+                .map(|(j, _)| j);
+            let Some(j) = old else {
+                i += 1;
+                continue;
+            };
+            let old = self.vanished.remove(j);
+            let new = self.appeared.remove(i);
+            restarts.push(Restart {
+                old_pid: old.pid,
+                new_pid: new.pid,
+                agent_type: new.agent_type,
+                cwd: new.cwd,
+            });
+        }
+        restarts
+    }
+}
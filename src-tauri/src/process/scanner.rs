@@ -62,6 +62,8 @@ impl ProcessScanner {
             .as_secs_f64();
 
         let mut results = Vec::new();
+        // Every process, for the focus module's process-tree walks
+        let mut table = Vec::new();
 
         unsafe {
             let snapshot = match CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) {
@@ -76,28 +78,29 @@ impl ProcessScanner {
                 loop {
                     let pid = entry.th32ProcessID;
 
-                    // UTF-16 direct comparison — no String allocation for non-matching processes
                     let name_len = entry
                         .szExeFile
                         .iter()
                         .position(|&c| c == 0)
                         .unwrap_or(entry.szExeFile.len());
                     let name_slice = &entry.szExeFile[..name_len];
+                    let name = String::from_utf16_lossy(name_slice);
 
+                    // UTF-16 direct comparison — no lowercasing allocation per process
                     if self.is_target_process(name_slice) {
-                        let name = String::from_utf16_lossy(name_slice);
                         let (cwd, create_time) = Self::query_process(pid, now);
                         let uptime = (now - create_time) as u64;
 
                         results.push(ProcessInfo {
                             pid,
-                            name,
+                            name: name.clone(),
                             agent_type: self.agent_type.clone(),
                             cwd,
                             uptime,
                             create_time,
                         });
                     }
+                    table.push((pid, entry.th32ParentProcessID, name));
 
                     if Process32NextW(snapshot, &mut entry).is_err() {
                         break;
@@ -108,6 +111,7 @@ impl ProcessScanner {
             let _ = CloseHandle(snapshot);
         }
 
+        super::snapshot::store_processes(super::snapshot::ProcessTable::from_entries(table));
         results
     }
 
@@ -214,3 +218,57 @@ impl ProcessScanner {
         }
     }
 }
+
+/// Whether `process_create_time` works on this platform.
+pub const CAN_QUERY_CREATE_TIME: bool = cfg!(any(windows, target_os = "linux"));
+
+/// Creation time (unix seconds) of process `pid`, or `None` if no such
+/// process is running (or it cannot be opened). Paired with a PID, this
+/// tells a live process apart from a later one that reused the PID.
+#[cfg(windows)]
+pub fn process_create_time(pid: u32) -> Option<f64> {
+    use windows::Win32::Foundation::*;
+    use windows::Win32::System::Threading::*;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut creation = FILETIME::default();
+        let mut exit = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        let ok = GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user).is_ok();
+        // A handle can still be opened briefly after exit; the exit code tells
+        let mut code = 0u32;
+        let running = GetExitCodeProcess(handle, &mut code).is_ok() && code == STILL_ACTIVE.0 as u32;
+        let _ = CloseHandle(handle);
+        if !ok || !running {
+            return None;
+        }
+        let ft = ((creation.dwHighDateTime as u64) << 32) | creation.dwLowDateTime as u64;
+        let unix_offset: u64 = 116_444_736_000_000_000;
+        (ft > unix_offset).then(|| (ft - unix_offset) as f64 / 10_000_000.0)
+    }
+}
+
+/// Linux: `starttime` from /proc/<pid>/stat (clock ticks after boot) plus
+/// `btime` from /proc/stat. Assumes the usual 100 ticks per second.
+#[cfg(target_os = "linux")]
+pub fn process_create_time(pid: u32) -> Option<f64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Fields after the parenthesized command name; starttime is field 22
+    let rest = &stat[stat.rfind(')')? + 1..];
+    let start_ticks: f64 = rest.split_whitespace().nth(19)?.parse().ok()?;
+    let btime: f64 = std::fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|l| l.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(btime + start_ticks / 100.0)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn process_create_time(_pid: u32) -> Option<f64> {
+    None
+}
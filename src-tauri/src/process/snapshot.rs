@@ -0,0 +1,168 @@
+//! Short-lived caches of the process table and the top-level windows.
+//!
+//! Finding a session's terminal walks the process tree up from the agent
+//! and then looks for a window of the terminal process. Taking a fresh
+//! Toolhelp snapshot and walking every window per click made tray menus
+//! with many sessions stall when clicked in quick succession, so both are
+//! reused for `MAX_AGE`. The scanner hands over the table from its own
+//! snapshot, so a focus right after a scan takes none.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a snapshot is reused: a window opened or closed a moment ago
+/// is still seen on the next click.
+const MAX_AGE: Duration = Duration::from_millis(400);
+
+/// All processes from one Toolhelp snapshot.
+#[derive(Default)]
+pub struct ProcessTable {
+    /// PID → (parent PID, exe name)
+    entries: HashMap<u32, (u32, String)>,
+}
+
+impl ProcessTable {
+    /// From (pid, parent pid, exe name) entries.
+    pub fn from_entries(entries: impl IntoIterator<Item = (u32, u32, String)>) -> Self {
+        Self { entries: entries.into_iter().map(|(pid, parent, name)| (pid, (parent, name))).collect() }
+    }
+
+    fn capture() -> Self {
+        use windows::Win32::Foundation::CloseHandle;
+        use windows::Win32::System::Diagnostics::ToolHelp::*;
+
+        let mut entries = Vec::new();
+        unsafe {
+            let snapshot = match CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) {
+                Ok(h) => h,
+                Err(_) => return Self::default(),
+            };
+            let mut entry = PROCESSENTRY32W::default();
+            entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+
+            if Process32FirstW(snapshot, &mut entry).is_ok() {
+                loop {
+                    let name = String::from_utf16_lossy(
+                        &entry.szExeFile[..entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len())]
+                    );
+                    entries.push((entry.th32ProcessID, entry.th32ParentProcessID, name));
+                    if Process32NextW(snapshot, &mut entry).is_err() {
+                        break;
+                    }
+                }
+            }
+            let _ = CloseHandle(snapshot);
+        }
+        Self::from_entries(entries)
+    }
+
+    /// Exe name of `pid`.
+    pub fn name(&self, pid: u32) -> Option<&str> {
+        self.entries.get(&pid).map(|(_, name)| name.as_str())
+    }
+
+    /// Parent PID and the parent's exe name.
+    pub fn parent(&self, pid: u32) -> Option<(u32, &str)> {
+        let parent = self.entries.get(&pid).map(|(pp, _)| *pp).filter(|&pp| pp != 0 && pp != pid)?;
+        Some((parent, self.name(parent).unwrap_or("")))
+    }
+
+    /// Direct children of `pid` with their exe names.
+    pub fn children(&self, pid: u32) -> impl Iterator<Item = (u32, &str)> {
+        self.entries
+            .iter()
+            .filter(move |(_, (parent, _))| *parent == pid)
+            .map(|(child, (_, name))| (*child, name.as_str()))
+    }
+}
+
+/// A visible top-level window with a title.
+pub struct Window {
+    pub hwnd: isize,
+    pub pid: u32,
+    pub title: String,
+}
+
+/// Visible, titled top-level windows, indexed by owning PID.
+#[derive(Default)]
+pub struct WindowList {
+    /// Z order, topmost first.
+    pub windows: Vec<Window>,
+    /// PID → its topmost window.
+    by_pid: HashMap<u32, isize>,
+}
+
+impl WindowList {
+    fn capture() -> Self {
+        use windows::Win32::UI::WindowsAndMessaging::*;
+
+        let mut list = Self::default();
+        unsafe {
+            let mut hwnd = match GetTopWindow(None) {
+                Ok(h) => h,
+                Err(_) => return list,
+            };
+            loop {
+                if IsWindowVisible(hwnd).as_bool() {
+                    let len = GetWindowTextLengthW(hwnd);
+                    if len > 0 {
+                        let mut pid: u32 = 0;
+                        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+                        let mut buf = vec![0u16; len as usize + 1];
+                        let copied = GetWindowTextW(hwnd, &mut buf).max(0) as usize;
+                        let title = String::from_utf16_lossy(&buf[..copied]);
+                        list.by_pid.entry(pid).or_insert(hwnd.0 as isize);
+                        list.windows.push(Window { hwnd: hwnd.0 as isize, pid, title });
+                    }
+                }
+                hwnd = match GetWindow(hwnd, GW_HWNDNEXT) {
+                    Ok(h) => h,
+                    Err(_) => break,
+                };
+            }
+        }
+        list
+    }
+
+    /// Topmost visible, titled window of `pid`.
+    pub fn for_pid(&self, pid: u32) -> Option<isize> {
+        self.by_pid.get(&pid).copied()
+    }
+}
+
+struct Cached<T> {
+    at: Instant,
+    value: Arc<T>,
+}
+
+static PROCESSES: Mutex<Option<Cached<ProcessTable>>> = Mutex::new(None);
+static WINDOWS: Mutex<Option<Cached<WindowList>>> = Mutex::new(None);
+
+/// The process table, at most `MAX_AGE` old.
+pub fn processes() -> Arc<ProcessTable> {
+    fresh(&PROCESSES, ProcessTable::capture)
+}
+
+/// The window list, at most `MAX_AGE` old.
+pub fn windows() -> Arc<WindowList> {
+    fresh(&WINDOWS, WindowList::capture)
+}
+
+/// Keep a table the scanner just built for the next `processes()`.
+pub fn store_processes(table: ProcessTable) {
+    *mutex_lock!(PROCESSES) = Some(Cached { at: Instant::now(), value: Arc::new(table) });
+}
+
+fn fresh<T>(slot: &Mutex<Option<Cached<T>>>, capture: fn() -> T) -> Arc<T> {
+    if let Some(c) = mutex_lock!(slot).as_ref() {
+        if c.at.elapsed() < MAX_AGE {
+            return c.value.clone();
+        }
+    }
+    // Captured without the lock held: a concurrent caller may capture too,
+    // and the later one wins
+    let value = Arc::new(capture());
+    *mutex_lock!(slot) = Some(Cached { at: Instant::now(), value: value.clone() });
+    value
+}
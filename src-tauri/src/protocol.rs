@@ -22,6 +22,41 @@ pub enum HookEvent {
     SessionStart,
     SessionEnd,
     PermissionRequest,
+    /// Derived server-side: a usage/rate limit notice (never sent by hooks).
+    LimitReached,
+    /// Derived server-side: the transcript shows a different model.
+    ModelChanged,
+    /// Reported by another local tool through `/api/external-event`.
+    External,
+    /// Derived server-side: a permission request granted by an
+    /// auto-approve window (audit trail).
+    AutoApproved,
+    /// Derived server-side: an agent process was replaced by a new one in
+    /// the same CWD (crash or relaunch).
+    Restarted,
+    /// Derived server-side: an agent has run for a while without a single
+    /// hook event (hooks missing or broken).
+    HooksSilent,
+    /// Derived server-side: the hook binary failed to deliver events
+    /// (read from its error spool).
+    HookError,
+    /// Derived server-side: the hook daemon stopped answering and was
+    /// respawned (or the respawn failed).
+    DaemonRestarted,
+    /// Derived server-side: a session or project crossed its budget
+    /// warning threshold or limit.
+    BudgetAlert,
+    /// Derived server-side: a permission request answered like the
+    /// identical one before it (audit trail).
+    PermissionRepeated,
+    /// Derived server-side: a permission request timed out unanswered
+    /// (audit trail).
+    PermissionExpired,
+    /// Derived server-side: a tool call matched a `sensitive` pattern.
+    SensitiveCommand,
+    /// Derived server-side: the desk's overall state (`status::PetState`)
+    /// changed; kept for `/api/stats/timeline`, left out of event lists.
+    StateChanged,
     #[serde(other)]
     Unknown,
 }
@@ -36,6 +71,19 @@ impl fmt::Display for HookEvent {
             Self::SessionStart => write!(f, "session_start"),
             Self::SessionEnd => write!(f, "session_end"),
             Self::PermissionRequest => write!(f, "permission_request"),
+            Self::LimitReached => write!(f, "limit_reached"),
+            Self::ModelChanged => write!(f, "model_changed"),
+            Self::External => write!(f, "external"),
+            Self::AutoApproved => write!(f, "auto_approved"),
+            Self::Restarted => write!(f, "restarted"),
+            Self::HooksSilent => write!(f, "hooks_silent"),
+            Self::HookError => write!(f, "hook_error"),
+            Self::DaemonRestarted => write!(f, "daemon_restarted"),
+            Self::BudgetAlert => write!(f, "budget_alert"),
+            Self::PermissionRepeated => write!(f, "permission_repeated"),
+            Self::PermissionExpired => write!(f, "permission_expired"),
+            Self::SensitiveCommand => write!(f, "sensitive_command"),
+            Self::StateChanged => write!(f, "state_changed"),
             Self::Unknown => write!(f, "unknown"),
         }
     }
@@ -63,6 +111,21 @@ impl Default for SessionStatus {
     }
 }
 
+impl SessionStatus {
+    /// Whether a status update may move a session from `self` to `next`.
+    /// An ended session only comes back through a fresh `register`
+    /// (SessionStart), so a late hook can't revive it; nothing goes back
+    /// to `Unknown`.
+    pub fn can_transition_to(&self, next: &SessionStatus) -> bool {
+        match (self, next) {
+            (a, b) if a == b => true,
+            (_, Self::Unknown) => false,
+            (Self::Ended, _) => false,
+            _ => true,
+        }
+    }
+}
+
 impl fmt::Display for SessionStatus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -76,6 +139,39 @@ impl fmt::Display for SessionStatus {
     }
 }
 
+/// Why a session is waiting for the user, so the island can show the
+/// right icon and action for it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitingReason {
+    /// The turn finished; the agent waits for the next prompt.
+    Finished,
+    /// A permission prompt is open in the terminal.
+    Permission,
+    /// Claude Code's idle prompt: no input for a while after finishing.
+    Idle,
+    /// The agent asks to approve its plan before leaving plan mode.
+    Plan,
+    /// Any other notification.
+    #[serde(other)]
+    Other,
+}
+
+impl WaitingReason {
+    /// Reason behind a Notification hook. `tool` is the tool the agent
+    /// started last, if known (observe mode).
+    pub fn from_notification(ntype: &str, message: &str, tool: Option<&str>) -> Self {
+        match ntype {
+            "permission_prompt" if tool == Some("ExitPlanMode") || message.to_ascii_lowercase().contains("plan mode") => {
+                Self::Plan
+            }
+            "permission_prompt" => Self::Permission,
+            "idle_prompt" => Self::Idle,
+            _ => Self::Other,
+        }
+    }
+}
+
 // ─── Permission Decision ─────────────────────────────────
 
 /// Permission decision sent by the UI.
@@ -88,16 +184,26 @@ pub enum PermissionDecisionKind {
     AlwaysAllow,
     /// Pass to terminal — PreToolUse returns "ask", PermissionRequest returns "deny".
     AskTerminal,
+    /// Plan review (ExitPlanMode): go ahead with the plan.
+    ApprovePlan,
+    /// Plan review: keep planning; the message says what to change.
+    RejectPlan,
 }
 
 impl PermissionDecisionKind {
     /// Map to Claude Code's hookSpecificOutput behavior string.
     pub fn to_behavior(&self) -> &'static str {
         match self {
-            Self::Allow | Self::AllowSession | Self::AlwaysAllow => "approve",
-            Self::Deny | Self::AskTerminal => "deny",
+            Self::Allow | Self::AllowSession | Self::AlwaysAllow | Self::ApprovePlan => "approve",
+            Self::Deny | Self::AskTerminal | Self::RejectPlan => "deny",
         }
     }
+
+    /// Whether the agent is refused and told why (`message`), as opposed
+    /// to allowed or handed to the terminal.
+    pub fn refuses(&self) -> bool {
+        matches!(self, Self::Deny | Self::RejectPlan)
+    }
 }
 
 // ─── Default helpers for serde ───────────────────────────
@@ -130,11 +236,31 @@ pub struct SignalPayload {
     pub model: String,
     #[serde(default)]
     pub hook_pid: Option<u32>,
+    /// Version of the hook binary that sent this (absent on old binaries).
+    #[serde(default)]
+    pub hook_version: Option<String>,
+    /// Microseconds since the epoch when the hook fired; orders events that
+    /// arrive out of order (absent on old binaries).
+    #[serde(default)]
+    pub hook_seq: Option<u64>,
     /// PID of the ancestor claude.exe process (set by hook binary).
     #[serde(default)]
     pub agent_pid: Option<u32>,
     #[serde(default)]
     pub parent_session_id: Option<String>,
+    /// SessionStart only: startup | resume | clear | compact.
+    #[serde(default)]
+    pub source: String,
+    #[serde(default)]
+    pub transcript_path: String,
+    /// Adapter that normalized it (`claude_code`, `codex`); empty for
+    /// signals the server makes itself.
+    #[serde(default)]
+    pub agent: String,
+    /// Names the hook invocation in logs, events, toasts and remote
+    /// messages (absent on old binaries; the server makes one then).
+    #[serde(default)]
+    pub correlation_id: String,
 }
 
 /// POST /api/hook body — lightweight status update.
@@ -147,6 +273,20 @@ pub struct HookPayload {
     /// PID of the ancestor claude.exe process (set by hook binary).
     #[serde(default)]
     pub agent_pid: Option<u32>,
+    #[serde(default)]
+    pub hook_pid: Option<u32>,
+    /// Version of the hook binary that sent this (absent on old binaries).
+    #[serde(default)]
+    pub hook_version: Option<String>,
+    /// Microseconds since the epoch when the hook fired; orders events that
+    /// arrive out of order (absent on old binaries).
+    #[serde(default)]
+    pub hook_seq: Option<u64>,
+    /// Tool about to run (`pre_tool` from the observe-mode PreToolUse hook).
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    #[serde(default)]
+    pub correlation_id: String,
 }
 
 /// POST /api/permission-request — tool permission from hook binary.
@@ -162,6 +302,8 @@ pub struct PermissionRequestPayload {
     pub tool_input: Value,
     #[serde(default = "default_json_array")]
     pub permission_suggestions: Value,
+    #[serde(default)]
+    pub correlation_id: String,
 }
 
 /// POST /api/permission-respond — user decision from UI.
@@ -169,6 +311,19 @@ pub struct PermissionRequestPayload {
 pub struct PermissionRespondPayload {
     pub id: String,
     pub decision: PermissionDecisionKind,
+    /// Indices into the request's `permission_suggestions` to persist as
+    /// `updatedPermissions`. Absent: all of them for `always_allow`.
+    #[serde(default)]
+    pub selected_suggestions: Option<Vec<usize>>,
+    /// File-edit tools only: files or directories (trailing `/`) the
+    /// approval covers, turned into precise `Edit(...)` rules instead of
+    /// approving the tool everywhere.
+    #[serde(default)]
+    pub allowed_paths: Option<Vec<String>>,
+    /// Explanation sent back to the agent with a deny, e.g. "use podman,
+    /// not docker", so it can change course instead of just stopping.
+    #[serde(default)]
+    pub message: Option<String>,
 }
 
 /// POST /api/pre-tool-check — PreToolUse hook blocks here for approval.
@@ -185,6 +340,8 @@ pub struct PreToolCheckPayload {
     /// The full hook payload (for passthrough fields).
     #[serde(default = "default_json_object")]
     pub raw: Value,
+    #[serde(default)]
+    pub correlation_id: String,
 }
 
 /// POST /api/chat/send — send a message to a Claude Code session via SendInput.
@@ -201,3 +358,61 @@ pub struct ChatSendPayload {
     #[serde(default)]
     pub force: bool,
 }
+
+// ─── Web Agents ──────────────────────────────────────────
+
+/// Lifecycle states a browser extension can report for a web agent task.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebAgentState {
+    /// Task created — registers the session.
+    Started,
+    /// Agent is producing output or running tools.
+    Working,
+    /// Agent asked the user something and is waiting.
+    NeedsInput,
+    /// Turn finished; `message` is the final reply.
+    Done,
+    /// Task failed; `message` explains why.
+    Error,
+    /// Tab or task closed.
+    Ended,
+}
+
+/// POST /api/web-agent — activity of a web-based agent reported by a
+/// companion browser extension (Claude.ai tasks, ChatGPT operator runs).
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebAgentPayload {
+    /// Site or product, e.g. `claude_ai` or `chatgpt`.
+    pub source: String,
+    /// Stable id of the task/conversation within `source`.
+    pub task_id: String,
+    pub state: WebAgentState,
+    /// Display name (conversation title); defaults to the task id.
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub model: String,
+}
+
+/// POST /api/external-event — a notification from another local tool
+/// (build script, test watcher) routed through the island/tray/remote
+/// pipeline.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExternalEventPayload {
+    /// Reporting tool, e.g. `cargo-watch` or `ci`.
+    pub source: String,
+    pub title: String,
+    #[serde(default)]
+    pub message: String,
+    /// 1 = log only, 2 = toast + sound, 3 = also remote channels.
+    #[serde(default = "default_external_level")]
+    pub level: u8,
+    /// Project (path or name) the event is about.
+    #[serde(default)]
+    pub project: String,
+}
+
+fn default_external_level() -> u8 { 2 }
@@ -0,0 +1,143 @@
+//! Flood protection for the hook endpoints.
+//!
+//! One token bucket per `session_id` (requests without one share a bucket),
+//! refilled at `manager.rate_limit_per_sec` up to `manager.rate_limit_burst`.
+//! An empty bucket answers 429; the first rejection of a burst is reported
+//! once as an `error` SSE message instead of on every request.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::server::AppState;
+
+/// Same cap axum's `Json` extractor applies.
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+    /// Rejected since the bucket last let a request through.
+    throttled: bool,
+}
+
+/// Outcome of `RateLimiter::check`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Allowed,
+    /// Rejected; `first` on the first rejection of a burst.
+    Throttled { first: bool },
+}
+
+pub struct RateLimiter {
+    per_sec: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    rejected: AtomicU64,
+}
+
+impl RateLimiter {
+    /// `per_sec` of 0 disables limiting.
+    pub fn new(per_sec: f64, burst: u32) -> Self {
+        Self {
+            per_sec,
+            burst: f64::from(burst.max(1)),
+            buckets: Mutex::new(HashMap::new()),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.per_sec > 0.0
+    }
+
+    /// Take a token from `key`'s bucket.
+    pub fn check(&self, key: &str) -> Verdict {
+        if !self.enabled() {
+            return Verdict::Allowed;
+        }
+        let now = Instant::now();
+        let mut buckets = mutex_lock!(self.buckets);
+        let b = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            last: now,
+            throttled: false,
+        });
+        b.tokens = (b.tokens + now.duration_since(b.last).as_secs_f64() * self.per_sec).min(self.burst);
+        b.last = now;
+        if b.tokens >= 1.0 {
+            b.tokens -= 1.0;
+            b.throttled = false;
+            return Verdict::Allowed;
+        }
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        Verdict::Throttled { first: !std::mem::replace(&mut b.throttled, true) }
+    }
+
+    /// Drop buckets that have been full (idle) for at least `idle`.
+    pub fn evict_idle(&self, idle: Duration) {
+        let now = Instant::now();
+        mutex_lock!(self.buckets).retain(|_, b| now.duration_since(b.last) < idle);
+    }
+
+    /// Counters for `/api/diagnostics`.
+    pub fn stats(&self) -> Value {
+        json!({
+            "enabled": self.enabled(),
+            "per_sec": self.per_sec,
+            "burst": self.burst,
+            "tracked_sessions": mutex_lock!(self.buckets).len(),
+            "rejected": self.rejected.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Middleware for the hook routes: peek at `session_id` in the JSON body
+/// and reject with 429 once that session's bucket is empty.
+pub async fn hook_rate_limit(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    if !state.rate_limiter.enabled() {
+        return next.run(req).await;
+    }
+    let (parts, body) = req.into_parts();
+    let bytes = match to_bytes(body, BODY_LIMIT).await {
+        Ok(b) => b,
+        Err(e) => {
+            return (StatusCode::PAYLOAD_TOO_LARGE, Json(json!({ "ok": false, "error": format!("{}", e) })))
+                .into_response();
+        }
+    };
+    let session_id = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|v| v.get("session_id").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_default();
+
+    if let Verdict::Throttled { first } = state.rate_limiter.check(&session_id) {
+        let path = parts.uri.path();
+        if first {
+            tracing::warn!("Rate limited session {:?} on {}", session_id, path);
+            state.sse.broadcast("error", json!({
+                "error": "rate_limited",
+                "session_id": &session_id,
+                "path": path,
+                "message": format!("Session {} is sending hook requests too fast; dropping them", session_id),
+            }));
+        }
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("retry-after", "1")],
+            Json(json!({ "ok": false, "error": "rate limited" })),
+        )
+            .into_response();
+    }
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
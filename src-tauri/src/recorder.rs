@@ -0,0 +1,188 @@
+//! Record-and-replay of incoming hook traffic.
+//!
+//! `POST /api/record/start` captures every request to the hook endpoints
+//! (signals, lightweight hooks, permission requests and pre-tool checks)
+//! to a JSONL file until `POST /api/record/stop`. Each line is a
+//! `simulator::Step`, with its delay since the previous request, so a
+//! recording replays like any scenario: `agent-desk --replay <file>`, or
+//! `agent-desk-sim <file>`.
+//!
+//! Payloads are sanitized on the way in: transcript paths are dropped,
+//! long text is clipped and secrets are redacted (see redact.rs), so a
+//! recording can be attached to a bug report.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::server::AppState;
+
+/// Same cap axum's `Json` extractor applies.
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+/// Longest string kept in a recorded payload.
+const MAX_TEXT: usize = 200;
+/// Longest string kept inside `tool_input` (commands, file contents).
+const MAX_TOOL_TEXT: usize = 80;
+
+struct Recording {
+    path: PathBuf,
+    file: std::fs::File,
+    last: Instant,
+    count: usize,
+}
+
+pub struct Recorder {
+    active: Mutex<Option<Recording>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self { active: Mutex::new(None) }
+    }
+
+    /// Start recording to `path`, or to a timestamped file in `dir`.
+    /// Replaces any recording in progress.
+    pub fn start(&self, path: Option<PathBuf>, dir: &Path) -> Result<PathBuf, String> {
+        let path = match path {
+            Some(p) => p,
+            None => dir.join(format!("rec-{}.jsonl", chrono::Local::now().format("%Y%m%d-%H%M%S"))),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let file = std::fs::File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        tracing::info!("Recording hook traffic to {}", path.display());
+        *mutex_lock!(self.active) = Some(Recording { path: path.clone(), file, last: Instant::now(), count: 0 });
+        Ok(path)
+    }
+
+    /// Stop recording; the file and how many requests it holds.
+    pub fn stop(&self) -> Option<(PathBuf, usize)> {
+        let rec = mutex_lock!(self.active).take()?;
+        tracing::info!("Recorded {} hook requests to {}", rec.count, rec.path.display());
+        Some((rec.path, rec.count))
+    }
+
+    pub fn is_active(&self) -> bool {
+        mutex_lock!(self.active).is_some()
+    }
+
+    fn record(&self, kind: &str, event: &str, mut body: Value) {
+        let mut guard = mutex_lock!(self.active);
+        let Some(rec) = guard.as_mut() else { return };
+        sanitize(&mut body);
+        let now = Instant::now();
+        let step = json!({
+            "delay_ms": if rec.count == 0 { 0 } else { now.duration_since(rec.last).as_millis() as u64 },
+            "kind": kind,
+            "event": event,
+            "body": body,
+        });
+        rec.last = now;
+        rec.count += 1;
+        if let Err(e) = writeln!(rec.file, "{}", step) {
+            tracing::warn!("Recording to {} failed, stopping: {}", rec.path.display(), e);
+            *guard = None;
+        }
+    }
+}
+
+/// Middleware on the hook routes: while recording, copy each request body
+/// into the recording before passing it on.
+pub async fn record_hooks(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    if !state.recorder.is_active() {
+        return next.run(req).await;
+    }
+    let (parts, body) = req.into_parts();
+    let bytes = match to_bytes(body, BODY_LIMIT).await {
+        Ok(b) => b,
+        // Let the handler's own extractor report it
+        Err(_) => return next.run(Request::from_parts(parts, Body::empty())).await,
+    };
+    if let Ok(mut body) = serde_json::from_slice::<Value>(&bytes) {
+        let query = parts.uri.query().unwrap_or("");
+        let param = |name: &str| {
+            query
+                .split('&')
+                .filter_map(|kv| kv.split_once('='))
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| urlencoding::decode(v).map(|s| s.into_owned()).unwrap_or_default())
+        };
+        let (kind, event) = match parts.uri.path() {
+            "/api/signal" => {
+                if let (Some(agent), Some(obj)) = (param("agent"), body.as_object_mut()) {
+                    obj.entry("agent").or_insert(json!(agent));
+                }
+                let event = body.get("event").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                ("signal", event)
+            }
+            "/api/hook" => ("hook", param("event").unwrap_or_default()),
+            "/api/permission-request" => ("permission", String::new()),
+            "/api/pre-tool-check" => ("pre_tool_check", String::new()),
+            _ => ("", String::new()),
+        };
+        if !kind.is_empty() {
+            state.recorder.record(kind, &event, body);
+        }
+    }
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+/// Drop what a recording has no business carrying: transcript paths, and
+/// anything past the first few dozen characters of free text.
+fn sanitize(body: &mut Value) {
+    let Some(obj) = body.as_object_mut() else { return };
+    obj.remove("transcript_path");
+    for (key, value) in obj.iter_mut() {
+        // Before clipping, so a secret cut in half is still found
+        crate::redact::json(value);
+        clip(value, if key == "tool_input" { MAX_TOOL_TEXT } else { MAX_TEXT });
+    }
+}
+
+fn clip(value: &mut Value, max: usize) {
+    match value {
+        Value::String(s) if s.chars().count() > max => {
+            let kept: String = s.chars().take(max).collect();
+            *s = format!("{}\u{2026}", kept);
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| clip(v, max)),
+        Value::Object(map) => map.values_mut().for_each(|v| clip(v, max)),
+        _ => {}
+    }
+}
+
+/// `--replay <file>`: play a recording into the server on `port` at
+/// `speed`× in the background.
+#[cfg(any(feature = "simulator", debug_assertions))]
+pub fn replay(path: PathBuf, port: u16, speed: f64) {
+    std::thread::spawn(move || {
+        let scenario = match crate::simulator::load_file(&path) {
+            Ok(s) => s.prepared(),
+            Err(e) => {
+                tracing::error!("Replay: {}", e);
+                return;
+            }
+        };
+        let rt = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(rt) => rt,
+            Err(e) => {
+                tracing::error!("Replay: {}", e);
+                return;
+            }
+        };
+        let base = format!("http://{}", crate::setup::local_addr(port));
+        match rt.block_on(crate::simulator::run(&reqwest::Client::new(), &base, &scenario, speed)) {
+            Ok(n) => tracing::info!("Replayed {} requests from {}", n, path.display()),
+            Err(e) => tracing::error!("Replay of {} stopped: {}", path.display(), e),
+        }
+    });
+}
@@ -0,0 +1,113 @@
+//! Redaction of secrets in text that leaves this machine.
+//!
+//! Agents print API keys, tokens and addresses along with their work, and
+//! that text ends up in remote notifications, recordings attached to bug
+//! reports, team reports and the status page. With `redact.enabled`, every
+//! match of the built-in patterns (API keys and tokens, private keys,
+//! emails) and of `redact.patterns` is replaced with `redact.replacement`
+//! on the way out. A pattern with a group named `secret` only has that
+//! group replaced, so `token: abc…` keeps its key.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::config::RedactConfig;
+
+const BUILTIN: &[&str] = &[
+    // Anthropic / OpenAI style keys
+    r"\bsk-(?:ant-)?[A-Za-z0-9_-]{20,}",
+    // GitHub tokens
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    r"\bgithub_pat_[A-Za-z0-9_]{22,}",
+    // AWS access key ids
+    r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b",
+    // Slack tokens
+    r"\bxox[abprs]-[A-Za-z0-9-]{10,}",
+    // Google API keys
+    r"\bAIza[0-9A-Za-z_-]{35}\b",
+    r"(?i)\bbearer\s+(?P<secret>[A-Za-z0-9._~+/=-]{20,})",
+    r#"(?i)\b(?:api[_-]?key|secret|token|password|passwd)\b["']?\s*[:=]\s*["']?(?P<secret>[^\s"',;]{8,})"#,
+    r"(?s)-----BEGIN [A-Z ]*PRIVATE KEY-----.*?(?:-----END [A-Z ]*PRIVATE KEY-----|$)",
+    r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b",
+];
+
+pub struct Redactor {
+    patterns: Vec<Regex>,
+    replacement: String,
+}
+
+static REDACTOR: OnceLock<Redactor> = OnceLock::new();
+
+/// Compile the patterns from `cfg`; invalid ones are logged and skipped.
+/// Call once at startup, before anything is sent.
+pub fn init(cfg: &RedactConfig) {
+    let mut sources: Vec<&str> = Vec::new();
+    if cfg.enabled {
+        if cfg.builtin {
+            sources.extend(BUILTIN.iter().copied());
+        }
+        sources.extend(cfg.patterns.iter().map(String::as_str));
+    }
+    let patterns = sources
+        .into_iter()
+        .filter_map(|p| match Regex::new(p) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                tracing::warn!("redact.patterns: invalid pattern {:?} skipped: {}", p, e);
+                None
+            }
+        })
+        .collect();
+    let _ = REDACTOR.set(Redactor { patterns, replacement: cfg.replacement.clone() });
+}
+
+/// `s` with every secret replaced.
+pub fn text(s: &str) -> Cow<'_, str> {
+    match REDACTOR.get() {
+        Some(r) if !r.patterns.is_empty() => r.apply(s),
+        _ => Cow::Borrowed(s),
+    }
+}
+
+/// Redact every string in `value`.
+pub fn json(value: &mut Value) {
+    match value {
+        Value::String(s) => {
+            if let Cow::Owned(redacted) = text(s) {
+                *s = redacted;
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(json),
+        Value::Object(map) => map.values_mut().for_each(json),
+        _ => {}
+    }
+}
+
+impl Redactor {
+    fn apply<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let mut out = Cow::Borrowed(s);
+        for re in &self.patterns {
+            if !re.is_match(&out) {
+                continue;
+            }
+            let replaced = re
+                .replace_all(&out, |caps: &Captures| {
+                    let whole = caps.get(0).map_or("", |m| m.as_str());
+                    let Some(secret) = caps.name("secret") else { return self.replacement.clone() };
+                    let start = caps.get(0).map_or(0, |m| m.start());
+                    format!(
+                        "{}{}{}",
+                        &whole[..secret.start() - start],
+                        self.replacement,
+                        &whole[secret.end() - start..],
+                    )
+                })
+                .into_owned();
+            out = Cow::Owned(replaced);
+        }
+        out
+    }
+}
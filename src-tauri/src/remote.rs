@@ -1,14 +1,298 @@
-//! Remote notification channels — Telegram, DingTalk, WeChat push.
+//! Remote notification channels — Telegram, DingTalk, WeChat, Pushover and
+//! Bark push.
 
 use base64::Engine as _;
-use crate::config::{DingTalkConfig, TelegramConfig, WeChatConfig};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::config::{BarkConfig, Config, DingTalkConfig, NetworkConfig, PushoverConfig, TelegramConfig, WeChatConfig};
 
-/// Send a message to Telegram bot.
-pub async fn send_telegram(config: &TelegramConfig, client: &reqwest::Client, message: &str) {
-    if !config.enabled || config.bot_token.is_empty() || config.chat_id.is_empty() {
-        return;
+// ─── HTTP clients ────────────────────────────────────────
+
+/// Outbound HTTP clients built from `network` (proxy, extra root CA,
+/// timeouts): a general one that verifies TLS, and for the remote channels
+/// one that verifies and one for channels with `skip_tls_verify`. Each keeps
+/// its connections open between sends.
+#[derive(Clone)]
+pub struct HttpClients {
+    /// No overall timeout: the simulator long-polls through it.
+    pub verified: reqwest::Client,
+    remote: reqwest::Client,
+    insecure: reqwest::Client,
+    retries: u32,
+    /// Turns to send, `network.remote_max_concurrent` of them.
+    sending: Arc<tokio::sync::Semaphore>,
+    /// Dispatches waiting or sending.
+    queued: Arc<AtomicUsize>,
+    max_queued: usize,
+}
+
+impl HttpClients {
+    /// Falls back to a plain client (and logs why) if the settings are unusable,
+    /// so a bad proxy URL or CA file doesn't keep the app from starting.
+    pub fn new(net: &NetworkConfig) -> Self {
+        let build = |verify, timeout| {
+            build_client(net, verify, timeout).unwrap_or_else(|e| {
+                tracing::warn!("network settings ignored: {}", e);
+                reqwest::Client::new()
+            })
+        };
+        let remote_timeout = Some(Duration::from_secs(net.remote_timeout_secs.max(1)));
+        Self {
+            verified: build(true, None),
+            remote: build(true, remote_timeout),
+            insecure: build(false, remote_timeout),
+            retries: net.remote_retries,
+            sending: Arc::new(tokio::sync::Semaphore::new(net.remote_max_concurrent.max(1))),
+            queued: Arc::new(AtomicUsize::new(0)),
+            max_queued: net.remote_max_queued.max(1),
+        }
+    }
+
+    /// Client for a remote channel.
+    pub fn pick(&self, skip_tls_verify: bool) -> &reqwest::Client {
+        if skip_tls_verify { &self.insecure } else { &self.remote }
+    }
+}
+
+fn build_client(net: &NetworkConfig, verify_tls: bool, timeout: Option<Duration>) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(net.connect_timeout_secs.max(1)))
+        .pool_idle_timeout(Duration::from_secs(90));
+    if let Some(t) = timeout {
+        builder = builder.timeout(t);
+    }
+    if !net.proxy.is_empty() {
+        let proxy = reqwest::Proxy::all(&net.proxy).map_err(|e| format!("network.proxy {}: {}", net.proxy, e))?;
+        builder = builder.proxy(proxy);
+    }
+    if !net.ca_cert.is_empty() {
+        let pem = std::fs::read(&net.ca_cert).map_err(|e| format!("network.ca_cert {}: {}", net.ca_cert, e))?;
+        let cert = reqwest::Certificate::from_pem(&pem).map_err(|e| format!("network.ca_cert {}: {}", net.ca_cert, e))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    if !verify_tls {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder.build().map_err(|e| error_chain(&e))
+}
+
+/// An error with its causes, e.g. "error sending request: … : certificate
+/// verify failed" — the top level alone hides proxy and TLS problems.
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut msg = e.to_string();
+    let mut source = e.source();
+    while let Some(s) = source {
+        msg.push_str(": ");
+        msg.push_str(&s.to_string());
+        source = s.source();
+    }
+    msg
+}
+
+/// Turn a channel API response into a detailed error: transport failure,
+/// non-2xx status, or `api_error` finding a failure in the JSON body.
+async fn check_response(
+    res: Result<reqwest::Response, reqwest::Error>,
+    api_error: impl Fn(&Value) -> Option<String>,
+) -> Result<(), String> {
+    let resp = res.map_err(|e| {
+        if e.is_connect() {
+            format!("{}{}", CONNECT_FAILED, error_chain(&e))
+        } else {
+            error_chain(&e)
+        }
+    })?;
+    let status = resp.status();
+    let body = resp.text().await.map_err(|e| error_chain(&e))?;
+    if !status.is_success() {
+        return Err(status_error(status, &body));
+    }
+    match serde_json::from_str::<Value>(&body).ok().as_ref().and_then(api_error) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Start of the error for a request that never reached the server (refused,
+/// DNS, TLS handshake), which is safe to send again.
+const CONNECT_FAILED: &str = "connection failed: ";
+
+/// The error for a non-2xx reply: `HTTP <status>: <start of body>`.
+fn status_error(status: reqwest::StatusCode, body: &str) -> String {
+    format!("HTTP {}: {}", status, body.chars().take(300).collect::<String>())
+}
+
+/// Whether a failed send is worth retrying: the connection failed, or the
+/// server answered 429 or 5xx. Other statuses (bad token, bad config) fail
+/// the same way every time, and a timeout may have delivered the message
+/// already.
+fn retryable(err: &str) -> bool {
+    if err.starts_with(CONNECT_FAILED) {
+        return true;
+    }
+    err.strip_prefix("HTTP ")
+        .and_then(|rest| rest.get(..3))
+        .and_then(|code| code.parse::<u16>().ok())
+        .is_some_and(|code| code == 429 || (500..600).contains(&code))
+}
+
+/// Hide a credential that appears in a request URL.
+fn redact(msg: String, secret: &str) -> String {
+    if secret.is_empty() { msg } else { msg.replace(secret, "<redacted>") }
+}
+
+// ─── Messages ────────────────────────────────────────────
+
+/// A notification for the remote channels. Plain-text channels send `text`
+/// as is; DingTalk formats it per event (`dingtalk.msgtypes`).
+#[derive(Debug, Clone, Default)]
+pub struct RemoteMessage {
+    /// `stop`, `notification`, `limit_reached`, `permission_request`,
+    /// `away`, `external` or `test`.
+    pub event: String,
+    /// First line is the headline.
+    pub text: String,
+    /// Action card buttons: (label, URL).
+    pub buttons: Vec<(String, String)>,
+    /// Where the message leads, e.g. the session it is about (see
+    /// `deep_link::session_link`); empty = nowhere.
+    pub link: String,
+    /// Hook invocation it is about, for the logs; never sent.
+    pub correlation_id: String,
+    /// `text` in other languages, for channels with their own `language`:
+    /// (language, text). Channels whose language is missing get `text`.
+    pub translations: Vec<(String, String)>,
+}
+
+impl RemoteMessage {
+    pub fn new(event: &str, text: impl Into<String>) -> Self {
+        Self { event: event.to_string(), text: text.into(), ..Default::default() }
     }
 
+    /// `text` with the link on its own last line, for plain-text channels.
+    pub fn full_text(&self) -> String {
+        if self.link.is_empty() {
+            self.text.clone()
+        } else {
+            format!("{}\n{}", self.text, self.link)
+        }
+    }
+
+    /// This message as a channel set to `language` gets it.
+    fn in_language(&self, language: &str) -> Cow<'_, RemoteMessage> {
+        match self.translations.iter().find(|(lang, _)| !language.is_empty() && lang == language) {
+            Some((_, text)) => Cow::Owned(RemoteMessage { text: text.clone(), ..self.clone() }),
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    /// URL a tap on the notification opens: the first button, else the link.
+    fn tap_url(&self) -> Option<(&str, &str)> {
+        self.buttons
+            .first()
+            .map(|(label, url)| (label.as_str(), url.as_str()))
+            .or_else(|| (!self.link.is_empty()).then_some(("Open session", self.link.as_str())))
+    }
+}
+
+/// DingTalk request body for `msg` in the configured message type. An
+/// action card without buttons is sent as markdown.
+fn dingtalk_body(config: &DingTalkConfig, msg: &RemoteMessage) -> Value {
+    let (title, rest) = msg.text.split_once('\n').unwrap_or((&msg.text, ""));
+    // DingTalk markdown only breaks lines on two trailing spaces
+    let markdown = |with_link: bool| {
+        let body: Vec<&str> = rest.lines().collect();
+        let mut text = format!("#### {}\n\n{}", title, body.join("  \n"));
+        if with_link && !msg.link.is_empty() {
+            text.push_str(&format!("\n\n[Open session]({})", msg.link));
+        }
+        text
+    };
+    let mut buttons = msg.buttons.clone();
+    if !msg.link.is_empty() {
+        buttons.push(("Open session".to_string(), msg.link.clone()));
+    }
+    match config.msgtype(&msg.event) {
+        "actionCard" if !buttons.is_empty() => serde_json::json!({
+            "msgtype": "actionCard",
+            "actionCard": {
+                "title": title,
+                "text": markdown(false),
+                "btnOrientation": "1",
+                "btns": buttons.iter()
+                    .map(|(label, url)| serde_json::json!({ "title": label, "actionURL": url }))
+                    .collect::<Vec<_>>(),
+            }
+        }),
+        "markdown" | "actionCard" => serde_json::json!({
+            "msgtype": "markdown",
+            "markdown": { "title": title, "text": markdown(true) }
+        }),
+        _ => serde_json::json!({
+            "msgtype": "text",
+            "text": { "content": msg.full_text() }
+        }),
+    }
+}
+
+/// WeCom group robot body. Markdown shows the headline in bold; content
+/// is capped at the API's 4096-byte limit.
+fn wecom_body(message: &str, markdown: bool) -> Value {
+    const MAX_BYTES: usize = 4096;
+    let content = if markdown {
+        match message.split_once('\n') {
+            Some((title, rest)) => format!("**{}**\n{}", title, rest),
+            None => format!("**{}**", message),
+        }
+    } else {
+        message.to_string()
+    };
+    let mut end = content.len().min(MAX_BYTES);
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    let content = &content[..end];
+    if markdown {
+        serde_json::json!({ "msgtype": "markdown", "markdown": { "content": content } })
+    } else {
+        serde_json::json!({ "msgtype": "text", "text": { "content": content } })
+    }
+}
+
+// ─── Channels ────────────────────────────────────────────
+
+pub fn telegram_ready(config: &TelegramConfig) -> bool {
+    config.enabled && !config.bot_token.is_empty() && !config.chat_id.is_empty()
+}
+
+pub fn dingtalk_ready(config: &DingTalkConfig) -> bool {
+    config.enabled && !config.access_token.is_empty()
+}
+
+pub fn wechat_ready(config: &WeChatConfig) -> bool {
+    config.enabled
+        && match config.provider.as_str() {
+            "" | "pushplus" => !config.pushplus_token.is_empty(),
+            "serverchan" => !config.serverchan_sendkey.is_empty(),
+            "wecom" => !config.wecom_key.is_empty(),
+            _ => false,
+        }
+}
+
+pub fn pushover_ready(config: &PushoverConfig) -> bool {
+    config.enabled && !config.token.is_empty() && !config.user.is_empty()
+}
+
+pub fn bark_ready(config: &BarkConfig) -> bool {
+    config.enabled && !config.device_key.is_empty()
+}
+
+/// Send a message to Telegram bot.
+pub async fn send_telegram(config: &TelegramConfig, client: &reqwest::Client, message: &str) -> Result<(), String> {
     let url = format!(
         "https://api.telegram.org/bot{}/sendMessage",
         config.bot_token
@@ -19,21 +303,20 @@ pub async fn send_telegram(config: &TelegramConfig, client: &reqwest::Client, me
             "chat_id": config.chat_id,
             "text": message,
         }))
-        .timeout(std::time::Duration::from_secs(10))
         .send()
         .await;
 
-    if let Err(e) = res {
-        tracing::warn!("Telegram send error: {}", e);
-    }
+    check_response(res, |v| {
+        (v.get("ok") == Some(&Value::Bool(false)))
+            .then(|| v.get("description").and_then(|d| d.as_str()).unwrap_or("not ok").to_string())
+    })
+    .await
+    // The URL carries the bot token; keep it out of logs and API replies
+    .map_err(|e| redact(e, &config.bot_token))
 }
 
 /// Send a message to DingTalk webhook.
-pub async fn send_dingtalk(config: &DingTalkConfig, client: &reqwest::Client, message: &str) {
-    if !config.enabled || config.access_token.is_empty() {
-        return;
-    }
-
+pub async fn send_dingtalk(config: &DingTalkConfig, client: &reqwest::Client, message: &RemoteMessage) -> Result<(), String> {
     let webhook = if config.webhook_url.is_empty() {
         "https://oapi.dingtalk.com/robot/send"
     } else {
@@ -63,84 +346,316 @@ pub async fn send_dingtalk(config: &DingTalkConfig, client: &reqwest::Client, me
 
     let res = client
         .post(&url)
-        .json(&serde_json::json!({
-            "msgtype": "text",
-            "text": { "content": message }
-        }))
-        .timeout(std::time::Duration::from_secs(10))
+        .json(&dingtalk_body(config, message))
         .send()
         .await;
 
-    if let Err(e) = res {
-        tracing::warn!("DingTalk send error: {}", e);
-    }
+    check_response(res, |v| {
+        let code = v.get("errcode").and_then(|c| c.as_i64()).unwrap_or(0);
+        (code != 0).then(|| format!("errcode {}: {}", code, v.get("errmsg").and_then(|m| m.as_str()).unwrap_or("")))
+    })
+    .await
+    .map_err(|e| redact(e, &config.access_token))
 }
 
-/// Send a message to WeChat (PushPlus or ServerChan).
-pub async fn send_wechat(config: &WeChatConfig, client: &reqwest::Client, message: &str) {
-    if !config.enabled {
-        return;
-    }
-
+/// Send a message to WeChat (PushPlus, ServerChan or a WeCom group robot).
+pub async fn send_wechat(config: &WeChatConfig, client: &reqwest::Client, message: &str) -> Result<(), String> {
     let provider = if config.provider.is_empty() {
         "pushplus"
     } else {
         &config.provider
     };
 
-    let res = match provider {
+    match provider {
         "pushplus" => {
-            if config.pushplus_token.is_empty() {
-                return;
-            }
-            client
+            let res = client
                 .post("https://www.pushplus.plus/send")
                 .json(&serde_json::json!({
                     "token": config.pushplus_token,
                     "title": "Agent Desk",
                     "content": message,
                 }))
-                .timeout(std::time::Duration::from_secs(10))
                 .send()
-                .await
+                .await;
+            check_response(res, |v| {
+                let code = v.get("code").and_then(|c| c.as_i64()).unwrap_or(200);
+                (code != 200).then(|| format!("code {}: {}", code, v.get("msg").and_then(|m| m.as_str()).unwrap_or("")))
+            })
+            .await
         }
         "serverchan" => {
-            if config.serverchan_sendkey.is_empty() {
-                return;
-            }
             let url = format!(
                 "https://sctapi.ftqq.com/{}.send",
                 config.serverchan_sendkey
             );
-            client
+            let res = client
                 .post(&url)
                 .json(&serde_json::json!({
                     "title": "Agent Desk",
                     "desp": message,
                 }))
-                .timeout(std::time::Duration::from_secs(10))
                 .send()
-                .await
+                .await;
+            check_response(res, |v| {
+                let code = v.get("code").and_then(|c| c.as_i64()).unwrap_or(0);
+                (code != 0).then(|| format!("code {}: {}", code, v.get("message").and_then(|m| m.as_str()).unwrap_or("")))
+            })
+            .await
+            .map_err(|e| redact(e, &config.serverchan_sendkey))
         }
-        _ => return,
-    };
+        "wecom" => {
+            let url = format!(
+                "https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key={}",
+                config.wecom_key
+            );
+            let res = client
+                .post(&url)
+                .json(&wecom_body(message, !config.wecom_text))
+                .send()
+                .await;
+            check_response(res, |v| {
+                let code = v.get("errcode").and_then(|c| c.as_i64()).unwrap_or(0);
+                (code != 0).then(|| format!("errcode {}: {}", code, v.get("errmsg").and_then(|m| m.as_str()).unwrap_or("")))
+            })
+            .await
+            .map_err(|e| redact(e, &config.wecom_key))
+        }
+        other => Err(format!("unknown provider: {}", other)),
+    }
+}
+
+/// Send a message to Pushover, with the event's priority. Emergency (2)
+/// repeats every minute for up to an hour until acknowledged.
+pub async fn send_pushover(config: &PushoverConfig, client: &reqwest::Client, message: &RemoteMessage) -> Result<(), String> {
+    let (title, body) = message.text.split_once('\n').unwrap_or(("Agent Desk", &message.text));
+    let priority = config.priority(&message.event);
+    let mut payload = serde_json::json!({
+        "token": config.token,
+        "user": config.user,
+        "title": title,
+        "message": if body.trim().is_empty() { title } else { body },
+        "priority": priority,
+    });
+    if priority == 2 {
+        payload["retry"] = serde_json::json!(60);
+        payload["expire"] = serde_json::json!(3600);
+    }
+    if let Some((label, url)) = message.tap_url() {
+        payload["url"] = serde_json::json!(url);
+        payload["url_title"] = serde_json::json!(label);
+    }
+    let res = client
+        .post("https://api.pushover.net/1/messages.json")
+        .json(&payload)
+        .send()
+        .await;
+    check_response(res, |v| {
+        (v.get("status").and_then(|s| s.as_i64()) != Some(1)).then(|| {
+            v.get("errors")
+                .and_then(|e| e.as_array())
+                .map(|e| e.iter().filter_map(|m| m.as_str()).collect::<Vec<_>>().join("; "))
+                .unwrap_or_else(|| "status 0".to_string())
+        })
+    })
+    .await
+}
 
-    if let Err(e) = res {
-        tracing::warn!("WeChat ({}) send error: {}", provider, e);
+/// Send a message to a Bark server (iOS), at the event's interruption level.
+pub async fn send_bark(config: &BarkConfig, client: &reqwest::Client, message: &RemoteMessage) -> Result<(), String> {
+    let server = if config.server_url.is_empty() {
+        "https://api.day.app"
+    } else {
+        config.server_url.trim_end_matches('/')
+    };
+    let (title, body) = message.text.split_once('\n').unwrap_or(("Agent Desk", &message.text));
+    let level = config.level(&message.event);
+    let mut payload = serde_json::json!({
+        "device_key": config.device_key,
+        "title": title,
+        "body": if body.trim().is_empty() { title } else { body },
+        "group": "Agent Desk",
+        "level": level,
+    });
+    if level == "critical" {
+        payload["volume"] = serde_json::json!(5);
     }
+    if let Some((_, url)) = message.tap_url() {
+        payload["url"] = serde_json::json!(url);
+    }
+    let res = client
+        .post(format!("{}/push", server))
+        .json(&payload)
+        .send()
+        .await;
+    check_response(res, |v| {
+        let code = v.get("code").and_then(|c| c.as_i64()).unwrap_or(200);
+        (code != 200).then(|| format!("code {}: {}", code, v.get("message").and_then(|m| m.as_str()).unwrap_or("")))
+    })
+    .await
+    .map_err(|e| redact(e, &config.device_key))
 }
 
 /// Dispatch message to all enabled remote channels concurrently.
-pub async fn dispatch_remote(
-    telegram: &TelegramConfig,
-    dingtalk: &DingTalkConfig,
-    wechat: &WeChatConfig,
-    client: &reqwest::Client,
-    message: &str,
-) {
+///
+/// At most `network.remote_max_concurrent` dispatches send at once; past
+/// `remote_max_queued` waiting or sending, new messages are dropped (and
+/// logged) so a hanging webhook can't pile up tasks.
+pub async fn dispatch_remote(config: &Config, clients: &HttpClients, message: &RemoteMessage) {
+    if clients.queued.fetch_add(1, Ordering::SeqCst) >= clients.max_queued {
+        clients.queued.fetch_sub(1, Ordering::SeqCst);
+        tracing::warn!("Remote {} dropped: {} dispatches already queued", message.event, clients.max_queued);
+        return;
+    }
+    // Agent output can carry secrets it happened to print
+    let message = &RemoteMessage {
+        text: crate::redact::text(&message.text).into_owned(),
+        translations: message
+            .translations
+            .iter()
+            .map(|(lang, text)| (lang.clone(), crate::redact::text(text).into_owned()))
+            .collect(),
+        ..message.clone()
+    };
+    // The semaphore is never closed
+    let results = match clients.sending.acquire().await {
+        Ok(_turn) => Some(send_all(config, clients, message).await),
+        Err(_) => None,
+    };
+    clients.queued.fetch_sub(1, Ordering::SeqCst);
+    let Some((tg, dt, wx, po, bk)) = results else { return };
+    let wechat = &config.wechat;
+    let cid = if message.correlation_id.is_empty() { String::new() } else { format!("[{}] ", message.correlation_id) };
+    tracing::debug!("{}Remote {} dispatched", cid, message.event);
+    if let Err(e) = tg {
+        tracing::warn!("{}Telegram send error: {}", cid, e);
+    }
+    if let Err(e) = dt {
+        tracing::warn!("{}DingTalk send error: {}", cid, e);
+    }
+    if let Err(e) = wx {
+        tracing::warn!("{}WeChat ({}) send error: {}", cid, wechat.provider, e);
+    }
+    if let Err(e) = po {
+        tracing::warn!("{}Pushover send error: {}", cid, e);
+    }
+    if let Err(e) = bk {
+        tracing::warn!("{}Bark send error: {}", cid, e);
+    }
+}
+
+type SendResult = Result<(), String>;
+
+/// Languages set on ready channels that differ from `messages.language`:
+/// the translations an event message needs.
+pub fn languages(config: &Config) -> Vec<&str> {
+    let set = [
+        (telegram_ready(&config.telegram), config.telegram.language.as_str()),
+        (dingtalk_ready(&config.dingtalk), config.dingtalk.language.as_str()),
+        (wechat_ready(&config.wechat), config.wechat.language.as_str()),
+        (pushover_ready(&config.pushover), config.pushover.language.as_str()),
+        (bark_ready(&config.bark), config.bark.language.as_str()),
+    ];
+    let mut out: Vec<&str> = Vec::new();
+    for (ready, lang) in set {
+        if ready && !lang.is_empty() && !lang.eq_ignore_ascii_case(&config.messages.language) && !out.contains(&lang) {
+            out.push(lang);
+        }
+    }
+    out
+}
+
+/// Every ready channel at once, each retried per `network.remote_retries`.
+async fn send_all(
+    config: &Config,
+    clients: &HttpClients,
+    message: &RemoteMessage,
+) -> (SendResult, SendResult, SendResult, SendResult, SendResult) {
+    let (telegram, dingtalk, wechat) = (&config.telegram, &config.dingtalk, &config.wechat);
+    let (pushover, bark) = (&config.pushover, &config.bark);
+    let retries = clients.retries;
     tokio::join!(
-        send_telegram(telegram, client, message),
-        send_dingtalk(dingtalk, client, message),
-        send_wechat(wechat, client, message),
-    );
+        async {
+            if telegram_ready(telegram) {
+                let text = message.in_language(&telegram.language).full_text();
+                with_retries(retries, || send_telegram(telegram, clients.pick(telegram.skip_tls_verify), &text)).await
+            } else {
+                Ok(())
+            }
+        },
+        async {
+            if dingtalk_ready(dingtalk) {
+                let message = message.in_language(&dingtalk.language);
+                with_retries(retries, || send_dingtalk(dingtalk, clients.pick(dingtalk.skip_tls_verify), &message)).await
+            } else {
+                Ok(())
+            }
+        },
+        async {
+            if wechat_ready(wechat) {
+                let text = message.in_language(&wechat.language).full_text();
+                with_retries(retries, || send_wechat(wechat, clients.pick(wechat.skip_tls_verify), &text)).await
+            } else {
+                Ok(())
+            }
+        },
+        async {
+            if pushover_ready(pushover) {
+                let message = message.in_language(&pushover.language);
+                with_retries(retries, || send_pushover(pushover, clients.pick(pushover.skip_tls_verify), &message)).await
+            } else {
+                Ok(())
+            }
+        },
+        async {
+            if bark_ready(bark) {
+                let message = message.in_language(&bark.language);
+                with_retries(retries, || send_bark(bark, clients.pick(bark.skip_tls_verify), &message)).await
+            } else {
+                Ok(())
+            }
+        },
+    )
+}
+
+/// `send` tried up to `retries` more times after a `retryable` failure, 2s,
+/// 4s, … apart. The last error is returned.
+async fn with_retries<F, Fut>(retries: u32, mut send: F) -> SendResult
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = SendResult>,
+{
+    let mut attempt = 0;
+    loop {
+        match send().await {
+            Err(e) if attempt < retries && retryable(&e) => {
+                attempt += 1;
+                tracing::debug!("Remote send failed ({}), retry {}/{}", e, attempt, retries);
+                tokio::time::sleep(Duration::from_secs(2 << (attempt - 1).min(4))).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn http(code: u16) -> String {
+        status_error(StatusCode::from_u16(code).unwrap(), "body")
+    }
+
+    #[test]
+    fn retries_only_connection_errors_429_and_5xx() {
+        for code in [429, 500, 502, 503, 504] {
+            assert!(retryable(&http(code)), "{} should be retried", code);
+        }
+        for code in [400, 401, 403, 404, 413] {
+            assert!(!retryable(&http(code)), "{} should not be retried", code);
+        }
+        assert!(retryable(&format!("{}tcp connect error", CONNECT_FAILED)));
+        assert!(!retryable("error sending request: operation timed out"));
+        assert!(!retryable("errcode 310000: sign not match"));
+    }
 }
@@ -0,0 +1,137 @@
+//! Sensitive tool calls, independent of the agent's own permissions.
+//!
+//! Each tool call is reduced to one line of text — the command for Bash,
+//! else the tool name and its input as JSON — and matched against the
+//! `sensitive.alert` and `sensitive.confirm` patterns. A match raises a
+//! `sensitive_command` event (event log, toast, remote channels).
+//!
+//! Calls are seen in two places. The pre-tool-check hook sees them before
+//! they run: a `confirm` match is always put to the user, even when a
+//! session rule or an auto-approve window would allow it. The transcripts
+//! of recently active sessions show every call, including those the agent
+//! ran without asking; those can only be reported after the fact.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::chat::{ChatEvent, EnrichedMessage};
+use crate::config::SensitiveConfig;
+
+/// How long a reported call is remembered, so the transcript scan does not
+/// report what the hook already did.
+const REPORTED_KEEP_SECS: f64 = 600.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Alert,
+    Confirm,
+}
+
+/// A tool call that matched.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub level: Level,
+    /// The pattern it matched.
+    pub pattern: String,
+    /// The call as matched.
+    pub text: String,
+}
+
+pub struct Sensitive {
+    alert: Vec<Regex>,
+    confirm: Vec<Regex>,
+    /// Per session: transcript index to scan from.
+    scanned: Mutex<HashMap<String, usize>>,
+    /// "session\ncall" → when it was reported.
+    reported: Mutex<HashMap<String, f64>>,
+}
+
+impl Sensitive {
+    /// Compile the patterns; invalid ones are logged and skipped.
+    pub fn new(cfg: &SensitiveConfig) -> Self {
+        let compile = |list: &[String], key: &str| -> Vec<Regex> {
+            list.iter()
+                .filter_map(|p| match Regex::new(p) {
+                    Ok(re) => Some(re),
+                    Err(e) => {
+                        tracing::warn!("sensitive.{}: invalid pattern {:?} skipped: {}", key, p, e);
+                        None
+                    }
+                })
+                .collect()
+        };
+        Self {
+            alert: compile(&cfg.alert, "alert"),
+            confirm: compile(&cfg.confirm, "confirm"),
+            scanned: Mutex::new(HashMap::new()),
+            reported: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alert.is_empty() && self.confirm.is_empty()
+    }
+
+    /// The first pattern the call matches, `confirm` ones first.
+    pub fn check(&self, tool_name: &str, tool_input: &Value) -> Option<Hit> {
+        if self.is_empty() {
+            return None;
+        }
+        let text = call_text(tool_name, tool_input);
+        let (level, re) = self
+            .confirm
+            .iter()
+            .map(|re| (Level::Confirm, re))
+            .chain(self.alert.iter().map(|re| (Level::Alert, re)))
+            .find(|(_, re)| re.is_match(&text))?;
+        Some(Hit { level, pattern: re.as_str().to_string(), text })
+    }
+
+    /// Note that `text` in `session_id` is being reported; false if it was
+    /// already, recently.
+    pub fn first_report(&self, session_id: &str, text: &str, now: f64) -> bool {
+        let mut reported = mutex_lock!(self.reported);
+        reported.retain(|_, at| now - *at < REPORTED_KEEP_SECS);
+        reported.insert(format!("{}\n{}", session_id, text), now).is_none()
+    }
+
+    /// Matching tool calls among a session's new transcript messages;
+    /// the next scan starts at `next_index`. Calls made before `since`
+    /// (app start) are skipped.
+    pub fn scan(&self, session_id: &str, messages: &[EnrichedMessage], next_index: usize, since: f64) -> Vec<Hit> {
+        mutex_lock!(self.scanned).insert(session_id.to_string(), next_index);
+        messages
+            .iter()
+            .filter(|m| {
+                chrono::DateTime::parse_from_rfc3339(&m.timestamp)
+                    .is_ok_and(|t| t.timestamp_millis() as f64 / 1000.0 >= since)
+            })
+            .filter_map(|m| match &m.event {
+                ChatEvent::ToolCall { name, input, .. } => self.check(name, input),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Transcript index to scan `session_id` from.
+    pub fn scanned(&self, session_id: &str) -> usize {
+        mutex_lock!(self.scanned).get(session_id).copied().unwrap_or(0)
+    }
+
+    /// Forget sessions no longer scanned.
+    pub fn retain_sessions(&self, keep: impl Fn(&str) -> bool) {
+        mutex_lock!(self.scanned).retain(|sid, _| keep(sid));
+    }
+}
+
+/// One line standing for a tool call: a Bash command as is, else the tool
+/// name and its input.
+pub fn call_text(tool_name: &str, tool_input: &Value) -> String {
+    match tool_input.get("command").and_then(|v| v.as_str()) {
+        Some(cmd) => cmd.to_string(),
+        None => format!("{} {}", tool_name, tool_input),
+    }
+}
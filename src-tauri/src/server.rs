@@ -10,29 +10,33 @@ use axum::{
 };
 use serde::Deserialize;
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::sync::RwLock;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
-use tokio_stream::wrappers::BroadcastStream;
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
 use tokio_stream::StreamExt;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
 use crate::adapter::AdapterRegistry;
 use crate::config::Config;
-use crate::events::{Event, EventStore};
+use crate::events::{ClearFilter, Event, EventStore};
 use crate::focus;
 use crate::remote;
 use crate::session::{SessionTracker, SessionUpdate};
-use crate::chat::ChatReader;
-use crate::permission::PermissionStore;
+use crate::chat::{ChatPage, ChatReader};
+use crate::permission::{Closed, PermissionStore};
+use crate::metrics::RequestMetrics;
 use crate::sse::SSEBroadcaster;
+use crate::status::{compute_state, display_message, pill_state, pill_text, scan_and_merge, PillState};
+use crate::toast::ToastKey;
 use crate::protocol::{
-    HookEvent, SessionStatus, PermissionDecisionKind,
+    HookEvent, SessionStatus, WaitingReason, PermissionDecisionKind,
     SignalPayload, HookPayload, PermissionRequestPayload, PermissionRespondPayload,
-    PreToolCheckPayload, ChatSendPayload,
+    PreToolCheckPayload, ChatSendPayload, WebAgentPayload, WebAgentState, ExternalEventPayload,
 };
 
 pub struct AppState {
@@ -44,6 +48,11 @@ pub struct AppState {
     pub notify_tray: std::sync::mpsc::Sender<()>,
     pub app_handle: std::sync::OnceLock<tauri::AppHandle>,
     pub last_seen_ts: RwLock<f64>,
+    /// `last_seen.json` next to the sessions file; keeps `last_seen_ts`
+    /// across restarts.
+    pub last_seen_path: PathBuf,
+    /// What happened while the app was closed, until the user looks.
+    pub missed: RwLock<Option<Value>>,
     pub permissions: PermissionStore,
     pub chat_reader: ChatReader,
     pub current_hotkey: RwLock<String>,
@@ -51,32 +60,98 @@ pub struct AppState {
     pub live_sound_stop: RwLock<String>,
     pub live_sound_notification: RwLock<String>,
     pub live_sound_permission: RwLock<String>,
+    /// `island.auto_show` / `auto_expand` / `respect_dnd`, live-editable.
+    pub live_auto_show: RwLock<Vec<String>>,
+    pub live_auto_expand: RwLock<Vec<String>>,
+    pub live_respect_dnd: AtomicBool,
     pub http_client: reqwest::Client,
+    /// Clients for remote channels, honouring `network` and `skip_tls_verify`.
+    pub http_clients: remote::HttpClients,
     pub start_time: Instant,
     pub dedup_cache: RwLock<HashMap<String, f64>>,
+    /// Ceilings of the caches above and the chat/event caches.
+    pub memory: crate::memory::MemoryBudget,
+    pub metrics: RequestMetrics,
+    /// PID of the hook daemon we spawned (0 = none); updated on respawn.
+    pub daemon_pid: AtomicU32,
+    /// Last hook binary version seen in a payload ("legacy" = pre-handshake binary).
+    pub last_hook_version: RwLock<String>,
+    /// Away detection; holds toasts/sounds back while the user is idle.
+    pub presence: crate::presence::Presence,
+    /// Island panel state (expanded, view, permission pinning).
+    pub island_state: crate::island_state::IslandState,
+    /// Running with `--headless`: toasts go to connected desktop clients.
+    pub headless: AtomicBool,
+    /// Per-session token buckets for the hook endpoints.
+    pub rate_limiter: crate::ratelimit::RateLimiter,
+    /// Teammates' latest reports (team mode).
+    pub team: crate::team::TeamStore,
+    /// Agents running without hook events.
+    pub hook_health: crate::hook_health::HookHealth,
+    /// Last run and last error of each background loop.
+    pub heartbeats: crate::heartbeat::Heartbeats,
+    /// Wakes the process scanner while it idles (a hook event arrived).
+    pub scan_wake: tokio::sync::Notify,
+    pub recorder: crate::recorder::Recorder,
+    /// Token/cost budgets and the sessions paused at their limit.
+    pub budget: crate::budget::BudgetTracker,
+    /// Tool counts and times from the observe-mode PreToolUse hook.
+    pub tool_stats: crate::tool_stats::ToolStats,
+    /// `sensitive` patterns and the transcript scan position.
+    pub sensitive: crate::sensitive::Sensitive,
+    /// `general.ignore_paths`: projects never monitored.
+    pub ignore_paths: crate::ignore::PathPatterns,
+    /// `island.priority_paths`: projects whose permission requests go first.
+    pub priority_paths: crate::ignore::PathPatterns,
+    /// Toasts, sounds and island pop-ups paused (jump list task); the
+    /// island still pops up for permission requests.
+    pub notifications_paused: AtomicBool,
+    /// When a snooze (see action.rs) resumes notifications.
+    pub snoozed_until: Mutex<Option<f64>>,
 }
 
 impl AppState {
     pub fn new(config: Config) -> (Self, std::sync::mpsc::Receiver<()>) {
+        crate::migrate::run(&config.general.sessions_file, &config.manager.events_file);
         let event_store = EventStore::new(
             config.manager.events_file.clone(),
             config.manager.max_events_age,
+            config.manager.max_events_count,
+            config.manager.max_file_size,
         );
         let session_tracker =
             SessionTracker::new(config.general.sessions_file.clone());
-        let sse = SSEBroadcaster::new();
+        let sse = SSEBroadcaster::new(config.manager.sse_capacity);
         let registry = AdapterRegistry::new();
         let permissions = PermissionStore::new();
-        let chat_reader = ChatReader::new();
+        let claude_roots = config.general.transcript_roots.get("claude_code").map(Vec::as_slice).unwrap_or_default();
+        let chat_reader = ChatReader::new(
+            config.general.chat_cache_messages,
+            config.general.tool_output_bytes,
+            crate::chat::claude_transcript_roots(claude_roots),
+        );
         let (tx, rx) = std::sync::mpsc::channel();
+        crate::redact::init(&config.redact);
+        let sensitive = crate::sensitive::Sensitive::new(&config.sensitive);
+        let ignore_paths = crate::ignore::PathPatterns::new("general.ignore_paths", &config.general.ignore_paths);
+        let priority_paths = crate::ignore::PathPatterns::new("island.priority_paths", &config.island.priority_paths);
+        let last_seen_path = std::path::Path::new(&config.general.sessions_file).with_file_name("last_seen.json");
+        let last_seen_ts = load_last_seen(&last_seen_path);
 
         let current_hotkey = RwLock::new(config.island.hotkey.clone());
         let live_sound_enabled = AtomicBool::new(config.island.sound_enabled);
         let live_sound_stop = RwLock::new(config.island.sound_stop.clone());
         let live_sound_notification = RwLock::new(config.island.sound_notification.clone());
         let live_sound_permission = RwLock::new(config.island.sound_permission.clone());
-
-        let http_client = reqwest::Client::new();
+        let live_auto_show = RwLock::new(config.island.auto_show.clone());
+        let live_auto_expand = RwLock::new(config.island.auto_expand.clone());
+        let live_respect_dnd = AtomicBool::new(config.island.respect_dnd);
+
+        let http_clients = remote::HttpClients::new(&config.network);
+        let rate_limiter = crate::ratelimit::RateLimiter::new(
+            config.manager.rate_limit_per_sec,
+            config.manager.rate_limit_burst,
+        );
 
         (Self {
             config: Arc::new(config),
@@ -86,7 +161,9 @@ impl AppState {
             registry,
             notify_tray: tx,
             app_handle: std::sync::OnceLock::new(),
-            last_seen_ts: RwLock::new(0.0),
+            last_seen_ts: RwLock::new(last_seen_ts),
+            last_seen_path,
+            missed: RwLock::new(None),
             permissions,
             chat_reader,
             current_hotkey,
@@ -94,152 +171,466 @@ impl AppState {
             live_sound_stop,
             live_sound_notification,
             live_sound_permission,
-            http_client,
+            live_auto_show,
+            live_auto_expand,
+            live_respect_dnd,
+            http_client: http_clients.verified.clone(),
+            http_clients,
             start_time: Instant::now(),
             dedup_cache: RwLock::new(HashMap::new()),
+            memory: crate::memory::MemoryBudget::new(),
+            metrics: RequestMetrics::new(),
+            daemon_pid: AtomicU32::new(0),
+            last_hook_version: RwLock::new(String::new()),
+            presence: crate::presence::Presence::new(),
+            island_state: crate::island_state::IslandState::new(),
+            headless: AtomicBool::new(false),
+            rate_limiter,
+            team: crate::team::TeamStore::new(),
+            hook_health: crate::hook_health::HookHealth::new(),
+            heartbeats: crate::heartbeat::Heartbeats::new(),
+            scan_wake: tokio::sync::Notify::new(),
+            recorder: crate::recorder::Recorder::new(),
+            budget: crate::budget::BudgetTracker::new(),
+            tool_stats: crate::tool_stats::ToolStats::new(now_secs()),
+            notifications_paused: AtomicBool::new(false),
+            snoozed_until: Mutex::new(None),
+            ignore_paths,
+            priority_paths,
+            sensitive,
         }, rx)
     }
 }
 
-pub async fn run_server(state: Arc<AppState>) {
+/// Run the HTTP+SSE server. `ready` fires once the listener is bound; if
+/// binding fails it is dropped instead, so waiters see an error.
+pub async fn run_server(state: Arc<AppState>, ready: tokio::sync::oneshot::Sender<()>) {
     let port = state.config.manager.port;
+    let host = crate::ui::bind_host(&state.config.manager);
 
-    // Background: periodic SSE refresh
+    // Background: periodic SSE refresh, plus chat resets queued by readers
+    // outside a request (budget, sensitive scan, stats)
     let sse_state = state.clone();
+    state.heartbeats.register("sse_refresh", Duration::from_secs(5));
     tokio::spawn(async move {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            broadcast_chat_resets(&sse_state);
             sse_state.sse.broadcast("refresh", json!({}));
+            sse_state.heartbeats.beat("sse_refresh");
         }
     });
 
     // Background: session tracker flush (sync file I/O → spawn_blocking)
     let flush_state = state.clone();
+    state.heartbeats.register("session_flush", Duration::from_secs(5));
     tokio::spawn(async move {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
             let s = flush_state.clone();
-            let _ = tokio::task::spawn_blocking(move || {
+            let result = tokio::task::spawn_blocking(move || {
                 s.session_tracker.flush_if_dirty();
             })
             .await;
+            flush_state.heartbeats.report("session_flush", result);
         }
     });
 
     // Background: hourly event compaction (sync file I/O → spawn_blocking)
     let compact_state = state.clone();
+    state.heartbeats.register("event_compaction", Duration::from_secs(3600));
     tokio::spawn(async move {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
             let s = compact_state.clone();
-            let _ = tokio::task::spawn_blocking(move || {
+            let result = tokio::task::spawn_blocking(move || {
                 s.event_store.compact();
             })
             .await;
+            compact_state.heartbeats.report("event_compaction", result);
         }
     });
 
-    // Background: process scanner (Win32 syscalls → spawn_blocking)
+    // Background: process scanner (Win32 syscalls → spawn_blocking), then
+    // the desk state for the timeline. With no agent running and no active
+    // session it slows to `general.idle_scan_secs`, until a hook event
+    // wakes it.
     let scan_state = state.clone();
+    let idle_scan = Duration::from_secs(state.config.general.idle_scan_secs.max(SCAN_SECS));
+    state.heartbeats.register("process_scan", idle_scan);
     tokio::spawn(async move {
+        let mut restarts = crate::process::RestartDetector::new(RESTART_WINDOW);
+        let mut desk_state = None;
+        let mut idle = false;
         loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            if idle {
+                tokio::select! {
+                    _ = tokio::time::sleep(idle_scan) => {}
+                    _ = scan_state.scan_wake.notified() => tracing::debug!("Hook event, scanning now"),
+                }
+            } else {
+                tokio::time::sleep(Duration::from_secs(SCAN_SECS)).await;
+            }
             let s = scan_state.clone();
-            let _ = tokio::task::spawn_blocking(move || {
+            let result = tokio::task::spawn_blocking(move || {
                 s.registry.scan_all();
+                crate::desk_timeline::observe(&s, &mut desk_state);
+                desk_state
             })
             .await;
+            desk_state = scan_state.heartbeats.report("process_scan", result).flatten();
+            let procs = scan_state.registry.get_cached();
+            for r in restarts.observe(&procs) {
+                handle_agent_restart(&scan_state, r).await;
+            }
+            check_hook_silence(&scan_state, &procs).await;
+            let was_idle = idle;
+            idle = procs.is_empty() && !has_active_session(&scan_state);
+            if idle != was_idle {
+                tracing::debug!("Process scan every {}s", if idle { idle_scan.as_secs() } else { SCAN_SECS });
+            }
         }
     });
 
-    // Background: purge ended sessions (every 300s)
+    // Background: purge stale sessions per retention policy (every 300s)
     let purge_state = state.clone();
+    state.heartbeats.register("session_purge", Duration::from_secs(300));
     tokio::spawn(async move {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
             let s = purge_state.clone();
-            let ttl = s.config.general.session_ttl;
-            let _ = tokio::task::spawn_blocking(move || {
-                s.session_tracker.purge_stale(ttl);
+            let result = tokio::task::spawn_blocking(move || {
+                let retention = &s.config.general.retention;
+                let purged = s.session_tracker.purge_stale(|st| retention.for_status(st));
+                if !purged.is_empty() {
+                    tracing::info!("Purged {} stale sessions", purged.len());
+                }
             })
             .await;
+            purge_state.heartbeats.report("session_purge", result);
+        }
+    });
+
+    // Background: hook error spool (every 10s)
+    let spool_state = state.clone();
+    state.heartbeats.register("hook_error_spool", Duration::from_secs(10));
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            let result = tokio::task::spawn_blocking(crate::hook_health::take_spooled).await;
+            if let Some(errors) = spool_state.heartbeats.report("hook_error_spool", result) {
+                report_hook_errors(&spool_state, errors).await;
+            }
         }
     });
 
+    // Background: budget thresholds (every 30s)
+    if state.config.budget.enabled {
+        let budget_state = state.clone();
+        state.heartbeats.register("budget_check", Duration::from_secs(30));
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+                check_budgets(&budget_state).await;
+                budget_state.heartbeats.beat("budget_check");
+            }
+        });
+    }
+
+    // Background: sensitive tool calls in transcripts (every 5s)
+    if !state.sensitive.is_empty() {
+        let sensitive_state = state.clone();
+        state.heartbeats.register("sensitive_scan", Duration::from_secs(5));
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                scan_sensitive(&sensitive_state).await;
+                sensitive_state.heartbeats.beat("sensitive_scan");
+            }
+        });
+    }
+
     // Background: evict stale chat caches (every 600s)
     let chat_state = state.clone();
+    state.heartbeats.register("chat_evict", Duration::from_secs(600));
     tokio::spawn(async move {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(600)).await;
             let s = chat_state.clone();
-            let _ = tokio::task::spawn_blocking(move || {
+            let result = tokio::task::spawn_blocking(move || {
                 s.chat_reader.evict_stale(std::time::Duration::from_secs(600));
             })
             .await;
+            chat_state.heartbeats.report("chat_evict", result);
         }
     });
 
     // Background: clean dedup cache (every 60s, remove entries older than 5s)
+    // and rate limit buckets idle for 10 minutes
     let dedup_state = state.clone();
+    state.heartbeats.register("dedup_clean", Duration::from_secs(60));
     tokio::spawn(async move {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
             let cutoff = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64() - 5.0;
-            let mut cache = write_lock!(dedup_state.dedup_cache);
-            cache.retain(|_, ts| *ts > cutoff);
+            write_lock!(dedup_state.dedup_cache).retain(|_, ts| *ts > cutoff);
+            dedup_state.rate_limiter.evict_idle(std::time::Duration::from_secs(600));
+            dedup_state.heartbeats.beat("dedup_clean");
+        }
+    });
+
+    // Background: cache memory ceilings (every 60s)
+    let memory_state = state.clone();
+    state.heartbeats.register("memory_budget", Duration::from_secs(60));
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+            let s = memory_state.clone();
+            let result = tokio::task::spawn_blocking(move || s.memory.enforce(&s, &s.config.memory)).await;
+            memory_state.heartbeats.report("memory_budget", result);
+        }
+    });
+
+    // Background: coalesced permission countdown (one broadcast for all pending)
+    let countdown_state = state.clone();
+    state.heartbeats.register("permission_countdown", Duration::from_secs(10));
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            broadcast_permission_countdown(&countdown_state);
+            let pending = countdown_state.permissions.get_pending();
+            crate::toast::tick_countdowns(|tag| pending.iter().any(|r| r.id == tag));
+            countdown_state.heartbeats.beat("permission_countdown");
+        }
+    });
+
+    // Background: away detection (poll idle time every 5s)
+    let presence_state = state.clone();
+    state.heartbeats.register("presence", Duration::from_secs(5));
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            presence_tick(&presence_state).await;
+            presence_state.heartbeats.beat("presence");
         }
     });
 
-    // CORS: allow tauri://localhost and browser origins to reach the API
+    // Background: GitHub Actions agent runs (REST polling)
+    if state.config.github.enabled {
+        let gh_state = state.clone();
+        tokio::spawn(async move {
+            poll_github_runs(gh_state).await;
+        });
+    }
+
+    // Background: team mode report forwarding
+    if state.config.team.enabled && !state.config.team.sink_url.is_empty() {
+        let team_state = state.clone();
+        tokio::spawn(async move {
+            crate::team::run_forwarder(team_state).await;
+        });
+    }
+
+    // Background: `pill_state` broadcasts
+    tokio::spawn(track_pill_state(state.clone()));
+
+    // Background: island updates over Tauri events (see island_push.rs)
+    if !state.headless.load(Ordering::Relaxed) {
+        tokio::spawn(push_to_island(state.clone()));
+    }
+
+    // Background: hook daemon supervision (ping every 30s, bounded respawns)
+    if state.config.manager.use_hook_daemon {
+        let daemon_state = state.clone();
+        tokio::spawn(async move {
+            supervise_hook_daemon(daemon_state).await;
+        });
+    }
+
+    // CORS: only the island webview and local pages may reach the API —
+    // otherwise any website open in a browser could drive it.
     let cors = CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(cors_origin(&state.config.manager))
         .allow_methods(Any)
         .allow_headers(Any);
 
+    // Hook endpoints: rate limited per session so a runaway hook loop
+    // can't flood the event log
+    let hook_routes = Router::new()
+        .route("/api/hook", post(api_hook))
+        .route("/api/signal", post(api_signal))
+        .route("/api/permission-request", post(api_permission_request))
+        .route("/api/pre-tool-check", post(api_pre_tool_check))
+        .route_layer(middleware::from_fn_with_state(state.clone(), crate::recorder::record_hooks))
+        .route_layer(middleware::from_fn_with_state(state.clone(), crate::ratelimit::hook_rate_limit))
+        .route_layer(middleware::from_fn(gunzip_hook_body));
+
+    // Development only: replay mock agent sessions
+    let dev_routes = Router::new();
+    #[cfg(debug_assertions)]
+    let dev_routes = dev_routes.route("/api/simulate/scenario", post(api_simulate_scenario));
+
     let app = Router::new()
         .route("/api/health", get(api_health))
+        .route("/api/metrics", get(api_metrics))
+        .route("/api/diagnostics", get(api_diagnostics))
+        .route("/api/logs/level", get(api_log_level_get).post(api_log_level_set))
+        .route("/api/record/start", post(api_record_start))
+        .route("/api/record/stop", post(api_record_stop))
+        .route("/api/budget", get(api_budget))
+        .route("/api/budget/ack", post(api_budget_ack))
+        .route("/api/dashboard", get(api_dashboard))
+        .route("/api/inbox", get(api_inbox))
         .route("/api/all", get(api_all))
         .route("/api/events", get(api_events))
+        .route("/api/events/{id}/ack", post(api_event_ack))
+        .route("/api/events/{id}/dismiss", post(api_event_dismiss))
         .route("/api/sessions", get(api_sessions))
+        .route("/api/sessions/purge", post(api_sessions_purge))
+        .route("/api/sessions/history", get(api_sessions_history))
+        .route("/api/usage", get(api_usage))
+        .route("/api/stats/tools", get(api_stats_tools))
+        .route("/api/stats/timeline", get(api_stats_timeline))
+        .route("/api/adapters", get(api_adapters))
         .route("/api/status", get(api_status))
         .route("/api/stream", get(api_stream))
-        .route("/api/hook", post(api_hook))
-        .route("/api/signal", post(api_signal))
+        .route("/api/web-agent", post(api_web_agent))
+        .route("/api/external-event", post(api_external_event))
+        .route("/api/action", post(api_action))
         .route("/api/focus", post(api_focus))
+        .route("/api/deep-link", post(api_deep_link))
         .route("/api/clear", post(api_clear))
         .route("/api/mark_read", post(api_mark_read))
-        .route("/api/session/{id}", delete(api_delete_session))
+        .route("/api/session/{id}", delete(api_delete_session).patch(api_session_patch))
+        .route("/api/session/{id}/timeline", get(api_session_timeline))
+        .route("/api/session/{id}/link", post(api_session_link))
+        .route("/api/session/{id}/reply", post(api_session_reply))
+        .route("/api/session/{id}/export-chat", post(api_session_export_chat))
+        .route("/api/session/{id}/mute-remote", post(api_session_mute_remote))
+        .route("/api/replies", get(api_replies))
+        .route("/api/views", get(api_views))
+        .route("/api/remote/test", post(api_remote_test))
+        .route("/api/team", get(api_team))
+        .route("/api/team/report", post(api_team_report))
         .route("/api/eval", post(api_eval))
         .route("/api/island/expand", post(api_island_expand))
         .route("/api/island/collapse", post(api_island_collapse))
         .route("/api/island/pill-state", post(api_island_pill_state))
         .route("/api/island/config", get(api_island_config))
+        .route("/api/island/pill", get(api_island_pill))
         .route("/api/island/hide", post(api_island_hide))
+        .route("/api/island/reset-position", post(api_island_reset_position))
+        .route("/api/island/command", post(api_island_command))
+        .route("/api/island/state", get(api_island_state_get).post(api_island_state_set))
         .route("/api/hotkey/capture", post(api_hotkey_capture))
         .route("/api/hotkey/save", post(api_hotkey_save))
         .route("/api/settings", get(api_settings_get).post(api_settings_save))
-        .route("/api/permission-request", post(api_permission_request))
+        .route("/api/settings/schema", get(api_settings_schema))
+        .route("/api/settings/validate", post(api_settings_validate))
+        .route("/api/settings/sounds", get(api_settings_sounds))
+        .route("/api/settings/test-notification", post(api_settings_test_notification))
+        .route("/api/setup/status", get(api_setup_status))
+        .route("/api/setup/run", post(api_setup_run))
+        .route("/api/setup/plan", get(api_setup_plan))
+        .route("/api/setup/apply", post(api_setup_apply))
+        .route("/api/setup/backups", get(api_setup_backups))
+        .route("/api/setup/restore-backup", post(api_setup_restore_backup))
+        .route("/api/backup", get(api_backup))
+        .route(
+            "/api/backup/restore",
+            post(api_backup_restore).layer(axum::extract::DefaultBodyLimit::max(BACKUP_BODY_LIMIT)),
+        )
         .route("/api/permission-respond", post(api_permission_respond))
         .route("/api/permissions", get(api_permissions))
-        .route("/api/pre-tool-check", post(api_pre_tool_check))
+        .route(
+            "/api/permissions/auto-approve",
+            get(api_auto_approve_get).post(api_auto_approve_start).delete(api_auto_approve_stop),
+        )
         .route("/api/chat", get(api_chat))
         .route("/api/chat/v2", get(api_chat_v2))
+        .route("/api/chat/tool-result/{uuid}", get(api_chat_tool_result))
         .route("/api/chat/send", post(api_chat_send))
+        .merge(hook_routes)
+        .merge(dev_routes)
+        .route("/status", get(crate::status_page::status_page))
+        .route(
+            "/permit/{id}/{decision}",
+            get(crate::permit_link::permit_page).post(crate::permit_link::permit_apply),
+        )
+        .route("/", get(|| async { axum::response::Redirect::to("/ui/") }))
+        .nest_service("/ui", crate::ui::router(&state.config.manager.ui_dir))
+        .layer(middleware::from_fn_with_state(state.clone(), crate::ui::lan_auth))
+        .layer(middleware::from_fn_with_state(state.clone(), origin_guard))
         .layer(cors)
         .layer(middleware::from_fn(version_header))
+        .layer(middleware::from_fn_with_state(state.clone(), request_log))
         .with_state(state);
 
-    let addr = format!("127.0.0.1:{}", port);
-    let listener = tokio::net::TcpListener::bind(&addr)
-        .await
-        .expect("Failed to bind HTTP server");
+    let addr = crate::setup::host_port(host, port);
+    let listener = match tokio::net::TcpListener::bind(&addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            tracing::error!("Failed to bind HTTP server on {}: {}", addr, e);
+            return;
+        }
+    };
     tracing::info!("HTTP server listening on {}", addr);
+    let _ = ready.send(());
 
-    axum::serve(listener, app)
+    // Peer addresses let `lan_auth` wave loopback clients through
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
         .expect("HTTP server error");
 }
 
+/// Largest hook body accepted once inflated: the cap axum's `Json`
+/// extractor applies.
+const HOOK_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Middleware on the hook routes: inflate `Content-Encoding: gzip` bodies
+/// (the hook gzips big payloads, like a Stop carrying a long assistant
+/// message) so the rate limiter, recorder and handlers all see plain JSON.
+async fn gunzip_hook_body(req: axum::extract::Request, next: Next) -> Response {
+    use axum::response::IntoResponse;
+    use std::io::Read;
+
+    let gzipped = req
+        .headers()
+        .get("content-encoding")
+        .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"gzip"));
+    if !gzipped {
+        return next.run(req).await;
+    }
+    let (mut parts, body) = req.into_parts();
+    let inflated = match axum::body::to_bytes(body, HOOK_BODY_LIMIT).await {
+        Ok(bytes) => {
+            let mut out = Vec::new();
+            flate2::read::GzDecoder::new(&bytes[..])
+                .take(HOOK_BODY_LIMIT as u64 + 1)
+                .read_to_end(&mut out)
+                .map_err(|e| e.to_string())
+                .map(|_| out)
+        }
+        Err(e) => Err(e.to_string()),
+    };
+    let error = match inflated {
+        Ok(out) if out.len() <= HOOK_BODY_LIMIT => {
+            parts.headers.remove("content-encoding");
+            parts.headers.remove("content-length");
+            let req = axum::extract::Request::from_parts(parts, axum::body::Body::from(out));
+            return next.run(req).await;
+        }
+        Ok(_) => "body too large".to_string(),
+        Err(e) => e,
+    };
+    tracing::warn!("Dropping gzipped hook body on {}: {}", parts.uri.path(), error);
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        Json(json!({ "ok": false, "error": format!("bad gzip body: {}", error) })),
+    )
+        .into_response()
+}
+
 /// Middleware: add X-Agent-Desk-Version header to all responses.
 async fn version_header(req: axum::extract::Request, next: Next) -> Response {
     let mut resp = next.run(req).await;
@@ -250,199 +641,594 @@ async fn version_header(req: axum::extract::Request, next: Next) -> Response {
     resp
 }
 
-// --- Shared helpers ---
-
-pub fn scan_and_merge(state: &AppState) -> Vec<Value> {
-    let processes = state.registry.get_cached();
-    let session_ttl = state.config.general.session_ttl;
+/// Mirror agent workflow runs into sessions: new runs start a session,
+/// running ones stay Active, and completion goes through the signal
+/// pipeline (Stop on success, a notification otherwise).
+async fn poll_github_runs(state: Arc<AppState>) {
+    use crate::adapter::github_actions::{session_id, GithubPoller, RunUpdate};
 
-    // Cleanup: remove discovered-{pid} sessions whose PID no longer exists.
-    {
-        let live_pids: std::collections::HashSet<u32> = processes.iter().map(|p| p.pid).collect();
-        let active = state.session_tracker.get_active(session_ttl);
-        for (sid, info) in &active {
-            if sid.starts_with("discovered-") {
-                let still_running = info.agent_pid.map_or(false, |pid| live_pids.contains(&pid));
-                if !still_running {
-                    state.session_tracker.remove(sid);
+    let cfg = state.config.github.clone();
+    if cfg.repos.is_empty() {
+        tracing::warn!("github.enabled is on but github.repos is empty — not polling");
+        return;
+    }
+    let interval = cfg.poll_secs.max(15);
+    let mut poller = GithubPoller::new();
+    loop {
+        let updates = poller.poll(&state.http_client, &cfg).await;
+        let changed = !updates.is_empty();
+        for update in updates {
+            match update {
+                RunUpdate::Running { repo, run, new } => {
+                    let sid = session_id(&repo, run.id);
+                    let workflow = run.name.clone().unwrap_or_default();
+                    if new {
+                        let _ = handle_signal(state.clone(), github_signal(HookEvent::SessionStart, &sid, &repo, workflow, "", "")).await;
+                    }
+                    let status = if run.status == "queued" { SessionStatus::Idle } else { SessionStatus::Active };
+                    state.session_tracker.update(
+                        &sid,
+                        SessionUpdate {
+                            status: Some(status),
+                            cwd: Some(repo),
+                            last_message: Some(run.display_title),
+                            ..Default::default()
+                        },
+                    );
+                }
+                RunUpdate::Finished { repo, run } => {
+                    let sid = session_id(&repo, run.id);
+                    let workflow = run.name.clone().unwrap_or_default();
+                    let conclusion = run.conclusion.unwrap_or_else(|| "unknown".to_string());
+                    let summary = format!("{} ({}) \u{2014} {}\n{}", run.display_title, conclusion, workflow, run.html_url);
+                    let signal = if conclusion == "success" {
+                        github_signal(HookEvent::Stop, &sid, &repo, workflow, "", &summary)
+                    } else {
+                        github_signal(HookEvent::Notification, &sid, &repo, workflow, "ci_failure", &summary)
+                    };
+                    let _ = handle_signal(state.clone(), signal).await;
                 }
             }
         }
+        if changed {
+            let _ = state.notify_tray.send(());
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
     }
+}
 
-    let tracked = state.session_tracker.get_active(session_ttl);
+/// A signal for a workflow run; `text` is the final message (Stop) or the
+/// notification body.
+fn github_signal(event: HookEvent, sid: &str, repo: &str, workflow: String, ntype: &str, text: &str) -> SignalPayload {
+    let stop = event == HookEvent::Stop;
+    SignalPayload {
+        event,
+        session_id: sid.to_string(),
+        cwd: repo.to_string(),
+        notification_type: ntype.to_string(),
+        message: if stop { String::new() } else { text.to_string() },
+        last_assistant_message: if stop { text.to_string() } else { String::new() },
+        model: workflow,
+        hook_pid: None,
+        hook_version: None,
+        hook_seq: None,
+        agent_pid: None,
+        parent_session_id: None,
+        source: String::new(),
+        transcript_path: String::new(),
+        agent: String::new(),
+        correlation_id: String::new(),
+    }
+}
 
-    // Strategy: session tracker is the source of truth (CWD, status from hooks).
-    // Process scanner provides PID/uptime/create_time.
-    // Match by CWD only — no greedy fallback.
+/// `last_seen_ts` saved by the previous run (0 = never).
+fn load_last_seen(path: &std::path::Path) -> f64 {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+        .and_then(|v| v.get("last_seen_ts").and_then(|t| t.as_f64()))
+        .unwrap_or(0.0)
+}
+
+/// Summarize unread activity since the previous run's `last_seen_ts` in one
+/// toast, and keep it for the island banner (`missed` in `/api/all`) until
+/// the user marks events read. Called once at startup.
+pub fn announce_missed_activity(state: &AppState) {
+    let last_seen = *read_lock!(state.last_seen_ts);
+    if last_seen <= 0.0 {
+        return; // first run — nothing to compare with
+    }
+    let mut finished = HashSet::new();
+    let mut waiting = HashSet::new();
+    let mut limited = HashSet::new();
+    let mut unread = 0;
+    for e in state.event_store.get_events(last_seen).iter().filter(|e| !e.acked && !e.dismissed) {
+        unread += 1;
+        let set = match e.event {
+            HookEvent::Stop => &mut finished,
+            HookEvent::Notification | HookEvent::PermissionRequest => &mut waiting,
+            HookEvent::LimitReached => &mut limited,
+            _ => continue,
+        };
+        set.insert(e.session_id.clone());
+    }
+    if unread == 0 {
+        return;
+    }
+    // A session that finished after asking for input is just finished
+    waiting.retain(|s| !finished.contains(s));
 
-    let mut matched_sessions: std::collections::HashSet<String> =
-        std::collections::HashSet::new();
-    let mut result = Vec::new();
+    // N 个会话已完成 / N 个会话在等你 / N 个会话额度用完 / N 条未读动态
+    let mut lines = Vec::new();
+    if !finished.is_empty() {
+        lines.push(format!("{} \u{4e2a}\u{4f1a}\u{8bdd}\u{5df2}\u{5b8c}\u{6210}", finished.len()));
+    }
+    if !waiting.is_empty() {
+        lines.push(format!("{} \u{4e2a}\u{4f1a}\u{8bdd}\u{5728}\u{7b49}\u{4f60}", waiting.len()));
+    }
+    if !limited.is_empty() {
+        lines.push(format!("{} \u{4e2a}\u{4f1a}\u{8bdd}\u{989d}\u{5ea6}\u{7528}\u{5b8c}", limited.len()));
+    }
+    if lines.is_empty() {
+        lines.push(format!("{} \u{6761}\u{672a}\u{8bfb}\u{52a8}\u{6001}", unread));
+    }
+    let summary = json!({
+        "since": last_seen,
+        "unread": unread,
+        "finished": finished.len(),
+        "waiting": waiting.len(),
+        "limited": limited.len(),
+        "text": lines.join(" \u{00b7} "),
+        "inbox": "/api/inbox",
+    });
+    tracing::info!("Missed while closed: {}", lines.join(", "));
+    *write_lock!(state.missed) = Some(summary.clone());
+    state.sse.broadcast("missed", summary);
 
-    // Build CWD → tracker info lookup (normalized)
-    let mut cwd_tracker: HashMap<String, Vec<&crate::session::SessionInfo>> = HashMap::new();
-    for (_sid, info) in &tracked {
-        if info.status == SessionStatus::Ended || matched_sessions.contains(&info.session_id) {
-            continue;
-        }
-        let tcwd = info.cwd.replace('/', "\\").to_lowercase();
-        let tcwd = tcwd.trim_end_matches('\\').to_string();
-        if tcwd.is_empty() { continue; }
-        cwd_tracker.entry(tcwd).or_default().push(info);
+    if can_toast(state) {
+        // 📬 你不在的时候
+        show_toast(state, "\u{1f4ec} \u{4f60}\u{4e0d}\u{5728}\u{7684}\u{65f6}\u{5019}", &lines.join("\n"), None, None, "");
     }
+}
 
-    let mut unmatched_procs = Vec::new();
+/// Give up respawning after this many consecutive restarts.
+const MAX_DAEMON_RESTARTS: u32 = 5;
 
-    for proc in processes.iter() {
-        let pcwd = proc.cwd.replace('/', "\\").to_lowercase();
-        let pcwd_norm = pcwd.trim_end_matches('\\');
+/// Poll input idle time and act on away/return/escalation transitions.
+async fn presence_tick(state: &Arc<AppState>) {
+    use crate::presence::Transition;
 
-        // CWD match only
-        let tinfo = cwd_tracker.get(pcwd_norm).and_then(|entries| {
-            entries.iter()
-                .filter(|e| !matched_sessions.contains(&e.session_id))
-                .max_by(|a, b| a.updated_at.partial_cmp(&b.updated_at).unwrap_or(std::cmp::Ordering::Equal))
-                .copied()
-        });
+    crate::action::end_snooze(state);
 
-        if let Some(info) = tinfo {
-            matched_sessions.insert(info.session_id.clone());
+    let cfg = &state.config.presence;
+    let idle = crate::presence::idle_secs();
+    match state.presence.tick(idle, cfg.away_after_secs, cfg.escalate_after_secs) {
+        Transition::None => {}
+        Transition::Away => {
+            tracing::info!("User away (idle {}s) — deferring toasts and sounds", idle.unwrap_or(0));
+            state.sse.broadcast("presence", json!({ "away": true }));
+        }
+        Transition::Returned(deferred) => {
+            tracing::info!("User back — {} deferred notification(s)", deferred.len());
+            state.sse.broadcast("presence", json!({ "away": false, "deferred": deferred.len() }));
+            if !deferred.is_empty() && can_toast(state) {
+                // 🔔 离开期间 N 条通知
+                let title = format!("\u{1f514} \u{79bb}\u{5f00}\u{671f}\u{95f4} {} \u{6761}\u{901a}\u{77e5}", deferred.len());
+                summary_toast(state, &title, &deferred);
+            }
+        }
+        Transition::Escalate(pending) => {
+            let mins = idle.unwrap_or(0) / 60;
+            tracing::info!("User away {} min — escalating {} notification(s) to remote channels", mins, pending.len());
+            let body: Vec<&str> = pending.iter().map(|d| d.message.as_str()).collect();
+            let msg = remote::RemoteMessage::new(
+                "away",
+                format!("[Away {} min] {} notification(s)\n\n{}", mins, pending.len(), body.join("\n\n")),
+            );
+            let cfg = Arc::clone(&state.config);
+            let clients = state.http_clients.clone();
+            tokio::spawn(async move {
+                remote::dispatch_remote(&cfg, &clients, &msg).await;
+            });
         }
+    }
 
-        if let Some(info) = tinfo {
-            // CWD-matched: merge process + tracker
-            let status = match info.status {
-                SessionStatus::Waiting | SessionStatus::Idle => "waiting",
-                SessionStatus::Stopped | SessionStatus::Ended => "stopped",
-                SessionStatus::Active => "active",
-                _ => "waiting",
-            };
-            let display_cwd = if info.cwd.is_empty() { &proc.cwd } else { &info.cwd };
-            result.push(json!({
-                "pid": proc.pid,
-                "name": proc.name,
-                "agent_type": proc.agent_type,
-                "cwd": display_cwd,
-                "uptime": proc.uptime,
-                "create_time": proc.create_time,
-                "status": status,
-                "session_id": &info.session_id,
-                "notification_type": info.notification_type.as_deref().unwrap_or(""),
-                "notification_message": info.notification_message.as_deref().unwrap_or(""),
-                "last_message": info.last_message.as_deref().unwrap_or(""),
-            }));
-        } else {
-            // Unmatched process — remember for fallback pairing
-            unmatched_procs.push(proc);
+    // Attention batch: show what was held once the interval is over. Held
+    // toasts that come due while the user is away join the away summary.
+    let every = cfg.batch_every_mins * 60;
+    if every == 0 {
+        return;
+    }
+    let Some(held) = state.presence.batch_due(now_secs(), every) else { return };
+    if !held.is_empty() {
+        tracing::info!("Attention batch \u{2014} {} held notification(s)", held.len());
+        if state.presence.is_away() {
+            for d in held {
+                state.presence.defer(d.title, d.message);
+            }
+        } else if can_toast(state) {
+            // 🔕 专注期间 N 条通知
+            let title = format!("\u{1f515} \u{4e13}\u{6ce8}\u{671f}\u{95f4} {} \u{6761}\u{901a}\u{77e5}", held.len());
+            summary_toast(state, &title, &held);
         }
     }
+    state.sse.broadcast("batch", json!(state.presence.batch_status()));
+}
+
+/// One toast listing the titles of held notifications, newest first.
+fn summary_toast(state: &AppState, title: &str, held: &[crate::presence::Deferred]) {
+    let mut lines: Vec<&str> = held.iter().rev().take(5).map(|d| d.title.as_str()).collect();
+    let more = held.len().saturating_sub(lines.len());
+    let extra = format!("+{}", more);
+    if more > 0 {
+        lines.push(&extra);
+    }
+    let sound = state.live_sound_enabled.load(Ordering::Relaxed)
+        .then(|| read_lock!(state.live_sound_notification).clone());
+    show_toast(state, title, &lines.join("\n"), sound, None, "");
+}
+
+/// Whether toasts are held for attention batches (see presence.rs).
+fn batching(state: &AppState) -> bool {
+    state.config.presence.batch_every_mins > 0
+}
+
+/// Hold a non-urgent toast for the next attention batch, and tell the
+/// island how many are waiting.
+fn hold_toast(state: &AppState, title: String) {
+    let status = state.presence.hold(title, now_secs(), state.config.presence.batch_every_mins * 60);
+    state.sse.broadcast("batch", json!(status));
+}
+
+/// Ping the hook daemon periodically and respawn it if it stops answering.
+///
+/// Backoff doubles per consecutive restart (30s, 60s, 120s, …); the counter
+/// resets once the daemon has stayed healthy for 10 minutes.
+async fn supervise_hook_daemon(state: Arc<AppState>) {
+    let port = state.config.manager.port;
+    let mut restarts: u32 = 0;
+    let mut next_allowed = Instant::now();
+    let mut healthy_since: Option<Instant> = None;
+
+    loop {
+        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+        if state.daemon_pid.load(Ordering::Relaxed) == 0 {
+            continue; // never spawned (no hook binary) — nothing to supervise
+        }
+
+        let alive = tokio::task::spawn_blocking(move || crate::setup::ping_hook_daemon(port).is_ok())
+            .await
+            .unwrap_or(false);
+        if alive {
+            let since = *healthy_since.get_or_insert_with(Instant::now);
+            if restarts > 0 && since.elapsed() >= std::time::Duration::from_secs(600) {
+                restarts = 0;
+            }
+            continue;
+        }
+        healthy_since = None;
+
+        if restarts >= MAX_DAEMON_RESTARTS {
+            continue;
+        }
+        if Instant::now() < next_allowed {
+            continue;
+        }
 
-    // Phase 2: pair unmatched processes with unmatched tracker entries.
-    //
-    // Prefer PID match (agent_pid from hook) for stable identity.
-    // Fallback to recency sort when PID is unavailable.
-    let mut unmatched_trackers: Vec<&crate::session::SessionInfo> = tracked.values()
-        .filter(|i| {
-            !matched_sessions.contains(&i.session_id)
-                && i.status != SessionStatus::Ended
+        let old_pid = state.daemon_pid.load(Ordering::Relaxed);
+        let new_pid = tokio::task::spawn_blocking(move || {
+            // A hung daemon still holds the port; a crashed one may have had
+            // its PID reused, so clear by port rather than by PID.
+            crate::setup::kill_orphaned_daemon(port);
+            crate::setup::spawn_hook_daemon(port)
         })
-        .collect();
-    // Sort: most recently updated first (best match wins for recency fallback)
-    unmatched_trackers.sort_by(|a, b| b.updated_at.partial_cmp(&a.updated_at).unwrap_or(std::cmp::Ordering::Equal));
-
-    for proc in &unmatched_procs {
-        // Try PID match first (stable), then fall back to first available (recency)
-        let idx = unmatched_trackers.iter()
-            .position(|i| i.agent_pid == Some(proc.pid))
-            .or_else(|| if unmatched_trackers.is_empty() { None } else { Some(0) });
-        if let Some(idx) = idx {
-            let info = unmatched_trackers.remove(idx);
-            matched_sessions.insert(info.session_id.clone());
-            let status = match info.status {
-                SessionStatus::Waiting | SessionStatus::Idle => "waiting",
-                SessionStatus::Stopped | SessionStatus::Ended => "stopped",
-                SessionStatus::Active => "active",
-                _ => "waiting",
-            };
-            result.push(json!({
-                "pid": proc.pid,
-                "name": proc.name,
-                "agent_type": proc.agent_type,
-                "cwd": &info.cwd,
-                "uptime": proc.uptime,
-                "create_time": proc.create_time,
-                "status": status,
-                "session_id": &info.session_id,
-                "notification_type": info.notification_type.as_deref().unwrap_or(""),
-                "notification_message": info.notification_message.as_deref().unwrap_or(""),
-                "last_message": info.last_message.as_deref().unwrap_or(""),
-            }));
+        .await
+        .ok()
+        .flatten();
+
+        restarts += 1;
+        next_allowed = Instant::now() + std::time::Duration::from_secs(30 << restarts.min(5));
+        let message = match new_pid {
+            Some(pid) => {
+                state.daemon_pid.store(pid, Ordering::Relaxed);
+                tracing::warn!(
+                    "Hook daemon (PID {}) unresponsive — respawned as PID {} (restart {}/{})",
+                    old_pid, pid, restarts, MAX_DAEMON_RESTARTS
+                );
+                state.sse.broadcast("daemon_restarted", json!({
+                    "old_pid": old_pid,
+                    "pid": pid,
+                    "restarts": restarts,
+                }));
+                format!(
+                    "[Hook daemon] PID {} unresponsive, respawned as PID {} (restart {}/{})",
+                    old_pid, pid, restarts, MAX_DAEMON_RESTARTS
+                )
+            }
+            None => {
+                tracing::warn!("Hook daemon unresponsive and respawn failed (attempt {}/{})", restarts, MAX_DAEMON_RESTARTS);
+                format!(
+                    "[Hook daemon] PID {} unresponsive, respawn failed (attempt {}/{})",
+                    old_pid, restarts, MAX_DAEMON_RESTARTS
+                )
+            }
+        };
+        let evt = Event::new(HookEvent::DaemonRestarted, "", "", &message, 2);
+        {
+            let s = state.clone();
+            let _ = tokio::task::spawn_blocking(move || s.event_store.append_event(evt)).await;
         }
-        else {
-            // Phase 3: auto-discover unmatched processes.
-            //
-            // This process is running but has no tracker entry — the SessionStart
-            // hook fired before agent-desk was running (or agent-desk restarted).
-            // Register a "discovered" session so the UI shows it immediately.
-            // When hooks eventually fire (user types something), the session
-            // will get properly updated with correct CWD/status.
-            let synthetic_id = format!("discovered-{}", proc.pid);
-            state.session_tracker.register(
-                &synthetic_id,
-                &proc.cwd,
-                None,
-                Some(proc.pid),
-            );
-            result.push(json!({
-                "pid": proc.pid,
-                "name": proc.name,
-                "agent_type": proc.agent_type,
-                "cwd": &proc.cwd,
-                "uptime": proc.uptime,
-                "create_time": proc.create_time,
-                "status": "waiting",
-                "session_id": &synthetic_id,
-                "notification_type": "",
-                "notification_message": "",
-                "last_message": "",
-            }));
+        let _ = state.notify_tray.send(());
+        if restarts >= MAX_DAEMON_RESTARTS {
+            tracing::error!("Hook daemon restart limit reached — hooks will use direct HTTP until app restart");
         }
     }
+}
+
+/// Routes that intentionally hold the connection open (SSE / long-poll);
+/// their latency is recorded but never reported as slow.
+const LONG_POLL_ROUTES: &[&str] = &["/api/stream", "/api/permission-request", "/api/pre-tool-check"];
 
-    result
+/// Middleware: log method/path/status/latency and feed the latency histogram.
+async fn request_log(
+    State(state): State<Arc<AppState>>,
+    req: axum::extract::Request,
+    next: Next,
+) -> Response {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let route = req
+        .extensions()
+        .get::<axum::extract::MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let start = Instant::now();
+    let resp = next.run(req).await;
+    let elapsed = start.elapsed();
+    let status = resp.status().as_u16();
+
+    state.metrics.record(method.as_str(), &route, status, elapsed);
+
+    let ms = elapsed.as_secs_f64() * 1000.0;
+    let slow_ms = state.config.manager.slow_request_ms;
+    if slow_ms > 0 && ms >= slow_ms as f64 && !LONG_POLL_ROUTES.contains(&route.as_str()) {
+        tracing::warn!("Slow request: {} {} -> {} in {:.1}ms", method, path, status, ms);
+    } else {
+        tracing::debug!("{} {} -> {} in {:.1}ms", method, path, status, ms);
+    }
+    resp
 }
 
-pub fn compute_state(processes: &[Value]) -> Value {
-    let active_count = processes.len();
-    let mut waiting_count = 0;
-    let mut working_count = 0;
+// --- Shared helpers ---
 
-    for proc in processes {
-        match proc.get("status").and_then(|s| s.as_str()) {
-            Some("waiting") => waiting_count += 1,
-            Some("active") => working_count += 1,
-            _ => {}
-        }
+/// Send the island panel state to all SSE clients.
+pub(crate) fn broadcast_island_state(state: &AppState) {
+    state.sse.broadcast("island_state", state.island_state.snapshot());
+}
+
+/// Re-pin or unpin the island after the pending permission set changed.
+fn sync_island_permissions(state: &AppState) {
+    let pending = state.permissions.get_pending().len();
+    if state.island_state.set_pending_permissions(pending) {
+        broadcast_island_state(state);
+    }
+}
+
+/// Track the hook binary version from a payload and warn (log + SSE) the
+/// first time a version different from the app's own shows up.
+///
+/// Only payloads carrying `hook_pid` come from the hook binary; those
+/// without `hook_version` predate the handshake and count as "legacy".
+fn note_hook_version(state: &AppState, hook_pid: Option<u32>, hook_version: Option<&str>) {
+    if hook_pid.is_none() && hook_version.is_none() {
+        return; // not from the hook binary (curl, web agent, ...)
+    }
+    let seen = hook_version.unwrap_or("legacy");
+    if *read_lock!(state.last_hook_version) == seen {
+        return;
+    }
+    *write_lock!(state.last_hook_version) = seen.to_string();
+
+    let expected = env!("CARGO_PKG_VERSION");
+    if seen != expected {
+        tracing::warn!("Hook binary version {} does not match app version {} — reinstall or restart to update hooks", seen, expected);
+        state.sse.broadcast("hook_version_mismatch", json!({
+            "hook_version": seen,
+            "app_version": expected,
+        }));
     }
+}
+
+/// Whether a permission request jumps the queue: its session is pinned or
+/// its project is in `island.priority_paths`.
+fn permission_priority(state: &AppState, session_id: &str, cwd: &str) -> bool {
+    state.session_tracker.pinned(session_id) || state.priority_paths.matches(cwd)
+}
 
-    let state = if active_count == 0 {
-        "sleeping"
-    } else if waiting_count > 0 {
-        "attention"
-    } else if working_count > 0 {
-        "thinking"
+/// The sound a new permission request plays.
+fn permission_sound(state: &AppState, priority: bool) -> String {
+    if priority {
+        state.config.island.sound_permission_priority.clone()
     } else {
-        "done"
-    };
+        read_lock!(state.live_sound_permission).clone()
+    }
+}
 
-    json!({
-        "state": state,
-        "active_processes": active_count,
-        "pending_actions": waiting_count,
+/// Whether toasts can be shown: by our own window, or by a desktop client
+/// connected to this headless instance.
+fn can_toast(state: &AppState) -> bool {
+    state.app_handle.get().is_some() || state.headless.load(Ordering::Relaxed)
+}
+
+/// The sound a hook event plays, `None` with sounds off.
+fn event_sound(state: &AppState, event: &HookEvent) -> Option<String> {
+    state.live_sound_enabled.load(Ordering::Relaxed).then(|| match event {
+        HookEvent::Stop => read_lock!(state.live_sound_stop).clone(),
+        HookEvent::PermissionRequest => read_lock!(state.live_sound_permission).clone(),
+        _ => read_lock!(state.live_sound_notification).clone(),
+    })
+}
+
+/// Show a toast and play `sound`. Headless, the `toast` SSE message lets a
+/// connected desktop client (see service.rs) show it instead.
+/// `key` groups the toast in Action Center and replaces an older one with
+/// the same key (see `toast`). `cid` is the correlation id of the hook
+/// invocation behind it, if any.
+fn show_toast(state: &AppState, title: &str, body: &str, sound: Option<String>, key: Option<ToastKey>, cid: &str) {
+    show_linked_toast(state, title, body, sound, key, cid, None);
+}
+
+/// `show_toast` with deep links to open from the toast.
+fn show_linked_toast(
+    state: &AppState,
+    title: &str,
+    body: &str,
+    sound: Option<String>,
+    key: Option<ToastKey>,
+    cid: &str,
+    links: Option<&crate::toast::ToastLinks>,
+) {
+    if !cid.is_empty() {
+        tracing::debug!("[{}] Toast: {}", cid, title);
+    }
+    if state.notifications_paused.load(Ordering::Relaxed) {
+        tracing::debug!("Notifications paused \u{2014} toast skipped: {}", title);
+        return;
+    }
+    if let Some(handle) = state.app_handle.get() {
+        crate::toast::show(handle, title, body, key, links);
+        if let Some(st) = sound {
+            crate::tray::play_notification_sound(&st);
+        }
+    } else {
+        state.sse.broadcast("toast", json!({
+            "title": title,
+            "body": body,
+            "sound": sound,
+            "group": key.map(|k| k.group),
+            "tag": key.map(|k| k.tag),
+            "links": links,
+            "correlation_id": cid,
+        }));
+    }
+}
+
+/// Show and/or expand the island for a `permission`, `stop`,
+/// `notification` or `session_start` event, as `island.auto_show` /
+/// `auto_expand` say. Nothing pops up while Windows holds notifications
+/// back (full-screen app, presentation) unless `respect_dnd` is off. The
+/// `island_pop` SSE message tells the page (ours or a client's) to expand.
+fn pop_island(state: &AppState, kind: &str) {
+    use tauri::Manager;
+    let window = state.app_handle.get().and_then(|h| h.get_webview_window("island"));
+    let hidden = window.as_ref().is_some_and(|w| !w.is_visible().unwrap_or(true));
+    let flash = hidden && kind == "permission" && state.config.island.taskbar_badge && state.config.island.taskbar_flash;
+    if let Some(w) = window.as_ref().filter(|_| flash) {
+        crate::taskbar::flash(w);
+    }
+    if state.live_respect_dnd.load(Ordering::Relaxed) && crate::presence::notifications_suppressed() {
+        tracing::debug!("Notifications suppressed by Windows \u{2014} island not popped for {}", kind);
+        return;
+    }
+    if kind != "permission" && state.notifications_paused.load(Ordering::Relaxed) {
+        return;
+    }
+    let show = read_lock!(state.live_auto_show).iter().any(|k| k == kind);
+    if let Some(w) = window.as_ref().filter(|_| show) {
+        let _ = w.show();
+    }
+    if (show || !hidden) && read_lock!(state.live_auto_expand).iter().any(|k| k == kind) {
+        if window.is_some() {
+            crate::island::expand(state.config.island.panel_width, state.config.island.panel_height);
+        }
+        state.sse.broadcast("island_pop", json!({ "kind": kind }));
+    }
+}
+
+/// Build the CORS origin policy from `manager.allowed_origins`.
+fn cors_origin(cfg: &crate::config::ManagerConfig) -> AllowOrigin {
+    if cfg.cors_permissive {
+        tracing::warn!("CORS permissive mode enabled — any origin may call the API");
+        return AllowOrigin::any();
+    }
+    let allowed = allowed_origins(cfg);
+    AllowOrigin::predicate(move |origin: &axum::http::HeaderValue, _| {
+        let Ok(origin) = origin.to_str() else { return false };
+        let origin = origin.to_ascii_lowercase();
+        allowed.iter().any(|a| origin_matches(a, &origin))
     })
 }
 
+/// `manager.allowed_origins`, normalized for `origin_matches`.
+fn allowed_origins(cfg: &crate::config::ManagerConfig) -> Vec<String> {
+    cfg.allowed_origins
+        .iter()
+        .map(|o| o.trim().trim_end_matches('/').to_ascii_lowercase())
+        .filter(|o| !o.is_empty())
+        .collect()
+}
+
+/// Whether a request's `Origin` (if any) may make it: an allowed origin, or
+/// a page this server served itself (the LAN status and permit pages, also
+/// when reached through `manager.public_url`).
+fn request_origin_allowed(cfg: &crate::config::ManagerConfig, headers: &axum::http::HeaderMap) -> bool {
+    let Some(origin) = headers.get(axum::http::header::ORIGIN) else {
+        return true; // not from a browser page
+    };
+    if cfg.cors_permissive {
+        return true;
+    }
+    let Ok(origin) = origin.to_str() else { return false };
+    let origin = origin.to_ascii_lowercase();
+    let same_origin = headers
+        .get(axum::http::header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|host| {
+            let host = host.to_ascii_lowercase();
+            origin == format!("http://{}", host) || origin == format!("https://{}", host)
+        });
+    let public = cfg.public_url.trim().trim_end_matches('/').to_ascii_lowercase();
+    let via_public_url = !public.is_empty() && (public == origin || public.starts_with(&format!("{}/", origin)));
+    same_origin || via_public_url || allowed_origins(cfg).iter().any(|a| origin_matches(a, &origin))
+}
+
+/// Middleware: refuse requests with side effects (anything but GET, HEAD
+/// and OPTIONS) from origins `manager.allowed_origins` doesn't list. CORS
+/// only guards what a page can read back; a "simple" cross-site POST (no
+/// body, a form, `text/plain`) still reaches its handler.
+async fn origin_guard(State(state): State<Arc<AppState>>, req: axum::extract::Request, next: Next) -> Response {
+    use axum::http::Method;
+    use axum::response::IntoResponse;
+
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if safe || request_origin_allowed(&state.config.manager, req.headers()) {
+        return next.run(req).await;
+    }
+    tracing::warn!(
+        "Refused {} {} from origin {:?}",
+        req.method(),
+        req.uri().path(),
+        req.headers().get(axum::http::header::ORIGIN)
+    );
+    (
+        axum::http::StatusCode::FORBIDDEN,
+        Json(json!({ "ok": false, "error": "origin not allowed" })),
+    )
+        .into_response()
+}
+
+/// Exact match, or `allowed` has no port and `origin` is the same host with one.
+fn origin_matches(allowed: &str, origin: &str) -> bool {
+    if allowed == origin {
+        return true;
+    }
+    match origin.strip_prefix(allowed) {
+        Some(rest) => rest.strip_prefix(':').is_some_and(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit())),
+        None => false,
+    }
+}
+
 // --- API handlers ---
 
 #[derive(Deserialize)]
@@ -455,7 +1241,7 @@ async fn api_all(
     Query(q): Query<AfterQuery>,
 ) -> Json<Value> {
     let after_ts = q.after.unwrap_or(0.0);
-    let processes = scan_and_merge(&state);
+    let processes = sessions_with_rates(&state).await;
     let status = compute_state(&processes);
     let events = state.event_store.get_events(after_ts);
 
@@ -463,47 +1249,462 @@ async fn api_all(
         "status": status,
         "processes": processes,
         "events": events,
+        "missed": *read_lock!(state.missed),
+        "auto_approve": state.permissions.windows(now_secs()),
+        "batch": batching(&state).then(|| state.presence.batch_status()),
     }))
 }
 
-async fn api_events(
-    State(state): State<Arc<AppState>>,
-    Query(q): Query<AfterQuery>,
-) -> Json<Value> {
-    let after_ts = q.after.unwrap_or(0.0);
-    let events = state.event_store.get_events(after_ts);
-    Json(json!({ "events": events }))
-}
-
-async fn api_sessions(State(state): State<Arc<AppState>>) -> Json<Value> {
-    let processes = scan_and_merge(&state);
-    Json(json!({ "processes": processes }))
+/// Sessions with their token rates (blocking scan on a worker thread).
+async fn sessions_with_rates(state: &Arc<AppState>) -> Vec<Value> {
+    let s = state.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut processes = scan_and_merge(&s);
+        add_token_rates(&s, &mut processes);
+        add_time_text(&s.config.messages.language, &mut processes);
+        processes
+    })
+    .await
+    .unwrap_or_default()
 }
 
-async fn api_status(State(state): State<Arc<AppState>>) -> Json<Value> {
-    let processes = scan_and_merge(&state);
-    let mut status = compute_state(&processes);
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs_f64();
-    let recent = state.event_store.get_events(now - 300.0).len();
-    let last_seen = *read_lock!(state.last_seen_ts);
-    let unread_count = state.event_store.get_events(last_seen).len();
-    if let Some(obj) = status.as_object_mut() {
-        obj.insert("recent_events".to_string(), json!(recent));
-        obj.insert("unread_count".to_string(), json!(unread_count));
+/// Shortest gap between two island state pushes, so a burst of SSE
+/// messages (a tool-heavy turn) costs one scan.
+const ISLAND_PUSH_GAP: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Broadcast `pill_state` whenever the pill's state (`status::pill_state`)
+/// changes, rechecked after SSE traffic (other than its own `pill_state`
+/// messages) at most every `ISLAND_PUSH_GAP`.
+async fn track_pill_state(state: Arc<AppState>) {
+    use tokio::sync::broadcast::error::RecvError;
+
+    let mut rx = state.sse.subscribe();
+    let mut last = None;
+    loop {
+        match rx.recv().await {
+            Ok(text) => {
+                if serde_json::from_str::<Value>(&text).is_ok_and(|v| v["type"] == "pill_state") {
+                    continue;
+                }
+            }
+            Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return,
+        }
+        tokio::time::sleep(ISLAND_PUSH_GAP).await;
+        // The check below covers whatever arrived meanwhile
+        rx = rx.resubscribe();
+        let s = state.clone();
+        let current = match tokio::task::spawn_blocking(move || {
+            let processes = scan_and_merge(&s);
+            pill_state(&processes, s.permissions.get_pending().len(), s.hook_health.silent().len())
+        })
+        .await
+        {
+            Ok(current) => current,
+            Err(e) => {
+                tracing::warn!("Pill state scan failed: {}", e);
+                continue;
+            }
+        };
+        if last != Some(current) {
+            last = Some(current);
+            state.sse.broadcast("pill_state", json!({ "state": current }));
+        }
     }
-    Json(status)
 }
 
-async fn api_stream(
-    State(state): State<Arc<AppState>>,
-) -> Sse<impl tokio_stream::Stream<Item = Result<SseEvent, Infallible>>> {
-    let rx = state.sse.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|result| match result {
-        Ok(msg) => Some(Ok(SseEvent::default().data(msg))),
-        Err(_) => None, // Lagged — skip
+/// Keep the island page current without it polling (see island_push.rs):
+/// relay each SSE message, then push the sessions and pending permissions
+/// if they changed. The 5s `refresh` message picks up what the process
+/// scanner found in between.
+async fn push_to_island(state: Arc<AppState>) {
+    use crate::island_push::{self, IslandPush};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let mut rx = state.sse.subscribe();
+    let (mut last_state, mut last_perms) = (String::new(), String::new());
+    let mut dirty = false;
+    let mut last_push = Instant::now();
+    loop {
+        let wait = ISLAND_PUSH_GAP.saturating_sub(last_push.elapsed());
+        tokio::select! {
+            msg = rx.recv() => {
+                match msg {
+                    Ok(text) => {
+                        if let Some(handle) = state.app_handle.get() {
+                            island_push::relay(handle, &text);
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return,
+                }
+                dirty = true;
+                continue;
+            }
+            _ = tokio::time::sleep(wait), if dirty => {}
+        }
+        dirty = false;
+        last_push = Instant::now();
+        let Some(handle) = state.app_handle.get() else { continue };
+
+        let processes = sessions_with_rates(&state).await;
+        let push = IslandPush::State {
+            status: compute_state(&processes),
+            processes,
+            missed: read_lock!(state.missed).clone(),
+            auto_approve: state.permissions.windows(now_secs()),
+            batch: batching(&state).then(|| state.presence.batch_status()),
+        };
+        let text = serde_json::to_string(&push).unwrap_or_default();
+        if text != last_state {
+            island_push::send(handle, &push);
+            last_state = text;
+        }
+        let push = IslandPush::Permissions { requests: state.permissions.get_pending() };
+        let text = serde_json::to_string(&push).unwrap_or_default();
+        if text != last_perms {
+            island_push::send(handle, &push);
+            last_perms = text;
+        }
+    }
+}
+
+/// GET /api/adapters — agents and what each supports (chat, approvals,
+/// sending input), keyed by the `agent_type` of sessions.
+async fn api_adapters(State(state): State<Arc<AppState>>) -> Json<Value> {
+    Json(json!({ "adapters": state.registry.list() }))
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    after: Option<f64>,
+    limit: Option<usize>,
+    /// Only events older than this (paging back through history).
+    before: Option<f64>,
+    /// Text to find (case-insensitive) in the message, CWD or session id.
+    q: Option<String>,
+    /// Event type, e.g. `stop`.
+    event: Option<String>,
+    /// `folded`: one item per agent turn (see `events::fold`).
+    view: Option<String>,
+}
+
+/// Events newest-first; `limit` caps how many are returned (default 200).
+/// With `view=folded` it counts agent turns rather than events.
+async fn api_events(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<EventsQuery>,
+) -> Json<Value> {
+    let after_ts = q.after.unwrap_or(0.0);
+    let limit = q.limit.unwrap_or(200);
+    let needle = q.q.as_deref().map(str::to_lowercase).filter(|s| !s.is_empty());
+    let kind = q.event.as_deref().filter(|s| !s.is_empty());
+    let language = &state.config.messages.language;
+    let folded = match q.view.as_deref().filter(|v| !v.is_empty()) {
+        None => false,
+        Some("folded") => true,
+        Some(other) => return Json(json!({ "ok": false, "error": format!("unknown view: {}", other) })),
+    };
+    if !folded && q.before.is_none() && needle.is_none() && kind.is_none() {
+        return Json(json!({ "events": with_ago(state.event_store.get_recent(after_ts, limit), language) }));
+    }
+    let visible = state.event_store.get_events(after_ts);
+    let matching = visible
+        .iter()
+        .rev()
+        .filter(|e| q.before.is_none_or(|b| e.ts < b))
+        .filter(|e| kind.is_none_or(|k| e.event.to_string() == k))
+        .filter(|e| {
+            needle.as_ref().is_none_or(|n| {
+                [&e.message, &e.cwd, &e.session_id].iter().any(|f| f.to_lowercase().contains(n.as_str()))
+            })
+        });
+    if folded {
+        let mut turns = crate::events::fold(matching.cloned().collect());
+        turns.truncate(limit);
+        return Json(json!({ "events": with_ago(turns, language) }));
+    }
+    let events: Vec<Event> = matching.take(limit).cloned().collect();
+    Json(json!({ "events": with_ago(events, language) }))
+}
+
+/// Events with `ago` (e.g. `5m ago`) next to their `ts`, in `language`.
+fn with_ago<T: serde::Serialize>(events: Vec<T>, language: &str) -> Vec<Value> {
+    let now = now_secs();
+    events
+        .into_iter()
+        .map(|e| {
+            let mut v = json!(e);
+            let ts = v.get("ts").and_then(|t| t.as_f64()).unwrap_or(now);
+            if let Some(obj) = v.as_object_mut() {
+                obj.insert("ago".to_string(), json!(crate::messages::ago(now - ts, language)));
+            }
+            v
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct SessionHistoryQuery {
+    /// Text to find (case-insensitive) in the CWD, session id, model or
+    /// last message.
+    q: Option<String>,
+    limit: Option<usize>,
+}
+
+/// GET /api/sessions/history — every tracked session, ended ones included,
+/// most recently updated first (default 200).
+async fn api_sessions_history(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<SessionHistoryQuery>,
+) -> Json<Value> {
+    let needle = q.q.as_deref().map(str::to_lowercase).filter(|s| !s.is_empty());
+    let sessions: Vec<Value> = state
+        .session_tracker
+        .all()
+        .into_iter()
+        .filter(|s| {
+            needle.as_ref().is_none_or(|n| {
+                [Some(&s.cwd), Some(&s.session_id), s.model.as_ref(), s.last_message.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .any(|f| f.to_lowercase().contains(n.as_str()))
+            })
+        })
+        .take(q.limit.unwrap_or(200))
+        .map(|s| {
+            let mut v = json!(s);
+            v["project"] = json!(crate::tray::project_name(&s.cwd));
+            v
+        })
+        .collect();
+    Json(json!({ "ok": true, "sessions": sessions }))
+}
+
+#[derive(Deserialize)]
+struct UsageQuery {
+    days: Option<u32>,
+}
+
+/// GET /api/usage — token usage per local day over the last `days` (default
+/// 14, at most 90), in total and per project, from the transcripts of
+/// tracked sessions.
+async fn api_usage(State(state): State<Arc<AppState>>, Query(q): Query<UsageQuery>) -> Json<Value> {
+    let days = q.days.unwrap_or(14).clamp(1, 90);
+    let s = state.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        use chrono::TimeZone;
+        let today = chrono::Local::now().date_naive();
+        // Local midnights, oldest first, then now: one bucket per day
+        let starts: Vec<chrono::NaiveDate> = (0..days).rev().map(|d| today - chrono::Days::new(d as u64)).collect();
+        let mut bounds: Vec<f64> = starts
+            .iter()
+            .filter_map(|d| d.and_hms_opt(0, 0, 0))
+            .filter_map(|t| chrono::Local.from_local_datetime(&t).earliest())
+            .map(|t| t.timestamp() as f64)
+            .collect();
+        bounds.push(now_secs());
+        if bounds.len() != starts.len() + 1 {
+            return json!({ "ok": false, "error": "local time conversion failed" });
+        }
+        let mut totals = vec![(0u64, 0u64); starts.len()];
+        let mut projects: HashMap<String, (u64, u64)> = HashMap::new();
+        for info in s.session_tracker.all().iter().filter(|i| i.updated_at >= bounds[0] && !i.cwd.is_empty()) {
+            let buckets = s.chat_reader.usage_between(&info.session_id, &info.cwd, &bounds);
+            let p = projects.entry(crate::tray::project_name(&info.cwd).to_string()).or_default();
+            for (t, u) in totals.iter_mut().zip(&buckets) {
+                t.0 += u.input_tokens;
+                t.1 += u.output_tokens;
+                p.0 += u.input_tokens;
+                p.1 += u.output_tokens;
+            }
+        }
+        let mut by_project: Vec<Value> = projects
+            .into_iter()
+            .filter(|(_, (i, o))| i + o > 0)
+            .map(|(name, (i, o))| json!({ "project": name, "input_tokens": i, "output_tokens": o }))
+            .collect();
+        by_project.sort_by_key(|p| std::cmp::Reverse(p["input_tokens"].as_u64().unwrap_or(0) + p["output_tokens"].as_u64().unwrap_or(0)));
+        let daily: Vec<Value> = starts
+            .iter()
+            .zip(totals)
+            .map(|(d, (i, o))| json!({ "date": d.format("%Y-%m-%d").to_string(), "input_tokens": i, "output_tokens": o }))
+            .collect();
+        json!({ "ok": true, "days": daily, "projects": by_project })
+    })
+    .await;
+    match result {
+        Ok(v) => Json(v),
+        Err(e) => Json(json!({ "ok": false, "error": format!("{}", e) })),
+    }
+}
+
+#[derive(Deserialize)]
+struct ToolStatsQuery {
+    project: Option<String>,
+}
+
+/// GET /api/stats/tools — per project and tool since the app started: runs
+/// and time from the observe-mode PreToolUse hook (`pre_tool` until the
+/// session's next event), failures from the transcripts. `project` narrows
+/// it to a folder name or path.
+async fn api_stats_tools(State(state): State<Arc<AppState>>, Query(q): Query<ToolStatsQuery>) -> Json<Value> {
+    let s = state.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let since = s.tool_stats.since();
+        let mut outcomes = crate::tool_stats::Outcomes::new();
+        for info in s.session_tracker.all().iter().filter(|i| i.updated_at >= since && !i.cwd.is_empty()) {
+            let key = crate::process::cwd_key(&info.cwd);
+            for (tool, (results, errors)) in s.chat_reader.tool_outcomes(&info.session_id, &info.cwd, since) {
+                let o = outcomes.entry((key.clone(), tool)).or_insert_with(|| (info.cwd.clone(), 0, 0));
+                o.1 += results;
+                o.2 += errors;
+            }
+        }
+        let project = q.project.as_deref().map(str::trim).filter(|p| !p.is_empty());
+        json!({
+            "ok": true,
+            "observing": s.config.general.observe_tools,
+            "since": since,
+            "projects": s.tool_stats.snapshot(&outcomes, project),
+        })
+    })
+    .await;
+    match result {
+        Ok(v) => Json(v),
+        Err(e) => Json(json!({ "ok": false, "error": format!("{}", e) })),
+    }
+}
+
+#[derive(Deserialize)]
+struct TimelineQuery {
+    day: Option<String>,
+}
+
+/// GET /api/stats/timeline — the desk's state changes over a local day
+/// (`?day=YYYY-MM-DD`, default today) as segments, with time per state.
+async fn api_stats_timeline(State(state): State<Arc<AppState>>, Query(q): Query<TimelineQuery>) -> Json<Value> {
+    let s = state.clone();
+    let result = tokio::task::spawn_blocking(move || crate::desk_timeline::day(&s, q.day.as_deref())).await;
+    match result {
+        Ok(Ok(v)) => Json(v),
+        Ok(Err(e)) => Json(json!({ "ok": false, "error": e })),
+        Err(e) => Json(json!({ "ok": false, "error": format!("{}", e) })),
+    }
+}
+
+#[derive(Deserialize)]
+struct SessionsQuery {
+    /// Name of a saved view from `general.views`; other fields narrow it further.
+    view: Option<String>,
+    #[serde(flatten)]
+    filter: crate::config::SessionFilter,
+}
+
+/// GET /api/sessions — merged sessions, optionally filtered by `status`,
+/// `project`, `agent_type`, `label` or a saved `view`.
+async fn api_sessions(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<SessionsQuery>,
+) -> Json<Value> {
+    let view = match q.view.as_deref().filter(|v| !v.is_empty()) {
+        Some(name) => match state.config.general.views.iter().find(|v| v.name == name) {
+            Some(v) => Some(&v.filter),
+            None => return Json(json!({ "ok": false, "error": format!("unknown view: {}", name) })),
+        },
+        None => None,
+    };
+    let mut processes = scan_and_merge(&state);
+    processes.retain(|p| q.filter.matches(p) && view.is_none_or(|f| f.matches(p)));
+    let s = state.clone();
+    let processes = tokio::task::spawn_blocking(move || {
+        add_token_rates(&s, &mut processes);
+        add_time_text(&s.config.messages.language, &mut processes);
+        processes
+    })
+    .await
+    .unwrap_or_default();
+    Json(json!({ "processes": processes }))
+}
+
+/// Minutes of history in `token_sparkline`.
+const SPARKLINE_MINUTES: usize = 10;
+
+/// Give working and waiting sessions with a transcript their output rate:
+/// `token_sparkline` (output tokens per minute, oldest first) and
+/// `tokens_per_min` (average of the last 3 minutes).
+fn add_token_rates(state: &AppState, processes: &mut [Value]) {
+    let now = now_secs();
+    for p in processes.iter_mut() {
+        if !matches!(p.get("status").and_then(|v| v.as_str()), Some("active" | "waiting")) {
+            continue;
+        }
+        let sid = p.get("session_id").and_then(|v| v.as_str()).unwrap_or("");
+        let cwd = p.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+        if sid.is_empty() || cwd.is_empty() {
+            continue;
+        }
+        let spark = state.chat_reader.output_per_minute(sid, cwd, now, SPARKLINE_MINUTES);
+        if spark.iter().all(|n| *n == 0) {
+            continue;
+        }
+        let rate = spark[SPARKLINE_MINUTES - 3..].iter().sum::<u64>() / 3;
+        if let Some(obj) = p.as_object_mut() {
+            obj.insert("tokens_per_min".to_string(), json!(rate));
+            obj.insert("token_sparkline".to_string(), json!(spark));
+        }
+    }
+}
+
+/// Give sessions their age as text, next to the raw `uptime` seconds:
+/// `uptime_text` (`12m 30s`) and `started_ago` (`12m ago`), in `language`.
+fn add_time_text(language: &str, processes: &mut [Value]) {
+    for p in processes.iter_mut() {
+        let Some(uptime) = p.get("uptime").and_then(|v| v.as_u64()) else { continue };
+        if let Some(obj) = p.as_object_mut() {
+            obj.insert("uptime_text".to_string(), json!(crate::messages::duration_in(uptime as f64, language)));
+            obj.insert("started_ago".to_string(), json!(crate::messages::ago(uptime as f64, language)));
+        }
+    }
+}
+
+/// GET /api/views — saved session filters for quick filtering.
+async fn api_views(State(state): State<Arc<AppState>>) -> Json<Value> {
+    Json(json!({ "views": state.config.general.views }))
+}
+
+async fn api_status(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let processes = scan_and_merge(&state);
+    let mut status = compute_state(&processes);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let recent = state.event_store.count_after(now - 300.0);
+    let last_seen = *read_lock!(state.last_seen_ts);
+    let unread_count = state.event_store.count_unread_after(last_seen);
+    if let Some(obj) = status.as_object_mut() {
+        obj.insert("recent_events".to_string(), json!(recent));
+        obj.insert("unread_count".to_string(), json!(unread_count));
+    }
+    Json(status)
+}
+
+async fn api_stream(
+    State(state): State<Arc<AppState>>,
+    axum::extract::ConnectInfo(peer): axum::extract::ConnectInfo<std::net::SocketAddr>,
+    headers: axum::http::HeaderMap,
+) -> Sse<impl tokio_stream::Stream<Item = Result<SseEvent, Infallible>>> {
+    let user_agent = headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let (rx, client) = state.sse.connect(peer.to_string(), user_agent);
+    let stream = BroadcastStream::new(rx).map(move |result| match result {
+        Ok(msg) => Ok(SseEvent::default().data(msg)),
+        // Fell behind the ring: tell the client to refetch full state
+        Err(BroadcastStreamRecvError::Lagged(missed)) => Ok(SseEvent::default().data(client.lagged(missed))),
     });
     Sse::new(stream).keep_alive(KeepAlive::default())
 }
@@ -519,9 +1720,351 @@ async fn api_health(State(state): State<Arc<AppState>>) -> Json<Value> {
         "uptime": uptime,
         "sessions": session_count,
         "pending_permissions": pending_permissions,
+        "headless": state.headless.load(Ordering::Relaxed),
+        "sse_clients": state.sse.client_count(),
+    }))
+}
+
+/// Per-endpoint latency histograms collected by `request_log`.
+async fn api_metrics(State(state): State<Arc<AppState>>) -> Json<Value> {
+    Json(json!({
+        "ok": true,
+        "uptime": state.start_time.elapsed().as_secs(),
+        "routes": state.metrics.snapshot(),
+        "sse": state.sse.stats(),
+        "sessions": state.session_tracker.stats(),
+    }))
+}
+
+/// Health of the moving parts: server, hook daemon relay, stores.
+async fn api_diagnostics(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let port = state.config.manager.port;
+    let use_daemon = state.config.manager.use_hook_daemon;
+    let daemon = match tokio::task::spawn_blocking(move || use_daemon.then(|| crate::setup::ping_hook_daemon(port))).await {
+        Ok(None) => json!({ "enabled": false, "reachable": false }),
+        Ok(Some(Ok(mut stats))) => {
+            if let Some(obj) = stats.as_object_mut() {
+                obj.remove("ok");
+                obj.remove("pong");
+                obj.insert("reachable".to_string(), json!(true));
+                // Daemons predating versioning report no protocol
+                let protocol = obj.get("protocol").and_then(|v| v.as_u64());
+                obj.insert("compatible".to_string(), json!(protocol == Some(crate::setup::DAEMON_PROTOCOL)));
+            }
+            stats
+        }
+        Ok(Some(Err(e))) => json!({ "reachable": false, "error": e }),
+        Err(e) => json!({ "reachable": false, "error": format!("{}", e) }),
+    };
+
+    let app_version = env!("CARGO_PKG_VERSION");
+    let hook_version = read_lock!(state.last_hook_version).clone();
+    let daemon_version = daemon.get("version").and_then(|v| v.as_str()).map(str::to_string);
+    let hook = json!({
+        "last_seen_version": if hook_version.is_empty() { Value::Null } else { json!(hook_version) },
+        "daemon_version": daemon_version,
+        "mismatch": (!hook_version.is_empty() && hook_version != app_version)
+            || daemon_version.as_deref().is_some_and(|v| v != app_version),
+    });
+    let tasks = state.heartbeats.tasks();
+    let tasks_stale: Vec<&str> = tasks.iter().filter(|t| t.stale).map(|t| t.name).collect();
+    let s = state.clone();
+    let memory = tokio::task::spawn_blocking(move || s.memory.usage(&s)).await.unwrap_or_default();
+
+    Json(json!({
+        "ok": true,
+        "version": app_version,
+        "uptime": state.start_time.elapsed().as_secs(),
+        "port": port,
+        "daemon": daemon,
+        "hook": hook,
+        "sessions": state.session_tracker.get_active(state.config.general.session_ttl).len(),
+        "pending_permissions": state.permissions.get_pending().len(),
+        "events": state.event_store.count_after(0.0),
+        "rate_limit": state.rate_limiter.stats(),
+        "sse": state.sse.stats(),
+        "hooks_silent": state.hook_health.silent(),
+        "hook_errors": state.hook_health.errors(),
+        "tasks": tasks,
+        "tasks_stale": tasks_stale,
+        "memory": memory,
     }))
 }
 
+/// Single aggregate payload for a monitoring view: per-project counts,
+/// waiting queue, hourly event histogram, today's tokens, permission stats.
+async fn api_dashboard(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let s = state.clone();
+    let result = tokio::task::spawn_blocking(move || build_dashboard(&s)).await;
+    match result {
+        Ok(v) => Json(v),
+        Err(e) => Json(json!({ "ok": false, "error": format!("{}", e) })),
+    }
+}
+
+fn build_dashboard(state: &AppState) -> Value {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    let processes = scan_and_merge(state);
+    let status = compute_state(&processes);
+
+    // --- 1. Per-project session counts (live processes) ---
+    let mut projects: std::collections::BTreeMap<String, (u64, u64, u64)> = std::collections::BTreeMap::new();
+    for p in &processes {
+        let cwd = p.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+        let name = crate::tray::project_name(cwd).to_string();
+        let entry = projects.entry(name).or_default();
+        entry.0 += 1;
+        match p.get("status").and_then(|v| v.as_str()) {
+            Some("active") => entry.1 += 1,
+            Some("waiting") => entry.2 += 1,
+            _ => {}
+        }
+    }
+    let projects: Vec<Value> = projects
+        .into_iter()
+        .map(|(project, (total, active, waiting))| json!({
+            "project": project,
+            "sessions": total,
+            "active": active,
+            "waiting": waiting,
+        }))
+        .collect();
+
+    // --- 2. Waiting queue (longest wait first) ---
+    let tracked = state.session_tracker.get_active(state.config.general.session_ttl);
+    let pending = state.permissions.get_pending();
+    let mut waiting: Vec<Value> = tracked
+        .values()
+        .filter(|i| i.status == SessionStatus::Waiting)
+        .map(|i| {
+            let since = i.timeline.last().map(|c| c.ts).unwrap_or(i.updated_at);
+            let perm = pending.iter().find(|p| p.session_id == i.session_id);
+            json!({
+                "session_id": &i.session_id,
+                "project": crate::tray::project_name(&i.cwd),
+                "cwd": &i.cwd,
+                "since": since,
+                "waiting_secs": (now - since).max(0.0).round(),
+                "reason": i.notification_message.as_deref().unwrap_or(""),
+                "permission": perm.map(|p| json!({ "id": &p.id, "tool_name": &p.tool_name })),
+            })
+        })
+        .collect();
+    waiting.sort_by(|a, b| {
+        let sa = a["since"].as_f64().unwrap_or(0.0);
+        let sb = b["since"].as_f64().unwrap_or(0.0);
+        sa.total_cmp(&sb)
+    });
+
+    // --- 3. Events in the last hour, 5-minute buckets (oldest first) ---
+    const BUCKET_SECS: f64 = 300.0;
+    const BUCKETS: usize = 12;
+    let window_start = now - BUCKET_SECS * BUCKETS as f64;
+    let mut counts = [0u64; BUCKETS];
+    for e in state.event_store.get_events(window_start).iter() {
+        let idx = ((e.ts - window_start) / BUCKET_SECS) as usize;
+        counts[idx.min(BUCKETS - 1)] += 1;
+    }
+    let buckets: Vec<Value> = counts
+        .iter()
+        .enumerate()
+        .map(|(i, n)| json!({ "start": window_start + BUCKET_SECS * i as f64, "count": n }))
+        .collect();
+
+    // --- 4. Token usage since local midnight ---
+    use chrono::TimeZone;
+    let midnight = chrono::Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|t| chrono::Local.from_local_datetime(&t).earliest())
+        .map(|t| t.timestamp() as f64)
+        .unwrap_or(now - 86400.0);
+    let (mut input_tokens, mut output_tokens, mut token_sessions) = (0u64, 0u64, 0u64);
+    for info in tracked.values().filter(|i| i.updated_at >= midnight && !i.cwd.is_empty()) {
+        let u = state.chat_reader.token_usage_since(&info.session_id, &info.cwd, midnight);
+        if u.input_tokens + u.output_tokens > 0 {
+            input_tokens += u.input_tokens;
+            output_tokens += u.output_tokens;
+            token_sessions += 1;
+        }
+    }
+    broadcast_chat_resets(state);
+
+    json!({
+        "ok": true,
+        "generated_at": now,
+        "status": status,
+        "projects": projects,
+        "waiting": waiting,
+        "events_last_hour": {
+            "bucket_secs": BUCKET_SECS,
+            "total": counts.iter().sum::<u64>(),
+            "buckets": buckets,
+        },
+        "tokens_today": {
+            "input_tokens": input_tokens,
+            "output_tokens": output_tokens,
+            "sessions": token_sessions,
+        },
+        "permissions": state.permissions.stats(),
+    })
+}
+
+/// GET /api/inbox — everything that needs the user, most urgent first.
+async fn api_inbox(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let s = state.clone();
+    let result = tokio::task::spawn_blocking(move || build_inbox(&s)).await;
+    match result {
+        Ok(v) => Json(v),
+        Err(e) => Json(json!({ "ok": false, "error": format!("{}", e) })),
+    }
+}
+
+/// Suggested action: what to call to resolve an inbox item.
+fn inbox_action(action: &str, path: &str, body: Value) -> Value {
+    json!({ "action": action, "method": "POST", "path": path, "body": body })
+}
+
+/// Merge pending permissions, waiting and stalled sessions, and unacked
+/// high-level events into one list. At most one item per session: the most
+/// urgent source wins. Sorted by priority (0 = most urgent), then oldest first.
+///
+/// A session idle at its prompt after `Stop` is represented by its unacked
+/// stop event, so acking or dismissing the event clears it from the inbox.
+fn build_inbox(state: &AppState) -> Value {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    let session_ttl = state.config.general.session_ttl;
+    let tracked = state.session_tracker.get_active(session_ttl);
+    // Live sessions only — trackers of closed terminals linger for session_ttl
+    let live: HashMap<String, String> = scan_and_merge(state)
+        .iter()
+        .filter_map(|p| Some((
+            p.get("session_id")?.as_str()?.to_string(),
+            p.get("status")?.as_str()?.to_string(),
+        )))
+        .collect();
+    let mut items: Vec<Value> = Vec::new();
+    let mut covered: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+    let focus = |sid: &str, cwd: &str| {
+        inbox_action("focus", "/api/focus", json!({ "session_id": sid, "cwd": cwd }))
+    };
+    let send = |action: &str, sid: &str, cwd: &str, message: &str, force: bool| {
+        inbox_action(action, "/api/chat/send", json!({
+            "session_id": sid, "cwd": cwd, "message": message, "force": force,
+        }))
+    };
+
+    // --- 1. Pending permissions (blocking the agent right now) ---
+    for p in state.permissions.get_pending() {
+        covered.insert(p.session_id.clone());
+        items.push(json!({
+            "kind": "permission",
+            "priority": 0,
+            "id": format!("perm:{}", p.id),
+            "session_id": &p.session_id,
+            "project": crate::tray::project_name(&p.cwd),
+            "cwd": &p.cwd,
+            "title": format!("Allow {}?", p.tool_name),
+            "since": p.timestamp,
+            "deadline": p.deadline,
+            "actions": [
+                inbox_action("approve", "/api/permission-respond", json!({ "id": &p.id, "decision": "allow" })),
+                inbox_action("deny", "/api/permission-respond", json!({ "id": &p.id, "decision": "deny" })),
+                focus(&p.session_id, &p.cwd),
+            ],
+        }));
+    }
+
+    // --- 2. Terminal-side prompts and stalled sessions ---
+    let stall_secs = state.config.general.stall_secs as f64;
+    for info in tracked.values() {
+        if covered.contains(&info.session_id) || !live.contains_key(&info.session_id) {
+            continue;
+        }
+        let sid = info.session_id.as_str();
+        let since = info.timeline.last().map(|c| c.ts).unwrap_or(info.updated_at);
+        let project = crate::tray::project_name(&info.cwd);
+        let mut item = match info.status {
+            // Claude Code is showing a prompt in the terminal (no hook-side request)
+            SessionStatus::Waiting if matches!(info.waiting_reason, Some(WaitingReason::Permission | WaitingReason::Plan)) => json!({
+                "kind": "waiting",
+                "priority": 1,
+                "title": info.notification_message.as_deref().filter(|m| !m.is_empty()).unwrap_or("Needs approval in terminal"),
+                "actions": [focus(sid, &info.cwd)],
+            }),
+            SessionStatus::Active if stall_secs > 0.0 && now - info.updated_at >= stall_secs => json!({
+                "kind": "stalled",
+                "priority": 2,
+                "title": format!("No activity for {} min", ((now - info.updated_at) / 60.0) as u64),
+                "actions": [
+                    focus(sid, &info.cwd),
+                    send("continue", sid, &info.cwd, "continue", true),
+                ],
+            }),
+            _ => continue,
+        };
+        if let Some(obj) = item.as_object_mut() {
+            obj.insert("id".into(), json!(format!("session:{}", sid)));
+            obj.insert("session_id".into(), json!(sid));
+            obj.insert("project".into(), json!(project));
+            obj.insert("cwd".into(), json!(&info.cwd));
+            obj.insert("since".into(), json!(since));
+        }
+        covered.insert(info.session_id.clone());
+        items.push(item);
+    }
+
+    // --- 3. Unacked stop/notification events (newest per session) ---
+    for e in state.event_store.get_events(now - session_ttl as f64).iter().rev() {
+        if e.acked || e.level < 2 || covered.contains(&e.session_id) {
+            continue;
+        }
+        covered.insert(e.session_id.clone());
+        let waiting = live.get(&e.session_id).is_some_and(|s| s == "waiting");
+        let mut actions = vec![
+            inbox_action("ack", &format!("/api/events/{}/ack", e.id), json!({})),
+            inbox_action("dismiss", &format!("/api/events/{}/dismiss", e.id), json!({})),
+        ];
+        // External events have no terminal behind them
+        if e.source.is_empty() {
+            actions.push(focus(&e.session_id, &e.cwd));
+        }
+        if waiting {
+            actions.push(send("continue", &e.session_id, &e.cwd, "continue", false));
+        }
+        items.push(json!({
+            "kind": if waiting { "waiting" } else { "event" },
+            "priority": if waiting { 3 } else { 4 },
+            "id": format!("event:{}", e.id),
+            "session_id": &e.session_id,
+            "project": crate::tray::project_name(&e.cwd),
+            "cwd": &e.cwd,
+            "title": &e.message,
+            "since": e.ts,
+            "event": &e.event,
+            "last_message": display_message(&e.last_assistant_message),
+            "source": &e.source,
+            "actions": actions,
+        }));
+    }
+
+    items.sort_by(|a, b| {
+        let pa = a["priority"].as_u64().unwrap_or(u64::MAX);
+        let pb = b["priority"].as_u64().unwrap_or(u64::MAX);
+        let sa = a["since"].as_f64().unwrap_or(0.0);
+        let sb = b["since"].as_f64().unwrap_or(0.0);
+        pa.cmp(&pb).then(sa.total_cmp(&sb))
+    });
+
+    json!({
+        "ok": true,
+        "generated_at": now,
+        "count": items.len(),
+        "items": items,
+    })
+}
+
 #[derive(Deserialize)]
 struct HookQuery {
     event: Option<HookEvent>,
@@ -536,11 +2079,22 @@ async fn api_hook(
         Ok(Json(p)) => p,
         Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
     };
+    if state.ignore_paths.matches(&payload.cwd) {
+        return Json(json!({ "ok": true, "ignored": true }));
+    }
+    note_hook_version(&state, payload.hook_pid, payload.hook_version.as_deref());
+    state.scan_wake.notify_waiters();
     let event = q.event.as_ref();
     let sid = &payload.session_id;
+    if !payload.correlation_id.is_empty() {
+        tracing::debug!("[{}] hook {} {}", payload.correlation_id, event.map(|e| e.to_string()).unwrap_or_default(), sid);
+    }
     let cwd = &payload.cwd;
+    // Tools can legitimately start back to back, so observed `pre_tool`
+    // hooks are never deduplicated
+    let tool = payload.tool_name.as_deref().filter(|t| !t.is_empty());
     // Dedup: skip if same session+event within 500ms window
-    if let Some(ev) = event {
+    if let Some(ev) = event.filter(|_| tool.is_none()) {
         if !sid.is_empty() {
             let dedup_key = format!("{}:{}", sid, ev);
             let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
@@ -564,15 +2118,25 @@ async fn api_hook(
                 notification_type: Some(String::new()),
                 notification_message: Some(String::new()),
                 agent_pid: payload.agent_pid,
+                seq: payload.hook_seq,
+                // A new prompt starts without a tool
+                current_tool: Some(tool.map(str::to_string)),
                 ..Default::default()
             },
         );
+        let at = hook_time(payload.hook_seq);
+        match tool {
+            Some(t) => state.tool_stats.start(sid, cwd, t, at),
+            None => state.tool_stats.finish(sid, at),
+        }
         state.sse.broadcast(
             "activity",
             json!({
                 "event": event,
                 "session_id": sid,
                 "cwd": cwd,
+                "color": state.session_tracker.color(sid),
+                "tool": tool,
             }),
         );
     }
@@ -584,35 +2148,475 @@ async fn api_hook(
 ///
 /// Called by hook scripts (notify_claude.py / notify_codex.py) via POST /api/signal.
 /// Pipeline: session update → event log → SSE broadcast → remote channels.
+#[derive(Deserialize)]
+struct SignalQuery {
+    agent: Option<String>,
+}
+
+/// The agent's adapter maps its payload onto the unified signal. The agent
+/// comes from `?agent=`, else the body's `agent`, else `claude_code`.
 async fn api_signal(
     State(state): State<Arc<AppState>>,
-    body: Result<Json<SignalPayload>, JsonRejection>,
+    Query(q): Query<SignalQuery>,
+    body: Result<Json<Value>, JsonRejection>,
 ) -> Json<Value> {
-    let payload = match body {
-        Ok(Json(p)) => p,
+    let raw = match body {
+        Ok(Json(v)) => v,
         Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
     };
-    let event = &payload.event;
+    let Some(event) = raw.get("event").and_then(|v| v.as_str()) else {
+        return Json(json!({ "ok": false, "error": "missing field `event`" }));
+    };
+    let agent = q.agent.as_deref()
+        .or_else(|| raw.get("agent").and_then(|v| v.as_str()))
+        .unwrap_or("claude_code");
+    let Some(payload) = state.registry.normalize(agent, event, &raw) else {
+        return Json(json!({ "ok": false, "error": format!("unknown agent: {}", agent) }));
+    };
+    handle_signal(state, payload).await
+}
+
+/// On SessionStart, fold the session this one resumes into it. With
+/// `source: resume` the CWD is enough to find it; without a source (older
+/// Claude Code) only transcript continuity counts.
+async fn merge_resumed_session(state: &Arc<AppState>, payload: &SignalPayload) -> bool {
+    let resume = payload.source == "resume";
+    if !resume && !payload.source.is_empty() {
+        return false; // startup / clear / compact
+    }
+    let (sid, cwd, transcript) = (payload.session_id.clone(), payload.cwd.clone(), payload.transcript_path.clone());
+    let s = state.clone();
+    let prior = tokio::task::spawn_blocking(move || s.chat_reader.prior_session_ids(&sid, &cwd, &transcript))
+        .await
+        .unwrap_or_default();
     let sid = &payload.session_id;
-    let cwd = &payload.cwd;
-    let ntype = &payload.notification_type;
-    let nmsg = &payload.message;
-    let last_msg = &payload.last_assistant_message;
-    let model = &payload.model;
+    let Some(old) = state.session_tracker.find_resumed(sid, &payload.cwd, payload.agent_pid, &prior, resume) else {
+        return false;
+    };
+    if !state.session_tracker.merge_resumed(&old, sid) {
+        return false;
+    }
+    let moved = {
+        let (s, old, new) = (state.clone(), old.clone(), sid.clone());
+        tokio::task::spawn_blocking(move || s.event_store.reassign_session(&old, &new))
+            .await
+            .unwrap_or(0)
+    };
+    tracing::info!("Session {} resumes {} — merged ({} events moved)", sid, old, moved);
+    state.sse.broadcast("session_resumed", json!({ "session_id": sid, "resumed_from": old }));
+    true
+}
 
-    // --- 1. Update session state ---
-    if !sid.is_empty() {
-        match event {
-            HookEvent::SessionStart => {
-                state.session_tracker.register(
-                    sid,
-                    cwd,
-                    if model.is_empty() { None } else { Some(model.as_str()) },
-                    payload.agent_pid,
-                );
-                // Link sub-agent to parent if parent_session_id is present
-                if let Some(ref parent_id) = payload.parent_session_id {
-                    if !parent_id.is_empty() {
+/// A new session in the CWD of one that ended at most this long ago is
+/// taken to continue its work.
+const HANDOFF_WINDOW_SECS: f64 = 60.0;
+
+/// Seconds between process scans while agents run or a session is active.
+const SCAN_SECS: u64 = 5;
+
+/// Whether any session is working, e.g. a remote run the scanner can't see.
+fn has_active_session(state: &AppState) -> bool {
+    state
+        .session_tracker
+        .get_active(state.config.general.session_ttl)
+        .values()
+        .any(|s| s.status == SessionStatus::Active)
+}
+
+/// An agent process replaced by a new one in the same CWD within this long
+/// counts as a restart of the same agent.
+const RESTART_WINDOW: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Keep the restarted agent's session on its new PID and log a `restarted`
+/// event carrying both PIDs.
+async fn handle_agent_restart(state: &Arc<AppState>, r: crate::process::Restart) {
+    let Some(sid) = state.session_tracker.adopt_restarted(r.old_pid, r.new_pid, &r.cwd) else {
+        tracing::debug!("Agent {} in {} restarted: PID {} -> {}, no session to move", r.agent_type, r.cwd, r.old_pid, r.new_pid);
+        return;
+    };
+    tracing::info!("Agent {} in {} restarted: PID {} -> {} (session {})", r.agent_type, r.cwd, r.old_pid, r.new_pid, sid);
+
+    let message = format!(
+        "[Restarted] {} | PID {} \u{2192} {}",
+        crate::tray::project_name(&r.cwd), r.old_pid, r.new_pid,
+    );
+    let evt = Event::new(HookEvent::Restarted, &sid, &r.cwd, &message, 2);
+    {
+        let s = state.clone();
+        let _ = tokio::task::spawn_blocking(move || s.event_store.append_event(evt)).await;
+    }
+    state.sse.broadcast("event", json!({
+        "event": HookEvent::Restarted,
+        "session_id": &sid,
+        "cwd": &r.cwd,
+        "message": &message,
+        "old_pid": r.old_pid,
+        "new_pid": r.new_pid,
+    }));
+    let _ = state.notify_tray.send(());
+}
+
+/// Log a `hooks_silent` event for each agent that has run for
+/// `general.hook_silence_secs` without sending a hook event.
+async fn check_hook_silence(state: &Arc<AppState>, procs: &[crate::process::ProcessInfo]) {
+    let after = state.config.general.hook_silence_secs;
+    if after == 0 {
+        return;
+    }
+    // Only agents whose hooks fire on every session (not just turn ends)
+    let hooked = |agent: &str| state.registry.capabilities(agent).is_some_and(|c| c.supports_permissions);
+    let fresh = state.hook_health.check(procs, hooked, after);
+    for a in fresh {
+        tracing::warn!("{} PID {} in {} has sent no hook events in {} s — hooks not configured?", a.agent_type, a.pid, a.cwd, a.uptime);
+        let message = format!(
+            "[Hooks silent] {} | {} PID {} running {} min with no hook events \u{2014} check settings.json",
+            crate::tray::project_name(&a.cwd), a.agent_type, a.pid, a.uptime / 60,
+        );
+        let evt = Event::new(HookEvent::HooksSilent, "", &a.cwd, &message, 2);
+        {
+            let s = state.clone();
+            let _ = tokio::task::spawn_blocking(move || s.event_store.append_event(evt)).await;
+        }
+        state.sse.broadcast("event", json!({
+            "event": HookEvent::HooksSilent,
+            "cwd": &a.cwd,
+            "message": &message,
+            "pid": a.pid,
+        }));
+        let _ = state.notify_tray.send(());
+    }
+}
+
+/// Log a `hook_error` event per project for requests the hook binary
+/// failed to deliver, as read from its error spool.
+async fn report_hook_errors(state: &Arc<AppState>, errors: Vec<crate::hook_health::HookError>) {
+    if errors.is_empty() {
+        return;
+    }
+    state.hook_health.record_errors(&errors);
+    let mut by_cwd: std::collections::BTreeMap<String, Vec<crate::hook_health::HookError>> = std::collections::BTreeMap::new();
+    for e in errors {
+        by_cwd.entry(e.cwd.clone()).or_default().push(e);
+    }
+    for (cwd, errors) in by_cwd {
+        let last = &errors[errors.len() - 1];
+        tracing::warn!("{} hook request(s) from {} failed, last: {} via {}: {}", errors.len(), cwd, last.event, last.source, last.error);
+        let project = if cwd.is_empty() { "unknown project" } else { crate::tray::project_name(&cwd) };
+        let message = format!(
+            "[Hook error] {} | {} hook call(s) failed, last: {} via {}: {}",
+            project, errors.len(), last.event, last.source, last.error,
+        );
+        let evt = Event {
+            source: last.source.clone(),
+            correlation_id: last.correlation_id.clone(),
+            ..Event::new(HookEvent::HookError, &last.session_id, &cwd, &message, 2)
+        };
+        {
+            let s = state.clone();
+            let _ = tokio::task::spawn_blocking(move || s.event_store.append_event(evt)).await;
+        }
+        state.sse.broadcast("event", json!({
+            "event": HookEvent::HookError,
+            "cwd": &cwd,
+            "message": &message,
+            "count": errors.len(),
+        }));
+        let _ = state.notify_tray.send(());
+    }
+}
+
+/// Compare the transcript usage of tracked sessions with the `budget`
+/// limits; each threshold crossed logs a `budget_alert` event, shows a
+/// toast and goes to the remote channels.
+async fn check_budgets(state: &Arc<AppState>) {
+    let crossings = {
+        let s = state.clone();
+        tokio::task::spawn_blocking(move || {
+            let usage: Vec<crate::budget::SessionUsage> = s
+                .session_tracker
+                .get_active(s.config.general.session_ttl)
+                .into_values()
+                .filter(|i| !i.cwd.is_empty())
+                .map(|i| {
+                    let u = s.chat_reader.token_usage_since(&i.session_id, &i.cwd, 0.0);
+                    crate::budget::SessionUsage {
+                        session_id: i.session_id,
+                        cwd: i.cwd,
+                        model: i.model,
+                        input_tokens: u.input_tokens,
+                        output_tokens: u.output_tokens,
+                    }
+                })
+                .collect();
+            s.budget.update(&s.config.budget, &usage)
+        })
+        .await
+        .unwrap_or_default()
+    };
+    for c in crossings {
+        let st = &c.status;
+        let project = crate::tray::project_name(&st.cwd);
+        let what = if st.scope == "session" { format!("session {}", st.name) } else { "project".to_string() };
+        let message = format!(
+            "[Budget] {} | {} at {:.0}% ({}){}",
+            project, what, st.percent, crate::budget::describe(st),
+            if st.paused { " \u{2014} permissions paused until acknowledged" } else { "" },
+        );
+        tracing::warn!("{}", message);
+        let evt = Event::new(HookEvent::BudgetAlert, st.key.strip_prefix("session:").unwrap_or(""), &st.cwd, &message, if c.at_limit { 3 } else { 2 });
+        {
+            let s = state.clone();
+            let _ = tokio::task::spawn_blocking(move || s.event_store.append_event(evt)).await;
+        }
+        state.sse.broadcast("event", json!({
+            "event": HookEvent::BudgetAlert,
+            "cwd": &st.cwd,
+            "message": &message,
+            "budget": st,
+        }));
+        let _ = state.notify_tray.send(());
+
+        let remote_now = state.config.presence.escalate_after_secs == 0 || state.presence.is_escalated();
+        if can_toast(state) {
+            // 💰 预算用尽 / 预算 80% — project
+            let title = if c.at_limit {
+                format!("\u{1f4b0} \u{9884}\u{7b97}\u{7528}\u{5c3d} \u{2014} {}", project)
+            } else {
+                format!("\u{1f4b0} \u{9884}\u{7b97} {:.0}% \u{2014} {}", st.percent, project)
+            };
+            if state.presence.is_away() {
+                let queued = if remote_now { String::new() } else { message.clone() };
+                state.presence.defer(title, queued);
+            } else if batching(state) {
+                hold_toast(state, title);
+            } else {
+                let sound = state.live_sound_enabled.load(Ordering::Relaxed)
+                    .then(|| read_lock!(state.live_sound_notification).clone());
+                show_toast(state, &title, &message, sound, Some(ToastKey { group: "budget", tag: &st.key }), "");
+            }
+        }
+        if remote_now {
+            let cfg = Arc::clone(&state.config);
+            let clients = state.http_clients.clone();
+            let msg = remote::RemoteMessage::new(&HookEvent::BudgetAlert.to_string(), message);
+            tokio::spawn(async move {
+                remote::dispatch_remote(&cfg, &clients, &msg).await;
+            });
+        }
+    }
+}
+
+/// Append an entry for the session that just ended to its project's
+/// journal (see journal.rs). Blocking: reads the transcript.
+fn write_journal(state: &AppState, sid: &str, cwd: &str) {
+    let Some(info) = state.session_tracker.get(sid) else { return };
+    let (prompts, files) = state.chat_reader.prompts_and_files(sid, cwd);
+    let entry = crate::journal::Entry {
+        session_id: sid,
+        cwd,
+        started_at: info.started_at,
+        ended_at: now_secs(),
+        model: info.model.as_deref().unwrap_or(""),
+        prompts,
+        files: &files,
+        summary: info.last_message.as_deref().unwrap_or(""),
+    };
+    match crate::journal::append(&state.config.journal, &entry) {
+        Ok(path) => tracing::debug!("Session {} added to {}", sid, path.display()),
+        Err(e) => tracing::warn!("Journal entry for session {} not written: {}", sid, e),
+    }
+}
+
+/// Write a session's whole transcript as markdown (see chat_export.rs)
+/// and return the file's path. Blocking: reads the transcript.
+pub fn export_chat(state: &AppState, session_id: &str) -> Result<std::path::PathBuf, String> {
+    let sid = state.session_tracker.resolve_short_id(session_id).unwrap_or_else(|| session_id.to_string());
+    let info = state.session_tracker.get(&sid);
+    let cwd = info.as_ref().map(|i| i.cwd.clone()).unwrap_or_default();
+    let page = state.chat_reader.read_enriched_before(&sid, &cwd, usize::MAX, usize::MAX);
+    if page.messages.is_empty() {
+        return Err(format!("no transcript for session {}", session_id));
+    }
+    let model = info.and_then(|i| i.model).unwrap_or_default();
+    let content = crate::chat_export::render(&sid, &cwd, &model, &page.messages);
+    let path = crate::chat_export::write(&sid, &cwd, &content).map_err(|e| format!("export not written: {}", e))?;
+    tracing::info!("Chat of session {} exported to {}", sid, path.display());
+    Ok(path)
+}
+
+/// Look for `sensitive` tool calls in the transcripts of sessions active
+/// in the last minute, which also show calls the agent made without the
+/// pre-tool-check hook seeing them.
+async fn scan_sensitive(state: &Arc<AppState>) {
+    let since = now_secs() - state.start_time.elapsed().as_secs_f64();
+    let s = state.clone();
+    let hits = tokio::task::spawn_blocking(move || {
+        let active = s.session_tracker.get_active(60);
+        s.sensitive.retain_sessions(|sid| active.contains_key(sid));
+        let mut hits = Vec::new();
+        for (sid, info) in &active {
+            let page = s.chat_reader.read_enriched(sid, &info.cwd, s.sensitive.scanned(sid), None);
+            for hit in s.sensitive.scan(sid, &page.messages, page.next_index, since) {
+                hits.push((sid.clone(), info.cwd.clone(), hit));
+            }
+        }
+        hits
+    })
+    .await
+    .unwrap_or_default();
+    for (sid, cwd, hit) in hits {
+        report_sensitive(state, &sid, &cwd, &hit).await;
+    }
+}
+
+/// Log a `sensitive_command` event for `hit`, with a toast and a remote
+/// message, unless the same call was just reported.
+async fn report_sensitive(state: &Arc<AppState>, sid: &str, cwd: &str, hit: &crate::sensitive::Hit) {
+    use crate::sensitive::Level;
+
+    let now = now_secs();
+    if !state.sensitive.first_report(sid, &hit.text, now) {
+        return;
+    }
+    let project = crate::tray::project_name(cwd);
+    let message = format!(
+        "[Sensitive] {} | {}\nmatches `{}`{}",
+        project,
+        crate::messages::truncate(&hit.text, 300),
+        hit.pattern,
+        if hit.level == Level::Confirm { " \u{2014} confirmation required" } else { "" },
+    );
+    tracing::warn!("Sensitive command in session {}: {}", sid, message);
+    let evt = Event::new(HookEvent::SensitiveCommand, sid, cwd, &message, 3);
+    {
+        let s = state.clone();
+        let _ = tokio::task::spawn_blocking(move || s.event_store.append_event(evt)).await;
+    }
+    state.sse.broadcast("event", json!({
+        "event": HookEvent::SensitiveCommand,
+        "session_id": sid,
+        "cwd": cwd,
+        "message": &message,
+        "pattern": &hit.pattern,
+    }));
+    let _ = state.notify_tray.send(());
+
+    // Not held for attention batches: it may need stopping now
+    let remote_now = state.config.presence.escalate_after_secs == 0 || state.presence.is_escalated();
+    let muted = state.session_tracker.remote_muted(sid);
+    if can_toast(state) {
+        // ⚠ 敏感命令 — project
+        let title = format!("\u{26a0} \u{654f}\u{611f}\u{547d}\u{4ee4} \u{2014} {}", project);
+        if state.presence.is_away() {
+            let queued = if remote_now || muted { String::new() } else { message.clone() };
+            state.presence.defer(title, queued);
+        } else {
+            let sound = state.live_sound_enabled.load(Ordering::Relaxed)
+                .then(|| read_lock!(state.live_sound_notification).clone());
+            show_toast(state, &title, &hit.text, sound, Some(ToastKey { group: sid, tag: "sensitive" }), "");
+        }
+    }
+    if remote_now && !muted {
+        let cfg = Arc::clone(&state.config);
+        let clients = state.http_clients.clone();
+        let mut msg = remote::RemoteMessage::new(&HookEvent::SensitiveCommand.to_string(), message);
+        msg.link = crate::deep_link::session_link(&state.config, sid).unwrap_or_default();
+        tokio::spawn(async move {
+            remote::dispatch_remote(&cfg, &clients, &msg).await;
+        });
+    }
+}
+
+/// Record the model of the session's latest transcript message and, if it
+/// differs from the one on record (e.g. an Opus → Sonnet fallback), log a
+/// `model_changed` event.
+async fn track_model_change(state: &Arc<AppState>, sid: &str, cwd: &str) {
+    let latest = {
+        let (s, sid, cwd) = (state.clone(), sid.to_string(), cwd.to_string());
+        tokio::task::spawn_blocking(move || s.chat_reader.latest_model(&sid, &cwd))
+            .await
+            .ok()
+            .flatten()
+    };
+    let Some(latest) = latest else { return };
+    let previous = state.session_tracker.model(sid);
+    if previous.as_deref() == Some(latest.as_str()) {
+        return;
+    }
+    state.session_tracker.update(sid, SessionUpdate { model: Some(latest.clone()), ..Default::default() });
+    // First model seen for the session is not a change
+    let Some(previous) = previous else { return };
+
+    tracing::info!("Session {} switched model: {} -> {}", sid, previous, latest);
+    let short_sid = if sid.len() > 8 { &sid[..8] } else { sid };
+    let message = format!("[Model] {} | {} \u{2192} {}", short_sid, previous, latest);
+    let evt = Event::new(HookEvent::ModelChanged, sid, cwd, &message, 2);
+    {
+        let s = state.clone();
+        let _ = tokio::task::spawn_blocking(move || s.event_store.append_event(evt)).await;
+    }
+    state.sse.broadcast("event", json!({
+        "event": HookEvent::ModelChanged,
+        "session_id": sid,
+        "cwd": cwd,
+        "message": &message,
+    }));
+    state.sse.broadcast("model_changed", json!({ "session_id": sid, "from": previous, "to": latest }));
+}
+
+/// The signal pipeline: session state, event log, SSE, tray, toast/sound and
+/// remote channels. Shared by hook signals and web agent reports.
+async fn handle_signal(state: Arc<AppState>, mut payload: SignalPayload) -> Json<Value> {
+    if state.ignore_paths.matches(&payload.cwd) {
+        tracing::debug!("{} from ignored project {} dropped", payload.event, payload.cwd);
+        return Json(json!({ "ok": true, "ignored": true }));
+    }
+    // Old hook binaries and the server's own signals send none
+    if payload.correlation_id.is_empty() {
+        payload.correlation_id = format!("s-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    }
+    // A usage limit arrives as a notification or as the turn's last message
+    let limit = match payload.event {
+        HookEvent::Notification => crate::limits::detect(&payload.message),
+        HookEvent::Stop => crate::limits::detect(&payload.last_assistant_message),
+        _ => None,
+    };
+    if let Some(l) = &limit {
+        tracing::info!("Session {} hit a usage limit (resets {:?})", payload.session_id, l.resets_at);
+        payload.event = HookEvent::LimitReached;
+        payload.notification_type = "limit_reached".to_string();
+        payload.message = l.message.clone();
+    }
+    state.hook_health.record(&payload.cwd, now_secs());
+    state.scan_wake.notify_waiters();
+    let event = &payload.event;
+    let sid = &payload.session_id;
+    let cwd = &payload.cwd;
+    let ntype = &payload.notification_type;
+    let nmsg = &payload.message;
+    let last_msg = &payload.last_assistant_message;
+    let model = &payload.model;
+    let cid = &payload.correlation_id;
+    tracing::debug!("[{}] {} {}", cid, event, sid);
+    note_hook_version(&state, payload.hook_pid, payload.hook_version.as_deref());
+    state.chat_reader.remember_path(sid, &payload.transcript_path);
+    if matches!(event, HookEvent::Stop | HookEvent::LimitReached | HookEvent::SessionEnd) {
+        state.tool_stats.finish(sid, hook_time(payload.hook_seq));
+    }
+
+    // --- 1. Update session state ---
+    if !sid.is_empty() {
+        match event {
+            HookEvent::SessionStart => {
+                state.session_tracker.register(
+                    sid,
+                    cwd,
+                    if model.is_empty() { None } else { Some(model.as_str()) },
+                    payload.agent_pid,
+                );
+                // Link sub-agent to parent if parent_session_id is present
+                if let Some(ref parent_id) = payload.parent_session_id {
+                    if !parent_id.is_empty() {
                         state.session_tracker.update(
                             sid,
                             SessionUpdate {
@@ -622,6 +2626,18 @@ async fn api_signal(
                         );
                     }
                 }
+                // `claude --resume` continues a conversation under a new id;
+                // a fresh top-level session right after another ended in the
+                // same CWD is taken as a handoff
+                let resumed = merge_resumed_session(&state, &payload).await;
+                let top_level = payload.parent_session_id.as_deref().is_none_or(str::is_empty);
+                let handoff = (!resumed && top_level)
+                    .then(|| state.session_tracker.infer_predecessor(sid, cwd, HANDOFF_WINDOW_SECS))
+                    .flatten();
+                if let Some(pred) = handoff {
+                    tracing::info!("Session {} continues {} (same CWD, started within {}s)", sid, pred, HANDOFF_WINDOW_SECS);
+                    state.sse.broadcast("session_linked", json!({ "session_id": sid, "predecessor": pred, "inferred": true }));
+                }
             }
             HookEvent::SessionEnd => {
                 state.session_tracker.update(
@@ -629,10 +2645,15 @@ async fn api_signal(
                     SessionUpdate {
                         status: Some(SessionStatus::Ended),
                         cwd: Some(cwd.clone()),
+                        seq: payload.hook_seq,
                         ..Default::default()
                     },
                 );
                 state.permissions.clear_session_rules(sid);
+                if state.config.journal.enabled && !cwd.is_empty() {
+                    let (s, sid, cwd) = (state.clone(), sid.clone(), cwd.clone());
+                    tokio::task::spawn_blocking(move || write_journal(&s, &sid, &cwd));
+                }
             }
             HookEvent::Stop => {
                 state.session_tracker.update(
@@ -648,6 +2669,24 @@ async fn api_signal(
                         // Clear notification on stop (back to prompt)
                         notification_type: Some(String::new()),
                         notification_message: Some(String::new()),
+                        waiting_reason: Some(Some(WaitingReason::Finished)),
+                        limit_resets_at: Some(None),
+                        seq: payload.hook_seq,
+                        ..Default::default()
+                    },
+                );
+            }
+            HookEvent::LimitReached => {
+                state.session_tracker.update(
+                    sid,
+                    SessionUpdate {
+                        status: Some(SessionStatus::Idle),
+                        cwd: Some(cwd.clone()),
+                        notification_type: Some(ntype.clone()),
+                        notification_message: Some(nmsg.clone()),
+                        waiting_reason: Some(None),
+                        limit_resets_at: Some(limit.as_ref().and_then(|l| l.resets_at)),
+                        seq: payload.hook_seq,
                         ..Default::default()
                     },
                 );
@@ -658,6 +2697,8 @@ async fn api_signal(
                 } else {
                     SessionStatus::Idle
                 };
+                let tool = state.session_tracker.current_tool(sid);
+                let reason = WaitingReason::from_notification(ntype, nmsg, tool.as_deref());
                 state.session_tracker.update(
                     sid,
                     SessionUpdate {
@@ -673,6 +2714,8 @@ async fn api_signal(
                         } else {
                             Some(nmsg.clone())
                         },
+                        waiting_reason: Some(Some(reason)),
+                        seq: payload.hook_seq,
                         ..Default::default()
                     },
                 );
@@ -680,47 +2723,51 @@ async fn api_signal(
             _ => {}
         }
 
-        // Store agent PID on every event (catches sessions where SessionStart was missed)
-        if let Some(apid) = payload.agent_pid {
+        // Store agent PID and type on every event (catches sessions where SessionStart was missed)
+        let agent_type = (!payload.agent.is_empty()).then(|| payload.agent.clone());
+        if payload.agent_pid.is_some() || agent_type.is_some() {
             state.session_tracker.update(
                 sid,
                 SessionUpdate {
-                    agent_pid: Some(apid),
+                    agent_pid: payload.agent_pid,
+                    agent_type,
                     ..Default::default()
                 },
             );
         }
     }
 
+    // A turn ended: see whether the transcript switched models
+    if *event == HookEvent::Stop && !sid.is_empty() && !cwd.is_empty() {
+        track_model_change(&state, sid, cwd).await;
+    }
+
     // --- 2. Format human-readable message ---
     let short_sid = if sid.len() > 8 { &sid[..8] } else { sid.as_str() };
-    let message = format_event_message(event, short_sid, cwd, ntype, nmsg, last_msg, model);
+    let text = crate::messages::EventText {
+        sid: short_sid,
+        cwd,
+        message: if *event == HookEvent::Stop { last_msg } else { nmsg },
+        model,
+        ..crate::messages::EventText::new(event, ntype)
+    };
+    let message = crate::messages::message(&state.config.messages, &text);
 
     // --- 3. Append to event log ---
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs_f64();
-    let short_id = &uuid::Uuid::new_v4().to_string()[..6];
     let level = match event {
         HookEvent::SessionStart | HookEvent::SessionEnd => 1,
         HookEvent::Stop => 2,
-        HookEvent::Notification => 3,
+        HookEvent::Notification | HookEvent::LimitReached => 3,
         _ => 1,
     };
 
     let evt = Event {
-        id: format!("evt_{}_{}", now as u64, short_id),
-        ts: now,
-        event: event.clone(),
-        session_id: sid.clone(),
-        cwd: cwd.clone(),
-        message: message.clone(),
         notification_type: ntype.clone(),
         last_assistant_message: last_msg.clone(),
-        level,
-        cleared: false,
+        correlation_id: cid.clone(),
+        ..Event::new(event.clone(), sid, cwd, &message, level)
     };
+    let now = evt.ts;
     {
         let s = state.clone();
         let _ = tokio::task::spawn_blocking(move || {
@@ -729,6 +2776,7 @@ async fn api_signal(
     }
 
     // --- 4. SSE broadcast ---
+    let color = state.session_tracker.color(sid).unwrap_or_else(|| crate::session::cwd_color(cwd).to_string());
     state.sse.broadcast(
         "event",
         json!({
@@ -736,6 +2784,8 @@ async fn api_signal(
             "session_id": sid,
             "cwd": cwd,
             "message": &message,
+            "color": &color,
+            "correlation_id": cid,
         }),
     );
 
@@ -743,171 +2793,878 @@ async fn api_signal(
     let _ = state.notify_tray.send(());
 
     // --- 6. Windows toast notification for stop and notification events ---
-    if *event == HookEvent::Stop || *event == HookEvent::Notification {
-        if let Some(handle) = state.app_handle.get() {
-            let proj = cwd.rsplit(['/', '\\']).next().unwrap_or(cwd);
-            let (title, toast_body) = match event {
-                HookEvent::Stop => {
-                    let truncated = if last_msg.chars().count() > 200 {
-                        format!("{}...", last_msg.chars().take(197).collect::<String>())
-                    } else {
-                        last_msg.to_string()
-                    };
-                    (format!("\u{2705} \u{4efb}\u{52a1}\u{5b8c}\u{6210} \u{2014} {}", proj), truncated)
-                    // ✅ 任务完成 — project
-                }
-                HookEvent::Notification => match ntype.as_str() {
-                    "permission_prompt" => {
-                        (format!("\u{1f514} \u{9700}\u{8981}\u{64cd}\u{4f5c} \u{2014} {}", proj), nmsg.to_string())
-                        // 🔔 需要操作 — project
-                    }
-                    "idle_prompt" => {
-                        (format!("\u{1f4a4} \u{7b49}\u{5f85}\u{8f93}\u{5165} \u{2014} {}", proj),
-                         "\u{7b49}\u{5f85}\u{8f93}\u{5165}\u{4e2d}...".to_string())
-                        // 💤 等待输入 — project
-                    }
-                    _ => {
-                        (format!("\u{1f4e2} \u{901a}\u{77e5} \u{2014} {}", proj), nmsg.to_string())
-                        // 📢 通知 — project
-                    }
-                },
-                _ => (String::new(), String::new()),
+    // While the user is away, toasts and sounds are deferred (see presence.rs)
+    let away = state.presence.is_away();
+    let mut deferred_title = None;
+    let notify = matches!(event, HookEvent::Stop | HookEvent::Notification | HookEvent::LimitReached);
+    // Skip both when the session's terminal is the foreground window
+    let agent_pid = payload.agent_pid
+        .or_else(|| state.session_tracker.agent_pid(sid));
+    let focused = match agent_pid {
+        Some(pid) if notify && !away && state.config.island.suppress_when_focused => {
+            tokio::task::spawn_blocking(move || crate::focus::is_session_foreground(pid))
+                .await
+                .unwrap_or(false)
+        }
+        _ => false,
+    };
+    if focused {
+        tracing::debug!("[{}] Session {} terminal is in the foreground — toast and sound skipped", cid, sid);
+    }
+    // Held for the next attention batch, unless a permission prompt
+    let batched = notify && !focused && !away && batching(&state) && ntype != "permission_prompt";
+    if notify && !focused {
+        if can_toast(&state) {
+            // The session's colour tells concurrent projects apart at a glance
+            let proj = format!(
+                "{} {}",
+                crate::session::color_glyph(&color),
+                cwd.rsplit(['/', '\\']).next().unwrap_or(cwd),
+            );
+            // How long the turn that just ended took
+            let duration = match event {
+                HookEvent::Stop => state.session_tracker.active_since(sid)
+                    .map(|since| crate::messages::duration(now - since))
+                    .unwrap_or_default(),
+                _ => String::new(),
             };
-            if !title.is_empty() {
-                crate::tray::send_notification(handle, &title, &toast_body);
-                if state.live_sound_enabled.load(Ordering::Relaxed) {
-                    let st = match event {
-                        HookEvent::Stop => read_lock!(state.live_sound_stop).clone(),
-                        _ => read_lock!(state.live_sound_notification).clone(),
-                    };
-                    crate::tray::play_notification_sound(&st);
-                }
+            let text = crate::messages::EventText { project: &proj, duration: &duration, ..text };
+            let title = crate::messages::toast_title(&state.config.messages, &text);
+            let toast_body = crate::messages::toast_body(&state.config.messages, &text);
+            if !title.is_empty() && away {
+                deferred_title = Some(title);
+            } else if !title.is_empty() && batched {
+                hold_toast(&state, title);
+            } else if !title.is_empty() {
+                let sound = event_sound(&state, event);
+                // One toast per session and kind, a newer one replacing it
+                let tag = match event {
+                    HookEvent::Stop => "stop",
+                    HookEvent::LimitReached => "limit",
+                    _ => "notification",
+                };
+                // A finished turn can be read right from the toast
+                let links = matches!(event, HookEvent::Stop).then(|| crate::toast::ToastLinks::stop(sid));
+                show_linked_toast(&state, &title, &toast_body, sound, Some(ToastKey { group: sid, tag }), cid, links.as_ref());
             }
         }
     }
 
+    // Pop the island up if configured to for this event
+    if !focused && !batched {
+        match event {
+            HookEvent::Stop => pop_island(&state, "stop"),
+            HookEvent::Notification => pop_island(&state, "notification"),
+            HookEvent::SessionStart => pop_island(&state, "session_start"),
+            _ => {}
+        }
+    }
+
     // --- 7. Remote channels (async, fire-and-forget) ---
-    // Arc::clone is cheap — no deep copy of Config
-    let cfg = Arc::clone(&state.config);
-    let client = state.http_client.clone();
-    let msg = message.clone();
-    tokio::spawn(async move {
-        remote::dispatch_remote(&cfg.telegram, &cfg.dingtalk, &cfg.wechat, &client, &msg).await;
-    });
+    // With escalation configured, remote channels only fire once the user
+    // has been away long enough; until then the message waits in the backlog.
+    let remote_now = state.config.presence.escalate_after_secs == 0 || state.presence.is_escalated();
+    // Muted sessions are neither pushed now nor escalated later
+    let muted = state.session_tracker.remote_muted(sid);
+    if let Some(title) = deferred_title {
+        let queued = if remote_now || muted { String::new() } else { message.clone() };
+        state.presence.defer(title, queued);
+    }
+    let remote_off = *event == HookEvent::LimitReached && !state.config.general.limit_notify_remote;
+    if remote_now && !remote_off && !muted {
+        // Arc::clone is cheap — no deep copy of Config
+        let cfg = Arc::clone(&state.config);
+        let clients = state.http_clients.clone();
+        let mut msg = remote::RemoteMessage::new(&event.to_string(), message.clone());
+        msg.translations = remote::languages(&state.config)
+            .into_iter()
+            .map(|lang| (lang.to_string(), crate::messages::message_in(&state.config.messages, &text, lang)))
+            .collect();
+        msg.link = crate::deep_link::session_link(&state.config, sid).unwrap_or_default();
+        msg.correlation_id = cid.clone();
+        tokio::spawn(async move {
+            remote::dispatch_remote(&cfg, &clients, &msg).await;
+        });
+    }
 
     Json(json!({ "ok": true }))
 }
 
-/// Format a human-readable event message (same logic as Python's format_message).
-fn format_event_message(
-    event: &HookEvent,
-    short_sid: &str,
-    cwd: &str,
-    ntype: &str,
-    nmsg: &str,
-    last_msg: &str,
-    model: &str,
-) -> String {
-    match event {
-        HookEvent::Stop => {
-            let truncated = if last_msg.chars().count() > 300 {
-                format!("{}...", last_msg.chars().take(297).collect::<String>())
-            } else {
-                last_msg.to_string()
-            };
-            format!("[Done] {}\n{}\n{}", short_sid, cwd, truncated)
+/// POST /api/web-agent — a browser extension reports a web agent task.
+/// Each task becomes a session `web-<source>-<task_id>`; state changes go
+/// through the same pipeline as hook signals.
+async fn api_web_agent(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<WebAgentPayload>, JsonRejection>,
+) -> Json<Value> {
+    let p = match body {
+        Ok(Json(p)) => p,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let valid = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    if !valid(&p.source) || !valid(&p.task_id) {
+        return Json(json!({ "ok": false, "error": "source and task_id must be non-empty [A-Za-z0-9_-]" }));
+    }
+    let sid = format!("web-{}-{}", p.source, p.task_id);
+    // The UI labels sessions by the last path segment of cwd
+    let title = if p.title.is_empty() { &p.task_id } else { &p.title };
+    let cwd = format!("{}/{}", p.source, title.replace(['/', '\\'], " "));
+
+    let (event, ntype) = match p.state {
+        WebAgentState::Working => {
+            state.session_tracker.update(
+                &sid,
+                SessionUpdate {
+                    status: Some(SessionStatus::Active),
+                    cwd: Some(cwd),
+                    notification_type: Some(String::new()),
+                    notification_message: Some(String::new()),
+                    ..Default::default()
+                },
+            );
+            state.sse.broadcast("activity", json!({ "event": HookEvent::UserPrompt, "session_id": sid }));
+            let _ = state.notify_tray.send(());
+            return Json(json!({ "ok": true, "session_id": sid }));
         }
-        HookEvent::Notification => match ntype {
-            "permission_prompt" => format!("[Confirm] {}\n{}", short_sid, nmsg),
-            "idle_prompt" => format!("[Idle] {} waiting for input", short_sid),
-            _ => format!("[Notice] {}\n{}", short_sid, nmsg),
-        },
-        HookEvent::SessionStart => {
-            let m = if model.is_empty() { "unknown" } else { model };
-            format!("[Start] {} | {} | {}", short_sid, m, cwd)
+        WebAgentState::Started => (HookEvent::SessionStart, ""),
+        WebAgentState::NeedsInput => (HookEvent::Notification, "idle_prompt"),
+        WebAgentState::Error => (HookEvent::Notification, "web_error"),
+        WebAgentState::Done => (HookEvent::Stop, ""),
+        WebAgentState::Ended => (HookEvent::SessionEnd, ""),
+    };
+    let done = p.state == WebAgentState::Done;
+    let signal = SignalPayload {
+        event,
+        session_id: sid.clone(),
+        cwd,
+        notification_type: ntype.to_string(),
+        message: if done { String::new() } else { p.message.clone() },
+        last_assistant_message: if done { p.message } else { String::new() },
+        model: if p.model.is_empty() { p.source } else { p.model },
+        hook_pid: None,
+        hook_version: None,
+        hook_seq: None,
+        agent_pid: None,
+        parent_session_id: None,
+        source: String::new(),
+        transcript_path: String::new(),
+        agent: String::new(),
+        correlation_id: String::new(),
+    };
+    let Json(mut resp) = handle_signal(state, signal).await;
+    resp["session_id"] = json!(sid);
+    Json(resp)
+}
+
+/// POST /api/external-event — surface another local tool's notification
+/// (build script, test watcher) like an agent event. Level 1 is only
+/// logged, 2 also toasts with a sound, 3 also goes to the remote channels.
+async fn api_external_event(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<ExternalEventPayload>, JsonRejection>,
+) -> Json<Value> {
+    let p = match body {
+        Ok(Json(p)) => p,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let valid = !p.source.is_empty() && p.source.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    if !valid {
+        return Json(json!({ "ok": false, "error": "source must be non-empty [A-Za-z0-9_-]" }));
+    }
+    let title = p.title.trim();
+    if title.is_empty() {
+        return Json(json!({ "ok": false, "error": "title is required" }));
+    }
+    let level = p.level.clamp(1, 3);
+    let sid = format!("ext-{}", p.source);
+    let message = if p.message.is_empty() {
+        format!("[{}] {}", p.source, title)
+    } else {
+        format!("[{}] {}\n{}", p.source, title, p.message)
+    };
+
+    let evt = Event {
+        last_assistant_message: p.message.clone(),
+        source: p.source.clone(),
+        ..Event::new(HookEvent::External, &sid, &p.project, &message, level)
+    };
+    let id = evt.id.clone();
+    {
+        let s = state.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            s.event_store.append_event(evt);
+        }).await;
+    }
+    state.sse.broadcast("event", json!({
+        "event": HookEvent::External,
+        "session_id": &sid,
+        "cwd": &p.project,
+        "source": &p.source,
+        "level": level,
+        "message": &message,
+    }));
+    let _ = state.notify_tray.send(());
+
+    // Same away/escalation rules as agent events (see handle_signal)
+    let remote_now = state.config.presence.escalate_after_secs == 0 || state.presence.is_escalated();
+    if level >= 2 && can_toast(&state) {
+        // 📣 source — title (project)
+        let toast_title = if p.project.is_empty() {
+            format!("\u{1f4e3} {} \u{2014} {}", p.source, title)
+        } else {
+            format!("\u{1f4e3} {} \u{2014} {} ({})", p.source, title, crate::tray::project_name(&p.project))
+        };
+        if state.presence.is_away() {
+            let queued = if level >= 3 && !remote_now { message.clone() } else { String::new() };
+            state.presence.defer(toast_title, queued);
+        } else if batching(&state) {
+            hold_toast(&state, toast_title);
+        } else {
+            let sound = state.live_sound_enabled.load(Ordering::Relaxed)
+                .then(|| read_lock!(state.live_sound_notification).clone());
+            show_toast(&state, &toast_title, &p.message, sound, None, "");
         }
-        HookEvent::SessionEnd => format!("[End] {}", short_sid),
-        _ => format!("[{}] {}", event, short_sid),
     }
+    if level >= 3 && remote_now {
+        let cfg = Arc::clone(&state.config);
+        let clients = state.http_clients.clone();
+        let msg = remote::RemoteMessage::new("external", message);
+        tokio::spawn(async move {
+            remote::dispatch_remote(&cfg, &clients, &msg).await;
+        });
+    }
+    Json(json!({ "ok": true, "id": id }))
+}
+
+#[derive(Deserialize)]
+struct ActionBody {
+    #[serde(flatten)]
+    action: crate::action::Action,
+    /// Who asks (`island`, `dashboard`, `cli`, …), for the log.
+    #[serde(default)]
+    source: String,
+}
+
+/// POST /api/action — carry out one `Action` (see action.rs).
+async fn api_action(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<ActionBody>, JsonRejection>,
+) -> Json<Value> {
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    run_action(&state, body.action, body.source).await
+}
+
+/// `action::run` off the async runtime (it may wait on other processes).
+async fn run_action(state: &Arc<AppState>, action: crate::action::Action, source: String) -> Json<Value> {
+    let s = state.clone();
+    let result = tokio::task::spawn_blocking(move || crate::action::run(&s, &action, &source))
+        .await
+        .unwrap_or_else(|e| Err(json!({ "ok": false, "error": format!("task join error: {}", e) })));
+    Json(result.unwrap_or_else(|e| e))
 }
 
+#[derive(Deserialize)]
+struct FocusBody {
+    #[serde(default)]
+    cwd: String,
+    pid: Option<u32>,
+    /// Raise and flash the terminal but keep keyboard focus (island hover).
+    #[serde(default)]
+    peek: bool,
+}
+
+/// POST /api/focus — the `focus_session` action by `cwd` and/or `pid`.
 async fn api_focus(
     State(state): State<Arc<AppState>>,
-    Json(body): Json<Value>,
+    body: Result<Json<FocusBody>, JsonRejection>,
 ) -> Json<Value> {
-    let cwd = body.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
-    let req_pid = body.get("pid").and_then(|v| v.as_u64()).map(|p| p as u32);
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let action = crate::action::Action::FocusSession { session_id: String::new(), cwd: body.cwd, pid: body.pid, peek: body.peek };
+    run_action(&state, action, "island".to_string()).await
+}
 
-    if cwd.is_empty() && req_pid.is_none() {
-        return Json(json!({ "ok": false, "error": "no cwd or pid" }));
+#[derive(Deserialize)]
+struct DeepLinkBody {
+    url: String,
+}
+
+/// POST /api/deep-link — carry out an `agentdesk://` URL (forwarded by a
+/// second launch of the exe, or sent by scripts).
+async fn api_deep_link(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<DeepLinkBody>, JsonRejection>,
+) -> Json<Value> {
+    let url = match body {
+        Ok(Json(b)) => b.url,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    match crate::deep_link::handle(&state, &url) {
+        Ok(()) => Json(json!({ "ok": true })),
+        Err(e) => Json(json!({ "ok": false, "error": e })),
     }
+}
 
-    // Resolve PID from scan_and_merge if not provided
-    let pid = req_pid.or_else(|| {
-        if cwd.is_empty() { return None; }
-        let cwd_norm = cwd.replace('/', "\\").to_lowercase();
-        let merged = scan_and_merge(&state);
-        merged.iter().find_map(|proc| {
-            let pcwd = proc.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
-            if pcwd.replace('/', "\\").to_lowercase() == cwd_norm {
-                proc.get("pid").and_then(|v| v.as_u64()).map(|p| p as u32)
-            } else {
-                None
+#[derive(Deserialize)]
+struct LogLevelBody {
+    filter: String,
+}
+
+/// GET /api/logs/level — the active log filter.
+async fn api_log_level_get() -> Json<Value> {
+    Json(json!({ "ok": true, "filter": crate::log_filter() }))
+}
+
+/// POST /api/logs/level — swap the log filter without a restart (e.g.
+/// `{"filter":"info,focus=debug"}` while reproducing a focus bug) and save
+/// it as `general.log_filter` for the next start.
+async fn api_log_level_set(body: Result<Json<LogLevelBody>, JsonRejection>) -> Json<Value> {
+    let filter = match body {
+        Ok(Json(b)) => b.filter.trim().to_string(),
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let filter = if filter.is_empty() { "info".to_string() } else { filter };
+    if let Err(e) = crate::set_log_filter(&filter) {
+        return Json(json!({ "ok": false, "error": e }));
+    }
+    let value = serde_json::to_string(&filter).unwrap_or_default();
+    let _ = tokio::task::spawn_blocking(move || {
+        crate::config::save_section_settings("general", &[("log_filter", &value)]);
+    })
+    .await;
+    Json(json!({ "ok": true, "filter": filter }))
+}
+
+#[derive(Deserialize, Default)]
+struct RecordStartBody {
+    /// Output file; default `recordings/rec-<time>.jsonl` next to sessions.json.
+    #[serde(default)]
+    path: Option<String>,
+}
+
+/// POST /api/record/start — capture incoming hook traffic for replay.
+async fn api_record_start(
+    State(state): State<Arc<AppState>>,
+    body: Option<Json<RecordStartBody>>,
+) -> Json<Value> {
+    let body = body.map(|Json(b)| b).unwrap_or_default();
+    let dir = std::path::Path::new(&state.config.general.sessions_file).with_file_name("recordings");
+    match state.recorder.start(body.path.map(std::path::PathBuf::from), &dir) {
+        Ok(path) => Json(json!({ "ok": true, "path": path })),
+        Err(e) => Json(json!({ "ok": false, "error": e })),
+    }
+}
+
+/// POST /api/record/stop — finish the recording.
+async fn api_record_stop(State(state): State<Arc<AppState>>) -> Json<Value> {
+    match state.recorder.stop() {
+        Some((path, count)) => Json(json!({ "ok": true, "path": path, "count": count })),
+        None => Json(json!({ "ok": false, "error": "not recording" })),
+    }
+}
+
+/// GET /api/budget — usage against each configured budget, as of the
+/// last check.
+async fn api_budget(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let cfg = &state.config.budget;
+    Json(json!({
+        "ok": true,
+        "enabled": cfg.enabled,
+        "warn_percent": cfg.warn_percent,
+        "pause_at_limit": cfg.pause_at_limit,
+        "budgets": state.budget.snapshot(),
+    }))
+}
+
+#[derive(Deserialize, Default)]
+struct BudgetAckBody {
+    /// Lift the pause of this session and of its project.
+    #[serde(default)]
+    session_id: Option<String>,
+    /// Or of one budget, by its `key` from `/api/budget`.
+    #[serde(default)]
+    key: Option<String>,
+}
+
+/// POST /api/budget/ack — acknowledge a budget alert, letting paused
+/// sessions ask for permissions again. No body lifts every pause.
+async fn api_budget_ack(
+    State(state): State<Arc<AppState>>,
+    body: Option<Json<BudgetAckBody>>,
+) -> Json<Value> {
+    let body = body.map(|Json(b)| b).unwrap_or_default();
+    let keys = match (body.session_id, body.key) {
+        (Some(sid), _) => {
+            let cwd = state
+                .session_tracker
+                .get_active(state.config.general.session_ttl)
+                .get(&sid)
+                .map(|i| i.cwd.clone())
+                .unwrap_or_default();
+            crate::budget::keys_for(&sid, &cwd)
+        }
+        (None, Some(key)) => vec![key],
+        (None, None) => Vec::new(),
+    };
+    let lifted = state.budget.acknowledge(&keys);
+    if lifted > 0 {
+        tracing::info!("Budget pause lifted ({} budget(s))", lifted);
+    }
+    Json(json!({ "ok": true, "lifted": lifted }))
+}
+
+#[cfg(debug_assertions)]
+#[derive(Deserialize)]
+struct SimulateBody {
+    /// A builtin scenario name, or a scenario object.
+    scenario: Value,
+    #[serde(default)]
+    speed: Option<f64>,
+}
+
+/// POST /api/simulate/scenario — replay a mock agent session against this
+/// server (debug builds only). Returns at once with the session id; the
+/// steps play out in the background.
+#[cfg(debug_assertions)]
+async fn api_simulate_scenario(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<SimulateBody>, JsonRejection>,
+) -> Json<Value> {
+    use crate::simulator::{self, Scenario};
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let scenario = match &body.scenario {
+        Value::String(name) => match simulator::builtin(name) {
+            Some(s) => s,
+            None => {
+                return Json(json!({
+                    "ok": false,
+                    "error": format!("unknown scenario {:?}; builtins: {}", name, simulator::BUILTIN.join(", ")),
+                }))
             }
-        })
+        },
+        other => match serde_json::from_value::<Scenario>(other.clone()) {
+            Ok(s) => s,
+            Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+        },
+    }
+    .prepared();
+    let reply = json!({ "ok": true, "session_id": &scenario.session_id, "steps": scenario.steps.len() });
+    let client = state.http_client.clone();
+    let base = format!("http://{}", crate::setup::local_addr(state.config.manager.port));
+    let speed = body.speed.unwrap_or(1.0);
+    tokio::spawn(async move {
+        if let Err(e) = simulator::run(&client, &base, &scenario, speed).await {
+            tracing::warn!("Simulation {:?} failed: {}", scenario.name, e);
+        }
     });
-
-    let cached = state.registry.get_cached();
-    let ok = focus::find_and_focus_terminal_with_pid(cwd, &cached, pid);
-    Json(json!({ "ok": ok }))
+    Json(reply)
 }
 
 /// Debug: eval JS in pet webview
 async fn api_eval(
     State(state): State<Arc<AppState>>,
-    Json(body): Json<Value>,
+    Json(body): Json<Value>,
+) -> Json<Value> {
+    let js = body.get("js").and_then(|v| v.as_str()).unwrap_or("");
+    if js.is_empty() {
+        return Json(json!({ "ok": false, "error": "no js" }));
+    }
+    if let Some(handle) = state.app_handle.get() {
+        use tauri::Manager;
+        if let Some(w) = handle.get_webview_window("island") {
+            let _ = tauri::WebviewWindow::eval(&w, js);
+            return Json(json!({ "ok": true }));
+        }
+    }
+    Json(json!({ "ok": false, "error": "no webview" }))
+}
+
+async fn api_mark_read(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    if let Ok(mut ts) = state.last_seen_ts.write() {
+        *ts = now;
+    }
+    *write_lock!(state.missed) = None;
+    let path = state.last_seen_path.clone();
+    tokio::task::spawn_blocking(move || {
+        let body = json!({ "last_seen_ts": now }).to_string();
+        if let Err(e) = std::fs::write(&path, body) {
+            tracing::warn!("Failed to save {}: {}", path.display(), e);
+        }
+    });
+    // Notify tray to refresh unread count in tooltip
+    let _ = state.notify_tray.send(());
+    Json(json!({ "ok": true }))
+}
+
+/// POST /api/clear — clear every event, or with a body only those matching
+/// `{session_id?, cwd?, before_ts?, level?}`.
+async fn api_clear(
+    State(state): State<Arc<AppState>>,
+    body: Option<Json<ClearFilter>>,
+) -> Json<Value> {
+    let mut filter = body.map(|Json(f)| f).unwrap_or_default();
+    if filter.is_empty() {
+        state.event_store.clear_all();
+        state.sse.broadcast("clear", json!({}));
+        let _ = state.notify_tray.send(());
+        return Json(json!({ "ok": true }));
+    }
+    // Accept a full session ID or an unambiguous prefix
+    filter.session_id = filter.session_id.map(|id| state.session_tracker.resolve_short_id(&id).unwrap_or(id));
+    let cleared = state.event_store.clear_matching(&filter);
+    state.sse.broadcast(
+        "clear",
+        json!({ "session_id": filter.session_id, "cwd": filter.cwd, "before_ts": filter.before_ts, "level": filter.level }),
+    );
+    let _ = state.notify_tray.send(());
+    Json(json!({ "ok": true, "cleared": cleared }))
+}
+
+/// Mark one event as handled: it stays listed but no longer counts as unread.
+async fn api_event_ack(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Json<Value> {
+    if !state.event_store.ack(&id) {
+        return Json(json!({ "ok": false, "error": "event not found" }));
+    }
+    state.sse.broadcast("event_acked", json!({ "id": id }));
+    let _ = state.notify_tray.send(());
+    Json(json!({ "ok": true }))
+}
+
+/// Remove one event from the list (implies ack).
+async fn api_event_dismiss(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Json<Value> {
+    if !state.event_store.dismiss(&id) {
+        return Json(json!({ "ok": false, "error": "event not found" }));
+    }
+    state.sse.broadcast("event_dismissed", json!({ "id": id }));
+    let _ = state.notify_tray.send(());
+    Json(json!({ "ok": true }))
+}
+
+async fn api_delete_session(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Json<Value> {
+    state.session_tracker.remove(&id);
+    state.session_tracker.flush_if_dirty();
+    state.sse.broadcast("refresh", json!({}));
+    Json(json!({ "ok": true }))
+}
+
+#[derive(Deserialize)]
+struct SessionPatch {
+    /// Palette name or `#rrggbb`; `null` restores the CWD-derived colour,
+    /// leaving it out keeps the current one.
+    #[serde(default, deserialize_with = "present")]
+    color: Option<Option<String>>,
+    /// Pin to the top of the session list (see `general.session_sort`).
+    pinned: Option<bool>,
+}
+
+/// A field that is present, `null` included: `Some(None)` for `null`.
+fn present<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<Option<String>>, D::Error> {
+    Option::<String>::deserialize(d).map(Some)
+}
+
+/// PATCH /api/session/{id} — change how a session is displayed.
+async fn api_session_patch(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    body: Result<Json<SessionPatch>, JsonRejection>,
+) -> Json<Value> {
+    let patch = match body {
+        Ok(Json(p)) => p,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let sid = state.session_tracker.resolve_short_id(&id).unwrap_or(id);
+    if let Some(color) = patch.color {
+        let color = match color.as_deref().map(crate::session::parse_color) {
+            Some(None) => {
+                let names: Vec<&str> = crate::session::SESSION_COLORS.iter().map(|(n, _, _)| *n).collect();
+                return Json(json!({ "ok": false, "error": format!("color must be #rrggbb or one of: {}", names.join(", ")) }));
+            }
+            Some(Some(c)) => Some(c),
+            None => None,
+        };
+        if !state.session_tracker.set_color(&sid, color) {
+            return Json(json!({ "ok": false, "error": "session not found" }));
+        }
+        let color = state.session_tracker.color(&sid);
+        state.sse.broadcast("session_color", json!({ "session_id": sid, "color": color }));
+    }
+    if let Some(pinned) = patch.pinned {
+        if !state.session_tracker.set_pinned(&sid, pinned) {
+            return Json(json!({ "ok": false, "error": "session not found" }));
+        }
+        state.sse.broadcast("refresh", json!({}));
+    }
+    let _ = state.notify_tray.send(());
+    Json(json!({
+        "ok": true,
+        "color": state.session_tracker.color(&sid),
+        "pinned": state.session_tracker.pinned(&sid),
+    }))
+}
+
+#[derive(Deserialize)]
+struct MuteRemoteBody {
+    #[serde(default = "default_true")]
+    muted: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// POST /api/session/{id}/mute-remote — stop (or with `{"muted": false}`
+/// resume) remote pushes about a session; its toasts still show.
+async fn api_session_mute_remote(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    body: Option<Json<MuteRemoteBody>>,
+) -> Json<Value> {
+    let muted = body.is_none_or(|Json(b)| b.muted);
+    let sid = state.session_tracker.resolve_short_id(&id).unwrap_or(id);
+    if !state.session_tracker.set_remote_muted(&sid, muted) {
+        return Json(json!({ "ok": false, "error": "session not found" }));
+    }
+    tracing::info!("Remote pushes for session {} {}", sid, if muted { "muted" } else { "unmuted" });
+    state.sse.broadcast("refresh", json!({}));
+    Json(json!({ "ok": true, "session_id": sid, "remote_muted": muted }))
+}
+
+#[derive(Deserialize, Default)]
+struct PurgeBody {
+    /// Only these statuses; empty = all.
+    #[serde(default)]
+    statuses: Vec<SessionStatus>,
+    /// Age threshold instead of the configured retention.
+    older_than_secs: Option<u64>,
+}
+
+/// POST /api/sessions/purge — apply the retention policy now, or purge the
+/// given statuses older than `older_than_secs` (0 = all of them).
+async fn api_sessions_purge(
+    State(state): State<Arc<AppState>>,
+    body: Option<Json<PurgeBody>>,
+) -> Json<Value> {
+    let body = body.map(|Json(b)| b).unwrap_or_default();
+    let retention = &state.config.general.retention;
+    let purged = state.session_tracker.purge_stale(|st| {
+        if !body.statuses.is_empty() && !body.statuses.contains(st) {
+            return 0;
+        }
+        // An explicit 0 purges everything matching: any age over 0 s
+        body.older_than_secs.map(|s| s.max(1)).unwrap_or_else(|| retention.for_status(st))
+    });
+    state.session_tracker.flush_if_dirty();
+    tracing::info!("Purged {} sessions on request", purged.len());
+    if !purged.is_empty() {
+        state.sse.broadcast("refresh", json!({}));
+    }
+    Json(json!({ "ok": true, "purged": purged }))
+}
+
+// ─── Remote channels ────────────────────────────────────
+
+const REMOTE_CHANNELS: &[&str] = &["telegram", "dingtalk", "wechat", "pushover", "bark"];
+
+#[derive(Deserialize, Default)]
+struct RemoteTestBody {
+    /// One of `REMOTE_CHANNELS`; empty = every channel.
+    #[serde(default)]
+    channel: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// POST /api/remote/test — send a test message through each remote channel
+/// and report per channel whether it arrived, with the full error if not.
+async fn api_remote_test(
+    State(state): State<Arc<AppState>>,
+    body: Option<Json<RemoteTestBody>>,
+) -> Json<Value> {
+    let body = body.map(|Json(b)| b).unwrap_or_default();
+    if !body.channel.is_empty() && !REMOTE_CHANNELS.contains(&body.channel.as_str()) {
+        return Json(json!({ "ok": false, "error": format!("unknown channel: {}", body.channel) }));
+    }
+    let message = if body.message.is_empty() { "Agent Desk test message" } else { body.message.as_str() };
+    let test_msg = remote::RemoteMessage::new("test", message);
+    let cfg = &state.config;
+    let clients = &state.http_clients;
+
+    let mut results = Vec::new();
+    for &channel in REMOTE_CHANNELS {
+        if !body.channel.is_empty() && body.channel != channel {
+            continue;
+        }
+        let start = Instant::now();
+        let (ready, skip_verify) = match channel {
+            "telegram" => (remote::telegram_ready(&cfg.telegram), cfg.telegram.skip_tls_verify),
+            "dingtalk" => (remote::dingtalk_ready(&cfg.dingtalk), cfg.dingtalk.skip_tls_verify),
+            "wechat" => (remote::wechat_ready(&cfg.wechat), cfg.wechat.skip_tls_verify),
+            "pushover" => (remote::pushover_ready(&cfg.pushover), cfg.pushover.skip_tls_verify),
+            _ => (remote::bark_ready(&cfg.bark), cfg.bark.skip_tls_verify),
+        };
+        let result = if !ready {
+            Err("not enabled or missing credentials".to_string())
+        } else {
+            let client = clients.pick(skip_verify);
+            match channel {
+                "telegram" => remote::send_telegram(&cfg.telegram, client, message).await,
+                "dingtalk" => remote::send_dingtalk(&cfg.dingtalk, client, &test_msg).await,
+                "wechat" => remote::send_wechat(&cfg.wechat, client, message).await,
+                "pushover" => remote::send_pushover(&cfg.pushover, client, &test_msg).await,
+                _ => remote::send_bark(&cfg.bark, client, &test_msg).await,
+            }
+        };
+        let ms = start.elapsed().as_millis() as u64;
+        results.push(match result {
+            Ok(()) => json!({ "channel": channel, "ok": true, "ms": ms }),
+            Err(e) => json!({ "channel": channel, "ok": false, "error": e, "ms": ms }),
+        });
+    }
+    let ok = results.iter().all(|r| r["ok"] == true);
+    Json(json!({ "ok": ok, "results": results }))
+}
+
+// ─── Team mode ──────────────────────────────────────────
+
+/// GET /api/team — teammates heard from recently (empty when team mode is off).
+async fn api_team(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let cfg = &state.config.team;
+    let members = if cfg.enabled { state.team.members(cfg.stale_secs) } else { Vec::new() };
+    Json(json!({
+        "ok": true,
+        "enabled": cfg.enabled,
+        "name": crate::team::member_name(cfg),
+        "members": members,
+    }))
+}
+
+/// POST /api/team/report — a teammate's current agent states.
+async fn api_team_report(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<crate::team::TeamReport>, JsonRejection>,
 ) -> Json<Value> {
-    let js = body.get("js").and_then(|v| v.as_str()).unwrap_or("");
-    if js.is_empty() {
-        return Json(json!({ "ok": false, "error": "no js" }));
+    let report = match body {
+        Ok(Json(r)) => r,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let cfg = &state.config.team;
+    if !cfg.enabled {
+        return Json(json!({ "ok": false, "error": "team mode is off" }));
     }
-    if let Some(handle) = state.app_handle.get() {
-        use tauri::Manager;
-        if let Some(w) = handle.get_webview_window("island") {
-            let _ = tauri::WebviewWindow::eval(&w, js);
-            return Json(json!({ "ok": true }));
-        }
+    if report.member.trim().is_empty() {
+        return Json(json!({ "ok": false, "error": "member is required" }));
     }
-    Json(json!({ "ok": false, "error": "no webview" }))
-}
-
-async fn api_mark_read(State(state): State<Arc<AppState>>) -> Json<Value> {
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs_f64();
-    if let Ok(mut ts) = state.last_seen_ts.write() {
-        *ts = now;
+    // Our own report echoed back (sink pointed at ourselves)
+    if report.member == crate::team::member_name(cfg) {
+        return Json(json!({ "ok": true }));
     }
-    // Notify tray to refresh unread count in tooltip
-    let _ = state.notify_tray.send(());
+    tracing::debug!("Team report from {}: {} ({} agents)", report.member, report.state, report.agents.len());
+    state.team.record(report);
+    state.sse.broadcast("team", json!({ "members": state.team.members(cfg.stale_secs) }));
     Json(json!({ "ok": true }))
 }
 
-async fn api_clear(State(state): State<Arc<AppState>>) -> Json<Value> {
-    state.event_store.clear_all();
-    state.sse.broadcast("clear", json!({}));
-    Json(json!({ "ok": true }))
+/// Status history for one session: raw transitions plus contiguous
+/// segments (`end` of the last one is now) for rendering an activity strip.
+async fn api_session_timeline(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Json<Value> {
+    // Accept a full session ID or an unambiguous prefix
+    let sid = state.session_tracker.resolve_short_id(&id).unwrap_or(id);
+    let Some((started_at, transitions)) = state.session_tracker.timeline(&sid) else {
+        return Json(json!({ "ok": false, "error": "session not found" }));
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    let segments: Vec<Value> = transitions
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let end = transitions.get(i + 1).map(|n| n.ts).unwrap_or(now);
+            json!({
+                "status": c.status,
+                "start": c.ts,
+                "end": end,
+                "duration": (end - c.ts).max(0.0),
+            })
+        })
+        .collect();
+
+    Json(json!({
+        "ok": true,
+        "session_id": sid,
+        "started_at": started_at,
+        "transitions": transitions,
+        "segments": segments,
+        "chain": session_chain(&state, &sid),
+    }))
 }
 
-async fn api_delete_session(
+/// The handoff chain of `sid`, oldest first, as shown by the history API.
+fn session_chain(state: &AppState, sid: &str) -> Vec<Value> {
+    state.session_tracker.chain(sid)
+        .iter()
+        .map(|s| json!({
+            "session_id": &s.session_id,
+            "cwd": &s.cwd,
+            "status": &s.status,
+            "started_at": s.started_at,
+            "updated_at": s.updated_at,
+            "last_message": &s.last_message,
+            "predecessor": &s.predecessor,
+            "inferred": s.predecessor_inferred,
+        }))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct LinkBody {
+    /// Session this one continues; null or empty removes the link.
+    predecessor: Option<String>,
+}
+
+/// Declare that session `id` continues the work of `predecessor`.
+async fn api_session_link(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
+    body: Result<Json<LinkBody>, JsonRejection>,
 ) -> Json<Value> {
-    state.session_tracker.remove(&id);
-    state.session_tracker.flush_if_dirty();
-    state.sse.broadcast("refresh", json!({}));
-    Json(json!({ "ok": true }))
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    // Accept full session IDs or unambiguous prefixes
+    let resolve = |id: String| state.session_tracker.resolve_short_id(&id).unwrap_or(id);
+    let sid = resolve(id);
+    let pred = body.predecessor.filter(|p| !p.is_empty()).map(resolve);
+    if let Err(e) = state.session_tracker.link(&sid, pred.as_deref()) {
+        return Json(json!({ "ok": false, "error": e }));
+    }
+    tracing::info!("Session {} linked to predecessor {:?}", sid, pred);
+    state.sse.broadcast("session_linked", json!({ "session_id": &sid, "predecessor": &pred, "inferred": false }));
+    Json(json!({ "ok": true, "session_id": &sid, "chain": session_chain(&state, &sid) }))
 }
 
 async fn api_island_expand(
@@ -916,7 +3673,7 @@ async fn api_island_expand(
 ) -> Json<Value> {
     if let Some(handle) = state.app_handle.get() {
         use tauri::Manager;
-        if let Some(w) = handle.get_webview_window("island") {
+        if handle.get_webview_window("island").is_some() {
             let (pw, ph) = if let Some(Json(b)) = body {
                 let pw = b.get("width").and_then(|v| v.as_u64()).unwrap_or(state.config.island.panel_width as u64) as u32;
                 let ph = b.get("height").and_then(|v| v.as_u64()).unwrap_or(state.config.island.panel_height as u64) as u32;
@@ -924,44 +3681,136 @@ async fn api_island_expand(
             } else {
                 (state.config.island.panel_width, state.config.island.panel_height)
             };
-            // Animation takes ~200ms — run off the tokio thread
-            tokio::task::spawn_blocking(move || {
-                crate::island::expand(&w, pw, ph);
-            });
+            crate::island::expand(pw, ph);
+            if state.island_state.expand() {
+                broadcast_island_state(&state);
+            }
             return Json(json!({ "ok": true }));
         }
     }
     Json(json!({ "ok": false, "error": "no island window" }))
 }
 
-async fn api_island_collapse(State(state): State<Arc<AppState>>) -> Json<Value> {
+/// POST /api/island/reset-position — show the island and move it back to
+/// the top center of the primary monitor (e.g. stranded off screen after a
+/// display change).
+async fn api_island_reset_position(State(state): State<Arc<AppState>>) -> Json<Value> {
     if let Some(handle) = state.app_handle.get() {
         use tauri::Manager;
         if let Some(w) = handle.get_webview_window("island") {
-            // Animation takes ~160ms — run off the tokio thread
-            tokio::task::spawn_blocking(move || {
-                crate::island::collapse(&w);
-            });
+            let _ = w.show();
+            crate::island::reset_position();
+            return Json(json!({ "ok": true }));
+        }
+    }
+    Json(json!({ "ok": false, "error": "no island window" }))
+}
+
+/// Collapse the panel. Refused while pending permissions pin it open,
+/// unless the body says `{"force": true}`.
+async fn api_island_collapse(
+    State(state): State<Arc<AppState>>,
+    body: Option<Json<Value>>,
+) -> Json<Value> {
+    let force = body.and_then(|Json(b)| b.get("force").and_then(|v| v.as_bool())).unwrap_or(false);
+    match state.island_state.collapse(force) {
+        Err(e) => return Json(json!({ "ok": false, "error": e.to_string(), "pinned": true })),
+        Ok(true) => broadcast_island_state(&state),
+        Ok(false) => {}
+    }
+    if let Some(handle) = state.app_handle.get() {
+        use tauri::Manager;
+        if handle.get_webview_window("island").is_some() {
+            crate::island::collapse();
             return Json(json!({ "ok": true }));
         }
     }
     Json(json!({ "ok": false, "error": "no island window" }))
 }
 
+#[derive(Deserialize)]
+struct IslandStateBody {
+    expanded: Option<bool>,
+    view: Option<crate::island_state::IslandView>,
+    session_id: Option<String>,
+    #[serde(default)]
+    force: bool,
+}
+
+async fn api_island_state_get(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let mut snap = state.island_state.snapshot();
+    if let Some(obj) = snap.as_object_mut() {
+        obj.insert("ok".into(), json!(true));
+    }
+    Json(snap)
+}
+
+/// Frontends report view changes and request expand/collapse here; the
+/// window follows and every client gets an `island_state` SSE message.
+async fn api_island_state_set(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<IslandStateBody>, JsonRejection>,
+) -> Json<Value> {
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let island = &state.island_state;
+    let mut changed = false;
+
+    if let Some(view) = body.view {
+        match island.set_view(view, body.session_id) {
+            Ok(c) => changed |= c,
+            Err(e) => return Json(json!({ "ok": false, "error": e.to_string() })),
+        }
+    }
+    let result = match body.expanded {
+        Some(true) => {
+            crate::island::expand(state.config.island.panel_width, state.config.island.panel_height);
+            changed |= island.expand();
+            Ok(())
+        }
+        Some(false) => island.collapse(body.force).map(|c| {
+            crate::island::collapse();
+            changed |= c;
+        }),
+        None => Ok(()),
+    };
+
+    if changed {
+        broadcast_island_state(&state);
+    }
+    let mut snap = island.snapshot();
+    if let Some(obj) = snap.as_object_mut() {
+        obj.insert("ok".into(), json!(result.is_ok()));
+        if let Err(e) = result {
+            obj.insert("error".into(), json!(e.to_string()));
+        }
+    }
+    Json(snap)
+}
+
 async fn api_island_pill_state(
     State(state): State<Arc<AppState>>,
     Json(body): Json<Value>,
 ) -> Json<Value> {
-    let active = body.get("active").and_then(|v| v.as_bool()).unwrap_or(false);
+    // `{"active": bool}` from pages predating `pill_state`
+    let pill = match body.get("state") {
+        Some(s) => match serde_json::from_value::<PillState>(s.clone()) {
+            Ok(p) => p,
+            Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+        },
+        None if body.get("active").and_then(|v| v.as_bool()).unwrap_or(false) => PillState::Working,
+        None => PillState::Idle,
+    };
     if let Some(handle) = state.app_handle.get() {
         use tauri::Manager;
-        if let Some(w) = handle.get_webview_window("island") {
-            let pill_w = state.config.island.pill_width;
-            let pill_w_active = state.config.island.pill_width_active;
-            // Animation takes ~150ms — run off the tokio thread
-            tokio::task::spawn_blocking(move || {
-                crate::island::set_pill_active(&w, active, pill_w, pill_w_active);
-            });
+        if handle.get_webview_window("island").is_some() {
+            crate::island::set_pill_state(
+                pill,
+                state.config.island.pill_width,
+                state.config.island.pill_width_active,
+            );
             return Json(json!({ "ok": true }));
         }
     }
@@ -979,6 +3828,35 @@ async fn api_island_hide(State(state): State<Arc<AppState>>) -> Json<Value> {
     Json(json!({ "ok": false, "error": "no island window" }))
 }
 
+#[derive(Deserialize)]
+struct IslandCommandBody {
+    /// `next_item`, `prev_item`, `select`, `approve` or `deny`.
+    action: String,
+}
+
+/// POST /api/island/command — drive the island panel from the keyboard:
+/// move the selection, open the selected session, or answer the selected
+/// (else oldest) permission request. Without a window of our own
+/// (headless), connected clients get it as an `island_command` message.
+async fn api_island_command(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<IslandCommandBody>, JsonRejection>,
+) -> Json<Value> {
+    let action = match body {
+        Ok(Json(b)) => b.action,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    if !crate::island::COMMANDS.contains(&action.as_str()) {
+        return Json(json!({ "ok": false, "error": format!("unknown action: {}", action) }));
+    }
+    use tauri::Manager;
+    match state.app_handle.get().and_then(|h| h.get_webview_window("island")) {
+        Some(w) => crate::island::run_command(&w, &action),
+        None => state.sse.broadcast("island_command", json!({ "action": &action })),
+    }
+    Json(json!({ "ok": true }))
+}
+
 // ─── Permission endpoints ───────────────────────────────
 
 /// Hook binary POSTs here and blocks until user responds (long-poll).
@@ -994,10 +3872,33 @@ async fn api_permission_request(
     let cwd = payload.cwd;
     let tool_name = payload.tool_name;
     let tool_input = payload.tool_input;
+    let cid = payload.correlation_id;
     let permission_suggestions = payload.permission_suggestions;
 
-    // Check session auto-approve rules before registering
-    if state.permissions.check_session_rule(&session_id, &tool_name) {
+    // Ignored project: no decision, the agent asks in the terminal
+    if state.ignore_paths.matches(&cwd) {
+        return Json(json!({}));
+    }
+
+    // Over budget with `pause_at_limit`: deny until the alert is acknowledged
+    if let Some(reason) = state.budget.paused(&session_id, &cwd) {
+        return Json(json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PermissionRequest",
+                "decision": {
+                    "behavior": "deny",
+                    "updatedPermissions": [],
+                    "message": reason,
+                }
+            }
+        }));
+    }
+
+    // Check session auto-approve rules and windows before registering
+    if state.permissions.check_session_rule(&session_id, &tool_name)
+        || state.permissions.check_session_paths(&session_id, &cwd, &tool_name, &tool_input)
+        || try_auto_approve(&state, &session_id, &cwd, &tool_name, &tool_input).await
+    {
         return Json(json!({
             "hookSpecificOutput": {
                 "hookEventName": "PermissionRequest",
@@ -1009,67 +3910,77 @@ async fn api_permission_request(
         }));
     }
 
+    // The agent retrying a request just answered gets the same answer
+    if let Some((decision, reason)) = try_repeat_decision(&state, &session_id, &cwd, &tool_name, &tool_input).await {
+        let mut decision_json = json!({
+            "behavior": decision.to_behavior(),
+            "updatedPermissions": [],
+        });
+        if decision == PermissionDecisionKind::Deny && !reason.is_empty() {
+            decision_json["message"] = json!(reason);
+        }
+        return Json(json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PermissionRequest",
+                "decision": decision_json,
+            }
+        }));
+    }
+
     let id = uuid::Uuid::new_v4().to_string();
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    let summary = crate::permission::ToolSummary::from_tool(&tool_name, &tool_input);
+    let plan = crate::permission::plan_text(&tool_name, &tool_input);
 
+    let priority = permission_priority(&state, &session_id, &cwd);
     let req = crate::permission::PermissionRequest {
         id: id.clone(),
         session_id: session_id.clone(),
         cwd: cwd.clone(),
         tool_name: tool_name.clone(),
         tool_input: tool_input.clone(),
+        summary: summary.clone(),
+        plan: plan.clone(),
         permission_suggestions: permission_suggestions.clone(),
         timestamp: now,
         timeout_secs: state.config.island.permission_timeout_secs,
+        deadline: now + state.config.island.permission_timeout_secs as f64,
+        priority,
     };
 
     let rx = state.permissions.register(req);
     let timeout_secs = state.config.island.permission_timeout_secs;
+    tracing::info!("[{}] Permission request {} for {} in session {}", cid, id, tool_name, session_id);
+    sync_island_permissions(&state);
 
     // SSE broadcast + sound + auto-expand island
     state.sse.broadcast("permission_request", json!({
         "id": &id,
         "tool_name": &tool_name,
+        "summary": &summary,
         "session_id": &session_id,
         "timeout_secs": timeout_secs,
+        "deadline": now + timeout_secs as f64,
+        "priority": priority,
+        "correlation_id": &cid,
     }));
-    let _ = state.notify_tray.send(());
-
-    if let Some(handle) = state.app_handle.get() {
-        use tauri::Manager;
-        if let Some(w) = handle.get_webview_window("island") {
-            let _ = w.show(); // Auto-show if hidden (permission needs user action)
-            let _ = w.eval("if(window.onExpand)window.onExpand();fetchPermissions();");
-            let pw = state.config.island.panel_width;
-            let ph = state.config.island.panel_height;
-            tokio::task::spawn_blocking(move || {
-                crate::island::expand(&w, pw, ph);
-            });
-        }
-        if state.live_sound_enabled.load(Ordering::Relaxed) {
-            let st = read_lock!(state.live_sound_permission).clone();
-            crate::tray::play_notification_sound(&st);
-        }
+    if let Some(plan) = &plan {
+        broadcast_plan_review(&state, &id, &session_id, &cwd, plan, now + timeout_secs as f64);
     }
+    let _ = state.notify_tray.send(());
 
-    // Countdown SSE: broadcast remaining time every 10s
-    let countdown_sse = state.sse.clone();
-    let countdown_id = id.clone();
-    let countdown_handle = tokio::spawn(async move {
-        let mut remaining = timeout_secs;
-        loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-            remaining = remaining.saturating_sub(10);
-            countdown_sse.broadcast("permission_countdown", json!({
-                "id": &countdown_id,
-                "remaining": remaining,
-                "total": timeout_secs,
-            }));
-            if remaining == 0 {
-                break;
+    pop_island(&state, "permission");
+    if state.app_handle.get().is_some() {
+        if state.presence.is_away() {
+            state.presence.defer(permission_title(&state, &cwd, &tool_name), String::new());
+        } else {
+            toast_permission(&state, &id, &session_id, &cwd, &tool_name, summary.as_ref(), now + timeout_secs as f64);
+            if state.live_sound_enabled.load(Ordering::Relaxed) {
+                crate::tray::play_notification_sound(&permission_sound(&state, priority));
             }
         }
-    });
+    }
+    notify_permission_remote(&state, &id, &session_id, &cwd, &tool_name, summary.as_ref(), &cid);
 
     // Long-poll: wait for decision
     let decision = tokio::time::timeout(
@@ -1077,33 +3988,39 @@ async fn api_permission_request(
         rx,
     ).await;
 
-    countdown_handle.abort(); // Stop countdown task
-
     match decision {
-        Ok(Ok(d)) => {
+        Ok(Ok(reply)) => {
             // Build the hookSpecificOutput that Claude Code expects
-            let behavior = d.to_behavior();
+            let behavior = reply.decision.to_behavior();
 
-            // For "always_allow", include updated_permissions from original suggestions
-            let updated_permissions = if d == PermissionDecisionKind::AlwaysAllow {
-                permission_suggestions.clone()
-            } else {
-                json!([])
-            };
+            // Path-scoped edit rules, else the suggestions the user picked
+            // (all of them for a bare "always_allow")
+            let updated_permissions = reply
+                .path_rules(&cwd)
+                .unwrap_or_else(|| reply.updated_permissions(&permission_suggestions));
 
+            let mut decision = json!({
+                "behavior": behavior,
+                "updatedPermissions": updated_permissions,
+            });
+            // A deny reason tells the agent what to do instead
+            if reply.decision.refuses() && !reply.message.is_empty() {
+                decision["message"] = json!(reply.message);
+            }
             Json(json!({
                 "hookSpecificOutput": {
                     "hookEventName": "PermissionRequest",
-                    "decision": {
-                        "behavior": behavior,
-                        "updatedPermissions": updated_permissions,
-                    }
+                    "decision": decision,
                 }
             }))
         }
         _ => {
             // Timeout or channel closed — clean up and return deny
-            state.permissions.remove(&id);
+            if let Some(req) = state.permissions.expire(&id, now_secs()) {
+                permission_expired(&state, &req, "deny").await;
+            }
+            crate::toast::finish_countdown(&id);
+            sync_island_permissions(&state);
             Json(json!({
                 "hookSpecificOutput": {
                     "hookEventName": "PermissionRequest",
@@ -1126,36 +4043,121 @@ async fn api_permission_respond(
         Ok(Json(p)) => p,
         Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
     };
+    match respond_permission(&state, payload) {
+        Ok(()) => Json(json!({ "ok": true })),
+        Err(closed) => Json(late_answer(closed)),
+    }
+}
+
+/// Reply to a decision for a request that is no longer pending, saying
+/// why (`reason`: `expired`, `answered` or `unknown`) so the UI can tell
+/// the user.
+pub(crate) fn late_answer(closed: Option<(Closed, f64)>) -> Value {
+    match closed {
+        Some((Closed::Expired, at)) => json!({
+            "ok": false,
+            "reason": "expired",
+            "closed_at": at,
+            "error": "This request timed out before it was answered; the agent already got the fallback answer.",
+        }),
+        Some((Closed::Answered, at)) => json!({
+            "ok": false,
+            "reason": "answered",
+            "closed_at": at,
+            "error": "This request was already answered.",
+        }),
+        None => json!({ "ok": false, "reason": "unknown", "error": "No such permission request." }),
+    }
+}
+
+/// A request timed out unanswered and the agent got `fallback` (`deny`,
+/// or `ask` for the terminal prompt): tell the UIs with
+/// `permission_expired` and leave a `permission_expired` event.
+async fn permission_expired(state: &Arc<AppState>, req: &crate::permission::PermissionRequest, fallback: &str) {
+    tracing::info!("Permission request {} for {} in session {} expired ({})", req.id, req.tool_name, req.session_id, fallback);
+    state.sse.broadcast("permission_expired", json!({
+        "id": &req.id,
+        "session_id": &req.session_id,
+        "cwd": &req.cwd,
+        "tool_name": &req.tool_name,
+        "fallback": fallback,
+    }));
+    let target = req.summary.as_ref().map(|s| format!(" {}", s.target)).unwrap_or_default();
+    let short_sid = if req.session_id.len() > 8 { &req.session_id[..8] } else { req.session_id.as_str() };
+    let message = format!("[Expired] {} | {}{}", short_sid, req.tool_name, target);
+    let extra = json!({ "tool_name": &req.tool_name, "request_id": &req.id, "fallback": fallback });
+    log_permission_audit(state, HookEvent::PermissionExpired, &req.session_id, &req.cwd, message, extra).await;
+}
+
+/// Apply a decision to a pending permission request. Fails with how the
+/// request ended (if known) when it is no longer pending: answered
+/// elsewhere or timed out.
+pub(crate) fn respond_permission(state: &AppState, payload: PermissionRespondPayload) -> Result<(), Option<(Closed, f64)>> {
     let id = &payload.id;
     let decision = payload.decision;
 
-    // Look up session_id and tool_name before responding (respond removes the request)
-    let (session_id, tool_name) = {
-        let pending = state.permissions.get_pending();
-        pending.iter().find(|r| r.id == *id).map(|r| (r.session_id.clone(), r.tool_name.clone())).unzip()
+    // Look up the request before responding (respond removes it)
+    let request = state.permissions.get_pending().into_iter().find(|r| r.id == *id);
+    let session_id = request.as_ref().map(|r| r.session_id.clone());
+
+    // File edits can be approved per path; in per-file mode a remembered
+    // approval without explicit paths covers just the file being edited
+    let remembered = matches!(decision, PermissionDecisionKind::AllowSession | PermissionDecisionKind::AlwaysAllow);
+    let edit_file = request.as_ref().and_then(|r| crate::permission::edit_target(&r.tool_name, &r.tool_input));
+    let allowed_paths = match (edit_file, payload.allowed_paths) {
+        (Some(_), Some(paths)) if remembered && !paths.is_empty() => Some(paths),
+        (Some(file), _) if remembered && state.config.island.per_file_edit_approval => Some(vec![file.to_string()]),
+        _ => None,
     };
 
     // For AllowSession, cache the rule before responding
-    if decision == PermissionDecisionKind::AllowSession {
-        if let (Some(sid), Some(tn)) = (&session_id, &tool_name) {
-            state.permissions.add_session_rule(sid, tn);
+    if let (PermissionDecisionKind::AllowSession, Some(req)) = (&decision, &request) {
+        match &allowed_paths {
+            Some(paths) => state.permissions.add_session_paths(&req.session_id, &req.cwd, paths),
+            None => state.permissions.add_session_rule(&req.session_id, &req.tool_name),
         }
     }
 
-    let ok = state.permissions.respond(id, decision.clone());
+    let mut message = payload.message.unwrap_or_default().trim().to_string();
+    if decision == PermissionDecisionKind::RejectPlan {
+        message = if message.is_empty() {
+            "The user rejected this plan. Stay in plan mode and present a revised plan.".to_string()
+        } else {
+            format!("The user rejected this plan. Stay in plan mode and revise it: {}", message)
+        };
+    }
+    let reply = crate::permission::PermissionReply {
+        decision: decision.clone(),
+        selected_suggestions: payload.selected_suggestions,
+        message: message.clone(),
+        allowed_paths,
+    };
+    let ok = state.permissions.respond(id, reply, now_secs());
+    if ok {
+        sync_island_permissions(state);
+        crate::toast::finish_countdown(id);
+    }
+    if let Some(req) = request.as_ref().filter(|_| ok) {
+        state.permissions.remember_decision(req, &decision, &message, now_secs());
+    }
 
     // Update session status immediately so UI reflects the change
     if ok {
         if let Some(sid) = &session_id {
             let new_status = match decision {
                 PermissionDecisionKind::Allow | PermissionDecisionKind::AllowSession | PermissionDecisionKind::AlwaysAllow => SessionStatus::Active,
+                // The agent goes on, with the plan or to rework it
+                PermissionDecisionKind::ApprovePlan | PermissionDecisionKind::RejectPlan => SessionStatus::Active,
                 PermissionDecisionKind::Deny => SessionStatus::Waiting,
                 PermissionDecisionKind::AskTerminal => SessionStatus::Waiting, // handed off to terminal
             };
+            // Handed off: the prompt now waits in the terminal
+            let reason = (decision == PermissionDecisionKind::AskTerminal).then_some(WaitingReason::Permission);
             state.session_tracker.update(sid, SessionUpdate {
                 status: Some(new_status),
                 notification_type: Some(String::new()),
                 notification_message: Some(String::new()),
+                waiting_reason: Some(reason),
                 ..Default::default()
             });
             state.sse.broadcast("activity", json!({
@@ -1165,14 +4167,305 @@ async fn api_permission_respond(
             }));
         }
     }
+    if ok { Ok(()) } else { Err(state.permissions.closed(id)) }
+}
+
+/// While the island is hidden, a permission request also gets a toast in
+/// its session's group, counting down to `deadline` until it is answered.
+/// Windows only; elsewhere the sound and tray badge have to do.
+fn toast_permission(
+    state: &AppState,
+    id: &str,
+    session_id: &str,
+    cwd: &str,
+    tool_name: &str,
+    summary: Option<&crate::permission::ToolSummary>,
+    deadline: f64,
+) {
+    use tauri::Manager;
+    let Some(handle) = state.app_handle.get() else { return };
+    if handle.get_webview_window("island").is_some_and(|w| w.is_visible().unwrap_or(false)) {
+        return;
+    }
+    let title = permission_title(state, cwd, tool_name);
+    let body = summary.map(|s| s.target.chars().take(200).collect::<String>()).unwrap_or_default();
+    let total = state.config.island.permission_timeout_secs as f64;
+    crate::toast::show_countdown(&title, &body, ToastKey { group: session_id, tag: id }, deadline, total);
+}
+
+/// Send the plan of an ExitPlanMode request as a `plan_review` SSE
+/// message (markdown), so UIs can show it as a document to read and
+/// answer with `approve_plan` / `reject_plan` instead of a tool card.
+fn broadcast_plan_review(state: &AppState, id: &str, session_id: &str, cwd: &str, plan: &str, deadline: f64) {
+    state.sse.broadcast("plan_review", json!({
+        "id": id,
+        "session_id": session_id,
+        "cwd": cwd,
+        "project": crate::tray::project_name(cwd),
+        "plan": plan,
+        "format": "markdown",
+        "deadline": deadline,
+        "timeout_secs": state.config.island.permission_timeout_secs,
+    }));
+}
+
+/// Toast title of a permission request.
+fn permission_title(state: &AppState, cwd: &str, tool_name: &str) -> String {
+    let text = crate::messages::EventText {
+        cwd,
+        project: crate::tray::project_name(cwd),
+        tool: tool_name,
+        ..crate::messages::EventText::new(&HookEvent::PermissionRequest, "")
+    };
+    crate::messages::toast_title(&state.config.messages, &text)
+}
+
+/// Broadcast one `permission_countdown` covering every pending request.
+///
+/// Carries absolute deadlines plus the server clock so clients can run
+/// their own smooth timers; skipped entirely when nothing is pending.
+fn broadcast_permission_countdown(state: &AppState) {
+    let pending = state.permissions.get_pending();
+    if pending.is_empty() {
+        return;
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    let requests: Vec<Value> = pending
+        .iter()
+        .map(|r| json!({
+            "id": &r.id,
+            "session_id": &r.session_id,
+            "deadline": r.deadline,
+            "remaining": (r.deadline - now).max(0.0).round() as u64,
+            "total": r.timeout_secs,
+        }))
+        .collect();
+    state.sse.broadcast("permission_countdown", json!({
+        "now": now,
+        "requests": requests,
+    }));
+}
+
+/// UI polls this to get pending permission requests.
+async fn api_permissions(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let requests = state.permissions.get_pending();
+    Json(json!({ "requests": requests }))
+}
+
+// ─── Auto-approve windows ────────────────────────────────
+
+/// Longest auto-approve window accepted.
+const MAX_AUTO_APPROVE_MINUTES: f64 = 8.0 * 60.0;
+
+/// When a hook fired (its `hook_seq`), else now.
+fn hook_time(seq: Option<u64>) -> f64 {
+    seq.map_or_else(now_secs, |s| s as f64 / 1_000_000.0)
+}
+
+fn now_secs() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()
+}
+
+/// Send a pending permission request to the remote channels (with
+/// `general.permission_notify_remote`), with one-tap decision links for
+/// channels that can show buttons.
+fn notify_permission_remote(
+    state: &AppState,
+    id: &str,
+    session_id: &str,
+    cwd: &str,
+    tool_name: &str,
+    summary: Option<&crate::permission::ToolSummary>,
+    cid: &str,
+) {
+    // Same away/escalation rules as agent events (see handle_signal)
+    let remote_now = state.config.presence.escalate_after_secs == 0 || state.presence.is_escalated();
+    if !state.config.general.permission_notify_remote || !remote_now || state.session_tracker.remote_muted(session_id) {
+        return;
+    }
+    let short_sid = if session_id.len() > 8 { &session_id[..8] } else { session_id };
+    let target = summary.map(|s| s.target.as_str()).unwrap_or("");
+    let mut msg = remote::RemoteMessage::new(
+        "permission_request",
+        format!("[Permission] {} | {}\n{}\n{}", short_sid, tool_name, cwd, target),
+    );
+    if state.config.manager.lan {
+        msg.buttons = crate::permit_link::buttons(&state.config.manager.public_url, id);
+    }
+    msg.link = crate::deep_link::session_link(&state.config, session_id).unwrap_or_default();
+    msg.correlation_id = cid.to_string();
+    let cfg = Arc::clone(&state.config);
+    let clients = state.http_clients.clone();
+    tokio::spawn(async move {
+        remote::dispatch_remote(&cfg, &clients, &msg).await;
+    });
+}
+
+/// Grant a permission request if an auto-approve window covers it, leaving
+/// an `auto_approved` event as audit trail. Returns whether it was granted.
+async fn try_auto_approve(state: &Arc<AppState>, session_id: &str, cwd: &str, tool_name: &str, tool_input: &Value) -> bool {
+    let now = now_secs();
+    let Some(window) = state.permissions.take_window(session_id, tool_name, now) else {
+        return false;
+    };
+    let target = crate::permission::ToolSummary::from_tool(tool_name, tool_input)
+        .map(|s| format!(" {}", s.target))
+        .unwrap_or_default();
+    let short_sid = if session_id.len() > 8 { &session_id[..8] } else { session_id };
+    let message = format!("[Auto-approved] {} | {}{}", short_sid, tool_name, target);
+    tracing::info!("Auto-approved {} for session {} (window {})", tool_name, session_id, window.id);
+
+    let extra = json!({ "tool_name": tool_name, "window_id": &window.id });
+    log_permission_audit(state, HookEvent::AutoApproved, session_id, cwd, message, extra).await;
+    broadcast_auto_approve(state);
+    let _ = state.notify_tray.send(());
+    true
+}
+
+/// Answer a request the way an identical one (same session, tool and
+/// input) was answered within `island.permission_repeat_secs`, leaving a
+/// `permission_repeated` event as audit trail. Returns the decision and the
+/// deny message.
+async fn try_repeat_decision(
+    state: &Arc<AppState>,
+    session_id: &str,
+    cwd: &str,
+    tool_name: &str,
+    tool_input: &Value,
+) -> Option<(PermissionDecisionKind, String)> {
+    let window = state.config.island.permission_repeat_secs;
+    if window == 0 {
+        return None;
+    }
+    let (decision, reason) = state.permissions.repeat_decision(session_id, tool_name, tool_input, now_secs(), window as f64)?;
+    let verb = if decision == PermissionDecisionKind::Deny { "denied" } else { "allowed" };
+    let target = crate::permission::ToolSummary::from_tool(tool_name, tool_input)
+        .map(|s| format!(" {}", s.target))
+        .unwrap_or_default();
+    let short_sid = if session_id.len() > 8 { &session_id[..8] } else { session_id };
+    let message = format!("[Repeated, {}] {} | {}{}", verb, short_sid, tool_name, target);
+    tracing::info!("Repeated request for {} in session {} {} again", tool_name, session_id, verb);
+
+    let extra = json!({ "tool_name": tool_name, "decision": &decision });
+    log_permission_audit(state, HookEvent::PermissionRepeated, session_id, cwd, message, extra).await;
+    let _ = state.notify_tray.send(());
+    Some((decision, reason))
+}
+
+/// Log a permission decision made without asking (level 1, so no toast)
+/// and broadcast it with the `extra` fields.
+async fn log_permission_audit(state: &Arc<AppState>, event: HookEvent, session_id: &str, cwd: &str, message: String, extra: Value) {
+    let evt = Event::new(event.clone(), session_id, cwd, &message, 1);
+    {
+        let s = state.clone();
+        let _ = tokio::task::spawn_blocking(move || s.event_store.append_event(evt)).await;
+    }
+    let mut data = json!({
+        "event": event,
+        "session_id": session_id,
+        "cwd": cwd,
+        "message": message,
+    });
+    if let (Some(d), Value::Object(extra)) = (data.as_object_mut(), extra) {
+        d.extend(extra);
+    }
+    state.sse.broadcast("event", data);
+}
+
+/// Push the open windows (with their approval counts) to the UI.
+fn broadcast_auto_approve(state: &AppState) {
+    state.sse.broadcast("auto_approve", json!({ "windows": state.permissions.windows(now_secs()) }));
+}
+
+async fn api_auto_approve_get(State(state): State<Arc<AppState>>) -> Json<Value> {
+    Json(json!({ "windows": state.permissions.windows(now_secs()) }))
+}
+
+#[derive(Deserialize)]
+struct AutoApproveBody {
+    /// Limit to one session (full ID or prefix); omitted = all sessions.
+    #[serde(default)]
+    session_id: Option<String>,
+    minutes: f64,
+    /// Limit to these tools; omitted/empty = all tools.
+    #[serde(default)]
+    tools: Vec<String>,
+}
+
+/// POST /api/permissions/auto-approve — approve matching permission
+/// requests without asking for the next `minutes`. Requests already
+/// waiting that the window covers are approved right away.
+async fn api_auto_approve_start(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<AutoApproveBody>, JsonRejection>,
+) -> Json<Value> {
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    if !(body.minutes > 0.0 && body.minutes <= MAX_AUTO_APPROVE_MINUTES) {
+        return Json(json!({
+            "ok": false,
+            "error": format!("minutes must be between 0 and {}", MAX_AUTO_APPROVE_MINUTES),
+        }));
+    }
+    let session_id = body.session_id
+        .filter(|s| !s.is_empty())
+        .map(|s| state.session_tracker.resolve_short_id(&s).unwrap_or(s));
+    let tools: Vec<String> = body.tools.into_iter().filter(|t| !t.is_empty()).collect();
+    let window = state.permissions.add_window(session_id, tools, now_secs(), body.minutes * 60.0);
+    tracing::info!(
+        "Auto-approve window {} opened for {} min (session {:?}, tools {:?})",
+        window.id, body.minutes, window.session_id, window.tools,
+    );
+
+    for req in state.permissions.get_pending() {
+        if !try_auto_approve(&state, &req.session_id, &req.cwd, &req.tool_name, &req.tool_input).await {
+            continue;
+        }
+        let reply = crate::permission::PermissionReply {
+            decision: PermissionDecisionKind::Allow,
+            selected_suggestions: None,
+            message: String::new(),
+            allowed_paths: None,
+        };
+        if !state.permissions.respond(&req.id, reply, now_secs()) {
+            continue;
+        }
+        state.session_tracker.update(&req.session_id, SessionUpdate {
+            status: Some(SessionStatus::Active),
+            notification_type: Some(String::new()),
+            notification_message: Some(String::new()),
+            ..Default::default()
+        });
+        state.sse.broadcast("activity", json!({
+            "event": "permission_resolved",
+            "session_id": &req.session_id,
+            "decision": PermissionDecisionKind::Allow,
+        }));
+    }
+    sync_island_permissions(&state);
+    broadcast_auto_approve(&state);
+    Json(json!({ "ok": true, "window": window }))
+}
 
-    Json(json!({ "ok": ok }))
+#[derive(Deserialize)]
+struct AutoApproveStopQuery {
+    /// Window to close; omitted = all.
+    id: Option<String>,
 }
 
-/// UI polls this to get pending permission requests.
-async fn api_permissions(State(state): State<Arc<AppState>>) -> Json<Value> {
-    let requests = state.permissions.get_pending();
-    Json(json!({ "requests": requests }))
+/// DELETE /api/permissions/auto-approve[?id=] — end windows early.
+async fn api_auto_approve_stop(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<AutoApproveStopQuery>,
+) -> Json<Value> {
+    let closed = state.permissions.close_windows(q.id.as_deref());
+    if closed > 0 {
+        tracing::info!("Closed {} auto-approve window(s)", closed);
+        broadcast_auto_approve(&state);
+    }
+    Json(json!({ "ok": true, "closed": closed }))
 }
 
 // ─── PreToolUse check endpoint ───────────────────────────
@@ -1198,7 +4491,8 @@ const SAFE_TOOLS: &[&str] = &[
 /// Returns PreToolUse hookSpecificOutput format.
 ///
 /// Flow:
-/// 1. Safe tool → instant allow
+/// 0. `sensitive` match → reported; a `confirm` match skips 1, 2, 2b, 2c
+/// 1. Safe tool → instant allow; session paused over budget → deny
 /// 2. Session rule cached → instant allow
 /// 3. Otherwise → register permission request, long-poll, return decision
 async fn api_pre_tool_check(
@@ -1213,9 +4507,25 @@ async fn api_pre_tool_check(
     let cwd = payload.cwd;
     let tool_name = payload.tool_name;
     let tool_input = payload.tool_input;
+    let cid = payload.correlation_id;
+
+    // Ignored project: no decision, the agent's own permissions apply
+    if state.ignore_paths.matches(&cwd) {
+        return Json(json!({}));
+    }
+
+    // 0. Sensitive commands (see sensitive.rs)
+    let sensitive = state.sensitive.check(&tool_name, &tool_input);
+    if let Some(hit) = &sensitive {
+        report_sensitive(&state, &session_id, &cwd, hit).await;
+    }
+    let must_confirm = sensitive.is_some_and(|h| h.level == crate::sensitive::Level::Confirm);
+    if must_confirm {
+        tracing::info!("[{}] {} in session {} matches a sensitive pattern \u{2014} asking", cid, tool_name, session_id);
+    }
 
     // 1. Safe tools → instant allow
-    if SAFE_TOOLS.contains(&tool_name.as_str()) {
+    if !must_confirm && SAFE_TOOLS.contains(&tool_name.as_str()) {
         return Json(json!({
             "hookSpecificOutput": {
                 "hookEventName": "PreToolUse",
@@ -1225,8 +4535,22 @@ async fn api_pre_tool_check(
         }));
     }
 
+    // 1b. Over budget with `pause_at_limit`
+    if let Some(reason) = state.budget.paused(&session_id, &cwd) {
+        return Json(json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PreToolUse",
+                "permissionDecision": "deny",
+                "permissionDecisionReason": reason
+            }
+        }));
+    }
+
     // 2. Session auto-approve rules
-    if state.permissions.check_session_rule(&session_id, &tool_name) {
+    if !must_confirm
+        && (state.permissions.check_session_rule(&session_id, &tool_name)
+            || state.permissions.check_session_paths(&session_id, &cwd, &tool_name, &tool_input))
+    {
         return Json(json!({
             "hookSpecificOutput": {
                 "hookEventName": "PreToolUse",
@@ -1236,66 +4560,93 @@ async fn api_pre_tool_check(
         }));
     }
 
+    // 2b. Auto-approve window
+    if !must_confirm && try_auto_approve(&state, &session_id, &cwd, &tool_name, &tool_input).await {
+        return Json(json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PreToolUse",
+                "permissionDecision": "allow",
+                "permissionDecisionReason": "auto-approve window"
+            }
+        }));
+    }
+
+    // 2c. Same request answered moments ago
+    let repeated = if must_confirm {
+        None
+    } else {
+        try_repeat_decision(&state, &session_id, &cwd, &tool_name, &tool_input).await
+    };
+    if let Some((decision, reason)) = repeated {
+        let (perm_decision, default_reason) = match decision {
+            PermissionDecisionKind::Deny => ("deny", "identical request denied moments ago"),
+            _ => ("allow", "identical request allowed moments ago"),
+        };
+        let reason = if reason.is_empty() { default_reason } else { reason.as_str() };
+        return Json(json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PreToolUse",
+                "permissionDecision": perm_decision,
+                "permissionDecisionReason": reason
+            }
+        }));
+    }
+
     // 3. Register permission request and long-poll
     let id = uuid::Uuid::new_v4().to_string();
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+    let summary = crate::permission::ToolSummary::from_tool(&tool_name, &tool_input);
+    let plan = crate::permission::plan_text(&tool_name, &tool_input);
 
+    let priority = permission_priority(&state, &session_id, &cwd);
     let req = crate::permission::PermissionRequest {
         id: id.clone(),
         session_id: session_id.clone(),
         cwd: cwd.clone(),
         tool_name: tool_name.clone(),
         tool_input: tool_input.clone(),
+        summary: summary.clone(),
+        plan: plan.clone(),
         permission_suggestions: json!([]),
         timestamp: now,
         timeout_secs: state.config.island.permission_timeout_secs,
+        deadline: now + state.config.island.permission_timeout_secs as f64,
+        priority,
     };
 
     let rx = state.permissions.register(req);
     let timeout_secs = state.config.island.permission_timeout_secs;
+    tracing::info!("[{}] Permission request {} for {} in session {}", cid, id, tool_name, session_id);
+    sync_island_permissions(&state);
 
     // SSE broadcast + sound + auto-expand island
     state.sse.broadcast("permission_request", json!({
         "id": &id,
         "tool_name": &tool_name,
+        "summary": &summary,
         "session_id": &session_id,
         "timeout_secs": timeout_secs,
+        "deadline": now + timeout_secs as f64,
+        "priority": priority,
+        "correlation_id": &cid,
     }));
+    if let Some(plan) = &plan {
+        broadcast_plan_review(&state, &id, &session_id, &cwd, plan, now + timeout_secs as f64);
+    }
     let _ = state.notify_tray.send(());
 
-    if let Some(handle) = state.app_handle.get() {
-        use tauri::Manager;
-        if let Some(w) = handle.get_webview_window("island") {
-            let _ = w.show();
-            let _ = w.eval("if(window.onExpand)window.onExpand();fetchPermissions();");
-            let pw = state.config.island.panel_width;
-            let ph = state.config.island.panel_height;
-            tokio::task::spawn_blocking(move || {
-                crate::island::expand(&w, pw, ph);
-            });
-        }
-        if state.live_sound_enabled.load(Ordering::Relaxed) {
-            let st = read_lock!(state.live_sound_permission).clone();
-            crate::tray::play_notification_sound(&st);
+    pop_island(&state, "permission");
+    if state.app_handle.get().is_some() {
+        if state.presence.is_away() {
+            state.presence.defer(permission_title(&state, &cwd, &tool_name), String::new());
+        } else {
+            toast_permission(&state, &id, &session_id, &cwd, &tool_name, summary.as_ref(), now + timeout_secs as f64);
+            if state.live_sound_enabled.load(Ordering::Relaxed) {
+                crate::tray::play_notification_sound(&permission_sound(&state, priority));
+            }
         }
     }
-
-    // Countdown SSE
-    let countdown_sse = state.sse.clone();
-    let countdown_id = id.clone();
-    let countdown_handle = tokio::spawn(async move {
-        let mut remaining = timeout_secs;
-        loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-            remaining = remaining.saturating_sub(10);
-            countdown_sse.broadcast("permission_countdown", json!({
-                "id": &countdown_id,
-                "remaining": remaining,
-                "total": timeout_secs,
-            }));
-            if remaining == 0 { break; }
-        }
-    });
+    notify_permission_remote(&state, &id, &session_id, &cwd, &tool_name, summary.as_ref(), &cid);
 
     // Long-poll: wait for decision
     let decision = tokio::time::timeout(
@@ -1303,17 +4654,21 @@ async fn api_pre_tool_check(
         rx,
     ).await;
 
-    countdown_handle.abort();
-
     match decision {
-        Ok(Ok(d)) => {
+        Ok(Ok(reply)) => {
+            let d = reply.decision;
             let perm_decision = match &d {
-                PermissionDecisionKind::Allow | PermissionDecisionKind::AllowSession | PermissionDecisionKind::AlwaysAllow => "allow",
-                PermissionDecisionKind::Deny => "deny",
+                PermissionDecisionKind::Allow
+                | PermissionDecisionKind::AllowSession
+                | PermissionDecisionKind::AlwaysAllow
+                | PermissionDecisionKind::ApprovePlan => "allow",
+                PermissionDecisionKind::Deny | PermissionDecisionKind::RejectPlan => "deny",
                 PermissionDecisionKind::AskTerminal => "ask", // hand off to terminal
             };
             let reason = match &d {
                 PermissionDecisionKind::AskTerminal => "user chose to handle in terminal",
+                // permissionDecisionReason is shown to the agent on deny
+                _ if d.refuses() && !reply.message.is_empty() => reply.message.as_str(),
                 _ => "user decision from Agent Desk",
             };
             Json(json!({
@@ -1326,7 +4681,11 @@ async fn api_pre_tool_check(
         }
         _ => {
             // Timeout or channel closed → ask Claude Code to show its own prompt
-            state.permissions.remove(&id);
+            if let Some(req) = state.permissions.expire(&id, now_secs()) {
+                permission_expired(&state, &req, "ask").await;
+            }
+            crate::toast::finish_countdown(&id);
+            sync_island_permissions(&state);
             Json(json!({
                 "hookSpecificOutput": {
                     "hookEventName": "PreToolUse",
@@ -1427,22 +4786,92 @@ async fn api_settings_get(State(state): State<Arc<AppState>>) -> Json<Value> {
     let sound_stop = read_lock!(state.live_sound_stop).clone();
     let sound_notification = read_lock!(state.live_sound_notification).clone();
     let sound_permission = read_lock!(state.live_sound_permission).clone();
+    let portable = crate::config::is_portable();
     let autostart = state.app_handle.get()
-        .and_then(|h| {
-            use tauri_plugin_autostart::ManagerExt;
-            h.autolaunch().is_enabled().ok()
-        })
-        .unwrap_or(false);
+        .filter(|_| !portable)
+        .is_some_and(crate::autostart::is_enabled);
     Json(json!({
         "hotkey": hotkey,
         "sound_enabled": sound_enabled,
         "sound_stop": sound_stop,
         "sound_notification": sound_notification,
         "sound_permission": sound_permission,
+        "auto_show": read_lock!(state.live_auto_show).clone(),
+        "auto_expand": read_lock!(state.live_auto_expand).clone(),
+        "respect_dnd": state.live_respect_dnd.load(Ordering::Relaxed),
         "autostart": autostart,
+        "portable": portable,
     }))
 }
 
+/// GET /api/settings/schema — JSON Schema of config.yaml.
+async fn api_settings_schema() -> Json<Value> {
+    Json(crate::config::config_schema())
+}
+
+/// POST /api/settings/validate — check candidate config.yaml text (the raw
+/// request body) without saving it. `ok` is false if it has errors;
+/// warnings (unknown keys) don't count.
+async fn api_settings_validate(body: String) -> Json<Value> {
+    let issues = crate::config::validate_config(&body);
+    let ok = !issues.iter().any(|i| i.severity == "error");
+    Json(json!({ "ok": ok, "issues": issues }))
+}
+
+/// GET /api/settings/sounds — names the sound settings accept: the
+/// built-in ones and the `.wav` files in the `sounds` folder next to
+/// config.yaml.
+async fn api_settings_sounds() -> Json<Value> {
+    let custom = tokio::task::spawn_blocking(crate::tray::custom_sounds).await.unwrap_or_default();
+    Json(json!({ "builtin": crate::tray::BUILTIN_SOUNDS, "custom": custom }))
+}
+
+#[derive(Deserialize)]
+struct TestNotificationRequest {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// POST /api/settings/test-notification `{type}` — show the toast and play
+/// the sound a real event of that type would, with sample text: `stop`,
+/// `notification`, `idle`, `permission` or `limit`. Away and focus
+/// suppression are skipped; `sound_enabled` is not.
+async fn api_settings_test_notification(
+    State(state): State<Arc<AppState>>,
+    body: Result<Json<TestNotificationRequest>, JsonRejection>,
+) -> Json<Value> {
+    let req = match body {
+        Ok(Json(r)) => r,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let (event, ntype) = match req.kind.as_str() {
+        "stop" => (HookEvent::Stop, ""),
+        "notification" => (HookEvent::Notification, ""),
+        "idle" => (HookEvent::Notification, "idle_prompt"),
+        "permission" => (HookEvent::PermissionRequest, ""),
+        "limit" => (HookEvent::LimitReached, ""),
+        other => return Json(json!({ "ok": false, "error": format!("unknown type: {}", other) })),
+    };
+    if !can_toast(&state) {
+        return Json(json!({ "ok": false, "error": "no desktop to show toasts on" }));
+    }
+    let proj = format!("{} agent-desk", crate::session::color_glyph(crate::session::SESSION_COLORS[0].1));
+    let text = crate::messages::EventText {
+        sid: "00000000",
+        cwd: "agent-desk",
+        project: &proj,
+        message: "Test notification",
+        tool: "Bash",
+        duration: "1m 05s",
+        ..crate::messages::EventText::new(&event, ntype)
+    };
+    let title = crate::messages::toast_title(&state.config.messages, &text);
+    let body = crate::messages::toast_body(&state.config.messages, &text);
+    let sound = event_sound(&state, &event);
+    show_toast(&state, &title, &body, sound.clone(), Some(ToastKey { group: "settings", tag: "test" }), "");
+    Json(json!({ "ok": true, "title": title, "sound": sound }))
+}
+
 async fn api_settings_save(
     State(state): State<Arc<AppState>>,
     Json(body): Json<Value>,
@@ -1460,13 +4889,28 @@ async fn api_settings_save(
     if let Some(v) = body.get("sound_permission").and_then(|v| v.as_str()) {
         *write_lock!(state.live_sound_permission) = v.to_string();
     }
+    // Auto-show/expand per event type (live update)
+    let event_list = |key: &str| -> Option<Vec<String>> {
+        body.get(key).and_then(|v| serde_json::from_value::<Vec<String>>(v.clone()).ok())
+    };
+    let auto_show = event_list("auto_show");
+    let auto_expand = event_list("auto_expand");
+    if let Some(v) = &auto_show {
+        *write_lock!(state.live_auto_show) = v.clone();
+    }
+    if let Some(v) = &auto_expand {
+        *write_lock!(state.live_auto_expand) = v.clone();
+    }
+    if let Some(v) = body.get("respect_dnd").and_then(|v| v.as_bool()) {
+        state.live_respect_dnd.store(v, Ordering::Relaxed);
+    }
 
-    // Autostart toggle via plugin
-    if let Some(v) = body.get("autostart").and_then(|v| v.as_bool()) {
+    // Autostart toggle (ignored in portable mode)
+    let portable = crate::config::is_portable();
+    if let Some(v) = body.get("autostart").and_then(|v| v.as_bool()).filter(|_| !portable) {
         if let Some(handle) = state.app_handle.get() {
-            use tauri_plugin_autostart::ManagerExt;
-            let al = handle.autolaunch();
-            if v { let _ = al.enable(); } else { let _ = al.disable(); }
+            let _ = crate::autostart::set_enabled(handle, v)
+                .inspect_err(|e| tracing::warn!("Failed to change autostart: {}", e));
         }
     }
 
@@ -1486,7 +4930,16 @@ async fn api_settings_save(
         if let Some(v) = body_clone.get("sound_permission").and_then(|v| v.as_str()) {
             changes.push(("sound_permission", format!("\"{}\"", v)));
         }
-        if let Some(v) = body_clone.get("autostart") {
+        if let Some(v) = &auto_show {
+            changes.push(("auto_show", serde_json::to_string(v).unwrap_or_default()));
+        }
+        if let Some(v) = &auto_expand {
+            changes.push(("auto_expand", serde_json::to_string(v).unwrap_or_default()));
+        }
+        if let Some(v) = body_clone.get("respect_dnd").and_then(|v| v.as_bool()) {
+            changes.push(("respect_dnd", format!("{}", v)));
+        }
+        if let Some(v) = body_clone.get("autostart").filter(|_| !portable) {
             changes.push(("autostart", format!("{}", v)));
         }
         if !changes.is_empty() {
@@ -1498,12 +4951,362 @@ async fn api_settings_save(
     Json(json!({ "ok": true }))
 }
 
+// ─── Setup wizard ───────────────────────────────────────
+
+/// Where `POST /api/setup/run` installs the Claude Code hooks.
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum HookScope {
+    /// `~/.claude/settings.json` — every project.
+    Global,
+    /// `<project_dir>/.claude/settings.local.json` — one project.
+    Project,
+    #[default]
+    None,
+}
+
+#[derive(Deserialize)]
+struct SetupRunBody {
+    #[serde(default)]
+    hooks: HookScope,
+    #[serde(default)]
+    project_dir: String,
+    autostart: Option<bool>,
+    port: Option<u16>,
+}
+
+/// GET /api/setup/status — what the first-run wizard needs to know.
+async fn api_setup_status(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let claude_cli = state.config.general.claude_cli.clone();
+    let checks = tokio::task::spawn_blocking(move || {
+        use crate::setup;
+        let hook_binary = setup::hook_binary_path();
+        let settings_path = setup::claude_settings_path();
+        let hooks_configured = settings_path.as_deref().is_some_and(setup::hooks_installed);
+        let claude_path = setup::find_on_path(&claude_cli);
+        let projects_dir = setup::claude_projects_dir();
+        let transcripts_readable = projects_dir.as_ref().is_some_and(|d| std::fs::read_dir(d).is_ok());
+        json!({
+            "hook_binary": hook_binary.map(|p| p.display().to_string()),
+            "hooks": {
+                "configured": hooks_configured,
+                "settings_path": settings_path.map(|p| p.display().to_string()),
+            },
+            "claude": {
+                "found": claude_path.is_some(),
+                "path": claude_path.map(|p| p.display().to_string()),
+            },
+            "transcripts": {
+                "dir": projects_dir.map(|p| p.display().to_string()),
+                "readable": transcripts_readable,
+            },
+        })
+    }).await.unwrap_or_else(|_| json!({}));
+
+    let portable = crate::config::is_portable();
+    let autostart = state.app_handle.get()
+        .filter(|_| !portable)
+        .is_some_and(crate::autostart::is_enabled);
+
+    let mut out = json!({
+        "ok": true,
+        "port": state.config.manager.port,
+        "autostart": autostart,
+        "portable": portable,
+    });
+    if let (Some(obj), Value::Object(extra)) = (out.as_object_mut(), checks) {
+        obj.extend(extra);
+    }
+    Json(out)
+}
+
+/// POST /api/setup/run — apply the options picked in the wizard.
+///
+/// Each step is reported separately so the wizard can show partial success.
+/// A port change is saved to config.yaml and takes effect on restart; hooks
+/// installed in the same run already point at the new port.
+async fn api_setup_run(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<SetupRunBody>,
+) -> Json<Value> {
+    let portable = crate::config::is_portable();
+    let port = body.port.unwrap_or(state.config.manager.port);
+    if port == 0 {
+        return Json(json!({ "ok": false, "error": "invalid port" }));
+    }
+    if body.hooks == HookScope::Project && body.project_dir.trim().is_empty() {
+        return Json(json!({ "ok": false, "error": "project_dir required for project hooks" }));
+    }
+
+    // --- 1. Autostart (OS entry; skipped in portable mode) ---
+    let autostart = match body.autostart {
+        Some(_) if portable => json!({ "ok": false, "error": "not available in portable mode" }),
+        Some(v) => match state.app_handle.get() {
+            Some(handle) => match crate::autostart::set_enabled(handle, v) {
+                Ok(()) => json!({ "ok": true, "enabled": v }),
+                Err(e) => json!({ "ok": false, "error": e }),
+            },
+            None => json!({ "ok": false, "error": "app not ready" }),
+        },
+        None => Value::Null,
+    };
+
+    // --- 2. Hooks + config.yaml (blocking file I/O) ---
+    let current_port = state.config.manager.port;
+    let autostart_saved = body.autostart.filter(|_| !portable);
+    let hooks = body.hooks;
+    let project_dir = body.project_dir;
+    let observe_tools = state.config.general.observe_tools;
+    let result = tokio::task::spawn_blocking(move || {
+        let hooks_result = match hooks {
+            HookScope::None => Value::Null,
+            HookScope::Global => match crate::setup::claude_settings_path() {
+                Some(path) => install_result(&path, port, observe_tools),
+                None => json!({ "ok": false, "error": "cannot determine home directory" }),
+            },
+            HookScope::Project => {
+                let path = std::path::Path::new(project_dir.trim())
+                    .join(".claude")
+                    .join("settings.local.json");
+                install_result(&path, port, observe_tools)
+            }
+        };
+
+        if let Some(v) = autostart_saved {
+            crate::config::save_island_settings(&[("autostart", if v { "true" } else { "false" })]);
+        }
+        let port_saved = port == current_port
+            || crate::config::save_section_settings("manager", &[("port", &port.to_string())]);
+        (hooks_result, port_saved)
+    }).await.unwrap_or_else(|e| (json!({ "ok": false, "error": format!("{}", e) }), port == current_port));
+    let (result, port_saved) = result;
+
+    let step_ok = |v: &Value| v.is_null() || v.get("ok").and_then(|o| o.as_bool()).unwrap_or(false);
+    let mut reply = json!({
+        "ok": step_ok(&result) && step_ok(&autostart) && port_saved,
+        "hooks": result,
+        "autostart": autostart,
+        "port": port,
+        "restart_required": port != current_port && port_saved,
+    });
+    if !port_saved {
+        reply["port_error"] = json!("config.yaml could not be read; port not saved");
+    }
+    Json(reply)
+}
+
+#[derive(Deserialize, Default)]
+struct SetupPlanQuery {
+    /// A project's `.claude/settings.local.json` instead of the global
+    /// settings.
+    #[serde(default)]
+    project_dir: String,
+}
+
+#[derive(Deserialize)]
+struct SetupApplyBody {
+    /// `plan_id` from `GET /api/setup/plan`: the changes being confirmed.
+    plan_id: String,
+    #[serde(default)]
+    project_dir: String,
+}
+
+/// Settings file of the global install, or of `project_dir`'s.
+fn hooks_settings_path(project_dir: &str) -> Result<std::path::PathBuf, String> {
+    if project_dir.trim().is_empty() {
+        crate::setup::claude_settings_path().ok_or_else(|| "cannot determine home directory".to_string())
+    } else {
+        Ok(std::path::Path::new(project_dir.trim()).join(".claude").join("settings.local.json"))
+    }
+}
+
+fn plan_json(plan: &crate::setup::HookPlan) -> Value {
+    let mut v = serde_json::to_value(plan).unwrap_or_default();
+    v["plan_id"] = json!(plan.id());
+    v
+}
+
+/// GET /api/setup/plan — the changes installing the hooks would make to the
+/// settings file, without making them.
+async fn api_setup_plan(State(state): State<Arc<AppState>>, Query(q): Query<SetupPlanQuery>) -> Json<Value> {
+    let (port, observe_tools) = (state.config.manager.port, state.config.general.observe_tools);
+    let result = tokio::task::spawn_blocking(move || {
+        let path = hooks_settings_path(&q.project_dir)?;
+        crate::setup::plan_hooks(&path, port, observe_tools)
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("{}", e)));
+    match result {
+        Ok(plan) => {
+            let mut v = plan_json(&plan);
+            v["ok"] = json!(true);
+            v["dry_run"] = json!(state.config.general.hooks_dry_run);
+            Json(v)
+        }
+        Err(e) => Json(json!({ "ok": false, "error": e })),
+    }
+}
+
+/// POST /api/setup/apply — make the changes of a plan reviewed at
+/// `GET /api/setup/plan`. If the settings file changed since, so that the
+/// plan differs, nothing is written and the new plan is returned.
+async fn api_setup_apply(State(state): State<Arc<AppState>>, Json(body): Json<SetupApplyBody>) -> Json<Value> {
+    let (port, observe_tools) = (state.config.manager.port, state.config.general.observe_tools);
+    let result = tokio::task::spawn_blocking(move || {
+        let path = hooks_settings_path(&body.project_dir).map_err(|e| json!({ "ok": false, "error": e }))?;
+        let plan = crate::setup::plan_hooks(&path, port, observe_tools).map_err(|e| json!({ "ok": false, "error": e }))?;
+        if plan.id() != body.plan_id {
+            return Err(json!({ "ok": false, "error": "the plan changed since it was reviewed", "plan": plan_json(&plan) }));
+        }
+        let changed = crate::setup::apply_hook_plan(&plan).map_err(|e| json!({ "ok": false, "error": e }))?;
+        tracing::info!("Applied hook plan {} to {}", body.plan_id, path.display());
+        Ok(json!({ "ok": true, "changed": changed, "settings_path": path.display().to_string() }))
+    })
+    .await
+    .unwrap_or_else(|e| Err(json!({ "ok": false, "error": format!("{}", e) })));
+    Json(result.unwrap_or_else(|e| e))
+}
+
+/// GET /api/setup/backups — the settings file backups kept, newest first.
+async fn api_setup_backups() -> Json<Value> {
+    let backups = tokio::task::spawn_blocking(crate::settings_backup::list).await.unwrap_or_default();
+    Json(json!({ "ok": true, "backups": backups }))
+}
+
+#[derive(Deserialize)]
+struct RestoreBackupBody {
+    /// Backup file name from `GET /api/setup/backups`; default the newest
+    /// backup of the settings file `project_dir` names.
+    file: Option<String>,
+    #[serde(default)]
+    project_dir: String,
+}
+
+/// POST /api/setup/restore-backup — put a settings file backup back. The
+/// file it replaces is backed up first.
+///
+/// The body is required (`{}` for the newest global backup): its JSON
+/// content type forces a CORS preflight, and `origin_guard` refuses other
+/// origins, so no web page can roll back the hooks.
+async fn api_setup_restore_backup(body: Result<Json<RestoreBackupBody>, JsonRejection>) -> Json<Value> {
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let result = tokio::task::spawn_blocking(move || {
+        let source = hooks_settings_path(&body.project_dir)?;
+        crate::settings_backup::restore(body.file.as_deref(), &source)
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("{}", e)));
+    match result {
+        Ok(b) => Json(json!({ "ok": true, "restored": b.source.display().to_string(), "backup": b.file })),
+        Err(e) => Json(json!({ "ok": false, "error": e })),
+    }
+}
+
+/// Largest backup `POST /api/backup/restore` accepts.
+const BACKUP_BODY_LIMIT: usize = 512 * 1024 * 1024;
+
+/// GET /api/backup — a zip of the config and state (see state_backup.rs).
+async fn api_backup(State(state): State<Arc<AppState>>) -> Response {
+    use axum::http::header;
+    use axum::response::IntoResponse;
+
+    let s = state.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        // Pending session changes first, so the zip has them as they are now
+        s.session_tracker.flush_if_dirty();
+        crate::state_backup::export(&s.config)
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("{}", e)));
+    match result {
+        Ok(zip) => {
+            let name = format!("agent-desk-backup-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+            let headers = [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name)),
+            ];
+            (headers, zip).into_response()
+        }
+        Err(e) => Json(json!({ "ok": false, "error": e })).into_response(),
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct RestoreQuery {
+    /// Also restore Claude Code's `settings.json`. Its hook commands run on
+    /// every agent action, so the dashboard asks before setting this.
+    #[serde(default)]
+    claude_settings: bool,
+}
+
+/// POST /api/backup/restore — body: a zip from `GET /api/backup`, sent as
+/// `Content-Type: application/zip`. Its files are put in place at the next
+/// start.
+///
+/// Restoring rewrites config.yaml and, when asked, the Claude Code hooks, so
+/// a web page must not be able to post one: `origin_guard` checks the
+/// `Origin`, and the content type forces a CORS preflight, which other
+/// origins fail.
+async fn api_backup_restore(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RestoreQuery>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> Json<Value> {
+    let zip_type = headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("application/zip"));
+    if !zip_type {
+        return Json(json!({ "ok": false, "error": "Content-Type must be application/zip" }));
+    }
+    let s = state.clone();
+    let result = tokio::task::spawn_blocking(move || crate::state_backup::stage(&s.config, &body, query.claude_settings))
+        .await
+        .unwrap_or_else(|e| Err(format!("{}", e)));
+    match result {
+        Ok(restore) => Json(json!({
+            "ok": true,
+            "staged": restore.staged,
+            "skipped": restore.skipped,
+            "restart_required": true,
+        })),
+        Err(e) => Json(json!({ "ok": false, "error": e })),
+    }
+}
+
+fn install_result(path: &std::path::Path, port: u16, observe_tools: bool) -> Value {
+    match crate::setup::install_hooks(path, port, observe_tools) {
+        Ok(changed) => json!({ "ok": true, "changed": changed, "settings_path": path.display().to_string() }),
+        Err(e) => json!({ "ok": false, "error": e, "settings_path": path.display().to_string() }),
+    }
+}
+
 // ─── Island config endpoint ─────────────────────────────
 
 async fn api_island_config(State(state): State<Arc<AppState>>) -> Json<Value> {
     Json(serde_json::to_value(&state.config.island).unwrap_or(json!({})))
 }
 
+/// GET /api/island/pill — the pill's one-line text (see `status::pill_text`),
+/// its `pill_state` and the overall state the tray shows, from the same
+/// scan.
+async fn api_island_pill(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let processes = scan_and_merge(&state);
+    let pending = state.permissions.get_pending();
+    let silent = state.hook_health.silent().len();
+    let pill = pill_state(&processes, pending.len(), silent);
+    let pending: Vec<(&str, &str)> = pending.iter().map(|r| (r.session_id.as_str(), r.cwd.as_str())).collect();
+    Json(json!({
+        "text": pill_text(&processes, &pending),
+        "pill_state": pill,
+        "state": compute_state(&processes)["state"],
+    }))
+}
+
 // ─── Chat endpoint ──────────────────────────────────────
 
 #[derive(Deserialize)]
@@ -1511,6 +5314,11 @@ struct ChatQuery {
     session_id: Option<String>,
     cwd: Option<String>,
     after: Option<usize>,
+    /// v2 only: page of history before this index instead of after `after`.
+    before: Option<usize>,
+    /// v2 only: page size. With neither `after` nor `before`, the latest
+    /// `limit` messages.
+    limit: Option<usize>,
 }
 
 async fn api_chat(
@@ -1518,22 +5326,37 @@ async fn api_chat(
     Query(q): Query<ChatQuery>,
 ) -> Json<Value> {
     let session_id = q.session_id.unwrap_or_default();
-    let cwd = q.cwd.unwrap_or_default();
+    let cwd = chat_cwd(&state, &session_id, q.cwd);
     let after = q.after.unwrap_or(0);
 
-    if session_id.is_empty() || cwd.is_empty() {
+    if session_id.is_empty() {
         return Json(json!({ "messages": [], "next_index": 0 }));
     }
 
     let s = state.clone();
-    let result = tokio::task::spawn_blocking(move || {
+    let page = tokio::task::spawn_blocking(move || {
         s.chat_reader.read_messages(&session_id, &cwd, after)
-    }).await.unwrap_or_else(|_| (vec![], 0));
+    }).await.unwrap_or_else(|_| ChatPage::empty(0));
+    broadcast_chat_resets(&state);
 
-    Json(json!({
-        "messages": result.0,
-        "next_index": result.1,
-    }))
+    Json(json!(page))
+}
+
+/// CWD to find a chat's transcript by: the one asked for, else the
+/// tracker's. Empty is fine too — the transcript is then found by session
+/// id alone (see `ChatReader::transcript_path`).
+fn chat_cwd(state: &AppState, session_id: &str, asked: Option<String>) -> String {
+    asked
+        .filter(|c| !c.is_empty())
+        .or_else(|| state.session_tracker.cwd(session_id))
+        .unwrap_or_default()
+}
+
+/// Tell clients to re-render chats whose transcript was rewritten (compaction).
+fn broadcast_chat_resets(state: &AppState) {
+    for session_id in state.chat_reader.take_resets() {
+        state.sse.broadcast("chat_reset", json!({ "session_id": session_id }));
+    }
 }
 
 /// Enriched chat — typed events with model/cost info.
@@ -1542,22 +5365,34 @@ async fn api_chat_v2(
     Query(q): Query<ChatQuery>,
 ) -> Json<Value> {
     let session_id = q.session_id.unwrap_or_default();
-    let cwd = q.cwd.unwrap_or_default();
-    let after = q.after.unwrap_or(0);
+    let cwd = chat_cwd(&state, &session_id, q.cwd);
+    let limit = q.limit.map(|n| n.clamp(1, 1000));
 
-    if session_id.is_empty() || cwd.is_empty() {
-        return Json(json!({ "messages": [], "next_index": 0 }));
+    if session_id.is_empty() {
+        return Json(json!({ "messages": [], "next_index": 0, "total": 0 }));
     }
 
     let s = state.clone();
-    let result = tokio::task::spawn_blocking(move || {
-        s.chat_reader.read_enriched(&session_id, &cwd, after)
-    }).await.unwrap_or_else(|_| (vec![], 0));
+    let page = tokio::task::spawn_blocking(move || match (q.before, q.after, limit) {
+        (Some(before), _, _) => s.chat_reader.read_enriched_before(&session_id, &cwd, before, limit.unwrap_or(200)),
+        // Opening a chat: just the tail, older pages on scroll
+        (None, None, Some(n)) => s.chat_reader.read_enriched_before(&session_id, &cwd, usize::MAX, n),
+        (None, after, _) => s.chat_reader.read_enriched(&session_id, &cwd, after.unwrap_or(0), limit),
+    }).await.unwrap_or_else(|_| ChatPage::empty(0));
+    broadcast_chat_resets(&state);
+
+    Json(json!(page))
+}
 
-    Json(json!({
-        "messages": result.0,
-        "next_index": result.1,
-    }))
+/// GET /api/chat/tool-result/{uuid} — full output of a Bash/Grep result
+/// (a v2 `tool_result` event with `full_bytes`).
+async fn api_chat_tool_result(State(state): State<Arc<AppState>>, Path(uuid): Path<String>) -> Json<Value> {
+    let s = state.clone();
+    let found = tokio::task::spawn_blocking(move || s.chat_reader.tool_output(&uuid)).await.ok().flatten();
+    match found {
+        Some((content, truncated)) => Json(json!({ "ok": true, "bytes": content.len(), "truncated": truncated, "content": content })),
+        None => Json(json!({ "ok": false, "error": "no captured output for this result" })),
+    }
 }
 
 /// POST /api/chat/send — send a message to a Claude Code session via SendInput.
@@ -1569,10 +5404,20 @@ async fn api_chat_send(
         Ok(Json(p)) => p,
         Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
     };
+    // All Win32 calls must happen on spawn_blocking (not tokio thread)
+    let s = state.clone();
+    let result = tokio::task::spawn_blocking(move || send_chat(&s, &payload))
+        .await
+        .unwrap_or_else(|e| Err(json!({ "ok": false, "error": format!("task join error: {}", e) })));
+    Json(result.map(|()| json!({ "ok": true })).unwrap_or_else(|e| e))
+}
 
+/// Type `payload.message` into the session's terminal. Blocking (Win32
+/// input); the error is the JSON reply for the API.
+pub fn send_chat(state: &AppState, payload: &ChatSendPayload) -> Result<(), Value> {
     let message = payload.message.trim().to_string();
     if message.is_empty() {
-        return Json(json!({ "ok": false, "error": "empty message" }));
+        return Err(json!({ "ok": false, "error": "empty message" }));
     }
 
     // Safety check: verify session state if session_id is provided
@@ -1581,14 +5426,14 @@ async fn api_chat_send(
         if let Some(info) = sessions.get(&payload.session_id) {
             match info.status {
                 SessionStatus::Active if !payload.force => {
-                    return Json(json!({
+                    return Err(json!({
                         "ok": false,
                         "error": "session is active (working). Set force=true to send anyway.",
                         "status": "active"
                     }));
                 }
                 SessionStatus::Ended => {
-                    return Json(json!({ "ok": false, "error": "session has ended" }));
+                    return Err(json!({ "ok": false, "error": "session has ended" }));
                 }
                 _ => {} // Idle, Waiting, Stopped, Active+force — all OK
             }
@@ -1599,7 +5444,7 @@ async fn api_chat_send(
     let pid = payload.pid.or_else(|| {
         if payload.cwd.is_empty() { return None; }
         let cwd_norm = payload.cwd.replace('/', "\\").to_lowercase();
-        let merged = scan_and_merge(&state);
+        let merged = scan_and_merge(state);
         merged.iter().find_map(|proc| {
             let pcwd = proc.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
             if pcwd.replace('/', "\\").to_lowercase() == cwd_norm {
@@ -1610,43 +5455,108 @@ async fn api_chat_send(
         })
     });
 
-    let cwd = payload.cwd.clone();
     let cached = state.registry.get_cached();
-    let sse = state.sse.clone();
-    let session_id = payload.session_id.clone();
-    let msg_clone = message.clone();
-
-    // All Win32 calls must happen on spawn_blocking (not tokio thread)
-    let result = tokio::task::spawn_blocking(move || {
-        // 1. Find terminal window
-        let terminal = match focus::find_terminal(&cwd, &cached, pid) {
-            Some(m) => m,
-            None => return Err("terminal window not found".to_string()),
-        };
-
-        // 2. Focus it
-        focus::focus_terminal(&terminal);
-
-        // 3. Wait for focus to settle
-        std::thread::sleep(std::time::Duration::from_millis(150));
+    // 1. Find terminal window
+    let result = match focus::find_terminal(&payload.session_id, &payload.cwd, &cached, pid) {
+        Some(terminal) => {
+            // 2. Focus it; never type into another agent's tab
+            if focus::focus_terminal(&terminal).tab_verified == Some(false) {
+                Err("the session's terminal tab could not be brought up".to_string())
+            } else {
+                // 3. Wait for focus to settle
+                std::thread::sleep(std::time::Duration::from_millis(150));
 
-        // 4. Type the message + Enter
-        crate::send_input::send_text_to_focused_window(&msg_clone)
-    })
-    .await
-    .unwrap_or_else(|e| Err(format!("task join error: {}", e)));
+                // 4. Type the message + Enter
+                crate::send_input::send_text_to_focused_window(&message)
+            }
+        }
+        None => Err("terminal window not found".to_string()),
+    };
 
     match result {
         Ok(()) => {
-            sse.broadcast("chat_sent", json!({
-                "session_id": &session_id,
+            state.sse.broadcast("chat_sent", json!({
+                "session_id": &payload.session_id,
                 "message": &message,
             }));
-            Json(json!({ "ok": true }))
+            Ok(())
         }
         Err(e) => {
-            tracing::warn!("chat/send failed for session {}: {}", session_id, e);
-            Json(json!({ "ok": false, "error": e }))
+            tracing::warn!("chat/send failed for session {}: {}", payload.session_id, e);
+            Err(json!({ "ok": false, "error": e }))
         }
     }
 }
+
+/// Configured quick replies, with `label` defaulting to the reply text.
+pub fn quick_replies(config: &Config) -> Vec<crate::config::QuickReply> {
+    config.general.quick_replies
+        .iter()
+        .map(|r| crate::config::QuickReply {
+            label: if r.label.is_empty() { r.text.clone() } else { r.label.clone() },
+            ..r.clone()
+        })
+        .collect()
+}
+
+/// Send the canned reply `reply_id` to session `id` through the chat-send
+/// path. Blocking, like `send_chat`.
+pub fn send_quick_reply(state: &AppState, id: &str, reply_id: &str) -> Result<(), Value> {
+    let Some(reply) = state.config.general.quick_replies.iter().find(|r| r.id == reply_id) else {
+        return Err(json!({ "ok": false, "error": format!("unknown reply: {}", reply_id) }));
+    };
+    // Accept a full session ID or an unambiguous prefix
+    let sid = state.session_tracker.resolve_short_id(id).unwrap_or_else(|| id.to_string());
+    let Some(info) = state.session_tracker.get_active(state.config.general.session_ttl).remove(&sid) else {
+        return Err(json!({ "ok": false, "error": "session not found" }));
+    };
+    tracing::info!("Quick reply {:?} to session {}", reply_id, sid);
+    send_chat(state, &ChatSendPayload {
+        session_id: sid,
+        cwd: info.cwd,
+        message: reply.text.clone(),
+        pid: info.agent_pid,
+        force: false,
+    })
+}
+
+async fn api_replies(State(state): State<Arc<AppState>>) -> Json<Value> {
+    Json(json!({ "replies": quick_replies(&state.config) }))
+}
+
+#[derive(Deserialize)]
+struct ReplyBody {
+    reply_id: String,
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: Option<String>,
+}
+
+/// POST /api/session/{id}/export-chat?format=md — the `export_chat` action.
+async fn api_session_export_chat(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(q): Query<ExportQuery>,
+) -> Json<Value> {
+    if q.format.as_deref().is_some_and(|f| f != "md") {
+        return Json(json!({ "ok": false, "error": "format must be md" }));
+    }
+    let action = crate::action::Action::ExportChat { session_id: id };
+    run_action(&state, action, "api".to_string()).await
+}
+
+/// POST /api/session/{id}/reply — send a configured quick reply.
+async fn api_session_reply(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    body: Result<Json<ReplyBody>, JsonRejection>,
+) -> Json<Value> {
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(e) => return Json(json!({ "ok": false, "error": format!("{}", e) })),
+    };
+    let action = crate::action::Action::SendReply { session_id: id, reply_id: body.reply_id };
+    run_action(&state, action, "island".to_string()).await
+}
@@ -0,0 +1,132 @@
+//! Background service: run the headless core (`--headless`) so monitoring
+//! and remote notifications survive logout.
+//!
+//! Windows: a Task Scheduler task started at boot as the current user
+//! without a stored password (S4U), so it keeps the user's data dir.
+//! Linux: a systemd user unit, with lingering enabled so it outlives the
+//! login session.
+//!
+//! A desktop launch that finds the service on the port connects to it
+//! instead of starting a second server (see `is_headless_service`).
+
+use std::io::{Read, Write};
+use std::process::Command;
+use std::time::Duration;
+
+#[cfg(windows)]
+const TASK_NAME: &str = "Agent Desk";
+#[cfg(not(windows))]
+const UNIT_NAME: &str = "agent-desk.service";
+
+/// Whether the server on `port` is an agent-desk running headless.
+pub fn is_headless_service(port: u16) -> bool {
+    let Ok(mut stream) = crate::setup::connect_local(port, Duration::from_millis(500)) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+    let req = format!("GET /api/health HTTP/1.0\r\nHost: {}\r\n\r\n", crate::setup::local_addr(port));
+    if stream.write_all(req.as_bytes()).is_err() {
+        return false;
+    }
+    let mut resp = String::new();
+    let _ = stream.read_to_string(&mut resp);
+    let Some((_, body)) = resp.split_once("\r\n\r\n") else {
+        return false;
+    };
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("headless").and_then(|h| h.as_bool()))
+        .unwrap_or(false)
+}
+
+fn run(cmd: &mut Command) -> Result<(), String> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let out = cmd.output().map_err(|e| format!("{:?}: {}", cmd.get_program(), e))?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        Err(format!("{:?} failed: {}", cmd.get_program(), if stderr.trim().is_empty() { stdout } else { stderr }.trim()))
+    }
+}
+
+/// Register and start the service. Returns a human-readable summary.
+#[cfg(windows)]
+pub fn install() -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let user = match (std::env::var("USERDOMAIN"), std::env::var("USERNAME")) {
+        (Ok(d), Ok(u)) => format!("{}\\{}", d, u),
+        (_, Ok(u)) => u,
+        _ => return Err("cannot determine the current user".into()),
+    };
+    let action = format!("\"{}\" --headless", exe.display());
+    // Boot-time tasks need an elevated prompt to create
+    run(Command::new("schtasks").args([
+        "/Create", "/F", "/TN", TASK_NAME, "/SC", "ONSTART", "/RU", &user, "/NP", "/RL", "LIMITED", "/TR", &action,
+    ]))?;
+    run(Command::new("schtasks").args(["/Run", "/TN", TASK_NAME]))?;
+    Ok(format!("Scheduled task \"{}\" installed and started ({})", TASK_NAME, action))
+}
+
+/// Stop and remove the service.
+#[cfg(windows)]
+pub fn uninstall() -> Result<String, String> {
+    let _ = run(Command::new("schtasks").args(["/End", "/TN", TASK_NAME]));
+    run(Command::new("schtasks").args(["/Delete", "/F", "/TN", TASK_NAME]))?;
+    Ok(format!("Scheduled task \"{}\" removed", TASK_NAME))
+}
+
+#[cfg(not(windows))]
+fn unit_path() -> Result<std::path::PathBuf, String> {
+    let base = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(d) if !d.is_empty() => std::path::PathBuf::from(d),
+        _ => std::path::PathBuf::from(std::env::var("HOME").map_err(|_| "HOME not set")?).join(".config"),
+    };
+    Ok(base.join("systemd").join("user").join(UNIT_NAME))
+}
+
+/// Register and start the service. Returns a human-readable summary.
+#[cfg(not(windows))]
+pub fn install() -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let path = unit_path()?;
+    let unit = format!(
+        "[Unit]\nDescription=Agent Desk (headless)\nAfter=network-online.target\n\n\
+         [Service]\nExecStart=\"{}\" --headless\nRestart=on-failure\nRestartSec=5\n\n\
+         [Install]\nWantedBy=default.target\n",
+        exe.display()
+    );
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&path, unit).map_err(|e| format!("{}: {}", path.display(), e))?;
+    run(Command::new("systemctl").args(["--user", "daemon-reload"]))?;
+    run(Command::new("systemctl").args(["--user", "enable", "--now", UNIT_NAME]))?;
+    // Without lingering, user units stop at logout
+    let linger = match std::env::var("USER") {
+        Ok(user) => run(Command::new("loginctl").args(["enable-linger", &user])),
+        Err(_) => Err("USER not set".into()),
+    };
+    let mut summary = format!("systemd user unit installed and started ({})", path.display());
+    if let Err(e) = linger {
+        summary.push_str(&format!("\nCould not enable lingering ({}); the service stops at logout", e));
+    }
+    Ok(summary)
+}
+
+/// Stop and remove the service.
+#[cfg(not(windows))]
+pub fn uninstall() -> Result<String, String> {
+    let path = unit_path()?;
+    let _ = run(Command::new("systemctl").args(["--user", "disable", "--now", UNIT_NAME]));
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    let _ = run(Command::new("systemctl").args(["--user", "daemon-reload"]));
+    Ok(format!("systemd user unit removed ({})", path.display()))
+}
@@ -2,11 +2,12 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::RwLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::protocol::SessionStatus;
+use crate::process::{cwd_key, process_create_time, CAN_QUERY_CREATE_TIME};
+use crate::protocol::{SessionStatus, WaitingReason};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionInfo {
@@ -27,16 +28,151 @@ pub struct SessionInfo {
     pub notification_type: Option<String>,
     #[serde(default)]
     pub notification_message: Option<String>,
+    /// Why the session waits; cleared once it works again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waiting_reason: Option<WaitingReason>,
     #[serde(default)]
     pub agent_pid: Option<u32>,
+    /// Creation time of `agent_pid` when it was recorded — detects PID reuse.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_create_time: Option<f64>,
+    /// Agent whose hooks report the session (`claude_code`, `codex`: the
+    /// `agent_type` of its process); unset until a signal says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_type: Option<String>,
     #[serde(default)]
     pub parent_session_id: Option<String>,
+    /// Session this one continues (`claude --resume`); merged into this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resumed_from: Option<String>,
+    /// Session whose work this one continues (a handoff: unlike a resume,
+    /// both stay separate sessions).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predecessor: Option<String>,
+    /// `predecessor` was guessed from CWD and timing, not declared.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub predecessor_inferred: bool,
+    /// Unix time a hit usage limit resets (set while the session is capped).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_resets_at: Option<f64>,
+    /// Status transitions, oldest first (capped at `MAX_TIMELINE`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timeline: Vec<StatusChange>,
+    /// `hook_seq` of the newest hook update applied.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub last_seq: u64,
+    /// Colour picked by the user (`#rrggbb`); unset = derived from the CWD.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Tool the agent started last (observe-mode PreToolUse hook); cleared
+    /// by the next prompt or once the session stops working.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_tool: Option<String>,
+    /// Pinned by the user: listed first (see `general.session_sort`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Muted by the user for remote channels: its toasts still show, but
+    /// nothing about it is pushed or escalated.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub remote_muted: bool,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+// ─── Session colours ─────────────────────────────────────
+
+/// Palette sessions are coloured from: (name, hex, tray glyph).
+pub const SESSION_COLORS: [(&str, &str, &str); 7] = [
+    ("red", "#E5534B", "\u{1f534}"),
+    ("orange", "#E8875B", "\u{1f7e0}"),
+    ("yellow", "#D4B72C", "\u{1f7e1}"),
+    ("green", "#57AB5A", "\u{1f7e2}"),
+    ("blue", "#539BF5", "\u{1f535}"),
+    ("purple", "#B083F0", "\u{1f7e3}"),
+    ("brown", "#A0785A", "\u{1f7e4}"),
+];
+
+/// Stable palette colour for a CWD, so one project keeps its colour across
+/// sessions and restarts (FNV-1a over the normalized path).
+pub fn cwd_color(cwd: &str) -> &'static str {
+    let key = cwd.replace('/', "\\").to_lowercase();
+    let hash = key
+        .trim_end_matches('\\')
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    SESSION_COLORS[(hash % SESSION_COLORS.len() as u64) as usize].1
+}
+
+/// A palette name or `#rrggbb` as uppercase hex; `None` if neither.
+pub fn parse_color(s: &str) -> Option<String> {
+    let s = s.trim();
+    if let Some((_, hex, _)) = SESSION_COLORS.iter().find(|(name, _, _)| name.eq_ignore_ascii_case(s)) {
+        return Some(hex.to_string());
+    }
+    let hex = s.strip_prefix('#')?;
+    (hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit())).then(|| format!("#{}", hex.to_ascii_uppercase()))
+}
+
+/// Coloured-circle emoji closest to `hex`, for text-only surfaces (tray, toasts).
+pub fn color_glyph(hex: &str) -> &'static str {
+    let rgb = |h: &str| -> Option<[i32; 3]> {
+        let h = h.strip_prefix('#')?;
+        let c = |i: usize| i32::from_str_radix(h.get(i..i + 2)?, 16).ok();
+        Some([c(0)?, c(2)?, c(4)?])
+    };
+    let Some(target) = rgb(hex) else {
+        return "\u{26aa}";
+    };
+    SESSION_COLORS
+        .iter()
+        .filter_map(|(_, h, glyph)| {
+            let c = rgb(h)?;
+            let dist: i32 = (0..3).map(|i| (c[i] - target[i]).pow(2)).sum();
+            Some((dist, *glyph))
+        })
+        .min_by_key(|(d, _)| *d)
+        .map_or("\u{26aa}", |(_, g)| g)
+}
+
+/// One status transition in a session's timeline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub ts: f64,
+    pub status: SessionStatus,
+}
+
+/// Oldest transitions are dropped beyond this many per session.
+const MAX_TIMELINE: usize = 200;
+
+impl SessionInfo {
+    /// The user's colour, else the one derived from the CWD.
+    pub fn display_color(&self) -> String {
+        self.color.clone().unwrap_or_else(|| cwd_color(&self.cwd).to_string())
+    }
+
+    /// Append a transition if `status` differs from the last recorded one.
+    fn record_status(&mut self, status: &SessionStatus, ts: f64) {
+        if self.timeline.last().is_some_and(|c| &c.status == status) {
+            return;
+        }
+        self.timeline.push(StatusChange { ts, status: status.clone() });
+        if self.timeline.len() > MAX_TIMELINE {
+            let excess = self.timeline.len() - MAX_TIMELINE;
+            self.timeline.drain(..excess);
+        }
+    }
 }
 
 pub struct SessionTracker {
     sessions: RwLock<HashMap<String, SessionInfo>>,
     path: PathBuf,
     dirty: AtomicBool,
+    /// Status updates refused by `SessionStatus::can_transition_to`.
+    transition_rejected: AtomicU64,
+    /// Hook updates dropped because a newer one was already applied.
+    out_of_order: AtomicU64,
 }
 
 fn now_ts() -> f64 {
@@ -46,17 +182,46 @@ fn now_ts() -> f64 {
         .as_secs_f64()
 }
 
+/// Whether agent `pid` is still the process recorded at `recorded` (its
+/// create time), not gone or reused by an unrelated process.
+fn agent_alive(pid: u32, recorded: Option<f64>) -> bool {
+    match (process_create_time(pid), recorded) {
+        (None, _) => false,
+        // Recorded before create times were kept: can't tell, trust it
+        (Some(_), None) => true,
+        (Some(actual), Some(recorded)) => (actual - recorded).abs() < 2.0,
+    }
+}
+
 impl SessionTracker {
     pub fn new(path: String) -> Self {
         let path = PathBuf::from(&path);
         let mut sessions = Self::load_from_file(&path);
 
-        // Startup cleanup: demote stale "active" sessions.
+        // Startup cleanup, 1: revalidate agent PIDs. A PID whose process is
+        // gone — or was reused by an unrelated process — ends the session.
+        let now = now_ts();
+        let mut cleaned = false;
+        if CAN_QUERY_CREATE_TIME {
+            for info in sessions.values_mut() {
+                let Some(pid) = info.agent_pid else { continue };
+                if agent_alive(pid, info.agent_create_time) {
+                    continue;
+                }
+                info.agent_pid = None;
+                info.agent_create_time = None;
+                if info.status != SessionStatus::Ended {
+                    info.status = SessionStatus::Ended;
+                    info.record_status(&SessionStatus::Ended, now);
+                }
+                cleaned = true;
+            }
+        }
+
+        // 2: demote stale "active" sessions.
         // Sessions stuck in Active from a previous run (no hook will update them)
         // get demoted to Idle so they don't show as "Working..." on restart.
-        let now = now_ts();
         let stale_threshold = 300.0; // 5 minutes
-        let mut cleaned = false;
         for info in sessions.values_mut() {
             let is_stale = (now - info.updated_at) > stale_threshold;
             let should_demote = is_stale && matches!(
@@ -65,6 +230,7 @@ impl SessionTracker {
             );
             if should_demote {
                 info.status = SessionStatus::Idle;
+                info.record_status(&SessionStatus::Idle, now);
                 // Do NOT update updated_at — keep old timestamp so Phase 2
                 // matching won't treat this as a recently-active session.
                 cleaned = true;
@@ -76,6 +242,8 @@ impl SessionTracker {
             sessions: RwLock::new(sessions),
             path,
             dirty,
+            transition_rejected: AtomicU64::new(0),
+            out_of_order: AtomicU64::new(0),
         }
     }
 
@@ -101,8 +269,21 @@ impl SessionTracker {
             last_message: None,
             notification_type: None,
             notification_message: None,
+            waiting_reason: None,
             agent_pid,
+            agent_create_time: agent_pid.and_then(process_create_time),
+            agent_type: None,
             parent_session_id: None,
+            resumed_from: None,
+            predecessor: None,
+            predecessor_inferred: false,
+            limit_resets_at: None,
+            timeline: vec![StatusChange { ts: now, status: SessionStatus::Idle }],
+            last_seq: 0,
+            color: None,
+            current_tool: None,
+            pinned: false,
+            remote_muted: false,
         };
         let mut sessions = write_lock!(self.sessions);
         sessions.insert(session_id.to_string(), info);
@@ -124,17 +305,61 @@ impl SessionTracker {
                 last_message: None,
                 notification_type: None,
                 notification_message: None,
+                waiting_reason: None,
                 agent_pid: None,
+                agent_create_time: None,
+                agent_type: None,
                 parent_session_id: None,
+                resumed_from: None,
+                predecessor: None,
+                predecessor_inferred: false,
+                limit_resets_at: None,
+                timeline: Vec::new(),
+                last_seq: 0,
+                color: None,
+                current_tool: None,
+                pinned: false,
+                remote_muted: false,
             }
         });
 
+        // A hook that fired before the last applied one is stale: a late
+        // `stop` must not undo the `user_prompt` that followed it
+        if let Some(seq) = updates.seq {
+            if seq < entry.last_seq {
+                tracing::debug!("Session {}: dropped out-of-order hook update (seq {} < {})", session_id, seq, entry.last_seq);
+                self.out_of_order.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            entry.last_seq = seq;
+        }
+
+        // Out-of-order hook delivery must not make the status flap
+        if let Some(status) = updates.status.as_ref().filter(|s| !entry.status.can_transition_to(s)) {
+            tracing::warn!(
+                "Session {}: rejected status transition {} -> {} (update ignored)",
+                session_id, entry.status, status,
+            );
+            self.transition_rejected.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
         if let Some(status) = updates.status {
+            // Working again means the usage limit is over
+            if status == SessionStatus::Active {
+                entry.limit_resets_at = None;
+                entry.waiting_reason = None;
+            } else {
+                entry.current_tool = None;
+            }
+            entry.record_status(&status, now);
             entry.status = status;
         }
         if let Some(cwd) = updates.cwd {
             entry.cwd = cwd;
         }
+        if let Some(model) = updates.model {
+            entry.model = Some(model);
+        }
         if let Some(msg) = updates.last_message {
             entry.last_message = Some(msg);
         }
@@ -144,16 +369,41 @@ impl SessionTracker {
         if let Some(nm) = updates.notification_message {
             entry.notification_message = Some(nm);
         }
+        if let Some(reason) = updates.waiting_reason {
+            entry.waiting_reason = reason;
+        }
         if let Some(pid) = updates.agent_pid {
+            // Sent with every event; only look the process up when it changes
+            if entry.agent_pid != Some(pid) || entry.agent_create_time.is_none() {
+                entry.agent_create_time = process_create_time(pid);
+            }
             entry.agent_pid = Some(pid);
         }
+        if let Some(agent) = updates.agent_type {
+            entry.agent_type = Some(agent);
+        }
         if let Some(parent) = updates.parent_session_id {
             entry.parent_session_id = Some(parent);
         }
+        if let Some(resets_at) = updates.limit_resets_at {
+            entry.limit_resets_at = resets_at;
+        }
+        if let Some(tool) = updates.current_tool {
+            entry.current_tool = tool;
+        }
         entry.updated_at = now;
         self.dirty.store(true, Ordering::Relaxed);
     }
 
+    /// Counters for `/api/metrics`.
+    pub fn stats(&self) -> serde_json::Value {
+        serde_json::json!({
+            "tracked": read_lock!(self.sessions).len(),
+            "transition_rejected": self.transition_rejected.load(Ordering::Relaxed),
+            "out_of_order": self.out_of_order.load(Ordering::Relaxed),
+        })
+    }
+
     /// Get sessions updated within TTL.
     pub fn get_active(&self, ttl: u64) -> HashMap<String, SessionInfo> {
         let now = now_ts();
@@ -165,6 +415,13 @@ impl SessionTracker {
             .collect()
     }
 
+    /// Every tracked session, ended ones included, most recently updated first.
+    pub fn all(&self) -> Vec<SessionInfo> {
+        let mut all: Vec<SessionInfo> = read_lock!(self.sessions).values().cloned().collect();
+        all.sort_by(|a, b| b.updated_at.total_cmp(&a.updated_at));
+        all
+    }
+
     /// Resolve a short ID prefix to full session ID.
     pub fn resolve_short_id(&self, prefix: &str) -> Option<String> {
         let sessions = read_lock!(self.sessions);
@@ -178,6 +435,298 @@ impl SessionTracker {
         }
     }
 
+    /// Model last recorded for a session.
+    pub fn model(&self, session_id: &str) -> Option<String> {
+        let sessions = read_lock!(self.sessions);
+        sessions.get(session_id).and_then(|info| info.model.clone())
+    }
+
+    /// Display colour of a session (see `SessionInfo::display_color`).
+    pub fn color(&self, session_id: &str) -> Option<String> {
+        read_lock!(self.sessions).get(session_id).map(SessionInfo::display_color)
+    }
+
+    /// Pick a session's colour, or with `None` go back to the CWD-derived
+    /// one. Returns false if the session is unknown.
+    pub fn set_color(&self, session_id: &str, color: Option<String>) -> bool {
+        let mut sessions = write_lock!(self.sessions);
+        let Some(info) = sessions.get_mut(session_id) else {
+            return false;
+        };
+        info.color = color;
+        self.dirty.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Whether the user pinned a session.
+    pub fn pinned(&self, session_id: &str) -> bool {
+        read_lock!(self.sessions).get(session_id).is_some_and(|info| info.pinned)
+    }
+
+    /// Pin or unpin a session. Returns false if the session is unknown.
+    pub fn set_pinned(&self, session_id: &str, pinned: bool) -> bool {
+        let mut sessions = write_lock!(self.sessions);
+        let Some(info) = sessions.get_mut(session_id) else {
+            return false;
+        };
+        info.pinned = pinned;
+        self.dirty.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Whether remote pushes about a session are muted.
+    pub fn remote_muted(&self, session_id: &str) -> bool {
+        read_lock!(self.sessions).get(session_id).is_some_and(|info| info.remote_muted)
+    }
+
+    /// Mute or unmute remote pushes about a session. Returns false if the
+    /// session is unknown.
+    pub fn set_remote_muted(&self, session_id: &str, muted: bool) -> bool {
+        let mut sessions = write_lock!(self.sessions);
+        let Some(info) = sessions.get_mut(session_id) else {
+            return false;
+        };
+        info.remote_muted = muted;
+        self.dirty.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// A copy of a session's record.
+    pub fn get(&self, session_id: &str) -> Option<SessionInfo> {
+        read_lock!(self.sessions).get(session_id).cloned()
+    }
+
+    /// CWD recorded for a session (may be empty).
+    pub fn cwd(&self, session_id: &str) -> Option<String> {
+        read_lock!(self.sessions).get(session_id).map(|info| info.cwd.clone())
+    }
+
+    /// Tool the session started last and hasn't finished (observe mode).
+    pub fn current_tool(&self, session_id: &str) -> Option<String> {
+        read_lock!(self.sessions).get(session_id).and_then(|info| info.current_tool.clone())
+    }
+
+    /// PID of the agent process behind a session, if a hook reported it.
+    pub fn agent_pid(&self, session_id: &str) -> Option<u32> {
+        let sessions = read_lock!(self.sessions);
+        sessions.get(session_id).and_then(|info| info.agent_pid)
+    }
+
+    /// Move the live session run by `old_pid` — or, if no session recorded
+    /// it, the latest live one in `cwd` — to the agent's restarted process.
+    /// Returns the session ID; None if there is no such session or the new
+    /// process already registered a session of its own.
+    pub fn adopt_restarted(&self, old_pid: u32, new_pid: u32, cwd: &str) -> Option<String> {
+        let cwd = cwd_key(cwd);
+        let mut sessions = write_lock!(self.sessions);
+        if sessions.values().any(|s| s.agent_pid == Some(new_pid)) {
+            return None;
+        }
+        let live = sessions.values().filter(|s| s.status != SessionStatus::Ended);
+        let sid = live
+            .clone()
+            .find(|s| s.agent_pid == Some(old_pid))
+            .or_else(|| {
+                live.filter(|s| s.agent_pid.is_none() && cwd_key(&s.cwd) == cwd)
+                    .max_by(|a, b| a.updated_at.total_cmp(&b.updated_at))
+            })?
+            .session_id
+            .clone();
+        let entry = sessions.get_mut(&sid)?;
+        entry.agent_pid = Some(new_pid);
+        entry.agent_create_time = process_create_time(new_pid);
+        self.dirty.store(true, Ordering::Relaxed);
+        Some(sid)
+    }
+
+    /// Session that a resume of `new_id` continues. `prior_ids` are the
+    /// session ids found in the new transcript (its copied history). With
+    /// `by_cwd`, fall back to the most recent non-working session in the
+    /// same CWD whose agent has exited or is the resuming one — preferring
+    /// the latter (`/resume`). A session still live in another terminal is
+    /// never taken.
+    pub fn find_resumed(
+        &self,
+        new_id: &str,
+        cwd: &str,
+        agent_pid: Option<u32>,
+        prior_ids: &[String],
+        by_cwd: bool,
+    ) -> Option<String> {
+        let sessions = read_lock!(self.sessions);
+        if let Some(id) = prior_ids.iter().find(|id| id.as_str() != new_id && sessions.contains_key(*id)) {
+            return Some(id.clone());
+        }
+        if !by_cwd {
+            return None;
+        }
+        sessions
+            .values()
+            .filter(|s| s.session_id != new_id && s.cwd == cwd && s.status != SessionStatus::Active)
+            .filter(|s| {
+                s.status == SessionStatus::Ended
+                    || match s.agent_pid {
+                        None => true,
+                        Some(pid) if agent_pid == Some(pid) => true,
+                        // Can't tell a live agent from an exited one: leave it
+                        Some(pid) => CAN_QUERY_CREATE_TIME && !agent_alive(pid, s.agent_create_time),
+                    }
+            })
+            .max_by(|a, b| {
+                let same_pid = |s: &SessionInfo| agent_pid.is_some() && s.agent_pid == agent_pid;
+                same_pid(a).cmp(&same_pid(b)).then(a.updated_at.total_cmp(&b.updated_at))
+            })
+            .map(|s| s.session_id.clone())
+    }
+
+    /// Fold `old` into `new` after a resume: `new` inherits the start time,
+    /// earlier timeline, parent link and remote mute and records
+    /// `resumed_from`; `old` is removed. Returns false if either session is unknown.
+    pub fn merge_resumed(&self, old: &str, new: &str) -> bool {
+        let mut sessions = write_lock!(self.sessions);
+        if old == new || !sessions.contains_key(new) {
+            return false;
+        }
+        let Some(prev) = sessions.remove(old) else {
+            return false;
+        };
+        let entry = sessions.get_mut(new).expect("checked above");
+        entry.started_at = entry.started_at.min(prev.started_at);
+        let mut timeline = prev.timeline;
+        timeline.append(&mut entry.timeline);
+        if timeline.len() > MAX_TIMELINE {
+            let excess = timeline.len() - MAX_TIMELINE;
+            timeline.drain(..excess);
+        }
+        entry.timeline = timeline;
+        if entry.parent_session_id.is_none() {
+            entry.parent_session_id = prev.parent_session_id;
+        }
+        if entry.model.is_none() {
+            entry.model = prev.model;
+        }
+        entry.remote_muted |= prev.remote_muted;
+        if entry.predecessor.is_none() {
+            entry.predecessor = prev.predecessor;
+            entry.predecessor_inferred = prev.predecessor_inferred;
+        }
+        entry.resumed_from = Some(old.to_string());
+        // Sub-agents and successors of the old session now belong to the resumed one
+        for info in sessions.values_mut() {
+            if info.parent_session_id.as_deref() == Some(old) {
+                info.parent_session_id = Some(new.to_string());
+            }
+            if info.predecessor.as_deref() == Some(old) {
+                info.predecessor = Some(new.to_string());
+            }
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Declare that `id` continues `predecessor` (`None` unlinks). Fails
+    /// for unknown sessions or a link that would close a loop.
+    pub fn link(&self, id: &str, predecessor: Option<&str>) -> Result<(), String> {
+        let mut sessions = write_lock!(self.sessions);
+        if !sessions.contains_key(id) {
+            return Err(format!("session not found: {}", id));
+        }
+        if let Some(pred) = predecessor {
+            if !sessions.contains_key(pred) {
+                return Err(format!("session not found: {}", pred));
+            }
+            // Walk back from the predecessor; reaching `id` means a cycle
+            let mut cur = Some(pred);
+            let mut steps = 0;
+            while let Some(c) = cur {
+                if c == id || steps > sessions.len() {
+                    return Err("link would create a cycle".to_string());
+                }
+                cur = sessions.get(c).and_then(|s| s.predecessor.as_deref());
+                steps += 1;
+            }
+        }
+        let entry = sessions.get_mut(id).expect("checked above");
+        entry.predecessor = predecessor.map(str::to_string);
+        entry.predecessor_inferred = false;
+        self.dirty.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Link a newly started session to one that ended in the same CWD at
+    /// most `window` seconds ago, unless it already has a predecessor.
+    /// Returns the session it was linked to.
+    pub fn infer_predecessor(&self, id: &str, cwd: &str, window: f64) -> Option<String> {
+        if cwd.is_empty() {
+            return None;
+        }
+        let now = now_ts();
+        let mut sessions = write_lock!(self.sessions);
+        if sessions.get(id).is_none_or(|s| s.predecessor.is_some()) {
+            return None;
+        }
+        let ended_at = |s: &SessionInfo| {
+            s.timeline.iter().rev().find(|c| c.status == SessionStatus::Ended).map(|c| c.ts)
+        };
+        let pred = sessions
+            .values()
+            .filter(|s| s.session_id != id && s.cwd == cwd && s.status == SessionStatus::Ended)
+            .filter_map(|s| Some((s, ended_at(s)?)))
+            .filter(|(_, at)| now - at <= window)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(s, _)| s.session_id.clone())?;
+        let entry = sessions.get_mut(id)?;
+        entry.predecessor = Some(pred.clone());
+        entry.predecessor_inferred = true;
+        self.dirty.store(true, Ordering::Relaxed);
+        Some(pred)
+    }
+
+    /// The handoff chain `id` belongs to, oldest first: its predecessors,
+    /// itself, then the latest successor at each step.
+    pub fn chain(&self, id: &str) -> Vec<SessionInfo> {
+        let sessions = read_lock!(self.sessions);
+        let Some(me) = sessions.get(id) else {
+            return Vec::new();
+        };
+        let mut seen = std::collections::HashSet::from([id.to_string()]);
+        let mut chain = Vec::new();
+        let mut cur = me.predecessor.as_deref().and_then(|p| sessions.get(p));
+        while let Some(s) = cur.filter(|s| seen.insert(s.session_id.clone())) {
+            chain.push(s.clone());
+            cur = s.predecessor.as_deref().and_then(|p| sessions.get(p));
+        }
+        chain.reverse();
+        chain.push(me.clone());
+        let mut last = id.to_string();
+        loop {
+            let next = sessions
+                .values()
+                .filter(|s| s.predecessor.as_deref() == Some(last.as_str()) && !seen.contains(&s.session_id))
+                .max_by(|a, b| a.started_at.total_cmp(&b.started_at));
+            let Some(next) = next else { break };
+            seen.insert(next.session_id.clone());
+            last = next.session_id.clone();
+            chain.push(next.clone());
+        }
+        chain
+    }
+
+    /// Session start time and status transitions, oldest first.
+    pub fn timeline(&self, session_id: &str) -> Option<(f64, Vec<StatusChange>)> {
+        let sessions = read_lock!(self.sessions);
+        sessions.get(session_id).map(|info| (info.started_at, info.timeline.clone()))
+    }
+
+    /// When the session last became active, i.e. the start of its current
+    /// or latest turn; its start time if it never did.
+    pub fn active_since(&self, session_id: &str) -> Option<f64> {
+        let sessions = read_lock!(self.sessions);
+        let info = sessions.get(session_id)?;
+        let since = info.timeline.iter().rev().find(|c| c.status == SessionStatus::Active).map(|c| c.ts);
+        Some(since.unwrap_or(info.started_at))
+    }
+
     /// Remove a session by ID.
     pub fn remove(&self, session_id: &str) {
         let mut sessions = write_lock!(self.sessions);
@@ -186,18 +735,24 @@ impl SessionTracker {
         }
     }
 
-    /// Purge sessions that ended more than `ttl` seconds ago.
-    pub fn purge_stale(&self, ttl: u64) {
+    /// Purge sessions not updated for more than `keep(status)` seconds
+    /// (0 = keep forever). Returns the removed IDs.
+    pub fn purge_stale(&self, keep: impl Fn(&SessionStatus) -> u64) -> Vec<String> {
         let now = now_ts();
-        let cutoff = now - ttl as f64;
         let mut sessions = write_lock!(self.sessions);
-        let before = sessions.len();
-        sessions.retain(|_, info| {
-            !(info.status == SessionStatus::Ended && info.updated_at < cutoff)
+        let mut purged = Vec::new();
+        sessions.retain(|sid, info| {
+            let ttl = keep(&info.status);
+            let stale = ttl > 0 && info.updated_at < now - ttl as f64;
+            if stale {
+                purged.push(sid.clone());
+            }
+            !stale
         });
-        if sessions.len() < before {
+        if !purged.is_empty() {
             self.dirty.store(true, Ordering::Relaxed);
         }
+        purged
     }
 
     /// Flush to disk if dirty. Call periodically.
@@ -219,9 +774,20 @@ impl SessionTracker {
 pub struct SessionUpdate {
     pub status: Option<SessionStatus>,
     pub cwd: Option<String>,
+    pub model: Option<String>,
     pub last_message: Option<String>,
     pub notification_type: Option<String>,
     pub notification_message: Option<String>,
+    /// `Some(None)` clears it.
+    pub waiting_reason: Option<Option<WaitingReason>>,
     pub agent_pid: Option<u32>,
+    pub agent_type: Option<String>,
     pub parent_session_id: Option<String>,
+    /// `Some(None)` clears a recorded usage limit.
+    pub limit_resets_at: Option<Option<f64>>,
+    /// `hook_seq` of the hook event behind this update; older than the
+    /// last applied one means it arrived late and is dropped.
+    pub seq: Option<u64>,
+    /// `Some(None)` clears the current tool.
+    pub current_tool: Option<Option<String>>,
 }
@@ -0,0 +1,132 @@
+//! Backups of the Claude Code settings files the app rewrites.
+//!
+//! Before `setup` writes `~/.claude/settings.json` (or a project's
+//! `.claude/settings.local.json`), the current file is copied to
+//! `<data dir>/settings-backups/` under a timestamped name. `index.json`
+//! there records which file each backup came from, and only the newest
+//! `general.settings_backups` of each are kept. `restore` puts one back,
+//! backing up what it replaces first, so a restore can be undone too.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+/// Backup folder and how many backups to keep per file, set by `configure`.
+static BACKUPS: OnceLock<(PathBuf, usize)> = OnceLock::new();
+
+/// Backups and restores one at a time, so `index.json` stays whole.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// One backup in `index.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    /// File name in the backup folder.
+    pub file: String,
+    /// The settings file it is a copy of.
+    pub source: PathBuf,
+    /// Unix time (seconds) it was taken.
+    pub created_at: f64,
+}
+
+/// Keep backups in `data_dir`, `keep` per settings file (0 = none). Call
+/// once at startup, before any hooks are written.
+pub fn configure(data_dir: &Path, keep: usize) {
+    let _ = BACKUPS.set((data_dir.join("settings-backups"), keep));
+}
+
+fn dir() -> Option<&'static Path> {
+    BACKUPS.get().filter(|(_, keep)| *keep > 0).map(|(dir, _)| dir.as_path())
+}
+
+/// Copy `path` to the backup folder, if it exists. Returns the backup.
+pub fn backup(path: &Path) -> Result<Option<Backup>, String> {
+    let Some((dir, keep)) = BACKUPS.get().filter(|(_, keep)| *keep > 0) else {
+        return Ok(None);
+    };
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let _guard = mutex_lock!(LOCK);
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let now = chrono::Local::now();
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("settings");
+    let mut file = format!("{}-{}.json", stem, now.format("%Y%m%d-%H%M%S"));
+    for n in 2.. {
+        if !dir.join(&file).exists() {
+            break;
+        }
+        file = format!("{}-{}-{}.json", stem, now.format("%Y%m%d-%H%M%S"), n);
+    }
+    std::fs::copy(path, dir.join(&file))
+        .map_err(|e| format!("Failed to back up {} to {}: {}", path.display(), dir.display(), e))?;
+    let backup = Backup { file, source: path.to_path_buf(), created_at: now.timestamp() as f64 };
+
+    let mut index = read_index(dir);
+    index.push(backup.clone());
+    // Oldest first: drop the oldest of this file's beyond `keep`
+    let count = index.iter().filter(|b| b.source == backup.source).count();
+    let mut excess = count.saturating_sub(*keep);
+    index.retain(|b| {
+        if excess > 0 && b.source == backup.source {
+            excess -= 1;
+            let _ = std::fs::remove_file(dir.join(&b.file));
+            return false;
+        }
+        true
+    });
+    write_index(dir, &index)?;
+    tracing::info!("Backed up {} to {}", path.display(), dir.join(&backup.file).display());
+    Ok(Some(backup))
+}
+
+/// Backups kept, newest first.
+pub fn list() -> Vec<Backup> {
+    let Some(dir) = dir() else {
+        return Vec::new();
+    };
+    let _guard = mutex_lock!(LOCK);
+    let mut index = read_index(dir);
+    index.reverse();
+    index
+}
+
+/// Put backup `file` back in place of its source, or with `None` the
+/// newest backup of `default_source`. Returns the backup restored.
+pub fn restore(file: Option<&str>, default_source: &Path) -> Result<Backup, String> {
+    let Some(dir) = dir() else {
+        return Err("settings backups are off (general.settings_backups: 0)".into());
+    };
+    let backup = {
+        let _guard = mutex_lock!(LOCK);
+        let index = read_index(dir);
+        match file {
+            Some(f) => index.into_iter().find(|b| b.file == f).ok_or_else(|| format!("no backup {}", f))?,
+            None => index
+                .into_iter()
+                .rev()
+                .find(|b| b.source == default_source)
+                .ok_or_else(|| format!("no backup of {}", default_source.display()))?,
+        }
+    };
+    let contents = std::fs::read(dir.join(&backup.file))
+        .map_err(|e| format!("Failed to read backup {}: {}", backup.file, e))?;
+    self::backup(&backup.source)?;
+    std::fs::write(&backup.source, contents)
+        .map_err(|e| format!("Failed to write {}: {}", backup.source.display(), e))?;
+    tracing::info!("Restored {} from backup {}", backup.source.display(), backup.file);
+    Ok(backup)
+}
+
+fn read_index(dir: &Path) -> Vec<Backup> {
+    std::fs::read_to_string(dir.join("index.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn write_index(dir: &Path, index: &[Backup]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(index).map_err(|e| format!("Failed to serialize backup index: {}", e))?;
+    std::fs::write(dir.join("index.json"), json).map_err(|e| format!("Failed to write backup index: {}", e))
+}
@@ -3,16 +3,21 @@
 //! Finds the bundled `agent-desk-hook.exe` next to the main executable,
 //! then ensures `~/.claude/settings.json` has hook entries for all events.
 
+use serde::Serialize;
 use serde_json::{json, Value};
-use std::path::PathBuf;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
 
 /// Claude Code hook name → agent-desk-hook `--event` argument.
 ///
-/// NOTE: PreToolUse is intentionally excluded. It blocks the terminal and
-/// prevents the user from approving permissions there. PermissionRequest
-/// is the correct hook — it fires when Claude Code's native permission
-/// system triggers and allows agent-desk to approve, with timeout fallback
-/// to the terminal prompt.
+/// NOTE: a blocking PreToolUse hook is intentionally excluded. It blocks
+/// the terminal and prevents the user from approving permissions there.
+/// PermissionRequest is the correct hook — it fires when Claude Code's
+/// native permission system triggers and allows agent-desk to approve, with
+/// timeout fallback to the terminal prompt. PreToolUse is only installed in
+/// observe mode (`OBSERVE_EVENT`).
 const HOOK_EVENTS: &[(&str, &str)] = &[
     ("UserPromptSubmit", "user_prompt"),
     ("Stop", "stop"),
@@ -22,8 +27,82 @@ const HOOK_EVENTS: &[(&str, &str)] = &[
     ("PermissionRequest", "permission_request"),
 ];
 
+/// With `general.observe_tools`: PreToolUse, reporting the tool name
+/// fire-and-forget. Prints nothing, so Claude Code carries on as usual.
+const OBSERVE_EVENT: (&str, &str) = ("PreToolUse", "pre_tool_observe");
+
+/// Port the hook binary assumes when no `--port` is given.
+const DEFAULT_PORT: u16 = 15924;
+
+/// Host the hook binary assumes when no `--host` is given.
+const DEFAULT_HOST: &str = "127.0.0.1";
+
+/// `manager.host`, set once by `configure`.
+static HOST: OnceLock<String> = OnceLock::new();
+
+/// `manager.use_hook_daemon`, set once by `configure`.
+static USE_DAEMON: OnceLock<bool> = OnceLock::new();
+
+/// Apply `manager.host` and `manager.use_hook_daemon`: the daemon listens
+/// on that host and hook commands point there, with `--no-daemon` when the
+/// daemon is off. Call once at startup, before the server or daemon starts.
+pub fn configure(host: &str, use_daemon: bool) {
+    let host = host.trim().trim_start_matches('[').trim_end_matches(']');
+    let _ = HOST.set(if host.is_empty() { DEFAULT_HOST } else { host }.to_string());
+    let _ = USE_DAEMON.set(use_daemon);
+}
+
+/// Whether hooks go through the hook daemon (`manager.use_hook_daemon`).
+pub fn use_hook_daemon() -> bool {
+    USE_DAEMON.get().copied().unwrap_or(true)
+}
+
+/// The server's host (`manager.host`), IPv6 literals without brackets.
+pub fn host() -> &'static str {
+    HOST.get().map_or(DEFAULT_HOST, String::as_str)
+}
+
+/// `host` as written in a URL: IPv6 literals in brackets.
+pub fn url_host(host: &str) -> String {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.contains(':') { format!("[{}]", host) } else { host.to_string() }
+}
+
+/// `host:port`, for socket addresses and URLs alike.
+pub fn host_port(host: &str, port: u16) -> String {
+    format!("{}:{}", url_host(host), port)
+}
+
+/// `port` on the server's host, e.g. `127.0.0.1:15925` or `[::1]:15925`.
+pub fn local_addr(port: u16) -> String {
+    host_port(host(), port)
+}
+
+/// Script telling a webview page where the server is: `API_HOST` (as in a
+/// URL) and `API_PORT`.
+pub fn page_globals(port: u16) -> String {
+    format!("window.API_HOST={};window.API_PORT={}", json!(url_host(host())), port)
+}
+
+/// Connect to `port` on the server's host, trying each address a name
+/// such as `localhost` resolves to.
+pub fn connect_local(port: u16, timeout: Duration) -> std::io::Result<TcpStream> {
+    let mut last = std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} did not resolve", host()));
+    for addr in local_addr(port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last = e,
+        }
+    }
+    Err(last)
+}
+
+/// Hook daemon line protocol we speak (`PROTOCOL` in the hook binary's
+/// daemon.rs). Daemons of any other version are replaced.
+pub const DAEMON_PROTOCOL: u64 = 2;
+
 /// Locate `agent-desk-hook.exe` next to the running executable.
-fn hook_binary_path() -> Option<PathBuf> {
+pub fn hook_binary_path() -> Option<PathBuf> {
     let exe = std::env::current_exe().ok()?;
     let hook = exe.parent()?.join("agent-desk-hook.exe");
     hook.exists().then_some(hook)
@@ -33,12 +112,8 @@ fn hook_binary_path() -> Option<PathBuf> {
 /// Checks if anything is listening on the daemon port (port+1) and tries to connect.
 pub fn kill_orphaned_daemon(port: u16) {
     let daemon_port = port + 1;
-    let addr = format!("127.0.0.1:{}", daemon_port);
     // If we can connect, something is listening — kill it via taskkill
-    if std::net::TcpStream::connect_timeout(
-        &addr.parse().unwrap(),
-        std::time::Duration::from_millis(100),
-    ).is_ok() {
+    if connect_local(daemon_port, Duration::from_millis(100)).is_ok() {
         tracing::info!("Orphaned hook daemon detected on port {}, killing...", daemon_port);
         #[cfg(windows)]
         {
@@ -69,7 +144,7 @@ pub fn spawn_hook_daemon(port: u16) -> Option<u32> {
     #[cfg(windows)]
     use std::os::windows::process::CommandExt;
     let mut cmd = Command::new(&hook_path);
-    cmd.args(["--daemon", "--port", &port.to_string()])
+    cmd.args(["--daemon", "--host", host(), "--port", &port.to_string()])
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null());
     #[cfg(windows)]
@@ -88,6 +163,85 @@ pub fn spawn_hook_daemon(port: u16) -> Option<u32> {
     }
 }
 
+/// What is listening on the hook daemon port.
+pub enum DaemonStatus {
+    Absent,
+    /// Speaks `DAEMON_PROTOCOL`.
+    Compatible { pid: u32 },
+    /// Another protocol, or too old to answer `hello`.
+    Incompatible { reason: String },
+}
+
+/// Exchange `hello` with whatever listens on the daemon port.
+pub fn hook_daemon_status(port: u16) -> DaemonStatus {
+    let reply = match daemon_request(port, &json!({ "type": "hello", "protocol": DAEMON_PROTOCOL })) {
+        Ok(v) => v,
+        Err(e) if e.starts_with("connect") => return DaemonStatus::Absent,
+        Err(e) => return DaemonStatus::Incompatible { reason: e },
+    };
+    if reply.get("hello").and_then(|v| v.as_bool()) != Some(true) {
+        // Pre-versioning daemons relay the hello instead of answering it
+        return DaemonStatus::Incompatible { reason: "no hello reply (daemon too old)".to_string() };
+    }
+    match reply.get("protocol").and_then(|v| v.as_u64()) {
+        Some(DAEMON_PROTOCOL) => DaemonStatus::Compatible {
+            pid: reply.get("pid").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+        },
+        other => DaemonStatus::Incompatible {
+            reason: format!("protocol {}, expected {}", other.map_or("?".to_string(), |p| p.to_string()), DAEMON_PROTOCOL),
+        },
+    }
+}
+
+/// Start the hook daemon, keeping one that is already running if it speaks
+/// our protocol (e.g. left over from a crash). One of another protocol, or
+/// too old to say, is stopped and replaced. Returns the daemon PID.
+pub fn start_hook_daemon(port: u16) -> Option<u32> {
+    match hook_daemon_status(port) {
+        DaemonStatus::Compatible { pid } if pid != 0 => {
+            tracing::info!("Reusing running hook daemon (PID {})", pid);
+            return Some(pid);
+        }
+        DaemonStatus::Compatible { .. } | DaemonStatus::Absent => {}
+        DaemonStatus::Incompatible { reason } => {
+            tracing::warn!("Replacing incompatible hook daemon: {}", reason);
+            // Daemons that answer `ping` report their PID
+            let pid = ping_hook_daemon(port).ok().and_then(|v| v.get("pid")?.as_u64());
+            match pid {
+                Some(pid) => {
+                    kill_hook_daemon(pid as u32);
+                    std::thread::sleep(std::time::Duration::from_millis(200));
+                }
+                None => kill_orphaned_daemon(port),
+            }
+        }
+    }
+    spawn_hook_daemon(port)
+}
+
+/// Ask the hook daemon for its health counters via a `ping` line.
+/// Returns the daemon's JSON reply, or a short reason it is unreachable.
+pub fn ping_hook_daemon(port: u16) -> Result<Value, String> {
+    daemon_request(port, &json!({ "type": "ping" }))
+}
+
+/// Send one control line to the daemon and read its reply line.
+fn daemon_request(port: u16, request: &Value) -> Result<Value, String> {
+    use std::io::{BufRead, BufReader, Write};
+
+    let stream = connect_local(port + 1, Duration::from_millis(200))
+        .map_err(|e| format!("connect: {}", e))?;
+    let _ = stream.set_read_timeout(Some(std::time::Duration::from_secs(1)));
+    let _ = stream.set_write_timeout(Some(std::time::Duration::from_secs(1)));
+
+    let mut writer = stream.try_clone().map_err(|e| format!("{}", e))?;
+    writeln!(writer, "{}", request).map_err(|e| format!("write: {}", e))?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).map_err(|e| format!("read: {}", e))?;
+    serde_json::from_str(line.trim()).map_err(|_| "unexpected reply (daemon too old?)".to_string())
+}
+
 /// Kill the hook daemon by PID.
 pub fn kill_hook_daemon(pid: u32) {
     #[cfg(windows)]
@@ -108,12 +262,45 @@ pub fn kill_hook_daemon(pid: u32) {
     }
 }
 
-/// `%USERPROFILE%/.claude/settings.json`
-fn claude_settings_path() -> Option<PathBuf> {
+/// Claude Code's config dir: `CLAUDE_CONFIG_DIR`, else `%USERPROFILE%/.claude`.
+pub fn claude_config_dir() -> Option<PathBuf> {
+    let from_env = std::env::var("CLAUDE_CONFIG_DIR").ok().filter(|d| !d.trim().is_empty());
+    if let Some(dir) = from_env {
+        return Some(PathBuf::from(dir.trim()));
+    }
     let home = std::env::var("USERPROFILE")
         .or_else(|_| std::env::var("HOME"))
         .ok()?;
-    Some(PathBuf::from(home).join(".claude").join("settings.json"))
+    Some(PathBuf::from(home).join(".claude"))
+}
+
+/// `<config dir>/settings.json`
+pub fn claude_settings_path() -> Option<PathBuf> {
+    Some(claude_config_dir()?.join("settings.json"))
+}
+
+/// `<config dir>/projects` — where Claude Code writes transcripts.
+pub fn claude_projects_dir() -> Option<PathBuf> {
+    Some(claude_config_dir()?.join("projects"))
+}
+
+/// Resolve a CLI name (or path) the way the shell would, trying the Windows
+/// executable extensions. Returns the first match on `PATH`.
+pub fn find_on_path(name: &str) -> Option<PathBuf> {
+    let direct = PathBuf::from(name);
+    if direct.components().count() > 1 {
+        return direct.is_file().then_some(direct);
+    }
+    let exts: &[&str] = if cfg!(windows) { &["exe", "cmd", "bat"] } else { &[""] };
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .flat_map(|dir| {
+            exts.iter().map(move |ext| {
+                let p = dir.join(name);
+                if ext.is_empty() { p } else { p.with_extension(ext) }
+            })
+        })
+        .find(|p| p.is_file())
 }
 
 /// Check if a hook entry (flat or nested) contains the given substring in its command.
@@ -140,87 +327,206 @@ fn item_contains_hook(item: &Value, needle: &str) -> bool {
     false
 }
 
-/// Ensure all Agent Desk hooks are present in `~/.claude/settings.json`.
+/// Keep an existing global hooks install current at startup.
 ///
-/// - Missing file → created with full hooks config
-/// - Missing `hooks` key → added
-/// - Missing events → appended (user's other hooks preserved)
-/// - Existing agent-desk-hook entries → path updated (handles reinstall to new location)
-pub fn ensure_hooks_configured() {
-    let hook_path = match hook_binary_path() {
-        Some(p) => p,
-        None => {
-            tracing::debug!("Hook binary not found next to exe, skipping auto-configure");
-            return;
-        }
+/// Hooks are only *installed* through the setup wizard (`POST /api/setup/run`);
+/// once an agent-desk-hook entry exists in `~/.claude/settings.json`, this
+/// rewrites it after a reinstall to a new path or a port change. With
+/// `dry_run` (`general.hooks_dry_run`) the changes are only logged, to be
+/// reviewed at `GET /api/setup/plan` and applied with `POST /api/setup/apply`.
+pub fn refresh_installed_hooks(port: u16, observe_tools: bool, dry_run: bool) {
+    let Some(settings_path) = claude_settings_path() else {
+        tracing::warn!("Cannot determine home directory, skipping hooks refresh");
+        return;
     };
-
-    let settings_path = match claude_settings_path() {
-        Some(p) => p,
-        None => {
-            tracing::warn!("Cannot determine home directory, skipping hooks auto-configure");
-            return;
+    if !hooks_installed(&settings_path) {
+        tracing::info!("Claude Code hooks not installed — run the setup wizard to install them");
+        return;
+    }
+    let result = plan_hooks(&settings_path, port, observe_tools).and_then(|plan| {
+        if dry_run && !plan.changes.is_empty() {
+            for c in &plan.changes {
+                tracing::info!("Pending hook change: {} {} {}", c.change, c.event, c.command.as_deref().unwrap_or(""));
+            }
+            tracing::warn!(
+                "{} hook change(s) to {} held back by general.hooks_dry_run — review GET /api/setup/plan, apply with POST /api/setup/apply",
+                plan.changes.len(),
+                settings_path.display()
+            );
+            return Ok(false);
         }
+        apply_hook_plan(&plan)
+    });
+    if let Err(e) = result {
+        tracing::warn!("{}", e);
+    }
+}
+
+/// Whether the settings file at `path` contains any agent-desk-hook entry.
+pub fn hooks_installed(path: &Path) -> bool {
+    let settings: Value = match std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+    {
+        Some(v) => v,
+        None => return false,
     };
+    settings
+        .get("hooks")
+        .and_then(|h| h.as_object())
+        .is_some_and(|hooks| {
+            hooks.values()
+                .filter_map(|v| v.as_array())
+                .flatten()
+                .any(|item| item_contains_hook(item, "agent-desk-hook"))
+        })
+}
 
-    // Ensure ~/.claude/ directory exists
-    if let Some(parent) = settings_path.parent() {
-        if let Err(e) = std::fs::create_dir_all(parent) {
-            tracing::warn!("Failed to create {}: {}", parent.display(), e);
-            return;
-        }
+/// One change `install_hooks` makes to a settings file.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookChange {
+    /// Claude Code hook event, e.g. `Stop`.
+    pub event: String,
+    /// `add`, `update` or `remove`.
+    pub change: &'static str,
+    /// The agent-desk-hook command after the change (`None` for `remove`).
+    pub command: Option<String>,
+    /// The command it replaces or removes (`None` for `add`).
+    pub previous: Option<String>,
+}
+
+/// What `install_hooks` would do to a settings file, without doing it.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookPlan {
+    pub settings_path: PathBuf,
+    /// The file doesn't exist yet.
+    pub creates_file: bool,
+    pub changes: Vec<HookChange>,
+    /// The whole file after the changes.
+    #[serde(skip)]
+    settings: Value,
+}
+
+impl HookPlan {
+    /// Identifies the plan, so an apply can check it is still the one that
+    /// was reviewed.
+    pub fn id(&self) -> String {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.settings_path.hash(&mut hasher);
+        serde_json::to_string(&self.changes).unwrap_or_default().hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
     }
+}
+
+/// Ensure all Agent Desk hooks are present in the Claude Code settings file
+/// at `settings_path` (global `~/.claude/settings.json` or a project's
+/// `.claude/settings.local.json`). Returns whether the file was rewritten.
+/// See `plan_hooks` for what changes.
+pub fn install_hooks(settings_path: &Path, port: u16, observe_tools: bool) -> Result<bool, String> {
+    apply_hook_plan(&plan_hooks(settings_path, port, observe_tools)?)
+}
+
+/// Work out what `install_hooks` would change in `settings_path`, touching
+/// nothing:
+///
+/// - Missing file → created with full hooks config
+/// - Missing `hooks` key → added
+/// - Missing events → appended (user's other hooks preserved)
+/// - Existing agent-desk-hook entries → command rewritten when the binary
+///   path or CLI shape (`--event`, `--host`, `--port`, timeout) changed
+/// - Duplicate agent-desk-hook entries, and entries under events we no
+///   longer register → removed
+///
+/// `observe_tools` adds the observe-only PreToolUse hook; without it an
+/// existing one is removed.
+pub fn plan_hooks(settings_path: &Path, port: u16, observe_tools: bool) -> Result<HookPlan, String> {
+    let hook_path = hook_binary_path()
+        .ok_or_else(|| "Hook binary not found next to exe".to_string())?;
 
     // Read existing settings or start fresh
-    let mut settings: Value = if settings_path.exists() {
-        std::fs::read_to_string(&settings_path)
+    let creates_file = !settings_path.exists();
+    let mut settings: Value = if creates_file {
+        json!({})
+    } else {
+        std::fs::read_to_string(settings_path)
             .ok()
             .and_then(|s| serde_json::from_str(&s).ok())
             .unwrap_or_else(|| json!({}))
-    } else {
-        json!({})
     };
 
-    let root = match settings.as_object_mut() {
-        Some(obj) => obj,
-        None => {
-            tracing::warn!("settings.json is not a JSON object, skipping");
-            return;
-        }
-    };
+    let root = settings.as_object_mut()
+        .ok_or_else(|| format!("{} is not a JSON object, skipping", settings_path.display()))?;
 
     // Ensure "hooks" is an object
     if !root.get("hooks").is_some_and(|v| v.is_object()) {
         root.insert("hooks".into(), json!({}));
     }
     let hooks = root["hooks"].as_object_mut().unwrap();
+    let mut changes: Vec<HookChange> = Vec::new();
+    let removed = |event: &str, item: &Value| HookChange {
+        event: event.to_string(),
+        change: "remove",
+        command: None,
+        previous: Some(hook_command(item)),
+    };
 
     // Use forward slashes — Claude Code executes hooks via bash, which eats backslashes
     let hook_cmd_path = hook_path.to_string_lossy().replace('\\', "/");
-    let mut changed = false;
-
-    // Clean up stale PreToolUse hook (removed — it blocks the terminal).
-    if let Some(Value::Array(arr)) = hooks.get_mut("PreToolUse") {
-        let before = arr.len();
-        arr.retain(|item| !item_contains_hook(item, "agent-desk-hook"));
-        if arr.len() < before {
-            changed = true;
-            tracing::info!("Removed stale PreToolUse agent-desk-hook entry");
-        }
-        // Remove the key entirely if the array is now empty
-        if arr.is_empty() {
-            hooks.remove("PreToolUse");
+
+    // Only pass --host and --port when they differ from the hook binary's
+    // defaults
+    let mut addr_args = String::new();
+    if host() != DEFAULT_HOST {
+        addr_args.push_str(&format!(" --host {}", host()));
+    }
+    if port != DEFAULT_PORT {
+        addr_args.push_str(&format!(" --port {}", port));
+    }
+    // Spares each hook the daemon probe's connect timeout
+    if !use_hook_daemon() {
+        addr_args.push_str(" --no-daemon");
+    }
+
+    let mut events = HOOK_EVENTS.to_vec();
+    if observe_tools {
+        events.push(OBSERVE_EVENT);
+    }
+
+    // Clean up agent-desk-hook entries under events we no longer register
+    // (e.g. a blocking PreToolUse from older versions, or the observe hook
+    // once switched off).
+    let stale_events: Vec<String> = hooks
+        .keys()
+        .filter(|k| !events.iter().any(|&(e, _)| e == k.as_str()))
+        .cloned()
+        .collect();
+    for event in stale_events {
+        if let Some(Value::Array(arr)) = hooks.get_mut(&event) {
+            let before = arr.len();
+            arr.retain(|item| {
+                let ours = item_contains_hook(item, "agent-desk-hook");
+                if ours {
+                    changes.push(removed(&event, item));
+                }
+                !ours
+            });
+            // Remove the key entirely if the array is now empty
+            if arr.len() < before && arr.is_empty() {
+                hooks.remove(&event);
+            }
         }
     }
 
-    for &(claude_event, hook_arg) in HOOK_EVENTS {
-        let command = format!("{} --event {}", hook_cmd_path, hook_arg);
+    for &(claude_event, hook_arg) in &events {
+        let command = format!("{} --event {}{}", hook_cmd_path, hook_arg, addr_args);
         // PermissionRequest is a long-poll: hook blocks until user responds.
         // Needs a large timeout so Claude Code doesn't kill the hook early.
-        let hook_obj = if claude_event == "PermissionRequest" {
-            json!({ "type": "command", "command": command, "timeout": 600 })
-        } else {
-            json!({ "type": "command", "command": command })
+        // The observe hook runs before every tool: cap it tightly instead.
+        let hook_obj = match claude_event {
+            "PermissionRequest" => json!({ "type": "command", "command": command, "timeout": 600 }),
+            "PreToolUse" => json!({ "type": "command", "command": command, "timeout": 5 }),
+            _ => json!({ "type": "command", "command": command }),
         };
         let entry = json!({ "hooks": [hook_obj] });
 
@@ -228,22 +534,39 @@ pub fn ensure_hooks_configured() {
             Some(Value::Array(arr)) => {
                 // Find existing agent-desk-hook entry (check both nested and flat formats)
                 let idx = arr.iter().position(|item| item_contains_hook(item, "agent-desk-hook"));
+                // Drop duplicates left by older installs — each would fire the hook again
+                if let Some(i) = idx {
+                    let mut pos = 0;
+                    arr.retain(|item| {
+                        let keep = pos <= i || !item_contains_hook(item, "agent-desk-hook");
+                        if !keep {
+                            changes.push(removed(claude_event, item));
+                        }
+                        pos += 1;
+                        keep
+                    });
+                }
                 match idx {
                     Some(i) if arr[i] == entry => {} // already up-to-date
                     Some(i) => {
+                        changes.push(HookChange {
+                            event: claude_event.into(),
+                            change: "update",
+                            command: Some(command),
+                            previous: Some(hook_command(&arr[i])),
+                        });
                         arr[i] = entry;
-                        changed = true;
                     }
                     None => {
+                        changes.push(HookChange { event: claude_event.into(), change: "add", command: Some(command), previous: None });
                         arr.push(entry);
-                        changed = true;
                     }
                 }
             }
             _ => {
                 // Missing or non-array → create
+                changes.push(HookChange { event: claude_event.into(), change: "add", command: Some(command), previous: None });
                 hooks.insert(claude_event.into(), json!([entry]));
-                changed = true;
             }
         }
     }
@@ -253,16 +576,43 @@ pub fn ensure_hooks_configured() {
     // The PermissionRequest hook gives agent-desk a chance to approve,
     // with automatic fallback to the terminal when it times out.
 
-    if !changed {
+    Ok(HookPlan { settings_path: settings_path.to_path_buf(), creates_file, changes, settings })
+}
+
+/// Write a plan's settings file. Returns whether there was anything to
+/// change.
+pub fn apply_hook_plan(plan: &HookPlan) -> Result<bool, String> {
+    let settings_path = plan.settings_path.as_path();
+    if plan.changes.is_empty() {
         tracing::debug!("Hooks already configured, no changes needed");
-        return;
+        return Ok(false);
     }
 
-    match serde_json::to_string_pretty(&settings) {
-        Ok(json_str) => match std::fs::write(&settings_path, json_str) {
-            Ok(_) => tracing::info!("Auto-configured hooks in {}", settings_path.display()),
-            Err(e) => tracing::warn!("Failed to write {}: {}", settings_path.display(), e),
-        },
-        Err(e) => tracing::warn!("Failed to serialize settings: {}", e),
+    // Ensure the .claude/ directory exists
+    if let Some(parent) = settings_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
     }
+    let json_str = serde_json::to_string_pretty(&plan.settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    crate::settings_backup::backup(settings_path)?;
+    std::fs::write(settings_path, json_str)
+        .map_err(|e| format!("Failed to write {}: {}", settings_path.display(), e))?;
+    for c in &plan.changes {
+        match c.change {
+            "remove" => tracing::info!("Removed {} agent-desk-hook entry", c.event),
+            _ => tracing::info!("Set {} hook command → {}", c.event, c.command.as_deref().unwrap_or("")),
+        }
+    }
+    tracing::info!("Configured hooks in {}", settings_path.display());
+    Ok(true)
+}
+
+/// The command of a hook entry (flat or nested), for showing a change.
+fn hook_command(item: &Value) -> String {
+    item.get("command")
+        .or_else(|| item.get("hooks").and_then(|h| h.get(0)).and_then(|h| h.get("command")))
+        .and_then(|c| c.as_str())
+        .unwrap_or("")
+        .to_string()
 }
@@ -0,0 +1,273 @@
+//! Mock agent simulator.
+//!
+//! Replays a scripted sequence of hook traffic (signals, lightweight hooks
+//! and permission requests) against a running server, writing a fake
+//! transcript as it goes, so the island, toasts and remote channels can be
+//! exercised without a real Claude session. Used by the `agent-desk-sim`
+//! binary (`simulator` feature), `POST /api/simulate/scenario` (debug
+//! builds) and `--replay` of recordings (see `recorder`).
+//!
+//! A scenario is JSON:
+//!
+//! ```json
+//! { "name": "demo", "cwd": "C:/code/demo", "steps": [
+//!     { "event": "session_start" },
+//!     { "delay_ms": 800, "event": "user_prompt",
+//!       "transcript": [{ "role": "user", "text": "fix the tests" }] },
+//!     { "delay_ms": 1500, "kind": "permission",
+//!       "body": { "tool_name": "Bash", "tool_input": { "command": "cargo test" } } },
+//!     { "delay_ms": 4000, "event": "stop", "body": { "last_assistant_message": "Done." } }
+//! ] }
+//! ```
+//!
+//! `kind` is `signal` (default, `/api/signal`), `hook` (`/api/hook?event=`),
+//! `permission` (`/api/permission-request`) or `pre_tool_check`
+//! (`/api/pre-tool-check`). The last two long-poll for the decision, so
+//! the replay moves on without them and only waits for their answers at
+//! the end. `session_id` and `cwd` fill in every body
+//! that lacks them; an empty `session_id` gets a fresh `sim-…` id per run.
+//! `transcript` rows are full Claude Code JSONL rows, or `{role, text}`
+//! shorthand.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub session_id: String,
+    #[serde(default = "default_cwd")]
+    pub cwd: String,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    /// Wait before this step, in scenario time (divided by the speed).
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    /// Hook event name (`session_start`, `user_prompt`, `pre_tool`, `stop`,
+    /// `notification`, `session_end`…); unused by `permission` steps.
+    #[serde(default)]
+    pub event: String,
+    #[serde(default)]
+    pub body: Value,
+    /// Rows appended to the fake transcript before the step is sent.
+    #[serde(default)]
+    pub transcript: Vec<Value>,
+}
+
+fn default_cwd() -> String {
+    std::env::temp_dir().join("agent-desk-sim").join("project").to_string_lossy().into_owned()
+}
+
+fn default_kind() -> String {
+    "signal".into()
+}
+
+/// Names accepted by `builtin`.
+pub const BUILTIN: &[&str] = &["basic", "permission", "waiting", "limit"];
+
+/// A scenario shipped with the simulator.
+pub fn builtin(name: &str) -> Option<Scenario> {
+    let steps = match name {
+        // Prompt, a few tools, done
+        "basic" => json!([
+            { "event": "session_start", "body": { "source": "startup" } },
+            { "delay_ms": 500, "event": "user_prompt",
+              "transcript": [{ "role": "user", "text": "Add a --verbose flag to the CLI" }] },
+            { "delay_ms": 1000, "kind": "hook", "event": "pre_tool",
+              "transcript": [{ "role": "assistant", "text": "Let me look at the argument parser." }] },
+            { "delay_ms": 1500, "kind": "hook", "event": "pre_tool" },
+            { "delay_ms": 2000, "kind": "hook", "event": "pre_tool",
+              "transcript": [{ "role": "assistant", "text": "Adding the flag and wiring it to the logger." }] },
+            { "delay_ms": 3000, "event": "stop",
+              "body": { "last_assistant_message": "Added --verbose; it raises the log level to debug." },
+              "transcript": [{ "role": "assistant", "text": "Added --verbose; it raises the log level to debug." }] }
+        ]),
+        // Work, then a Bash permission prompt left for the user
+        "permission" => json!([
+            { "event": "session_start", "body": { "source": "startup" } },
+            { "delay_ms": 500, "event": "user_prompt",
+              "transcript": [{ "role": "user", "text": "Run the test suite and fix failures" }] },
+            { "delay_ms": 1000, "kind": "hook", "event": "pre_tool" },
+            { "delay_ms": 1500, "kind": "permission",
+              "body": { "tool_name": "Bash", "tool_input": { "command": "cargo test --workspace", "description": "Run tests" } } }
+        ]),
+        // Finishes a turn with a question, then waits on input
+        "waiting" => json!([
+            { "event": "session_start", "body": { "source": "startup" } },
+            { "delay_ms": 500, "event": "user_prompt",
+              "transcript": [{ "role": "user", "text": "Clean up the old migrations" }] },
+            { "delay_ms": 2000, "event": "stop",
+              "body": { "last_assistant_message": "Should I also drop the legacy tables?" },
+              "transcript": [{ "role": "assistant", "text": "Should I also drop the legacy tables?" }] },
+            { "delay_ms": 1000, "event": "notification",
+              "body": { "notification_type": "idle_prompt", "message": "Claude is waiting for your input" } }
+        ]),
+        // Hits the usage limit mid-task
+        "limit" => json!([
+            { "event": "session_start", "body": { "source": "startup" } },
+            { "delay_ms": 500, "event": "user_prompt",
+              "transcript": [{ "role": "user", "text": "Refactor the storage layer" }] },
+            { "delay_ms": 1500, "event": "stop",
+              "body": { "last_assistant_message": "5-hour limit reached \u{2219} resets 5pm" } }
+        ]),
+        _ => return None,
+    };
+    Some(Scenario {
+        name: name.to_string(),
+        session_id: String::new(),
+        cwd: default_cwd(),
+        steps: serde_json::from_value(steps).ok()?,
+    })
+}
+
+/// A scenario file: one JSON scenario, or JSONL steps as written by
+/// `/api/record/start`.
+pub fn load_file(path: &std::path::Path) -> Result<Scenario, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    // A one-line recording is valid JSON too; only an object with `steps` is a scenario
+    let whole = serde_json::from_str::<Value>(&text).ok().filter(|v| v.get("steps").is_some());
+    let mut scenario = match whole {
+        Some(v) => serde_json::from_value::<Scenario>(v).map_err(|e| e.to_string())?,
+        None => Scenario {
+            name: String::new(),
+            session_id: String::new(),
+            cwd: String::new(),
+            steps: text
+                .lines()
+                .enumerate()
+                .filter(|(_, l)| !l.trim().is_empty())
+                .map(|(i, l)| serde_json::from_str::<Step>(l).map_err(|e| format!("line {}: {}", i + 1, e)))
+                .collect::<Result<_, _>>()?,
+        },
+    };
+    if scenario.name.is_empty() {
+        scenario.name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    }
+    Ok(scenario)
+}
+
+/// The local server's URL, from config.yaml's `manager.port`.
+pub fn local_base_url() -> String {
+    let manager = crate::config::load_config().manager;
+    format!("http://{}", crate::setup::host_port(&manager.host, manager.port))
+}
+
+impl Scenario {
+    /// Fix the session id for this run (fresh `sim-…` when unset).
+    pub fn prepared(mut self) -> Self {
+        if self.session_id.is_empty() {
+            self.session_id = format!("sim-{}", &uuid::Uuid::new_v4().to_string()[..8]);
+        }
+        self
+    }
+
+    fn transcript_path(&self) -> PathBuf {
+        std::env::temp_dir().join("agent-desk-sim").join(format!("{}.jsonl", self.session_id))
+    }
+}
+
+/// Replay `scenario` against the server at `base_url` (e.g.
+/// `http://127.0.0.1:15924`), `speed` times faster than scripted.
+/// Returns the number of steps sent, once any permission requests among
+/// them have been answered.
+pub async fn run(client: &reqwest::Client, base_url: &str, scenario: &Scenario, speed: f64) -> Result<usize, String> {
+    let base = base_url.trim_end_matches('/');
+    let speed = if speed > 0.0 { speed } else { 1.0 };
+    let transcript = scenario.transcript_path();
+    if let Some(dir) = transcript.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    tracing::info!("Simulating {:?} as {} ({} steps, {}x)", scenario.name, scenario.session_id, scenario.steps.len(), speed);
+
+    let mut pending = Vec::new();
+    for (i, step) in scenario.steps.iter().enumerate() {
+        if step.delay_ms > 0 {
+            tokio::time::sleep(Duration::from_secs_f64(step.delay_ms as f64 / 1000.0 / speed)).await;
+        }
+        if !step.transcript.is_empty() {
+            append_transcript(&transcript, &scenario.session_id, &scenario.cwd, &step.transcript)
+                .map_err(|e| format!("step {}: transcript: {}", i, e))?;
+        }
+
+        let mut body = match &step.body {
+            Value::Object(m) => m.clone(),
+            _ => serde_json::Map::new(),
+        };
+        body.entry("session_id").or_insert_with(|| json!(scenario.session_id));
+        body.entry("cwd").or_insert_with(|| json!(scenario.cwd));
+        let url = match step.kind.as_str() {
+            "signal" => {
+                body.entry("event").or_insert_with(|| json!(step.event));
+                body.entry("transcript_path").or_insert_with(|| json!(transcript.to_string_lossy()));
+                format!("{}/api/signal", base)
+            }
+            "hook" => format!("{}/api/hook?event={}", base, urlencoding::encode(&step.event)),
+            "permission" | "pre_tool_check" => {
+                let path = if step.kind == "permission" { "permission-request" } else { "pre-tool-check" };
+                let req = client.post(format!("{}/api/{}", base, path)).json(&body);
+                pending.push(tokio::spawn(async move {
+                    let _ = req.send().await;
+                }));
+                continue;
+            }
+            other => return Err(format!("step {}: unknown kind {:?}", i, other)),
+        };
+        let resp = client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("step {}: {}", i, e))?;
+        let reply: Value = resp.json().await.unwrap_or_default();
+        if reply.get("ok").and_then(|v| v.as_bool()) == Some(false) {
+            return Err(format!("step {}: {}", i, reply.get("error").and_then(|v| v.as_str()).unwrap_or("rejected")));
+        }
+    }
+    futures::future::join_all(pending).await;
+    Ok(scenario.steps.len())
+}
+
+/// Append rows to the fake transcript, expanding `{role, text}` shorthand
+/// into Claude Code's JSONL shape.
+fn append_transcript(path: &PathBuf, session_id: &str, cwd: &str, rows: &[Value]) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    for row in rows {
+        let row = if row.get("type").is_some() {
+            row.clone()
+        } else {
+            let role = row.get("role").and_then(|v| v.as_str()).unwrap_or("assistant");
+            let text = row.get("text").and_then(|v| v.as_str()).unwrap_or("");
+            let message = if role == "user" {
+                json!({ "role": "user", "content": text })
+            } else {
+                json!({
+                    "role": "assistant",
+                    "model": "claude-simulated",
+                    "content": [{ "type": "text", "text": text }],
+                    "usage": { "input_tokens": 0, "output_tokens": text.len() / 4 + 1 },
+                })
+            };
+            json!({
+                "type": role,
+                "uuid": uuid::Uuid::new_v4().to_string(),
+                "sessionId": session_id,
+                "cwd": cwd,
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+                "message": message,
+            })
+        };
+        writeln!(file, "{}", row)?;
+    }
+    Ok(())
+}
@@ -1,17 +1,73 @@
 use tokio::sync::broadcast;
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-const CHANNEL_CAPACITY: usize = 100;
+/// Per-client bookkeeping: who it is and how far it fell behind.
+struct ClientLag {
+    connected: Instant,
+    /// Unix time it connected.
+    connected_at: f64,
+    /// Peer address, e.g. `192.168.1.20:51234`.
+    peer: String,
+    user_agent: String,
+    /// Times the client fell behind the ring.
+    lag_events: u64,
+    /// Messages it never received.
+    dropped: u64,
+}
+
+#[derive(Default)]
+struct Clients {
+    next_id: AtomicU64,
+    lagging: Mutex<HashMap<u64, ClientLag>>,
+    /// Lifetime drops, including clients that have disconnected.
+    dropped_total: AtomicU64,
+}
 
 #[derive(Clone)]
 pub struct SSEBroadcaster {
     tx: broadcast::Sender<String>,
+    capacity: usize,
+    clients: Arc<Clients>,
+}
+
+/// A connected SSE client. Unregisters itself when the stream is dropped.
+pub struct SseClient {
+    id: u64,
+    clients: Arc<Clients>,
+}
+
+impl SseClient {
+    /// Record `missed` messages dropped because this client lagged, and
+    /// return the `resync` message telling it to refetch full state.
+    pub fn lagged(&self, missed: u64) -> String {
+        if let Some(c) = mutex_lock!(self.clients.lagging).get_mut(&self.id) {
+            c.lag_events += 1;
+            c.dropped += missed;
+        }
+        self.clients.dropped_total.fetch_add(missed, Ordering::Relaxed);
+        tracing::warn!("SSE client {} lagged, {} messages dropped", self.id, missed);
+        json!({ "type": "resync", "missed": missed }).to_string()
+    }
+}
+
+impl Drop for SseClient {
+    fn drop(&mut self) {
+        if let Some(c) = mutex_lock!(self.clients.lagging).remove(&self.id) {
+            tracing::debug!("SSE client {} ({}) disconnected after {}s", self.id, c.peer, c.connected.elapsed().as_secs());
+        }
+    }
 }
 
 impl SSEBroadcaster {
-    pub fn new() -> Self {
-        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
-        Self { tx }
+    /// `capacity`: messages buffered per client before it starts lagging.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(16);
+        let (tx, _) = broadcast::channel(capacity);
+        Self { tx, capacity, clients: Arc::new(Clients::default()) }
     }
 
     /// Broadcast a message to all SSE clients.
@@ -27,8 +83,55 @@ impl SSEBroadcaster {
         let _ = self.tx.send(msg);
     }
 
-    /// Subscribe to SSE events. Returns a receiver.
+    /// Every message from now on, for an in-process listener (not counted
+    /// as a client).
     pub fn subscribe(&self) -> broadcast::Receiver<String> {
         self.tx.subscribe()
     }
+
+    /// Subscribe an SSE client. Keep the `SseClient` alive as long as the
+    /// stream; it tracks the client's lag.
+    pub fn connect(&self, peer: String, user_agent: String) -> (broadcast::Receiver<String>, SseClient) {
+        let id = self.clients.next_id.fetch_add(1, Ordering::Relaxed);
+        let connected_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        tracing::debug!("SSE client {} connected from {} ({})", id, peer, user_agent);
+        mutex_lock!(self.clients.lagging).insert(id, ClientLag {
+            connected: Instant::now(),
+            connected_at,
+            peer,
+            user_agent,
+            lag_events: 0,
+            dropped: 0,
+        });
+        (self.tx.subscribe(), SseClient { id, clients: self.clients.clone() })
+    }
+
+    /// Connected stream clients.
+    pub fn client_count(&self) -> usize {
+        mutex_lock!(self.clients.lagging).len()
+    }
+
+    /// Capacity, connected clients and their drops, for metrics/diagnostics.
+    pub fn stats(&self) -> Value {
+        let clients = mutex_lock!(self.clients.lagging);
+        let mut list: Vec<Value> = clients
+            .iter()
+            .map(|(id, c)| json!({
+                "id": id,
+                "peer": c.peer,
+                "user_agent": c.user_agent,
+                "connected_at": c.connected_at,
+                "connected_secs": c.connected.elapsed().as_secs(),
+                "lag_events": c.lag_events,
+                "dropped": c.dropped,
+            }))
+            .collect();
+        list.sort_by_key(|c| c["id"].as_u64());
+        json!({
+            "capacity": self.capacity,
+            "client_count": list.len(),
+            "clients": list,
+            "dropped_total": self.clients.dropped_total.load(Ordering::Relaxed),
+        })
+    }
 }
@@ -0,0 +1,400 @@
+//! Whole-state backups. `GET /api/backup` zips the config, the state files
+//! in the data dir (sessions with their colors, pins and links, the event
+//! log, the island's place, schema versions), Claude Code's
+//! `settings.json` (hooks and permission rules) and the app's logs, with a
+//! `manifest.json` listing them. `POST /api/backup/restore` takes such a
+//! zip back; `settings.json` only when asked to, since its hook commands
+//! run on every agent action.
+//!
+//! The running stores would write over restored files, so a restore is
+//! staged in `<data dir>/restore-pending/` and put in place at the next
+//! start, before anything loads them. What it replaces is saved first as
+//! `pre-restore-<time>.zip` in the data dir. Logs are kept for the record
+//! and never restored.
+//!
+//! The zips are plain deflate archives written and read here with
+//! `flate2`, readable by any unzip tool.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+
+use crate::config::Config;
+
+/// Folder in the data dir holding a staged restore.
+const PENDING_DIR: &str = "restore-pending";
+
+/// Largest file accepted from a backup once inflated.
+const MAX_ENTRY_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Name of Claude Code's settings in a backup.
+const CLAUDE_SETTINGS: &str = "claude/settings.json";
+
+/// A file a backup carries: its name in the zip and where it lives.
+struct Source {
+    name: String,
+    path: PathBuf,
+    /// Put back by a restore (logs aren't).
+    restore: bool,
+}
+
+fn data_dir(config: &Config) -> PathBuf {
+    Path::new(&config.general.sessions_file).parent().unwrap_or(Path::new(".")).to_path_buf()
+}
+
+fn sources(config: &Config, with_logs: bool) -> Vec<Source> {
+    let state = Path::new(&config.general.sessions_file);
+    let mut sources = vec![
+        Source { name: "config.yaml".into(), path: crate::config::find_config_path(), restore: true },
+        Source { name: "state/sessions.json".into(), path: state.to_path_buf(), restore: true },
+        Source { name: "state/events.jsonl".into(), path: PathBuf::from(&config.manager.events_file), restore: true },
+    ];
+    for name in ["schema.json", "island.json", "last_seen.json"] {
+        sources.push(Source { name: format!("state/{}", name), path: state.with_file_name(name), restore: true });
+    }
+    if let Some(path) = crate::setup::claude_settings_path() {
+        sources.push(Source { name: CLAUDE_SETTINGS.into(), path, restore: true });
+    }
+    if with_logs {
+        let mut logs: Vec<PathBuf> = std::fs::read_dir(crate::config::log_dir())
+            .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_file()).collect())
+            .unwrap_or_default();
+        logs.sort();
+        for path in logs {
+            let name = format!("logs/{}", path.file_name().unwrap_or_default().to_string_lossy());
+            sources.push(Source { name, path, restore: false });
+        }
+    }
+    sources
+}
+
+/// A zip of everything `sources` lists that exists, and its manifest.
+fn pack(sources: &[Source]) -> Result<Vec<u8>, String> {
+    let mut entries = Vec::new();
+    for s in sources {
+        match std::fs::read(&s.path) {
+            Ok(data) => entries.push((s.name.clone(), data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to read {}: {}", s.path.display(), e)),
+        }
+    }
+    let manifest = json!({
+        "app": "agent-desk",
+        "version": env!("CARGO_PKG_VERSION"),
+        "created_at": chrono::Local::now().to_rfc3339(),
+        "files": entries.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+    });
+    entries.insert(0, ("manifest.json".into(), serde_json::to_vec_pretty(&manifest).unwrap_or_default()));
+    write_zip(&entries).map_err(|e| format!("Failed to build backup: {}", e))
+}
+
+/// The backup `GET /api/backup` serves. Blocking: reads every file.
+pub fn export(config: &Config) -> Result<Vec<u8>, String> {
+    pack(&sources(config, true))
+}
+
+/// What `stage` did with a backup's files.
+pub struct Restore {
+    pub staged: Vec<String>,
+    /// Restorable files left out: Claude Code's settings unless asked for.
+    pub skipped: Vec<String>,
+}
+
+/// Check `zip` is a backup and stage its files for the next start, Claude
+/// Code's `settings.json` only with `claude_settings`.
+pub fn stage(config: &Config, zip: &[u8], claude_settings: bool) -> Result<Restore, String> {
+    let entries = read_zip(zip)?;
+    let manifest = entries
+        .iter()
+        .find(|(name, _)| name == "manifest.json")
+        .and_then(|(_, data)| serde_json::from_slice::<serde_json::Value>(data).ok());
+    if manifest.as_ref().and_then(|m| m.get("app")).and_then(|v| v.as_str()) != Some("agent-desk") {
+        return Err("not an Agent Desk backup (no manifest.json)".into());
+    }
+    // Only the files a backup restores, so names can't point elsewhere
+    let restorable: Vec<String> = sources(config, false).into_iter().filter(|s| s.restore).map(|s| s.name).collect();
+    let (staged, skipped): (Vec<_>, Vec<_>) = entries
+        .iter()
+        .filter(|(name, _)| restorable.contains(name))
+        .partition(|(name, _)| claude_settings || name != CLAUDE_SETTINGS);
+    if staged.is_empty() {
+        return Err("the backup holds nothing to restore".into());
+    }
+
+    let dir = data_dir(config).join(PENDING_DIR);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear {}: {}", dir.display(), e))?;
+    }
+    for (name, data) in &staged {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    let names: Vec<String> = staged.iter().map(|(name, _)| name.clone()).collect();
+    tracing::info!("Backup restore staged for the next start: {}", names.join(", "));
+    Ok(Restore { staged: names, skipped: skipped.iter().map(|(name, _)| name.clone()).collect() })
+}
+
+/// Put a staged restore in place. Call at startup, before the stores load.
+/// Returns whether `config.yaml` was replaced, so it can be loaded again.
+pub fn apply_staged(config: &Config) -> bool {
+    let dir = data_dir(config).join(PENDING_DIR);
+    if !dir.is_dir() {
+        return false;
+    }
+    let sources: Vec<Source> = sources(config, false).into_iter().filter(|s| s.restore).collect();
+    let saved = data_dir(config).join(format!("pre-restore-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    if let Err(e) = pack(&sources).and_then(|zip| std::fs::write(&saved, zip).map_err(|e| e.to_string())) {
+        tracing::warn!("Backup restore not applied: saving the current state to {} failed: {}", saved.display(), e);
+        return false;
+    }
+
+    let mut config_restored = false;
+    for s in &sources {
+        let staged = dir.join(&s.name);
+        if !staged.is_file() {
+            continue;
+        }
+        if let Some(parent) = s.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        match std::fs::copy(&staged, &s.path) {
+            Ok(_) => {
+                tracing::info!("Restored {} from backup", s.path.display());
+                config_restored |= s.name == "config.yaml";
+            }
+            Err(e) => tracing::warn!("Failed to restore {}: {}", s.path.display(), e),
+        }
+    }
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        tracing::warn!("Failed to remove {}: {}", dir.display(), e);
+    }
+    tracing::info!("Backup restored; the previous state is kept in {}", saved.display());
+    config_restored
+}
+
+// ---------------------------------------------------------------------------
+// Zip files (deflate, no zip64: entries and archives under 4 GB)
+// ---------------------------------------------------------------------------
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIR: u32 = 0x0605_4b50;
+/// Names are UTF-8.
+const FLAG_UTF8: u16 = 0x0800;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+fn write_zip(entries: &[(String, Vec<u8>)]) -> std::io::Result<Vec<u8>> {
+    let now = chrono::Local::now().naive_local();
+    let (time, date) = dos_time(&now);
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    for (name, data) in entries {
+        let mut crc = flate2::Crc::new();
+        crc.update(data);
+        let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data)?;
+        let packed = encoder.finish()?;
+        let too_big = || std::io::Error::other(format!("{} is too large for a zip without zip64", name));
+        let offset = u32::try_from(out.len()).map_err(|_| too_big())?;
+        let size = u32::try_from(data.len()).map_err(|_| too_big())?;
+        let packed_size = u32::try_from(packed.len()).map_err(|_| too_big())?;
+        let name_len = name.len() as u16;
+
+        // Local header, then the data
+        put32(&mut out, LOCAL_HEADER);
+        for v in [20, FLAG_UTF8, DEFLATED, time, date] {
+            put16(&mut out, v);
+        }
+        for v in [crc.sum(), packed_size, size] {
+            put32(&mut out, v);
+        }
+        put16(&mut out, name_len);
+        put16(&mut out, 0);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&packed);
+
+        put32(&mut central, CENTRAL_HEADER);
+        for v in [20, 20, FLAG_UTF8, DEFLATED, time, date] {
+            put16(&mut central, v);
+        }
+        for v in [crc.sum(), packed_size, size] {
+            put32(&mut central, v);
+        }
+        // Name length, extra, comment, disk, internal attributes
+        for v in [name_len, 0, 0, 0, 0] {
+            put16(&mut central, v);
+        }
+        put32(&mut central, 0);
+        put32(&mut central, offset);
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    put32(&mut out, END_OF_CENTRAL_DIR);
+    for v in [0, 0, entries.len() as u16, entries.len() as u16] {
+        put16(&mut out, v);
+    }
+    put32(&mut out, central.len() as u32);
+    put32(&mut out, central_offset);
+    put16(&mut out, 0);
+    Ok(out)
+}
+
+/// Every file in a zip, as (name, contents).
+fn read_zip(zip: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let bad = |what: &str| format!("not a valid zip: {}", what);
+    // The end record is the last 22 bytes, unless a comment follows it
+    let end = (0..=zip.len().saturating_sub(22))
+        .rev()
+        .take(u16::MAX as usize + 1)
+        .find(|&i| get32(zip, i) == Some(END_OF_CENTRAL_DIR))
+        .ok_or_else(|| bad("no end of central directory"))?;
+    let count = get16(zip, end + 10).ok_or_else(|| bad("truncated"))? as usize;
+    let mut at = get32(zip, end + 16).ok_or_else(|| bad("truncated"))? as usize;
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if get32(zip, at) != Some(CENTRAL_HEADER) {
+            return Err(bad("broken central directory"));
+        }
+        let field16 = |off: usize| get16(zip, at + off).ok_or_else(|| bad("truncated"));
+        let field32 = |off: usize| get32(zip, at + off).ok_or_else(|| bad("truncated"));
+        let method = field16(10)?;
+        let crc = field32(16)?;
+        let packed_size = field32(20)? as usize;
+        let size = field32(24)? as u64;
+        let (name_len, extra_len, comment_len) = (field16(28)? as usize, field16(30)? as usize, field16(32)? as usize);
+        let local = field32(42)? as usize;
+        let name = zip.get(at + 46..at + 46 + name_len).ok_or_else(|| bad("truncated"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        at += 46 + name_len + extra_len + comment_len;
+
+        if get32(zip, local) != Some(LOCAL_HEADER) {
+            return Err(bad("broken local header"));
+        }
+        let start = local
+            + 30
+            + get16(zip, local + 26).ok_or_else(|| bad("truncated"))? as usize
+            + get16(zip, local + 28).ok_or_else(|| bad("truncated"))? as usize;
+        let packed = zip.get(start..start + packed_size).ok_or_else(|| bad("truncated"))?;
+        if size > MAX_ENTRY_BYTES {
+            return Err(format!("{} is too large", name));
+        }
+        let data = match method {
+            STORED => packed.to_vec(),
+            DEFLATED => {
+                // Not sized from the header: a corrupt one could claim 1 GB
+                let mut data = Vec::new();
+                flate2::read::DeflateDecoder::new(packed)
+                    .take(size + 1)
+                    .read_to_end(&mut data)
+                    .map_err(|e| format!("{}: {}", name, e))?;
+                data
+            }
+            m => return Err(format!("{}: unsupported compression method {}", name, m)),
+        };
+        let mut sum = flate2::Crc::new();
+        sum.update(&data);
+        if data.len() as u64 != size || sum.sum() != crc {
+            return Err(format!("{} is corrupt", name));
+        }
+        entries.push((name, data));
+    }
+    Ok(entries)
+}
+
+/// MS-DOS time and date, as zip headers store them.
+fn dos_time(t: &chrono::NaiveDateTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let time = ((t.hour() << 11) | (t.minute() << 5) | (t.second() / 2)) as u16;
+    let date = (((t.year().clamp(1980, 2107) - 1980) as u32) << 9) | (t.month() << 5) | t.day();
+    (time, date as u16)
+}
+
+fn put16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn get16(b: &[u8], at: usize) -> Option<u16> {
+    b.get(at..at + 2).map(|s| u16::from_le_bytes([s[0], s[1]]))
+}
+
+fn get32(b: &[u8], at: usize) -> Option<u32> {
+    b.get(at..at + 4).map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<(String, Vec<u8>)> {
+        vec![
+            ("manifest.json".into(), br#"{"app": "agent-desk"}"#.to_vec()),
+            ("state/events.jsonl".into(), "{\"id\": 1}\n".repeat(500).into_bytes()),
+            ("claude/设置.json".into(), Vec::new()),
+        ]
+    }
+
+    #[test]
+    fn zip_round_trips() {
+        let entries = sample();
+        let zip = write_zip(&entries).unwrap();
+        assert_eq!(read_zip(&zip).unwrap(), entries);
+        assert_eq!(read_zip(&write_zip(&[]).unwrap()).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn zip_with_a_trailing_comment_reads() {
+        let mut zip = write_zip(&sample()).unwrap();
+        let len = zip.len();
+        zip[len - 2..].copy_from_slice(&4u16.to_le_bytes());
+        zip.extend_from_slice(b"note");
+        assert_eq!(read_zip(&zip).unwrap(), sample());
+    }
+
+    #[test]
+    fn truncated_zips_are_rejected() {
+        let zip = write_zip(&sample()).unwrap();
+        for len in 0..zip.len() {
+            assert!(read_zip(&zip[..len]).is_err(), "accepted {} of {} bytes", len, zip.len());
+        }
+    }
+
+    #[test]
+    fn corrupt_zips_are_rejected() {
+        let zip = write_zip(&sample()).unwrap();
+        assert!(read_zip(b"PK\x05\x06 but not a zip at all").is_err());
+        // Every single-byte change fails cleanly (or leaves the files as
+        // they were, for bytes nothing checks: times, attributes)
+        for i in 0..zip.len() {
+            let mut bad = zip.clone();
+            bad[i] ^= 0x55;
+            if let Ok(entries) = read_zip(&bad) {
+                let names: Vec<&String> = entries.iter().map(|(name, _)| name).collect();
+                let data: Vec<&Vec<u8>> = entries.iter().map(|(_, data)| data).collect();
+                let orig = sample();
+                assert_eq!(data, orig.iter().map(|(_, d)| d).collect::<Vec<_>>(), "byte {}", i);
+                assert_eq!(names.len(), orig.len(), "byte {}", i);
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_entries_are_rejected() {
+        let mut zip = write_zip(&sample()[..1]).unwrap();
+        // The central directory's uncompressed size, past the limit
+        let central = (0..zip.len()).find(|&i| get32(&zip, i) == Some(CENTRAL_HEADER)).unwrap();
+        zip[central + 24..central + 28].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_zip(&zip).unwrap_err().contains("too large"));
+    }
+}
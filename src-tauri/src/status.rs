@@ -0,0 +1,693 @@
+//! Session status: which tracked session each scanned agent process is,
+//! and the overall state the island and tray show.
+//!
+//! The session tracker is the source of truth for CWD and status (from
+//! hooks); the process scanner adds PID, uptime and creation time. A
+//! process is paired with a session by the PID its hooks reported, else by
+//! CWD, else with the most recent session left over; a process nothing
+//! pairs with is shown as a "discovered" session of its own.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::process::cwd_key;
+use crate::process::ProcessInfo;
+use crate::protocol::{SessionStatus, WaitingReason};
+use crate::server::AppState;
+use crate::session::SessionInfo;
+
+/// Longest last message sent to the island and inbox. The session and its
+/// events keep the full text, which session history and `/api/events` return.
+const DISPLAY_MESSAGE_CHARS: usize = 1000;
+
+/// Prefix of the sessions made up for processes no hook has reported.
+const DISCOVERED_PREFIX: &str = "discovered-";
+
+/// `text` cut to `DISPLAY_MESSAGE_CHARS` for a live display payload.
+pub fn display_message(text: &str) -> Cow<'_, str> {
+    match text.char_indices().nth(DISPLAY_MESSAGE_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]).into(),
+        None => text.into(),
+    }
+}
+
+/// Status of a session as the UI shows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayStatus {
+    Active,
+    Waiting,
+    Stopped,
+}
+
+impl From<&SessionStatus> for DisplayStatus {
+    fn from(status: &SessionStatus) -> Self {
+        match status {
+            SessionStatus::Active => Self::Active,
+            SessionStatus::Stopped | SessionStatus::Ended => Self::Stopped,
+            SessionStatus::Waiting | SessionStatus::Idle | SessionStatus::Unknown => Self::Waiting,
+        }
+    }
+}
+
+/// One agent process with the session it runs.
+#[derive(Debug, Clone, Serialize)]
+pub struct MergedSession {
+    pub pid: u32,
+    pub name: String,
+    pub agent_type: String,
+    pub cwd: String,
+    pub uptime: u64,
+    pub create_time: f64,
+    pub status: DisplayStatus,
+    pub session_id: String,
+    pub notification_type: String,
+    pub notification_message: String,
+    /// Why it waits; only set while `status` is waiting.
+    pub waiting_reason: Option<WaitingReason>,
+    pub limit_resets_at: Option<f64>,
+    pub model: Option<String>,
+    /// Cut for display (see `display_message`).
+    pub last_message: String,
+    pub color: String,
+    pub current_tool: Option<String>,
+    pub pinned: bool,
+    /// Remote pushes about it are muted (`POST /api/session/{id}/mute-remote`).
+    pub remote_muted: bool,
+    /// When the session (or, if discovered, its process) started.
+    #[serde(skip)]
+    pub started_at: f64,
+    /// No session matched: `session_id` is a made-up `discovered-{pid}`
+    /// the caller still has to register.
+    #[serde(skip)]
+    pub discovered: bool,
+}
+
+impl MergedSession {
+    fn paired(proc: &ProcessInfo, info: &SessionInfo) -> Self {
+        Self {
+            pid: proc.pid,
+            name: proc.name.clone(),
+            agent_type: proc.agent_type.clone(),
+            cwd: if info.cwd.is_empty() { proc.cwd.clone() } else { info.cwd.clone() },
+            uptime: proc.uptime,
+            create_time: proc.create_time,
+            status: DisplayStatus::from(&info.status),
+            session_id: info.session_id.clone(),
+            notification_type: info.notification_type.clone().unwrap_or_default(),
+            notification_message: info.notification_message.clone().unwrap_or_default(),
+            waiting_reason: info.waiting_reason.filter(|_| DisplayStatus::from(&info.status) == DisplayStatus::Waiting),
+            limit_resets_at: info.limit_resets_at,
+            model: info.model.clone(),
+            last_message: display_message(info.last_message.as_deref().unwrap_or("")).into_owned(),
+            color: info.display_color(),
+            current_tool: info.current_tool.clone(),
+            pinned: info.pinned,
+            remote_muted: info.remote_muted,
+            started_at: info.started_at,
+            discovered: false,
+        }
+    }
+
+    fn discovered(proc: &ProcessInfo) -> Self {
+        Self {
+            pid: proc.pid,
+            name: proc.name.clone(),
+            agent_type: proc.agent_type.clone(),
+            cwd: proc.cwd.clone(),
+            uptime: proc.uptime,
+            create_time: proc.create_time,
+            status: DisplayStatus::Waiting,
+            session_id: format!("{}{}", DISCOVERED_PREFIX, proc.pid),
+            notification_type: String::new(),
+            notification_message: String::new(),
+            waiting_reason: None,
+            limit_resets_at: None,
+            model: None,
+            last_message: String::new(),
+            color: crate::session::cwd_color(&proc.cwd).to_string(),
+            current_tool: None,
+            pinned: false,
+            remote_muted: false,
+            started_at: proc.create_time,
+            discovered: true,
+        }
+    }
+}
+
+/// Result of `merge`.
+#[derive(Debug, Default)]
+pub struct Merged {
+    /// One per process, in scan order.
+    pub sessions: Vec<MergedSession>,
+    /// Discovered sessions whose process is gone, to drop from the tracker.
+    pub stale: Vec<String>,
+}
+
+/// Pair each process with a session updated within `ttl` seconds of `now`.
+///
+/// Ended sessions and sub-agents (which run inside their parent's process)
+/// never pair. A session only pairs with a process of its own agent type
+/// (when known), and one whose hooks reported another scanned process's
+/// PID is left to that process.
+pub fn merge(processes: &[ProcessInfo], sessions: &[SessionInfo], now: f64, ttl: u64) -> Merged {
+    let cutoff = now - ttl as f64;
+    let live: HashSet<u32> = processes.iter().map(|p| p.pid).collect();
+    let mut merged = Merged::default();
+
+    let mut candidates: Vec<&SessionInfo> = Vec::new();
+    for info in sessions.iter().filter(|i| i.updated_at >= cutoff) {
+        if info.session_id.starts_with(DISCOVERED_PREFIX) && !info.agent_pid.is_some_and(|p| live.contains(&p)) {
+            merged.stale.push(info.session_id.clone());
+            continue;
+        }
+        let sub_agent = info.parent_session_id.as_deref().is_some_and(|p| !p.is_empty());
+        if info.status != SessionStatus::Ended && !sub_agent {
+            candidates.push(info);
+        }
+    }
+    // Most recently updated first: on a tie in the later passes, the
+    // freshest session wins
+    candidates.sort_by(|a, b| b.updated_at.total_cmp(&a.updated_at));
+
+    let mut pairs: Vec<Option<&SessionInfo>> = vec![None; processes.len()];
+    let mut take = |fits: &dyn Fn(&ProcessInfo, &SessionInfo) -> bool| {
+        for (i, proc) in processes.iter().enumerate() {
+            if pairs[i].is_some() {
+                continue;
+            }
+            let found = candidates.iter().position(|info| same_agent(proc, info) && fits(proc, info));
+            if let Some(idx) = found {
+                pairs[i] = Some(candidates.remove(idx));
+            }
+        }
+    };
+    // 1. The PID its hooks reported: stable across CWD changes
+    take(&|proc, info| info.agent_pid == Some(proc.pid));
+    // 2. Same CWD
+    take(&|proc, info| {
+        !owned_elsewhere(info, proc, &live) && !info.cwd.is_empty() && cwd_key(&info.cwd) == cwd_key(&proc.cwd)
+    });
+    // 3. Whatever is left, most recent first
+    take(&|proc, info| !owned_elsewhere(info, proc, &live));
+
+    merged.sessions = processes
+        .iter()
+        .zip(pairs)
+        .map(|(proc, info)| match info {
+            Some(info) => MergedSession::paired(proc, info),
+            None => MergedSession::discovered(proc),
+        })
+        .collect();
+    merged
+}
+
+/// Put `sessions` in the order `general.session_sort` asks for: by each
+/// of `keys` in turn (`pinned`, `status`, `project`, `started`; others are
+/// ignored), then by PID so the order never depends on the scan.
+pub fn sort_sessions(sessions: &mut [MergedSession], keys: &[String]) {
+    if keys.is_empty() {
+        return;
+    }
+    sessions.sort_by(|a, b| {
+        keys.iter()
+            .map(|key| match key.as_str() {
+                "pinned" => b.pinned.cmp(&a.pinned),
+                "status" => status_rank(a).cmp(&status_rank(b)),
+                "project" => project_key(a).cmp(&project_key(b)),
+                "started" => a.started_at.total_cmp(&b.started_at),
+                _ => Ordering::Equal,
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| a.pid.cmp(&b.pid))
+    });
+}
+
+/// Waiting for the user first, then working, capped, and stopped.
+fn status_rank(s: &MergedSession) -> u8 {
+    if s.notification_type == "limit_reached" {
+        return 2;
+    }
+    match s.status {
+        DisplayStatus::Waiting => 0,
+        DisplayStatus::Active => 1,
+        DisplayStatus::Stopped => 3,
+    }
+}
+
+fn project_key(s: &MergedSession) -> String {
+    crate::tray::project_name(&s.cwd).to_lowercase()
+}
+
+/// The session's agent, if known, runs this kind of process.
+fn same_agent(proc: &ProcessInfo, info: &SessionInfo) -> bool {
+    info.agent_type.as_deref().is_none_or(|a| a == proc.agent_type)
+}
+
+/// The session's hooks reported a different process that is still running.
+fn owned_elsewhere(info: &SessionInfo, proc: &ProcessInfo, live: &HashSet<u32>) -> bool {
+    info.agent_pid.is_some_and(|pid| pid != proc.pid && live.contains(&pid))
+}
+
+/// Merged session list for the API: `merge` over the scanned processes
+/// and tracked sessions. Drops stale discovered sessions and registers
+/// new ones, so hooks that fire later update them.
+pub fn scan_and_merge(state: &AppState) -> Vec<Value> {
+    let processes = state.registry.get_cached();
+    let session_ttl = state.config.general.session_ttl;
+    let tracked: Vec<SessionInfo> = state.session_tracker.get_active(session_ttl).into_values().collect();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let mut merged = merge(&processes, &tracked, now, session_ttl);
+    merged.sessions.retain(|s| !state.ignore_paths.matches(&s.cwd));
+    sort_sessions(&mut merged.sessions, &state.config.general.session_sort);
+
+    for sid in &merged.stale {
+        state.session_tracker.remove(sid);
+    }
+    merged
+        .sessions
+        .into_iter()
+        .map(|s| {
+            if s.discovered {
+                // The SessionStart hook fired before agent-desk was running
+                // (or it restarted); the next hook updates CWD and status
+                state.session_tracker.register(&s.session_id, &s.cwd, None, Some(s.pid));
+            }
+            json!(s)
+        })
+        .collect()
+}
+
+/// What the island shows overall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PetState {
+    /// No agents running.
+    Sleeping,
+    /// Some session waits for the user.
+    Attention,
+    /// Some session is working.
+    Thinking,
+    /// Every other session is capped by a usage limit.
+    Limited,
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub state: PetState,
+    pub active_processes: usize,
+    pub pending_actions: usize,
+}
+
+/// Overall state of sessions given as (status, notification type). A
+/// capped session counts as neither waiting nor working.
+pub fn summarize<'a>(sessions: impl IntoIterator<Item = (&'a str, &'a str)>) -> Summary {
+    let (mut total, mut waiting, mut working, mut limited) = (0, 0, 0, 0);
+    for (status, notification_type) in sessions {
+        total += 1;
+        if notification_type == "limit_reached" {
+            limited += 1;
+            continue;
+        }
+        match status {
+            "waiting" => waiting += 1,
+            "active" => working += 1,
+            _ => {}
+        }
+    }
+
+    let state = if total == 0 {
+        PetState::Sleeping
+    } else if waiting > 0 {
+        PetState::Attention
+    } else if working > 0 {
+        PetState::Thinking
+    } else if limited > 0 {
+        PetState::Limited
+    } else {
+        PetState::Done
+    };
+    Summary { state, active_processes: total, pending_actions: waiting }
+}
+
+/// `summarize` over `scan_and_merge` entries.
+pub fn compute_state<'a>(processes: &'a [Value]) -> Value {
+    let field = |p: &'a Value, key: &str| p.get(key).and_then(|v| v.as_str()).unwrap_or("");
+    json!(summarize(processes.iter().map(|p| (field(p, "status"), field(p, "notification_type")))))
+}
+
+/// What the island pill shows, lowest precedence first: the highest state
+/// that applies wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PillState {
+    /// No sessions.
+    Sleeping,
+    /// Sessions, none working or waiting.
+    Idle,
+    /// Some session is working.
+    Working,
+    /// Some session waits for the user.
+    Waiting,
+    /// A permission request is pending.
+    Permission,
+    /// A session is stopped at a usage limit, or an agent's hooks are
+    /// silent.
+    Error,
+}
+
+impl PillState {
+    /// Whether the pill rests at `island.pill_width_active`.
+    pub fn wide(self) -> bool {
+        self > Self::Idle
+    }
+}
+
+/// The pill's state over `scan_and_merge` entries, the number of pending
+/// permission requests and of agents with silent hooks (see
+/// `HookHealth::silent`).
+pub fn pill_state(processes: &[Value], pending: usize, silent_hooks: usize) -> PillState {
+    let field = |p: &Value, key: &str| p.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let mut state = if processes.is_empty() { PillState::Sleeping } else { PillState::Idle };
+    for p in processes {
+        let s = if field(p, "notification_type") == "limit_reached" {
+            PillState::Error
+        } else {
+            match field(p, "status").as_str() {
+                "waiting" => PillState::Waiting,
+                "active" => PillState::Working,
+                _ => PillState::Idle,
+            }
+        };
+        state = state.max(s);
+    }
+    if pending > 0 {
+        state = state.max(PillState::Permission);
+    }
+    if silent_hooks > 0 {
+        state = PillState::Error;
+    }
+    state
+}
+
+/// Longest pill text; lower-precedence parts are dropped to fit.
+const PILL_MAX_CHARS: usize = 48;
+
+/// One-line island pill text over `scan_and_merge` entries and the pending
+/// permission requests as (session id, cwd), in `get_pending` order:
+/// "2 working · 1 waiting · repo-x needs approval". Sessions are counted as
+/// `summarize` counts them, a waiting session with a request counting as
+/// needing approval rather than waiting. When the text is too long, parts
+/// go in order: usage limits, then working, then waiting; approvals always
+/// stay.
+pub fn pill_text(processes: &[Value], pending: &[(&str, &str)]) -> String {
+    let field = |p: &Value, key: &str| p.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let (mut waiting, mut working, mut limited) = (0, 0, 0);
+    for p in processes {
+        if field(p, "notification_type") == "limit_reached" {
+            limited += 1;
+            continue;
+        }
+        let sid = field(p, "session_id");
+        match field(p, "status").as_str() {
+            "waiting" if !pending.iter().any(|(s, _)| *s == sid) => waiting += 1,
+            "active" => working += 1,
+            _ => {}
+        }
+    }
+
+    let approval = match pending {
+        [] => None,
+        [(_, cwd)] => Some(format!("{} needs approval", crate::tray::project_name(cwd.trim_end_matches(['/', '\\'])))),
+        _ => Some(format!("{} need approval", pending.len())),
+    };
+    // Display order, each with its precedence (higher stays longer)
+    let mut parts: Vec<(u8, String)> = Vec::new();
+    if working > 0 {
+        parts.push((1, format!("{} working", working)));
+    }
+    if waiting > 0 {
+        parts.push((2, format!("{} waiting", waiting)));
+    }
+    if limited > 0 {
+        parts.push((0, format!("{} at usage limit", limited)));
+    }
+    if let Some(a) = approval {
+        parts.push((3, a));
+    }
+    if parts.is_empty() {
+        return if processes.is_empty() { "No sessions".into() } else { "All done".into() };
+    }
+
+    let joined = |parts: &[(u8, String)]| parts.iter().map(|(_, t)| t.as_str()).collect::<Vec<_>>().join(" \u{00b7} ");
+    let mut text = joined(&parts);
+    while parts.len() > 1 && text.chars().count() > PILL_MAX_CHARS {
+        let lowest = parts.iter().enumerate().min_by_key(|(_, (p, _))| *p).map_or(0, |(i, _)| i);
+        parts.remove(lowest);
+        text = joined(&parts);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: f64 = 1_000_000.0;
+    const TTL: u64 = 600;
+
+    fn process(pid: u32, agent_type: &str, cwd: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: format!("{}.exe", agent_type),
+            agent_type: agent_type.to_string(),
+            cwd: cwd.to_string(),
+            uptime: 60,
+            create_time: NOW - 60.0,
+        }
+    }
+
+    fn session(id: &str, cwd: &str, age: f64, extra: Value) -> SessionInfo {
+        let mut v = json!({ "session_id": id, "cwd": cwd, "status": "active", "updated_at": NOW - age });
+        if let (Some(obj), Some(extra)) = (v.as_object_mut(), extra.as_object()) {
+            obj.extend(extra.clone());
+        }
+        serde_json::from_value(v).unwrap()
+    }
+
+    fn ids(merged: &Merged) -> Vec<&str> {
+        merged.sessions.iter().map(|s| s.session_id.as_str()).collect()
+    }
+
+    #[test]
+    fn cwd_matches_across_separators_case_and_trailing_slash() {
+        let procs = [process(1, "claude_code", r"C:\Work\App\")];
+        let sessions = [session("s1", "c:/work/app", 5.0, json!({}))];
+        let merged = merge(&procs, &sessions, NOW, TTL);
+        assert_eq!(ids(&merged), ["s1"]);
+        assert_eq!(merged.sessions[0].cwd, "c:/work/app");
+        assert_eq!(merged.sessions[0].status, DisplayStatus::Active);
+    }
+
+    #[test]
+    fn same_cwd_takes_most_recent_session() {
+        let procs = [process(1, "claude_code", "/repo")];
+        let sessions = [session("old", "/repo", 50.0, json!({})), session("new", "/repo", 5.0, json!({}))];
+        assert_eq!(ids(&merge(&procs, &sessions, NOW, TTL)), ["new"]);
+    }
+
+    #[test]
+    fn reported_pid_beats_cwd() {
+        let procs = [process(1, "claude_code", "/repo"), process(2, "claude_code", "/repo")];
+        let sessions = [
+            session("a", "/repo", 5.0, json!({ "agent_pid": 2 })),
+            session("b", "/repo", 50.0, json!({ "agent_pid": 1 })),
+        ];
+        assert_eq!(ids(&merge(&procs, &sessions, NOW, TTL)), ["b", "a"]);
+    }
+
+    #[test]
+    fn fallback_respects_agent_type() {
+        let procs = [process(1, "codex", "/elsewhere")];
+        let sessions = [
+            session("claude", "/repo", 5.0, json!({ "agent_type": "claude_code" })),
+            session("codex", "/other", 50.0, json!({ "agent_type": "codex" })),
+        ];
+        assert_eq!(ids(&merge(&procs, &sessions, NOW, TTL)), ["codex"]);
+
+        let sessions = [session("claude", "/repo", 5.0, json!({ "agent_type": "claude_code" }))];
+        let merged = merge(&procs, &sessions, NOW, TTL);
+        assert_eq!(ids(&merged), ["discovered-1"]);
+        assert!(merged.sessions[0].discovered);
+    }
+
+    #[test]
+    fn fallback_leaves_sessions_of_other_live_processes() {
+        // PID 2 moved on to a new session; its old one isn't handed to PID 1
+        let procs = [process(1, "claude_code", "/a"), process(2, "claude_code", "/b")];
+        let sessions = [
+            session("new", "/b", 5.0, json!({ "agent_pid": 2 })),
+            session("old", "/x", 50.0, json!({ "agent_pid": 2 })),
+        ];
+        assert_eq!(ids(&merge(&procs, &sessions, NOW, TTL)), ["discovered-1", "new"]);
+    }
+
+    #[test]
+    fn sub_agents_never_take_the_process() {
+        let procs = [process(1, "claude_code", "/repo")];
+        let sessions = [
+            session("parent", "/repo", 30.0, json!({})),
+            session("child", "/repo", 1.0, json!({ "parent_session_id": "parent" })),
+        ];
+        assert_eq!(ids(&merge(&procs, &sessions, NOW, TTL)), ["parent"]);
+
+        let sessions = [session("child", "/repo", 1.0, json!({ "parent_session_id": "parent" }))];
+        assert_eq!(ids(&merge(&procs, &sessions, NOW, TTL)), ["discovered-1"]);
+    }
+
+    #[test]
+    fn ended_sessions_never_pair() {
+        let procs = [process(1, "claude_code", "/repo")];
+        let sessions = [session("s1", "/repo", 5.0, json!({ "status": "ended" }))];
+        assert_eq!(ids(&merge(&procs, &sessions, NOW, TTL)), ["discovered-1"]);
+    }
+
+    #[test]
+    fn ttl_boundary_is_inclusive() {
+        let procs = [process(1, "claude_code", "/repo")];
+        let at_edge = [session("s1", "/repo", TTL as f64, json!({}))];
+        assert_eq!(ids(&merge(&procs, &at_edge, NOW, TTL)), ["s1"]);
+        let expired = [session("s1", "/repo", TTL as f64 + 0.5, json!({}))];
+        assert_eq!(ids(&merge(&procs, &expired, NOW, TTL)), ["discovered-1"]);
+    }
+
+    #[test]
+    fn ttl_zero_keeps_only_sessions_updated_now() {
+        let procs = [process(1, "claude_code", "/repo")];
+        let sessions = [session("s1", "/repo", 0.0, json!({})), session("s2", "/repo", 1.0, json!({}))];
+        assert_eq!(ids(&merge(&procs, &sessions, NOW, 0)), ["s1"]);
+    }
+
+    #[test]
+    fn discovered_sessions_follow_their_process() {
+        let procs = [process(7, "claude_code", "/repo")];
+        let sessions = [
+            session("discovered-7", "/repo", 5.0, json!({ "agent_pid": 7, "status": "idle" })),
+            session("discovered-8", "/repo", 5.0, json!({ "agent_pid": 8 })),
+        ];
+        let merged = merge(&procs, &sessions, NOW, TTL);
+        assert_eq!(ids(&merged), ["discovered-7"]);
+        assert!(!merged.sessions[0].discovered);
+        assert_eq!(merged.sessions[0].status, DisplayStatus::Waiting);
+        assert_eq!(merged.stale, ["discovered-8"]);
+    }
+
+    #[test]
+    fn display_message_caps_long_text() {
+        assert_eq!(display_message("short"), "short");
+        let long = "\u{00e9}".repeat(DISPLAY_MESSAGE_CHARS + 10);
+        assert_eq!(display_message(&long).chars().count(), DISPLAY_MESSAGE_CHARS + 3);
+    }
+
+    #[test]
+    fn sort_is_pinned_then_status_then_project_then_start() {
+        let keys: Vec<String> = ["pinned", "status", "project", "started"].map(String::from).to_vec();
+        let procs = [
+            process(1, "claude_code", "/b/zeta"),
+            process(2, "claude_code", "/a/alpha"),
+            process(3, "claude_code", "/c/Alpha"),
+            process(4, "claude_code", "/d/beta"),
+            process(5, "claude_code", "/e/gamma"),
+        ];
+        let sessions = [
+            session("zeta", "/b/zeta", 1.0, json!({ "status": "waiting", "started_at": 10.0 })),
+            session("alpha-late", "/a/alpha", 1.0, json!({ "started_at": 30.0 })),
+            session("alpha-early", "/c/Alpha", 1.0, json!({ "started_at": 20.0 })),
+            session("beta", "/d/beta", 1.0, json!({ "status": "stopped", "pinned": true })),
+            session("gamma", "/e/gamma", 1.0, json!({ "notification_type": "limit_reached" })),
+        ];
+        let mut merged = merge(&procs, &sessions, NOW, TTL);
+        sort_sessions(&mut merged.sessions, &keys);
+        assert_eq!(ids(&merged), ["beta", "zeta", "alpha-early", "alpha-late", "gamma"]);
+
+        // Same result whatever order the scan found them in
+        let mut reversed = merged.sessions.clone();
+        reversed.reverse();
+        sort_sessions(&mut reversed, &keys);
+        let again: Vec<&str> = reversed.iter().map(|s| s.session_id.as_str()).collect();
+        assert_eq!(again, ["beta", "zeta", "alpha-early", "alpha-late", "gamma"]);
+
+        sort_sessions(&mut merged.sessions, &[]);
+        assert_eq!(ids(&merged), ["beta", "zeta", "alpha-early", "alpha-late", "gamma"]);
+    }
+
+    #[test]
+    fn summarize_prefers_attention_then_thinking() {
+        assert_eq!(summarize([("", ""); 0]).state, PetState::Sleeping);
+        let s = summarize([("active", ""), ("waiting", "")]);
+        assert_eq!((s.state, s.active_processes, s.pending_actions), (PetState::Attention, 2, 1));
+        assert_eq!(summarize([("active", ""), ("stopped", "")]).state, PetState::Thinking);
+        assert_eq!(summarize([("stopped", "")]).state, PetState::Done);
+    }
+
+    #[test]
+    fn pill_text_counts_and_names_the_approval() {
+        let procs = [
+            json!({ "session_id": "a", "status": "active" }),
+            json!({ "session_id": "b", "status": "active" }),
+            json!({ "session_id": "c", "status": "waiting" }),
+            json!({ "session_id": "d", "status": "waiting" }),
+        ];
+        assert_eq!(pill_text(&procs, &[("d", "D:\\work\\repo-x\\")]), "2 working \u{00b7} 1 waiting \u{00b7} repo-x needs approval");
+        assert_eq!(pill_text(&procs, &[("c", "/a"), ("d", "/b")]), "2 working \u{00b7} 2 need approval");
+        assert_eq!(pill_text(&[json!({ "status": "stopped" })], &[]), "All done");
+        assert_eq!(pill_text(&[], &[]), "No sessions");
+    }
+
+    #[test]
+    fn pill_state_follows_precedence() {
+        let session = |status: &str| json!({ "status": status });
+        let limited = json!({ "status": "waiting", "notification_type": "limit_reached" });
+        assert_eq!(pill_state(&[], 0, 0), PillState::Sleeping);
+        assert_eq!(pill_state(&[session("stopped")], 0, 0), PillState::Idle);
+        assert_eq!(pill_state(&[session("stopped"), session("active")], 0, 0), PillState::Working);
+        assert_eq!(pill_state(&[session("active"), session("waiting")], 0, 0), PillState::Waiting);
+        assert_eq!(pill_state(&[session("active"), session("waiting")], 1, 0), PillState::Permission);
+        assert_eq!(pill_state(&[session("active"), limited], 1, 0), PillState::Error);
+        assert_eq!(pill_state(&[session("stopped")], 0, 1), PillState::Error);
+        assert_eq!(pill_state(&[], 1, 0), PillState::Permission);
+    }
+
+    #[test]
+    fn pill_is_wide_from_working_up() {
+        assert!(!PillState::Sleeping.wide());
+        assert!(!PillState::Idle.wide());
+        assert!(PillState::Working.wide());
+        assert!(PillState::Error.wide());
+    }
+
+    #[test]
+    fn pill_text_drops_low_precedence_parts_to_fit() {
+        let procs = [
+            json!({ "session_id": "a", "status": "active" }),
+            json!({ "session_id": "b", "status": "waiting" }),
+            json!({ "session_id": "c", "status": "waiting", "notification_type": "limit_reached" }),
+        ];
+        let text = pill_text(&procs, &[("d", "/work/long-project-name")]);
+        assert_eq!(text, "1 waiting \u{00b7} long-project-name needs approval");
+    }
+
+    #[test]
+    fn limited_sessions_count_as_neither_waiting_nor_working() {
+        let s = summarize([("waiting", "limit_reached")]);
+        assert_eq!((s.state, s.pending_actions), (PetState::Limited, 0));
+        assert_eq!(summarize([("waiting", "limit_reached"), ("stopped", "")]).state, PetState::Limited);
+    }
+}
@@ -0,0 +1,122 @@
+//! Read-only status page (`/status`) for a second screen or a teammate.
+//!
+//! Served without the LAN token when `manager.status_page` is on, so it
+//! only shows aggregate state: how many agents are working, waiting or
+//! capped. Project names appear only with `manager.status_page_projects`;
+//! CWDs, messages and transcripts never do.
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::server::AppState;
+use crate::status::{compute_state, scan_and_merge};
+
+/// Seconds between automatic reloads of the page.
+const REFRESH_SECS: u32 = 10;
+
+/// GET /status — 404 unless enabled, so the route doesn't advertise itself.
+pub async fn status_page(State(state): State<Arc<AppState>>) -> Response {
+    let cfg = &state.config.manager;
+    if !cfg.status_page {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let processes = scan_and_merge(&state);
+    let html = render(&processes, cfg.status_page_projects);
+    ([(header::CACHE_CONTROL, "no-store")], Html(html)).into_response()
+}
+
+/// Overall state → (label, colour).
+fn headline(state: &str) -> (&'static str, &'static str) {
+    match state {
+        "attention" => ("Waiting for input", "#6699FF"),
+        "thinking" => ("Working", "#D97857"),
+        "limited" => ("Usage limit reached", "#FFB300"),
+        "done" => ("Idle", "#66BF73"),
+        _ => ("No agents running", "#888888"),
+    }
+}
+
+/// Coarse status of one merged process entry; also used by team reports.
+pub(crate) fn status_label(proc: &Value) -> &'static str {
+    if proc.get("notification_type").and_then(|v| v.as_str()) == Some("limit_reached") {
+        return "limited";
+    }
+    match proc.get("status").and_then(|v| v.as_str()) {
+        Some("active") => "working",
+        Some("waiting") => "waiting",
+        Some("stopped") => "done",
+        _ => "idle",
+    }
+}
+
+fn render(processes: &[Value], show_projects: bool) -> String {
+    let summary = compute_state(processes);
+    let state = summary.get("state").and_then(|v| v.as_str()).unwrap_or("sleeping");
+    let (title, color) = headline(state);
+
+    let mut counts = [("working", 0), ("waiting", 0), ("limited", 0), ("done", 0), ("idle", 0)];
+    for proc in processes {
+        let label = status_label(proc);
+        if let Some(c) = counts.iter_mut().find(|(l, _)| *l == label) {
+            c.1 += 1;
+        }
+    }
+    let count_rows: String = counts
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(l, n)| format!("<li><b>{}</b> {}</li>", n, l))
+        .collect();
+
+    let project_rows = if show_projects {
+        let rows: String = processes
+            .iter()
+            .map(|p| {
+                let cwd = p.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+                let name = crate::redact::text(crate::tray::project_name(cwd));
+                let name = if name.is_empty() { "?" } else { &*name };
+                format!("<tr><td>{}</td><td>{}</td></tr>", escape(name), status_label(p))
+            })
+            .collect();
+        if rows.is_empty() { String::new() } else { format!("<table>{}</table>", rows) }
+    } else {
+        String::new()
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta http-equiv="refresh" content="{refresh}">
+<title>Agent Desk — {title}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; background: #111; color: #eee; margin: 0; padding: 2em; }}
+h1 {{ color: {color}; margin: 0 0 .5em; }}
+ul {{ list-style: none; padding: 0; }}
+li {{ margin: .2em 0; }}
+table {{ border-collapse: collapse; margin-top: 1em; }}
+td {{ padding: .2em 1em .2em 0; }}
+footer {{ color: #777; font-size: .8em; margin-top: 2em; }}
+</style></head>
+<body>
+<h1>{title}</h1>
+<ul>{counts}</ul>
+{projects}
+<footer>{n} agent(s) · refreshes every {refresh}s</footer>
+</body></html>"#,
+        refresh = REFRESH_SECS,
+        title = title,
+        color = color,
+        counts = count_rows,
+        projects = project_rows,
+        n = processes.len(),
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
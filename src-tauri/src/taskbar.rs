@@ -0,0 +1,99 @@
+//! Taskbar badge and flash for the island window.
+//!
+//! With `island.taskbar_badge` on, the island keeps a taskbar button whose
+//! overlay badge counts what needs you: pending permission requests plus
+//! sessions waiting on input. With `island.taskbar_flash`, a permission
+//! request that arrives while the island is hidden flashes that button.
+//! Windows draws the badge as an overlay icon; elsewhere the platform's own
+//! badge count is used.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use tauri::WebviewWindow;
+
+const BADGE_SIZE: u32 = 16;
+
+/// Count currently shown, so the tray refresh only touches the window on
+/// change (-1 = never set).
+static SHOWN: AtomicI64 = AtomicI64::new(-1);
+
+/// 3×5 glyphs for 0-9 and '+', one row per byte, high bit on the left.
+const GLYPHS: [[u8; 5]; 11] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b000, 0b010, 0b111, 0b010, 0b000],
+];
+
+/// Show `count` on the island's taskbar button; 0 clears it.
+pub fn set_badge(window: &WebviewWindow, count: usize) {
+    let count = count as i64;
+    if SHOWN.swap(count, Ordering::Relaxed) == count {
+        return;
+    }
+    #[cfg(windows)]
+    {
+        let icon = (count > 0).then(|| badge_icon(count as usize));
+        if let Err(e) = window.set_overlay_icon(icon) {
+            tracing::debug!("Failed to set taskbar overlay: {}", e);
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        if let Err(e) = window.set_badge_count((count > 0).then_some(count)) {
+            tracing::debug!("Failed to set badge count: {}", e);
+        }
+    }
+}
+
+/// Flash the island's taskbar button until it's focused.
+pub fn flash(window: &WebviewWindow) {
+    if let Err(e) = window.request_user_attention(Some(tauri::UserAttentionType::Informational)) {
+        tracing::debug!("Failed to flash taskbar: {}", e);
+    }
+}
+
+/// Red disc with the count in white ("9+" past nine).
+#[cfg_attr(not(windows), allow(dead_code))]
+fn badge_icon(count: usize) -> tauri::image::Image<'static> {
+    let glyphs: Vec<usize> = if count > 9 { vec![9, 10] } else { vec![count] };
+    let mut rgba = vec![0u8; (BADGE_SIZE * BADGE_SIZE * 4) as usize];
+    let mid = (BADGE_SIZE as f32 - 1.0) / 2.0;
+    for y in 0..BADGE_SIZE {
+        for x in 0..BADGE_SIZE {
+            let d = ((x as f32 - mid).powi(2) + (y as f32 - mid).powi(2)).sqrt();
+            let alpha = (mid + 0.5 - d).clamp(0.0, 1.0);
+            let i = ((y * BADGE_SIZE + x) * 4) as usize;
+            rgba[i..i + 4].copy_from_slice(&[0xE8, 0x3B, 0x3B, (alpha * 255.0) as u8]);
+        }
+    }
+    // One glyph at 2× scale (6×10), two at 1× with a gap (7×5)
+    let scale = if glyphs.len() == 1 { 2 } else { 1 };
+    let width = glyphs.len() as u32 * 4 * scale - scale;
+    let x0 = (BADGE_SIZE - width) / 2;
+    let y0 = (BADGE_SIZE - 5 * scale) / 2;
+    for (n, &g) in glyphs.iter().enumerate() {
+        for (row, bits) in GLYPHS[g].iter().enumerate() {
+            for col in 0..3u32 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = x0 + (n as u32 * 4 + col) * scale + dx;
+                        let y = y0 + row as u32 * scale + dy;
+                        let i = ((y * BADGE_SIZE + x) * 4) as usize;
+                        rgba[i..i + 4].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+                    }
+                }
+            }
+        }
+    }
+    tauri::image::Image::new_owned(rgba, BADGE_SIZE, BADGE_SIZE)
+}
@@ -0,0 +1,173 @@
+//! Team mode: share anonymized agent states with teammates.
+//!
+//! Each instance with `team.sink_url` set posts a small report — its
+//! overall state and one entry per agent (status and agent type, hashed
+//! session id, project name only with `team.share_projects`) — to a
+//! shared sink: another agent-desk's `/api/team/report` or any webhook.
+//! An instance with team mode on keeps the latest report from each member
+//! and the island lists them in the team tab.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::TeamConfig;
+use crate::server::AppState;
+use crate::status::{compute_state, scan_and_merge};
+
+/// How often the forwarder looks for changes to send.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// One member's agents as seen by teammates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamReport {
+    pub member: String,
+    /// Overall state, as in `/api/status` (`thinking`, `attention`, …).
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub agents: Vec<TeamAgent>,
+    /// Receiver's clock when the report arrived (the sender's is ignored).
+    #[serde(default, skip_deserializing)]
+    pub received_at: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TeamAgent {
+    /// Hash of the session id: stable across reports, reveals nothing.
+    pub id: String,
+    /// `working` | `waiting` | `limited` | `done` | `idle`.
+    pub status: String,
+    #[serde(default)]
+    pub agent_type: String,
+    /// Project name (last CWD component); empty unless the sender shares it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub project: String,
+}
+
+/// Latest report per teammate.
+pub struct TeamStore {
+    members: RwLock<HashMap<String, TeamReport>>,
+}
+
+impl TeamStore {
+    pub fn new() -> Self {
+        Self { members: RwLock::new(HashMap::new()) }
+    }
+
+    /// Keep `report` as the member's current state.
+    pub fn record(&self, mut report: TeamReport) {
+        report.received_at = now_secs();
+        write_lock!(self.members).insert(report.member.clone(), report);
+    }
+
+    /// Members heard from within `stale_secs`, by name. Older ones are dropped.
+    pub fn members(&self, stale_secs: u64) -> Vec<TeamReport> {
+        let cutoff = now_secs() - stale_secs as f64;
+        let mut members = write_lock!(self.members);
+        members.retain(|_, r| r.received_at >= cutoff);
+        let mut list: Vec<TeamReport> = members.values().cloned().collect();
+        list.sort_by(|a, b| a.member.cmp(&b.member));
+        list
+    }
+}
+
+/// Name this instance reports under: `team.name`, else the host name.
+pub fn member_name(cfg: &TeamConfig) -> String {
+    if !cfg.name.is_empty() {
+        return cfg.name.clone();
+    }
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "agent-desk".to_string())
+}
+
+/// This instance's report, built from the merged process list.
+pub fn build_report(member: &str, processes: &[Value], share_projects: bool) -> TeamReport {
+    let state = compute_state(processes)
+        .get("state")
+        .and_then(|v| v.as_str())
+        .unwrap_or("sleeping")
+        .to_string();
+    let agents = processes
+        .iter()
+        .map(|p| {
+            let sid = p.get("session_id").and_then(|v| v.as_str()).unwrap_or("");
+            let key = if sid.is_empty() {
+                p.get("pid").map(|v| v.to_string()).unwrap_or_default()
+            } else {
+                sid.to_string()
+            };
+            let cwd = p.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+            TeamAgent {
+                id: anon_id(member, &key),
+                status: crate::status_page::status_label(p).to_string(),
+                agent_type: p.get("agent_type").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                project: if share_projects {
+                    crate::redact::text(crate::tray::project_name(cwd)).into_owned()
+                } else {
+                    String::new()
+                },
+            }
+        })
+        .collect();
+    TeamReport { member: member.to_string(), state, agents, received_at: 0.0 }
+}
+
+/// First 12 hex digits of SHA-256 over member and key.
+fn anon_id(member: &str, key: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(format!("{}\n{}", member, key).as_bytes());
+    digest.iter().take(6).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Post this instance's report to `team.sink_url` whenever it changes, and
+/// at least every `heartbeat_secs` so the sink knows we're still here.
+pub async fn run_forwarder(state: std::sync::Arc<AppState>) {
+    let cfg = &state.config.team;
+    let member = member_name(cfg);
+    let heartbeat = Duration::from_secs(cfg.heartbeat_secs.max(10));
+    tracing::info!("Team mode: reporting as {} to {}", member, cfg.sink_url);
+
+    let mut last_sent: Option<(String, Vec<TeamAgent>)> = None;
+    let mut last_at = Instant::now();
+    let mut failing = false;
+    loop {
+        let report = build_report(&member, &scan_and_merge(&state), cfg.share_projects);
+        let current = (report.state.clone(), report.agents.clone());
+        if last_sent.as_ref() != Some(&current) || last_at.elapsed() >= heartbeat {
+            let mut req = state
+                .http_client
+                .post(&cfg.sink_url)
+                .json(&report)
+                .timeout(Duration::from_secs(10));
+            if !cfg.token.is_empty() {
+                req = req.bearer_auth(&cfg.token);
+            }
+            match req.send().await.and_then(|r| r.error_for_status()) {
+                Ok(_) => {
+                    if failing {
+                        tracing::info!("Team sink reachable again");
+                    }
+                    failing = false;
+                    last_sent = Some(current);
+                    last_at = Instant::now();
+                }
+                Err(e) => {
+                    // Warn once per outage, then keep retrying quietly
+                    if !failing {
+                        tracing::warn!("Team report to {} failed: {}", cfg.sink_url, e);
+                    }
+                    failing = true;
+                }
+            }
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+fn now_secs() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()
+}
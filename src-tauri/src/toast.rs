@@ -0,0 +1,322 @@
+//! Desktop toasts.
+//!
+//! On Windows toasts go straight through the WinRT `ToastNotificationManager`
+//! under our own AppUserModelID. The unpackaged exe registers that ID under
+//! `HKCU\Software\Classes\AppUserModelId` at startup; an MSIX package brings
+//! its own identity and skips the registration. Every toast can carry a group
+//! (the session, so Action Center stacks them per session) and a tag (what it
+//! is about), so a newer toast about the same thing replaces the old one in
+//! place. Permission toasts count down to their timeout and are withdrawn
+//! once the request is answered. Clicking a toast, or one of its buttons,
+//! can open an `agentdesk://` link (`ToastLinks`), which the OS hands back
+//! to the running instance (see deep_link.rs).
+//!
+//! Elsewhere, or if WinRT fails, toasts go through the notification plugin.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+/// Where a toast sits in Action Center: toasts with the same group stack
+/// together, and one with the same group and tag replaces the other.
+#[derive(Debug, Clone, Copy)]
+pub struct ToastKey<'a> {
+    pub group: &'a str,
+    pub tag: &'a str,
+}
+
+/// Deep links a toast opens (Windows only): `launch` on a click on the
+/// toast itself, one button per action.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToastLinks {
+    #[serde(default)]
+    pub launch: Option<String>,
+    /// Button label and link.
+    #[serde(default)]
+    pub actions: Vec<(String, String)>,
+}
+
+impl ToastLinks {
+    /// A session's stop toast: clicking it focuses the terminal, "查看结果"
+    /// (view result) opens the session's chat in the island.
+    pub fn stop(session_id: &str) -> Self {
+        let sid = urlencoding::encode(session_id);
+        let scheme = crate::deep_link::SCHEME;
+        Self {
+            launch: Some(format!("{}://focus/{}", scheme, sid)),
+            actions: vec![("\u{67e5}\u{770b}\u{7ed3}\u{679c}".to_string(), format!("{}://chat/{}", scheme, sid))],
+        }
+    }
+}
+
+/// A countdown toast on screen, by tag.
+struct Countdown {
+    group: String,
+    /// Unix seconds.
+    deadline: f64,
+    total_secs: f64,
+}
+
+static COUNTDOWNS: Mutex<Option<HashMap<String, Countdown>>> = Mutex::new(None);
+
+/// Give the process its AppUserModelID and register it for the current user
+/// (in the background). Call before any window is created.
+pub fn init() {
+    #[cfg(windows)]
+    win::init();
+}
+
+/// Show a toast; `key` places it in Action Center and `links` makes it
+/// clickable (Windows only).
+pub fn show(handle: &AppHandle, title: &str, body: &str, key: Option<ToastKey>, links: Option<&ToastLinks>) {
+    #[cfg(windows)]
+    {
+        match win::show(&xml(title, body, false, links), key, None) {
+            Ok(()) => return,
+            Err(e) => tracing::debug!("WinRT toast failed ({}), using the notification plugin", e),
+        }
+    }
+    let _ = (key, links);
+    crate::tray::send_notification(handle, title, body);
+}
+
+/// Show a toast with a bar counting down to `deadline` (unix seconds),
+/// updated by `tick_countdowns`. Windows only: returns false when the
+/// toast can't count down, leaving the caller to fall back.
+pub fn show_countdown(title: &str, body: &str, key: ToastKey, deadline: f64, total_secs: f64) -> bool {
+    #[cfg(windows)]
+    {
+        let values = countdown_values(deadline - now_secs(), total_secs);
+        match win::show(&xml(title, body, true, None), Some(key), Some(&values)) {
+            Ok(()) => {
+                mutex_lock!(COUNTDOWNS)
+                    .get_or_insert_with(HashMap::new)
+                    .insert(key.tag.to_string(), Countdown { group: key.group.to_string(), deadline, total_secs });
+                return true;
+            }
+            Err(e) => tracing::debug!("WinRT countdown toast failed: {}", e),
+        }
+    }
+    let _ = (title, body, key, deadline, total_secs);
+    false
+}
+
+/// Move every countdown toast on; withdraw those whose tag `live` no longer
+/// accepts (request answered or expired).
+pub fn tick_countdowns(live: impl Fn(&str) -> bool) {
+    let mut guard = mutex_lock!(COUNTDOWNS);
+    let Some(countdowns) = guard.as_mut() else { return };
+    let now = now_secs();
+    countdowns.retain(|tag, c| {
+        let key = ToastKey { group: &c.group, tag };
+        if !live(tag) {
+            remove(key);
+            return false;
+        }
+        update(key, &countdown_values(c.deadline - now, c.total_secs));
+        true
+    });
+}
+
+/// Withdraw the countdown toast tagged `tag` right away.
+pub fn finish_countdown(tag: &str) {
+    let entry = mutex_lock!(COUNTDOWNS).as_mut().and_then(|c| c.remove(tag));
+    if let Some(c) = entry {
+        remove(ToastKey { group: &c.group, tag });
+    }
+}
+
+fn update(key: ToastKey, values: &[(&str, String)]) {
+    #[cfg(windows)]
+    {
+        if let Err(e) = win::update(key, values) {
+            tracing::debug!("Toast update failed: {}", e);
+        }
+    }
+    let _ = (key, values);
+}
+
+fn remove(key: ToastKey) {
+    #[cfg(windows)]
+    {
+        if let Err(e) = win::remove(key) {
+            tracing::debug!("Toast removal failed: {}", e);
+        }
+    }
+    let _ = key;
+}
+
+/// Progress bar data: share of the time left, "m:ss", and a status line
+/// (等待审批 — waiting for approval).
+fn countdown_values(remaining: f64, total: f64) -> [(&'static str, String); 3] {
+    let remaining = remaining.max(0.0);
+    let share = if total > 0.0 { (remaining / total).min(1.0) } else { 0.0 };
+    let secs = remaining.round() as u64;
+    [
+        ("progressValue", format!("{:.3}", share)),
+        ("progressValueString", format!("{}:{:02}", secs / 60, secs % 60)),
+        ("progressStatus", "\u{7b49}\u{5f85}\u{5ba1}\u{6279}".to_string()),
+    ]
+}
+
+/// ToastGeneric payload. Silent: sounds are played separately, following
+/// the sound settings. Links are protocol activations.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn xml(title: &str, body: &str, countdown: bool, links: Option<&ToastLinks>) -> String {
+    let progress = if countdown {
+        r#"<progress value="{progressValue}" valueStringOverride="{progressValueString}" status="{progressStatus}"/>"#
+    } else {
+        ""
+    };
+    let launch = match links.and_then(|l| l.launch.as_deref()) {
+        Some(url) => format!(r#" activationType="protocol" launch="{}""#, escape(url)),
+        None => String::new(),
+    };
+    let actions: String = links
+        .map(|l| l.actions.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .map(|(label, url)| {
+            format!(r#"<action content="{}" activationType="protocol" arguments="{}"/>"#, escape(label), escape(url))
+        })
+        .collect();
+    let actions = if actions.is_empty() { actions } else { format!("<actions>{}</actions>", actions) };
+    format!(
+        r#"<toast{}><visual><binding template="ToastGeneric"><text>{}</text><text>{}</text>{}</binding></visual>{}<audio silent="true"/></toast>"#,
+        launch,
+        escape(title),
+        escape(body),
+        progress,
+        actions,
+    )
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn now_secs() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+#[cfg(windows)]
+mod win {
+    use super::ToastKey;
+    use std::sync::OnceLock;
+    use windows::core::{Result, HSTRING};
+    use windows::Data::Xml::Dom::XmlDocument;
+    use windows::UI::Notifications::{NotificationData, ToastNotification, ToastNotificationManager, ToastNotifier};
+
+    /// AppUserModelID toasts are shown under (the bundle identifier).
+    const AUMID: &str = "com.agent-desk.app";
+
+    /// Whether the process runs from an MSIX package (which has its own
+    /// AppUserModelID).
+    fn packaged() -> bool {
+        static PACKAGED: OnceLock<bool> = OnceLock::new();
+        *PACKAGED.get_or_init(|| {
+            use windows::Win32::Storage::Packaging::Appx::GetCurrentPackageFullName;
+            const APPMODEL_ERROR_NO_PACKAGE: u32 = 15700;
+            let mut len = 0u32;
+            unsafe { GetCurrentPackageFullName(&mut len, None).0 != APPMODEL_ERROR_NO_PACKAGE }
+        })
+    }
+
+    pub fn init() {
+        if packaged() {
+            return;
+        }
+        unsafe {
+            use windows::Win32::UI::Shell::SetCurrentProcessExplicitAppUserModelID;
+            if let Err(e) = SetCurrentProcessExplicitAppUserModelID(&HSTRING::from(AUMID)) {
+                tracing::debug!("SetCurrentProcessExplicitAppUserModelID failed: {}", e);
+            }
+        }
+        std::thread::spawn(|| {
+            if let Err(e) = register() {
+                tracing::warn!("Toast AppUserModelID not registered: {}", e);
+            }
+        });
+    }
+
+    /// `HKCU\Software\Classes\AppUserModelId\<AUMID>`: the name and icon
+    /// Action Center shows for an unpackaged app. Idempotent.
+    fn register() -> std::result::Result<(), String> {
+        use std::os::windows::process::CommandExt;
+        let key = format!("HKCU\\Software\\Classes\\AppUserModelId\\{}", AUMID);
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        let icon = exe.with_file_name("icon.png");
+        let mut entries = vec![("DisplayName", "Agent Desk".to_string())];
+        if icon.exists() {
+            entries.push(("IconUri", icon.display().to_string()));
+        }
+        for (name, data) in entries {
+            let out = std::process::Command::new("reg")
+                .args(["add", &key, "/v", name, "/d", &data, "/f"])
+                .creation_flags(0x08000000) // CREATE_NO_WINDOW
+                .output()
+                .map_err(|e| e.to_string())?;
+            if !out.status.success() {
+                return Err(String::from_utf8_lossy(&out.stderr).trim().to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn notifier() -> Result<ToastNotifier> {
+        if packaged() {
+            ToastNotificationManager::CreateToastNotifier()
+        } else {
+            ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(AUMID))
+        }
+    }
+
+    fn data(values: &[(&str, String)]) -> Result<NotificationData> {
+        let data = NotificationData::new()?;
+        let map = data.Values()?;
+        for (k, v) in values {
+            map.Insert(&HSTRING::from(*k), &HSTRING::from(v.as_str()))?;
+        }
+        Ok(data)
+    }
+
+    pub fn show(xml: &str, key: Option<ToastKey>, values: Option<&[(&str, String)]>) -> Result<()> {
+        let doc = XmlDocument::new()?;
+        doc.LoadXml(&HSTRING::from(xml))?;
+        let toast = ToastNotification::CreateToastNotification(&doc)?;
+        if let Some(k) = key {
+            toast.SetGroup(&HSTRING::from(short(k.group)))?;
+            toast.SetTag(&HSTRING::from(short(k.tag)))?;
+        }
+        if let Some(v) = values {
+            toast.SetData(&data(v)?)?;
+        }
+        notifier()?.Show(&toast)
+    }
+
+    pub fn update(key: ToastKey, values: &[(&str, String)]) -> Result<()> {
+        notifier()?
+            .UpdateWithTagAndGroup(&data(values)?, &HSTRING::from(short(key.tag)), &HSTRING::from(short(key.group)))
+            .map(|_| ())
+    }
+
+    pub fn remove(key: ToastKey) -> Result<()> {
+        let history = ToastNotificationManager::History()?;
+        let (tag, group) = (HSTRING::from(short(key.tag)), HSTRING::from(short(key.group)));
+        if packaged() {
+            history.RemoveGroupedTag(&tag, &group)
+        } else {
+            history.RemoveGroupedTagWithId(&tag, &group, &HSTRING::from(AUMID))
+        }
+    }
+
+    /// Tags and groups are limited to 64 characters.
+    fn short(s: &str) -> &str {
+        s.char_indices().nth(64).map_or(s, |(i, _)| &s[..i])
+    }
+}
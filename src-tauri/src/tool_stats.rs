@@ -0,0 +1,167 @@
+//! Tool usage analytics.
+//!
+//! Fed by the observe-mode PreToolUse hook (`general.observe_tools`): a tool
+//! runs from its `pre_tool` until the session's next hook event (the next
+//! tool, a prompt, or the end of the turn), so its time includes waiting
+//! for approval. Counts and times are kept per project and tool since the
+//! app started; failures come from the transcripts (see
+//! `ChatReader::tool_outcomes`).
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+
+use crate::process::cwd_key;
+
+/// A "run" longer than this means the session was left alone mid-tool; it
+/// counts, but its time does not.
+const MAX_RUN_SECS: f64 = 3600.0;
+
+/// The tool a session started last and hasn't finished.
+struct Running {
+    tool: String,
+    cwd: String,
+    since: f64,
+}
+
+#[derive(Default)]
+struct Totals {
+    cwd: String,
+    count: u64,
+    /// Runs whose time is known (finished, not over `MAX_RUN_SECS`).
+    timed: u64,
+    secs: f64,
+}
+
+/// (project key, tool) → (cwd, results, errors), from the transcripts.
+pub type Outcomes = HashMap<(String, String), (String, u64, u64)>;
+
+/// One tool in one project.
+#[derive(Debug, Serialize)]
+pub struct ToolUsage {
+    pub tool: String,
+    /// Runs seen by the PreToolUse hook.
+    pub count: u64,
+    pub total_secs: f64,
+    pub avg_secs: f64,
+    /// Percent of the project's tool time.
+    pub share: f64,
+    /// Tool results in the transcripts, and how many were errors.
+    pub results: u64,
+    pub failures: u64,
+    /// Percent of `results` that failed.
+    pub failure_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectTools {
+    pub project: String,
+    pub cwd: String,
+    pub total_secs: f64,
+    /// Most time first.
+    pub tools: Vec<ToolUsage>,
+}
+
+pub struct ToolStats {
+    running: Mutex<HashMap<String, Running>>,
+    /// (project key, tool) → totals.
+    totals: RwLock<HashMap<(String, String), Totals>>,
+    started_at: f64,
+}
+
+impl ToolStats {
+    pub fn new(now: f64) -> Self {
+        Self {
+            running: Mutex::new(HashMap::new()),
+            totals: RwLock::new(HashMap::new()),
+            started_at: now,
+        }
+    }
+
+    /// Unix time counting started.
+    pub fn since(&self) -> f64 {
+        self.started_at
+    }
+
+    /// `tool` started in the session at `at`, finishing the one before it.
+    pub fn start(&self, session_id: &str, cwd: &str, tool: &str, at: f64) {
+        let prev = mutex_lock!(self.running).insert(
+            session_id.to_string(),
+            Running { tool: tool.to_string(), cwd: cwd.to_string(), since: at },
+        );
+        let mut totals = write_lock!(self.totals);
+        if let Some(prev) = prev {
+            add_time(&mut totals, &prev, at);
+        }
+        let t = totals.entry((cwd_key(cwd), tool.to_string())).or_default();
+        t.cwd = cwd.to_string();
+        t.count += 1;
+    }
+
+    /// The session moved on (prompt, end of turn): its tool finished at `at`.
+    pub fn finish(&self, session_id: &str, at: f64) {
+        let Some(run) = mutex_lock!(self.running).remove(session_id) else { return };
+        add_time(&mut write_lock!(self.totals), &run, at);
+    }
+
+    /// Usage per project, most tool time first, merged with the transcript
+    /// `outcomes`. `project` narrows it to a folder name or path.
+    pub fn snapshot(&self, outcomes: &Outcomes, project: Option<&str>) -> Vec<ProjectTools> {
+        let totals = read_lock!(self.totals);
+        let keys: std::collections::HashSet<&(String, String)> = totals.keys().chain(outcomes.keys()).collect();
+        let mut projects: HashMap<&str, ProjectTools> = HashMap::new();
+        for key in keys {
+            let t = totals.get(key);
+            let (cwd, results, failures) = match outcomes.get(key) {
+                Some((cwd, r, f)) => (t.map_or(cwd.as_str(), |t| t.cwd.as_str()), *r, *f),
+                None => (t.map_or("", |t| t.cwd.as_str()), 0, 0),
+            };
+            if project.is_some_and(|p| !crate::tray::project_name(cwd).eq_ignore_ascii_case(p) && cwd_key(p) != key.0) {
+                continue;
+            }
+            let (count, timed, secs) = t.map_or((0, 0, 0.0), |t| (t.count, t.timed, t.secs));
+            let p = projects.entry(key.0.as_str()).or_insert_with(|| ProjectTools {
+                project: crate::tray::project_name(cwd).to_string(),
+                cwd: cwd.to_string(),
+                total_secs: 0.0,
+                tools: Vec::new(),
+            });
+            p.total_secs += secs;
+            p.tools.push(ToolUsage {
+                tool: key.1.clone(),
+                count,
+                total_secs: round(secs),
+                avg_secs: if timed > 0 { round(secs / timed as f64) } else { 0.0 },
+                share: 0.0,
+                results,
+                failures,
+                failure_rate: if results > 0 { round(failures as f64 * 100.0 / results as f64) } else { 0.0 },
+            });
+        }
+        let mut projects: Vec<ProjectTools> = projects.into_values().collect();
+        for p in projects.iter_mut() {
+            for t in p.tools.iter_mut() {
+                t.share = if p.total_secs > 0.0 { round(t.total_secs * 100.0 / p.total_secs) } else { 0.0 };
+            }
+            p.tools.sort_by(|a, b| b.total_secs.total_cmp(&a.total_secs).then(b.count.cmp(&a.count)));
+            p.total_secs = round(p.total_secs);
+        }
+        projects.sort_by(|a, b| b.total_secs.total_cmp(&a.total_secs));
+        projects
+    }
+}
+
+fn add_time(totals: &mut HashMap<(String, String), Totals>, run: &Running, end: f64) {
+    let secs = end - run.since;
+    if !(0.0..=MAX_RUN_SECS).contains(&secs) {
+        return;
+    }
+    if let Some(t) = totals.get_mut(&(cwd_key(&run.cwd), run.tool.clone())) {
+        t.timed += 1;
+        t.secs += secs;
+    }
+}
+
+fn round(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, LazyLock, Mutex};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
 
 use serde_json::Value;
-use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::tray::{TrayIconBuilder, TrayIconEvent};
 use tauri::AppHandle;
 
@@ -16,23 +17,71 @@ use crate::server::AppState;
 
 const ICON_SIZE: u32 = 32;
 
-/// Pre-built RGBA circle icons for each pet state.
-static ICONS: LazyLock<HashMap<&str, Vec<u8>>> = LazyLock::new(|| {
-    let states: [(&str, u8, u8, u8); 6] = [
-        ("sleeping", 0x7f, 0x84, 0x9c),
-        ("idle", 0x89, 0xb4, 0xfa),
-        ("thinking", 0xfa, 0xb3, 0x87),
-        ("done", 0xa6, 0xe3, 0xa1),
-        ("attention", 0xf9, 0xe2, 0xaf),
-        ("error", 0xf3, 0x8b, 0xa8),
-    ];
+/// Pet states and their circle colors.
+const STATES: [(&str, u8, u8, u8); 7] = [
+    ("sleeping", 0x7f, 0x84, 0x9c),
+    ("idle", 0x89, 0xb4, 0xfa),
+    ("thinking", 0xfa, 0xb3, 0x87),
+    ("done", 0xa6, 0xe3, 0xa1),
+    ("attention", 0xf9, 0xe2, 0xaf),
+    ("error", 0xf3, 0x8b, 0xa8),
+    ("limited", 0xcb, 0xa6, 0xf7),
+];
+
+/// Taskbar color scheme, for picking icon variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskbarTheme {
+    Light,
+    Dark,
+}
+
+impl TaskbarTheme {
+    fn suffix(self) -> &'static str {
+        match self {
+            TaskbarTheme::Light => "light",
+            TaskbarTheme::Dark => "dark",
+        }
+    }
+}
+
+/// Pre-built RGBA circle icons for each pet state. Light-taskbar variants
+/// get a darker rim so pale colors don't wash out.
+static ICONS: LazyLock<HashMap<(&str, TaskbarTheme), Vec<u8>>> = LazyLock::new(|| {
     let mut map = HashMap::new();
-    for (name, r, g, b) in states {
-        map.insert(name, generate_circle_icon(r, g, b, ICON_SIZE));
+    for (name, r, g, b) in STATES {
+        map.insert((name, TaskbarTheme::Dark), generate_circle_icon(r, g, b, ICON_SIZE, 1.0, false));
+        map.insert((name, TaskbarTheme::Light), generate_circle_icon(r, g, b, ICON_SIZE, 1.0, true));
     }
     map
 });
 
+/// User-supplied icons from the tray icon directory, keyed by file stem
+/// (`thinking`, `thinking-light`, ...).
+static CUSTOM_ICONS: OnceLock<HashMap<String, tauri::image::Image<'static>>> = OnceLock::new();
+
+/// Whether the taskbar was light at the last full tray update.
+static LIGHT_TASKBAR: AtomicBool = AtomicBool::new(false);
+
+/// Pulse frames for the `thinking` tray icon (one breath in/out).
+const PULSE_FRAMES: usize = 12;
+
+/// Delay between pulse frames.
+pub const PULSE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(120);
+
+/// Pulse frames, dark-taskbar set first.
+static THINKING_FRAMES: LazyLock<[Vec<Vec<u8>>; 2]> = LazyLock::new(|| {
+    [false, true].map(|rim| {
+        (0..PULSE_FRAMES)
+            .map(|i| {
+                // 1.0 → 0.55 → 1.0 over the cycle, smoothed with a cosine
+                let phase = i as f32 / PULSE_FRAMES as f32 * std::f32::consts::TAU;
+                let scale = 0.775 + 0.225 * phase.cos();
+                generate_circle_icon(0xfa, 0xb3, 0x87, ICON_SIZE, scale, rim)
+            })
+            .collect()
+    })
+});
+
 /// Monotonic counter — ensures unique menu-item IDs across rebuilds.
 static MENU_GEN: AtomicU64 = AtomicU64::new(0);
 
@@ -40,6 +89,22 @@ static MENU_GEN: AtomicU64 = AtomicU64::new(0);
 static SESSION_MAP: LazyLock<Mutex<HashMap<String, (String, Option<u32>)>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
+/// Quick-reply mapping: menu-item ID → (session ID, reply ID).
+static REPLY_MAP: LazyLock<Mutex<HashMap<String, (String, String)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Recent-event mapping: menu-item ID → (session ID, CWD).
+static EVENT_MAP: LazyLock<Mutex<HashMap<String, (String, String)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Chat-export mapping: menu-item ID → session ID.
+static EXPORT_MAP: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Per-project clear mapping: menu-item ID → CWD.
+static CLEAR_MAP: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
 /// Last hash of tray menu content — skip rebuild if unchanged.
 static LAST_TRAY_HASH: LazyLock<Mutex<u64>> = LazyLock::new(|| Mutex::new(0));
 
@@ -47,10 +112,13 @@ static LAST_TRAY_HASH: LazyLock<Mutex<u64>> = LazyLock::new(|| Mutex::new(0));
 // Icon generation
 // ---------------------------------------------------------------------------
 
-fn generate_circle_icon(r: u8, g: u8, b: u8, size: u32) -> Vec<u8> {
+/// Circle icon whose radius is `scale` × the full-size radius; `rim` draws
+/// a darker 2px edge for contrast on light taskbars.
+fn generate_circle_icon(r: u8, g: u8, b: u8, size: u32, scale: f32, rim: bool) -> Vec<u8> {
     let mut buf = vec![0u8; (size * size * 4) as usize];
     let center = size as f32 / 2.0;
-    let radius = center - 1.0;
+    let radius = (center - 1.0) * scale;
+    let shade = |c: u8| (c as f32 * 0.55) as u8;
 
     for y in 0..size {
         for x in 0..size {
@@ -59,6 +127,7 @@ fn generate_circle_icon(r: u8, g: u8, b: u8, size: u32) -> Vec<u8> {
             let dist = (dx * dx + dy * dy).sqrt();
             let offset = ((y * size + x) * 4) as usize;
 
+            let (r, g, b) = if rim && dist > radius - 2.0 { (shade(r), shade(g), shade(b)) } else { (r, g, b) };
             if dist <= radius - 0.5 {
                 buf[offset] = r;
                 buf[offset + 1] = g;
@@ -76,6 +145,72 @@ fn generate_circle_icon(r: u8, g: u8, b: u8, size: u32) -> Vec<u8> {
     buf
 }
 
+/// Load `<state>[-light|-dark].png|.ico` from `dir`. Called once at startup;
+/// states without a file keep the generated circles.
+pub fn load_custom_icons(dir: &Path) {
+    let mut icons = HashMap::new();
+    for (state, ..) in STATES {
+        for stem in [state.to_string(), format!("{}-light", state), format!("{}-dark", state)] {
+            let Some(path) = ["png", "ico"].iter().map(|ext| dir.join(format!("{}.{}", stem, ext))).find(|p| p.is_file())
+            else {
+                continue;
+            };
+            match tauri::image::Image::from_path(&path) {
+                Ok(img) => {
+                    icons.insert(stem, img);
+                }
+                Err(e) => tracing::warn!("Tray icon {}: {}", path.display(), e),
+            }
+        }
+    }
+    if !icons.is_empty() {
+        tracing::info!("Loaded {} custom tray icons from {}", icons.len(), dir.display());
+    }
+    let _ = CUSTOM_ICONS.set(icons);
+}
+
+fn custom_icon(state: &str, theme: TaskbarTheme) -> Option<&'static tauri::image::Image<'static>> {
+    let icons = CUSTOM_ICONS.get()?;
+    icons.get(&format!("{}-{}", state, theme.suffix())).or_else(|| icons.get(state))
+}
+
+/// Icon for `state`: the user's themed file, their plain file, then the
+/// generated circle.
+fn state_icon(state: &str, theme: TaskbarTheme) -> Option<tauri::image::Image<'static>> {
+    if let Some(img) = custom_icon(state, theme) {
+        return Some(tauri::image::Image::new(img.rgba(), img.width(), img.height()));
+    }
+    let (name, ..) = STATES.iter().find(|(s, ..)| *s == state)?;
+    ICONS.get(&(*name, theme)).map(|rgba| tauri::image::Image::new(rgba, ICON_SIZE, ICON_SIZE))
+}
+
+/// Current taskbar color scheme (Windows `SystemUsesLightTheme`; dark elsewhere).
+#[cfg(windows)]
+pub fn taskbar_theme() -> TaskbarTheme {
+    use windows::core::w;
+    use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+
+    let mut value: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    let err = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+            w!("SystemUsesLightTheme"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut _),
+            Some(&mut size),
+        )
+    };
+    if err.is_ok() && value == 1 { TaskbarTheme::Light } else { TaskbarTheme::Dark }
+}
+
+#[cfg(not(windows))]
+pub fn taskbar_theme() -> TaskbarTheme {
+    TaskbarTheme::Dark
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -88,6 +223,7 @@ fn state_label(state: &str) -> &'static str {
         "done"     => "\u{5e72}\u{5b8c}\u{5566}\u{ff01}",
         "attention"=> "\u{9700}\u{8981}\u{4f60}\u{ff01}",
         "error"    => "\u{51fa}\u{9519}\u{4e86}\u{ff01}",
+        "limited"  => "\u{989d}\u{5ea6}\u{7528}\u{5b8c}\u{4e86}",
         _          => "???",
     }
 }
@@ -100,6 +236,7 @@ fn state_emoji(state: &str) -> &'static str {
         "done"     => "\u{2705}",   // ✅
         "attention"=> "\u{1f514}",  // 🔔
         "error"    => "\u{274c}",   // ❌
+        "limited"  => "\u{23f3}",   // ⏳
         _          => "\u{1f63e}",  // 😾
     }
 }
@@ -113,7 +250,7 @@ fn status_text(status: &str) -> &'static str {
     }
 }
 
-fn project_name(cwd: &str) -> &str {
+pub(crate) fn project_name(cwd: &str) -> &str {
     cwd.rsplit(['/', '\\']).next().unwrap_or(cwd)
 }
 
@@ -138,11 +275,19 @@ pub fn setup_tray(
     let quit = MenuItem::with_id(app, format!("quit_{}", seq), "\u{274c} \u{9000}\u{51fa}", true, None::<&str>)?;
     let menu = Menu::with_items(app, &[&header, &sep, &quit])?;
 
-    let initial_icon = ICONS.get("sleeping").unwrap();
-    let icon = tauri::image::Image::new(initial_icon, ICON_SIZE, ICON_SIZE);
+    let icon_dir = match state.config.island.tray_icon_dir.trim() {
+        "" => crate::config::find_config_path()
+            .parent()
+            .map(|d| d.join("tray-icons"))
+            .unwrap_or_else(|| "tray-icons".into()),
+        dir => dir.into(),
+    };
+    load_custom_icons(&icon_dir);
+    let icon = state_icon("sleeping", taskbar_theme()).unwrap();
 
     let panel_w = state.config.island.panel_width;
     let panel_h = state.config.island.panel_height;
+    let click_state = state.clone();
 
     let _tray = TrayIconBuilder::with_id("main")
         .icon(icon)
@@ -171,10 +316,10 @@ pub fn setup_tray(
                 let app = tray.app_handle();
                 if let Some(w) = app.get_webview_window("island") {
                     let _ = w.show();
-                    let _ = w.eval("if(window.onExpand)window.onExpand()");
-                    std::thread::spawn(move || {
-                        crate::island::expand(&w, panel_w, panel_h);
-                    });
+                    crate::island::expand(panel_w, panel_h);
+                    // Always tell the webview — it may have drifted from the server state
+                    click_state.island_state.expand();
+                    crate::server::broadcast_island_state(&click_state);
                 }
             }
         })
@@ -184,18 +329,59 @@ pub fn setup_tray(
             // Session click → focus terminal
             if let Some((cwd, pid)) = SESSION_MAP.lock().unwrap().get(id).cloned() {
                 let cached = state.registry.get_cached();
-                focus::find_and_focus_terminal_with_pid(&cwd, &cached, pid);
+                focus::find_and_focus_terminal_with_pid("", &cwd, &cached, pid);
+            } else if let Some((sid, reply_id)) = REPLY_MAP.lock().unwrap().get(id).cloned() {
+                // Typing into the terminal blocks; keep the menu responsive
+                let (app, state) = (app.clone(), state.clone());
+                std::thread::spawn(move || {
+                    let reply = crate::action::Action::SendReply { session_id: sid, reply_id };
+                    if let Err(e) = crate::action::run(&state, &reply, "tray") {
+                        let msg = e.get("error").and_then(|v| v.as_str()).unwrap_or("failed").to_string();
+                        send_notification(&app, "\u{26a1} \u{5feb}\u{6377}\u{56de}\u{590d}\u{5931}\u{8d25}", &msg);
+                    }
+                });
+            } else if let Some((sid, cwd)) = EVENT_MAP.lock().unwrap().get(id).cloned() {
+                // Scanning for the terminal blocks; keep the menu responsive
+                let state = state.clone();
+                std::thread::spawn(move || open_event_session(&state, sid, cwd));
+            } else if let Some(sid) = EXPORT_MAP.lock().unwrap().get(id).cloned() {
+                // Reading a long transcript takes a moment
+                let (app, state) = (app.clone(), state.clone());
+                std::thread::spawn(move || {
+                    let export = crate::action::Action::ExportChat { session_id: sid };
+                    match crate::action::run(&state, &export, "tray") {
+                        Ok(v) => {
+                            let path = v.get("path").and_then(|v| v.as_str()).unwrap_or("");
+                            send_notification(&app, "\u{1f4e4} \u{5bf9}\u{8bdd}\u{5df2}\u{5bfc}\u{51fa}", path);
+                        }
+                        Err(e) => {
+                            let msg = e.get("error").and_then(|v| v.as_str()).unwrap_or("failed").to_string();
+                            send_notification(&app, "\u{1f4e4} \u{5bfc}\u{51fa}\u{5bf9}\u{8bdd}\u{5931}\u{8d25}", &msg);
+                        }
+                    }
+                });
             } else if id.starts_with("show_") {
                 use tauri::Manager;
                 if let Some(w) = app.get_webview_window("island") {
                     let _ = w.show();
                 }
+            } else if id.starts_with("dashopen_") {
+                crate::dashboard::toggle(app, state.config.manager.port);
+            } else if id.starts_with("widget_") {
+                crate::widget::toggle(app, state.config.manager.port, &state.config.widget);
+            } else if let Some(cwd) = CLEAR_MAP.lock().unwrap().get(id).cloned() {
+                let filter = crate::events::ClearFilter { cwd: Some(cwd), ..Default::default() };
+                state.event_store.clear_matching(&filter);
+                state.sse.broadcast("clear", serde_json::json!({ "cwd": filter.cwd }));
+                let _ = state.notify_tray.send(());
             } else if id.starts_with("clear_") {
                 state.event_store.clear_all();
                 state.sse.broadcast("clear", serde_json::json!({}));
                 let _ = state.notify_tray.send(());
             } else if id.starts_with("quit_") {
                 app.exit(0);
+            } else if let Some((action, _)) = id.split_once('_').filter(|(a, _)| MAINTENANCE.contains(a)) {
+                run_maintenance(app, &state, action);
             }
         })
         .build(app)?;
@@ -203,10 +389,171 @@ pub fn setup_tray(
     Ok(())
 }
 
+// ---------------------------------------------------------------------------
+// Maintenance submenu
+// ---------------------------------------------------------------------------
+
+/// Menu-item ID prefixes of the maintenance submenu.
+const MAINTENANCE: [&str; 4] = ["cfgopen", "logopen", "daemonstat", "repair"];
+
+/// A recent event was clicked: focus its session's terminal, or failing
+/// that open the session's chat in the island.
+fn open_event_session(state: &Arc<AppState>, session_id: String, cwd: String) {
+    use crate::action::Action;
+    let focus = Action::FocusSession { session_id: session_id.clone(), cwd, pid: None, peek: false };
+    if crate::action::run(state, &focus, "tray").is_ok() || session_id.is_empty() {
+        return;
+    }
+    let _ = crate::action::run(state, &Action::OpenChat { session_id }, "tray");
+}
+
+fn run_maintenance(app: &AppHandle, state: &Arc<AppState>, action: &str) {
+    match action {
+        "cfgopen" => {
+            let path = crate::config::find_config_path();
+            if let Err(e) = open::that(&path) {
+                tracing::warn!("Failed to open {}: {}", path.display(), e);
+            }
+        }
+        "logopen" => {
+            let dir = crate::config::log_dir();
+            if let Err(e) = open::that(&dir) {
+                tracing::warn!("Failed to open {}: {}", dir.display(), e);
+            }
+        }
+        // Both talk to the daemon / touch settings.json — keep them off the
+        // event loop and report back with a toast
+        "daemonstat" => {
+            let (app, port) = (app.clone(), state.config.manager.port);
+            let pid = state.daemon_pid.load(Ordering::Relaxed);
+            let enabled = state.config.manager.use_hook_daemon;
+            std::thread::spawn(move || {
+                // 已关闭 (manager.use_hook_daemon)
+                let ping = enabled.then(|| crate::setup::ping_hook_daemon(port));
+                let body = match ping {
+                    None => "\u{5df2}\u{5173}\u{95ed} (manager.use_hook_daemon)".to_string(),
+                    Some(Ok(v)) => format!(
+                        "\u{2705} PID {} \u{00b7} v{}",
+                        pid,
+                        v.get("version").and_then(|v| v.as_str()).unwrap_or("?"),
+                    ),
+                    Some(Err(e)) => format!("\u{274c} {}", e),
+                };
+                send_notification(&app, "Hook \u{5b88}\u{62a4}\u{8fdb}\u{7a0b}", &body);
+            });
+        }
+        "repair" => {
+            let (app, state) = (app.clone(), state.clone());
+            std::thread::spawn(move || {
+                let body = repair_hooks(&state);
+                send_notification(&app, "\u{4fee}\u{590d} Hook", &body);
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Respawn the hook daemon (unless it is turned off) and rewrite the hook
+/// entries in Claude Code's settings. Returns a summary for the toast.
+fn repair_hooks(state: &AppState) -> String {
+    let port = state.config.manager.port;
+    let old_pid = state.daemon_pid.load(Ordering::Relaxed);
+    let daemon = if !state.config.manager.use_hook_daemon {
+        None
+    } else {
+        crate::setup::kill_orphaned_daemon(port);
+        crate::setup::spawn_hook_daemon(port)
+    };
+    let daemon = match daemon {
+        Some(pid) => {
+            state.daemon_pid.store(pid, Ordering::Relaxed);
+            tracing::info!("Hook daemon restarted from tray (PID {} -> {})", old_pid, pid);
+            state.sse.broadcast("daemon_restarted", serde_json::json!({ "old_pid": old_pid, "pid": pid }));
+            format!("\u{5b88}\u{62a4}\u{8fdb}\u{7a0b} PID {}", pid)
+        }
+        // 守护进程已关闭
+        None if !state.config.manager.use_hook_daemon => "\u{5b88}\u{62a4}\u{8fdb}\u{7a0b}\u{5df2}\u{5173}\u{95ed}".to_string(),
+        None => "\u{5b88}\u{62a4}\u{8fdb}\u{7a0b}\u{542f}\u{52a8}\u{5931}\u{8d25}".to_string(),
+    };
+    let hooks = match crate::setup::claude_settings_path() {
+        None => "\u{627e}\u{4e0d}\u{5230} settings.json".to_string(),
+        Some(path) => match crate::setup::install_hooks(&path, port, state.config.general.observe_tools) {
+            Ok(true) => "Hook \u{5df2}\u{91cd}\u{65b0}\u{5199}\u{5165}".to_string(),
+            Ok(false) => "Hook \u{914d}\u{7f6e}\u{6b63}\u{5e38}".to_string(),
+            Err(e) => e,
+        },
+    };
+    format!("{} \u{00b7} {}", daemon, hooks)
+}
+
+/// Tray for a desktop client of a headless service: the service owns the
+/// sessions, so there is no status icon or session list — just the island,
+/// the dashboard and widget windows and quit.
+pub fn setup_client_tray(
+    app: &tauri::App,
+    port: u16,
+    panel_w: u32,
+    panel_h: u32,
+    widget: crate::config::WidgetConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let header = MenuItem::with_id(app, "header", "Agent Desk \u{2014} service", false, None::<&str>)?;
+    let show = MenuItem::with_id(app, "show", "\u{1f441} \u{663e}\u{793a}\u{7a97}\u{53e3}", true, None::<&str>)?;
+    let dashboard = MenuItem::with_id(app, "dashboard", "\u{1f310} Dashboard", true, None::<&str>)?;
+    // 🪟 小组件
+    let widget_item = MenuItem::with_id(app, "widget", "\u{1fa9f} \u{5c0f}\u{7ec4}\u{4ef6}", true, None::<&str>)?;
+    let sep = PredefinedMenuItem::separator(app)?;
+    let quit = MenuItem::with_id(app, "quit", "\u{274c} \u{9000}\u{51fa}", true, None::<&str>)?;
+    let menu = if widget.enabled {
+        Menu::with_items(app, &[&header, &show, &dashboard, &widget_item, &sep, &quit])?
+    } else {
+        Menu::with_items(app, &[&header, &show, &dashboard, &sep, &quit])?
+    };
+
+    let icon = state_icon("sleeping", taskbar_theme()).unwrap();
+    let _tray = TrayIconBuilder::with_id("main")
+        .icon(icon)
+        .menu(&menu)
+        .tooltip("Agent Desk \u{2014} service")
+        .on_tray_icon_event(move |tray, event| {
+            if !matches!(event, TrayIconEvent::Click { button: tauri::tray::MouseButton::Left, .. }) {
+                return;
+            }
+            if let Some(w) = tray.app_handle().get_webview_window("island") {
+                let _ = w.show();
+                crate::island::expand(panel_w, panel_h);
+            }
+        })
+        .on_menu_event(move |app, event| match event.id.as_ref() {
+            "show" => {
+                if let Some(w) = app.get_webview_window("island") {
+                    let _ = w.show();
+                }
+            }
+            "dashboard" => crate::dashboard::toggle(app, port),
+            "widget" => crate::widget::toggle(app, port, &widget),
+            "quit" => app.exit(0),
+            _ => {}
+        })
+        .build(app)?;
+    Ok(())
+}
+
 // ---------------------------------------------------------------------------
 // Periodic update (called from tray-updater thread)
 // ---------------------------------------------------------------------------
 
+/// Show pulse frame `frame` (wraps) of the thinking icon. A custom thinking
+/// icon is left as is.
+pub fn animate_thinking(handle: &AppHandle, frame: usize) {
+    let theme = if LIGHT_TASKBAR.load(Ordering::Relaxed) { TaskbarTheme::Light } else { TaskbarTheme::Dark };
+    if custom_icon("thinking", theme).is_some() {
+        return;
+    }
+    let Some(tray) = handle.tray_by_id("main") else { return };
+    let rgba = &THINKING_FRAMES[(theme == TaskbarTheme::Light) as usize][frame % PULSE_FRAMES];
+    let _ = tray.set_icon(Some(tauri::image::Image::new(rgba, ICON_SIZE, ICON_SIZE)));
+}
+
 pub fn update_tray(
     handle: &AppHandle,
     state: &AppState,
@@ -225,23 +572,34 @@ pub fn update_tray(
 
     let session_count = processes.len();
 
-    // 1. Icon
-    if let Some(rgba) = ICONS.get(state_str) {
-        let icon = tauri::image::Image::new(rgba, ICON_SIZE, ICON_SIZE);
+    // 1. Icon (re-read the taskbar theme so a switch is picked up)
+    let theme = taskbar_theme();
+    LIGHT_TASKBAR.store(theme == TaskbarTheme::Light, Ordering::Relaxed);
+    if let Some(icon) = state_icon(state_str, theme) {
         let _ = tray.set_icon(Some(icon));
     }
 
     // 1b. Push state to island webview (direct sync, no HTTP roundtrip)
     if let Some(w) = handle.get_webview_window("island") {
-        let _ = w.eval(&format!(
-            "if(window.onTrayState)window.onTrayState('{}',{})",
-            state_str, session_count
-        ));
+        crate::island_push::send(handle, &crate::island_push::IslandPush::TrayState {
+            state: state_str.to_string(),
+            sessions: session_count,
+        });
+        if state.config.island.taskbar_badge {
+            let waiting = processes
+                .iter()
+                .filter(|p| p.get("status").and_then(|v| v.as_str()) == Some("waiting"))
+                .count();
+            crate::taskbar::set_badge(&w, state.permissions.get_pending().len() + waiting);
+        }
+    }
+    if state.config.island.jump_list {
+        crate::jumplist::update(processes, state.notifications_paused.load(Ordering::Relaxed));
     }
 
     // 2. Tooltip
     let unread = state.last_seen_ts.read().ok().map(|ts| {
-        state.event_store.get_events(*ts).len()
+        state.event_store.count_unread_after(*ts)
     }).unwrap_or(0);
 
     let tooltip = if session_count == 0 && unread == 0 {
@@ -262,6 +620,29 @@ pub fn update_tray(
             state_label(state_str), session_count, unread,
         )
     };
+    // · 最新动态 5分钟前 — in Chinese like the rest of the tray, unless
+    // messages.language says otherwise
+    let language = match state.config.messages.language.as_str() {
+        "" => "zh",
+        l => l,
+    };
+    let tooltip = match state.event_store.get_recent(0.0, 1).first() {
+        Some(latest) if session_count > 0 || unread > 0 => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            format!("{}\n\u{6700}\u{65b0}\u{52a8}\u{6001} {}", tooltip, crate::messages::ago(now - latest.ts, language))
+        }
+        _ => tooltip,
+    };
+    // ⚠ N 个会话未收到 hook — check settings.json
+    let silent = state.hook_health.silent().len();
+    let tooltip = if silent == 0 {
+        tooltip
+    } else {
+        format!("{}\n\u{26a0} {} \u{4e2a}\u{4f1a}\u{8bdd}\u{672a}\u{6536}\u{5230} hook \u{2014} settings.json", tooltip, silent)
+    };
     let _ = tray.set_tooltip(Some(&tooltip));
 
     // 3. Menu — skip rebuild if content hash unchanged
@@ -274,6 +655,7 @@ pub fn update_tray(
             if let Some(v) = obj.get("status") { v.to_string().hash(&mut hasher); }
             if let Some(v) = obj.get("cwd") { v.to_string().hash(&mut hasher); }
             if let Some(v) = obj.get("notification_type") { v.to_string().hash(&mut hasher); }
+            if let Some(v) = obj.get("color") { v.to_string().hash(&mut hasher); }
         }
     }
     let new_hash = hasher.finish();
@@ -346,7 +728,9 @@ fn build_menu(
                 _         => "\u{25cb}",
             };
 
-            let label = format!("{} {} ({})", indicator, name, status_text(proc_status));
+            // Colour glyph matches the session's colour in the island
+            let glyph = proc.get("color").and_then(|v| v.as_str()).map_or("", crate::session::color_glyph);
+            let label = format!("{} {} {} ({})", indicator, glyph, name, status_text(proc_status));
             let pid = proc.get("pid").and_then(|v| v.as_u64()).map(|p| p as u32);
             let id = format!("sess_{}_{}", seq, i);
             session_map.insert(id.clone(), (cwd.to_string(), pid));
@@ -359,14 +743,82 @@ fn build_menu(
 
     drop(session_map);
 
+    // ── Quick replies for sessions waiting on input ──
+    let replies = crate::server::quick_replies(&state.config);
+    let waiting: Vec<&Value> = processes
+        .iter()
+        .filter(|p| p.get("status").and_then(|v| v.as_str()) == Some("waiting"))
+        .filter(|p| p.get("notification_type").and_then(|v| v.as_str()) != Some("limit_reached"))
+        .filter(|p| p.get("session_id").and_then(|v| v.as_str()).is_some_and(|s| !s.is_empty()))
+        .filter(|p| {
+            let agent = p.get("agent_type").and_then(|v| v.as_str()).unwrap_or("");
+            state.registry.capabilities(agent).is_none_or(|c| c.supports_send)
+        })
+        .collect();
+    let mut reply_map = REPLY_MAP.lock().unwrap();
+    reply_map.clear();
+    if !replies.is_empty() && !waiting.is_empty() {
+        let mut submenus = Vec::new();
+        for (i, proc) in waiting.iter().enumerate() {
+            let sid = proc.get("session_id").and_then(|v| v.as_str()).unwrap_or("");
+            let cwd = proc.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+            let mut items = Vec::new();
+            for (j, reply) in replies.iter().enumerate() {
+                let id = format!("reply_{}_{}_{}", seq, i, j);
+                reply_map.insert(id.clone(), (sid.to_string(), reply.id.clone()));
+                items.push(MenuItem::with_id(handle, &id, &reply.label, true, None::<&str>)?);
+            }
+            let refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> =
+                items.iter().map(|m| m as &dyn tauri::menu::IsMenuItem<tauri::Wry>).collect();
+            // A single waiting session gets its replies directly in the menu
+            let label = if waiting.len() == 1 {
+                format!("\u{26a1} \u{5feb}\u{6377}\u{56de}\u{590d} \u{2014} {}", project_name(cwd))
+            } else {
+                project_name(cwd).to_string()
+            };
+            submenus.push(Submenu::with_items(handle, label, true, &refs)?);
+        }
+        menu.append(&PredefinedMenuItem::separator(handle)?)?;
+        if let [only] = submenus.as_slice() {
+            menu.append(only)?;
+        } else {
+            let refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> =
+                submenus.iter().map(|m| m as &dyn tauri::menu::IsMenuItem<tauri::Wry>).collect();
+            menu.append(&Submenu::with_items(handle, "\u{26a1} \u{5feb}\u{6377}\u{56de}\u{590d}", true, &refs)?)?;
+        }
+    }
+    drop(reply_map);
+
+    // ── Chat export, one entry per session with a transcript ──
+    let mut export_map = EXPORT_MAP.lock().unwrap();
+    export_map.clear();
+    let mut export_items = Vec::new();
+    for (i, proc) in processes.iter().enumerate() {
+        let sid = proc.get("session_id").and_then(|v| v.as_str()).unwrap_or("");
+        if sid.is_empty() {
+            continue;
+        }
+        let cwd = proc.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+        let id = format!("export_{}_{}", seq, i);
+        export_map.insert(id.clone(), sid.to_string());
+        export_items.push(MenuItem::with_id(handle, &id, project_name(cwd), true, None::<&str>)?);
+    }
+    drop(export_map);
+    if !export_items.is_empty() {
+        let refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> =
+            export_items.iter().map(|m| m as &dyn tauri::menu::IsMenuItem<tauri::Wry>).collect();
+        menu.append(&Submenu::with_items(handle, "\u{1f4e4} \u{5bfc}\u{51fa}\u{5bf9}\u{8bdd}", true, &refs)?)?; // 📤 导出对话
+    }
+
     // ── Recent events (last 5) ──
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs_f64();
-    let events = state.event_store.get_events(now - state.config.general.session_ttl as f64);
-    let recent: Vec<_> = events.iter().rev().take(5).collect();
+    let recent = state.event_store.get_recent(now - state.config.general.session_ttl as f64, 5);
 
+    let mut event_map = EVENT_MAP.lock().unwrap();
+    event_map.clear();
     if !recent.is_empty() {
         menu.append(&PredefinedMenuItem::separator(handle)?)?;
         menu.append(&MenuItem::with_id(
@@ -377,29 +829,83 @@ fn build_menu(
 
         for (i, evt) in recent.iter().enumerate() {
             let first_line = evt.message.lines().next().unwrap_or(&evt.message);
-            let display = if first_line.chars().count() > 60 {
+            let mut display = if first_line.chars().count() > 60 {
                 format!("{}...", first_line.chars().take(57).collect::<String>())
             } else {
                 first_line.to_string()
             };
+            // External tools' events stand apart from agent events
+            if !evt.source.is_empty() {
+                display.insert_str(0, "\u{1f4e3} ");
+            }
+            // Clicking one goes to its session; events without one stay labels
+            let id = format!("evt_{}_{}", seq, i);
+            let linked = !evt.session_id.is_empty() || !evt.cwd.is_empty();
+            if linked {
+                event_map.insert(id.clone(), (evt.session_id.clone(), evt.cwd.clone()));
+            }
             menu.append(&MenuItem::with_id(
-                handle, format!("evt_{}_{}", seq, i), &display, false, None::<&str>,
+                handle, &id, &display, linked, None::<&str>,
             )?)?;
         }
     }
+    drop(event_map);
 
     // ── Bottom ──
     menu.append(&PredefinedMenuItem::separator(handle)?)?;
+    let cfg_item = MenuItem::with_id(handle, format!("cfgopen_{}", seq), "\u{2699} \u{6253}\u{5f00}\u{914d}\u{7f6e}\u{6587}\u{4ef6}", true, None::<&str>)?;
+    let log_item = MenuItem::with_id(handle, format!("logopen_{}", seq), "\u{1f4c2} \u{6253}\u{5f00}\u{65e5}\u{5fd7}\u{76ee}\u{5f55}", true, None::<&str>)?;
+    let stat_item = MenuItem::with_id(handle, format!("daemonstat_{}", seq), "\u{1fa7a} \u{5b88}\u{62a4}\u{8fdb}\u{7a0b}\u{72b6}\u{6001}", true, None::<&str>)?;
+    let repair_item = MenuItem::with_id(handle, format!("repair_{}", seq), "\u{1f527} \u{91cd}\u{542f}\u{5b88}\u{62a4}\u{8fdb}\u{7a0b}\u{5e76}\u{4fee}\u{590d} Hook", true, None::<&str>)?;
+    menu.append(&Submenu::with_items(
+        handle,
+        "\u{1f6e0} \u{7ef4}\u{62a4}",
+        true,
+        &[&cfg_item, &log_item, &PredefinedMenuItem::separator(handle)?, &stat_item, &repair_item],
+    )?)?;
     menu.append(&MenuItem::with_id(
         handle, format!("show_{}", seq),
         "\u{1f441} \u{663e}\u{793a}\u{7a97}\u{53e3}",
         true, None::<&str>,
     )?)?;
     menu.append(&MenuItem::with_id(
-        handle, format!("clear_{}", seq),
-        "\u{1f9f9} \u{6e05}\u{7406}\u{52a8}\u{6001}",
+        handle, format!("dashopen_{}", seq),
+        "\u{1f4ca} \u{6253}\u{5f00}\u{4eea}\u{8868}\u{76d8}",
         true, None::<&str>,
     )?)?;
+    if state.config.widget.enabled {
+        menu.append(&MenuItem::with_id(
+            handle, format!("widget_{}", seq),
+            "\u{1fa9f} \u{5c0f}\u{7ec4}\u{4ef6}", // 🪟 小组件
+            true, None::<&str>,
+        )?)?;
+    }
+    // Clear everything, or just one project's events
+    let mut projects: Vec<&str> = Vec::new();
+    let visible = state.event_store.get_events(now - state.config.general.session_ttl as f64);
+    for evt in visible.iter().rev() {
+        if !evt.cwd.is_empty() && !projects.contains(&evt.cwd.as_str()) && projects.len() < 8 {
+            projects.push(&evt.cwd);
+        }
+    }
+    let mut clear_map = CLEAR_MAP.lock().unwrap();
+    clear_map.clear();
+    let mut clear_items = vec![MenuItem::with_id(
+        handle, format!("clear_{}", seq),
+        "\u{5168}\u{90e8}",
+        true, None::<&str>,
+    )?];
+    for (i, cwd) in projects.iter().enumerate() {
+        let id = format!("clearproj_{}_{}", seq, i);
+        clear_map.insert(id.clone(), cwd.to_string());
+        clear_items.push(MenuItem::with_id(
+            handle, &id, format!("\u{4ec5} {}", project_name(cwd)), true, None::<&str>,
+        )?);
+    }
+    drop(clear_map);
+    let refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> =
+        clear_items.iter().map(|m| m as &dyn tauri::menu::IsMenuItem<tauri::Wry>).collect();
+    menu.append(&Submenu::with_items(handle, "\u{1f9f9} \u{6e05}\u{7406}\u{52a8}\u{6001}", true, &refs)?)?;
     menu.append(&MenuItem::with_id(
         handle, format!("quit_{}", seq),
         "\u{274c} \u{9000}\u{51fa}",
@@ -423,9 +929,33 @@ pub fn send_notification(handle: &AppHandle, title: &str, body: &str) {
         .show();
 }
 
-/// Play a system notification sound via Win32 MessageBeep.
+/// Built-in sound names, played via MessageBeep.
+pub const BUILTIN_SOUNDS: [&str; 5] = ["asterisk", "hand", "question", "exclamation", "default"];
+
+/// Folder next to config.yaml holding custom `.wav` sounds.
+fn sounds_dir() -> std::path::PathBuf {
+    let config = crate::config::find_config_path();
+    config.parent().unwrap_or(std::path::Path::new(".")).join("sounds")
+}
+
+/// File names of the custom `.wav` sounds, sorted.
+pub fn custom_sounds() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(sounds_dir()) else { return Vec::new() };
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|n| n.to_ascii_lowercase().ends_with(".wav"))
+        .collect();
+    names.sort_by_key(|n| n.to_lowercase());
+    names
+}
+
+/// Play a notification sound: a built-in one via Win32 MessageBeep, or a
+/// custom `.wav` from the sounds folder (see `custom_sounds`).
 ///
 /// `sound_type`: "asterisk" | "hand" | "question" | "exclamation" | "default"
+/// or a file name such as "chime.wav"
 pub fn play_notification_sound(sound_type: &str) {
     #[cfg(windows)]
     {
@@ -433,6 +963,29 @@ pub fn play_notification_sound(sound_type: &str) {
         unsafe extern "system" {
             fn MessageBeep(uType: u32) -> i32;
         }
+        #[link(name = "winmm")]
+        unsafe extern "system" {
+            fn PlaySoundW(pszSound: *const u16, hmod: isize, fdwSound: u32) -> i32;
+        }
+        const SND_ASYNC: u32 = 0x0001;
+        const SND_NODEFAULT: u32 = 0x0002;
+        const SND_FILENAME: u32 = 0x0002_0000;
+
+        // A bare file name only, so a setting can't point outside the folder
+        let custom = sound_type.to_ascii_lowercase().ends_with(".wav")
+            && !sound_type.contains(['/', '\\']);
+        if custom {
+            let path = sounds_dir().join(sound_type);
+            if path.is_file() {
+                use std::os::windows::ffi::OsStrExt;
+                let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+                unsafe {
+                    PlaySoundW(wide.as_ptr(), 0, SND_FILENAME | SND_ASYNC | SND_NODEFAULT);
+                }
+                return;
+            }
+            tracing::warn!("Custom sound {} not found, playing the default beep", path.display());
+        }
         let code = match sound_type {
             "hand"        => 0x00000010,
             "question"    => 0x00000020,
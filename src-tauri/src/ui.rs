@@ -0,0 +1,141 @@
+//! The island UI over HTTP (`/ui/*`), and the token gate for LAN mode.
+//!
+//! The built-in page is the same `pet.html` the island webview loads, so a
+//! normal browser gets the same dashboard; `/ui/dashboard.html` is the
+//! dashboard window's page. `manager.ui_dir` overrides them file by file;
+//! anything missing there falls back to the built-in pages.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Json, Response},
+    routing::get,
+    Router,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tower_http::services::ServeDir;
+
+use crate::server::AppState;
+
+const BUILTIN_INDEX: &str = include_str!("../../src/pet.html");
+const BUILTIN_DASHBOARD: &str = include_str!("../../src/dashboard.html");
+
+/// Cookie set after a successful `?token=` login so follow-up requests
+/// (fetch, EventSource) don't need the token in every URL.
+const TOKEN_COOKIE: &str = "agent_desk_token";
+
+/// Router for everything under `/ui`.
+pub fn router(ui_dir: &str) -> Router {
+    let builtin = Router::new()
+        .route("/dashboard.html", get(|| async { Html(BUILTIN_DASHBOARD) }))
+        .fallback(builtin_index);
+    if ui_dir.is_empty() {
+        return builtin;
+    }
+    let dir = Path::new(ui_dir);
+    if !dir.is_dir() {
+        tracing::warn!("manager.ui_dir {} is not a directory, serving the built-in UI", ui_dir);
+        return builtin;
+    }
+    tracing::info!("Serving UI from {}", dir.display());
+    Router::new().fallback_service(ServeDir::new(dir).fallback(builtin))
+}
+
+/// Any path without a file behind it gets the built-in page (single-page app).
+async fn builtin_index() -> Html<&'static str> {
+    Html(BUILTIN_INDEX)
+}
+
+/// Address to bind: all interfaces in LAN mode (only with a token) or for
+/// the public status page, else `manager.host`.
+pub fn bind_host(cfg: &crate::config::ManagerConfig) -> &'static str {
+    let local = crate::setup::host();
+    // All IPv6 interfaces when the local host is IPv6
+    let all = if local.parse::<std::net::Ipv6Addr>().is_ok() { "::" } else { "0.0.0.0" };
+    // Without a token `lan_auth` still turns away every other remote request
+    if cfg.status_page {
+        return all;
+    }
+    if !cfg.lan {
+        return local;
+    }
+    if cfg.lan_token.is_empty() {
+        tracing::warn!("manager.lan is on but manager.lan_token is empty — staying on {}", local);
+        return local;
+    }
+    all
+}
+
+/// Middleware: loopback peers pass; everyone else needs `manager.lan_token`
+/// as a bearer token, a `?token=` query parameter, or the login cookie.
+/// The read-only `/status` page is open to all when enabled.
+pub async fn lan_auth(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let peer = req
+        .extensions()
+        .get::<axum::extract::ConnectInfo<SocketAddr>>()
+        .map(|c| c.0);
+    if peer.is_none_or(|p| p.ip().is_loopback()) {
+        return next.run(req).await;
+    }
+    if state.config.manager.status_page && req.method() == Method::GET && req.uri().path() == "/status" {
+        return next.run(req).await;
+    }
+    // Permission links carry their own signature (see permit_link)
+    if state.config.manager.lan && req.uri().path().starts_with("/permit/") {
+        return next.run(req).await;
+    }
+
+    let expected = state.config.manager.lan_token.as_str();
+    let from_query = query_token(&req).is_some_and(|t| token_eq(&t, expected));
+    let ok = from_query
+        || bearer_token(&req).is_some_and(|t| token_eq(t, expected))
+        || cookie_token(&req).is_some_and(|t| token_eq(t, expected));
+    if expected.is_empty() || !ok {
+        tracing::warn!("Rejected {} {} from {:?}", req.method(), req.uri().path(), peer);
+        return (StatusCode::UNAUTHORIZED, Json(json!({"ok": false, "error": "unauthorized"}))).into_response();
+    }
+
+    let mut resp = next.run(req).await;
+    if from_query {
+        let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict", TOKEN_COOKIE, expected);
+        if let Ok(v) = HeaderValue::from_str(&cookie) {
+            resp.headers_mut().append(header::SET_COOKIE, v);
+        }
+    }
+    resp
+}
+
+fn bearer_token(req: &Request) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+fn query_token(req: &Request) -> Option<String> {
+    req.uri().query()?.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        (k == "token").then(|| urlencoding::decode(v).map(|s| s.into_owned()).unwrap_or_default())
+    })
+}
+
+fn cookie_token(req: &Request) -> Option<&str> {
+    req.headers()
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|c| c.trim().strip_prefix(TOKEN_COOKIE)?.strip_prefix('='))
+}
+
+/// Compare without bailing out on the first differing byte.
+pub(crate) fn token_eq(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
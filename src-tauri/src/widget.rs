@@ -0,0 +1,61 @@
+//! Desktop widget: a small frameless window listing the sessions with a
+//! status dot each, independent of the island. Clicking a row focuses the
+//! session's terminal. It loads `widget.html`, which talks to the HTTP API
+//! like the dashboard and sizes itself to its rows.
+//!
+//! Created at startup with `widget.enabled`, at the right edge of the
+//! primary monitor; `widget.on_top` keeps it above other windows. Closing
+//! it only hides it; the tray menu brings it back.
+
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::config::WidgetConfig;
+
+const LABEL: &str = "widget";
+
+/// Width in logical pixels; the page sets the height.
+const WIDTH: f64 = 240.0;
+
+/// Show the widget, creating it if needed.
+pub fn open(app: &AppHandle, port: u16, cfg: &WidgetConfig) {
+    if let Some(w) = app.get_webview_window(LABEL) {
+        let _ = w.show();
+        return;
+    }
+    let built = WebviewWindowBuilder::new(app, LABEL, WebviewUrl::App("widget.html".into()))
+        .title("Agent Desk")
+        .inner_size(WIDTH, 60.0)
+        .decorations(false)
+        .resizable(false)
+        .skip_taskbar(true)
+        .always_on_top(cfg.on_top)
+        .focused(false)
+        .visible(false)
+        .initialization_script(&crate::setup::page_globals(port))
+        .build();
+    let w = match built {
+        Ok(w) => w,
+        Err(e) => {
+            tracing::warn!("Failed to open widget window: {}", e);
+            return;
+        }
+    };
+    // Right edge of the primary monitor, a quarter of the way down
+    if let Some(m) = w.primary_monitor().ok().flatten() {
+        let (pos, size, scale) = (m.position(), m.size(), m.scale_factor());
+        let x = pos.x + size.width as i32 - ((WIDTH + 16.0) * scale) as i32;
+        let y = pos.y + size.height as i32 / 4;
+        let _ = w.set_position(tauri::Position::Physical(tauri::PhysicalPosition::new(x, y)));
+    }
+    let _ = w.show();
+}
+
+/// Hide the widget if it is showing, else show it.
+pub fn toggle(app: &AppHandle, port: u16, cfg: &WidgetConfig) {
+    match app.get_webview_window(LABEL).filter(|w| w.is_visible().unwrap_or(false)) {
+        Some(w) => {
+            let _ = w.hide();
+        }
+        None => open(app, port, cfg),
+    }
+}